@@ -0,0 +1,27 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rig::{
+    bench::Dataset,
+    chunking::{
+        FixedSizeSplitter, MarkdownSplitter, RecursiveSplitter, SentenceSplitter, TextSplitter,
+    },
+};
+
+fn splitters(c: &mut Criterion) {
+    let text = Dataset::generate(42, 100, 200).texts().join("\n\n");
+
+    let mut group = c.benchmark_group("chunking");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    let splitters: [(&str, Box<dyn TextSplitter>); 4] = [
+        ("fixed", Box::new(FixedSizeSplitter::new(1000, 200))),
+        ("recursive", Box::new(RecursiveSplitter::new(1000, 200))),
+        ("sentence", Box::new(SentenceSplitter::new(1000, 200))),
+        ("markdown", Box::new(MarkdownSplitter::new(1000, 200))),
+    ];
+    for (name, splitter) in splitters {
+        group.bench_function(name, |b| b.iter(|| splitter.split(&text)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, splitters);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+//! Overhead of the batching of the embeddings builder, compared to embedding the same texts
+//! with the model directly.
+use criterion::{
+    async_executor::{AsyncExecutor, FuturesExecutor},
+    criterion_group, criterion_main, BenchmarkId, Criterion, Throughput,
+};
+use rig::{
+    bench::{Dataset, HashEmbeddingModel},
+    embeddings::{EmbeddingModel, EmbeddingsBuilder},
+};
+
+fn batching(c: &mut Criterion) {
+    let model = HashEmbeddingModel::new(256);
+
+    let mut group = c.benchmark_group("embeddings_builder");
+    for count in [100, 1_000, 5_000] {
+        let texts = Dataset::generate(42, count, 50).texts();
+        group.throughput(Throughput::Elements(count as u64));
+
+        group.bench_with_input(BenchmarkId::new("model", count), &texts, |b, texts| {
+            b.iter(|| {
+                FuturesExecutor
+                    .block_on(model.embed_texts(texts.clone()))
+                    .expect("The texts should be embedded")
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("builder", count), &texts, |b, texts| {
+            b.iter(|| {
+                let builder = EmbeddingsBuilder::new(model.clone())
+                    .documents(texts.clone())
+                    .expect("The texts should be embeddable");
+                FuturesExecutor
+                    .block_on(builder.build())
+                    .expect("The texts should be embedded")
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, batching);
+criterion_main!(benches);
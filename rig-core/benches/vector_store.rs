@@ -0,0 +1,27 @@
+use criterion::{async_executor::FuturesExecutor, criterion_group, criterion_main, Criterion};
+use rig::{
+    bench::{self, Dataset, HashEmbeddingModel},
+    vector_store::in_memory_store::InMemoryVectorStore,
+};
+
+fn in_memory_top_n(c: &mut Criterion) {
+    let model = HashEmbeddingModel::new(256);
+    for count in [1_000, 10_000] {
+        let dataset = Dataset::generate(42, count, 50);
+        let queries = dataset.queries(100);
+        let index = InMemoryVectorStore::from_documents_with_ids(dataset.embeddings(&model))
+            .index(model.clone());
+
+        bench::bench_top_n(
+            c,
+            &format!("in_memory/{count}"),
+            &FuturesExecutor,
+            &index,
+            &queries,
+            10,
+        );
+    }
+}
+
+criterion_group!(benches, in_memory_top_n);
+criterion_main!(benches);
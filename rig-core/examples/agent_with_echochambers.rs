@@ -99,7 +99,7 @@ impl Tool for SendMessage {
         let content = format!("\"{}\"", args.content);
 
         let response = client
-            .post(&format!(
+            .post(format!(
                 "https://echochambers.ai/api/rooms/{}/message",
                 args.room_id
             ))
@@ -214,7 +214,7 @@ impl Tool for GetRoomMetrics {
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let client = reqwest::Client::new();
         let response = client
-            .get(&format!(
+            .get(format!(
                 "https://echochambers.ai/api/metrics/rooms/{}",
                 args.room_id
             ))
@@ -260,7 +260,7 @@ impl Tool for GetAgentMetrics {
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let client = reqwest::Client::new();
         let response = client
-            .get(&format!(
+            .get(format!(
                 "https://echochambers.ai/api/metrics/agents/{}",
                 args.room_id
             ))
@@ -306,7 +306,7 @@ impl Tool for GetMetricsHistory {
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
         let client = reqwest::Client::new();
         let response = client
-            .get(&format!(
+            .get(format!(
                 "https://echochambers.ai/api/metrics/history/{}",
                 args.room_id
             ))
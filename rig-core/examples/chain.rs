@@ -42,7 +42,7 @@ async fn main() -> Result<(), anyhow::Error> {
         // while simultaneously applying a passthrough operation. The latter will allow
         // us to forward the initial prompt to the next operation in the chain.
         .chain(parallel!(
-            passthrough(),
+            passthrough::<&str>(),
             lookup::<_, _, String>(index, 1), // Required to specify document type
         ))
         // Chain a "map" operation to the current chain, which will combine the user
@@ -60,7 +60,7 @@ async fn main() -> Result<(), anyhow::Error> {
             ),
             Err(err) => {
                 println!("Error: {}! Prompting without additional context", err);
-                format!("{prompt}")
+                prompt.to_string()
             }
         })
         // Chain a "prompt" operation which will prompt out agent with the final prompt
@@ -0,0 +1,40 @@
+use rig::{
+    completion::Prompt,
+    image_generation::ImageGenerationModel,
+    message::Message,
+    providers::openai::{self, DALL_E_3, GPT_4O},
+};
+use serde_json::json;
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    // Create OpenAI client and image generation model
+    let client = openai::Client::from_env();
+    let dall_e = client.image_generation_model(DALL_E_3);
+
+    // Generate an image, returned as base64-encoded data
+    let generation = dall_e
+        .image_generation_request()
+        .prompt("A crab made of rusty metal, in the style of a woodcut")
+        .size(1024, 1024)
+        .additional_params(json!({"response_format": "b64_json"}))
+        .send()
+        .await?;
+    let image = generation.images.into_iter().next().expect("One image");
+
+    tokio::fs::write("crab.png", image.to_bytes()?).await?;
+    println!("Image saved to crab.png");
+
+    // Give the generated image to an agent
+    let agent = client
+        .agent(GPT_4O)
+        .preamble("You are an art critic.")
+        .build();
+
+    let response = agent
+        .prompt(Message::user_with_image("Critique this woodcut.", image))
+        .await?;
+    println!("{}", response);
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use rig::{
+    completion::{
+        message::{Image, ImageMediaType},
+        Prompt,
+    },
+    message::Message,
+    providers::openai::{self, GPT_4O},
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use tokio::fs;
+
+const IMAGE_FILE_PATH: &str = "rig-core/examples/images/camponotus_flavomarginatus_ant.jpg";
+const IMAGE_URL: &str =
+    "https://upload.wikimedia.org/wikipedia/commons/a/a7/Camponotus_flavomarginatus_ant.jpg";
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    // Create OpenAI client
+    let client = openai::Client::from_env();
+
+    // Create agent with a single context prompt
+    let agent = client
+        .agent(GPT_4O)
+        .preamble("You are an image describer.")
+        .temperature(0.5)
+        .build();
+
+    // Prompt the agent with a local image, sent as base64-encoded data
+    let image_base64 = BASE64_STANDARD.encode(fs::read(IMAGE_FILE_PATH).await?);
+    let response = agent
+        .prompt(Message::user_with_image(
+            "What insect is this?",
+            Image::base64(image_base64, ImageMediaType::JPEG),
+        ))
+        .await?;
+    println!("{}", response);
+
+    // Prompt the agent with an image given by its URL
+    let response = agent
+        .prompt(Message::user_with_image(
+            "Where does it live?",
+            Image::url(IMAGE_URL),
+        ))
+        .await?;
+    println!("{}", response);
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::env::args;
+
+use rig::{completion::Prompt, registry::ModelRegistry};
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    // The model is chosen at runtime, e.g.: `openai:gpt-4o` or `ollama:llama3.2`
+    let model = args().nth(1).unwrap_or_else(|| "openai:gpt-4o".to_string());
+
+    // Register the providers whose API key is set in the environment
+    let registry = ModelRegistry::from_env();
+    println!(
+        "Available providers: {}",
+        registry
+            .completion_providers()
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let agent = registry
+        .agent(&model)?
+        .preamble("You are a comedian here to entertain the user using humour and jokes.")
+        .build();
+
+    let response = agent.prompt("Entertain me!").await?;
+    println!("{model}: {response}");
+
+    Ok(())
+}
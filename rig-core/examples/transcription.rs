@@ -0,0 +1,38 @@
+use std::env::args;
+
+use rig::{
+    completion::Prompt,
+    providers::openai::{self, GPT_4O, WHISPER_1},
+    transcription::TranscriptionModel,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let path = args()
+        .nth(1)
+        .expect("Usage: cargo run --example transcription <audio file>");
+
+    // Create OpenAI client and transcription model
+    let client = openai::Client::from_env();
+    let whisper = client.transcription_model(WHISPER_1);
+
+    // Transcribe the voice note
+    let transcription = whisper
+        .transcription_request()
+        .load_file(&path)?
+        .send()
+        .await?;
+    println!("Transcription: {}", transcription.text);
+
+    // Give the transcription to an agent as context
+    let agent = client
+        .agent(GPT_4O)
+        .preamble("You summarize voice notes as short to-do lists.")
+        .context(&transcription.text)
+        .build();
+
+    let response = agent.prompt("Summarize the voice note.").await?;
+    println!("{}", response);
+
+    Ok(())
+}
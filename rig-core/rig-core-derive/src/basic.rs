@@ -1,10 +1,12 @@
-use syn::{parse_quote, Attribute, DataStruct, Meta};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{parse_quote, Attribute, Fields, Meta};
 
 use crate::EMBED;
 
 /// Finds and returns fields with simple `#[embed]` attribute tags only.
-pub(crate) fn basic_embed_fields(data_struct: &DataStruct) -> impl Iterator<Item = &syn::Field> {
-    data_struct.fields.iter().filter(|field| {
+pub(crate) fn basic_embed_fields(fields: &Fields) -> impl Iterator<Item = &syn::Field> {
+    fields.iter().filter(|field| {
         field.attrs.iter().any(|attribute| match attribute {
             Attribute {
                 meta: Meta::Path(path),
@@ -16,10 +18,27 @@ pub(crate) fn basic_embed_fields(data_struct: &DataStruct) -> impl Iterator<Item
 }
 
 /// Adds bounds to where clause that force all fields tagged with `#[embed]` to implement the `Embed` trait.
+///
+/// Only the types referencing a type parameter are bounded: the other ones are checked where
+/// they are embedded, and bounding them would not compile for recursive types (e.g.: a field
+/// `children: Vec<Self>` would require the impl being defined).
 pub(crate) fn add_struct_bounds(generics: &mut syn::Generics, field_type: &syn::Type) {
+    if !references_type_params(generics, field_type.to_token_stream()) {
+        return;
+    }
+
     let where_clause = generics.make_where_clause();
 
     where_clause.predicates.push(parse_quote! {
-        #field_type: Embed
+        #field_type: rig::embeddings::embed::Embed
     });
 }
+
+/// Whether `tokens` (e.g.: of a field type) mention one of the type parameters of `generics`.
+fn references_type_params(generics: &syn::Generics, tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => generics.type_params().any(|param| param.ident == ident),
+        TokenTree::Group(group) => references_type_params(generics, group.stream()),
+        _ => false,
+    })
+}
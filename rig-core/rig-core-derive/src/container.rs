@@ -0,0 +1,78 @@
+use quote::ToTokens;
+use syn::LitStr;
+
+use crate::EMBED;
+
+const MODE: &str = "mode";
+const SEPARATOR: &str = "separator";
+const CHECK_SERIALIZE: &str = "check_serialize";
+
+/// Embeds the texts of the tagged fields separately, one embedding per text (the default).
+const SEPARATE: &str = "separate";
+/// Joins the texts of the tagged fields into a single text, embedded once.
+const CONCAT: &str = "concat";
+
+/// Default separator of the texts joined with `#[embed(mode = "concat")]`
+const DEFAULT_SEPARATOR: &str = "\n";
+
+/// Options of a struct or enum tagged with `#[embed(...)]`,
+/// ie. `#[embed(mode = "concat", separator = "...")]` and `#[embed(check_serialize)]`.
+pub(crate) struct ContainerOptions {
+    /// Separator of the joined texts, if the texts of the tagged fields are joined
+    pub(crate) concat: Option<LitStr>,
+    /// Whether the fields are checked to implement `Serialize`
+    pub(crate) check_serialize: bool,
+}
+
+/// Parses the `#[embed(...)]` attributes of the struct or enum itself.
+pub(crate) fn container_options(input: &syn::DeriveInput) -> syn::Result<ContainerOptions> {
+    let mut mode = None;
+    let mut separator = None;
+    let mut check_serialize = false;
+
+    for attribute in input.attrs.iter().filter(|a| a.path().is_ident(EMBED)) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident(CHECK_SERIALIZE) {
+                check_serialize = true;
+            } else if meta.path.is_ident(MODE) {
+                let value = meta.value()?.parse::<LitStr>()?;
+                match value.value().as_str() {
+                    SEPARATE | CONCAT => mode = Some(value),
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            value,
+                            format!(
+                                "unknown embedding mode `{}`, expected `{}` or `{}`",
+                                other, SEPARATE, CONCAT
+                            ),
+                        ))
+                    }
+                }
+            } else if meta.path.is_ident(SEPARATOR) {
+                separator = Some(meta.value()?.parse::<LitStr>()?);
+            } else {
+                let path = meta.path.to_token_stream().to_string().replace(' ', "");
+                return Err(syn::Error::new_spanned(
+                    meta.path,
+                    format_args!("unknown embedding attribute `{}`", path),
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    match (mode, separator) {
+        (Some(mode), separator) if mode.value() == CONCAT => Ok(ContainerOptions {
+            concat: Some(separator.unwrap_or_else(|| LitStr::new(DEFAULT_SEPARATOR, mode.span()))),
+            check_serialize,
+        }),
+        (_, Some(separator)) => Err(syn::Error::new_spanned(
+            separator,
+            format!("`{}` requires `{} = \"{}\"`", SEPARATOR, MODE, CONCAT),
+        )),
+        _ => Ok(ContainerOptions {
+            concat: None,
+            check_serialize,
+        }),
+    }
+}
@@ -1,25 +1,47 @@
 use quote::ToTokens;
-use syn::{meta::ParseNestedMeta, ExprPath};
+use syn::{meta::ParseNestedMeta, parse_quote, ExprPath, LitStr};
 
 use crate::EMBED;
 
 const EMBED_WITH: &str = "embed_with";
+const PREFIX: &str = "prefix";
+const FORMAT: &str = "format";
+const SKIP_IF_EMPTY: &str = "skip_if_empty";
 
-/// Finds and returns fields with #[embed(embed_with = "...")] attribute tags only.
-/// Also returns the "..." part of the tag (ie. the custom function).
+/// Options of a field tagged with `#[embed(...)]`, ie. `#[embed(embed_with = "...")]`,
+/// `#[embed(prefix = "...")]`, `#[embed(format = "...")]` and `#[embed(skip_if_empty)]`.
+#[derive(Default)]
+pub(crate) struct EmbedOptions {
+    /// Custom function embedding the field
+    pub(crate) embed_with: Option<ExprPath>,
+    /// Prefix of the texts of the field
+    pub(crate) prefix: Option<LitStr>,
+    /// Format string embedded instead of the field, and the fields it references
+    pub(crate) format: Option<(LitStr, Vec<syn::Ident>)>,
+    /// Whether the empty (or whitespace only) texts of the field are skipped
+    pub(crate) skip_if_empty: bool,
+}
+
+impl EmbedOptions {
+    /// Whether the texts of the field are rewritten before being added to the embedder.
+    pub(crate) fn rewrites_texts(&self) -> bool {
+        self.prefix.is_some() || self.skip_if_empty
+    }
+}
+
+/// Finds and returns fields with `#[embed(...)]` attribute tags only, along with their options.
 pub(crate) fn custom_embed_fields(
-    data_struct: &syn::DataStruct,
-) -> syn::Result<Vec<(&syn::Field, syn::ExprPath)>> {
-    data_struct
-        .fields
+    fields: &syn::Fields,
+) -> syn::Result<Vec<(&syn::Field, EmbedOptions)>> {
+    fields
         .iter()
         .filter_map(|field| {
             field
                 .attrs
                 .iter()
                 .filter_map(|attribute| match attribute.is_custom() {
-                    Ok(true) => match attribute.expand_tag() {
-                        Ok(path) => Some(Ok((field, path))),
+                    Ok(true) => match attribute.expand_tag(fields) {
+                        Ok(options) => Some(Ok((field, options))),
                         Err(e) => Some(Err(e)),
                     },
                     Ok(false) => None,
@@ -30,13 +52,33 @@ pub(crate) fn custom_embed_fields(
         .collect::<Result<Vec<_>, _>>()
 }
 
+/// Adds bounds to where clause that force all fields tagged with `#[embed(embed_with = "...")]`
+/// to implement `Clone`, since the custom function takes the field by value.
+pub(crate) fn add_custom_bounds(generics: &mut syn::Generics, field_type: &syn::Type) {
+    let where_clause = generics.make_where_clause();
+
+    where_clause.predicates.push(parse_quote! {
+        #field_type: ::core::clone::Clone
+    });
+}
+
+/// Adds bounds to where clause that force all fields referenced by a `#[embed(format = "...")]`
+/// string to implement `Display`.
+pub(crate) fn add_format_bounds(generics: &mut syn::Generics, field_type: &syn::Type) {
+    let where_clause = generics.make_where_clause();
+
+    where_clause.predicates.push(parse_quote! {
+        #field_type: ::core::fmt::Display
+    });
+}
+
 trait CustomAttributeParser {
-    // Determine if field is tagged with an #[embed(embed_with = "...")] attribute.
+    // Determine if field is tagged with an #[embed(...)] attribute.
     fn is_custom(&self) -> syn::Result<bool>;
 
-    // Get the "..." part of the #[embed(embed_with = "...")] attribute.
-    // Ex: If attribute is tagged with #[embed(embed_with = "my_embed")], returns "my_embed".
-    fn expand_tag(&self) -> syn::Result<syn::ExprPath>;
+    // Get the options of the #[embed(...)] attribute.
+    // Ex: If attribute is tagged with #[embed(embed_with = "my_embed")], returns the "my_embed" path.
+    fn expand_tag(&self, fields: &syn::Fields) -> syn::Result<EmbedOptions>;
 }
 
 impl CustomAttributeParser for syn::Attribute {
@@ -57,10 +99,17 @@ impl CustomAttributeParser for syn::Attribute {
         }
 
         self.parse_nested_meta(|meta| {
+            if meta.path.is_ident(SKIP_IF_EMPTY) {
+                return Ok(());
+            }
+
             // Parse the meta attribute as an expression. Need this to compile.
             meta.value()?.parse::<syn::Expr>()?;
 
-            if meta.path.is_ident(EMBED_WITH) {
+            if meta.path.is_ident(EMBED_WITH)
+                || meta.path.is_ident(PREFIX)
+                || meta.path.is_ident(FORMAT)
+            {
                 Ok(())
             } else {
                 let path = meta.path.to_token_stream().to_string().replace(' ', "");
@@ -74,15 +123,15 @@ impl CustomAttributeParser for syn::Attribute {
         Ok(true)
     }
 
-    fn expand_tag(&self) -> syn::Result<syn::ExprPath> {
-        fn function_path(meta: &ParseNestedMeta<'_>) -> syn::Result<ExprPath> {
-            // #[embed(embed_with = "...")]
+    fn expand_tag(&self, fields: &syn::Fields) -> syn::Result<EmbedOptions> {
+        fn string_value(meta: &ParseNestedMeta<'_>, name: &str) -> syn::Result<LitStr> {
+            // #[embed(name = "...")]
             let expr = meta.value()?.parse::<syn::Expr>().unwrap();
             let mut value = &expr;
             while let syn::Expr::Group(e) = value {
                 value = &e.expr;
             }
-            let string = if let syn::Expr::Lit(syn::ExprLit {
+            if let syn::Expr::Lit(syn::ExprLit {
                 lit: syn::Lit::Str(lit_str),
                 ..
             }) = value
@@ -94,30 +143,82 @@ impl CustomAttributeParser for syn::Attribute {
                         format!("unexpected suffix `{}` on string literal", suffix),
                     ));
                 }
-                lit_str.clone()
+                Ok(lit_str.clone())
             } else {
-                return Err(syn::Error::new_spanned(
+                Err(syn::Error::new_spanned(
                     value,
                     format!(
                         "expected {} attribute to be a string: `{} = \"...\"`",
-                        EMBED_WITH, EMBED_WITH
+                        name, name
                     ),
-                ));
-            };
-
-            string.parse()
+                ))
+            }
         }
 
-        let mut custom_func_path = None;
+        let mut options = EmbedOptions::default();
 
-        self.parse_nested_meta(|meta| match function_path(&meta) {
-            Ok(path) => {
-                custom_func_path = Some(path);
-                Ok(())
+        self.parse_nested_meta(|meta| {
+            if meta.path.is_ident(SKIP_IF_EMPTY) {
+                options.skip_if_empty = true;
+            } else if meta.path.is_ident(EMBED_WITH) {
+                options.embed_with = Some(string_value(&meta, EMBED_WITH)?.parse()?);
+            } else if meta.path.is_ident(PREFIX) {
+                options.prefix = Some(string_value(&meta, PREFIX)?);
+            } else if meta.path.is_ident(FORMAT) {
+                let format = string_value(&meta, FORMAT)?;
+                let names = format_arguments(&format, fields)?;
+                options.format = Some((format, names));
             }
-            Err(e) => Err(e),
+            Ok(())
         })?;
 
-        Ok(custom_func_path.unwrap())
+        if let (Some(_), Some((format, _))) = (&options.embed_with, &options.format) {
+            return Err(syn::Error::new_spanned(
+                format,
+                format!("`{}` and `{}` cannot be used together", EMBED_WITH, FORMAT),
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the fields referenced by the `{field}` (or `{field:spec}`) arguments of the `format`
+/// string, in order and without duplicates. Only named fields can be referenced.
+fn format_arguments(format: &LitStr, fields: &syn::Fields) -> syn::Result<Vec<syn::Ident>> {
+    let value = format.value();
+    let mut names: Vec<syn::Ident> = vec![];
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '{' => {
+                let argument = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                let name = argument.split(':').next().unwrap_or_default().trim();
+
+                let field = fields
+                    .iter()
+                    .filter_map(|field| field.ident.as_ref())
+                    .find(|ident| *ident == name)
+                    .ok_or_else(|| {
+                        syn::Error::new_spanned(
+                            format,
+                            format!(
+                                "`{{{}}}` does not name a field: {} arguments must be field names, e.g. `{{word}}`",
+                                argument, FORMAT
+                            ),
+                        )
+                    })?;
+                if !names.contains(field) {
+                    names.push(field.clone());
+                }
+            }
+            _ => (),
+        }
     }
+
+    Ok(names)
 }
@@ -1,13 +1,17 @@
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::DataStruct;
+use quote::{format_ident, quote};
+use syn::{DataEnum, DataStruct};
 
 use crate::{
     basic::{add_struct_bounds, basic_embed_fields},
-    custom::custom_embed_fields,
+    container::container_options,
+    custom::{add_custom_bounds, add_format_bounds, custom_embed_fields, EmbedOptions},
+    serialize::serialize_assertions,
 };
 
 pub(crate) fn expand_derive_embedding(input: &mut syn::DeriveInput) -> syn::Result<TokenStream> {
+    let options = container_options(input)?;
+    let assertions = options.check_serialize.then(|| serialize_assertions(input));
     let name = &input.ident;
     let data = &input.data;
     let generics = &mut input.generics;
@@ -15,7 +19,7 @@ pub(crate) fn expand_derive_embedding(input: &mut syn::DeriveInput) -> syn::Resu
     let target_stream = match data {
         syn::Data::Struct(data_struct) => {
             let (basic_targets, basic_target_size) = data_struct.basic(generics);
-            let (custom_targets, custom_target_size) = data_struct.custom()?;
+            let (custom_targets, custom_target_size) = data_struct.custom(generics)?;
 
             // If there are no fields tagged with `#[embed]` or `#[embed(embed_with = "...")]`, return an empty TokenStream.
             // ie. do not implement `Embed` trait for the struct.
@@ -31,20 +35,39 @@ pub(crate) fn expand_derive_embedding(input: &mut syn::DeriveInput) -> syn::Resu
                 #custom_targets;
             }
         }
-        _ => {
+        // Variants without fields tagged with `#[embed]` or `#[embed(embed_with = "...")]`
+        // embed their serialization, so there is no minimum number of tagged fields.
+        syn::Data::Enum(data_enum) => data_enum.variants(name, generics)?,
+        syn::Data::Union(_) => {
             return Err(syn::Error::new_spanned(
                 input,
-                "Embed derive macro should only be used on structs",
+                "Embed derive macro should only be used on structs and enums",
             ))
         }
     };
 
+    // With `#[embed(mode = "concat")]`, the texts of the tagged fields are joined into one text.
+    let target_stream = match options.concat {
+        Some(separator) => quote! {
+            let mut __record = rig::embeddings::embed::TextEmbedder::default();
+            {
+                let embedder = &mut __record;
+                #target_stream;
+            }
+            let __texts = __record.into_texts();
+            if !__texts.is_empty() {
+                embedder.embed(__texts.join(#separator));
+            }
+        },
+        None => target_stream,
+    };
+
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
     let gen = quote! {
-        // Note: `Embed` trait is imported with the macro.
+        #assertions
 
-        impl #impl_generics Embed for #name #ty_generics #where_clause {
+        impl #impl_generics rig::embeddings::embed::Embed for #name #ty_generics #where_clause {
             fn embed(&self, embedder: &mut rig::embeddings::embed::TextEmbedder) -> Result<(), rig::embeddings::embed::EmbedError> {
                 #target_stream;
 
@@ -60,13 +83,13 @@ trait StructParser {
     // Handles fields tagged with `#[embed]`
     fn basic(&self, generics: &mut syn::Generics) -> (TokenStream, usize);
 
-    // Handles fields tagged with `#[embed(embed_with = "...")]`
-    fn custom(&self) -> syn::Result<(TokenStream, usize)>;
+    // Handles fields tagged with `#[embed(...)]` (e.g.: `#[embed(embed_with = "...")]`)
+    fn custom(&self, generics: &mut syn::Generics) -> syn::Result<(TokenStream, usize)>;
 }
 
 impl StructParser for DataStruct {
     fn basic(&self, generics: &mut syn::Generics) -> (TokenStream, usize) {
-        let embed_targets = basic_embed_fields(self)
+        let embed_targets = basic_embed_fields(&self.fields)
             // Iterate over every field tagged with `#[embed]`
             .map(|field| {
                 add_struct_bounds(generics, &field.ty);
@@ -81,22 +104,33 @@ impl StructParser for DataStruct {
 
         (
             quote! {
-                #(#embed_targets.embed(embedder)?;)*
+                #(rig::embeddings::embed::Embed::embed(&#embed_targets, embedder)?;)*
             },
             embed_targets.len(),
         )
     }
 
-    fn custom(&self) -> syn::Result<(TokenStream, usize)> {
-        let embed_targets = custom_embed_fields(self)?
-            // Iterate over every field tagged with `#[embed(embed_with = "...")]`
+    fn custom(&self, generics: &mut syn::Generics) -> syn::Result<(TokenStream, usize)> {
+        let embed_targets = custom_embed_fields(&self.fields)?
+            // Iterate over every field tagged with `#[embed(...)]`
             .into_iter()
-            .map(|(field, custom_func_path)| {
+            .map(|(field, options)| {
                 let field_name = &field.ident;
+                let format_arguments = options
+                    .format
+                    .iter()
+                    .flat_map(|(_, names)| names)
+                    .map(|name| (name.clone(), quote! { self.#name }))
+                    .collect();
 
-                quote! {
-                    #custom_func_path(embedder, self.#field_name.clone())?;
-                }
+                custom_target(
+                    generics,
+                    &self.fields,
+                    field,
+                    &options,
+                    quote! { self.#field_name },
+                    format_arguments,
+                )
             })
             .collect::<Vec<_>>();
 
@@ -108,3 +142,198 @@ impl StructParser for DataStruct {
         ))
     }
 }
+
+trait EnumParser {
+    // Generates a `match` arm per variant, embedding the tagged fields of the variant
+    fn variants(&self, name: &syn::Ident, generics: &mut syn::Generics)
+        -> syn::Result<TokenStream>;
+}
+
+impl EnumParser for DataEnum {
+    fn variants(
+        &self,
+        name: &syn::Ident,
+        generics: &mut syn::Generics,
+    ) -> syn::Result<TokenStream> {
+        let arms = self
+            .variants
+            .iter()
+            .map(|variant| {
+                let variant_name = &variant.ident;
+                let fields = &variant.fields;
+
+                let basic_fields = basic_embed_fields(fields).collect::<Vec<_>>();
+                let custom_fields = custom_embed_fields(fields)?;
+
+                if basic_fields.is_empty() && custom_fields.is_empty() {
+                    return Ok(quote! {
+                        #name::#variant_name { .. } => embedder.embed_serialized(self)?,
+                    });
+                }
+
+                let mut bindings = vec![];
+
+                let basic_targets = basic_fields
+                    .into_iter()
+                    .map(|field| {
+                        add_struct_bounds(generics, &field.ty);
+
+                        let binding = bind(fields, field, &mut bindings);
+                        quote! {
+                            rig::embeddings::embed::Embed::embed(#binding, embedder)?;
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                let custom_targets = custom_fields
+                    .into_iter()
+                    .map(|(field, options)| {
+                        let binding = bind(fields, field, &mut bindings);
+                        let format_arguments = options
+                            .format
+                            .iter()
+                            .flat_map(|(_, names)| names)
+                            .map(|name| {
+                                let field = fields
+                                    .iter()
+                                    .find(|field| field.ident.as_ref() == Some(name))
+                                    .expect("format arguments are fields of the variant");
+                                let binding = bind(fields, field, &mut bindings);
+                                (name.clone(), quote! { #binding })
+                            })
+                            .collect();
+
+                        custom_target(
+                            generics,
+                            fields,
+                            field,
+                            &options,
+                            quote! { #binding },
+                            format_arguments,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let bindings = bindings
+                    .iter()
+                    .map(|(member, binding)| quote! { #member: #binding });
+
+                Ok(quote! {
+                    #name::#variant_name { #(#bindings,)* .. } => {
+                        #(#basic_targets)*
+                        #(#custom_targets)*
+                    }
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        Ok(quote! {
+            match self {
+                #(#arms)*
+            }
+        })
+    }
+}
+
+/// Generates the code embedding a field tagged with `#[embed(...)]`, whose value is `value`
+/// (e.g.: `self.text`). `format_arguments` are the values of the fields referenced by the
+/// `format` option, if any.
+fn custom_target(
+    generics: &mut syn::Generics,
+    fields: &syn::Fields,
+    field: &syn::Field,
+    options: &EmbedOptions,
+    value: TokenStream,
+    format_arguments: Vec<(syn::Ident, TokenStream)>,
+) -> TokenStream {
+    // The texts are embedded into a temporary embedder when they are rewritten.
+    let target = match options.rewrites_texts() {
+        true => format_ident!("__texts"),
+        false => format_ident!("embedder"),
+    };
+
+    let embed = match (&options.embed_with, &options.format) {
+        (Some(custom_func_path), _) => {
+            add_custom_bounds(generics, &field.ty);
+            quote! {
+                #custom_func_path(#target, #value.clone())?;
+            }
+        }
+        (None, Some((format, _))) => {
+            let (names, values): (Vec<_>, Vec<_>) = format_arguments.into_iter().unzip();
+            for name in &names {
+                if let Some(field) = fields.iter().find(|f| f.ident.as_ref() == Some(name)) {
+                    add_format_bounds(generics, &field.ty);
+                }
+            }
+            quote! {
+                #target.embed(::std::format!(#format, #(#names = #values),*));
+            }
+        }
+        (None, None) => {
+            add_struct_bounds(generics, &field.ty);
+            quote! {
+                rig::embeddings::embed::Embed::embed(&#value, #target)?;
+            }
+        }
+    };
+
+    if !options.rewrites_texts() {
+        return embed;
+    }
+
+    let skip = options.skip_if_empty.then(|| {
+        quote! {
+            if __text.trim().is_empty() {
+                continue;
+            }
+        }
+    });
+    let text = match &options.prefix {
+        Some(prefix) => quote! { ::std::format!("{}{}", #prefix, __text) },
+        None => quote! { __text },
+    };
+
+    quote! {
+        {
+            let mut __texts = rig::embeddings::embed::TextEmbedder::default();
+            {
+                let __texts = &mut __texts;
+                #embed
+            }
+            for __text in __texts.into_texts() {
+                #skip
+                embedder.embed(#text);
+            }
+        }
+    }
+}
+
+/// Binds `field` of a variant to a variable in the variant's pattern (e.g.: `text: __field_text`
+/// or `0: __field_0` for tuple variants) and returns the variable. A field is only bound once.
+fn bind(
+    fields: &syn::Fields,
+    field: &syn::Field,
+    bindings: &mut Vec<(syn::Member, syn::Ident)>,
+) -> syn::Ident {
+    let index = fields
+        .iter()
+        .position(|f| std::ptr::eq(f, field))
+        .expect("field belongs to the variant");
+
+    let (member, binding) = match &field.ident {
+        Some(ident) => (
+            syn::Member::Named(ident.clone()),
+            format_ident!("__field_{}", ident),
+        ),
+        None => (
+            syn::Member::Unnamed(index.into()),
+            format_ident!("__field_{}", index),
+        ),
+    };
+    if !bindings.iter().any(|(bound, _)| *bound == member) {
+        bindings.push((member, binding.clone()));
+    }
+
+    binding
+}
@@ -1,13 +1,42 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, LitStr};
 
 mod basic;
+mod container;
 mod custom;
 mod embed;
+mod prompt;
+mod serialize;
+mod state;
 
 pub(crate) const EMBED: &str = "embed";
 
+/// Derives the `Embed` trait, embedding the fields tagged with `#[embed]` (which must implement
+/// `Embed`) or with `#[embed(...)]` options:
+/// - `embed_with = "path"`: embeds the field with a custom function
+///   `fn(&mut TextEmbedder, T) -> Result<(), EmbedError>` (the field must implement `Clone`),
+/// - `format = "{word}: {definition}"`: embeds the format string instead of the field, whose
+///   arguments are fields of the struct (or variant) implementing `Display`,
+/// - `prefix = "Title: "`: prefixes the texts of the field,
+/// - `skip_if_empty`: skips the empty (or whitespace only) texts of the field.
+///
+/// A `#[embed]` field can be of a type deriving `Embed` itself (or of containers of such types,
+/// e.g.: `Vec<Address>`, including recursive ones such as `children: Vec<Category>`): its texts
+/// are those of its own tagged fields, and are rewritten by the options of the field (e.g.:
+/// with a `prefix`) or joined in `concat` mode like the other texts.
+///
+/// The `#[embed]` fields are embedded first, then the `#[embed(...)]` ones, in the order of their
+/// declaration. By default, each text of the tagged fields is embedded separately. With
+/// `#[embed(mode = "concat")]` on the struct (or enum), the texts are joined into a single text
+/// (separated by newlines, or by the `separator = "..."` option), so that the record gets a
+/// single embedding.
+///
+/// With `#[embed(check_serialize)]` on the struct (or enum), the derive checks that the record
+/// implements `Serialize` (as required to store it in most vector stores), reporting the fields
+/// that don't serialize. The fields skipped with `#[serde(skip)]` (or `skip_serializing`) are
+/// not checked.
+///
 /// References:
 /// <https://doc.rust-lang.org/book/ch19-06-macros.html#how-to-write-a-custom-derive-macro>
 /// <https://doc.rust-lang.org/reference/procedural-macros.html>
@@ -19,3 +48,55 @@ pub fn derive_embedding_trait(item: TokenStream) -> TokenStream {
         .unwrap_or_else(syn::Error::into_compile_error)
         .into()
 }
+
+/// Builds a prompt from a template whose variables are checked at compile time.
+///
+/// `prompt!("Summarize {doc} for {audience}")` returns a builder with a method per variable of
+/// the template (here `doc` and `audience`, taking any value implementing `Display`), whose
+/// `build` method renders the prompt. `build` only compiles once all the variables are set, so
+/// that prompts cannot miss a variable at runtime, unlike the `PromptTemplate`s parsed at
+/// runtime. Literal braces are written `{{` and `}}`.
+///
+/// ```ignore
+/// let prompt = rig::prompt!("Summarize {doc} for {audience}")
+///     .doc(&article)
+///     .audience("engineers")
+///     .build();
+/// ```
+#[proc_macro]
+pub fn prompt(input: TokenStream) -> TokenStream {
+    let template = parse_macro_input!(input as LitStr);
+
+    prompt::expand_prompt(&template)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives the `ConversationState` trait, mapping the named fields of a struct to the variables
+/// of the conversation: injected in the prompt templates, and updated from the `<state>` blocks
+/// of the outputs of the model. The fields must implement `Serialize` and `Deserialize`, and
+/// their doc comments describe the variables to the model.
+///
+/// The fields can be tagged with:
+/// - `#[state(rename = "name")]`: names the variable differently from the field,
+/// - `#[state(readonly)]`: the variable is injected in the templates, but not updated by the
+///   model (e.g.: the name of the user),
+/// - `#[state(skip)]`: the field is not a variable of the state.
+///
+/// ```ignore
+/// #[derive(rig::ConversationState, Default)]
+/// struct Booking {
+///     /// City of the trip, once known
+///     city: Option<String>,
+///     #[state(readonly)]
+///     customer: String,
+/// }
+/// ```
+#[proc_macro_derive(ConversationState, attributes(state))]
+pub fn derive_conversation_state(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    state::expand_derive_state(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
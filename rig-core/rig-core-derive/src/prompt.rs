@@ -0,0 +1,180 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::LitStr;
+
+/// Part of the template of a `prompt!`
+enum Part {
+    Text(String),
+    Variable(syn::Ident),
+}
+
+/// Parses the `{name}` variables of the template, `{{` and `}}` being literal braces.
+fn parse_template(template: &LitStr) -> syn::Result<Vec<Part>> {
+    let value = template.value();
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed {
+                    return Err(syn::Error::new_spanned(
+                        template,
+                        "unclosed `{` in the prompt template (literal braces are written `{{`)",
+                    ));
+                }
+
+                let name = name.trim();
+                let variable = syn::parse_str::<syn::Ident>(name).map_err(|_| {
+                    syn::Error::new_spanned(
+                        template,
+                        format!(
+                            "`{{{}}}` is not a variable: variables are identifiers, e.g. `{{doc}}` \
+                            (conditional sections require a `PromptTemplate`)",
+                            name
+                        ),
+                    )
+                })?;
+                if variable == "build" {
+                    return Err(syn::Error::new_spanned(
+                        template,
+                        "`build` cannot be the name of a prompt variable",
+                    ));
+                }
+                parts.push(Part::Text(std::mem::take(&mut text)));
+                parts.push(Part::Variable(variable));
+            }
+            '}' => {
+                return Err(syn::Error::new_spanned(
+                    template,
+                    "unmatched `}` in the prompt template (literal braces are written `}}`)",
+                ))
+            }
+            c => text.push(c),
+        }
+    }
+    parts.push(Part::Text(text));
+
+    Ok(parts)
+}
+
+pub(crate) fn expand_prompt(template: &LitStr) -> syn::Result<TokenStream> {
+    let parts = parse_template(template)?;
+
+    // Variables of the template, in order and without duplicates
+    let mut variables: Vec<&syn::Ident> = vec![];
+    for part in &parts {
+        if let Part::Variable(variable) = part {
+            if !variables.contains(&variable) {
+                variables.push(variable);
+            }
+        }
+    }
+
+    // The state of each variable is a type parameter of the builder: the marker type named after
+    // the variable while it is missing, and `String` once it is set.
+    let params = (0..variables.len())
+        .map(|i| format_ident!("__V{}", i))
+        .collect::<Vec<_>>();
+
+    let setters = variables.iter().enumerate().map(|(i, variable)| {
+        let state = params.iter().enumerate().map(|(j, param)| match i == j {
+            true => quote! { ::std::string::String },
+            false => quote! { #param },
+        });
+        let fields = variables.iter().map(|other| match other == variable {
+            true => quote! { #other: ::std::string::ToString::to_string(&value) },
+            false => quote! { #other: self.#other },
+        });
+        let doc = format!("Set the `{{{}}}` variable of the prompt.", variable);
+
+        quote! {
+            #[doc = #doc]
+            pub fn #variable(
+                self,
+                value: impl ::std::fmt::Display,
+            ) -> __PromptBuilder<#(#state),*> {
+                __PromptBuilder { #(#fields),* }
+            }
+        }
+    });
+
+    let render = parts.iter().map(|part| match part {
+        Part::Text(text) if text.is_empty() => quote! {},
+        Part::Text(text) => quote! { prompt.push_str(#text); },
+        Part::Variable(variable) => {
+            quote! { prompt.push_str(__PromptVariable::text(&self.#variable)); }
+        }
+    });
+    let capacity = parts
+        .iter()
+        .map(|part| match part {
+            Part::Text(text) => text.len(),
+            Part::Variable(_) => 0,
+        })
+        .sum::<usize>();
+
+    Ok(quote! {
+        {
+            #[diagnostic::on_unimplemented(
+                message = "the prompt variable `{Self}` is not set",
+                label = "missing prompt variable",
+                note = "set each variable of the prompt with the method named after it before building it"
+            )]
+            trait __PromptVariable {
+                fn text(&self) -> &str;
+            }
+
+            impl __PromptVariable for ::std::string::String {
+                fn text(&self) -> &str {
+                    self
+                }
+            }
+
+            #(
+                #[allow(non_camel_case_types)]
+                struct #variables;
+            )*
+
+            /// Builder of the prompt, whose variables must all be set to build it.
+            struct __PromptBuilder<#(#params),*> {
+                #(#variables: #params),*
+            }
+
+            #[allow(dead_code)]
+            impl<#(#params),*> __PromptBuilder<#(#params),*> {
+                #(#setters)*
+
+                /// Render the prompt with its variables.
+                pub fn build(self) -> ::std::string::String
+                where
+                    #(#params: __PromptVariable),*
+                {
+                    let mut prompt = ::std::string::String::with_capacity(#capacity);
+                    #(#render)*
+                    prompt
+                }
+            }
+
+            __PromptBuilder { #(#variables: #variables),* }
+        }
+    })
+}
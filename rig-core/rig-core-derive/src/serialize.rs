@@ -0,0 +1,82 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{parse_quote, spanned::Spanned};
+
+const SERDE: &str = "serde";
+const SKIP: &str = "skip";
+const SKIP_SERIALIZING: &str = "skip_serializing";
+
+/// Generates the assertions of `#[embed(check_serialize)]`: every serialized field of the struct
+/// (or of the variants of the enum) must implement `Serialize`, and so must the record itself.
+/// Each assertion is spanned to the type of its field, so that the error points to it.
+pub(crate) fn serialize_assertions(input: &syn::DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let fields = match &input.data {
+        syn::Data::Struct(data_struct) => data_struct.fields.iter().collect::<Vec<_>>(),
+        syn::Data::Enum(data_enum) => data_enum
+            .variants
+            .iter()
+            .flat_map(|variant| &variant.fields)
+            .collect(),
+        syn::Data::Union(_) => vec![],
+    };
+
+    let field_assertions = fields
+        .into_iter()
+        .filter(|field| !skips_serialization(field))
+        .map(|field| {
+            let ty = &field.ty;
+            quote_spanned! {ty.span()=>
+                rig::embeddings::embed::assert_serialize::<#ty>();
+            }
+        });
+
+    // Generic fields are checked where the record is used, their parameters must serialize.
+    let mut generics = input.generics.clone();
+    let type_params = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    let where_clause = generics.make_where_clause();
+    for param in type_params {
+        where_clause.predicates.push(parse_quote! {
+            #param: rig::embeddings::embed::SerializeCheck
+        });
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        const _: () = {
+            #[allow(dead_code)]
+            fn __assert_serialize #impl_generics () #where_clause {
+                #(#field_assertions)*
+                rig::embeddings::embed::assert_serialize::<#name #ty_generics>();
+            }
+        };
+    }
+}
+
+/// Whether the field is skipped by serde, ie. tagged with `#[serde(skip)]` or
+/// `#[serde(skip_serializing)]`.
+fn skips_serialization(field: &syn::Field) -> bool {
+    let mut skip = false;
+    for attribute in field.attrs.iter().filter(|a| a.path().is_ident(SERDE)) {
+        // Other serde attributes are validated by serde itself.
+        let _ = attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident(SKIP) || meta.path.is_ident(SKIP_SERIALIZING) {
+                skip = true;
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|meta| {
+                    if meta.input.peek(syn::Token![=]) {
+                        meta.value()?.parse::<syn::Expr>()?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        });
+    }
+    skip
+}
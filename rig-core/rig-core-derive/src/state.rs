@@ -0,0 +1,144 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::LitStr;
+
+const STATE: &str = "state";
+const RENAME: &str = "rename";
+const SKIP: &str = "skip";
+const READONLY: &str = "readonly";
+
+/// Options of a field tagged with `#[state(...)]`, ie. `#[state(rename = "...")]`,
+/// `#[state(skip)]` and `#[state(readonly)]`.
+#[derive(Default)]
+struct StateOptions {
+    /// Name of the variable, if not the name of the field
+    rename: Option<LitStr>,
+    /// Whether the field is not a variable of the state
+    skip: bool,
+    /// Whether the variable is not updated from the outputs of the model
+    readonly: bool,
+}
+
+fn state_options(field: &syn::Field) -> syn::Result<StateOptions> {
+    let mut options = StateOptions::default();
+
+    for attribute in field.attrs.iter().filter(|a| a.path().is_ident(STATE)) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident(RENAME) {
+                options.rename = Some(meta.value()?.parse::<LitStr>()?);
+            } else if meta.path.is_ident(SKIP) {
+                options.skip = true;
+            } else if meta.path.is_ident(READONLY) {
+                options.readonly = true;
+            } else {
+                let path = meta.path.to_token_stream().to_string().replace(' ', "");
+                return Err(syn::Error::new_spanned(
+                    meta.path,
+                    format_args!("unknown state attribute `{}`", path),
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(options)
+}
+
+/// Description of the field, ie. its doc comment.
+fn description(field: &syn::Field) -> String {
+    field
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) => Some(doc.value().trim().to_string()),
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn expand_derive_state(input: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(fields),
+        ..
+    }) = &input.data
+    else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "ConversationState derive macro should only be used on structs with named fields",
+        ));
+    };
+
+    let mut variables = vec![];
+    let mut to_variables = vec![];
+    let mut updates = vec![];
+    for field in &fields.named {
+        let options = state_options(field)?;
+        if options.skip {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().expect("named fields");
+        let variable = options
+            .rename
+            .map(|rename| rename.value())
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        let description = description(field);
+        let extract = !options.readonly;
+
+        variables.push(quote! {
+            rig::state::StateVariable {
+                name: #variable,
+                description: #description,
+                extract: #extract,
+            }
+        });
+        to_variables.push(quote! {
+            variables.insert(
+                #variable.to_string(),
+                rig::state::to_variable(#variable, &self.#ident)?,
+            );
+        });
+        updates.push(quote! {
+            if let Some(value) = variables.get(#variable) {
+                self.#ident = rig::state::from_variable(#variable, value)?;
+                updated.push(#variable.to_string());
+            }
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics rig::state::ConversationState for #name #ty_generics #where_clause {
+            fn variables() -> Vec<rig::state::StateVariable> {
+                vec![#(#variables),*]
+            }
+
+            fn to_variables(&self) -> Result<rig::state::Variables, rig::state::StateError> {
+                let mut variables = rig::state::Variables::new();
+                #(#to_variables)*
+                Ok(variables)
+            }
+
+            fn update(
+                &mut self,
+                variables: &rig::state::Variables,
+            ) -> Result<Vec<String>, rig::state::StateError> {
+                let mut updated = vec![];
+                #(#updates)*
+                Ok(updated)
+            }
+        }
+    })
+}
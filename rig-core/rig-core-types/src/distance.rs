@@ -20,7 +20,7 @@ pub trait VectorDistance {
 }
 
 #[cfg(not(feature = "rayon"))]
-impl VectorDistance for crate::embeddings::Embedding {
+impl VectorDistance for crate::embedding::Embedding {
     fn dot_product(&self, other: &Self) -> f64 {
         self.vec
             .iter()
@@ -75,7 +75,7 @@ impl VectorDistance for crate::embeddings::Embedding {
 
 #[cfg(feature = "rayon")]
 mod rayon {
-    use crate::embeddings::{distance::VectorDistance, Embedding};
+    use crate::{distance::VectorDistance, embedding::Embedding};
     use rayon::prelude::*;
 
     impl VectorDistance for Embedding {
@@ -135,7 +135,7 @@ mod rayon {
 #[cfg(test)]
 mod tests {
     use super::VectorDistance;
-    use crate::embeddings::Embedding;
+    use crate::embedding::Embedding;
 
     fn embeddings() -> (Embedding, Embedding) {
         let embedding_1 = Embedding {
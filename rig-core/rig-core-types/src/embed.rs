@@ -1,5 +1,5 @@
 //! The module defines the [Embed] trait, which must be implemented for types
-//! that can be embedded by the [crate::embeddings::EmbeddingsBuilder].
+//! that can be embedded by the `EmbeddingsBuilder` of `rig-core`.
 //!
 //! The module also defines the [EmbedError] struct which is used for when the [Embed::embed]
 //! method of the [Embed] trait fails.
@@ -7,7 +7,14 @@
 //! The module also defines the [TextEmbedder] struct which accumulates string values that need to be embedded.
 //! It is used directly with the [Embed] trait.
 //!
-//! Finally, the module implements [Embed] for many common primitive types.
+//! Finally, the module implements [Embed] for many common primitive types and standard containers.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+    sync::Arc,
+};
 
 /// Error type used for when the [Embed::embed] method of the [Embed] trait fails.
 /// Used by default implementations of [Embed] for common types.
@@ -24,6 +31,13 @@ impl EmbedError {
 /// Derive this trait for objects that need to be converted to vector embeddings.
 /// The [Embed::embed] method accumulates string values that need to be embedded by adding them to the [TextEmbedder].
 /// If an error occurs, the method should return [EmbedError].
+///
+/// Any type can implement the trait, producing any number of texts: the
+/// `EmbeddingsBuilder` of `rig-core` returns the embeddings of each
+/// document in the order its texts were added, each with its text ([Embedding::document]), so
+/// that metadata of the embedded items can be matched to their embeddings by position.
+///
+/// [Embedding::document]: crate::embedding::Embedding::document
 /// # Example
 /// ```rust
 /// use std::env;
@@ -68,7 +82,7 @@ pub trait Embed {
 /// Used by the [Embed] trait.
 #[derive(Default)]
 pub struct TextEmbedder {
-    pub(crate) texts: Vec<String>,
+    texts: Vec<String>,
 }
 
 impl TextEmbedder {
@@ -76,8 +90,36 @@ impl TextEmbedder {
     pub fn embed(&mut self, text: String) {
         self.texts.push(text);
     }
+
+    /// Adds the JSON serialization of `value` to the list of texts that need to be embedded.
+    /// Used by the `Embed` derive macro for enum variants without `#[embed]` fields.
+    pub fn embed_serialized(&mut self, value: &impl serde::Serialize) -> Result<(), EmbedError> {
+        self.texts
+            .push(serde_json::to_string(value).map_err(EmbedError::new)?);
+        Ok(())
+    }
+
+    /// Returns the texts added to the [TextEmbedder], in order.
+    pub fn into_texts(self) -> Vec<String> {
+        self.texts
+    }
 }
 
+/// Implemented by the types that serialize. Used by `#[derive(Embed)]` to check that the fields of
+/// the records tagged with `#[embed(check_serialize)]` serialize, pointing to those that don't.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` does not implement `Serialize`",
+    label = "must implement `Serialize`, as required by `#[embed(check_serialize)]`",
+    note = "derive `Serialize` for it, or skip the field with `#[serde(skip)]`"
+)]
+pub trait SerializeCheck: serde::Serialize {}
+
+impl<T: ?Sized + serde::Serialize> SerializeCheck for T {}
+
+#[doc(hidden)]
+pub fn assert_serialize<T: ?Sized + SerializeCheck>() {}
+
 /// Utility function that returns a vector of strings that need to be embedded for a
 /// given object that implements the [Embed] trait.
 pub fn to_texts(item: impl Embed) -> Result<Vec<String>, EmbedError> {
@@ -188,3 +230,77 @@ impl<T: Embed> Embed for Vec<T> {
         Ok(())
     }
 }
+
+impl Embed for Cow<'_, str> {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.to_string());
+        Ok(())
+    }
+}
+
+/// `None` values are skipped.
+impl<T: Embed> Embed for Option<T> {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        match self {
+            Some(item) => item.embed(embedder),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Embed + ?Sized> Embed for Box<T> {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        (**self).embed(embedder)
+    }
+}
+
+impl<T: Embed + ?Sized> Embed for Arc<T> {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        (**self).embed(embedder)
+    }
+}
+
+impl<T: Embed + ?Sized> Embed for Rc<T> {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        (**self).embed(embedder)
+    }
+}
+
+/// Only the values of the map are embedded, in arbitrary order.
+impl<K, V: Embed, S> Embed for HashMap<K, V, S> {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        for value in self.values() {
+            value.embed(embedder)?;
+        }
+        Ok(())
+    }
+}
+
+/// Only the values of the map are embedded, in the order of their keys.
+impl<K, V: Embed> Embed for BTreeMap<K, V> {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        for value in self.values() {
+            value.embed(embedder)?;
+        }
+        Ok(())
+    }
+}
+
+/// Implements [Embed] for tuples whose elements all implement [Embed], embedding the elements in order.
+macro_rules! impl_embed_for_tuple {
+    ($($ty:ident),+) => {
+        impl<$($ty: Embed),+> Embed for ($($ty,)+) {
+            #[allow(non_snake_case)]
+            fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+                let ($($ty,)+) = self;
+                $($ty.embed(embedder)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_embed_for_tuple!(A);
+impl_embed_for_tuple!(A, B);
+impl_embed_for_tuple!(A, B, C);
+impl_embed_for_tuple!(A, B, C, D);
@@ -0,0 +1,20 @@
+//! The module defines the [Embedding] struct, which represents a single document embedding.
+
+use serde::{Deserialize, Serialize};
+
+/// Struct that holds a single document and its embedding.
+#[derive(Clone, Default, Deserialize, Serialize, Debug)]
+pub struct Embedding {
+    /// The document that was embedded. Used for debugging.
+    pub document: String,
+    /// The embedding vector
+    pub vec: Vec<f64>,
+}
+
+impl PartialEq for Embedding {
+    fn eq(&self, other: &Self) -> bool {
+        self.document == other.document
+    }
+}
+
+impl Eq for Embedding {}
@@ -0,0 +1,22 @@
+//! The core traits and types of Rig which don't depend on an HTTP client or an async runtime:
+//! the [Embed] trait, the [Embedding] vectors and their [VectorDistance], the [message] types
+//! of the completion requests and the [OneOrMany] list.
+//!
+//! They are re-exported by `rig-core` at their usual paths (e.g.: `rig::embeddings::Embed`,
+//! `rig::completion::message::Message`), which should be used by applications. This crate is
+//! meant for the crates only needing the abstractions of Rig, such as alternative runtimes or
+//! test harnesses.
+//!
+//! # Cargo features
+//! - `rayon`: parallel computation of embedding distances
+
+pub mod distance;
+pub mod embed;
+pub mod embedding;
+pub mod message;
+pub mod one_or_many;
+
+pub use distance::VectorDistance;
+pub use embed::{to_texts, Embed, EmbedError, TextEmbedder};
+pub use embedding::Embedding;
+pub use one_or_many::{EmptyListError, OneOrMany};
@@ -1,11 +1,10 @@
 use std::{convert::Infallible, str::FromStr};
 
-use crate::OneOrMany;
+use crate::one_or_many::OneOrMany;
+use base64::{prelude::BASE64_STANDARD, Engine};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use super::CompletionError;
-
 // ================================================================
 // Message models
 // ================================================================
@@ -201,7 +200,7 @@ pub enum ImageDetail {
 impl Message {
     /// This helper method is primarily used to extract the first string prompt from a `Message`.
     /// Since `Message` might have more than just text content, we need to find the first text.
-    pub(crate) fn rag_text(&self) -> Option<String> {
+    pub fn rag_text(&self) -> Option<String> {
         match self {
             Message::User { content } => {
                 for item in content.iter() {
@@ -222,6 +221,15 @@ impl Message {
         }
     }
 
+    /// Helper constructor to make creating user messages with a text and an image (e.g.: a
+    /// screenshot or a photo) easier.
+    pub fn user_with_image(text: impl Into<String>, image: Image) -> Self {
+        Message::User {
+            content: OneOrMany::many([UserContent::text(text), UserContent::Image(image)])
+                .expect("There are two contents"),
+        }
+    }
+
     /// Helper constructor to make creating assistant messages easier.
     pub fn assistant(text: impl Into<String>) -> Self {
         Message::Assistant {
@@ -230,6 +238,79 @@ impl Message {
     }
 }
 
+impl Image {
+    /// Image given by its URL.
+    pub fn url(url: impl Into<String>) -> Self {
+        Image {
+            data: url.into(),
+            format: Some(ContentFormat::String),
+            ..Default::default()
+        }
+    }
+
+    /// Image given by its base64-encoded data.
+    pub fn base64(data: impl Into<String>, media_type: ImageMediaType) -> Self {
+        Image {
+            data: data.into(),
+            format: Some(ContentFormat::Base64),
+            media_type: Some(media_type),
+            detail: None,
+        }
+    }
+
+    /// Set the detail with which the image should be processed (open-ai specific).
+    pub fn detail(mut self, detail: ImageDetail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Image given by a URL, which can be a `data:` URL of base64-encoded data.
+    pub fn from_url(url: impl Into<String>) -> Self {
+        let url = url.into();
+        url.strip_prefix("data:")
+            .and_then(|data_url| data_url.split_once(";base64,"))
+            .map(|(mime_type, data)| Image {
+                data: data.to_string(),
+                format: Some(ContentFormat::Base64),
+                media_type: ImageMediaType::from_mime_type(mime_type),
+                detail: None,
+            })
+            .unwrap_or_else(|| Image::url(url))
+    }
+
+    /// URL of the image, which is a `data:` URL if the image is given by its base64-encoded data.
+    pub fn to_url(&self) -> Result<String, MessageError> {
+        // The base64 alphabet does not contain `:`, so the data of base64 images cannot be
+        // mistaken for URLs
+        if self.format == Some(ContentFormat::String) || self.data.contains(':') {
+            return Ok(self.data.clone());
+        }
+        match &self.media_type {
+            Some(media_type) => Ok(format!(
+                "data:{};base64,{}",
+                media_type.to_mime_type(),
+                self.data
+            )),
+            None => Err(MessageError::ConversionError(
+                "Base64 images require a media type".into(),
+            )),
+        }
+    }
+
+    /// Decoded data of the image, if it is given by its base64-encoded data (images given by
+    /// their URL must be downloaded instead).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MessageError> {
+        if self.format == Some(ContentFormat::String) || self.data.contains(':') {
+            return Err(MessageError::ConversionError(
+                "Image is given by its URL".into(),
+            ));
+        }
+        BASE64_STANDARD
+            .decode(&self.data)
+            .map_err(|e| MessageError::ConversionError(format!("Invalid base64 image: {e}")))
+    }
+}
+
 impl UserContent {
     /// Helper constructor to make creating user text content easier.
     pub fn text(text: impl Into<String>) -> Self {
@@ -554,9 +635,3 @@ pub enum MessageError {
     #[error("Message conversion error: {0}")]
     ConversionError(String),
 }
-
-impl From<MessageError> for CompletionError {
-    fn from(error: MessageError) -> Self {
-        CompletionError::RequestError(error.into())
-    }
-}
@@ -0,0 +1,615 @@
+//! Anonymization of personal data, e.g.: before sending prompts to third-party providers, or
+//! while ingesting documents.
+//!
+//! An [Anonymizer] replaces the personal data (the entities) found by its [Detector]s with
+//! tokens such as `<PERSON_1>` or `<EMAIL_2>`, and records the original texts of the tokens in
+//! a [TokenMap], so that the texts written with the tokens (e.g.: the responses of a model) can
+//! be de-anonymized. The same entity is always replaced by the same token, so that the model
+//! can still tell who is who.
+//!
+//! Detectors provided by Rig:
+//! - `RegexDetector` (with the `regex` feature): finds the matches of regular expressions, e.g.
+//!   emails and phone numbers with `RegexDetector::pii`
+//! - [LlmDetector]: asks a model for the named entities (people, organizations, locations, ...),
+//!   which regular expressions cannot find. The model should run locally (e.g.: with Ollama),
+//!   since it sees the personal data.
+//!
+//! Detectors run in the order they are added, each on the text anonymized by the previous ones:
+//! a model added after the regular expressions is a fallback for the entities they missed.
+//!
+//! An [AnonymizedModel] anonymizes the requests of a completion model (the prompt, the chat
+//! history and the documents) and de-anonymizes its responses, so that agents using it never
+//! send the personal data to the provider. The [anonymize](crate::pipeline::agent_ops::anonymize)
+//! op anonymizes the inputs of pipelines (e.g.: documents before they are embedded).
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     anonymization::{AnonymizedModel, Anonymizer, LlmDetector},
+//!     completion::Prompt,
+//!     providers::{ollama, openai},
+//! };
+//!
+//! let anonymizer = Anonymizer::pii()
+//!     .detector(LlmDetector::new(ollama::Client::new().completion_model("llama3.2")));
+//!
+//! let anonymized = anonymizer.anonymize("Email Jane Doe at jane@example.com").await?;
+//! assert_eq!(anonymized.text, "Email <PERSON_1> at <EMAIL_1>");
+//! assert_eq!(anonymized.tokens.deanonymize("Hi <PERSON_1>!"), "Hi Jane Doe!");
+//!
+//! // The provider only sees the tokens
+//! let model = AnonymizedModel::new(openai.completion_model("gpt-4o"), anonymizer);
+//! let agent = AgentBuilder::new(model).build();
+//! let response = agent.prompt("Write a birthday note to Jane Doe").await?;
+//! ```
+use std::{collections::BTreeMap, future::Future, sync::Arc};
+
+use futures::future::BoxFuture;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::{
+        CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Message,
+    },
+    extractor::{ExtractionError, Extractor, ExtractorBuilder},
+    message::{AssistantContent, ToolResultContent, UserContent},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AnonymizationError {
+    /// Error of the model detecting the entities
+    #[error("ExtractionError: {0}")]
+    ExtractionError(#[from] ExtractionError),
+}
+
+/// Personal data found in a text.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct Entity {
+    /// Category of the entity, in uppercase (e.g.: `PERSON` or `EMAIL`)
+    pub category: String,
+    /// Text of the entity, as it appears in the text
+    pub text: String,
+}
+
+impl Entity {
+    pub fn new(category: &str, text: &str) -> Self {
+        Self {
+            category: category.to_string(),
+            text: text.to_string(),
+        }
+    }
+}
+
+/// Trait for detectors of the personal data of texts.
+pub trait Detector: Send + Sync {
+    /// Entities found in `text`.
+    fn detect(
+        &self,
+        text: &str,
+    ) -> impl Future<Output = Result<Vec<Entity>, AnonymizationError>> + Send;
+}
+
+/// Wrapper trait to store detectors of different types
+trait DetectorDyn: Send + Sync {
+    fn detect_dyn<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<Entity>, AnonymizationError>>;
+}
+
+impl<D: Detector> DetectorDyn for D {
+    fn detect_dyn<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<Entity>, AnonymizationError>> {
+        Box::pin(self.detect(text))
+    }
+}
+
+/// Detector finding the matches of regular expressions.
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone, Default)]
+pub struct RegexDetector {
+    patterns: Vec<(String, regex::Regex)>,
+}
+
+#[cfg(feature = "regex")]
+impl RegexDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Detector of the usual personal data: emails (`EMAIL`), credit card numbers (`CARD`), US
+    /// social security numbers (`SSN`), phone numbers (`PHONE`) and IPv4 addresses (`IP`). The
+    /// patterns are the ones of [Redact::pii](crate::guardrails::Redact::pii).
+    pub fn pii() -> Self {
+        crate::guardrails::PII_PATTERNS
+            .iter()
+            .fold(Self::new(), |detector, (category, pattern)| {
+                detector
+                    .pattern(category, pattern)
+                    .expect("PII patterns are valid")
+            })
+    }
+
+    /// Detect the matches of `pattern` as entities of `category`.
+    pub fn pattern(mut self, category: &str, pattern: &str) -> Result<Self, regex::Error> {
+        self.patterns
+            .push((category.to_string(), regex::Regex::new(pattern)?));
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Detector for RegexDetector {
+    async fn detect(&self, text: &str) -> Result<Vec<Entity>, AnonymizationError> {
+        Ok(self
+            .patterns
+            .iter()
+            .flat_map(|(category, pattern)| {
+                pattern
+                    .find_iter(text)
+                    .map(|found| Entity::new(category, found.as_str()))
+            })
+            .collect())
+    }
+}
+
+/// Categories of the entities detected by default by an [LlmDetector]
+pub const DEFAULT_ENTITY_CATEGORIES: &[&str] = &["PERSON", "ORGANIZATION", "LOCATION", "ADDRESS"];
+
+/// Entities submitted by the model of an [LlmDetector]
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct DetectedEntities {
+    /// Personal data found in the text
+    entities: Vec<Entity>,
+}
+
+/// Detector asking a model for the named entities of texts (named-entity recognition).
+pub struct LlmDetector<M: CompletionModel> {
+    extractor: Extractor<M, DetectedEntities>,
+}
+
+impl<M: CompletionModel> LlmDetector<M> {
+    /// Detector of the [DEFAULT_ENTITY_CATEGORIES].
+    pub fn new(model: M) -> Self {
+        Self::with_categories(model, DEFAULT_ENTITY_CATEGORIES)
+    }
+
+    /// Detector of the entities of the given categories.
+    pub fn with_categories(model: M, categories: &[&str]) -> Self {
+        let extractor = ExtractorBuilder::new(model)
+            .preamble(&format!(
+                "Find the personal data of the text: the entities of the categories {}. \
+                Submit each entity with its category and its text exactly as it appears in the \
+                text. Ignore the tokens such as <PERSON_1>, which are already anonymized.",
+                categories.join(", ")
+            ))
+            .build();
+        Self { extractor }
+    }
+}
+
+impl<M: CompletionModel> Detector for LlmDetector<M> {
+    async fn detect(&self, text: &str) -> Result<Vec<Entity>, AnonymizationError> {
+        let detected = self.extractor.extract(text).await?;
+        // Only keep the entities actually found in the text
+        Ok(detected
+            .entities
+            .into_iter()
+            .filter(|entity| !entity.text.is_empty() && text.contains(&entity.text))
+            .collect())
+    }
+}
+
+/// Original texts of the tokens replacing the entities of anonymized texts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TokenMap {
+    tokens: BTreeMap<String, String>,
+}
+
+impl TokenMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Original text of `token`, if any.
+    pub fn original(&self, token: &str) -> Option<&str> {
+        self.tokens.get(token).map(String::as_str)
+    }
+
+    /// Tokens and their original texts.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tokens
+            .iter()
+            .map(|(token, original)| (token.as_str(), original.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Replace the tokens of `text` with their original texts.
+    pub fn deanonymize(&self, text: &str) -> String {
+        self.tokens
+            .iter()
+            .fold(text.to_string(), |text, (token, original)| {
+                text.replace(token, original)
+            })
+    }
+
+    /// Token of `entity`: the existing one if the entity was already replaced, a new one
+    /// otherwise
+    fn token(&mut self, entity: &Entity) -> String {
+        let category = entity.category.to_uppercase();
+        let prefix = format!("<{category}_");
+        let mut count = 0;
+        for (token, original) in &self.tokens {
+            if token.starts_with(&prefix) {
+                if *original == entity.text {
+                    return token.clone();
+                }
+                count += 1;
+            }
+        }
+        let token = format!("{prefix}{}>", count + 1);
+        self.tokens.insert(token.clone(), entity.text.clone());
+        token
+    }
+
+    /// Whether `text` is part of a token, and must not be replaced
+    fn is_token_part(&self, text: &str) -> bool {
+        self.tokens.keys().any(|token| token.contains(text))
+    }
+}
+
+/// Anonymized text, with the original texts of its tokens.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Anonymized {
+    pub text: String,
+    pub tokens: TokenMap,
+}
+
+/// Anonymizer replacing the entities found by its detectors with tokens (see the
+/// [module](self) documentation). Clones of an anonymizer share its detectors.
+#[derive(Clone, Default)]
+pub struct Anonymizer {
+    detectors: Vec<Arc<dyn DetectorDyn>>,
+}
+
+impl Anonymizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Anonymizer of the usual personal data found by `RegexDetector::pii`.
+    #[cfg(feature = "regex")]
+    pub fn pii() -> Self {
+        Self::new().detector(RegexDetector::pii())
+    }
+
+    /// Add a detector, run on the text anonymized by the previous ones.
+    pub fn detector(mut self, detector: impl Detector + 'static) -> Self {
+        self.detectors.push(Arc::new(detector));
+        self
+    }
+
+    /// Anonymize `text`.
+    pub async fn anonymize(&self, text: &str) -> Result<Anonymized, AnonymizationError> {
+        let mut tokens = TokenMap::new();
+        let text = self.anonymize_with(text, &mut tokens).await?;
+        Ok(Anonymized { text, tokens })
+    }
+
+    /// Anonymize `text`, replacing the entities already part of `tokens` with the same tokens
+    /// (e.g.: to anonymize the messages of a conversation consistently) and adding the new ones.
+    pub async fn anonymize_with(
+        &self,
+        text: &str,
+        tokens: &mut TokenMap,
+    ) -> Result<String, AnonymizationError> {
+        let mut text = text.to_string();
+        for detector in &self.detectors {
+            let mut entities = detector.detect_dyn(&text).await?;
+            // Replace the longest entities first, e.g.: "Jane Doe" before "Jane"
+            entities.sort_by_key(|entity| std::cmp::Reverse(entity.text.len()));
+            for entity in entities {
+                if entity.text.is_empty()
+                    || !text.contains(&entity.text)
+                    || tokens.is_token_part(&entity.text)
+                {
+                    continue;
+                }
+                let token = tokens.token(&entity);
+                text = text.replace(&entity.text, &token);
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Completion model anonymizing its requests before sending them to the wrapped model, and
+/// de-anonymizing the responses (see the [module](self) documentation).
+///
+/// The texts of the prompt, of the chat history (including the tool calls and their results)
+/// and of the documents are anonymized, with the same tokens for the whole request. The
+/// preamble and the tool definitions, written by the developer, are sent as is.
+#[derive(Clone)]
+pub struct AnonymizedModel<M: CompletionModel> {
+    model: M,
+    anonymizer: Anonymizer,
+}
+
+impl<M: CompletionModel> AnonymizedModel<M> {
+    pub fn new(model: M, anonymizer: Anonymizer) -> Self {
+        Self { model, anonymizer }
+    }
+
+    /// Anonymize the texts of `request`, returning the tokens replacing its entities
+    async fn anonymize_request(
+        &self,
+        request: &mut CompletionRequest,
+    ) -> Result<TokenMap, AnonymizationError> {
+        let mut tokens = TokenMap::new();
+        let mut texts = vec![];
+        for message in request
+            .chat_history
+            .iter_mut()
+            .chain(std::iter::once(&mut request.prompt))
+        {
+            message_texts(message, &mut texts);
+        }
+        texts.extend(
+            request
+                .documents
+                .iter_mut()
+                .map(|document| &mut document.text),
+        );
+
+        for text in texts {
+            *text = self.anonymizer.anonymize_with(text, &mut tokens).await?;
+        }
+        Ok(tokens)
+    }
+}
+
+/// Collect the texts of `message` (including the string values of the arguments of tool calls)
+pub(crate) fn message_texts<'a>(message: &'a mut Message, texts: &mut Vec<&'a mut String>) {
+    match message {
+        Message::User { content } => {
+            for content in content.iter_mut() {
+                match content {
+                    UserContent::Text(text) => texts.push(&mut text.text),
+                    UserContent::ToolResult(result) => {
+                        for content in result.content.iter_mut() {
+                            if let ToolResultContent::Text(text) = content {
+                                texts.push(&mut text.text);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Message::Assistant { content } => {
+            for content in content.iter_mut() {
+                match content {
+                    AssistantContent::Text(text) => texts.push(&mut text.text),
+                    AssistantContent::ToolCall(call) => {
+                        json_strings(&mut call.function.arguments, texts)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Collect the string values of `value`, recursively
+pub(crate) fn json_strings<'a>(value: &'a mut serde_json::Value, texts: &mut Vec<&'a mut String>) {
+    match value {
+        serde_json::Value::String(text) => texts.push(text),
+        serde_json::Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| json_strings(value, texts)),
+        serde_json::Value::Object(object) => object
+            .values_mut()
+            .for_each(|value| json_strings(value, texts)),
+        _ => {}
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for AnonymizedModel<M> {
+    type Response = M::Response;
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.model_name()
+    }
+
+    async fn completion(
+        &self,
+        mut request: CompletionRequest,
+    ) -> Result<CompletionResponse<M::Response>, CompletionError> {
+        let tokens = self
+            .anonymize_request(&mut request)
+            .await
+            .map_err(|e| CompletionError::RequestError(Box::new(e)))?;
+
+        let mut response = self.model.completion(request).await?;
+        let mut texts = vec![];
+        for content in response.choice.iter_mut() {
+            match content {
+                AssistantContent::Text(text) => texts.push(&mut text.text),
+                AssistantContent::ToolCall(call) => {
+                    json_strings(&mut call.function.arguments, &mut texts)
+                }
+            }
+        }
+        for text in texts {
+            *text = tokens.deanonymize(text);
+        }
+        Ok(response)
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.model.structured_output(name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        self.model.json_mode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{Prompt, ToolDefinition},
+        providers::mock::MockCompletionModel,
+        tool::Tool,
+    };
+
+    /// Detector finding fixed entities
+    struct Fixed(Vec<Entity>);
+
+    impl Detector for Fixed {
+        async fn detect(&self, text: &str) -> Result<Vec<Entity>, AnonymizationError> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|entity| text.contains(&entity.text))
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Send error")]
+    struct SendError;
+
+    #[derive(Deserialize)]
+    struct SendArgs {
+        to: String,
+    }
+
+    /// Tool recording the recipients of the messages it sends
+    #[derive(Clone, Default)]
+    struct SendMessage(Arc<Mutex<Vec<String>>>);
+
+    impl Tool for SendMessage {
+        const NAME: &'static str = "send";
+
+        type Error = SendError;
+        type Args = SendArgs;
+        type Output = ();
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Send a message".to_string(),
+                parameters: json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            self.0.lock().unwrap().push(args.to);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_anonymizer() {
+        let anonymizer = Anonymizer::new().detector(Fixed(vec![
+            Entity::new("PERSON", "Jane"),
+            Entity::new("PERSON", "Jane Doe"),
+            Entity::new("PERSON", "John"),
+        ]));
+        let anonymized = anonymizer
+            .anonymize("Jane Doe met John. Jane Doe left.")
+            .await
+            .unwrap();
+        assert_eq!(
+            anonymized.text,
+            "<PERSON_1> met <PERSON_2>. <PERSON_1> left."
+        );
+        assert_eq!(anonymized.tokens.original("<PERSON_1>"), Some("Jane Doe"));
+        assert_eq!(
+            anonymized.tokens.deanonymize("Say hi to <PERSON_2>"),
+            "Say hi to John"
+        );
+
+        // The tokens are reused across texts
+        let mut tokens = anonymized.tokens;
+        let text = anonymizer
+            .anonymize_with("John and Jane", &mut tokens)
+            .await
+            .unwrap();
+        assert_eq!(text, "<PERSON_2> and <PERSON_3>");
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[cfg(feature = "regex")]
+    #[tokio::test]
+    async fn test_llm_fallback() {
+        // The model only sees the text anonymized by the regular expressions
+        let model = MockCompletionModel::new().tool_call(
+            "submit",
+            json!({"entities": [
+                {"category": "PERSON", "text": "Jane Doe"},
+                {"category": "PERSON", "text": "Nobody"}
+            ]}),
+        );
+        let anonymizer = Anonymizer::pii().detector(LlmDetector::new(model.clone()));
+        let anonymized = anonymizer
+            .anonymize("Jane Doe (jane@example.com, 555-123-4567)")
+            .await
+            .unwrap();
+        assert_eq!(anonymized.text, "<PERSON_1> (<EMAIL_1>, <PHONE_1>)");
+        assert_eq!(
+            model.last_request().unwrap().prompt,
+            Message::user("Jane Doe (<EMAIL_1>, <PHONE_1>)")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_anonymized_model() {
+        let model = MockCompletionModel::new()
+            .tool_call("send", json!({"to": "<PERSON_1>", "body": "Hi"}))
+            .text("Sent to <PERSON_1>.");
+        let anonymizer = Anonymizer::new().detector(Fixed(vec![Entity::new("PERSON", "Jane Doe")]));
+        let tool = SendMessage::default();
+        let agent = AgentBuilder::new(AnonymizedModel::new(model.clone(), anonymizer))
+            .tool(tool.clone())
+            .max_turns(1)
+            .build();
+
+        assert_eq!(
+            agent.prompt("Say hi to Jane Doe").await.unwrap(),
+            "Sent to Jane Doe."
+        );
+        let requests = model.requests();
+        assert_eq!(requests[0].prompt, Message::user("Say hi to <PERSON_1>"));
+        // The tool was called with the original text, which is anonymized again in the history
+        assert_eq!(*tool.0.lock().unwrap(), vec!["Jane Doe"]);
+        let Message::Assistant { content } = &requests[1].chat_history[1] else {
+            panic!("Unexpected message {:?}", requests[1].chat_history[1]);
+        };
+        assert!(matches!(
+            content.first(),
+            AssistantContent::ToolCall(call) if call.function.arguments["to"] == "<PERSON_1>"
+        ));
+    }
+}
@@ -0,0 +1,313 @@
+//! Helpers to benchmark vector stores and embedding pipelines with [criterion], also used by the
+//! benchmarks of this crate (`cargo bench -p rig-core --features bench`).
+//!
+//! [Dataset]s are generated from a seed, so that the results of different runs (and of
+//! different vector store implementations) are comparable, and [HashEmbeddingModel] embeds texts
+//! locally, so that no provider request is measured.
+//!
+//! # Example
+//! Benchmark of a custom [VectorStoreIndex] against the in-memory store:
+//! ```rust
+//! use rig::{
+//!     bench::{self, criterion::Criterion, Dataset, HashEmbeddingModel},
+//!     vector_store::in_memory_store::InMemoryVectorStore,
+//! };
+//!
+//! fn top_n(c: &mut Criterion) {
+//!     let runtime = tokio::runtime::Runtime::new().unwrap();
+//!     let model = HashEmbeddingModel::new(256);
+//!     let dataset = Dataset::generate(42, 10_000, 50);
+//!     let queries = dataset.queries(100);
+//!
+//!     let in_memory = InMemoryVectorStore::from_documents_with_ids(dataset.embeddings(&model))
+//!         .index(model.clone());
+//!     bench::bench_top_n(c, "in_memory", &runtime, &in_memory, &queries, 10);
+//!
+//!     let index = runtime.block_on(MyStore::index(dataset.embeddings(&model), model));
+//!     bench::bench_top_n(c, "my_store", &runtime, &index, &queries, 10);
+//! }
+//! ```
+use criterion::{async_executor::AsyncExecutor, Criterion, Throughput};
+
+use crate::{
+    embeddings::{Embedding, EmbeddingError, EmbeddingModel},
+    vector_store::VectorStoreIndex,
+    OneOrMany,
+};
+
+pub use criterion;
+
+const SYLLABLES: [&str; 16] = [
+    "ka", "lo", "mi", "ra", "te", "su", "no", "vi", "da", "pe", "zo", "fu", "gri", "bel", "tor",
+    "quen",
+];
+
+/// Number of distinct words of the generated datasets
+const VOCABULARY_SIZE: u64 = 2048;
+
+/// Deterministic pseudo-random generator (SplitMix64)
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random word of the vocabulary, the first words being more frequent than the last ones
+    fn word(&mut self) -> String {
+        let rank =
+            (self.next() % VOCABULARY_SIZE) * (self.next() % VOCABULARY_SIZE) / VOCABULARY_SIZE;
+        let mut word = String::new();
+        let mut rank = rank as usize + 1;
+        while rank > 0 {
+            word.push_str(SYLLABLES[rank % SYLLABLES.len()]);
+            rank /= SYLLABLES.len();
+        }
+        word
+    }
+
+    fn sentence(&mut self, words: usize) -> String {
+        let mut sentence = (0..words.max(1))
+            .map(|_| self.word())
+            .collect::<Vec<_>>()
+            .join(" ");
+        sentence.push('.');
+        sentence
+    }
+}
+
+/// Documents generated from a seed, the same seed always generating the same documents.
+#[derive(Clone, Debug)]
+pub struct Dataset {
+    seed: u64,
+    /// Ids (`doc{n}`) and texts of the documents
+    pub documents: Vec<(String, String)>,
+}
+
+impl Dataset {
+    /// Generate `count` documents of about `words` words each, split in sentences (of 4 to 16
+    /// words) and paragraphs.
+    pub fn generate(seed: u64, count: usize, words: usize) -> Self {
+        let mut rng = Rng(seed);
+        let documents = (0..count)
+            .map(|i| {
+                let mut text = String::new();
+                let mut remaining = words.max(1);
+                while remaining > 0 {
+                    let length = (4 + rng.next() % 13).min(remaining as u64) as usize;
+                    if !text.is_empty() {
+                        text.push_str(if rng.next().is_multiple_of(5) {
+                            "\n\n"
+                        } else {
+                            " "
+                        });
+                    }
+                    text.push_str(&rng.sentence(length));
+                    remaining -= length;
+                }
+                (format!("doc{i}"), text)
+            })
+            .collect();
+        Self { seed, documents }
+    }
+
+    /// Search queries of 2 to 6 words, generated from the seed of the dataset.
+    pub fn queries(&self, count: usize) -> Vec<String> {
+        let mut rng = Rng(self.seed ^ 0x5175_6572_7921);
+        (0..count)
+            .map(|_| {
+                let words = 2 + rng.next() % 5;
+                (0..words).map(|_| rng.word()).collect::<Vec<_>>().join(" ")
+            })
+            .collect()
+    }
+
+    /// Texts of the documents
+    pub fn texts(&self) -> Vec<String> {
+        self.documents
+            .iter()
+            .map(|(_, text)| text.clone())
+            .collect()
+    }
+
+    /// Ids, texts and embeddings of the documents, e.g.: to build an
+    /// [InMemoryVectorStore](crate::vector_store::in_memory_store::InMemoryVectorStore) with
+    /// `from_documents_with_ids`.
+    pub fn embeddings(
+        &self,
+        model: &HashEmbeddingModel,
+    ) -> Vec<(String, String, OneOrMany<Embedding>)> {
+        self.documents
+            .iter()
+            .map(|(id, text)| (id.clone(), text.clone(), OneOrMany::one(model.embed(text))))
+            .collect()
+    }
+}
+
+/// Local embedding model hashing the words of the texts into the dimensions of their embeddings
+/// (normalized bags of words), so that texts sharing words are close to each other.
+#[derive(Clone, Debug)]
+pub struct HashEmbeddingModel {
+    ndims: usize,
+}
+
+impl HashEmbeddingModel {
+    pub fn new(ndims: usize) -> Self {
+        Self {
+            ndims: ndims.max(1),
+        }
+    }
+
+    /// Embed `text` synchronously
+    pub fn embed(&self, text: &str) -> Embedding {
+        let mut vec = vec![0.0; self.ndims];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            // FNV-1a, stable across runs and platforms
+            let hash = word
+                .to_lowercase()
+                .bytes()
+                .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                    (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+                });
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vec[(hash % self.ndims as u64) as usize] += sign;
+        }
+        let norm = vec.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            vec.iter_mut().for_each(|x| *x /= norm);
+        }
+        Embedding {
+            document: text.to_string(),
+            vec,
+        }
+    }
+}
+
+impl EmbeddingModel for HashEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 256;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some("hash")
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        Ok(texts.into_iter().map(|text| self.embed(&text)).collect())
+    }
+}
+
+/// Benchmark the [top_n](VectorStoreIndex::top_n) (with JSON documents) and
+/// [top_n_ids](VectorStoreIndex::top_n_ids) searches of `index` in the `name` group, cycling
+/// through `queries`. The searches run on `executor`, e.g.: a tokio runtime (with the
+/// `async_tokio` feature of criterion) or
+/// [FuturesExecutor](criterion::async_executor::FuturesExecutor).
+///
+/// Panics if `queries` is empty or if a search fails.
+pub fn bench_top_n<I: VectorStoreIndex, A: AsyncExecutor>(
+    c: &mut Criterion,
+    name: &str,
+    executor: &A,
+    index: &I,
+    queries: &[String],
+    n: usize,
+) {
+    assert!(!queries.is_empty(), "No queries to benchmark");
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(1));
+
+    let mut i = 0;
+    group.bench_function(format!("top_n/{n}"), |b| {
+        b.iter(|| {
+            i = (i + 1) % queries.len();
+            executor
+                .block_on(index.top_n::<serde_json::Value>(&queries[i], n))
+                .expect("The search should succeed")
+        })
+    });
+    group.bench_function(format!("top_n_ids/{n}"), |b| {
+        b.iter(|| {
+            i = (i + 1) % queries.len();
+            executor
+                .block_on(index.top_n_ids(&queries[i], n))
+                .expect("The search should succeed")
+        })
+    });
+    group.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        embeddings::distance::VectorDistance, vector_store::in_memory_store::InMemoryVectorStore,
+    };
+
+    #[tokio::test]
+    async fn test_dataset() {
+        let dataset = Dataset::generate(42, 50, 30);
+        assert_eq!(dataset.documents.len(), 50);
+        assert_eq!(dataset.documents[3].0, "doc3");
+        assert_eq!(
+            dataset.documents[3].1.split_whitespace().count(),
+            30,
+            "{}",
+            dataset.documents[3].1
+        );
+        // Same seed, same documents and queries
+        assert_eq!(dataset.documents, Dataset::generate(42, 50, 30).documents);
+        assert_eq!(dataset.queries(5), Dataset::generate(42, 1, 1).queries(5));
+        assert_ne!(dataset.documents, Dataset::generate(7, 50, 30).documents);
+
+        let model = HashEmbeddingModel::new(64);
+        let embedding = model.embed(&dataset.documents[0].1);
+        assert_eq!(embedding.vec.len(), 64);
+        assert!((embedding.cosine_similarity(&embedding, false) - 1.0).abs() < 1e-9);
+
+        // A document is the closest one to itself
+        let index =
+            InMemoryVectorStore::from_documents_with_ids(dataset.embeddings(&model)).index(model);
+        let results = index.top_n_ids(&dataset.documents[7].1, 1).await.unwrap();
+        assert_eq!(results[0].1, "doc7");
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        // Texts without words have a null embedding, instead of a NaN one
+        let model = HashEmbeddingModel::new(0);
+        assert_eq!(model.ndims(), 1);
+        assert_eq!(model.embed(" ,. ").vec, vec![0.0]);
+        assert_eq!(
+            Dataset::generate(1, 1, 0).documents[0].1.split(' ').count(),
+            1
+        );
+        assert!(Dataset::generate(1, 0, 10).documents.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "No queries to benchmark")]
+    fn test_bench_without_queries() {
+        let model = HashEmbeddingModel::new(8);
+        let index = InMemoryVectorStore::<String>::from_documents(vec![]).index(model);
+        bench_top_n(
+            &mut Criterion::default(),
+            "empty",
+            &criterion::async_executor::FuturesExecutor,
+            &index,
+            &[],
+            1,
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Synchronous facade of the agents, embeddings and vector store searches, for CLI tools and
+//! scripts which do not want to adopt async.
+//!
+//! The [Agent], [EmbeddingsBuilder] and [VectorStoreIndex] of this module wrap their async
+//! counterparts, and block the current thread until their futures complete. The futures are run
+//! on a tokio runtime started on the first call and shared by the whole process, since the HTTP
+//! clients of the providers require tokio. Other futures (e.g.: of extractors or pipelines) can
+//! be run the same way with [block_on].
+//!
+//! As the blocking clients of `reqwest`, the methods of this module must not be called from an
+//! async context: they panic when called from within a tokio runtime.
+//!
+//! Note: this module requires the `blocking` feature.
+//!
+//! # Example
+//! ```rust
+//! use rig::{blocking, embeddings::EmbeddingsBuilder, providers::openai, vector_store::in_memory_store::InMemoryVectorStore};
+//!
+//! fn main() -> Result<(), anyhow::Error> {
+//!     let openai = openai::Client::from_env();
+//!     let model = openai.embedding_model(openai::TEXT_EMBEDDING_ADA_002);
+//!
+//!     let documents = blocking::EmbeddingsBuilder::new(model.clone())
+//!         .documents(["Flurbos are green", "Glarbs are blue"])?
+//!         .build()?;
+//!     let index = blocking::VectorStoreIndex::new(InMemoryVectorStore::from_documents(documents).index(model));
+//!
+//!     let agent: blocking::Agent<_> = openai.agent("gpt-4o").dynamic_context(1, index.into_inner()).build().into();
+//!     println!("{}", agent.prompt("What color are flurbos?")?);
+//!     Ok(())
+//! }
+//! ```
+use std::{future::Future, sync::OnceLock};
+
+use serde::Deserialize;
+
+use crate::{
+    agent,
+    completion::{Chat, CompletionModel, Message, Prompt, PromptError, Usage},
+    embeddings::{
+        self,
+        builder::{EmbeddingsReport, InsertReport},
+        Embed, EmbedError, Embedding, EmbeddingError, EmbeddingModel,
+    },
+    vector_store::{self, Filter, InsertDocuments, SearchOptions, VectorStoreError},
+    OneOrMany,
+};
+
+/// Run `future` to completion on the runtime of the blocking facade, blocking the current thread.
+///
+/// # Panics
+/// When called from within a tokio runtime.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to start the runtime of the blocking facade")
+        })
+        .block_on(future)
+}
+
+/// Synchronous [Agent](agent::Agent).
+pub struct Agent<M: CompletionModel> {
+    inner: agent::Agent<M>,
+}
+
+impl<M: CompletionModel> Agent<M> {
+    pub fn new(agent: agent::Agent<M>) -> Self {
+        Self { inner: agent }
+    }
+
+    /// Send a prompt to the agent, see [Prompt::prompt].
+    pub fn prompt(&self, prompt: impl Into<Message> + Send) -> Result<String, PromptError> {
+        block_on(self.inner.prompt(prompt))
+    }
+
+    /// Send a prompt with the chat history to the agent, see [Chat::chat].
+    pub fn chat(
+        &self,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<String, PromptError> {
+        block_on(self.inner.chat(prompt, chat_history))
+    }
+
+    /// Async agent wrapped by this agent
+    pub fn inner(&self) -> &agent::Agent<M> {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> agent::Agent<M> {
+        self.inner
+    }
+}
+
+impl<M: CompletionModel> From<agent::Agent<M>> for Agent<M> {
+    fn from(agent: agent::Agent<M>) -> Self {
+        Self::new(agent)
+    }
+}
+
+/// Synchronous [EmbeddingsBuilder](embeddings::EmbeddingsBuilder). Builders configured with
+/// other options (e.g.: their batch size or failure policy) can be converted with [From].
+pub struct EmbeddingsBuilder<M: EmbeddingModel, T: Embed> {
+    inner: embeddings::EmbeddingsBuilder<M, T>,
+}
+
+impl<M: EmbeddingModel, T: Embed> EmbeddingsBuilder<M, T> {
+    pub fn new(model: M) -> Self {
+        Self {
+            inner: embeddings::EmbeddingsBuilder::new(model),
+        }
+    }
+
+    /// Add a document to be embedded to the builder.
+    pub fn document(mut self, document: T) -> Result<Self, EmbedError> {
+        self.inner = self.inner.document(document)?;
+        Ok(self)
+    }
+
+    /// Add multiple documents to be embedded to the builder.
+    pub fn documents(mut self, documents: impl IntoIterator<Item = T>) -> Result<Self, EmbedError> {
+        self.inner = self.inner.documents(documents)?;
+        Ok(self)
+    }
+}
+
+impl<M: EmbeddingModel, T: Embed + Send> EmbeddingsBuilder<M, T> {
+    /// See [EmbeddingsBuilder::build](embeddings::EmbeddingsBuilder::build).
+    pub fn build(self) -> Result<Vec<(T, OneOrMany<Embedding>)>, EmbeddingError> {
+        block_on(self.inner.build())
+    }
+
+    /// See [EmbeddingsBuilder::build_with_usage](embeddings::EmbeddingsBuilder::build_with_usage).
+    #[allow(clippy::type_complexity)]
+    pub fn build_with_usage(
+        self,
+    ) -> Result<(Vec<(T, OneOrMany<Embedding>)>, Usage), EmbeddingError> {
+        block_on(self.inner.build_with_usage())
+    }
+
+    /// See [EmbeddingsBuilder::build_report](embeddings::EmbeddingsBuilder::build_report).
+    pub fn build_report(self) -> Result<EmbeddingsReport<T>, EmbeddingError> {
+        block_on(self.inner.build_report())
+    }
+
+    /// See [EmbeddingsBuilder::build_into](embeddings::EmbeddingsBuilder::build_into).
+    pub fn build_into<S: InsertDocuments<T>>(
+        self,
+        store: &mut S,
+    ) -> Result<InsertReport<T>, VectorStoreError> {
+        block_on(self.inner.build_into(store))
+    }
+}
+
+impl<M: EmbeddingModel, T: Embed> From<embeddings::EmbeddingsBuilder<M, T>>
+    for EmbeddingsBuilder<M, T>
+{
+    fn from(builder: embeddings::EmbeddingsBuilder<M, T>) -> Self {
+        Self { inner: builder }
+    }
+}
+
+/// Synchronous searches of a [VectorStoreIndex](vector_store::VectorStoreIndex).
+pub struct VectorStoreIndex<I: vector_store::VectorStoreIndex> {
+    inner: I,
+}
+
+impl<I: vector_store::VectorStoreIndex> VectorStoreIndex<I> {
+    pub fn new(index: I) -> Self {
+        Self { inner: index }
+    }
+
+    /// See [VectorStoreIndex::top_n](vector_store::VectorStoreIndex::top_n).
+    pub fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        block_on(self.inner.top_n(query, n))
+    }
+
+    /// See [VectorStoreIndex::top_n_ids](vector_store::VectorStoreIndex::top_n_ids).
+    pub fn top_n_ids(&self, query: &str, n: usize) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        block_on(self.inner.top_n_ids(query, n))
+    }
+
+    /// See [VectorStoreIndex::top_n_with_filter](vector_store::VectorStoreIndex::top_n_with_filter).
+    pub fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        block_on(self.inner.top_n_with_filter(query, n, filter))
+    }
+
+    /// See [VectorStoreIndex::top_n_ids_with_filter](vector_store::VectorStoreIndex::top_n_ids_with_filter).
+    pub fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        block_on(self.inner.top_n_ids_with_filter(query, n, filter))
+    }
+
+    /// See [VectorStoreIndex::top_n_with_options](vector_store::VectorStoreIndex::top_n_with_options).
+    pub fn top_n_with_options<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        block_on(self.inner.top_n_with_options(query, n, options))
+    }
+
+    /// Async index wrapped by this index (e.g.: to add it as the dynamic context of an agent)
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: vector_store::VectorStoreIndex> From<I> for VectorStoreIndex<I> {
+    fn from(index: I) -> Self {
+        Self::new(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        providers::mock::{MockCompletionModel, MockEmbeddingModel},
+        vector_store::in_memory_store::InMemoryVectorStore,
+    };
+
+    #[test]
+    fn test_blocking() {
+        let model = MockEmbeddingModel::new(16);
+        let documents = EmbeddingsBuilder::new(model.clone())
+            .documents([
+                "Flurbos are green".to_string(),
+                "Glarbs are blue".to_string(),
+            ])
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(documents.len(), 2);
+
+        let index =
+            VectorStoreIndex::new(InMemoryVectorStore::from_documents(documents).index(model));
+        let results = index.top_n::<String>("Flurbos green", 1).unwrap();
+        assert_eq!(results[0].2, "Flurbos are green");
+
+        let completion_model = MockCompletionModel::new().text("Green");
+        let agent: Agent<_> = AgentBuilder::new(completion_model.clone())
+            .dynamic_context(1, index.into_inner())
+            .build()
+            .into();
+        assert_eq!(agent.prompt("What color are flurbos?").unwrap(), "Green");
+        let request = completion_model.last_request().unwrap();
+        assert_eq!(request.documents[0].text, "\"Flurbos are green\"");
+    }
+
+    /// Embedding model failing every request
+    #[derive(Clone)]
+    struct FailingModel;
+
+    impl EmbeddingModel for FailingModel {
+        const MAX_DOCUMENTS: usize = 1;
+
+        fn ndims(&self) -> usize {
+            16
+        }
+
+        async fn embed_texts(
+            &self,
+            _texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<crate::embeddings::Embedding>, EmbeddingError> {
+            Err(EmbeddingError::ProviderError("Overloaded".into()))
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let result = EmbeddingsBuilder::new(FailingModel)
+            .document("Hello".to_string())
+            .unwrap()
+            .build();
+        assert!(matches!(result, Err(EmbeddingError::ProviderError(_))));
+
+        let index = VectorStoreIndex::new(
+            InMemoryVectorStore::<String>::from_documents(vec![]).index(FailingModel),
+        );
+        assert!(matches!(
+            index.top_n_ids("Hello", 1),
+            Err(VectorStoreError::EmbeddingError(_))
+        ));
+
+        let agent =
+            Agent::new(AgentBuilder::new(MockCompletionModel::new().error("Overloaded")).build());
+        assert!(matches!(
+            agent.prompt("Hello"),
+            Err(PromptError::CompletionError(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "Cannot start a runtime from within a runtime")]
+    async fn test_block_on_within_runtime() {
+        block_on(async {});
+    }
+}
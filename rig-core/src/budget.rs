@@ -0,0 +1,298 @@
+//! Cost estimation and budget limits of model usage.
+//!
+//! The cost of a completion is estimated from its token [Usage] and from the price of the model,
+//! in US dollars per million tokens ([ModelPrice]). The prices of the known models are listed in
+//! [PRICES]: they are the public list prices at the time of writing and may be outdated, so
+//! custom prices (e.g.: negotiated ones, or of fine-tuned models) can be given to a [Budget].
+//!
+//! A [Budget] accumulates the cost of the completions of the agents it is given to
+//! ([AgentBuilder::budget](crate::agent::AgentBuilder::budget)). Clones of a budget share the
+//! spent amount, so that a budget given to several agents (e.g.: a supervisor and its sub-agents,
+//! or the agents of a pipeline) limits their total cost. Once the limit is exceeded, the next
+//! completions are aborted with [PromptError::BudgetExceeded](crate::completion::PromptError::BudgetExceeded),
+//! or only logged with [BudgetPolicy::Warn]. The completion exceeding the limit is not aborted,
+//! since its cost is only known once the provider answered.
+//!
+//! # Example
+//! ```rust
+//! use rig::budget::{Budget, ModelPrice};
+//!
+//! let budget = Budget::new(0.50).price("my-fine-tuned-model", ModelPrice::new(3.0, 12.0));
+//!
+//! let researcher = openai.agent("gpt-4o").budget(budget.clone()).build();
+//! let writer = openai.agent("gpt-4o-mini").budget(budget.clone()).build();
+//!
+//! match writer.prompt(researcher.prompt("Research solid-state batteries").await?).await {
+//!     Err(PromptError::BudgetExceeded(e)) => println!("Over budget: {e}"),
+//!     response => println!("{}", response?),
+//! }
+//! println!("Spent ${:.4}", budget.spent());
+//! ```
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use crate::completion::Usage;
+
+/// Price of a model, in US dollars per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    /// Price of the input tokens
+    pub input: f64,
+    /// Price of the output tokens
+    pub output: f64,
+}
+
+impl ModelPrice {
+    pub const fn new(input: f64, output: f64) -> Self {
+        Self { input, output }
+    }
+
+    /// Cost of `usage`, in US dollars.
+    pub fn cost(&self, usage: Usage) -> f64 {
+        (usage.input_tokens as f64 * self.input + usage.output_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// List prices of the known models, by model name prefix (e.g.: `gpt-4o` also prices the
+/// `gpt-4o-2024-08-06` snapshot). See [model_price].
+pub const PRICES: &[(&str, ModelPrice)] = &[
+    // OpenAI
+    ("gpt-4o", ModelPrice::new(2.5, 10.0)),
+    ("gpt-4o-mini", ModelPrice::new(0.15, 0.6)),
+    ("gpt-4-turbo", ModelPrice::new(10.0, 30.0)),
+    ("gpt-4", ModelPrice::new(30.0, 60.0)),
+    ("gpt-3.5-turbo", ModelPrice::new(0.5, 1.5)),
+    ("o1", ModelPrice::new(15.0, 60.0)),
+    ("o1-mini", ModelPrice::new(1.1, 4.4)),
+    ("o3-mini", ModelPrice::new(1.1, 4.4)),
+    // Anthropic
+    ("claude-3-5-sonnet", ModelPrice::new(3.0, 15.0)),
+    ("claude-3-5-haiku", ModelPrice::new(0.8, 4.0)),
+    ("claude-3-opus", ModelPrice::new(15.0, 75.0)),
+    ("claude-3-sonnet", ModelPrice::new(3.0, 15.0)),
+    ("claude-3-haiku", ModelPrice::new(0.25, 1.25)),
+    // Gemini
+    ("gemini-1.5-pro", ModelPrice::new(1.25, 5.0)),
+    ("gemini-1.5-flash", ModelPrice::new(0.075, 0.3)),
+    ("gemini-2.0-flash", ModelPrice::new(0.1, 0.4)),
+    // DeepSeek
+    ("deepseek-chat", ModelPrice::new(0.27, 1.1)),
+    ("deepseek-reasoner", ModelPrice::new(0.55, 2.19)),
+    // Cohere
+    ("command-r-plus", ModelPrice::new(2.5, 10.0)),
+    ("command-r", ModelPrice::new(0.15, 0.6)),
+    // Mistral
+    ("mistral-large", ModelPrice::new(2.0, 6.0)),
+];
+
+/// Price of `model` in [PRICES]: the price of the longest prefix of the model name, if any.
+pub fn model_price(model: &str) -> Option<ModelPrice> {
+    longest_prefix(PRICES.iter().map(|(name, price)| (*name, *price)), model)
+}
+
+fn longest_prefix<'a>(
+    prices: impl Iterator<Item = (&'a str, ModelPrice)>,
+    model: &str,
+) -> Option<ModelPrice> {
+    prices
+        .filter(|(name, _)| model.starts_with(name))
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, price)| price)
+}
+
+/// What to do once a [Budget] is exceeded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BudgetPolicy {
+    /// Abort the next completions
+    #[default]
+    Abort,
+    /// Log a warning, and keep going
+    Warn,
+}
+
+/// Error of the completions aborted because their budget was exceeded
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("Budget of ${limit:.2} exceeded: ${spent:.4} spent")]
+pub struct BudgetExceeded {
+    /// Limit of the budget, in US dollars
+    pub limit: f64,
+    /// Amount spent, in US dollars
+    pub spent: f64,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    spent: f64,
+    usage: Usage,
+    /// Whether the warning of the exceeded budget was logged
+    warned: bool,
+    /// Models without price, already warned about
+    unpriced: HashSet<String>,
+}
+
+/// Limit of the cost of completions, in US dollars (see the [module](self) documentation).
+#[derive(Debug, Clone)]
+pub struct Budget {
+    limit: f64,
+    policy: BudgetPolicy,
+    prices: HashMap<String, ModelPrice>,
+    state: Arc<Mutex<BudgetState>>,
+}
+
+impl Budget {
+    /// Budget of `limit` US dollars, aborting the completions once exceeded.
+    pub fn new(limit: f64) -> Self {
+        Self {
+            limit,
+            policy: BudgetPolicy::default(),
+            prices: HashMap::new(),
+            state: Arc::default(),
+        }
+    }
+
+    /// Set what to do once the budget is exceeded (aborting the completions by default).
+    pub fn policy(mut self, policy: BudgetPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Price the models whose name starts with `model` at `price`, instead of their price in
+    /// [PRICES].
+    pub fn price(mut self, model: &str, price: ModelPrice) -> Self {
+        self.prices.insert(model.to_string(), price);
+        self
+    }
+
+    /// Limit of the budget, in US dollars.
+    pub fn limit(&self) -> f64 {
+        self.limit
+    }
+
+    /// Amount spent, in US dollars.
+    pub fn spent(&self) -> f64 {
+        self.state().spent
+    }
+
+    /// Amount left before the limit, in US dollars (negative once exceeded).
+    pub fn remaining(&self) -> f64 {
+        self.limit - self.spent()
+    }
+
+    /// Token usage of the completions recorded by the budget.
+    pub fn usage(&self) -> Usage {
+        self.state().usage
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.spent() > self.limit
+    }
+
+    /// Price of `model`: its custom price, or its price in [PRICES].
+    pub fn model_price(&self, model: &str) -> Option<ModelPrice> {
+        longest_prefix(
+            self.prices
+                .iter()
+                .map(|(name, price)| (name.as_str(), *price)),
+            model,
+        )
+        .or_else(|| model_price(model))
+    }
+
+    /// Record the usage of a completion of `model`, returning its cost. The completions of
+    /// models without price cost nothing, with a warning.
+    pub fn record(&self, model: Option<&str>, usage: Usage) -> f64 {
+        let model = model.unwrap_or("unknown");
+        let price = self.model_price(model);
+        let mut state = self.state();
+        state.usage += usage;
+        let Some(price) = price else {
+            if state.unpriced.insert(model.to_string()) {
+                tracing::warn!(target: "rig",
+                    "No price for model {model}, its completions are not counted in the budget"
+                );
+            }
+            return 0.0;
+        };
+
+        let cost = price.cost(usage);
+        state.spent += cost;
+        if state.spent > self.limit && !state.warned {
+            state.warned = true;
+            tracing::warn!(target: "rig",
+                "Budget of ${:.2} exceeded: ${:.4} spent",
+                self.limit,
+                state.spent
+            );
+        }
+        cost
+    }
+
+    /// Check that the budget allows more completions: fails once the budget is exceeded, unless
+    /// its policy is [BudgetPolicy::Warn].
+    pub fn check(&self) -> Result<(), BudgetExceeded> {
+        let spent = self.spent();
+        match self.policy {
+            BudgetPolicy::Abort if spent > self.limit => Err(BudgetExceeded {
+                limit: self.limit,
+                spent,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reset the amount spent, e.g.: at the start of a new billing period.
+    pub fn reset(&self) {
+        *self.state() = BudgetState::default();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BudgetState> {
+        self.state.lock().expect("budget lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_price() {
+        assert_eq!(model_price("gpt-4o"), Some(ModelPrice::new(2.5, 10.0)));
+        assert_eq!(
+            model_price("gpt-4o-mini-2024-07-18"),
+            Some(ModelPrice::new(0.15, 0.6))
+        );
+        assert_eq!(model_price("llama3.2"), None);
+        assert_eq!(
+            ModelPrice::new(2.5, 10.0).cost(Usage::new(1_000_000, 100_000)),
+            3.5
+        );
+    }
+
+    #[test]
+    fn test_budget() {
+        let budget = Budget::new(1.0).price("gpt-4o", ModelPrice::new(1.0, 2.0));
+        let shared = budget.clone();
+        assert_eq!(budget.record(Some("gpt-4o"), Usage::new(500_000, 0)), 0.5);
+        assert_eq!(shared.record(Some("llama3.2"), Usage::new(500_000, 0)), 0.0);
+        assert!(budget.check().is_ok());
+
+        shared.record(Some("gpt-4o-2024-08-06"), Usage::new(0, 500_000));
+        assert_eq!(budget.spent(), 1.5);
+        assert_eq!(budget.remaining(), -0.5);
+        assert_eq!(budget.usage(), Usage::new(1_000_000, 500_000));
+        assert_eq!(
+            budget.check(),
+            Err(BudgetExceeded {
+                limit: 1.0,
+                spent: 1.5
+            })
+        );
+        assert!(budget.clone().policy(BudgetPolicy::Warn).check().is_ok());
+
+        budget.reset();
+        assert_eq!(shared.spent(), 0.0);
+    }
+}
@@ -0,0 +1,617 @@
+//! Failure injection ("chaos mode"), to test the resilience of an application to provider
+//! outages before one happens.
+//!
+//! A [ChaosModel] wraps a completion (or embedding) model and a [ChaosTool] wraps a tool. Each of
+//! their requests fails, with the probabilities of their [ChaosConfig], with one of the
+//! [Fault]s that providers return during outages:
+//! - [Fault::Timeout]: the request fails after a delay, as if the provider did not answer,
+//! - [Fault::RateLimit]: the request is rejected with a `429 Too Many Requests` error (see
+//!   [CompletionError::is_rate_limited]),
+//! - [Fault::MalformedJson]: the response cannot be parsed,
+//! - [Fault::TruncatedStream]: the stream of the response is interrupted midway (only for the
+//!   streamed completions).
+//!
+//! The other requests reach the wrapped model or tool. The faults are drawn from a seeded
+//! generator, so that a failing test can be reproduced with the same [ChaosConfig::seed], and the
+//! injected faults are counted by [ChaosModel::stats] (and [ChaosTool::stats]).
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     chaos::{ChaosConfig, ChaosModel},
+//!     completion::{Prompt, ResumableModel},
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! // 20% of the requests fail, rate limited or with an interrupted stream
+//! let chaos = ChaosConfig::new().rate_limit(0.1).truncated_stream(0.1).seed(42);
+//! let model = ChaosModel::new(openai.completion_model(openai::GPT_4O), chaos);
+//!
+//! // The configuration under test must recover from the injected faults
+//! let agent = rig::agent::AgentBuilder::new(ResumableModel::new(model.clone())).build();
+//! for _ in 0..50 {
+//!     agent.prompt("Tell me a joke").await?;
+//! }
+//! println!("{:?}", model.stats());
+//! ```
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_stream::stream;
+use futures::{Future, StreamExt};
+
+use crate::{
+    completion::{
+        CompletionError, CompletionModel, CompletionRequest, CompletionResponse, ToolDefinition,
+        Usage,
+    },
+    embeddings::{Embedding, EmbeddingError, EmbeddingLimits, EmbeddingModel},
+    runtime::{self, Elapsed, SystemTime, UNIX_EPOCH},
+    streaming::{StreamingCompletionModel, StreamingResult},
+    tool::{ToolDyn, ToolError},
+};
+
+/// Fault injected by chaos mode (see the [module](self) documentation)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    /// The request fails after the [timeout delay](ChaosConfig::timeout_delay)
+    Timeout,
+    /// The request is rejected with a `429 Too Many Requests` error
+    RateLimit,
+    /// The response is not valid JSON
+    MalformedJson,
+    /// The stream of the response is interrupted after some of its chunks
+    TruncatedStream,
+}
+
+/// Errors of the tool calls failed by a [ChaosTool], and messages of the injected provider
+/// errors.
+#[derive(Debug, thiserror::Error)]
+pub enum ChaosError {
+    #[error("Request timed out after {} ms (injected fault)", .0.as_millis())]
+    Timeout(Duration),
+
+    #[error("429 Too Many Requests: rate limit exceeded (injected fault)")]
+    RateLimited,
+
+    #[error("Response stream truncated (injected fault)")]
+    Truncated,
+}
+
+/// Probabilities of the faults injected by a [ChaosModel] or a [ChaosTool]. No fault is injected
+/// by default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChaosConfig {
+    timeout: f64,
+    rate_limit: f64,
+    malformed_json: f64,
+    truncated_stream: f64,
+    timeout_delay: Duration,
+    seed: Option<u64>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            timeout: 0.0,
+            rate_limit: 0.0,
+            malformed_json: 0.0,
+            truncated_stream: 0.0,
+            timeout_delay: Duration::from_secs(1),
+            seed: None,
+        }
+    }
+}
+
+impl ChaosConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Probability (between 0 and 1) that a request times out.
+    pub fn timeout(mut self, probability: f64) -> Self {
+        self.timeout = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Probability (between 0 and 1) that a request is rate limited.
+    pub fn rate_limit(mut self, probability: f64) -> Self {
+        self.rate_limit = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Probability (between 0 and 1) that a response is malformed JSON.
+    pub fn malformed_json(mut self, probability: f64) -> Self {
+        self.malformed_json = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Probability (between 0 and 1) that a streamed response is interrupted. Ignored for the
+    /// requests which are not streamed.
+    pub fn truncated_stream(mut self, probability: f64) -> Self {
+        self.truncated_stream = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay after which the timed out requests fail (defaults to 1 second), e.g.: the timeout
+    /// of the HTTP client of the application.
+    pub fn timeout_delay(mut self, delay: Duration) -> Self {
+        self.timeout_delay = delay;
+        self
+    }
+
+    /// Seed of the generator drawing the faults, so that the same faults are injected in the
+    /// same order on each run. Defaults to a seed derived from the current time.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Number of requests made to a [ChaosModel] or a [ChaosTool], and of the faults injected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    /// Requests made, including the failed ones
+    pub requests: usize,
+    pub timeouts: usize,
+    pub rate_limits: usize,
+    pub malformed_json: usize,
+    pub truncated_streams: usize,
+}
+
+impl ChaosStats {
+    /// Total number of faults injected
+    pub fn faults(&self) -> usize {
+        self.timeouts + self.rate_limits + self.malformed_json + self.truncated_streams
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    requests: AtomicUsize,
+    timeouts: AtomicUsize,
+    rate_limits: AtomicUsize,
+    malformed_json: AtomicUsize,
+    truncated_streams: AtomicUsize,
+}
+
+/// State shared by the clones of a [ChaosModel] (or by the calls of a [ChaosTool])
+#[derive(Debug)]
+struct Chaos {
+    config: ChaosConfig,
+    enabled: AtomicBool,
+    rng: AtomicU64,
+    counters: Counters,
+}
+
+impl Chaos {
+    fn new(config: ChaosConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        Self {
+            config,
+            enabled: AtomicBool::new(true),
+            rng: AtomicU64::new(seed),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Next number of the generator (SplitMix64)
+    fn next(&self) -> u64 {
+        let mut z = self
+            .rng
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Record a request, and draw the fault to inject into it, if any. Truncations are only drawn
+    /// for the `streamed` requests.
+    fn draw(&self, streamed: bool) -> Option<Fault> {
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+        if !self.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let config = &self.config;
+        let truncated_stream = if streamed {
+            config.truncated_stream
+        } else {
+            0.0
+        };
+        let uniform = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        let fault = [
+            (Fault::Timeout, config.timeout),
+            (Fault::RateLimit, config.rate_limit),
+            (Fault::MalformedJson, config.malformed_json),
+            (Fault::TruncatedStream, truncated_stream),
+        ]
+        .into_iter()
+        .scan(0.0, |cumulative, (fault, probability)| {
+            *cumulative += probability;
+            Some((fault, *cumulative))
+        })
+        .find(|(_, cumulative)| uniform < *cumulative)
+        .map(|(fault, _)| fault)?;
+
+        let counter = match fault {
+            Fault::Timeout => &self.counters.timeouts,
+            Fault::RateLimit => &self.counters.rate_limits,
+            Fault::MalformedJson => &self.counters.malformed_json,
+            Fault::TruncatedStream => &self.counters.truncated_streams,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(target: "rig", "Injecting a {:?} fault", fault);
+        Some(fault)
+    }
+
+    fn stats(&self) -> ChaosStats {
+        let counters = &self.counters;
+        ChaosStats {
+            requests: counters.requests.load(Ordering::Relaxed),
+            timeouts: counters.timeouts.load(Ordering::Relaxed),
+            rate_limits: counters.rate_limits.load(Ordering::Relaxed),
+            malformed_json: counters.malformed_json.load(Ordering::Relaxed),
+            truncated_streams: counters.truncated_streams.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Error of a response which is not valid JSON
+fn malformed_json() -> serde_json::Error {
+    serde_json::from_str::<serde_json::Value>(r#"{"choices": [{"message": {"content": "#)
+        .expect_err("truncated JSON is invalid")
+}
+
+/// Completion or embedding model injecting faults into its requests (see the [module](self)
+/// documentation).
+///
+/// Clones of the model share their [ChaosStats] and fault generator.
+#[derive(Clone)]
+pub struct ChaosModel<M> {
+    model: M,
+    chaos: Arc<Chaos>,
+}
+
+impl<M> ChaosModel<M> {
+    /// Inject faults into the requests of `model`, with the probabilities of `config`.
+    pub fn new(model: M, config: ChaosConfig) -> Self {
+        Self {
+            model,
+            chaos: Arc::new(Chaos::new(config)),
+        }
+    }
+
+    /// Number of requests made to the model (and its clones), and of the faults injected
+    pub fn stats(&self) -> ChaosStats {
+        self.chaos.stats()
+    }
+
+    /// Stop (or resume) injecting faults into the requests of the model and its clones, e.g.: to
+    /// check that an application recovers once the outage is over.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.chaos.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Fail a non streamed request with `fault`
+    async fn fail(&self, fault: Fault) -> CompletionError {
+        match fault {
+            Fault::Timeout => {
+                let delay = self.chaos.config.timeout_delay;
+                runtime::sleep(delay).await;
+                CompletionError::DeadlineExceeded(Elapsed(delay))
+            }
+            Fault::RateLimit => CompletionError::ProviderError(ChaosError::RateLimited.to_string()),
+            Fault::MalformedJson | Fault::TruncatedStream => malformed_json().into(),
+        }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for ChaosModel<M> {
+    type Response = M::Response;
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.model_name()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<M::Response>, CompletionError> {
+        match self.chaos.draw(false) {
+            Some(fault) => Err(self.fail(fault).await),
+            None => self.model.completion(request).await,
+        }
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.model.structured_output(name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        self.model.json_mode()
+    }
+}
+
+impl<M: StreamingCompletionModel> StreamingCompletionModel for ChaosModel<M> {
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingResult, CompletionError> {
+        let fault = self.chaos.draw(true);
+        if let Some(fault @ (Fault::Timeout | Fault::RateLimit | Fault::MalformedJson)) = fault {
+            return Err(self.fail(fault).await);
+        }
+
+        let mut stream = self.model.stream(request).await?;
+        if fault.is_none() {
+            return Ok(stream);
+        }
+
+        // Interrupted after 0 to 3 chunks (or at its end, if it is shorter)
+        let chunks = self.chaos.next() % 4;
+        let stream: StreamingResult = Box::pin(stream! {
+            for _ in 0..chunks {
+                match stream.next().await {
+                    Some(chunk) => yield chunk,
+                    None => break,
+                }
+            }
+            yield Err(CompletionError::ResponseError(ChaosError::Truncated.to_string()));
+        });
+        Ok(stream)
+    }
+
+    fn supports_prefill(&self) -> bool {
+        self.model.supports_prefill()
+    }
+}
+
+impl<M: EmbeddingModel> EmbeddingModel for ChaosModel<M> {
+    const MAX_DOCUMENTS: usize = M::MAX_DOCUMENTS;
+
+    fn ndims(&self) -> usize {
+        self.model.ndims()
+    }
+
+    fn limits(&self) -> EmbeddingLimits {
+        self.model.limits()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.model_name()
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        Ok(self.embed_texts_with_usage(texts).await?.0)
+    }
+
+    async fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<(Vec<Embedding>, Option<Usage>), EmbeddingError> {
+        match self.chaos.draw(false) {
+            Some(Fault::Timeout) => {
+                let delay = self.chaos.config.timeout_delay;
+                runtime::sleep(delay).await;
+                Err(EmbeddingError::ProviderError(
+                    ChaosError::Timeout(delay).to_string(),
+                ))
+            }
+            Some(Fault::RateLimit) => Err(EmbeddingError::ProviderError(
+                ChaosError::RateLimited.to_string(),
+            )),
+            Some(Fault::MalformedJson | Fault::TruncatedStream) => Err(malformed_json().into()),
+            None => self.model.embed_texts_with_usage(texts).await,
+        }
+    }
+}
+
+/// Tool wrapper injecting faults into the calls of a tool (see the [module](self)
+/// documentation). It has the same name and definition as the wrapped tool.
+///
+/// Timeouts and rate limits fail the calls with a [ChaosError], and malformed JSON with a
+/// [ToolError::JsonError], which are reported to the model like any other tool error.
+pub struct ChaosTool {
+    tool: Box<dyn ToolDyn>,
+    chaos: Arc<Chaos>,
+}
+
+impl ChaosTool {
+    /// Inject faults into the calls of `tool`, with the probabilities of `config`.
+    pub fn new(tool: impl ToolDyn + 'static, config: ChaosConfig) -> Self {
+        Self {
+            tool: Box::new(tool),
+            chaos: Arc::new(Chaos::new(config)),
+        }
+    }
+
+    /// Number of calls of the tool, and of the faults injected
+    pub fn stats(&self) -> ChaosStats {
+        self.chaos.stats()
+    }
+
+    /// Stop (or resume) injecting faults into the calls of the tool.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.chaos.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+impl ToolDyn for ChaosTool {
+    fn name(&self) -> String {
+        self.tool.name()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        self.tool.definition(prompt)
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            match self.chaos.draw(false) {
+                Some(Fault::Timeout) => {
+                    let delay = self.chaos.config.timeout_delay;
+                    runtime::sleep(delay).await;
+                    Err(ToolError::ToolCallError(Box::new(ChaosError::Timeout(
+                        delay,
+                    ))))
+                }
+                Some(Fault::RateLimit) => {
+                    Err(ToolError::ToolCallError(Box::new(ChaosError::RateLimited)))
+                }
+                Some(Fault::MalformedJson | Fault::TruncatedStream) => {
+                    Err(ToolError::JsonError(malformed_json()))
+                }
+                None => self.tool.call(args).await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{MockCompletionModel, MockEmbeddingModel},
+        streaming::StreamingChoice,
+        tool::Tool,
+    };
+
+    #[derive(serde::Deserialize)]
+    struct EchoArgs {
+        text: String,
+    }
+
+    struct Echo;
+
+    impl Tool for Echo {
+        const NAME: &'static str = "echo";
+
+        type Error = ToolError;
+        type Args = EchoArgs;
+        type Output = String;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Echo the text".to_string(),
+                parameters: serde_json::json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(args.text)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chaos_model() {
+        let config = ChaosConfig::new()
+            .timeout(0.2)
+            .rate_limit(0.2)
+            .malformed_json(0.2)
+            .timeout_delay(Duration::from_millis(1))
+            .seed(7);
+        let model = ChaosModel::new(MockCompletionModel::new().repeat("Hi"), config);
+        let agent = crate::agent::AgentBuilder::new(model.clone()).build();
+
+        let mut errors = vec![];
+        for _ in 0..100 {
+            if let Err(e) = agent.prompt("Hello").await {
+                errors.push(e);
+            }
+        }
+        let stats = model.stats();
+        assert_eq!(stats.requests, 100);
+        assert_eq!(stats.faults(), errors.len());
+        assert_eq!(stats.truncated_streams, 0);
+        assert!(stats.timeouts > 0 && stats.rate_limits > 0 && stats.malformed_json > 0);
+        assert!((40..80).contains(&stats.faults()));
+        let rate_limited = errors
+            .iter()
+            .filter(|e| {
+                matches!(e, crate::completion::PromptError::CompletionError(e) if e.is_rate_limited())
+            })
+            .count();
+        assert_eq!(rate_limited, stats.rate_limits);
+
+        // The same seed injects the same faults
+        let replayed = ChaosModel::new(MockCompletionModel::new().repeat("Hi"), config);
+        for _ in 0..100 {
+            let _ = replayed.completion_request("Hello").send().await;
+        }
+        assert_eq!(replayed.stats(), stats);
+
+        // Once disabled, the requests reach the model
+        model.set_enabled(false);
+        assert_eq!(agent.prompt("Hello").await.unwrap(), "Hi");
+
+        // Truncated streams end with an interruption
+        let config = ChaosConfig::new().truncated_stream(1.0).seed(1);
+        let model = ChaosModel::new(MockCompletionModel::new().repeat("Hi"), config);
+        let chunks = model
+            .stream(model.completion_request("Hello").build())
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            chunks.last(),
+            Some(Err(CompletionError::ResponseError(_)))
+        ));
+        assert!(chunks[..chunks.len() - 1]
+            .iter()
+            .all(|chunk| matches!(chunk, Ok(StreamingChoice::Message(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_chaos_embeddings_and_tools() {
+        let config = ChaosConfig::new().rate_limit(1.0);
+        let model = ChaosModel::new(MockEmbeddingModel::new(8), config);
+        let error = model.embed_text("Hello").await.unwrap_err();
+        assert!(error.is_rate_limited());
+        model.set_enabled(false);
+        assert_eq!(model.embed_text("Hello").await.unwrap().vec.len(), 8);
+
+        let tool = ChaosTool::new(Echo, ChaosConfig::new().malformed_json(1.0));
+        assert_eq!(tool.name(), "echo");
+        let result = tool.call(r#"{"text": "Hi"}"#.to_string()).await;
+        assert!(matches!(result, Err(ToolError::JsonError(_))));
+        tool.set_enabled(false);
+        let result = tool.call(r#"{"text": "Hi"}"#.to_string()).await;
+        assert_eq!(result.unwrap(), r#""Hi""#);
+        assert_eq!(
+            tool.stats(),
+            ChaosStats {
+                requests: 2,
+                malformed_json: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use super::TextSplitter;
+
+/// Splits text into windows of `chunk_size` characters, each window starting `overlap`
+/// characters before the end of the previous one. Does not take the structure of the text
+/// into account, see [RecursiveSplitter](super::RecursiveSplitter) for that.
+///
+/// # Example
+/// ```rust
+/// use rig::chunking::{FixedSizeSplitter, TextSplitter};
+///
+/// let splitter = FixedSizeSplitter::new(4, 1);
+/// assert_eq!(splitter.split("abcdefghij"), vec!["abcd", "defg", "ghij"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedSizeSplitter {
+    chunk_size: usize,
+    overlap: usize,
+}
+
+impl FixedSizeSplitter {
+    /// Create a new splitter.
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` is zero or if `overlap` is not smaller than `chunk_size`.
+    pub fn new(chunk_size: usize, overlap: usize) -> Self {
+        assert!(chunk_size > 0, "chunk_size must be greater than 0");
+        assert!(
+            overlap < chunk_size,
+            "overlap must be smaller than chunk_size"
+        );
+        Self {
+            chunk_size,
+            overlap,
+        }
+    }
+}
+
+impl TextSplitter for FixedSizeSplitter {
+    fn split(&self, text: &str) -> Vec<String> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let step = self.chunk_size - self.overlap;
+
+        let mut chunks = vec![];
+        let mut start = 0;
+        while start < chars.len() {
+            let end = (start + self.chunk_size).min(chars.len());
+            let chunk = chars[start..end].iter().collect::<String>();
+            if !chunk.trim().is_empty() {
+                chunks.push(chunk);
+            }
+            if end == chars.len() {
+                break;
+            }
+            start += step;
+        }
+
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_size_splitter() {
+        assert_eq!(
+            FixedSizeSplitter::new(4, 0).split("abcdefghij"),
+            vec!["abcd", "efgh", "ij"]
+        );
+        assert_eq!(
+            FixedSizeSplitter::new(4, 2).split("abcdefgh"),
+            vec!["abcd", "cdef", "efgh"]
+        );
+        assert_eq!(
+            FixedSizeSplitter::new(4, 2).split("héllo"),
+            vec!["héll", "llo"]
+        );
+        assert!(FixedSizeSplitter::new(4, 0).split("").is_empty());
+    }
+
+    #[test]
+    fn test_blank_chunks() {
+        assert_eq!(
+            FixedSizeSplitter::new(3, 0).split("ab    cd"),
+            vec!["ab ", "cd"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk_size must be greater than 0")]
+    fn test_empty_chunk_size() {
+        FixedSizeSplitter::new(0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlap must be smaller than chunk_size")]
+    fn test_overlap_too_large() {
+        FixedSizeSplitter::new(4, 4);
+    }
+}
@@ -0,0 +1,163 @@
+use super::{char_len, RecursiveSplitter, TextSplitter};
+
+/// Splits markdown documents on their headers, so that each chunk belongs to a single
+/// section. Every chunk is prefixed with the headers of the section it belongs to (e.g.
+/// `# Guide\n## Installation\n`) so that it keeps its context once embedded.
+///
+/// Sections longer than `chunk_size` characters are further split with a
+/// [RecursiveSplitter] using the same `chunk_size` and `overlap`.
+/// Headers inside fenced code blocks are ignored.
+///
+/// # Example
+/// ```rust
+/// use rig::chunking::{MarkdownSplitter, TextSplitter};
+///
+/// let splitter = MarkdownSplitter::new(1000, 0);
+/// assert_eq!(
+///     splitter.split("# Guide\nIntro\n## Install\nRun it"),
+///     vec!["# Guide\nIntro", "# Guide\n## Install\nRun it"]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct MarkdownSplitter {
+    chunk_size: usize,
+    overlap: usize,
+    include_headers: bool,
+}
+
+impl MarkdownSplitter {
+    /// Create a new splitter.
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` is zero or if `overlap` is not smaller than `chunk_size`.
+    pub fn new(chunk_size: usize, overlap: usize) -> Self {
+        assert!(chunk_size > 0, "chunk_size must be greater than 0");
+        assert!(
+            overlap < chunk_size,
+            "overlap must be smaller than chunk_size"
+        );
+        Self {
+            chunk_size,
+            overlap,
+            include_headers: true,
+        }
+    }
+
+    /// Whether to prefix chunks with the headers of their section (default: `true`).
+    pub fn include_headers(mut self, include_headers: bool) -> Self {
+        self.include_headers = include_headers;
+        self
+    }
+}
+
+/// Return the level of a markdown header line (e.g.: 2 for `## Title`).
+fn header_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' '))).then_some(level)
+}
+
+impl TextSplitter for MarkdownSplitter {
+    fn split(&self, text: &str) -> Vec<String> {
+        // (headers of the section, body of the section)
+        let mut sections: Vec<(String, String)> = vec![];
+        let mut headers: Vec<(usize, &str)> = vec![];
+        let mut body = String::new();
+        let mut in_code_block = false;
+
+        let mut flush = |headers: &[(usize, &str)], body: &mut String| {
+            if !body.trim().is_empty() {
+                let prefix = headers
+                    .iter()
+                    .map(|(_, header)| format!("{header}\n"))
+                    .collect::<String>();
+                sections.push((prefix, std::mem::take(body)));
+            }
+            body.clear();
+        };
+
+        for line in text.lines() {
+            if line.trim_start().starts_with("```") {
+                in_code_block = !in_code_block;
+            }
+
+            match header_level(line).filter(|_| !in_code_block) {
+                Some(level) => {
+                    flush(&headers, &mut body);
+                    headers.retain(|(l, _)| *l < level);
+                    headers.push((level, line.trim_end()));
+                }
+                None => {
+                    body.push_str(line);
+                    body.push('\n');
+                }
+            }
+        }
+        flush(&headers, &mut body);
+
+        sections
+            .into_iter()
+            .flat_map(|(prefix, body)| {
+                let prefix = if self.include_headers {
+                    prefix
+                } else {
+                    String::new()
+                };
+                let body_size = self
+                    .chunk_size
+                    .saturating_sub(char_len(&prefix))
+                    .max(self.chunk_size / 2)
+                    .max(1);
+                let overlap = self.overlap.min(body_size - 1);
+
+                RecursiveSplitter::new(body_size, overlap)
+                    .split(&body)
+                    .into_iter()
+                    .map(move |chunk| format!("{prefix}{chunk}"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_splitter() {
+        let text = "\
+# Title
+Intro text.
+
+## Section A
+Content A.
+### Sub A
+Deep content.
+```
+# not a header
+```
+## Section B
+Content B.
+";
+        let splitter = MarkdownSplitter::new(1000, 0);
+        assert_eq!(
+            splitter.split(text),
+            vec![
+                "# Title\nIntro text.",
+                "# Title\n## Section A\nContent A.",
+                "# Title\n## Section A\n### Sub A\nDeep content.\n```\n# not a header\n```",
+                "# Title\n## Section B\nContent B.",
+            ]
+        );
+
+        let splitter = MarkdownSplitter::new(1000, 0).include_headers(false);
+        assert_eq!(splitter.split(text)[0], "Intro text.");
+    }
+
+    #[test]
+    fn test_header_level() {
+        assert_eq!(header_level("## Title"), Some(2));
+        assert_eq!(header_level("#hashtag"), None);
+        assert_eq!(header_level("####### too deep"), None);
+    }
+}
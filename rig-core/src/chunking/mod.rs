@@ -0,0 +1,146 @@
+//! This module provides text splitters used to break large documents into smaller chunks
+//! before embedding them. Embedding a whole document (e.g.: an entire PDF) as a single
+//! vector dilutes its meaning and hurts retrieval quality, so documents should usually be
+//! chunked first.
+//!
+//! All splitters implement the [TextSplitter] trait and measure chunk sizes in characters:
+//! - [FixedSizeSplitter]: fixed-size windows with overlap.
+//! - [RecursiveSplitter]: splits on paragraphs, then lines, then sentences, then words
+//!   until every chunk fits, and merges small pieces back together.
+//! - [SentenceSplitter]: groups whole sentences into chunks.
+//! - [MarkdownSplitter]: splits on markdown headers and prefixes every chunk with the
+//!   headers it belongs to.
+//!
+//! Splitters can be applied to the output of the [loaders](crate::loaders) with their
+//! `chunk` method, and the resulting chunks fed to the
+//! [EmbeddingsBuilder](crate::embeddings::EmbeddingsBuilder).
+//!
+//! # Example
+//! ```rust,ignore
+//! use rig::{
+//!     chunking::RecursiveSplitter,
+//!     embeddings::EmbeddingsBuilder,
+//!     loaders::PdfFileLoader,
+//! };
+//!
+//! let chunks = PdfFileLoader::with_glob("docs/*.pdf")?
+//!     .read()
+//!     .ignore_errors()
+//!     .chunk(RecursiveSplitter::new(1000, 200));
+//!
+//! let embeddings = EmbeddingsBuilder::new(model)
+//!     .documents(chunks)?
+//!     .build()
+//!     .await?;
+//! ```
+
+pub mod fixed;
+pub mod markdown;
+pub mod recursive;
+pub mod sentence;
+
+use std::collections::VecDeque;
+
+pub use fixed::FixedSizeSplitter;
+pub use markdown::MarkdownSplitter;
+pub use recursive::RecursiveSplitter;
+pub use sentence::SentenceSplitter;
+
+/// Trait for types that can split a text into chunks.
+pub trait TextSplitter {
+    /// Split `text` into chunks. Empty chunks are never returned.
+    fn split(&self, text: &str) -> Vec<String>;
+}
+
+impl<T: TextSplitter + ?Sized> TextSplitter for &T {
+    fn split(&self, text: &str) -> Vec<String> {
+        (**self).split(text)
+    }
+}
+
+impl<T: TextSplitter + ?Sized> TextSplitter for Box<T> {
+    fn split(&self, text: &str) -> Vec<String> {
+        (**self).split(text)
+    }
+}
+
+pub(crate) fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Merge consecutive `pieces` (which must each fit in `chunk_size` characters) into chunks of
+/// at most `chunk_size` characters. Each new chunk starts with the trailing pieces of the
+/// previous one, as long as they fit in `overlap` characters.
+pub(crate) fn merge_pieces(
+    pieces: impl IntoIterator<Item = String>,
+    chunk_size: usize,
+    overlap: usize,
+) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current: VecDeque<(String, usize)> = VecDeque::new();
+    let mut current_len = 0;
+
+    let emit = |current: &VecDeque<(String, usize)>, chunks: &mut Vec<String>| {
+        let chunk = current
+            .iter()
+            .map(|(piece, _)| piece.as_str())
+            .collect::<String>();
+        let chunk = chunk.trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
+        }
+    };
+
+    for piece in pieces {
+        let piece_len = char_len(&piece);
+
+        if current_len + piece_len > chunk_size && !current.is_empty() {
+            emit(&current, &mut chunks);
+
+            while current_len > overlap || (current_len > 0 && current_len + piece_len > chunk_size)
+            {
+                let (_, len) = current.pop_front().expect("current is not empty");
+                current_len -= len;
+            }
+        }
+
+        current.push_back((piece, piece_len));
+        current_len += piece_len;
+    }
+
+    emit(&current, &mut chunks);
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_pieces() {
+        let pieces = ["aaa ", "bbb ", "ccc ", "ddd"].map(String::from);
+
+        assert_eq!(
+            merge_pieces(pieces.clone(), 8, 0),
+            vec!["aaa bbb", "ccc ddd"]
+        );
+        assert_eq!(
+            merge_pieces(pieces, 8, 4),
+            vec!["aaa bbb", "bbb ccc", "ccc ddd"]
+        );
+    }
+
+    #[test]
+    fn test_merge_edge_cases() {
+        assert!(merge_pieces(Vec::<String>::new(), 8, 0).is_empty());
+        assert!(merge_pieces(["  ", "\n"].map(String::from), 8, 0).is_empty());
+
+        // The overlap is dropped when the next piece wouldn't fit with it
+        let pieces = ["aaa ", "bbbbbbb"].map(String::from);
+        assert_eq!(merge_pieces(pieces, 8, 4), vec!["aaa", "bbbbbbb"]);
+
+        let splitter: Box<dyn TextSplitter> = Box::new(FixedSizeSplitter::new(2, 0));
+        assert_eq!(splitter.split("abc"), vec!["ab", "c"]);
+    }
+}
@@ -0,0 +1,138 @@
+use super::{char_len, merge_pieces, FixedSizeSplitter, TextSplitter};
+
+/// Default separators, from the coarsest to the finest.
+pub const DEFAULT_SEPARATORS: [&str; 5] = ["\n\n", "\n", ". ", " ", ""];
+
+/// Splits text on the coarsest separator possible (paragraphs, then lines, then sentences,
+/// then words and finally characters) until every piece fits in `chunk_size` characters,
+/// then merges consecutive pieces back into chunks of up to `chunk_size` characters with
+/// up to `overlap` characters shared between consecutive chunks.
+///
+/// This is a good default for most texts as it keeps paragraphs and sentences together
+/// whenever possible.
+///
+/// # Example
+/// ```rust
+/// use rig::chunking::{RecursiveSplitter, TextSplitter};
+///
+/// let splitter = RecursiveSplitter::new(1000, 200);
+/// let chunks = splitter.split("First paragraph.\n\nSecond paragraph.");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecursiveSplitter {
+    chunk_size: usize,
+    overlap: usize,
+    separators: Vec<String>,
+}
+
+impl RecursiveSplitter {
+    /// Create a new splitter using the [DEFAULT_SEPARATORS].
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` is zero or if `overlap` is not smaller than `chunk_size`.
+    pub fn new(chunk_size: usize, overlap: usize) -> Self {
+        assert!(chunk_size > 0, "chunk_size must be greater than 0");
+        assert!(
+            overlap < chunk_size,
+            "overlap must be smaller than chunk_size"
+        );
+        Self {
+            chunk_size,
+            overlap,
+            separators: DEFAULT_SEPARATORS.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Replace the separators, ordered from the coarsest to the finest. Text that cannot be
+    /// split further with the given separators is split on character boundaries.
+    pub fn with_separators(
+        mut self,
+        separators: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.separators = separators.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Split `text` into pieces that each fit in `chunk_size`, keeping separators attached
+    /// to the end of the pieces so that concatenating them gives back the original text.
+    fn pieces(&self, text: &str, separators: &[String]) -> Vec<String> {
+        if char_len(text) <= self.chunk_size {
+            return vec![text.to_string()];
+        }
+
+        let position = separators
+            .iter()
+            .position(|sep| sep.is_empty() || text.contains(sep.as_str()));
+
+        let Some(position) = position.filter(|&i| !separators[i].is_empty()) else {
+            return FixedSizeSplitter::new(self.chunk_size, 0).split(text);
+        };
+        let (separator, finer) = (&separators[position], &separators[position + 1..]);
+
+        text.split_inclusive(separator.as_str())
+            .flat_map(|piece| {
+                if char_len(piece) > self.chunk_size {
+                    self.pieces(piece, finer)
+                } else {
+                    vec![piece.to_string()]
+                }
+            })
+            .collect()
+    }
+}
+
+impl TextSplitter for RecursiveSplitter {
+    fn split(&self, text: &str) -> Vec<String> {
+        merge_pieces(
+            self.pieces(text, &self.separators),
+            self.chunk_size,
+            self.overlap,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recursive_splitter() {
+        let text = "Paragraph one is here.\n\nParagraph two. It has two sentences.\n\nThree.";
+        let splitter = RecursiveSplitter::new(40, 0);
+        assert_eq!(
+            splitter.split(text),
+            vec![
+                "Paragraph one is here.",
+                "Paragraph two. It has two sentences.",
+                "Three."
+            ]
+        );
+
+        let splitter = RecursiveSplitter::new(20, 0);
+        assert_eq!(
+            splitter.split(text),
+            vec![
+                "Paragraph one is",
+                "here.",
+                "Paragraph two. It",
+                "has two sentences.",
+                "Three."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recursive_splitter_overlap() {
+        let splitter = RecursiveSplitter::new(10, 4);
+        assert_eq!(
+            splitter.split("one two three four"),
+            vec!["one two", "two three", "four"]
+        );
+    }
+
+    #[test]
+    fn test_recursive_splitter_falls_back_to_characters() {
+        let splitter = RecursiveSplitter::new(4, 0).with_separators(["\n"]);
+        assert_eq!(splitter.split("abcdefgh"), vec!["abcd", "efgh"]);
+    }
+}
@@ -0,0 +1,119 @@
+use super::{char_len, merge_pieces, FixedSizeSplitter, TextSplitter};
+
+/// Groups whole sentences into chunks of up to `chunk_size` characters, with up to
+/// `overlap` characters worth of sentences repeated between consecutive chunks.
+///
+/// A sentence ends with `.`, `!` or `?` followed by whitespace, or with a blank line.
+/// Sentences longer than `chunk_size` are split on character boundaries.
+///
+/// # Example
+/// ```rust
+/// use rig::chunking::{SentenceSplitter, TextSplitter};
+///
+/// let splitter = SentenceSplitter::new(30, 0);
+/// assert_eq!(
+///     splitter.split("The sky is blue. The grass is green. Is it?"),
+///     vec!["The sky is blue.", "The grass is green. Is it?"]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct SentenceSplitter {
+    chunk_size: usize,
+    overlap: usize,
+}
+
+impl SentenceSplitter {
+    /// Create a new splitter.
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` is zero or if `overlap` is not smaller than `chunk_size`.
+    pub fn new(chunk_size: usize, overlap: usize) -> Self {
+        assert!(chunk_size > 0, "chunk_size must be greater than 0");
+        assert!(
+            overlap < chunk_size,
+            "overlap must be smaller than chunk_size"
+        );
+        Self {
+            chunk_size,
+            overlap,
+        }
+    }
+}
+
+/// Split `text` into sentences, keeping the trailing whitespace attached to each sentence.
+pub(crate) fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = vec![];
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let boundary = match (c, chars.peek()) {
+            ('.' | '!' | '?', Some((_, next))) => next.is_whitespace(),
+            ('\n', Some((_, '\n'))) => true,
+            _ => false,
+        };
+        if boundary {
+            // Include the whitespace following the sentence
+            let mut end = i + c.len_utf8();
+            while let Some((j, next)) = chars.peek() {
+                if !next.is_whitespace() {
+                    break;
+                }
+                end = j + next.len_utf8();
+                chars.next();
+            }
+            sentences.push(&text[start..end]);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+
+    sentences
+}
+
+impl TextSplitter for SentenceSplitter {
+    fn split(&self, text: &str) -> Vec<String> {
+        let pieces = sentences(text).into_iter().flat_map(|sentence| {
+            if char_len(sentence) > self.chunk_size {
+                FixedSizeSplitter::new(self.chunk_size, 0).split(sentence)
+            } else {
+                vec![sentence.to_string()]
+            }
+        });
+
+        merge_pieces(pieces, self.chunk_size, self.overlap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentences() {
+        assert_eq!(
+            sentences("Hello there! How are you? I'm fine.\n\nNew paragraph"),
+            vec![
+                "Hello there! ",
+                "How are you? ",
+                "I'm fine.\n\n",
+                "New paragraph"
+            ]
+        );
+        assert_eq!(
+            sentences("Version 1.5 is out."),
+            vec!["Version 1.5 is out."]
+        );
+    }
+
+    #[test]
+    fn test_sentence_splitter_overlap() {
+        let splitter = SentenceSplitter::new(30, 15);
+        assert_eq!(
+            splitter.split("One two three. Four five. Six seven eight."),
+            vec!["One two three. Four five.", "Four five. Six seven eight."]
+        );
+    }
+}
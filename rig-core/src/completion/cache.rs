@@ -0,0 +1,443 @@
+//! Caching of completion responses.
+//!
+//! A [CompletionCache] stores the responses of completion requests in a [KvStore] (e.g.: an
+//! [InMemoryStore], or a [RedisStore](crate::storage::redis::RedisStore) shared by the replicas
+//! of a service), keyed by the model and the whole request (prompt, chat history, preamble,
+//! documents, tools and parameters). An agent with a cache (see
+//! [AgentBuilder::cache](crate::agent::AgentBuilder::cache)) answers the repeated requests from
+//! the cache instead of sending them to the model, cutting their cost and latency. Responses
+//! answered from the cache report no token usage.
+//!
+//! Requests match:
+//! - exactly by default,
+//! - or semantically (see [CompletionCache::semantic]): a request also matches a cached request
+//!   which only differs by its prompt, if the prompts are similar enough (e.g.: "What's the
+//!   capital of France?" and "what is the capital of france"). The prompts are embedded with an
+//!   embedding model, and the embeddings of the cached prompts are kept in memory: only the
+//!   responses cached by the same [CompletionCache] match semantically.
+//!
+//! Entries expire after the time-to-live of the cache, if any (see [CompletionCache::ttl]).
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{completion::cache::CompletionCache, providers::openai, storage::redis::RedisStore};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let cache = CompletionCache::new(RedisStore::from_url("redis://127.0.0.1/").await?)
+//!     .ttl(Duration::from_secs(24 * 3600))
+//!     .semantic(openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL), 0.95);
+//!
+//! let agent = openai.agent("gpt-4o").preamble("You are a helpful assistant.").cache(cache).build();
+//! ```
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use super::{AssistantContent, CompletionRequest, Message, Usage};
+use crate::{
+    embeddings::{
+        distance::VectorDistance, Embedding, EmbeddingError, EmbeddingModel, EmbeddingModelDyn,
+    },
+    memory::message_text,
+    runtime::{SystemTime, UNIX_EPOCH},
+    storage::{InMemoryStore, KvStore, StorageError},
+    tool::cache::canonicalize,
+    OneOrMany,
+};
+
+/// Default prefix of the keys of the cached responses.
+pub const DEFAULT_NAMESPACE: &str = "completions/";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("StorageError: {0}")]
+    StorageError(#[from] StorageError),
+
+    /// The prompt could not be embedded, in semantic mode
+    #[error("EmbeddingError: {0}")]
+    EmbeddingError(#[from] EmbeddingError),
+}
+
+/// Response of a completion request, as cached.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedCompletion {
+    pub choice: OneOrMany<AssistantContent>,
+    /// Token usage of the request when it was sent to the model
+    pub usage: Option<Usage>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    completion: CachedCompletion,
+    /// Expiration time, in seconds since the Unix epoch
+    expires_at: Option<u64>,
+}
+
+/// Semantic matching of the prompts of the requests.
+struct SemanticMatcher {
+    model: Box<dyn EmbeddingModelDyn>,
+    threshold: f64,
+    /// Keys and prompt embeddings of the cached responses, by key of their request without prompt
+    prompts: Mutex<HashMap<String, Vec<(String, Embedding)>>>,
+}
+
+/// Result of a lookup, from which the response of a missed request is cached.
+pub(crate) struct CacheLookup {
+    pub(crate) hit: Option<CachedCompletion>,
+    key: String,
+    /// Key of the request without its prompt, and embedding of the prompt, in semantic mode
+    prompt: Option<(String, Embedding)>,
+}
+
+/// Cache of completion responses, stored in a [KvStore] (see the [module](self) documentation).
+pub struct CompletionCache<S = InMemoryStore> {
+    store: S,
+    namespace: String,
+    ttl: Option<Duration>,
+    semantic: Option<SemanticMatcher>,
+}
+
+impl Default for CompletionCache {
+    fn default() -> Self {
+        Self::new(InMemoryStore::new())
+    }
+}
+
+impl<S: KvStore> CompletionCache<S> {
+    /// Cache the responses in `store`, without expiration, matching the requests exactly.
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            ttl: None,
+            semantic: None,
+        }
+    }
+
+    /// Expire the cached responses after `ttl`.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Prefix the keys of the cached responses with `namespace` (defaults to
+    /// [DEFAULT_NAMESPACE]), e.g.: to share a store with other artifacts.
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    /// Also match the requests whose prompts are similar to the prompt of a cached request (with
+    /// a cosine similarity of at least `threshold`, e.g.: `0.95`) and which are otherwise
+    /// identical. The prompts are embedded with `model`.
+    pub fn semantic(mut self, model: impl EmbeddingModel + 'static, threshold: f64) -> Self {
+        self.semantic = Some(SemanticMatcher {
+            model: Box::new(model),
+            threshold,
+            prompts: Mutex::new(HashMap::new()),
+        });
+        self
+    }
+
+    /// Cached response of `request` to the model named `model`, if any.
+    pub async fn get(
+        &self,
+        model: Option<&str>,
+        request: &CompletionRequest,
+    ) -> Result<Option<CachedCompletion>, CacheError> {
+        Ok(self.lookup(model, request).await?.hit)
+    }
+
+    /// Cache `completion` as the response of `request` to the model named `model`.
+    pub async fn put(
+        &self,
+        model: Option<&str>,
+        request: &CompletionRequest,
+        completion: CachedCompletion,
+    ) -> Result<(), CacheError> {
+        let lookup = self.lookup(model, request).await?;
+        self.insert(lookup, completion).await
+    }
+
+    /// Remove the cached responses.
+    pub async fn clear(&self) -> Result<(), CacheError> {
+        for key in self.store.keys(&self.namespace).await? {
+            self.store.remove(&key).await?;
+        }
+        if let Some(semantic) = &self.semantic {
+            semantic
+                .prompts
+                .lock()
+                .expect("cache prompts lock poisoned")
+                .clear();
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn lookup(
+        &self,
+        model: Option<&str>,
+        request: &CompletionRequest,
+    ) -> Result<CacheLookup, CacheError> {
+        let key = self.key(model, request);
+        let mut lookup = CacheLookup {
+            hit: self.load(&key).await?,
+            key,
+            prompt: None,
+        };
+        let Some(semantic) = self.semantic.as_ref().filter(|_| lookup.hit.is_none()) else {
+            return Ok(lookup);
+        };
+
+        // Requests which only differ by their prompts are candidates
+        let context = self.key(
+            model,
+            &CompletionRequest {
+                prompt: Message::user(""),
+                ..request.clone()
+            },
+        );
+        let (embeddings, _) = semantic
+            .model
+            .embed_texts(vec![message_text(&request.prompt)])
+            .await?;
+        let embedding = embeddings
+            .into_iter()
+            .next()
+            .ok_or_else(|| EmbeddingError::ResponseError("No embedding returned".into()))?;
+
+        let best = semantic
+            .prompts
+            .lock()
+            .expect("cache prompts lock poisoned")
+            .get(&context)
+            .into_iter()
+            .flatten()
+            .map(|(key, cached)| (cached.cosine_similarity(&embedding, false), key.clone()))
+            .filter(|(similarity, _)| *similarity >= semantic.threshold)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b));
+        if let Some((similarity, key)) = best {
+            tracing::debug!(target: "rig", "Semantic cache hit (similarity {similarity:.3})");
+            lookup.hit = self.load(&key).await?;
+        }
+        lookup.prompt = Some((context, embedding));
+        Ok(lookup)
+    }
+
+    pub(crate) async fn insert(
+        &self,
+        lookup: CacheLookup,
+        completion: CachedCompletion,
+    ) -> Result<(), CacheError> {
+        let expires_at = self.ttl.map(|ttl| (now() + ttl).as_secs());
+        self.store
+            .save(
+                &lookup.key,
+                &CacheEntry {
+                    completion,
+                    expires_at,
+                },
+            )
+            .await?;
+
+        if let (Some(semantic), Some((context, embedding))) = (&self.semantic, lookup.prompt) {
+            let mut prompts = semantic
+                .prompts
+                .lock()
+                .expect("cache prompts lock poisoned");
+            let prompts = prompts.entry(context).or_default();
+            prompts.retain(|(key, _)| *key != lookup.key);
+            prompts.push((lookup.key, embedding));
+        }
+        Ok(())
+    }
+
+    /// Load the entry of `key`, removing it if it expired.
+    async fn load(&self, key: &str) -> Result<Option<CachedCompletion>, CacheError> {
+        match self.store.load::<CacheEntry>(key).await? {
+            Some(entry) if entry.expires_at.is_some_and(|at| at <= now().as_secs()) => {
+                self.store.remove(key).await?;
+                Ok(None)
+            }
+            entry => Ok(entry.map(|entry| entry.completion)),
+        }
+    }
+
+    fn key(&self, model: Option<&str>, request: &CompletionRequest) -> String {
+        let model = model
+            .unwrap_or("default")
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect::<String>();
+        let request = serde_json::to_value(request).unwrap_or_default();
+        format!(
+            "{}{model}/{:016x}",
+            self.namespace,
+            fnv1a(canonicalize(&request).as_bytes())
+        )
+    }
+}
+
+/// Wrapper trait to store caches of any store
+pub(crate) trait CompletionCacheDyn: Send + Sync {
+    fn lookup_dyn<'a>(
+        &'a self,
+        model: Option<&'a str>,
+        request: &'a CompletionRequest,
+    ) -> BoxFuture<'a, Result<CacheLookup, CacheError>>;
+
+    fn insert_dyn(
+        &self,
+        lookup: CacheLookup,
+        completion: CachedCompletion,
+    ) -> BoxFuture<'_, Result<(), CacheError>>;
+}
+
+impl<S: KvStore> CompletionCacheDyn for CompletionCache<S> {
+    fn lookup_dyn<'a>(
+        &'a self,
+        model: Option<&'a str>,
+        request: &'a CompletionRequest,
+    ) -> BoxFuture<'a, Result<CacheLookup, CacheError>> {
+        Box::pin(self.lookup(model, request))
+    }
+
+    fn insert_dyn(
+        &self,
+        lookup: CacheLookup,
+        completion: CachedCompletion,
+    ) -> BoxFuture<'_, Result<(), CacheError>> {
+        Box::pin(self.insert(lookup, completion))
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// 64-bit FNV-1a hash, stable across processes (unlike the hasher of the standard library), so
+/// that the keys of a store shared by several processes match.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{providers::mock::MockEmbeddingModel, storage::KvStore};
+
+    fn request(prompt: &str) -> CompletionRequest {
+        CompletionRequest {
+            prompt: Message::user(prompt),
+            preamble: Some("Be brief.".into()),
+            chat_history: vec![],
+            documents: vec![],
+            tools: vec![],
+            temperature: Some(0.0),
+            max_tokens: None,
+            additional_params: None,
+            document_format: Default::default(),
+        }
+    }
+
+    fn completion(text: &str) -> CachedCompletion {
+        CachedCompletion {
+            choice: OneOrMany::one(AssistantContent::text(text)),
+            usage: Some(Usage::new(10, 2)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exact_cache() {
+        let store = InMemoryStore::new();
+        let cache = CompletionCache::new(store.clone());
+        let hello = request("Hello");
+
+        assert_eq!(cache.get(Some("gpt-4o"), &hello).await.unwrap(), None);
+        cache
+            .put(Some("gpt-4o"), &hello, completion("Hi!"))
+            .await
+            .unwrap();
+        assert_eq!(
+            cache.get(Some("gpt-4o"), &hello).await.unwrap(),
+            Some(completion("Hi!"))
+        );
+
+        // Other models, prompts and parameters miss
+        assert_eq!(cache.get(Some("gpt-4o-mini"), &hello).await.unwrap(), None);
+        assert_eq!(
+            cache.get(Some("gpt-4o"), &request("Hi")).await.unwrap(),
+            None
+        );
+        let hot = CompletionRequest {
+            temperature: Some(1.0),
+            ..hello.clone()
+        };
+        assert_eq!(cache.get(Some("gpt-4o"), &hot).await.unwrap(), None);
+
+        let keys = store.keys(DEFAULT_NAMESPACE).await.unwrap();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].starts_with("completions/gpt-4o/"));
+        cache.clear().await.unwrap();
+        assert_eq!(cache.get(Some("gpt-4o"), &hello).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl() {
+        let store = InMemoryStore::new();
+        let cache = CompletionCache::new(store.clone()).ttl(Duration::ZERO);
+        cache
+            .put(None, &request("Hello"), completion("Hi!"))
+            .await
+            .unwrap();
+
+        // Expired entries are removed when they are looked up
+        assert_eq!(store.keys("").await.unwrap().len(), 1);
+        assert_eq!(cache.get(None, &request("Hello")).await.unwrap(), None);
+        assert!(store.keys("").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_semantic_cache() {
+        let cache = CompletionCache::default().semantic(MockEmbeddingModel::new(64), 0.8);
+        cache
+            .put(
+                None,
+                &request("what is the capital of france"),
+                completion("Paris"),
+            )
+            .await
+            .unwrap();
+
+        // Similar prompts hit, dissimilar prompts and requests differing otherwise miss
+        assert_eq!(
+            cache
+                .get(None, &request("what is the capital of france ?"))
+                .await
+                .unwrap(),
+            Some(completion("Paris"))
+        );
+        assert_eq!(
+            cache
+                .get(None, &request("how tall is mount everest"))
+                .await
+                .unwrap(),
+            None
+        );
+        let other_preamble = CompletionRequest {
+            preamble: Some("Be verbose.".into()),
+            ..request("what is the capital of france ?")
+        };
+        assert_eq!(cache.get(None, &other_preamble).await.unwrap(), None);
+    }
+}
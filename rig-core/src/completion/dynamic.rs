@@ -0,0 +1,157 @@
+//! Type-erased completion models, to choose the model of an agent at runtime.
+//!
+//! [DynCompletionModel] wraps any [CompletionModel] behind a [CompletionModelDyn] trait object,
+//! so that agents backed by models of different providers have the same type, e.g.:
+//! `Agent<DynCompletionModel>`. See [ModelRegistry](crate::registry::ModelRegistry) to create
+//! such models from `provider:model` strings.
+use std::{any::Any, sync::Arc};
+
+use futures::future::BoxFuture;
+
+use super::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse};
+
+/// Raw response of a [DynCompletionModel], which can be downcast to the response type of the
+/// wrapped model (e.g.: `openai::CompletionResponse`).
+pub type DynResponse = Box<dyn Any + Send + Sync>;
+
+/// Wrapper trait to allow for dynamic dispatch of completion models
+pub trait CompletionModelDyn: Send + Sync {
+    fn model_name(&self) -> Option<&str>;
+
+    fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> BoxFuture<'_, Result<CompletionResponse<DynResponse>, CompletionError>>;
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value>;
+
+    fn json_mode(&self) -> Option<serde_json::Value>;
+}
+
+impl<M> CompletionModelDyn for M
+where
+    M: CompletionModel,
+    M::Response: 'static,
+{
+    fn model_name(&self) -> Option<&str> {
+        <Self as CompletionModel>::model_name(self)
+    }
+
+    fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> BoxFuture<'_, Result<CompletionResponse<DynResponse>, CompletionError>> {
+        Box::pin(async move {
+            let response = <Self as CompletionModel>::completion(self, request).await?;
+            Ok(CompletionResponse {
+                choice: response.choice,
+                usage: response.usage,
+                raw_response: Box::new(response.raw_response) as DynResponse,
+            })
+        })
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        <Self as CompletionModel>::structured_output(self, name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        <Self as CompletionModel>::json_mode(self)
+    }
+}
+
+/// [CompletionModel] delegating to a completion model chosen at runtime.
+///
+/// # Example
+/// ```rust
+/// use rig::{completion::DynCompletionModel, providers::{anthropic, openai}};
+///
+/// let model = match config.provider.as_str() {
+///     "anthropic" => DynCompletionModel::new(anthropic_client.completion_model(anthropic::CLAUDE_3_5_SONNET)),
+///     _ => DynCompletionModel::new(openai_client.completion_model(openai::GPT_4O)),
+/// };
+/// let agent = AgentBuilder::new(model).preamble("You are a helpful assistant.").build();
+/// ```
+#[derive(Clone)]
+pub struct DynCompletionModel(Arc<dyn CompletionModelDyn>);
+
+impl DynCompletionModel {
+    pub fn new<M>(model: M) -> Self
+    where
+        M: CompletionModel + 'static,
+        M::Response: 'static,
+    {
+        Self(Arc::new(model))
+    }
+}
+
+impl CompletionModel for DynCompletionModel {
+    type Response = DynResponse;
+
+    fn model_name(&self) -> Option<&str> {
+        self.0.model_name()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<DynResponse>, CompletionError> {
+        self.0.completion(request).await
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.0.structured_output(name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        self.0.json_mode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CompletionError, CompletionModel, CompletionRequest, CompletionResponse, DynCompletionModel,
+    };
+
+    /// Model failing every request
+    #[derive(Clone)]
+    struct Overloaded;
+
+    impl CompletionModel for Overloaded {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Err(CompletionError::ProviderError("Overloaded".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dyn_completion_model_errors() {
+        let model = DynCompletionModel::new(Overloaded);
+        // Errors of the wrapped model are returned as is
+        assert!(matches!(
+            model.completion_request("Hi").send().await,
+            Err(CompletionError::ProviderError(message)) if message == "Overloaded"
+        ));
+        assert_eq!(
+            model.structured_output("answer", &serde_json::json!({})),
+            None
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Automatic failover between completion models, so that agents survive the outage of a
+//! provider.
+//!
+//! A [FallbackCompletionModel] sends each completion request to the first of its models (in
+//! the order in which they were added) which is healthy. If the request fails with an error
+//! of one of the failover [ErrorClass]es (by default: HTTP, rate limit, provider and response
+//! errors, and timeouts), the model is marked unhealthy for a cooldown, and the request is
+//! sent to the next model. Other errors (e.g.: a request that cannot be built) are returned
+//! right away.
+//!
+//! Unhealthy models are skipped until their cooldown elapses, unless all the models are
+//! unhealthy, in which case they are all tried in order. [FallbackCompletionModel::check_health]
+//! sends a lightweight probe request to each model to refresh their health, e.g.: periodically.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{completion::FallbackCompletionModel, providers::{anthropic, openai}};
+//!
+//! let openai = openai::Client::from_env();
+//! let anthropic = anthropic::Client::from_env();
+//!
+//! let model = FallbackCompletionModel::new(openai.completion_model(openai::GPT_4O))
+//!     .fallback(anthropic.completion_model(anthropic::CLAUDE_3_5_SONNET))
+//!     .attempt_timeout(Duration::from_secs(30))
+//!     .cooldown(Duration::from_secs(60));
+//!
+//! let agent = rig::agent::AgentBuilder::new(model)
+//!     .preamble("You are a helpful assistant.")
+//!     .build();
+//! ```
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use super::{
+    CompletionError, CompletionModel, CompletionRequest, CompletionResponse, DynCompletionModel,
+    DynResponse, Message,
+};
+use crate::runtime::{self, Instant};
+
+/// Default time during which a failed model is skipped
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Class of the errors of the completion requests, deciding whether they trigger a failover
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// The request could not be sent, or its response received (e.g.: connection error)
+    Http,
+    /// The request was rejected because of a rate limit or an overloaded provider
+    RateLimited,
+    /// The provider returned an error
+    Provider,
+    /// The response could not be parsed
+    Response,
+    /// The request did not complete in time
+    Timeout,
+    /// The request could not be built (e.g.: content not supported by the provider)
+    Request,
+    /// The request or the response could not be (de)serialized
+    Json,
+}
+
+impl ErrorClass {
+    /// Class of `error`, if any: cancelled requests have none, and never trigger a failover.
+    pub fn of(error: &CompletionError) -> Option<Self> {
+        if error.is_rate_limited() {
+            return Some(Self::RateLimited);
+        }
+        match error {
+            #[cfg(feature = "http")]
+            CompletionError::HttpError(_) => Some(Self::Http),
+            CompletionError::JsonError(_) => Some(Self::Json),
+            CompletionError::RequestError(_) => Some(Self::Request),
+            CompletionError::ResponseError(_) => Some(Self::Response),
+            CompletionError::ProviderError(_) => Some(Self::Provider),
+            CompletionError::DeadlineExceeded(_) => Some(Self::Timeout),
+            CompletionError::Cancelled => None,
+        }
+    }
+}
+
+/// Health of a model of a [FallbackCompletionModel]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelHealth {
+    /// Name of the model, if known
+    pub model: Option<String>,
+    /// Whether the model is used (i.e.: it is not cooling down after a failure)
+    pub healthy: bool,
+    /// Consecutive failures of the model
+    pub failures: usize,
+}
+
+/// Number of requests made by a [FallbackCompletionModel]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FallbackStats {
+    /// Completion requests sent to the model
+    pub requests: usize,
+    /// Requests sent to the next model after the failure of a model
+    pub failovers: usize,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    /// End of the cooldown of the model, after its last failure
+    unhealthy_until: Option<Instant>,
+    failures: usize,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    requests: AtomicUsize,
+    failovers: AtomicUsize,
+}
+
+/// Completion model failing over to the next of its models when a model fails (see the
+/// [module](self) documentation).
+///
+/// Clones of the model share the health of the models and the [FallbackStats].
+#[derive(Clone)]
+pub struct FallbackCompletionModel {
+    models: Vec<DynCompletionModel>,
+    health: Arc<Mutex<Vec<Health>>>,
+    failover_on: HashSet<ErrorClass>,
+    cooldown: Duration,
+    attempt_timeout: Option<Duration>,
+    counters: Arc<Counters>,
+}
+
+impl FallbackCompletionModel {
+    /// Fail over from `model`, the primary model, to the [fallback](Self::fallback) models.
+    pub fn new<M>(model: M) -> Self
+    where
+        M: CompletionModel + 'static,
+        M::Response: 'static,
+    {
+        Self {
+            models: vec![DynCompletionModel::new(model)],
+            health: Arc::new(Mutex::new(vec![Health::default()])),
+            failover_on: HashSet::from([
+                ErrorClass::Http,
+                ErrorClass::RateLimited,
+                ErrorClass::Provider,
+                ErrorClass::Response,
+                ErrorClass::Timeout,
+            ]),
+            cooldown: DEFAULT_COOLDOWN,
+            attempt_timeout: None,
+            counters: Arc::default(),
+        }
+    }
+
+    /// Add a fallback model, used when the previous models fail.
+    pub fn fallback<M>(mut self, model: M) -> Self
+    where
+        M: CompletionModel + 'static,
+        M::Response: 'static,
+    {
+        self.models.push(DynCompletionModel::new(model));
+        self.health
+            .lock()
+            .expect("fallback health lock poisoned")
+            .push(Health::default());
+        self
+    }
+
+    /// Set the classes of the errors triggering a failover, replacing the default ones.
+    pub fn failover_on(mut self, classes: impl IntoIterator<Item = ErrorClass>) -> Self {
+        self.failover_on = classes.into_iter().collect();
+        self
+    }
+
+    /// Set the time during which a failed model is skipped (defaults to 30 seconds).
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Give up on the requests to each model after `timeout`, failing over to the next model
+    /// (the timeouts belong to the [ErrorClass::Timeout] class).
+    pub fn attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = Some(timeout);
+        self
+    }
+
+    /// Number of requests made by the model (and its clones)
+    pub fn stats(&self) -> FallbackStats {
+        FallbackStats {
+            requests: self.counters.requests.load(Ordering::Relaxed),
+            failovers: self.counters.failovers.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Health of the models, in order
+    pub fn health(&self) -> Vec<ModelHealth> {
+        let now = Instant::now();
+        let health = self.health.lock().expect("fallback health lock poisoned");
+        self.models
+            .iter()
+            .zip(health.iter())
+            .map(|(model, health)| ModelHealth {
+                model: model.model_name().map(str::to_string),
+                healthy: health.unhealthy_until.is_none_or(|until| until <= now),
+                failures: health.failures,
+            })
+            .collect()
+    }
+
+    /// Send a probe request (a one token completion) to each model, updating their health, and
+    /// return the health of the models.
+    pub async fn check_health(&self) -> Vec<ModelHealth> {
+        let probes = self.models.iter().enumerate().map(|(i, model)| async move {
+            let probe = model
+                .completion_request(Message::user("ping"))
+                .max_tokens(1)
+                .build();
+            let result = self.attempt(model, probe).await;
+            self.record(i, &result);
+        });
+        futures::future::join_all(probes).await;
+        self.health()
+    }
+
+    /// Indices of the models, in the order in which they are tried: the healthy models first.
+    fn order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let health = self.health.lock().expect("fallback health lock poisoned");
+        let (healthy, unhealthy): (Vec<_>, Vec<_>) = (0..self.models.len())
+            .partition(|&i| health[i].unhealthy_until.is_none_or(|until| until <= now));
+        healthy.into_iter().chain(unhealthy).collect()
+    }
+
+    async fn attempt(
+        &self,
+        model: &DynCompletionModel,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<DynResponse>, CompletionError> {
+        match self.attempt_timeout {
+            Some(timeout) => runtime::timeout(timeout, model.completion(request))
+                .await
+                .unwrap_or_else(|elapsed| Err(CompletionError::DeadlineExceeded(elapsed))),
+            None => model.completion(request).await,
+        }
+    }
+
+    /// Record the outcome of a request to the model `i`, returning whether it failed with an
+    /// error triggering a failover.
+    fn record<T>(&self, i: usize, result: &Result<T, CompletionError>) -> bool {
+        let mut health = self.health.lock().expect("fallback health lock poisoned");
+        let health = &mut health[i];
+        match result {
+            Ok(_) => {
+                *health = Health::default();
+                false
+            }
+            Err(e) if ErrorClass::of(e).is_some_and(|class| self.failover_on.contains(&class)) => {
+                health.failures += 1;
+                health.unhealthy_until = Some(Instant::now() + self.cooldown);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Value common to all the models, if they agree on it
+    fn common<T: PartialEq>(&self, value: impl Fn(&DynCompletionModel) -> Option<T>) -> Option<T> {
+        let mut values = self.models.iter().map(value);
+        let first = values.next()??;
+        values.all(|v| v.as_ref() == Some(&first)).then_some(first)
+    }
+}
+
+impl CompletionModel for FallbackCompletionModel {
+    type Response = DynResponse;
+
+    fn model_name(&self) -> Option<&str> {
+        self.models[0].model_name()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<DynResponse>, CompletionError> {
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+
+        let order = self.order();
+        let mut result = Err(CompletionError::ProviderError("No model".to_string()));
+        for (attempt, i) in order.iter().copied().enumerate() {
+            if attempt > 0 {
+                self.counters.failovers.fetch_add(1, Ordering::Relaxed);
+            }
+            result = self.attempt(&self.models[i], request.clone()).await;
+            if !self.record(i, &result) {
+                return result;
+            }
+            if let Err(e) = &result {
+                tracing::warn!(target: "rig",
+                    "Completion model {} failed, failing over to the next model: {e}",
+                    self.models[i].model_name().unwrap_or("(unnamed)")
+                );
+            }
+        }
+        result
+    }
+
+    /// Structured outputs are requested only if all the models request them the same way, since
+    /// the parameters of the request are sent to every model.
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.common(|model| model.structured_output(name, schema))
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        self.common(|model| model.json_mode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{completion::AssistantContent, providers::mock::MockCompletionModel};
+
+    fn text(response: &CompletionResponse<DynResponse>) -> String {
+        match response.choice.first() {
+            AssistantContent::Text(text) => text.text,
+            AssistantContent::ToolCall(_) => panic!("expected a text response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let primary = MockCompletionModel::new()
+            .error("503 unavailable")
+            .repeat("primary");
+        let secondary = MockCompletionModel::new().repeat("secondary");
+        let model = FallbackCompletionModel::new(primary.clone())
+            .fallback(secondary.clone())
+            .cooldown(Duration::from_millis(100));
+
+        // The failed primary is skipped during its cooldown
+        let request = || primary.completion_request("Hi").build();
+        assert_eq!(
+            text(&model.completion(request()).await.unwrap()),
+            "secondary"
+        );
+        assert_eq!(
+            text(&model.completion(request()).await.unwrap()),
+            "secondary"
+        );
+        assert_eq!(primary.requests().len(), 1);
+        assert_eq!(
+            model.stats(),
+            FallbackStats {
+                requests: 2,
+                failovers: 1
+            }
+        );
+        assert!(!model.health()[0].healthy);
+        assert_eq!(model.health()[0].failures, 1);
+
+        // Then tried again
+        runtime::sleep(Duration::from_millis(150)).await;
+        assert_eq!(text(&model.completion(request()).await.unwrap()), "primary");
+        assert!(model.health().iter().all(|health| health.healthy));
+
+        // Errors of the other classes are returned right away
+        let model = FallbackCompletionModel::new(MockCompletionModel::new().error("down"))
+            .fallback(secondary.clone())
+            .failover_on([ErrorClass::RateLimited]);
+        assert!(matches!(
+            model.completion(request()).await,
+            Err(CompletionError::ProviderError(_))
+        ));
+        let model =
+            FallbackCompletionModel::new(MockCompletionModel::new().error("Too many requests"))
+                .fallback(secondary)
+                .failover_on([ErrorClass::RateLimited]);
+        assert_eq!(
+            text(&model.completion(request()).await.unwrap()),
+            "secondary"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_checks() {
+        let slow = MockCompletionModel::new()
+            .repeat("slow")
+            .latency(Duration::from_secs(5));
+        let model = FallbackCompletionModel::new(slow.clone())
+            .fallback(MockCompletionModel::new().repeat("fast"))
+            .attempt_timeout(Duration::from_millis(20));
+
+        let health = model.check_health().await;
+        assert!(!health[0].healthy);
+        assert!(health[1].healthy);
+        assert_eq!(slow.last_request().unwrap().max_tokens, Some(1));
+
+        // All the models are tried when none is healthy
+        let model = FallbackCompletionModel::new(MockCompletionModel::new().error("down"))
+            .fallback(MockCompletionModel::new().repeat("up"));
+        model.health.lock().unwrap()[1].unhealthy_until =
+            Some(Instant::now() + Duration::from_secs(60));
+        let request = MockCompletionModel::new().completion_request("Hi").build();
+        assert_eq!(text(&model.completion(request).await.unwrap()), "up");
+    }
+}
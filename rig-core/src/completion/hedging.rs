@@ -0,0 +1,328 @@
+//! Request hedging, to cut the tail latency of completion models.
+//!
+//! A [HedgedModel] sends each completion request to its model and, if no response was received
+//! after a latency threshold (e.g.: the p95 latency of the model), sends a duplicate of the
+//! request. The first successful response is returned, and the other request is cancelled (its
+//! future is dropped, which aborts the HTTP request of the provider clients).
+//!
+//! Hedged requests are billed twice: the share of hedged requests can be bounded with
+//! [HedgedModel::max_hedge_ratio], and is reported by [HedgedModel::stats].
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{completion::HedgedModel, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! // Send a second request if the first one takes more than 2 seconds, for at most 5% of the
+//! // requests
+//! let model = HedgedModel::new(openai.completion_model(openai::GPT_4O), Duration::from_secs(2))
+//!     .max_hedge_ratio(0.05);
+//!
+//! let agent = rig::agent::AgentBuilder::new(model)
+//!     .preamble("You are a helpful assistant.")
+//!     .build();
+//! ```
+use std::{
+    pin::pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::future::{select, Either};
+
+use super::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse};
+use crate::runtime;
+
+/// Number of requests made by a [HedgedModel]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HedgingStats {
+    /// Completion requests sent to the model (not counting the duplicates)
+    pub requests: usize,
+    /// Requests for which a duplicate was sent
+    pub hedged: usize,
+    /// Hedged requests answered by the duplicate
+    pub hedge_wins: usize,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    requests: AtomicUsize,
+    hedged: AtomicUsize,
+    hedge_wins: AtomicUsize,
+}
+
+/// Completion model sending a duplicate of the requests that are slower than a threshold, and
+/// returning the first response.
+///
+/// Clones of the model share their [HedgingStats] (and hedging budget).
+#[derive(Clone)]
+pub struct HedgedModel<M> {
+    model: M,
+    delay: Duration,
+    max_hedge_ratio: f64,
+    counters: Arc<Counters>,
+}
+
+impl<M: CompletionModel> HedgedModel<M> {
+    /// Hedge the requests of `model` that take longer than `delay`.
+    pub fn new(model: M, delay: Duration) -> Self {
+        Self {
+            model,
+            delay,
+            max_hedge_ratio: 1.0,
+            counters: Arc::default(),
+        }
+    }
+
+    /// Set the maximum share of the requests that are hedged (between 0 and 1), bounding the
+    /// cost overhead of the hedging. Slow requests are not hedged while the share of hedged
+    /// requests is above this ratio. Defaults to 1 (all slow requests are hedged).
+    pub fn max_hedge_ratio(mut self, ratio: f64) -> Self {
+        self.max_hedge_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Number of requests made by the model (and its clones)
+    pub fn stats(&self) -> HedgingStats {
+        HedgingStats {
+            requests: self.counters.requests.load(Ordering::Relaxed),
+            hedged: self.counters.hedged.load(Ordering::Relaxed),
+            hedge_wins: self.counters.hedge_wins.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether a slow request can be hedged without exceeding the hedging budget
+    fn within_budget(&self) -> bool {
+        let requests = self.counters.requests.load(Ordering::Relaxed);
+        let hedged = self.counters.hedged.load(Ordering::Relaxed);
+        (hedged + 1) as f64 <= self.max_hedge_ratio * requests as f64
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for HedgedModel<M> {
+    type Response = M::Response;
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.model_name()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<M::Response>, CompletionError> {
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+
+        let primary = pin!(self.model.completion(request.clone()));
+        let primary = match select(primary, pin!(runtime::sleep(self.delay))).await {
+            Either::Left((response, _)) => return response,
+            Either::Right((_, primary)) => primary,
+        };
+        if !self.within_budget() {
+            return primary.await;
+        }
+
+        self.counters.hedged.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(target: "rig",
+            "No completion response after {:?}, sending a hedged request",
+            self.delay
+        );
+        let hedge = pin!(self.model.completion(request));
+
+        // Return the first successful response, dropping (i.e.: cancelling) the other request
+        match select(primary, hedge).await {
+            Either::Left((Ok(response), _)) => Ok(response),
+            Either::Right((Ok(response), _)) => {
+                self.counters.hedge_wins.fetch_add(1, Ordering::Relaxed);
+                Ok(response)
+            }
+            Either::Left((Err(_), hedge)) => {
+                let response = hedge.await;
+                if response.is_ok() {
+                    self.counters.hedge_wins.fetch_add(1, Ordering::Relaxed);
+                }
+                response
+            }
+            Either::Right((Err(_), primary)) => primary.await,
+        }
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.model.structured_output(name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        self.model.json_mode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{atomic::AtomicBool, Mutex},
+    };
+
+    use super::*;
+    use crate::{completion::Prompt, message::AssistantContent, OneOrMany};
+
+    /// Sets its flag when dropped before being defused, i.e.: if its request was cancelled
+    struct CancelGuard(Arc<AtomicBool>, bool);
+
+    impl Drop for CancelGuard {
+        fn drop(&mut self) {
+            if !self.1 {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Model whose first request is slow, answering with the index of the request
+    #[derive(Clone, Default)]
+    struct SlowFirstModel {
+        calls: Arc<AtomicUsize>,
+        cancelled: Arc<AtomicBool>,
+    }
+
+    impl CompletionModel for SlowFirstModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut guard = CancelGuard(self.cancelled.clone(), false);
+            if call == 0 {
+                runtime::sleep(Duration::from_secs(5)).await;
+            }
+            guard.1 = true;
+
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!("call {call}"))),
+                usage: None,
+                raw_response: (),
+            })
+        }
+    }
+
+    /// Model answering its requests with the next scripted answer (or error), after a latency
+    #[derive(Clone)]
+    struct ScriptedModel {
+        script: Arc<Mutex<VecDeque<Result<&'static str, &'static str>>>>,
+        latency: Duration,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl ScriptedModel {
+        fn new(script: Vec<Result<&'static str, &'static str>>, latency: Duration) -> Self {
+            Self {
+                script: Arc::new(Mutex::new(script.into())),
+                latency,
+                calls: Arc::default(),
+            }
+        }
+    }
+
+    impl CompletionModel for ScriptedModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let answer = self.script.lock().unwrap().pop_front().unwrap();
+            runtime::sleep(self.latency).await;
+            let text = answer.map_err(|e| CompletionError::ProviderError(e.into()))?;
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(text)),
+                usage: None,
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hedged_model() {
+        let model = SlowFirstModel::default();
+        let hedged = HedgedModel::new(model.clone(), Duration::from_millis(20));
+        let agent = crate::agent::AgentBuilder::new(hedged.clone()).build();
+
+        // The slow request is hedged and cancelled, the next ones answer in time
+        assert_eq!(agent.prompt("Hi").await.unwrap(), "call 1");
+        assert!(model.cancelled.load(Ordering::SeqCst));
+        assert_eq!(agent.prompt("Hi").await.unwrap(), "call 2");
+        assert_eq!(
+            hedged.stats(),
+            HedgingStats {
+                requests: 2,
+                hedged: 1,
+                hedge_wins: 1
+            }
+        );
+
+        // Without hedging budget, the slow request is awaited
+        let model = SlowFirstModel::default();
+        let hedged =
+            HedgedModel::new(model.clone(), Duration::from_millis(20)).max_hedge_ratio(0.0);
+        let response = runtime::timeout(
+            Duration::from_millis(100),
+            hedged.completion_request("Hi").send(),
+        )
+        .await;
+        assert!(response.is_err());
+        assert_eq!(model.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(hedged.stats().hedged, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hedged_errors() {
+        // Failures before the delay are returned without hedging
+        let model = ScriptedModel::new(vec![Err("Overloaded")], Duration::ZERO);
+        let hedged = HedgedModel::new(model.clone(), Duration::from_secs(1));
+        assert!(matches!(
+            hedged.completion_request("Hi").send().await,
+            Err(CompletionError::ProviderError(message)) if message == "Overloaded"
+        ));
+        assert_eq!(model.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(hedged.stats().hedged, 0);
+
+        // A failed slow request falls back to its duplicate
+        let model = ScriptedModel::new(
+            vec![Err("Overloaded"), Ok("Hello")],
+            Duration::from_millis(50),
+        );
+        let hedged = HedgedModel::new(model, Duration::from_millis(10));
+        let agent = crate::agent::AgentBuilder::new(hedged.clone()).build();
+        assert_eq!(agent.prompt("Hi").await.unwrap(), "Hello");
+        assert_eq!(hedged.stats().hedge_wins, 1);
+
+        // The error of the duplicate is returned when both requests fail
+        let model = ScriptedModel::new(
+            vec![Err("Overloaded"), Err("Timeout")],
+            Duration::from_millis(50),
+        );
+        let hedged = HedgedModel::new(model, Duration::from_millis(10));
+        assert!(matches!(
+            hedged.completion_request("Hi").send().await,
+            Err(CompletionError::ProviderError(message)) if message == "Timeout"
+        ));
+        assert_eq!(
+            hedged.stats(),
+            HedgingStats {
+                requests: 1,
+                hedged: 1,
+                hedge_wins: 0
+            }
+        );
+    }
+}
@@ -1,5 +1,16 @@
-pub mod message;
+pub mod cache;
+pub mod dynamic;
+pub mod fallback;
+pub mod hedging;
 pub mod request;
+pub mod resume;
 
+pub use rig_types::message;
+
+pub use cache::CompletionCache;
+pub use dynamic::{CompletionModelDyn, DynCompletionModel, DynResponse};
+pub use fallback::FallbackCompletionModel;
+pub use hedging::HedgedModel;
 pub use message::{AssistantContent, Message, MessageError};
 pub use request::*;
+pub use resume::{ResumableModel, ResumeStrategy};
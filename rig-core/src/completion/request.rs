@@ -62,16 +62,27 @@
 //!
 //! For more information on how to use the completion functionality, refer to the documentation of
 //! the individual traits, structs, and enums defined in this module.
-use std::collections::HashMap;
+use std::{collections::HashMap, future::Future, time::Duration};
 
+use futures::{
+    future::{self, BoxFuture, Either},
+    FutureExt, StreamExt,
+};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tracing::{field::Empty, Instrument};
 
 use crate::streaming::{StreamingCompletionModel, StreamingResult};
 use crate::OneOrMany;
 use crate::{
+    budget::BudgetExceeded,
+    guardrails::GuardrailViolation,
     json_utils,
-    message::{Message, UserContent},
+    message::{Message, MessageError, UserContent},
+    prompt::{PromptTemplate, PromptTemplateError},
+    runtime::{self, CancellationToken, Elapsed},
+    telemetry::RunContext,
     tool::ToolSetError,
 };
 
@@ -81,6 +92,7 @@ use super::message::AssistantContent;
 #[derive(Debug, Error)]
 pub enum CompletionError {
     /// Http error (e.g.: connection error, timeout, etc.)
+    #[cfg(feature = "http")]
     #[error("HttpError: {0}")]
     HttpError(#[from] reqwest::Error),
 
@@ -99,6 +111,56 @@ pub enum CompletionError {
     /// Error returned by the completion model provider
     #[error("ProviderError: {0}")]
     ProviderError(String),
+
+    /// The request was cancelled with the cancellation token of its [PromptOptions]
+    #[error("Cancelled")]
+    Cancelled,
+
+    /// The request did not complete before the deadline of its [PromptOptions]
+    #[error("DeadlineExceeded: {0}")]
+    DeadlineExceeded(#[from] Elapsed),
+}
+
+impl CompletionError {
+    /// Whether the request was rejected because of a rate limit or an overloaded provider (e.g.:
+    /// a `429 Too Many Requests` response), in which case it can be retried later.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            #[cfg(feature = "http")]
+            CompletionError::HttpError(e) => is_rate_limit_status(e),
+            CompletionError::ProviderError(message) => is_rate_limit_message(message),
+            _ => false,
+        }
+    }
+}
+
+impl From<MessageError> for CompletionError {
+    fn from(error: MessageError) -> Self {
+        CompletionError::RequestError(error.into())
+    }
+}
+
+#[cfg(feature = "http")]
+pub(crate) fn is_rate_limit_status(error: &reqwest::Error) -> bool {
+    error
+        .status()
+        .is_some_and(|status| status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+}
+
+/// Whether the error message of a provider reports a rate limit (the status code of the
+/// responses is not kept in the provider errors)
+pub(crate) fn is_rate_limit_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    [
+        "rate limit",
+        "rate_limit",
+        "ratelimit",
+        "too many requests",
+        "resource_exhausted",
+        "overloaded",
+    ]
+    .iter()
+    .any(|marker| message.contains(marker))
 }
 
 #[derive(Debug, Error)]
@@ -108,9 +170,158 @@ pub enum PromptError {
 
     #[error("ToolCallError: {0}")]
     ToolError(#[from] ToolSetError),
+
+    /// The model was still calling tools after the maximum number of turns
+    #[error("MaxTurnsError: no answer after {0} turns of tool calls")]
+    MaxTurnsError(usize),
+
+    /// The prompt or the response was rejected by a guard of the agent
+    #[error("GuardrailViolation: {0}")]
+    GuardrailViolation(#[from] GuardrailViolation),
+
+    /// The prompt was cancelled with the cancellation token of its [PromptOptions]
+    #[error("Cancelled")]
+    Cancelled,
+
+    /// The prompt was not answered before the deadline of its [PromptOptions]
+    #[error("DeadlineExceeded: {0}")]
+    DeadlineExceeded(#[from] Elapsed),
+
+    /// The budget of the agent was exceeded (see [AgentBuilder::budget](crate::agent::AgentBuilder::budget))
+    #[error("BudgetExceeded: {0}")]
+    BudgetExceeded(#[from] BudgetExceeded),
+}
+
+/// Options of a prompt (or of a completion request): a timeout and a cancellation token, to give
+/// up on hung provider calls and slow tools. On timeout or cancellation, the work in progress
+/// (the requests to the provider, including the streamed responses, and the tool calls) is
+/// dropped, which aborts the in-flight HTTP requests, and the prompt fails with
+/// [PromptError::DeadlineExceeded] or [PromptError::Cancelled].
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use rig::{completion::PromptOptions, runtime::CancellationToken};
+///
+/// let token = CancellationToken::new();
+/// // e.g.: cancelled when the user closes the connection
+/// let options = PromptOptions::new()
+///     .timeout(Duration::from_secs(30))
+///     .cancel_token(token.clone());
+///
+/// let response = agent.prompt_with("Summarize this thread", options).await?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PromptOptions {
+    pub timeout: Option<Duration>,
+    pub cancel_token: Option<CancellationToken>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+impl PromptOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give up after `timeout`, counted from the start of the prompt.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Give up once `token` is cancelled.
+    pub fn cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Start the clock of the deadline, returning the [Interruption] of the work done with these
+    /// options.
+    pub(crate) fn interruption(&self) -> Interruption {
+        let deadline = self.timeout.map(|timeout| {
+            runtime::sleep(timeout)
+                .map(move |_| Interrupted::Deadline(Elapsed(timeout)))
+                .boxed()
+        });
+        let cancellation = self
+            .cancel_token
+            .as_ref()
+            .map(|token| token.cancelled().map(|_| Interrupted::Cancelled).boxed());
+        let interrupted = match (deadline, cancellation) {
+            (Some(deadline), Some(cancellation)) => future::select(deadline, cancellation)
+                .map(|either| either.factor_first().0)
+                .boxed(),
+            (Some(interrupted), None) | (None, Some(interrupted)) => interrupted,
+            (None, None) => future::pending().boxed(),
+        };
+        Interruption(interrupted)
+    }
+}
+
+/// Why work done with [PromptOptions] was interrupted
+#[derive(Debug, Clone)]
+pub(crate) enum Interrupted {
+    Cancelled,
+    Deadline(Elapsed),
+}
+
+impl From<Interrupted> for CompletionError {
+    fn from(interrupted: Interrupted) -> Self {
+        match interrupted {
+            Interrupted::Cancelled => CompletionError::Cancelled,
+            Interrupted::Deadline(elapsed) => CompletionError::DeadlineExceeded(elapsed),
+        }
+    }
+}
+
+impl From<Interrupted> for PromptError {
+    fn from(interrupted: Interrupted) -> Self {
+        match interrupted {
+            Interrupted::Cancelled => PromptError::Cancelled,
+            Interrupted::Deadline(elapsed) => PromptError::DeadlineExceeded(elapsed),
+        }
+    }
+}
+
+/// Deadline and cancellation of the work done with [PromptOptions], resolving once the work
+/// must be given up.
+pub(crate) struct Interruption(BoxFuture<'static, Interrupted>);
+
+impl Interruption {
+    /// Run `future` to completion, unless interrupted first, in which case `future` is dropped.
+    /// Once interrupted, every guarded future fails right away.
+    pub(crate) async fn guard<T, E: From<Interrupted>>(
+        &mut self,
+        future: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let future = std::pin::pin!(future);
+        match future::select(future, &mut self.0).await {
+            Either::Left((output, _)) => output,
+            Either::Right((interrupted, _)) => {
+                self.0 = future::ready(interrupted.clone()).boxed();
+                Err(interrupted.into())
+            }
+        }
+    }
+
+    /// Guard the remaining chunks of `stream`: once interrupted, the stream yields a last
+    /// [CompletionError::Cancelled] or [CompletionError::DeadlineExceeded] error and ends.
+    pub(crate) fn guard_stream(self, stream: StreamingResult) -> StreamingResult {
+        Box::pin(futures::stream::unfold(
+            Some((stream, self)),
+            |state| async move {
+                let (mut stream, mut interruption) = state?;
+                match interruption.guard(stream.next().map(Ok)).await {
+                    Ok(Some(chunk)) => Some((chunk, Some((stream, interruption)))),
+                    Ok(None) => None,
+                    Err(error) => Some((Err(error), None)),
+                }
+            },
+        ))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct Document {
     pub id: String,
     pub text: String,
@@ -140,7 +351,105 @@ impl std::fmt::Display for Document {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Format in which the documents of a request are rendered into its prompt.
+///
+/// Providers with native support for documents (e.g.: Cohere) send them as is instead.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentFormat {
+    /// `<file>` elements in an `<attachments>` element, with the metadata of the documents as
+    /// attributes of a `<metadata />` element (see the [Display] implementation of [Document])
+    ///
+    /// [Display]: std::fmt::Display
+    #[default]
+    Xml,
+    /// Markdown sections titled by the ids of the documents, with their metadata as a list
+    Markdown,
+    /// JSON object with the list of the documents, their metadata as fields
+    Json,
+    /// Documents rendered with a [PromptTemplate], whose variables
+    /// are the `id`, `text` and metadata fields of each document, and joined by newlines. Build
+    /// it with [DocumentFormat::template] to check the template.
+    Template(String),
+}
+
+impl DocumentFormat {
+    /// Format rendering each document with `template` (e.g.: `"[{id}] {text}"`).
+    pub fn template(template: &str) -> Result<Self, PromptTemplateError> {
+        PromptTemplate::new(template)?;
+        Ok(Self::Template(template.to_string()))
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Render `documents` in this format. Documents that cannot be rendered with a template
+    /// (e.g.: missing one of its variables) are rendered as XML instead.
+    pub fn render(&self, documents: &[Document]) -> String {
+        match self {
+            Self::Xml => format!(
+                "<attachments>\n{}</attachments>",
+                documents
+                    .iter()
+                    .map(|doc| self.render_document(doc))
+                    .collect::<String>()
+            ),
+            Self::Markdown => format!(
+                "# Documents\n\n{}",
+                documents
+                    .iter()
+                    .map(|doc| self.render_document(doc))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            Self::Json => {
+                serde_json::to_string_pretty(&serde_json::json!({ "documents": documents }))
+                    .expect("documents serialize to JSON")
+            }
+            Self::Template(_) => documents
+                .iter()
+                .map(|doc| self.render_document(doc))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Render a single document, without the markup surrounding the documents (e.g.: to count
+    /// its tokens).
+    pub(crate) fn render_document(&self, doc: &Document) -> String {
+        match self {
+            Self::Xml => doc.to_string(),
+            Self::Markdown => {
+                let mut props = doc.additional_props.iter().collect::<Vec<_>>();
+                props.sort_by(|a, b| a.0.cmp(b.0));
+                let metadata = props
+                    .iter()
+                    .map(|(key, value)| format!("- {key}: {value}\n"))
+                    .collect::<String>();
+                match metadata.is_empty() {
+                    true => format!("## Source: {}\n\n{}\n", doc.id, doc.text),
+                    false => format!("## Source: {}\n{metadata}\n{}\n", doc.id, doc.text),
+                }
+            }
+            Self::Json => serde_json::to_string_pretty(doc).expect("documents serialize to JSON"),
+            Self::Template(template) => {
+                match PromptTemplate::new(template).and_then(|template| template.render(doc)) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        tracing::warn!(target: "rig",
+                            "Failed to render document {} with its template: {e}",
+                            doc.id
+                        );
+                        doc.to_string()
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct ToolDefinition {
     pub name: String,
     pub description: String,
@@ -203,13 +512,74 @@ pub trait Completion<M: CompletionModel> {
     ) -> impl std::future::Future<Output = Result<CompletionRequestBuilder<M>, CompletionError>> + Send;
 }
 
+/// Token usage of a request to a model provider, as reported by the provider.
+/// Usages can be added together to aggregate the usage of several requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Number of tokens of the input (i.e.: prompt, or documents to embed)
+    pub input_tokens: u64,
+    /// Number of tokens generated by the model
+    pub output_tokens: u64,
+    /// Total number of tokens billed for the request
+    pub total_tokens: u64,
+}
+
+impl Usage {
+    pub fn new(input_tokens: u64, output_tokens: u64) -> Self {
+        Self {
+            input_tokens,
+            output_tokens,
+            total_tokens: input_tokens + output_tokens,
+        }
+    }
+}
+
+impl std::ops::Add for Usage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+        }
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl std::iter::Sum for Usage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, usage| acc + usage)
+    }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Input tokens: {} Output tokens: {} Total tokens: {}",
+            self.input_tokens, self.output_tokens, self.total_tokens
+        )
+    }
+}
+
 /// General completion response struct that contains the high-level completion choice
 /// and the raw response. The completion choice contains one or more assistant content.
-#[derive(Debug)]
+///
+/// Responses can be (de)serialized (e.g.: to record them and replay them later with a mock
+/// model) if their raw response can be.
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CompletionResponse<T> {
     /// The completion choice (represented by one or more assistant message content)
     /// returned by the completion model provider
     pub choice: OneOrMany<AssistantContent>,
+    /// The token usage of the request, if reported by the completion model provider
+    pub usage: Option<Usage>,
     /// The raw response returned by the completion model provider
     pub raw_response: T,
 }
@@ -221,6 +591,11 @@ pub trait CompletionModel: Clone + Send + Sync {
     /// The raw response type returned by the underlying completion model.
     type Response: Send + Sync;
 
+    /// Name of the model (e.g.: `gpt-4o`), recorded in the traces of the requests made with it.
+    fn model_name(&self) -> Option<&str> {
+        None
+    }
+
     /// Generates a completion response for the given completion request.
     fn completion(
         &self,
@@ -232,19 +607,43 @@ pub trait CompletionModel: Clone + Send + Sync {
     fn completion_request(&self, prompt: impl Into<Message>) -> CompletionRequestBuilder<Self> {
         CompletionRequestBuilder::new(self.clone(), prompt)
     }
+
+    /// Provider-specific additional parameters constraining the response to be a JSON object
+    /// matching `schema` (i.e.: native structured outputs), if the model supports them.
+    /// Used by [Extractor](crate::extractor::Extractor)s, which otherwise rely on a tool call.
+    fn structured_output(
+        &self,
+        _name: &str,
+        _schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Provider-specific additional parameters constraining the response to be a valid JSON
+    /// object, without a schema (i.e.: JSON mode), if the model supports them.
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        None
+    }
 }
 
 /// Struct representing a general completion request that can be sent to a completion model provider.
+///
+/// Requests can be (de)serialized, e.g.: to log them, replay them offline or queue them for
+/// asynchronous processing. Missing lists deserialize as empty and missing options as `None`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct CompletionRequest {
     /// The prompt to be sent to the completion model provider
     pub prompt: Message,
     /// The preamble to be sent to the completion model provider
     pub preamble: Option<String>,
     /// The chat history to be sent to the completion model provider
+    #[serde(default)]
     pub chat_history: Vec<Message>,
     /// The documents to be sent to the completion model provider
+    #[serde(default)]
     pub documents: Vec<Document>,
     /// The tools to be sent to the completion model provider
+    #[serde(default)]
     pub tools: Vec<ToolDefinition>,
     /// The temperature to be sent to the completion model provider
     pub temperature: Option<f64>,
@@ -252,6 +651,9 @@ pub struct CompletionRequest {
     pub max_tokens: Option<u64>,
     /// Additional provider-specific parameters to be sent to the completion model provider
     pub additional_params: Option<serde_json::Value>,
+    /// Format in which the documents are rendered into the prompt
+    #[serde(default, skip_serializing_if = "DocumentFormat::is_default")]
+    pub document_format: DocumentFormat,
 }
 
 impl CompletionRequest {
@@ -259,13 +661,7 @@ impl CompletionRequest {
         let mut new_prompt = self.prompt.clone();
         if let Message::User { ref mut content } = new_prompt {
             if !self.documents.is_empty() {
-                let attachments = self
-                    .documents
-                    .iter()
-                    .map(|doc| doc.to_string())
-                    .collect::<Vec<_>>()
-                    .join("");
-                let formatted_content = format!("<attachments>\n{}</attachments>", attachments);
+                let formatted_content = self.document_format.render(&self.documents);
                 let mut new_content = vec![UserContent::text(formatted_content)];
                 new_content.extend(content.clone());
                 *content = OneOrMany::many(new_content).expect("This has more than 1 item");
@@ -329,6 +725,7 @@ pub struct CompletionRequestBuilder<M: CompletionModel> {
     temperature: Option<f64>,
     max_tokens: Option<u64>,
     additional_params: Option<serde_json::Value>,
+    document_format: DocumentFormat,
 }
 
 impl<M: CompletionModel> CompletionRequestBuilder<M> {
@@ -343,6 +740,7 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
             temperature: None,
             max_tokens: None,
             additional_params: None,
+            document_format: DocumentFormat::default(),
         }
     }
 
@@ -418,6 +816,32 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
         self
     }
 
+    /// Constrains the response to be a valid JSON object (i.e.: JSON mode), if the model
+    /// supports it (see [CompletionModel::json_mode]). The prompt should still ask for JSON.
+    pub fn json_mode(self) -> Self {
+        match json_mode_params(&self.model) {
+            Some(params) => self.additional_params(params),
+            None => self,
+        }
+    }
+
+    /// Constrains the response to be a JSON object matching `schema`, named `name`, if the
+    /// model supports native structured outputs (see [CompletionModel::structured_output]).
+    /// Falls back to JSON mode otherwise.
+    pub fn output_schema(self, name: &str, schema: serde_json::Value) -> Self {
+        match output_schema_params(&self.model, name, &schema) {
+            Some(params) => self.additional_params(params),
+            None => self,
+        }
+    }
+
+    /// Constrains the response to be a JSON object matching the schema of `T`, named after
+    /// its title (see [output_schema](Self::output_schema)).
+    pub fn output_type<T: JsonSchema>(self) -> Self {
+        let (name, schema) = output_type_schema::<T>();
+        self.output_schema(&name, schema)
+    }
+
     /// Sets the temperature for the completion request.
     pub fn temperature(mut self, temperature: f64) -> Self {
         self.temperature = Some(temperature);
@@ -444,6 +868,33 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
         self
     }
 
+    /// Sets the format in which the documents are rendered into the prompt (defaults to
+    /// [DocumentFormat::Xml]).
+    pub fn document_format(mut self, document_format: DocumentFormat) -> Self {
+        self.document_format = document_format;
+        self
+    }
+
+    /// Documents of the request, e.g.: to verify the grounding of the response
+    pub(crate) fn get_documents(&self) -> &[Document] {
+        &self.documents
+    }
+
+    /// The request this builder would send, leaving the builder untouched.
+    pub(crate) fn request(&self) -> CompletionRequest {
+        CompletionRequest {
+            prompt: self.prompt.clone(),
+            preamble: self.preamble.clone(),
+            chat_history: self.chat_history.clone(),
+            documents: self.documents.clone(),
+            tools: self.tools.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            additional_params: self.additional_params.clone(),
+            document_format: self.document_format.clone(),
+        }
+    }
+
     /// Builds the completion request.
     pub fn build(self) -> CompletionRequest {
         CompletionRequest {
@@ -455,14 +906,92 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
             temperature: self.temperature,
             max_tokens: self.max_tokens,
             additional_params: self.additional_params,
+            document_format: self.document_format,
         }
     }
 
     /// Sends the completion request to the completion model provider and returns the completion response.
     pub async fn send(self) -> Result<CompletionResponse<M::Response>, CompletionError> {
         let model = self.model.clone();
-        model.completion(self.build()).await
+        let run = RunContext::current();
+        let span = tracing::info_span!(
+            target: "rig",
+            "completion",
+            gen_ai.request.model = model.model_name(),
+            rig.session_id = run.as_ref().map(RunContext::session_id),
+            rig.run_id = run.as_ref().map(RunContext::run_id),
+            gen_ai.usage.input_tokens = Empty,
+            gen_ai.usage.output_tokens = Empty,
+        );
+        let response = model
+            .completion(self.build())
+            .instrument(span.clone())
+            .await?;
+        if let Some(usage) = response.usage {
+            span.record("gen_ai.usage.input_tokens", usage.input_tokens);
+            span.record("gen_ai.usage.output_tokens", usage.output_tokens);
+        }
+        Ok(response)
+    }
+
+    /// Send the completion request, giving up on the deadline or the cancellation of `options`
+    /// (see [PromptOptions]).
+    pub async fn send_with(
+        self,
+        options: PromptOptions,
+    ) -> Result<CompletionResponse<M::Response>, CompletionError> {
+        options.interruption().guard(self.send()).await
+    }
+}
+
+/// Additional parameters enabling the JSON mode of `model`, or `None` (with a warning) if it
+/// does not support it.
+pub(crate) fn json_mode_params(model: &impl CompletionModel) -> Option<serde_json::Value> {
+    let params = model.json_mode();
+    if params.is_none() {
+        tracing::warn!(
+            target: "rig",
+            "Model {} does not support JSON mode, the response is not constrained",
+            model.model_name().unwrap_or("unknown")
+        );
     }
+    params
+}
+
+/// Additional parameters constraining the responses of `model` to match `schema`, falling back
+/// to its JSON mode (with a warning) if it does not support native structured outputs.
+pub(crate) fn output_schema_params(
+    model: &impl CompletionModel,
+    name: &str,
+    schema: &serde_json::Value,
+) -> Option<serde_json::Value> {
+    model.structured_output(name, schema).or_else(|| {
+        tracing::warn!(
+            target: "rig",
+            "Model {} does not support structured outputs with schema {name}, falling back to JSON mode",
+            model.model_name().unwrap_or("unknown")
+        );
+        json_mode_params(model)
+    })
+}
+
+/// Name and schema of the structured outputs of type `T`. The name is the title of the schema,
+/// restricted to the characters accepted by providers.
+pub(crate) fn output_type_schema<T: JsonSchema>() -> (String, serde_json::Value) {
+    let schema = serde_json::json!(schemars::schema_for!(T));
+    let name = schema
+        .get("title")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or("output")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect::<String>();
+    let name = if name.is_empty() {
+        "output".to_string()
+    } else {
+        name
+    };
+    (name, schema)
 }
 
 impl<M: StreamingCompletionModel> CompletionRequestBuilder<M> {
@@ -471,6 +1000,17 @@ impl<M: StreamingCompletionModel> CompletionRequestBuilder<M> {
         let model = self.model.clone();
         model.stream(self.build()).await
     }
+
+    /// Stream the completion request, until the deadline or the cancellation of `options` (see
+    /// [PromptOptions]), which also applies to the streamed response.
+    pub async fn stream_with(
+        self,
+        options: PromptOptions,
+    ) -> Result<StreamingResult, CompletionError> {
+        let mut interruption = options.interruption();
+        let stream = interruption.guard(self.stream()).await?;
+        Ok(interruption.guard_stream(stream))
+    }
 }
 
 #[cfg(test)]
@@ -535,6 +1075,7 @@ mod tests {
             temperature: None,
             max_tokens: None,
             additional_params: None,
+            document_format: DocumentFormat::Xml,
         };
 
         let expected = Message::User {
@@ -554,4 +1095,200 @@ mod tests {
 
         assert_eq!(request.prompt_with_context(), expected);
     }
+
+    #[test]
+    fn test_document_formats() {
+        let documents = vec![
+            Document {
+                id: "doc1".to_string(),
+                text: "Document 1 text.".to_string(),
+                additional_props: HashMap::from([("author".to_string(), "Jane".to_string())]),
+            },
+            Document {
+                id: "doc2".to_string(),
+                text: "Document 2 text.".to_string(),
+                additional_props: HashMap::new(),
+            },
+        ];
+
+        assert_eq!(
+            DocumentFormat::Markdown.render(&documents),
+            concat!(
+                "# Documents\n\n",
+                "## Source: doc1\n- author: Jane\n\nDocument 1 text.\n\n",
+                "## Source: doc2\n\nDocument 2 text.\n"
+            )
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&DocumentFormat::Json.render(&documents[..1])).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "documents": [{"id": "doc1", "text": "Document 1 text.", "author": "Jane"}]
+            })
+        );
+
+        // Documents missing a variable of the template are rendered as XML
+        let format = DocumentFormat::template("[{id}] {text} ({author})").unwrap();
+        assert_eq!(
+            format.render(&documents),
+            "[doc1] Document 1 text. (Jane)\n<file id: doc2>\nDocument 2 text.\n</file>\n"
+        );
+        assert!(DocumentFormat::template("{id").is_err());
+
+        let request = CompletionRequest {
+            prompt: "Hi".into(),
+            preamble: None,
+            chat_history: Vec::new(),
+            documents: vec![documents[1].clone()],
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            additional_params: None,
+            document_format: DocumentFormat::template("{id}: {text}").unwrap(),
+        };
+        assert_eq!(
+            request.prompt_with_context(),
+            Message::User {
+                content: OneOrMany::many(vec![
+                    UserContent::text("doc2: Document 2 text."),
+                    UserContent::text("Hi"),
+                ])
+                .unwrap(),
+            }
+        );
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json["document_format"],
+            serde_json::json!({"template": "{id}: {text}"})
+        );
+        assert_eq!(
+            serde_json::from_value::<CompletionRequest>(json).unwrap(),
+            request
+        );
+    }
+
+    #[cfg(feature = "providers")]
+    #[test]
+    fn test_response_format() {
+        #[derive(JsonSchema)]
+        #[allow(dead_code)]
+        struct Weather {
+            city: String,
+            celsius: f64,
+        }
+
+        let openai = crate::providers::openai::Client::new("key").completion_model("gpt-4o");
+        let request = openai
+            .completion_request("What is the weather in Paris?")
+            .additional_params(serde_json::json!({"seed": 42}))
+            .output_type::<Weather>()
+            .build();
+        let params = request.additional_params.unwrap();
+        assert_eq!(params["seed"], 42);
+        let format = &params["response_format"];
+        assert_eq!(format["type"], "json_schema");
+        assert_eq!(format["json_schema"]["name"], "Weather");
+        assert_eq!(format["json_schema"]["strict"], true);
+        assert_eq!(
+            format["json_schema"]["schema"]["required"],
+            serde_json::json!(["celsius", "city"])
+        );
+
+        let request = openai.completion_request("Hi").json_mode().build();
+        assert_eq!(
+            request.additional_params,
+            Some(serde_json::json!({"response_format": {"type": "json_object"}}))
+        );
+
+        // Models without structured outputs fall back to JSON mode
+        let gemini =
+            crate::providers::gemini::Client::new("key").completion_model("gemini-1.5-flash");
+        let request = gemini
+            .completion_request("Hi")
+            .output_schema("weather", serde_json::json!({"type": "object"}))
+            .build();
+        assert_eq!(
+            request.additional_params,
+            Some(serde_json::json!({"responseMimeType": "application/json"}))
+        );
+
+        // And leave the request untouched without JSON mode
+        let mock = crate::providers::mock::MockCompletionModel::new();
+        let request = mock
+            .completion_request("Hi")
+            .output_type::<Weather>()
+            .build();
+        assert_eq!(request.additional_params, None);
+    }
+
+    #[test]
+    fn test_completion_request_serde() {
+        let request = CompletionRequest {
+            prompt: "What is the weather in Paris?".into(),
+            preamble: Some("You are a weather bot.".to_string()),
+            chat_history: vec![
+                Message::user("Hi"),
+                Message::Assistant {
+                    content: OneOrMany::one(AssistantContent::tool_call(
+                        "call_1",
+                        "greet",
+                        serde_json::json!({"name": "John"}),
+                    )),
+                },
+            ],
+            documents: vec![Document {
+                id: "doc1".to_string(),
+                text: "Document 1 text.".to_string(),
+                additional_props: HashMap::from([("source".to_string(), "web".to_string())]),
+            }],
+            tools: vec![ToolDefinition {
+                name: "weather".to_string(),
+                description: "Get the weather".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            }],
+            temperature: Some(0.5),
+            max_tokens: Some(100),
+            additional_params: Some(serde_json::json!({"seed": 42})),
+            document_format: DocumentFormat::Xml,
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            serde_json::from_value::<CompletionRequest>(json).unwrap(),
+            request
+        );
+
+        // Lists and options can be omitted
+        let request: CompletionRequest = serde_json::from_value(serde_json::json!({
+            "prompt": {"role": "user", "content": [{"type": "text", "text": "Hello"}]},
+        }))
+        .unwrap();
+        assert_eq!(request.prompt, Message::user("Hello"));
+        assert!(request.chat_history.is_empty() && request.preamble.is_none());
+    }
+
+    #[test]
+    fn test_completion_response_serde() {
+        let response = CompletionResponse {
+            choice: OneOrMany::one(AssistantContent::text("Sunny")),
+            usage: Some(Usage::new(10, 2)),
+            raw_response: serde_json::json!({"id": "123"}),
+        };
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "choice": [{"text": "Sunny"}],
+                "usage": {"input_tokens": 10, "output_tokens": 2, "total_tokens": 12},
+                "raw_response": {"id": "123"},
+            })
+        );
+
+        let deserialized: CompletionResponse<serde_json::Value> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.choice, response.choice);
+        assert_eq!(deserialized.usage, response.usage);
+    }
 }
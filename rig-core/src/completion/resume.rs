@@ -0,0 +1,441 @@
+//! Resumption of the streamed completions interrupted by a disconnect.
+//!
+//! A [ResumableModel] wraps a streaming completion model: when the stream of a response fails
+//! midway (e.g.: the connection to the provider dropped), the request is sent again and the new
+//! stream picks up where the interrupted one stopped, so that the consumer of the stream (e.g.: a
+//! chat UI) doesn't see the disconnect. The interrupted response is either:
+//! - continued ([ResumeStrategy::Continue]), if the model supports prefills (see
+//!   [StreamingCompletionModel::supports_prefill]): the text streamed so far is sent as the
+//!   beginning of the assistant response, which the model completes,
+//! - or restarted ([ResumeStrategy::Restart], and the fallback of [ResumeStrategy::Continue]): the
+//!   response is generated again, skipping the part of it that was already streamed. Unless the
+//!   sampling of the model is deterministic (e.g.: with a temperature of 0), the restarted
+//!   response may not be a seamless continuation of the interrupted one.
+//!
+//! Only the interruptions of the streams are resumed: the errors starting a stream and the errors
+//! reported by the provider are returned as is.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{completion::ResumableModel, providers::anthropic, streaming::StreamingPrompt};
+//!
+//! let anthropic = anthropic::ClientBuilder::new("your-api-key").build();
+//!
+//! // Resume the interrupted streams up to 3 times, 500ms after their interruption
+//! let model = ResumableModel::new(anthropic.completion_model(anthropic::CLAUDE_3_5_SONNET))
+//!     .max_attempts(3)
+//!     .delay(Duration::from_millis(500));
+//!
+//! let agent = rig::agent::AgentBuilder::new(model).max_tokens(1024).build();
+//! let mut stream = agent.stream_prompt("Tell me a long story").await?;
+//! ```
+use std::time::Duration;
+
+use async_stream::stream;
+use futures::StreamExt;
+
+use super::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Message};
+use crate::{
+    runtime,
+    streaming::{StreamingChoice, StreamingCompletionModel, StreamingResult},
+};
+
+/// How a [ResumableModel] resumes an interrupted response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResumeStrategy {
+    /// Continue the interrupted response if the model supports prefills, else restart it
+    #[default]
+    Continue,
+    /// Generate the interrupted response again, skipping the part already streamed
+    Restart,
+}
+
+/// Streaming completion model resuming the streams interrupted by a disconnect.
+#[derive(Clone)]
+pub struct ResumableModel<M> {
+    model: M,
+    max_attempts: usize,
+    strategy: ResumeStrategy,
+    delay: Duration,
+}
+
+impl<M: StreamingCompletionModel> ResumableModel<M> {
+    /// Resume the interrupted streams of `model`, up to twice per response.
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            max_attempts: 2,
+            strategy: ResumeStrategy::default(),
+            delay: Duration::from_millis(250),
+        }
+    }
+
+    /// Set the maximum number of requests sent to resume a response (defaults to 2). Once they
+    /// are exhausted, the interruption error is returned by the stream.
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set how the interrupted responses are resumed (defaults to [ResumeStrategy::Continue]).
+    pub fn strategy(mut self, strategy: ResumeStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set the delay before each request resuming a response (defaults to 250ms).
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+impl<M: StreamingCompletionModel> CompletionModel for ResumableModel<M> {
+    type Response = M::Response;
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.model_name()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<M::Response>, CompletionError> {
+        self.model.completion(request).await
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.model.structured_output(name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        self.model.json_mode()
+    }
+}
+
+impl<M: StreamingCompletionModel + 'static> StreamingCompletionModel for ResumableModel<M> {
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingResult, CompletionError> {
+        let mut stream = self.model.stream(request.clone()).await?;
+        let this = self.clone();
+
+        Ok(Box::pin(stream! {
+            // Text and tool calls streamed so far
+            let mut text = String::new();
+            let mut tool_calls = 0;
+            let mut skip = Skip::default();
+            let mut attempts = 0;
+
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(StreamingChoice::Message(chunk)) => {
+                        let chunk = skip.text(chunk);
+                        if !chunk.is_empty() {
+                            text.push_str(&chunk);
+                            yield Ok(StreamingChoice::Message(chunk));
+                        }
+                    }
+                    Ok(tool_call @ StreamingChoice::ToolCall(..)) => {
+                        if skip.tool_calls > 0 {
+                            skip.tool_calls -= 1;
+                        } else {
+                            tool_calls += 1;
+                            yield Ok(tool_call);
+                        }
+                    }
+                    Err(error) if is_interruption(&error) && attempts < this.max_attempts => {
+                        let mut error = error;
+                        loop {
+                            attempts += 1;
+                            tracing::warn!(target: "rig",
+                                "Completion stream interrupted ({}), resuming it (attempt {}/{})",
+                                error, attempts, this.max_attempts
+                            );
+                            runtime::sleep(this.delay).await;
+
+                            let (request, resumed) = this.resume(&request, &text, tool_calls);
+                            match this.model.stream(request).await {
+                                Ok(resumed_stream) => {
+                                    stream = resumed_stream;
+                                    skip = resumed;
+                                    break;
+                                }
+                                Err(e)
+                                    if (is_interruption(&e) || e.is_rate_limited())
+                                        && attempts < this.max_attempts =>
+                                {
+                                    error = e;
+                                }
+                                Err(e) => {
+                                    yield Err(e);
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        yield Err(error);
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    fn supports_prefill(&self) -> bool {
+        self.model.supports_prefill()
+    }
+}
+
+impl<M: StreamingCompletionModel> ResumableModel<M> {
+    /// Request resuming the response to `request` after `text` and `tool_calls` were streamed,
+    /// and the part of the new stream to skip.
+    fn resume(
+        &self,
+        request: &CompletionRequest,
+        text: &str,
+        tool_calls: usize,
+    ) -> (CompletionRequest, Skip) {
+        // Prefills can't contain tool calls, and providers reject trailing whitespace in them
+        let prefill = text.trim_end();
+        let continued = self.strategy == ResumeStrategy::Continue
+            && self.model.supports_prefill()
+            && tool_calls == 0
+            && !prefill.is_empty();
+
+        if !continued {
+            let skip = Skip {
+                chars: text.chars().count(),
+                tool_calls,
+                ..Default::default()
+            };
+            return (request.clone(), skip);
+        }
+
+        let mut continuation = request.clone();
+        continuation
+            .chat_history
+            .push(request.prompt_with_context());
+        continuation.documents.clear();
+        continuation.prompt = Message::assistant(prefill);
+
+        let skip = Skip {
+            leading_whitespace: prefill.len() < text.len(),
+            ..Default::default()
+        };
+        (continuation, skip)
+    }
+}
+
+/// Part of a resumed stream that was already streamed before the interruption
+#[derive(Debug, Default)]
+struct Skip {
+    /// Characters of text to skip (restarted responses)
+    chars: usize,
+    /// Whether to skip the whitespace at the start of the text, which was trimmed from the
+    /// prefill (continued responses)
+    leading_whitespace: bool,
+    /// Tool calls to skip (restarted responses)
+    tool_calls: usize,
+}
+
+impl Skip {
+    /// Remove the skipped part of the text `chunk`
+    fn text(&mut self, chunk: String) -> String {
+        let mut chars = chunk.chars().peekable();
+        while self.chars > 0 && chars.next().is_some() {
+            self.chars -= 1;
+        }
+        if self.leading_whitespace {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_some() {
+                self.leading_whitespace = false;
+            }
+        }
+        chars.collect()
+    }
+}
+
+/// Whether `error`, returned in the middle of a stream, is an interruption of the stream (e.g.: a
+/// dropped connection or a truncated response), rather than an error reported by the provider
+fn is_interruption(error: &CompletionError) -> bool {
+    match error {
+        #[cfg(feature = "http")]
+        CompletionError::HttpError(e) => !e.is_status(),
+        CompletionError::ResponseError(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    use futures::stream;
+
+    use super::*;
+
+    type Script = Vec<Result<StreamingChoice, CompletionError>>;
+
+    /// Model streaming its scripted responses, one per request, and recording the requests
+    #[derive(Clone, Default)]
+    struct FlakyModel {
+        streams: Arc<Mutex<VecDeque<Script>>>,
+        requests: Arc<Mutex<Vec<CompletionRequest>>>,
+        prefill: bool,
+    }
+
+    impl FlakyModel {
+        fn new(prefill: bool, streams: Vec<Script>) -> Self {
+            Self {
+                streams: Arc::new(Mutex::new(streams.into())),
+                requests: Arc::default(),
+                prefill,
+            }
+        }
+    }
+
+    impl CompletionModel for FlakyModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Err(CompletionError::ProviderError(
+                "FlakyModel only streams".to_string(),
+            ))
+        }
+    }
+
+    impl StreamingCompletionModel for FlakyModel {
+        async fn stream(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<StreamingResult, CompletionError> {
+            self.requests.lock().unwrap().push(request);
+            let script = self.streams.lock().unwrap().pop_front().unwrap_or_default();
+            Ok(Box::pin(stream::iter(script)))
+        }
+
+        fn supports_prefill(&self) -> bool {
+            self.prefill
+        }
+    }
+
+    fn text(text: &str) -> Result<StreamingChoice, CompletionError> {
+        Ok(StreamingChoice::Message(text.to_string()))
+    }
+
+    fn disconnect() -> Result<StreamingChoice, CompletionError> {
+        Err(CompletionError::ResponseError("connection reset".into()))
+    }
+
+    async fn collect(model: ResumableModel<FlakyModel>) -> (Vec<String>, Option<String>) {
+        let mut stream = model.completion_request("Hi").stream().await.unwrap();
+        let mut chunks = vec![];
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => chunks.push(chunk.to_string()),
+                Err(e) => return (chunks, Some(e.to_string())),
+            }
+        }
+        (chunks, None)
+    }
+
+    #[tokio::test]
+    async fn test_continue_interrupted_stream() {
+        let model = FlakyModel::new(
+            true,
+            vec![
+                vec![text("Hello, "), disconnect()],
+                vec![text(" wo"), text("rld!")],
+            ],
+        );
+        let resumable = ResumableModel::new(model.clone()).delay(Duration::ZERO);
+
+        let (chunks, error) = collect(resumable).await;
+        assert_eq!(chunks.concat(), "Hello, world!");
+        assert_eq!(error, None);
+
+        // The text streamed so far is prefilled after the prompt
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests[1].chat_history, vec![Message::user("Hi")]);
+        assert_eq!(requests[1].prompt, Message::assistant("Hello,"));
+    }
+
+    #[tokio::test]
+    async fn test_restart_interrupted_stream() {
+        let tool_call = Ok(StreamingChoice::ToolCall(
+            "search".into(),
+            "call_1".into(),
+            serde_json::json!({"q": "rig"}),
+        ));
+        let model = FlakyModel::new(
+            false,
+            vec![
+                vec![text("Hel"), text("lo"), disconnect()],
+                vec![text("Hello the"), disconnect()],
+                vec![text("Hello there"), tool_call, text("!")],
+            ],
+        );
+        let resumable = ResumableModel::new(model.clone()).delay(Duration::ZERO);
+
+        let (chunks, error) = collect(resumable).await;
+        assert_eq!(chunks[..4], ["Hel", "lo", " the", "re"]);
+        assert!(chunks[4].starts_with("Tool call: search"));
+        assert_eq!(chunks[5], "!");
+        assert_eq!(error, None);
+        assert_eq!(
+            model.requests.lock().unwrap()[2].prompt,
+            Message::user("Hi")
+        );
+
+        // Without prefill support, the responses are restarted
+        let model = FlakyModel::new(
+            false,
+            vec![
+                vec![text("Hello, "), disconnect()],
+                vec![text("Hello, you")],
+            ],
+        );
+        let resumable = ResumableModel::new(model.clone()).delay(Duration::ZERO);
+        assert_eq!(collect(resumable).await.0.concat(), "Hello, you");
+    }
+
+    #[tokio::test]
+    async fn test_resume_errors() {
+        // The interruption is returned once the attempts are exhausted
+        let model = FlakyModel::new(
+            true,
+            vec![vec![text("Hello"), disconnect()], vec![disconnect()]],
+        );
+        let resumable = ResumableModel::new(model.clone())
+            .max_attempts(1)
+            .delay(Duration::ZERO);
+        let (chunks, error) = collect(resumable).await;
+        assert_eq!(chunks, vec!["Hello"]);
+        assert_eq!(error.unwrap(), "ResponseError: connection reset");
+        assert_eq!(model.requests.lock().unwrap().len(), 2);
+
+        // Provider errors aren't retried
+        let model = FlakyModel::new(
+            true,
+            vec![vec![
+                text("Hello"),
+                Err(CompletionError::ProviderError("overloaded".into())),
+            ]],
+        );
+        let resumable = ResumableModel::new(model.clone()).delay(Duration::ZERO);
+        let (_, error) = collect(resumable).await;
+        assert_eq!(error.unwrap(), "ProviderError: overloaded");
+        assert_eq!(model.requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,297 @@
+//! Contextual compression of the retrieved documents, keeping the context of the prompts small
+//! and focused.
+//!
+//! The chunks retrieved for a query are usually only partly relevant to it. A [Compressor]
+//! strips the parts of each document that are irrelevant to the query before the document is
+//! injected in the prompt:
+//! - a [ModelCompressor] asks a (cheap) completion model to extract the relevant sentences,
+//! - an [ExtractiveCompressor] keeps the sentences sharing terms with the query, without any
+//!   model call.
+//!
+//! An agent with a compressor
+//! ([RagConfig::compress](crate::agent::RagConfig::compress)) compresses
+//! the documents of its dynamic context, leaving out the documents with no relevant part.
+//!
+//! # Example
+//! ```rust
+//! use rig::{agent::RagConfig, compression::ModelCompressor, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .dynamic_context(10, index)
+//!     .rag(RagConfig::new().compress(ModelCompressor::new(
+//!         openai.completion_model(openai::GPT_4O_MINI),
+//!     )))
+//!     .build();
+//! ```
+use std::{collections::HashSet, future::Future};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    chunking::sentence::sentences,
+    completion::{AssistantContent, CompletionError, CompletionModel, Message},
+    vector_store::keyword::tokenize,
+};
+
+const COMPRESSOR_PREAMBLE: &str = "\
+You extract the sentences of a document that are relevant to a query, i.e.: that help answer \
+it. Copy the relevant sentences verbatim, in their order, without adding anything. If no \
+sentence is relevant, respond with NONE.";
+
+/// Answer of the model when no part of the document is relevant
+const NONE: &str = "NONE";
+
+/// Trait for compressors of the documents retrieved for a query.
+pub trait Compressor: Send + Sync {
+    /// Compress `document`, retrieved for `query`, into its parts relevant to the query. An
+    /// empty text means that no part of the document is relevant.
+    fn compress(
+        &self,
+        query: &str,
+        document: &str,
+    ) -> impl Future<Output = Result<String, CompletionError>> + Send;
+}
+
+/// [Compressor] asking a completion model to extract the sentences of the documents relevant to
+/// the query.
+pub struct ModelCompressor<M: CompletionModel> {
+    model: M,
+    preamble: String,
+}
+
+impl<M: CompletionModel> ModelCompressor<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            preamble: COMPRESSOR_PREAMBLE.to_string(),
+        }
+    }
+
+    /// Set the instructions of the model compressing the documents. The model must respond with
+    /// `NONE` when no part of a document is relevant.
+    pub fn preamble(mut self, preamble: &str) -> Self {
+        self.preamble = preamble.to_string();
+        self
+    }
+}
+
+impl<M: CompletionModel> Compressor for ModelCompressor<M> {
+    async fn compress(&self, query: &str, document: &str) -> Result<String, CompletionError> {
+        let response = self
+            .model
+            .completion_request(Message::user(format!(
+                "Query:\n{query}\n\nDocument:\n{document}"
+            )))
+            .preamble(self.preamble.clone())
+            .temperature(0.0)
+            .send()
+            .await?;
+
+        let compressed = response
+            .choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.trim()),
+                AssistantContent::ToolCall(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        match compressed.trim() {
+            NONE => Ok(String::new()),
+            compressed => Ok(compressed.to_string()),
+        }
+    }
+}
+
+/// [Compressor] keeping the sentences of the documents which contain terms of the query.
+///
+/// The terms are the lowercase words of at least 3 characters, except common English stop
+/// words (e.g.: "the", "what"). Queries without such terms leave the documents as is.
+#[derive(Debug, Clone)]
+pub struct ExtractiveCompressor {
+    min_overlap: usize,
+    max_sentences: Option<usize>,
+}
+
+impl Default for ExtractiveCompressor {
+    fn default() -> Self {
+        Self {
+            min_overlap: 1,
+            max_sentences: None,
+        }
+    }
+}
+
+impl ExtractiveCompressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the minimum number of distinct terms of the query that a sentence must contain to be
+    /// kept (defaults to 1).
+    pub fn min_overlap(mut self, min_overlap: usize) -> Self {
+        self.min_overlap = min_overlap.max(1);
+        self
+    }
+
+    /// Keep at most `max_sentences` sentences of each document, those sharing the most terms
+    /// with the query (in their order in the document).
+    pub fn max_sentences(mut self, max_sentences: usize) -> Self {
+        self.max_sentences = Some(max_sentences);
+        self
+    }
+
+    /// Relevant sentences of `document`, joined in their order
+    pub fn extract(&self, query: &str, document: &str) -> String {
+        let terms = significant_terms(query);
+        if terms.is_empty() {
+            return document.to_string();
+        }
+
+        let mut scored = sentences(document)
+            .into_iter()
+            .enumerate()
+            .map(|(i, sentence)| {
+                let overlap = significant_terms(sentence).intersection(&terms).count();
+                (i, overlap, sentence)
+            })
+            .filter(|(_, overlap, _)| *overlap >= self.min_overlap)
+            .collect::<Vec<_>>();
+        if let Some(max_sentences) = self.max_sentences {
+            scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            scored.truncate(max_sentences);
+            scored.sort_by_key(|(i, _, _)| *i);
+        }
+
+        scored
+            .into_iter()
+            .map(|(_, _, sentence)| sentence.trim())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Compressor for ExtractiveCompressor {
+    async fn compress(&self, query: &str, document: &str) -> Result<String, CompletionError> {
+        Ok(self.extract(query, document))
+    }
+}
+
+/// Common English words of at least 3 characters, which are not significant terms
+const STOP_WORDS: &[&str] = &[
+    "about", "all", "also", "and", "any", "are", "been", "but", "can", "could", "did", "does",
+    "for", "from", "had", "has", "have", "how", "into", "its", "not", "should", "than", "that",
+    "the", "their", "them", "then", "there", "these", "they", "this", "those", "was", "were",
+    "what", "when", "where", "which", "who", "why", "will", "with", "would", "you", "your",
+];
+
+/// Distinct terms of `text` of at least 3 characters, except the stop words
+fn significant_terms(text: &str) -> HashSet<String> {
+    tokenize(text)
+        .into_iter()
+        .filter(|term| term.chars().count() >= 3 && !STOP_WORDS.contains(&term.as_str()))
+        .collect()
+}
+
+/// Wrapper trait to store compressors of any type
+pub(crate) trait CompressorDyn: Send + Sync {
+    fn compress_dyn<'a>(
+        &'a self,
+        query: &'a str,
+        document: &'a str,
+    ) -> BoxFuture<'a, Result<String, CompletionError>>;
+}
+
+impl<C: Compressor> CompressorDyn for C {
+    fn compress_dyn<'a>(
+        &'a self,
+        query: &'a str,
+        document: &'a str,
+    ) -> BoxFuture<'a, Result<String, CompletionError>> {
+        Box::pin(self.compress(query, document))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockCompletionModel;
+
+    #[tokio::test]
+    async fn test_compressors() {
+        let document = "Flurbos are green. They live in caves. Glarbs are blue, unlike flurbos.";
+
+        let extractive = ExtractiveCompressor::new();
+        assert_eq!(
+            extractive.extract("What color are flurbos?", document),
+            "Flurbos are green. Glarbs are blue, unlike flurbos."
+        );
+        assert_eq!(
+            extractive
+                .max_sentences(1)
+                .extract("green flurbos", document),
+            "Flurbos are green."
+        );
+        assert_eq!(
+            ExtractiveCompressor::new().extract("Is it?", document),
+            document
+        );
+        assert_eq!(
+            ExtractiveCompressor::new()
+                .compress("zorks", document)
+                .await
+                .unwrap(),
+            ""
+        );
+
+        let model = MockCompletionModel::new()
+            .text(" Flurbos are green.\n")
+            .text("NONE");
+        let compressor = ModelCompressor::new(model.clone());
+        assert_eq!(
+            compressor
+                .compress("What color are flurbos?", document)
+                .await
+                .unwrap(),
+            "Flurbos are green."
+        );
+        assert_eq!(
+            crate::memory::message_text(&model.last_request().unwrap().prompt),
+            format!("Query:\nWhat color are flurbos?\n\nDocument:\n{document}")
+        );
+        assert_eq!(compressor.compress("zorks", document).await.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn test_compression_errors() {
+        let model = MockCompletionModel::new()
+            .error("Overloaded")
+            .tool_call("search", serde_json::json!({}));
+        let compressor = ModelCompressor::new(model.clone()).preamble("Keep the colors.");
+        assert!(matches!(
+            compressor.compress("flurbos", "Flurbos are green.").await,
+            Err(CompletionError::ProviderError(message)) if message == "Overloaded"
+        ));
+        let request = model.last_request().unwrap();
+        assert_eq!(request.preamble.as_deref(), Some("Keep the colors."));
+        assert_eq!(request.temperature, Some(0.0));
+
+        // Responses without text leave out the document
+        assert_eq!(
+            compressor
+                .compress("flurbos", "Flurbos are green.")
+                .await
+                .unwrap(),
+            ""
+        );
+
+        // Queries need the minimum number of terms of the sentences
+        let extractive = ExtractiveCompressor::new().min_overlap(2);
+        assert_eq!(
+            extractive.extract("green flurbos", "Flurbos are green. Flurbos live in caves."),
+            "Flurbos are green."
+        );
+    }
+}
@@ -2,14 +2,19 @@
 //! and batch generates the embeddings for each object when built.
 //! Only types that implement the [Embed] trait can be added to the [EmbeddingsBuilder].
 
-use std::{cmp::max, collections::HashMap};
+use std::{cmp::max, collections::VecDeque, iter::Peekable};
 
-use futures::{stream, StreamExt};
+use futures::{channel::mpsc, stream, SinkExt, StreamExt, TryFutureExt};
+use tracing::Instrument;
 
 use crate::{
+    completion::Usage,
     embeddings::{
-        embed::TextEmbedder, Embed, EmbedError, Embedding, EmbeddingError, EmbeddingModel,
+        embed::TextEmbedder, Embed, EmbedError, Embedding, EmbeddingError, EmbeddingLimits,
+        EmbeddingModel,
     },
+    tokenizer::{Estimate, TokenCounter},
+    vector_store::{InsertDocuments, VectorStoreError},
     OneOrMany,
 };
 
@@ -18,6 +23,19 @@ use crate::{
 ///
 /// Using the builder is preferred over using [EmbeddingModel::embed_text] directly as
 /// it will batch the documents in a single request to the model provider.
+/// Batches are sent concurrently, the batch size and the maximum number of concurrent
+/// requests can be tuned with [EmbeddingsBuilder::batch_size] and [EmbeddingsBuilder::concurrency].
+///
+/// The batches are sized to the [limits](EmbeddingModel::limits) of the model: a batch holds
+/// at most the maximum number of texts per request, and the total tokens of its texts fit in
+/// the maximum tokens per request. The texts longer than the maximum tokens per text are
+/// truncated (with a warning). Tokens are estimated (see [EmbeddingsBuilder::token_counter]).
+///
+/// Large builds can be inserted into a vector store as they are embedded with
+/// [EmbeddingsBuilder::build_into], and their progress can be followed with
+/// [EmbeddingsBuilder::on_progress]. By default, the build fails on the first error: with
+/// [FailurePolicy::Continue], the documents that cannot be embedded are skipped instead, and
+/// reported by [EmbeddingsBuilder::build_report].
 ///
 /// # Example
 /// ```rust
@@ -44,29 +62,139 @@ use crate::{
 ///         "1. *linlingdong* (noun): A term used by inhabitants of the sombrero galaxy to describe humans.".to_string(),
 ///         "2. *linlingdong* (noun): A rare, mystical instrument crafted by the ancient monks of the Nebulon Mountain Ranges on the planet Quarm.".to_string()
 ///     ])?
+///     .batch_size(100)
+///     .concurrency(4)
 ///     .build()
 ///     .await?;
 /// ```
 pub struct EmbeddingsBuilder<M: EmbeddingModel, T: Embed> {
     model: M,
     documents: Vec<(T, Vec<String>)>,
+    batch_size: usize,
+    concurrency: usize,
+    failure_policy: FailurePolicy,
+    on_progress: Option<Box<dyn Fn(EmbeddingProgress) + Send + Sync>>,
+    limits: EmbeddingLimits,
+    token_counter: Box<dyn TokenCounter>,
+}
+
+/// Behavior of an [EmbeddingsBuilder] when documents cannot be embedded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Fail the build on the first error
+    #[default]
+    FailFast,
+    /// Skip the documents that cannot be embedded. The texts of the failed requests are embedded
+    /// one by one, so that only the documents whose texts fail are skipped.
+    Continue,
+}
+
+/// Progress of the build of an [EmbeddingsBuilder], reported after each request to the model
+/// provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddingProgress {
+    /// Texts embedded so far
+    pub embedded_texts: usize,
+    /// Texts that could not be embedded so far
+    pub failed_texts: usize,
+    /// Texts of all the documents of the builder
+    pub total_texts: usize,
+}
+
+/// Document that could not be embedded by an [EmbeddingsBuilder]
+#[derive(Debug)]
+pub struct FailedDocument<T> {
+    /// Position of the document in the builder
+    pub index: usize,
+    pub document: T,
+    /// Error embedding (the first failed text of) the document
+    pub error: EmbeddingError,
+}
+
+/// Result of [EmbeddingsBuilder::build_into]
+#[derive(Debug)]
+pub struct InsertReport<T> {
+    /// Number of documents inserted into the store
+    pub inserted: usize,
+    /// Documents that could not be embedded (with [FailurePolicy::Continue])
+    pub failed: Vec<FailedDocument<T>>,
+    /// Total token usage of the requests (requests without reported usage are not counted)
+    pub usage: Usage,
+}
+
+/// Result of [EmbeddingsBuilder::build_report]
+#[derive(Debug)]
+pub struct EmbeddingsReport<T> {
+    /// Embedded documents, in the order they were added to the builder
+    pub embeddings: Vec<(T, OneOrMany<Embedding>)>,
+    /// Documents that could not be embedded (with [FailurePolicy::Continue])
+    pub failed: Vec<FailedDocument<T>>,
+    /// Total token usage of the requests (requests without reported usage are not counted)
+    pub usage: Usage,
 }
 
 impl<M: EmbeddingModel, T: Embed> EmbeddingsBuilder<M, T> {
     /// Create a new embedding builder with the given embedding model
     pub fn new(model: M) -> Self {
+        let limits = model.limits();
+        let batch_size = limits.max_documents.clamp(1, M::MAX_DOCUMENTS);
         Self {
             model,
             documents: vec![],
+            batch_size,
+            concurrency: max(1, 1024 / batch_size),
+            failure_policy: FailurePolicy::default(),
+            on_progress: None,
+            limits,
+            token_counter: Box::new(Estimate),
         }
     }
 
+    /// Set the maximum number of texts sent to the model provider in a single request.
+    /// Defaults to (and is capped at) the maximum number of texts per request of the model's
+    /// [limits](EmbeddingModel::limits).
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, self.limits.max_documents.min(M::MAX_DOCUMENTS));
+        self
+    }
+
+    /// Set the token counter sizing the batches to the token limits of the model (defaults to
+    /// [Estimate]), e.g.: a [Tokenizer](crate::tokenizer) of the model.
+    pub fn token_counter(mut self, counter: impl TokenCounter + 'static) -> Self {
+        self.token_counter = Box::new(counter);
+        self
+    }
+
+    /// Set the maximum number of concurrent requests sent to the model provider.
+    /// Defaults to `1024` divided by the default batch size (at least 1).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = max(1, concurrency);
+        self
+    }
+
+    /// Set the behavior of the build when documents cannot be embedded (defaults to
+    /// [FailurePolicy::FailFast]).
+    pub fn failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    /// Call `on_progress` after each request to the model provider, e.g. to log the progress of
+    /// the build or send it to a channel.
+    pub fn on_progress(
+        mut self,
+        on_progress: impl Fn(EmbeddingProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
     /// Add a document to be embedded to the builder. `document` must implement the [Embed] trait.
     pub fn document(mut self, document: T) -> Result<Self, EmbedError> {
         let mut embedder = TextEmbedder::default();
         document.embed(&mut embedder)?;
 
-        self.documents.push((document, embedder.texts));
+        self.documents.push((document, embedder.into_texts()));
 
         Ok(self)
     }
@@ -85,70 +213,379 @@ impl<M: EmbeddingModel, T: Embed> EmbeddingsBuilder<M, T> {
 impl<M: EmbeddingModel, T: Embed + Send> EmbeddingsBuilder<M, T> {
     /// Generate embeddings for all documents in the builder.
     /// Returns a vector of tuples, where the first element is the document and the second element is the embeddings (either one embedding or many).
+    /// Documents are returned in the order they were added to the builder.
+    ///
+    /// With [FailurePolicy::Continue], the documents that cannot be embedded are skipped (with a
+    /// warning): see [EmbeddingsBuilder::build_report] to get them.
     pub async fn build(self) -> Result<Vec<(T, OneOrMany<Embedding>)>, EmbeddingError> {
-        use stream::TryStreamExt;
+        Ok(self.build_with_usage().await?.0)
+    }
 
-        // Store the documents and their texts in a HashMap for easy access.
-        let mut docs = HashMap::new();
-        let mut texts = Vec::new();
+    /// Same as [EmbeddingsBuilder::build], but also returns the total token usage of the
+    /// requests sent to the model provider (requests without reported usage are not counted).
+    pub async fn build_with_usage(
+        self,
+    ) -> Result<(Vec<(T, OneOrMany<Embedding>)>, Usage), EmbeddingError> {
+        let report = self.build_report().await?;
+        for FailedDocument { index, error, .. } in &report.failed {
+            tracing::warn!(target: "rig", "Skipping document {index}, which could not be embedded: {error}");
+        }
+        Ok((report.embeddings, report.usage))
+    }
 
-        // Iterate over all documents in the builder and insert their docs and texts into the lookup stores.
-        for (i, (doc, doc_texts)) in self.documents.into_iter().enumerate() {
-            docs.insert(i, doc);
-            texts.push((i, doc_texts));
+    /// Same as [EmbeddingsBuilder::build], but also returns the documents that could not be
+    /// embedded (with [FailurePolicy::Continue]) and the total token usage.
+    pub async fn build_report(self) -> Result<EmbeddingsReport<T>, EmbeddingError> {
+        let span = tracing::info_span!(
+            target: "rig",
+            "embeddings.build",
+            gen_ai.request.model = self.model.model_name(),
+            rig.documents = self.documents.len(),
+            gen_ai.usage.input_tokens = tracing::field::Empty,
+        );
+        let result = self.embed_documents().instrument(span.clone()).await;
+        if let Ok(report) = &result {
+            span.record("gen_ai.usage.input_tokens", report.usage.input_tokens);
         }
+        result
+    }
 
-        // Compute the embeddings.
-        let mut embeddings = stream::iter(texts.into_iter())
-            // Merge the texts of each document into a single list of texts.
-            .flat_map(|(i, texts)| stream::iter(texts.into_iter().map(move |text| (i, text))))
-            // Chunk them into batches. Each batch size is at most the embedding API limit per request.
-            .chunks(M::MAX_DOCUMENTS)
-            // Generate the embeddings for each batch.
-            .map(|text| async {
-                let (ids, docs): (Vec<_>, Vec<_>) = text.into_iter().unzip();
-
-                let embeddings = self.model.embed_texts(docs).await?;
-                Ok::<_, EmbeddingError>(ids.into_iter().zip(embeddings).collect::<Vec<_>>())
-            })
-            // Parallelize the embeddings generation over 10 concurrent requests
-            .buffer_unordered(max(1, 1024 / M::MAX_DOCUMENTS))
-            // Collect the embeddings into a HashMap.
-            .try_fold(
-                HashMap::new(),
-                |mut acc: HashMap<_, OneOrMany<Embedding>>, embeddings| async move {
-                    embeddings.into_iter().for_each(|(i, embedding)| {
-                        acc.entry(i)
-                            .and_modify(|embeddings| embeddings.push(embedding.clone()))
-                            .or_insert(OneOrMany::one(embedding.clone()));
-                    });
-
-                    Ok(acc)
-                },
-            )
-            .await?;
-
-        // Merge the embeddings with their respective documents
-        Ok(docs
-            .into_iter()
-            .map(|(i, doc)| {
-                (
-                    doc,
-                    embeddings.remove(&i).expect("Document should be present"),
-                )
+    /// Generate the embeddings of the documents and insert them into `store` as they are
+    /// embedded, instead of collecting them first: the documents are inserted in the order they
+    /// were added to the builder, once all their texts are embedded, while the next batches are
+    /// being embedded. Only the embeddings not inserted yet are kept in memory.
+    ///
+    /// If an insert fails, the build stops and the documents inserted so far are kept in the
+    /// store.
+    pub async fn build_into<S: InsertDocuments<T>>(
+        self,
+        store: &mut S,
+    ) -> Result<InsertReport<T>, VectorStoreError> {
+        let span = tracing::info_span!(
+            target: "rig",
+            "embeddings.build",
+            gen_ai.request.model = self.model.model_name(),
+            rig.documents = self.documents.len(),
+            gen_ai.usage.input_tokens = tracing::field::Empty,
+        );
+        let result = self.embed_into(store).instrument(span.clone()).await;
+        if let Ok(report) = &result {
+            span.record("gen_ai.usage.input_tokens", report.usage.input_tokens);
+        }
+        result
+    }
+
+    async fn embed_into<S: InsertDocuments<T>>(
+        self,
+        store: &mut S,
+    ) -> Result<InsertReport<T>, VectorStoreError> {
+        let (docs, texts): (Vec<_>, Vec<_>) = self.documents.into_iter().unzip();
+        let counts = texts.iter().map(Vec::len).collect::<Vec<_>>();
+        let texts = texts.into_iter().flatten().collect::<Vec<_>>();
+        let total = texts.len();
+
+        // The batches are embedded concurrently, but their results are returned in order, so
+        // that the documents are completed in order.
+        let model = &self.model;
+        let policy = self.failure_policy;
+        let batches = batches(
+            texts,
+            self.batch_size,
+            &self.limits,
+            self.token_counter.as_ref(),
+        );
+        let mut results = stream::iter(batches)
+            .map(|(_, batch)| embed_batch(model, batch, policy))
+            .buffered(self.concurrency)
+            .boxed();
+
+        // The embedded documents are sent to the inserts, so that the store is written to while
+        // the next batches are embedded.
+        let (mut sender, mut receiver) = mpsc::channel(1);
+        let on_progress = &self.on_progress;
+        let embed = async move {
+            let mut docs = docs.into_iter().zip(counts).enumerate().peekable();
+            let mut embeddings = VecDeque::new();
+            let mut failed = vec![];
+            let mut total_usage = Usage::default();
+            let mut progress = EmbeddingProgress {
+                embedded_texts: 0,
+                failed_texts: 0,
+                total_texts: total,
+            };
+
+            loop {
+                let done = match results.next().await {
+                    Some(result) => {
+                        let (batch, usage) = result?;
+                        total_usage += usage;
+                        for embedding in &batch {
+                            match embedding {
+                                Ok(_) => progress.embedded_texts += 1,
+                                Err(_) => progress.failed_texts += 1,
+                            }
+                        }
+                        if let Some(on_progress) = on_progress {
+                            on_progress(progress);
+                        }
+                        embeddings.extend(batch);
+                        false
+                    }
+                    None => true,
+                };
+
+                let documents =
+                    complete_documents(&mut docs, &mut embeddings, policy, &mut failed)?;
+                if !documents.is_empty() && sender.send(documents).await.is_err() {
+                    // The inserts failed
+                    break;
+                }
+                if done {
+                    break;
+                }
+            }
+            Ok::<_, EmbeddingError>((failed, total_usage))
+        };
+
+        let insert = async {
+            let mut inserted = 0;
+            while let Some(documents) = receiver.next().await {
+                inserted += documents.len();
+                store.insert_documents(documents).await?;
+            }
+            Ok::<_, VectorStoreError>(inserted)
+        };
+
+        let ((failed, usage), inserted) =
+            futures::try_join!(embed.map_err(VectorStoreError::from), insert)?;
+        Ok(InsertReport {
+            inserted,
+            failed,
+            usage,
+        })
+    }
+
+    async fn embed_documents(self) -> Result<EmbeddingsReport<T>, EmbeddingError> {
+        let (docs, texts): (Vec<_>, Vec<_>) = self.documents.into_iter().unzip();
+
+        // Merge the texts of each document into a single list of texts, keeping track of the
+        // number of texts of each document to regroup the embeddings afterwards.
+        let counts = texts.iter().map(Vec::len).collect::<Vec<_>>();
+        let texts = texts.into_iter().flatten().collect::<Vec<_>>();
+        let total = texts.len();
+        let batches = batches(
+            texts,
+            self.batch_size,
+            &self.limits,
+            self.token_counter.as_ref(),
+        );
+
+        // Generate the embeddings for each batch, with at most `concurrency` requests in flight.
+        let model = &self.model;
+        let policy = self.failure_policy;
+        let mut results = stream::iter(batches)
+            .map(|(offset, batch)| async move {
+                let (embeddings, usage) = embed_batch(model, batch, policy).await?;
+                Ok::<_, EmbeddingError>((offset, embeddings, usage))
             })
-            .collect())
+            .buffer_unordered(self.concurrency)
+            .boxed();
+
+        let mut embeddings = (0..total).map(|_| None).collect::<Vec<_>>();
+        let mut total_usage = Usage::default();
+        let mut progress = EmbeddingProgress {
+            embedded_texts: 0,
+            failed_texts: 0,
+            total_texts: total,
+        };
+        while let Some(result) = results.next().await {
+            let (offset, batch, usage) = result?;
+            total_usage += usage;
+            for (i, embedding) in batch.into_iter().enumerate() {
+                match embedding {
+                    Ok(_) => progress.embedded_texts += 1,
+                    Err(_) => progress.failed_texts += 1,
+                }
+                embeddings[offset + i] = Some(embedding);
+            }
+            if let Some(on_progress) = &self.on_progress {
+                on_progress(progress);
+            }
+        }
+
+        // Regroup the embeddings with their respective documents
+        let mut embeddings = embeddings.into_iter().flatten();
+        let mut report = EmbeddingsReport {
+            embeddings: vec![],
+            failed: vec![],
+            usage: total_usage,
+        };
+        for (index, (document, count)) in docs.into_iter().zip(counts).enumerate() {
+            let embeddings = embeddings
+                .by_ref()
+                .take(count)
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|embeddings| {
+                    OneOrMany::many(embeddings).map_err(|_| {
+                        EmbeddingError::DocumentError("Document has no text to embed".into())
+                    })
+                });
+            match embeddings {
+                Ok(embeddings) => report.embeddings.push((document, embeddings)),
+                Err(error) if policy == FailurePolicy::FailFast => return Err(error),
+                Err(error) => report.failed.push(FailedDocument {
+                    index,
+                    document,
+                    error,
+                }),
+            }
+        }
+
+        Ok(report)
     }
 }
 
+/// Take the next documents of `docs` whose texts are all embedded in `embeddings`: the
+/// documents which could not be embedded are returned as errors with [FailurePolicy::FailFast],
+/// and added to `failed` with [FailurePolicy::Continue].
+#[allow(clippy::type_complexity)]
+fn complete_documents<T>(
+    docs: &mut Peekable<impl Iterator<Item = (usize, (T, usize))>>,
+    embeddings: &mut VecDeque<Result<Embedding, EmbeddingError>>,
+    policy: FailurePolicy,
+    failed: &mut Vec<FailedDocument<T>>,
+) -> Result<Vec<(T, OneOrMany<Embedding>)>, EmbeddingError> {
+    let mut documents = vec![];
+    while docs
+        .peek()
+        .is_some_and(|(_, (_, count))| *count <= embeddings.len())
+    {
+        let (index, (document, count)) = docs.next().expect("peeked document");
+        let result = embeddings
+            .drain(..count)
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|embeddings| {
+                OneOrMany::many(embeddings).map_err(|_| {
+                    EmbeddingError::DocumentError("Document has no text to embed".into())
+                })
+            });
+        match result {
+            Ok(embeddings) => documents.push((document, embeddings)),
+            Err(error) if policy == FailurePolicy::FailFast => return Err(error),
+            Err(error) => failed.push(FailedDocument {
+                index,
+                document,
+                error,
+            }),
+        }
+    }
+    Ok(documents)
+}
+
+/// Split `texts` into batches of up to `batch_size` texts fitting in the token limits of the
+/// model, along with the offset of their first text. The texts longer than the maximum tokens
+/// per text are truncated.
+fn batches(
+    texts: Vec<String>,
+    batch_size: usize,
+    limits: &EmbeddingLimits,
+    counter: &dyn TokenCounter,
+) -> Vec<(usize, Vec<String>)> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    let mut batch_tokens = 0;
+    let mut offset = 0;
+    for (i, mut text) in texts.into_iter().enumerate() {
+        let mut tokens = counter.count_tokens(&text);
+        if let Some(max_tokens) = limits.max_input_tokens.filter(|max| tokens > *max) {
+            tracing::warn!(target: "rig",
+                "Truncating text {i} of {tokens} tokens to the limit of {max_tokens} tokens of the embedding model"
+            );
+            let len = counter.truncate(&text, max_tokens).len();
+            text.truncate(len);
+            tokens = counter.count_tokens(&text);
+        }
+
+        let full = batch.len() >= batch_size
+            || limits
+                .max_request_tokens
+                .is_some_and(|max_tokens| batch_tokens + tokens > max_tokens);
+        if full && !batch.is_empty() {
+            let len = batch.len();
+            batches.push((offset, std::mem::take(&mut batch)));
+            offset += len;
+            batch_tokens = 0;
+        }
+        batch.push(text);
+        batch_tokens += tokens;
+    }
+    if !batch.is_empty() {
+        batches.push((offset, batch));
+    }
+    batches
+}
+
+/// Embed a batch of texts. With [FailurePolicy::Continue], the texts of a failed batch are
+/// embedded one by one, to only fail the texts that cannot be embedded.
+async fn embed_batch<M: EmbeddingModel>(
+    model: &M,
+    batch: Vec<String>,
+    policy: FailurePolicy,
+) -> Result<(Vec<Result<Embedding, EmbeddingError>>, Usage), EmbeddingError> {
+    let retry = (policy == FailurePolicy::Continue && batch.len() > 1).then(|| batch.clone());
+    let error = match embed_texts(model, batch).await {
+        Ok((embeddings, usage)) => return Ok((embeddings.into_iter().map(Ok).collect(), usage)),
+        Err(error) => error,
+    };
+
+    match (policy, retry) {
+        (FailurePolicy::FailFast, _) => Err(error),
+        (FailurePolicy::Continue, None) => Ok((vec![Err(error)], Usage::default())),
+        (FailurePolicy::Continue, Some(texts)) => {
+            let mut results = Vec::with_capacity(texts.len());
+            let mut total_usage = Usage::default();
+            for text in texts {
+                match embed_texts(model, vec![text]).await {
+                    Ok((mut embeddings, usage)) => {
+                        total_usage += usage;
+                        results.push(Ok(embeddings.remove(0)));
+                    }
+                    Err(error) => results.push(Err(error)),
+                }
+            }
+            Ok((results, total_usage))
+        }
+    }
+}
+
+/// Embed texts in a single request, checking that each text got an embedding
+async fn embed_texts<M: EmbeddingModel>(
+    model: &M,
+    texts: Vec<String>,
+) -> Result<(Vec<Embedding>, Usage), EmbeddingError> {
+    let len = texts.len();
+    let (embeddings, usage) = model.embed_texts_with_usage(texts).await?;
+    if embeddings.len() != len {
+        return Err(EmbeddingError::ResponseError(format!(
+            "Expected {len} embeddings, got {}",
+            embeddings.len()
+        )));
+    }
+    Ok((embeddings, usage.unwrap_or_default()))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        embeddings::{embed::EmbedError, embed::TextEmbedder, Embedding, EmbeddingModel},
-        Embed,
+        embeddings::{
+            embed::EmbedError, embed::TextEmbedder, Embedding, EmbeddingLimits, EmbeddingModel,
+        },
+        providers::mock::MockEmbeddingModel,
+        vector_store::{InsertDocuments, VectorStoreError},
+        Embed, OneOrMany,
     };
 
-    use super::EmbeddingsBuilder;
+    use super::{EmbeddingProgress, EmbeddingsBuilder, FailurePolicy};
 
     #[derive(Clone)]
     struct Model;
@@ -366,7 +803,7 @@ mod tests {
             .unwrap();
 
         result.sort_by(|(fake_definition_1, _), (fake_definition_2, _)| {
-            fake_definition_1.cmp(&fake_definition_2)
+            fake_definition_1.cmp(fake_definition_2)
         });
 
         assert_eq!(result.len(), 2);
@@ -384,4 +821,296 @@ mod tests {
             second_definition.1.rest()[0].document, "A fictional creature found in the distant, swampy marshlands of the planet Glibbo in the Andromeda galaxy.".to_string()
         )
     }
+
+    /// Model recording the size of each request and the maximum number of concurrent requests
+    #[derive(Clone, Default)]
+    struct RecordingModel {
+        batches: std::sync::Arc<std::sync::Mutex<Vec<usize>>>,
+        in_flight: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+        max_in_flight: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl EmbeddingModel for RecordingModel {
+        const MAX_DOCUMENTS: usize = 4;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            documents: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<crate::embeddings::Embedding>, crate::embeddings::EmbeddingError> {
+            use std::sync::atomic::Ordering;
+
+            let documents = documents.into_iter().collect::<Vec<_>>();
+            self.batches.lock().unwrap().push(documents.len());
+
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            // Later batches complete first
+            let delay = 50u64.saturating_sub(self.batches.lock().unwrap().len() as u64 * 10);
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(documents
+                .into_iter()
+                .map(|doc| Embedding {
+                    document: doc,
+                    vec: vec![0.0],
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_batch_size_and_concurrency() {
+        let model = RecordingModel::default();
+        let documents = (0..7)
+            .map(|i| vec![format!("doc{i} text0"), format!("doc{i} text1")])
+            .collect::<Vec<_>>();
+
+        let result = EmbeddingsBuilder::new(model.clone())
+            .batch_size(3)
+            .concurrency(2)
+            .documents(documents.clone())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(*model.batches.lock().unwrap(), vec![3, 3, 3, 3, 2]);
+        assert_eq!(
+            model
+                .max_in_flight
+                .load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+
+        // Documents and their embeddings keep their order
+        assert_eq!(
+            result.iter().map(|(doc, _)| doc).collect::<Vec<_>>(),
+            documents.iter().collect::<Vec<_>>()
+        );
+        for (doc, embeddings) in result {
+            assert_eq!(
+                embeddings
+                    .into_iter()
+                    .map(|embedding| embedding.document)
+                    .collect::<Vec<_>>(),
+                doc
+            );
+        }
+
+        // The batch size is capped by the model's limit
+        let model = RecordingModel::default();
+        EmbeddingsBuilder::new(model.clone())
+            .batch_size(100)
+            .documents(vec!["a", "b", "c", "d", "e"].into_iter().map(String::from))
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(*model.batches.lock().unwrap(), vec![4, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_build_model_limits() {
+        let model = MockEmbeddingModel::new(1).limits(
+            EmbeddingLimits::new(3)
+                .max_input_tokens(4)
+                .max_request_tokens(6),
+        );
+        let texts = [
+            "aaaa",
+            "bbbbbbbb",
+            &"c".repeat(40),
+            "dddd",
+            "eeee",
+            "ffff",
+            "gggg",
+        ];
+
+        let result = EmbeddingsBuilder::new(model.clone())
+            .documents(texts.iter().map(|text| text.to_string()))
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        // Batches of at most 3 texts and 6 tokens (of 4 characters), the long text being
+        // truncated to 4 tokens
+        assert_eq!(
+            model.batches(),
+            vec![
+                vec!["aaaa".to_string(), "bbbbbbbb".to_string()],
+                vec!["c".repeat(16), "dddd".to_string(), "eeee".to_string()],
+                vec!["ffff".to_string(), "gggg".to_string()],
+            ]
+        );
+        assert_eq!(result.len(), 7);
+        assert_eq!(result[2].1.first().document, "c".repeat(16));
+    }
+
+    #[tokio::test]
+    async fn test_build_with_usage() {
+        // The mock model reports one input token per word
+        let model = MockEmbeddingModel::new(1).limits(EmbeddingLimits::new(2));
+        let (result, usage) = EmbeddingsBuilder::new(model)
+            .documents(vec!["a", "b", "c", "d", "e"].into_iter().map(String::from))
+            .unwrap()
+            .build_with_usage()
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 5);
+        assert_eq!(usage, crate::completion::Usage::new(5, 0));
+    }
+
+    /// Model failing to embed the texts containing "bad", by requests of 2 texts
+    fn picky_model() -> MockEmbeddingModel {
+        MockEmbeddingModel::new(1)
+            .limits(EmbeddingLimits::new(2))
+            .fail_on("bad")
+    }
+
+    #[tokio::test]
+    async fn test_failure_policy() {
+        let documents = ["good 1", "bad", "good 2", "good 3", "also bad"].map(String::from);
+        let result = EmbeddingsBuilder::new(picky_model())
+            .documents(documents.clone())
+            .unwrap()
+            .build()
+            .await;
+        assert!(result.is_err());
+
+        let progress = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let report = EmbeddingsBuilder::new(picky_model())
+            .documents(documents)
+            .unwrap()
+            .concurrency(1)
+            .failure_policy(FailurePolicy::Continue)
+            .on_progress({
+                let progress = progress.clone();
+                move |update| progress.lock().unwrap().push(update)
+            })
+            .build_report()
+            .await
+            .unwrap();
+
+        let embedded = report
+            .embeddings
+            .iter()
+            .map(|(document, _)| document.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(embedded, vec!["good 1", "good 2", "good 3"]);
+        let failed = report
+            .failed
+            .iter()
+            .map(|failed| (failed.index, failed.document.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(failed, vec![(1, "bad"), (4, "also bad")]);
+
+        let progress = progress.lock().unwrap();
+        assert_eq!(progress.len(), 3);
+        assert_eq!(
+            progress[2],
+            EmbeddingProgress {
+                embedded_texts: 3,
+                failed_texts: 2,
+                total_texts: 5
+            }
+        );
+    }
+
+    /// Store recording the sizes of the inserts
+    #[derive(Default)]
+    struct RecordingStore {
+        documents: Vec<(Vec<String>, OneOrMany<Embedding>)>,
+        inserts: Vec<usize>,
+    }
+
+    impl InsertDocuments<Vec<String>> for RecordingStore {
+        async fn insert_documents(
+            &mut self,
+            documents: Vec<(Vec<String>, OneOrMany<Embedding>)>,
+        ) -> Result<(), VectorStoreError> {
+            self.inserts.push(documents.len());
+            self.documents.extend(documents);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_into() {
+        let model = RecordingModel::default();
+        let documents = (0..7)
+            .map(|i| vec![format!("doc{i} text0"), format!("doc{i} text1")])
+            .collect::<Vec<_>>();
+
+        let mut store = RecordingStore::default();
+        let report = EmbeddingsBuilder::new(model.clone())
+            .batch_size(3)
+            .concurrency(2)
+            .documents(documents.clone())
+            .unwrap()
+            .build_into(&mut store)
+            .await
+            .unwrap();
+
+        assert_eq!(report.inserted, 7);
+        assert!(report.failed.is_empty());
+        // Documents are inserted in order, as soon as their texts are embedded
+        assert_eq!(store.inserts, vec![1, 2, 1, 2, 1]);
+        assert_eq!(
+            store
+                .documents
+                .iter()
+                .map(|(doc, _)| doc)
+                .collect::<Vec<_>>(),
+            documents.iter().collect::<Vec<_>>()
+        );
+        for (doc, embeddings) in store.documents {
+            assert_eq!(
+                embeddings
+                    .into_iter()
+                    .map(|embedding| embedding.document)
+                    .collect::<Vec<_>>(),
+                doc
+            );
+        }
+
+        // The documents which cannot be embedded are reported, or fail the build
+        let documents = ["good 1", "bad", "good 2", "good 3", "also bad"].map(String::from);
+        let mut store = vec![];
+        let report = EmbeddingsBuilder::new(picky_model())
+            .documents(documents.clone())
+            .unwrap()
+            .failure_policy(FailurePolicy::Continue)
+            .build_into(&mut store)
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .iter()
+                .map(|(doc, _)| doc.as_str())
+                .collect::<Vec<_>>(),
+            vec!["good 1", "good 2", "good 3"]
+        );
+        assert_eq!(
+            report
+                .failed
+                .iter()
+                .map(|failed| failed.index)
+                .collect::<Vec<_>>(),
+            vec![1, 4]
+        );
+
+        let result = EmbeddingsBuilder::new(picky_model())
+            .documents(documents)
+            .unwrap()
+            .build_into(&mut vec![])
+            .await;
+        assert!(matches!(result, Err(VectorStoreError::EmbeddingError(_))));
+    }
 }
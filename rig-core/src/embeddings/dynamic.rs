@@ -0,0 +1,185 @@
+//! Type-erased embedding models, to choose the embedding model of an application at runtime.
+//!
+//! [DynEmbeddingModel] wraps any [EmbeddingModel] behind an [EmbeddingModelDyn] trait object,
+//! so that vector stores and indexes using models of different providers have the same type.
+//! See [ModelRegistry](crate::registry::ModelRegistry) to create such models from
+//! `provider:model` strings.
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+
+use super::{Embedding, EmbeddingError, EmbeddingLimits, EmbeddingModel};
+use crate::completion::Usage;
+
+/// Embeddings of texts, along with the token usage of the request if the provider reports it
+pub type EmbeddingsWithUsage = (Vec<Embedding>, Option<Usage>);
+
+/// Wrapper trait to allow for dynamic dispatch of embedding models
+pub trait EmbeddingModelDyn: Send + Sync {
+    /// The maximum number of documents that can be embedded in a single request.
+    fn max_documents(&self) -> usize;
+
+    fn limits(&self) -> EmbeddingLimits;
+
+    fn ndims(&self) -> usize;
+
+    fn model_name(&self) -> Option<&str>;
+
+    fn embed_texts(
+        &self,
+        texts: Vec<String>,
+    ) -> BoxFuture<'_, Result<EmbeddingsWithUsage, EmbeddingError>>;
+}
+
+impl<M: EmbeddingModel> EmbeddingModelDyn for M {
+    fn max_documents(&self) -> usize {
+        M::MAX_DOCUMENTS
+    }
+
+    fn limits(&self) -> EmbeddingLimits {
+        <Self as EmbeddingModel>::limits(self)
+    }
+
+    fn ndims(&self) -> usize {
+        <Self as EmbeddingModel>::ndims(self)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        <Self as EmbeddingModel>::model_name(self)
+    }
+
+    fn embed_texts(
+        &self,
+        texts: Vec<String>,
+    ) -> BoxFuture<'_, Result<EmbeddingsWithUsage, EmbeddingError>> {
+        Box::pin(self.embed_texts_with_usage(texts))
+    }
+}
+
+/// [EmbeddingModel] delegating to an embedding model chosen at runtime.
+///
+/// Since the maximum number of documents per request of the wrapped model is only known at
+/// runtime, texts are embedded in as many requests to the wrapped model as needed.
+#[derive(Clone)]
+pub struct DynEmbeddingModel(Arc<dyn EmbeddingModelDyn>);
+
+impl DynEmbeddingModel {
+    pub fn new(model: impl EmbeddingModel + 'static) -> Self {
+        Self(Arc::new(model))
+    }
+}
+
+impl EmbeddingModel for DynEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    fn ndims(&self) -> usize {
+        self.0.ndims()
+    }
+
+    fn limits(&self) -> EmbeddingLimits {
+        self.0.limits()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.0.model_name()
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        Ok(self.embed_texts_with_usage(texts).await?.0)
+    }
+
+    async fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<(Vec<Embedding>, Option<Usage>), EmbeddingError> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+        let mut embeddings = Vec::with_capacity(texts.len());
+        let mut usage: Option<Usage> = None;
+        for chunk in texts.chunks(self.0.max_documents().max(1)) {
+            let (chunk_embeddings, chunk_usage) = self.0.embed_texts(chunk.to_vec()).await?;
+            embeddings.extend(chunk_embeddings);
+            if let Some(chunk_usage) = chunk_usage {
+                *usage.get_or_insert_with(Usage::default) += chunk_usage;
+            }
+        }
+        Ok((embeddings, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{DynEmbeddingModel, Embedding, EmbeddingError, EmbeddingModel};
+    use crate::completion::Usage;
+
+    /// Model embedding at most 2 texts per request, failing on texts containing "error" and
+    /// recording the size of its requests
+    #[derive(Clone, Default)]
+    struct BatchModel(Arc<Mutex<Vec<usize>>>);
+
+    impl EmbeddingModel for BatchModel {
+        const MAX_DOCUMENTS: usize = 2;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(self.embed_texts_with_usage(texts).await?.0)
+        }
+
+        async fn embed_texts_with_usage(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<(Vec<Embedding>, Option<Usage>), EmbeddingError> {
+            let texts = texts.into_iter().collect::<Vec<_>>();
+            self.0.lock().unwrap().push(texts.len());
+            if let Some(text) = texts.iter().find(|text| text.contains("error")) {
+                return Err(EmbeddingError::ProviderError(format!(
+                    "Invalid text: {text}"
+                )));
+            }
+            let usage = Usage::new(texts.len() as u64, 0);
+            let embeddings = texts
+                .into_iter()
+                .map(|text| Embedding {
+                    vec: vec![text.len() as f64],
+                    document: text,
+                })
+                .collect();
+            Ok((embeddings, Some(usage)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dyn_embedding_model() {
+        let batches = BatchModel::default();
+        let model = DynEmbeddingModel::new(batches.clone());
+        let texts = ["a", "bb", "ccc"].map(String::from);
+
+        let (embeddings, usage) = model.embed_texts_with_usage(texts).await.unwrap();
+        assert_eq!(
+            embeddings.iter().map(|e| e.vec[0]).collect::<Vec<_>>(),
+            vec![1.0, 2.0, 3.0]
+        );
+        assert_eq!(usage, Some(Usage::new(3, 0)));
+        assert_eq!(*batches.0.lock().unwrap(), vec![2, 1]);
+        assert!(model.embed_texts(vec![]).await.unwrap().is_empty());
+
+        // A failing request fails the whole embedding, and no later request is sent
+        batches.0.lock().unwrap().clear();
+        let texts = ["a", "an error", "b", "c"].map(String::from);
+        assert!(matches!(
+            model.embed_texts(texts).await,
+            Err(EmbeddingError::ProviderError(message)) if message == "Invalid text: an error"
+        ));
+        assert_eq!(*batches.0.lock().unwrap(), vec![2]);
+    }
+}
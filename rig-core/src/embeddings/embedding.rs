@@ -1,16 +1,19 @@
 //! The module defines the [EmbeddingModel] trait, which represents an embedding model that can
 //! generate embeddings for documents.
 //!
-//! The module also defines the [Embedding] struct, which represents a single document embedding.
+//! The module also re-exports the [Embedding] struct, which represents a single document embedding.
 //!
 //! Finally, the module defines the [EmbeddingError] enum, which represents various errors that
 //! can occur during embedding generation or processing.
 
-use serde::{Deserialize, Serialize};
+pub use rig_types::embedding::Embedding;
+
+use crate::completion::Usage;
 
 #[derive(Debug, thiserror::Error)]
 pub enum EmbeddingError {
     /// Http error (e.g.: connection error, timeout, etc.)
+    #[cfg(feature = "http")]
     #[error("HttpError: {0}")]
     HttpError(#[from] reqwest::Error),
 
@@ -31,6 +34,21 @@ pub enum EmbeddingError {
     ProviderError(String),
 }
 
+impl EmbeddingError {
+    /// Whether the request was rejected because of a rate limit or an overloaded provider (e.g.:
+    /// a `429 Too Many Requests` response), in which case it can be retried later.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            #[cfg(feature = "http")]
+            EmbeddingError::HttpError(e) => crate::completion::request::is_rate_limit_status(e),
+            EmbeddingError::ProviderError(message) => {
+                crate::completion::request::is_rate_limit_message(message)
+            }
+            _ => false,
+        }
+    }
+}
+
 /// Trait for embedding models that can generate embeddings for documents.
 pub trait EmbeddingModel: Clone + Sync + Send {
     /// The maximum number of documents that can be embedded in a single request.
@@ -39,12 +57,35 @@ pub trait EmbeddingModel: Clone + Sync + Send {
     /// The number of dimensions in the embedding vector.
     fn ndims(&self) -> usize;
 
+    /// Limits of the requests to the model, by which the
+    /// [EmbeddingsBuilder](crate::embeddings::EmbeddingsBuilder) splits its texts into batches.
+    /// Defaults to [MAX_DOCUMENTS](Self::MAX_DOCUMENTS) texts per request, without token limits.
+    fn limits(&self) -> EmbeddingLimits {
+        EmbeddingLimits::new(Self::MAX_DOCUMENTS)
+    }
+
+    /// Name of the model (e.g.: `text-embedding-3-small`), recorded in the traces of the
+    /// requests made with it.
+    fn model_name(&self) -> Option<&str> {
+        None
+    }
+
     /// Embed multiple text documents in a single request
     fn embed_texts(
         &self,
         texts: impl IntoIterator<Item = String> + Send,
     ) -> impl std::future::Future<Output = Result<Vec<Embedding>, EmbeddingError>> + Send;
 
+    /// Embed multiple text documents in a single request, along with the token usage of the
+    /// request if the provider reports it (by default, it isn't).
+    fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> impl std::future::Future<Output = Result<(Vec<Embedding>, Option<Usage>), EmbeddingError>> + Send
+    {
+        async { Ok((self.embed_texts(texts).await?, None)) }
+    }
+
     /// Embed a single text document.
     fn embed_text(
         &self,
@@ -60,19 +101,36 @@ pub trait EmbeddingModel: Clone + Sync + Send {
     }
 }
 
-/// Struct that holds a single document and its embedding.
-#[derive(Clone, Default, Deserialize, Serialize, Debug)]
-pub struct Embedding {
-    /// The document that was embedded. Used for debugging.
-    pub document: String,
-    /// The embedding vector
-    pub vec: Vec<f64>,
+/// Limits of the requests to an [EmbeddingModel], as documented by its provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddingLimits {
+    /// Maximum number of texts embedded in a single request
+    pub max_documents: usize,
+    /// Maximum number of tokens of each text, if limited
+    pub max_input_tokens: Option<usize>,
+    /// Maximum total number of tokens of the texts of a request, if limited
+    pub max_request_tokens: Option<usize>,
 }
 
-impl PartialEq for Embedding {
-    fn eq(&self, other: &Self) -> bool {
-        self.document == other.document
+impl EmbeddingLimits {
+    /// Limits of `max_documents` texts per request, without token limits
+    pub fn new(max_documents: usize) -> Self {
+        Self {
+            max_documents: max_documents.max(1),
+            max_input_tokens: None,
+            max_request_tokens: None,
+        }
     }
-}
 
-impl Eq for Embedding {}
+    /// Set the maximum number of tokens of each text.
+    pub fn max_input_tokens(mut self, tokens: usize) -> Self {
+        self.max_input_tokens = Some(tokens);
+        self
+    }
+
+    /// Set the maximum total number of tokens of the texts of a request.
+    pub fn max_request_tokens(mut self, tokens: usize) -> Self {
+        self.max_request_tokens = Some(tokens);
+        self
+    }
+}
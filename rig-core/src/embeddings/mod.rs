@@ -4,12 +4,13 @@
 //! and document similarity.
 
 pub mod builder;
-pub mod embed;
+pub mod dynamic;
 pub mod embedding;
 pub mod tool;
 
-pub mod distance;
-pub use builder::EmbeddingsBuilder;
+pub use builder::{EmbeddingsBuilder, FailurePolicy};
+pub use dynamic::{DynEmbeddingModel, EmbeddingModelDyn};
 pub use embed::{to_texts, Embed, EmbedError, TextEmbedder};
-pub use embedding::{Embedding, EmbeddingError, EmbeddingModel};
+pub use embedding::{Embedding, EmbeddingError, EmbeddingLimits, EmbeddingModel};
+pub use rig_types::{distance, embed};
 pub use tool::ToolSchema;
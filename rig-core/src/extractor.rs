@@ -27,19 +27,81 @@
 //!     .await
 //!     .expect("Failed to extract data from text");
 //! ```
+//!
+//! The data submitted by the model is validated against the JSON schema of the target
+//! structure. If it is invalid, the model is re-prompted with the validation errors, up to
+//! [ExtractorBuilder::retries] times.
+//!
+//! To update data that was already extracted, [Extractor::patch] asks the model for a JSON merge
+//! patch of the existing data instead of the whole structure, which is cheaper for large
+//! structures and leaves the unchanged fields untouched:
+//! ```
+//! let person = extractor.patch(&person, "John Doe is now a surgeon.").await?;
+//! ```
+//!
+//! [Extractor::extract_with_confidence] also asks the model for its confidence in each extracted
+//! field, so that uncertain extractions can be reviewed:
+//! ```
+//! let person = extractor.extract_with_confidence("John Doe is around 30.").await?;
+//! if !person.is_confident(0.8) {
+//!     send_to_review(person.data, person.low_confidence_fields(0.8));
+//! }
+//! ```
+//!
+//! For complex structures, extraction accuracy can be improved by giving the model a few
+//! examples of valid data, either provided with [ExtractorBuilder::examples] or generated by the
+//! model itself with [ExtractorBuilder::synthesize_examples]:
+//! ```
+//! let extractor = openai.extractor::<Invoice>(openai::GPT_4O)
+//!     .synthesize_examples(3)
+//!     .await?
+//!     .build();
+//! ```
+//!
+//! Extractors of lists (i.e.: `Extractor<M, Vec<T>>`) with a streaming model can also emit each
+//! element as soon as it is complete with [Extractor::extract_stream]:
+//! ```
+//! let extractor = openai.extractor::<Vec<LineItem>>(openai::GPT_4O).build();
+//!
+//! let mut items = extractor.extract_stream(&invoice).await?;
+//! while let Some(item) = items.next().await {
+//!     println!("{:?}", item?);
+//! }
+//! ```
 
-use std::marker::PhantomData;
+use std::{
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    pin::Pin,
+};
 
+use async_stream::stream;
+use futures::{Stream, StreamExt};
 use schemars::{schema_for, JsonSchema};
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
 
 use crate::{
     agent::{Agent, AgentBuilder},
-    completion::{CompletionModel, Prompt, PromptError, ToolDefinition},
-    tool::Tool,
+    completion::{AssistantContent, CompletionModel, Message, PromptError, ToolDefinition},
+    message::{ToolResultContent, UserContent},
+    streaming::{StreamingChoice, StreamingCompletionModel},
+    tool::{output::validate, Tool},
+    OneOrMany,
 };
 
+/// Default number of times the model is re-prompted when the extracted data is invalid.
+pub const DEFAULT_RETRIES: usize = 2;
+
+const SUBMIT_TOOL_NAME: &str = "submit";
+
+/// Name of the function called by the model to submit data with confidence scores
+/// (see [Extractor::extract_with_confidence]).
+pub const CONFIDENCE_TOOL_NAME: &str = "submit_with_confidence";
+
+/// Name of the function called by the model to submit a merge patch (see [Extractor::patch]).
+pub const PATCH_TOOL_NAME: &str = "submit_patch";
+
 #[derive(Debug, thiserror::Error)]
 pub enum ExtractionError {
     #[error("No data extracted")]
@@ -50,11 +112,33 @@ pub enum ExtractionError {
 
     #[error("PromptError: {0}")]
     PromptError(#[from] PromptError),
+
+    #[error("Extracted data is invalid after {} attempt(s): {}", attempts.len(), last_errors(attempts))]
+    ValidationError { attempts: Vec<ExtractionAttempt> },
+}
+
+/// Extraction attempt whose data failed validation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractionAttempt {
+    /// The data submitted by the model (or its text response, if it did not submit any data)
+    pub data: Value,
+    /// The validation errors of the data
+    pub errors: Vec<String>,
+}
+
+fn last_errors(attempts: &[ExtractionAttempt]) -> String {
+    attempts
+        .last()
+        .map(|attempt| attempt.errors.join("; "))
+        .unwrap_or_default()
 }
 
 /// Extractor for structured data from text
 pub struct Extractor<M: CompletionModel, T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync> {
     agent: Agent<M>,
+    retries: usize,
+    /// Provider parameters of native structured outputs, used instead of the submit tool
+    structured_output: Option<Value>,
     _t: PhantomData<T>,
 }
 
@@ -63,22 +147,498 @@ where
     M: Sync,
 {
     pub async fn extract(&self, text: &str) -> Result<T, ExtractionError> {
-        let summary = self.agent.prompt(text).await?;
+        self.run(Message::user(text), Submission::Data).await
+    }
+
+    /// Update `current` with the information of `text`: instead of re-extracting the whole
+    /// structure, the model submits a JSON merge patch (RFC 7396) containing only the changed
+    /// fields, which is applied to `current` and validated like extracted data.
+    pub async fn patch(&self, current: &T, text: &str) -> Result<T, ExtractionError>
+    where
+        T: Serialize,
+    {
+        let current = serde_json::to_value(current)?;
+        let prompt = format!(
+            "Current data:\n{}\n\n\
+            Update the current data with the information of the text below. \
+            Call the `{PATCH_TOOL_NAME}` function with a JSON merge patch containing only the fields that change \
+            (set a field to null to remove it). Submit an empty patch if nothing changes.\n\n\
+            Text:\n{text}",
+            serde_json::to_string_pretty(&current)?
+        );
+
+        self.run(Message::user(prompt), Submission::Patch(current))
+            .await
+    }
+
+    /// Extract structured data from `text` along with the model's confidence in each of its
+    /// (top-level) fields and a justification, e.g.: to route uncertain extractions to a
+    /// human review (see [WithConfidence::low_confidence_fields]).
+    pub async fn extract_with_confidence(
+        &self,
+        text: &str,
+    ) -> Result<WithConfidence<T>, ExtractionError> {
+        let prompt = format!(
+            "Extract the data from the text below and call the `{CONFIDENCE_TOOL_NAME}` function with the data and, \
+            for each of its fields, your confidence in the extracted value (from 0 to 1) and a short justification.\n\n\
+            Text:\n{text}"
+        );
+
+        self.run(Message::user(prompt), Submission::WithConfidence)
+            .await
+    }
+
+    /// Prompt the model until it submits valid data, re-prompting it with the validation errors.
+    async fn run<R: DeserializeOwned>(
+        &self,
+        mut prompt: Message,
+        submission: Submission,
+    ) -> Result<R, ExtractionError> {
+        let schema = match submission {
+            Submission::WithConfidence => json!(schema_for!(WithConfidence<T>)),
+            _ => json!(schema_for!(T)),
+        };
+        let tool = match submission {
+            Submission::Data => None,
+            Submission::Patch(_) => Some(patch_tool_definition()),
+            Submission::WithConfidence => Some(ToolDefinition {
+                name: CONFIDENCE_TOOL_NAME.to_string(),
+                description: "Submit the structured data you extracted from the provided text, with your confidence in each of its fields.".to_string(),
+                parameters: schema.clone(),
+            }),
+        };
+        let structured_output = match submission {
+            Submission::Data => self.structured_output.clone(),
+            _ => None,
+        };
+        let (resubmit, disabled_tools) = match (&tool, &structured_output) {
+            (Some(tool), _) => (
+                format!("call the `{}` function again", tool.name),
+                HashSet::from([SUBMIT_TOOL_NAME.to_string()]),
+            ),
+            (None, Some(_)) => (
+                "submit the data again".to_string(),
+                HashSet::from([SUBMIT_TOOL_NAME.to_string()]),
+            ),
+            (None, None) => (
+                format!("call the `{SUBMIT_TOOL_NAME}` function again"),
+                HashSet::new(),
+            ),
+        };
+        let mut chat_history = vec![];
+        let mut attempts = vec![];
+
+        for _ in 0..=self.retries {
+            let mut request = self
+                .agent
+                .completion_with(prompt.clone(), chat_history.clone(), &disabled_tools)
+                .await
+                .map_err(PromptError::from)?;
+            if let Some(tool) = &tool {
+                request = request.tool(tool.clone());
+            }
+            if let Some(params) = &structured_output {
+                request = request.additional_params(params.clone());
+            }
+            let response = request.send().await.map_err(PromptError::from)?;
+            if let Some(usage) = response.usage {
+                self.agent.add_usage(usage);
+            }
+
+            let (data, tool_call_id) = match response.choice.first() {
+                AssistantContent::ToolCall(tool_call) => {
+                    (tool_call.function.arguments, Some(tool_call.id))
+                }
+                AssistantContent::Text(text) if text.text.is_empty() => {
+                    return Err(ExtractionError::NoData)
+                }
+                AssistantContent::Text(text) => (
+                    serde_json::from_str(&text.text).unwrap_or(Value::String(text.text)),
+                    None,
+                ),
+            };
+
+            let candidate = match &submission {
+                Submission::Patch(base) => {
+                    let mut candidate = base.clone();
+                    merge_patch(&mut candidate, &data);
+                    candidate
+                }
+                // Strict structured outputs make optional fields nullable instead
+                _ if structured_output.is_some() => remove_nulls(data.clone()),
+                _ => data.clone(),
+            };
+
+            let mut errors = validate(&schema, &candidate);
+            if let Submission::WithConfidence = submission {
+                errors.extend(missing_confidences(&json!(schema_for!(T)), &candidate));
+            }
+            if errors.is_empty() {
+                match serde_json::from_value(candidate) {
+                    Ok(data) => return Ok(data),
+                    Err(e) => errors.push(e.to_string()),
+                }
+            }
+
+            // Send the errors back to the model, as the result of its tool call if it made one
+            let feedback = format!(
+                "The submitted data is invalid:\n- {}\nFix these errors and {resubmit}.",
+                errors.join("\n- ")
+            );
+            chat_history.push(prompt);
+            chat_history.push(Message::Assistant {
+                content: response.choice,
+            });
+            prompt = match tool_call_id {
+                Some(id) => Message::User {
+                    content: OneOrMany::one(UserContent::tool_result(
+                        id,
+                        OneOrMany::one(ToolResultContent::text(feedback)),
+                    )),
+                },
+                None => Message::user(feedback),
+            };
+            attempts.push(ExtractionAttempt { data, errors });
+        }
+
+        Err(ExtractionError::ValidationError { attempts })
+    }
+}
+
+/// What the model is asked to submit
+enum Submission {
+    /// The data itself
+    Data,
+    /// A merge patch of the given data
+    Patch(Value),
+    /// The data with the confidence in each of its fields
+    WithConfidence,
+}
+
+/// Confidence of the model in an extracted field.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct FieldConfidence {
+    /// Confidence in the extracted value, from 0 (guess) to 1 (certain)
+    #[schemars(range(min = 0, max = 1))]
+    pub confidence: f64,
+    /// Short justification of the extracted value (e.g.: the part of the text it comes from)
+    pub justification: String,
+}
+
+/// Extracted data with the model's confidence in each of its fields,
+/// returned by [Extractor::extract_with_confidence].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct WithConfidence<T> {
+    /// The extracted data
+    pub data: T,
+    /// Confidence in each field of the data, by field name
+    pub fields: HashMap<String, FieldConfidence>,
+}
+
+impl<T> WithConfidence<T> {
+    /// Names of the fields whose confidence is below `threshold`, sorted.
+    pub fn low_confidence_fields(&self, threshold: f64) -> Vec<&str> {
+        let mut fields = self
+            .fields
+            .iter()
+            .filter(|(_, field)| field.confidence < threshold)
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        fields.sort();
+        fields
+    }
+
+    /// Whether the confidence of every field is at least `threshold`.
+    pub fn is_confident(&self, threshold: f64) -> bool {
+        self.low_confidence_fields(threshold).is_empty()
+    }
+}
+
+/// Validation errors for the fields of the data that have no confidence
+fn missing_confidences(schema: &Value, candidate: &Value) -> Vec<String> {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return vec![];
+    };
+    let data = candidate.get("data").and_then(Value::as_object);
+    let fields = candidate.get("fields").and_then(Value::as_object);
+
+    let mut errors = properties
+        .keys()
+        .filter(|name| data.is_some_and(|data| data.contains_key(*name)))
+        .filter(|name| !fields.is_some_and(|fields| fields.contains_key(*name)))
+        .map(|name| format!("$.fields: missing confidence of field `{name}`"))
+        .collect::<Vec<_>>();
+    errors.sort();
+    errors
+}
 
-        if summary.is_empty() {
-            return Err(ExtractionError::NoData);
+/// Apply a JSON merge patch (RFC 7396) to `target`: members of the patch replace those of the
+/// target, objects are merged recursively and `null` members are removed.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
         }
+    }
+}
+
+/// Stream of the elements extracted by [Extractor::extract_stream].
+#[cfg(not(target_arch = "wasm32"))]
+pub type ExtractionStream<T> = Pin<Box<dyn Stream<Item = Result<T, ExtractionError>> + Send>>;
+
+#[cfg(target_arch = "wasm32")]
+pub type ExtractionStream<T> = Pin<Box<dyn Stream<Item = Result<T, ExtractionError>>>>;
 
-        Ok(serde_json::from_str(&summary)?)
+impl<T, M> Extractor<M, Vec<T>>
+where
+    T: JsonSchema + DeserializeOwned + Send + Sync + 'static,
+    M: StreamingCompletionModel + Sync,
+{
+    /// Extract a list from `text`, emitting each element as soon as it is streamed by the model.
+    ///
+    /// Each element is validated against the schema of `T`: invalid elements are emitted as
+    /// errors without interrupting the stream. Elements are not retried.
+    pub async fn extract_stream(&self, text: &str) -> Result<ExtractionStream<T>, ExtractionError> {
+        let schema = json!(schema_for!(T));
+        let prompt = format!(
+            "Do not call any function. Instead, answer with a JSON array of the items extracted \
+            from the text below, and nothing else. Each item must match this JSON schema:\n{}\n\n\
+            Text:\n{text}",
+            serde_json::to_string(&schema)?
+        );
+
+        let mut response = self
+            .agent
+            .completion_with(
+                Message::user(prompt),
+                vec![],
+                &HashSet::from([SUBMIT_TOOL_NAME.to_string()]),
+            )
+            .await
+            .map_err(PromptError::from)?
+            .stream()
+            .await
+            .map_err(PromptError::from)?;
+
+        Ok(Box::pin(stream! {
+            let mut parser = JsonArrayParser::default();
+            let mut extracted = false;
+
+            while let Some(chunk) = response.next().await {
+                let elements = match chunk {
+                    Ok(StreamingChoice::Message(text)) => parser.feed(&text),
+                    // The model submitted the whole list at once
+                    Ok(StreamingChoice::ToolCall(_, _, arguments)) => {
+                        parser.feed(&arguments.to_string())
+                    }
+                    Err(e) => {
+                        yield Err(PromptError::from(e).into());
+                        return;
+                    }
+                };
+
+                for element in elements {
+                    extracted = true;
+                    yield validate_element(&schema, &element);
+                }
+            }
+
+            if !extracted && !parser.started {
+                yield Err(ExtractionError::NoData);
+            }
+        }))
     }
 }
 
+fn validate_element<T: DeserializeOwned>(
+    schema: &Value,
+    element: &str,
+) -> Result<T, ExtractionError> {
+    let data = serde_json::from_str::<Value>(element)?;
+
+    let mut errors = validate(schema, &data);
+    if errors.is_empty() {
+        match serde_json::from_value(data.clone()) {
+            Ok(data) => return Ok(data),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    Err(ExtractionError::ValidationError {
+        attempts: vec![ExtractionAttempt { data, errors }],
+    })
+}
+
+/// Incremental parser splitting a streamed JSON array into its top-level elements.
+/// Anything before the opening bracket (e.g.: a markdown code fence) is ignored.
+#[derive(Default)]
+struct JsonArrayParser {
+    /// Text of the element being parsed
+    current: String,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    started: bool,
+    done: bool,
+}
+
+impl JsonArrayParser {
+    /// Feed the next chunk of text and return the elements completed by it
+    fn feed(&mut self, chunk: &str) -> Vec<String> {
+        let mut elements = vec![];
+
+        for c in chunk.chars() {
+            if self.done {
+                break;
+            }
+            if !self.started {
+                self.started = c == '[';
+                continue;
+            }
+
+            if self.in_string {
+                self.current.push(c);
+                if self.escaped {
+                    self.escaped = false;
+                } else if c == '\\' {
+                    self.escaped = true;
+                } else if c == '"' {
+                    self.in_string = false;
+                    if self.depth == 0 {
+                        elements.extend(self.take());
+                    }
+                }
+                continue;
+            }
+
+            match c {
+                '"' => {
+                    self.in_string = true;
+                    self.current.push(c);
+                }
+                '{' | '[' => {
+                    self.depth += 1;
+                    self.current.push(c);
+                }
+                '}' | ']' if self.depth > 0 => {
+                    self.depth -= 1;
+                    self.current.push(c);
+                    if self.depth == 0 {
+                        elements.extend(self.take());
+                    }
+                }
+                // End of the array
+                ']' => {
+                    elements.extend(self.take());
+                    self.done = true;
+                }
+                // End of a scalar element
+                ',' if self.depth == 0 => elements.extend(self.take()),
+                c => self.current.push(c),
+            }
+        }
+
+        elements
+    }
+
+    fn take(&mut self) -> Option<String> {
+        let element = std::mem::take(&mut self.current);
+        let element = element.trim();
+        (!element.is_empty()).then(|| element.to_string())
+    }
+}
+
+/// Name of the function called by the model to submit synthesized examples.
+const EXAMPLES_TOOL_NAME: &str = "submit_examples";
+
+/// Parameters of the examples function, only used for its schema
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct Examples<T> {
+    examples: Vec<T>,
+}
+
+/// Generate up to `count` example instances of `T` with `model`, e.g.: to be used as few-shot
+/// examples by an extractor (see [ExtractorBuilder::examples]).
+/// Examples that do not match the schema of `T` are discarded.
+pub async fn synthesize_examples<T, M>(model: &M, count: usize) -> Result<Vec<T>, ExtractionError>
+where
+    T: JsonSchema + DeserializeOwned,
+    M: CompletionModel,
+{
+    let schema = json!(schema_for!(T));
+    let response = model
+        .completion_request(Message::user(format!(
+            "Generate {count} realistic and diverse examples of data matching the schema of the \
+            `{EXAMPLES_TOOL_NAME}` function, filling out every field, and submit them by calling the function."
+        )))
+        .tool(ToolDefinition {
+            name: EXAMPLES_TOOL_NAME.to_string(),
+            description: "Submit the generated examples.".to_string(),
+            parameters: json!(schema_for!(Examples<T>)),
+        })
+        .send()
+        .await
+        .map_err(PromptError::from)?;
+
+    let submitted = match response.choice.first() {
+        AssistantContent::ToolCall(tool_call) => tool_call.function.arguments,
+        AssistantContent::Text(text) => serde_json::from_str(&text.text).unwrap_or_default(),
+    };
+    let submitted = match submitted {
+        Value::Object(mut object) => match object.remove("examples") {
+            Some(Value::Array(examples)) => examples,
+            _ => vec![],
+        },
+        Value::Array(examples) => examples,
+        _ => vec![],
+    };
+    if submitted.is_empty() {
+        return Err(ExtractionError::NoData);
+    }
+
+    let mut examples = vec![];
+    let mut attempts = vec![];
+    for data in submitted.into_iter().take(count) {
+        let mut errors = validate(&schema, &data);
+        if errors.is_empty() {
+            match serde_json::from_value(data.clone()) {
+                Ok(example) => {
+                    examples.push(example);
+                    continue;
+                }
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        attempts.push(ExtractionAttempt { data, errors });
+    }
+
+    if examples.is_empty() {
+        return Err(ExtractionError::ValidationError { attempts });
+    }
+    Ok(examples)
+}
+
 /// Builder for the Extractor
 pub struct ExtractorBuilder<
     T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync + 'static,
     M: CompletionModel,
 > {
+    model: M,
     agent_builder: AgentBuilder<M>,
+    retries: usize,
+    structured_output: bool,
     _t: PhantomData<T>,
 }
 
@@ -87,6 +647,7 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync, M: Compl
 {
     pub fn new(model: M) -> Self {
         Self {
+            model: model.clone(),
             agent_builder: AgentBuilder::new(model)
                 .preamble("\
                     You are an AI assistant whose purpose is to extract structured data from the provided text.\n\
@@ -95,6 +656,8 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync, M: Compl
                     Be sure to fill out every field and ALWAYS CALL THE `submit` function, event with default values!!!.
                 ")
                 .tool(SubmitTool::<T> {_t: PhantomData}),
+            retries: DEFAULT_RETRIES,
+            structured_output: true,
             _t: PhantomData,
         }
     }
@@ -113,15 +676,85 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync, M: Compl
         self
     }
 
+    /// Add few-shot examples of valid data to the extractor's instructions
+    pub fn examples(mut self, examples: &[T]) -> Self {
+        let examples = examples
+            .iter()
+            .filter_map(|example| serde_json::to_string(example).ok())
+            .collect::<Vec<_>>();
+        if !examples.is_empty() {
+            self.agent_builder = self.agent_builder.append_preamble(&format!(
+                "\n=============== EXAMPLES ===============\nExamples of valid data to submit:\n{}",
+                examples.join("\n")
+            ));
+        }
+        self
+    }
+
+    /// Generate up to `count` examples of valid data with the extractor's model and add them
+    /// as few-shot examples (see [synthesize_examples]).
+    pub async fn synthesize_examples(self, count: usize) -> Result<Self, ExtractionError> {
+        let examples = synthesize_examples::<T, M>(&self.model, count).await?;
+        Ok(self.examples(&examples))
+    }
+
+    /// Set the maximum number of times the model is re-prompted with the validation errors
+    /// when the extracted data is invalid (defaults to [DEFAULT_RETRIES]).
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set whether the provider's native structured outputs are used to constrain the response
+    /// to the data's schema when the model supports them (see
+    /// [CompletionModel::structured_output]), instead of the `submit` tool (defaults to true).
+    /// Patches and extractions with confidence always use a tool.
+    pub fn structured_output(mut self, structured_output: bool) -> Self {
+        self.structured_output = structured_output;
+        self
+    }
+
     /// Build the Extractor
     pub fn build(self) -> Extractor<M, T> {
+        let structured_output = match self.structured_output {
+            true => self
+                .model
+                .structured_output(SUBMIT_TOOL_NAME, &json!(schema_for!(T))),
+            false => None,
+        };
+
         Extractor {
             agent: self.agent_builder.build(),
+            retries: self.retries,
+            structured_output,
             _t: PhantomData,
         }
     }
 }
 
+/// Remove the null members of the objects of `value`, recursively
+fn remove_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, remove_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(remove_nulls).collect()),
+        value => value,
+    }
+}
+
+fn patch_tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: PATCH_TOOL_NAME.to_string(),
+        description: "Submit a JSON merge patch (RFC 7396) of the current data: only include the fields that change, set a field to null to remove it.".to_string(),
+        parameters: json!({"type": "object"}),
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 struct SubmitTool<T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync> {
     _t: PhantomData<T>,
@@ -132,7 +765,7 @@ struct SubmitTool<T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync> {
 struct SubmitError;
 
 impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync> Tool for SubmitTool<T> {
-    const NAME: &'static str = "submit";
+    const NAME: &'static str = SUBMIT_TOOL_NAME;
     type Error = SubmitError;
     type Args = T;
     type Output = T;
@@ -150,3 +783,296 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync> Tool for
         Ok(data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockCompletionModel;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+    struct Person {
+        name: String,
+        age: u8,
+    }
+
+    /// Model submitting the given arguments in turn
+    fn submitting(submissions: Vec<Value>) -> MockCompletionModel {
+        submissions
+            .into_iter()
+            .fold(MockCompletionModel::new(), |model, arguments| {
+                model.tool_call("submit", arguments)
+            })
+    }
+
+    /// Names of the tools of each request received by `model`
+    fn tools(model: &MockCompletionModel) -> Vec<Vec<String>> {
+        model
+            .requests()
+            .into_iter()
+            .map(|request| request.tools.into_iter().map(|tool| tool.name).collect())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_extract_with_repair() {
+        let model = submitting(vec![
+            json!({"name": "John Doe", "age": "thirty"}),
+            json!({"name": "John Doe", "age": 30}),
+        ]);
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone()).build();
+
+        let person = extractor.extract("John Doe is 30.").await.unwrap();
+        assert_eq!(
+            person,
+            Person {
+                name: "John Doe".to_string(),
+                age: 30
+            }
+        );
+
+        // The validation errors are sent back as the result of the tool call
+        match &model.requests()[1].prompt {
+            Message::User { content } => match content.first() {
+                UserContent::ToolResult(result) => {
+                    assert_eq!(result.id, "call_0");
+                    assert!(matches!(
+                        result.content.first(),
+                        ToolResultContent::Text(text)
+                            if text.text.contains("$.age: expected integer, found string")
+                    ));
+                }
+                content => panic!("unexpected content {content:?}"),
+            },
+            message => panic!("unexpected message {message:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extract_retries_exhausted() {
+        let model = submitting(vec![json!({"name": "John Doe"}), json!({"age": 30})]);
+        let extractor = ExtractorBuilder::<Person, _>::new(model).retries(1).build();
+
+        match extractor.extract("John Doe is 30.").await {
+            Err(ExtractionError::ValidationError { attempts }) => {
+                assert_eq!(
+                    attempts,
+                    vec![
+                        ExtractionAttempt {
+                            data: json!({"name": "John Doe"}),
+                            errors: vec!["$: missing field `age`".to_string()],
+                        },
+                        ExtractionAttempt {
+                            data: json!({"age": 30}),
+                            errors: vec!["$: missing field `name`".to_string()],
+                        },
+                    ]
+                );
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut target = json!({"a": "b", "c": {"d": "e", "f": "g"}, "h": [1]});
+        merge_patch(
+            &mut target,
+            &json!({"a": "z", "c": {"f": null}, "h": [2, 3], "i": 1}),
+        );
+        assert_eq!(
+            target,
+            json!({"a": "z", "c": {"d": "e"}, "h": [2, 3], "i": 1})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_patch() {
+        let model = submitting(vec![json!({"age": null}), json!({"age": 31})]);
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone()).build();
+        let person = Person {
+            name: "John Doe".to_string(),
+            age: 30,
+        };
+
+        // Removing a required field is rejected and the model is re-prompted
+        let person = extractor
+            .patch(&person, "John Doe turned 31.")
+            .await
+            .unwrap();
+        assert_eq!(
+            person,
+            Person {
+                name: "John Doe".to_string(),
+                age: 31
+            }
+        );
+
+        // Only the patch function is available to the model
+        assert_eq!(tools(&model), vec![vec![PATCH_TOOL_NAME.to_string()]; 2]);
+        assert!(matches!(
+            &model.requests()[0].prompt,
+            Message::User { content } if matches!(
+                content.first(),
+                UserContent::Text(text) if text.text.contains("\"name\": \"John Doe\"")
+            )
+        ));
+    }
+
+    #[test]
+    fn test_json_array_parser() {
+        let mut parser = JsonArrayParser::default();
+        let text = "```json\n[{\"a\": [1, 2]}, \"b]\\\"\", 3 , {\"c\": \"}\"}]\n```";
+
+        // Elements are emitted as soon as they are complete, whatever the chunk boundaries
+        let elements = text
+            .chars()
+            .map(|c| parser.feed(&c.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            elements.concat(),
+            vec![r#"{"a": [1, 2]}"#, r#""b]\"""#, "3", r#"{"c": "}"}"#]
+        );
+        assert_eq!(
+            elements.iter().position(|elements| !elements.is_empty()),
+            text.find('}')
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extract_stream() {
+        // Each text is streamed as a chunk
+        let chunks = [
+            "[{\"name\": \"John\", \"ag",
+            "e\": 30}, {\"name\": \"Jane\"}",
+            ", {\"name\": \"Bob\", \"age\": 40}]",
+        ];
+        let model = MockCompletionModel::new()
+            .response(OneOrMany::many(chunks.map(AssistantContent::text)).unwrap());
+        let extractor = ExtractorBuilder::<Vec<Person>, _>::new(model.clone()).build();
+
+        let people = extractor
+            .extract_stream("John is 30, Jane and Bob is 40.")
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(people.len(), 3);
+        assert_eq!(
+            people[0].as_ref().unwrap(),
+            &Person {
+                name: "John".to_string(),
+                age: 30
+            }
+        );
+        assert!(matches!(
+            &people[1],
+            Err(ExtractionError::ValidationError { attempts })
+                if attempts[0].errors == vec!["$: missing field `age`".to_string()]
+        ));
+        assert_eq!(people[2].as_ref().unwrap().name, "Bob");
+        assert!(model.last_request().unwrap().tools.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_examples() {
+        let model = submitting(vec![
+            json!({"examples": [
+                {"name": "Jane Doe", "age": 41},
+                {"name": "John Doe", "age": -1},
+            ]}),
+            json!({"name": "John Doe", "age": 30}),
+        ]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone())
+            .synthesize_examples(2)
+            .await
+            .unwrap()
+            .build();
+
+        assert_eq!(tools(&model), vec![vec![EXAMPLES_TOOL_NAME.to_string()]]);
+
+        // Only the valid example is added to the instructions
+        extractor.extract("John Doe is 30.").await.unwrap();
+        assert!(model.requests()[1]
+            .preamble
+            .as_ref()
+            .unwrap()
+            .ends_with("Examples of valid data to submit:\n{\"name\":\"Jane Doe\",\"age\":41}"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_with_confidence() {
+        let data = json!({"name": "John Doe", "age": 30});
+        let name = json!({"confidence": 0.9, "justification": "Stated"});
+        let model = submitting(vec![
+            json!({"data": data, "fields": {"name": name}}),
+            json!({"data": data, "fields": {
+                "name": name,
+                "age": {"confidence": 0.4, "justification": "Approximate age"},
+            }}),
+        ]);
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone()).build();
+
+        let person = extractor
+            .extract_with_confidence("John Doe is around 30.")
+            .await
+            .unwrap();
+        assert_eq!(person.data.age, 30);
+        assert_eq!(person.low_confidence_fields(0.5), vec!["age"]);
+        assert!(!person.is_confident(0.5));
+
+        // Missing confidences are reported to the model
+        match &model.requests()[1].prompt {
+            Message::User { content } => assert!(matches!(
+                content.first(),
+                UserContent::ToolResult(result) if matches!(
+                    result.content.first(),
+                    ToolResultContent::Text(text)
+                        if text.text.contains("$.fields: missing confidence of field `age`")
+                )
+            )),
+            message => panic!("unexpected message {message:?}"),
+        }
+        assert_eq!(tools(&model)[0], vec![CONFIDENCE_TOOL_NAME.to_string()]);
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+    struct Contact {
+        name: String,
+        email: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_extract_structured_output() {
+        let model = MockCompletionModel::new()
+            .structured_outputs()
+            .text(r#"{"name": null, "email": null}"#)
+            .text(r#"{"name": "John Doe", "email": null}"#);
+        let extractor = ExtractorBuilder::<Contact, _>::new(model.clone()).build();
+
+        let contact = extractor.extract("John Doe").await.unwrap();
+        assert_eq!(
+            contact,
+            Contact {
+                name: "John Doe".to_string(),
+                email: None
+            }
+        );
+
+        // The response format is sent instead of the submit tool
+        let requests = model.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].additional_params.as_ref().unwrap()["response_format"]["name"],
+            "submit"
+        );
+        assert!(tools(&model).iter().all(Vec::is_empty));
+
+        // Unless disabled
+        let extractor = ExtractorBuilder::<Contact, _>::new(model.clone())
+            .structured_output(false)
+            .build();
+        assert!(extractor.structured_output.is_none());
+    }
+}
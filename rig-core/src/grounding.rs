@@ -0,0 +1,323 @@
+//! Verification of the grounding of answers in their context documents, to catch the
+//! hallucinations of RAG agents.
+//!
+//! A [GroundingVerifier] asks a completion model to split an answer into factual claims, and to
+//! judge whether each claim is entailed by the documents the answer was generated from. An agent
+//! with a verifier ([AgentBuilder::grounding](crate::agent::AgentBuilder::grounding)) checks its
+//! answers before returning them and, depending on the [GroundingPolicy] of the verifier, either
+//! annotates the unsupported claims or asks its model to revise the answer.
+//!
+//! # Example
+//! ```rust
+//! use rig::{grounding::{GroundingPolicy, GroundingVerifier}, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let verifier = GroundingVerifier::new(openai.completion_model(openai::GPT_4O_MINI))
+//!     .policy(GroundingPolicy::Revise(1));
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .dynamic_context(5, index)
+//!     .grounding(verifier)
+//!     .build();
+//! ```
+use futures::future::BoxFuture;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::{CompletionModel, Document},
+    extractor::{ExtractionError, Extractor, ExtractorBuilder},
+};
+
+const VERIFIER_PREAMBLE: &str = "\
+You verify that answers are grounded in their source documents. Split the answer into its \
+factual claims, and decide for each claim whether it is supported, i.e.: entailed by the \
+documents, citing the ids of the supporting documents. Claims that are only plausible, or \
+contradicted by the documents, are not supported. Statements that are not factual (e.g.: \
+greetings, or stating that the documents do not contain some information) are not claims.";
+
+/// Factual claim of an answer
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct Claim {
+    /// The claim, as stated by the answer
+    pub claim: String,
+    /// Whether the claim is entailed by the documents
+    pub supported: bool,
+    /// Ids of the documents supporting the claim
+    #[serde(default)]
+    pub sources: Vec<String>,
+}
+
+/// Claims of an answer, with their grounding in the documents
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct Verification {
+    pub claims: Vec<Claim>,
+}
+
+impl Verification {
+    /// Whether all the claims of the answer are supported by the documents
+    pub fn is_grounded(&self) -> bool {
+        self.claims.iter().all(|claim| claim.supported)
+    }
+
+    /// Claims of the answer not supported by the documents
+    pub fn unsupported(&self) -> Vec<&Claim> {
+        self.claims
+            .iter()
+            .filter(|claim| !claim.supported)
+            .collect()
+    }
+}
+
+/// What an agent does with the answers whose claims are not all supported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroundingPolicy {
+    /// List the unsupported claims after the answer, see [annotate]
+    Annotate,
+    /// Ask the model to revise the answer without the unsupported claims, up to the given number
+    /// of times, then annotate the claims that remain unsupported
+    Revise(usize),
+}
+
+/// Verifier of the grounding of answers, judging their claims with a completion model.
+pub struct GroundingVerifier<M: CompletionModel> {
+    extractor: Extractor<M, Verification>,
+    policy: GroundingPolicy,
+}
+
+impl<M: CompletionModel> GroundingVerifier<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            extractor: ExtractorBuilder::new(model)
+                .preamble(VERIFIER_PREAMBLE)
+                .build(),
+            policy: GroundingPolicy::Annotate,
+        }
+    }
+
+    /// Set the policy applied by agents to the answers that are not grounded (defaults to
+    /// [GroundingPolicy::Annotate]).
+    pub fn policy(mut self, policy: GroundingPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Judge whether the claims of `answer` are supported by `documents`.
+    pub async fn verify(
+        &self,
+        answer: &str,
+        documents: &[Document],
+    ) -> Result<Verification, ExtractionError> {
+        let documents = documents
+            .iter()
+            .map(ToString::to_string)
+            .collect::<String>();
+        self.extractor
+            .extract(&format!(
+                "<documents>\n{documents}</documents>\n\n<answer>\n{answer}\n</answer>"
+            ))
+            .await
+    }
+}
+
+/// Wrapper trait to store verifiers of any completion model
+pub(crate) trait GroundingVerifierDyn: Send + Sync {
+    fn policy(&self) -> GroundingPolicy;
+
+    fn verify_dyn<'a>(
+        &'a self,
+        answer: &'a str,
+        documents: &'a [Document],
+    ) -> BoxFuture<'a, Result<Verification, ExtractionError>>;
+}
+
+impl<M: CompletionModel> GroundingVerifierDyn for GroundingVerifier<M> {
+    fn policy(&self) -> GroundingPolicy {
+        self.policy
+    }
+
+    fn verify_dyn<'a>(
+        &'a self,
+        answer: &'a str,
+        documents: &'a [Document],
+    ) -> BoxFuture<'a, Result<Verification, ExtractionError>> {
+        Box::pin(self.verify(answer, documents))
+    }
+}
+
+/// List the unsupported claims of `verification` after `answer`, if any.
+pub fn annotate(answer: &str, verification: &Verification) -> String {
+    let unsupported = verification.unsupported();
+    if unsupported.is_empty() {
+        return answer.to_string();
+    }
+    let claims = unsupported
+        .iter()
+        .map(|claim| format!("- {}", claim.claim))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{answer}\n\nUnsupported claims (not found in the sources):\n{claims}")
+}
+
+/// Prompt asking the model to revise an answer without its unsupported claims
+pub(crate) fn revision_prompt(verification: &Verification) -> String {
+    let claims = verification
+        .unsupported()
+        .iter()
+        .map(|claim| format!("- {}", claim.claim))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "The following claims of your answer are not supported by the documents:\n{claims}\n\n\
+        Revise your answer so that it only states what the documents support, saying so when \
+        they do not contain the requested information. Respond with the revised answer only."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::{CompletionError, CompletionRequest, CompletionResponse},
+        message::AssistantContent,
+        OneOrMany,
+    };
+
+    /// Model judging the claims listed in the answer, supported if they appear in a document
+    #[derive(Clone, Default)]
+    struct JudgeModel(Arc<std::sync::Mutex<Option<String>>>);
+
+    impl CompletionModel for JudgeModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let text = crate::memory::message_text(&request.prompt);
+            *self.0.lock().unwrap() = Some(text.clone());
+            let (documents, answer) = text.split_once("<answer>").unwrap();
+            let (answer, _) = answer.split_once("</answer>").unwrap();
+            let claims = answer
+                .split('.')
+                .map(str::trim)
+                .filter(|claim| !claim.is_empty())
+                .map(|claim| {
+                    json!({"claim": claim, "supported": documents.contains(claim), "sources": []})
+                })
+                .collect::<Vec<_>>();
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::tool_call(
+                    "call",
+                    "submit",
+                    json!({ "claims": claims }),
+                )),
+                usage: None,
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let model = JudgeModel::default();
+        let verifier = GroundingVerifier::new(model.clone());
+        let documents = [Document {
+            id: "doc0".to_string(),
+            text: "Flurbos are green".to_string(),
+            additional_props: HashMap::new(),
+        }];
+
+        let verification = verifier
+            .verify("Flurbos are green. Flurbos live on Mars.", &documents)
+            .await
+            .unwrap();
+        assert!(model
+            .0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .contains("<file id: doc0>"));
+        assert!(!verification.is_grounded());
+        assert_eq!(
+            verification
+                .unsupported()
+                .into_iter()
+                .map(|claim| claim.claim.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Flurbos live on Mars"]
+        );
+        assert_eq!(
+            annotate("Flurbos are green. Flurbos live on Mars.", &verification),
+            "Flurbos are green. Flurbos live on Mars.\n\n\
+            Unsupported claims (not found in the sources):\n- Flurbos live on Mars"
+        );
+
+        let verification = verifier
+            .verify("Flurbos are green.", &documents)
+            .await
+            .unwrap();
+        assert!(verification.is_grounded());
+        assert_eq!(
+            annotate("Flurbos are green.", &verification),
+            "Flurbos are green."
+        );
+    }
+
+    /// Model failing its first request, then submitting claims without their support
+    #[derive(Clone, Default)]
+    struct FlakyModel(Arc<std::sync::Mutex<usize>>);
+
+    impl CompletionModel for FlakyModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let mut calls = self.0.lock().unwrap();
+            *calls += 1;
+            if *calls == 1 {
+                return Err(CompletionError::ProviderError("Overloaded".into()));
+            }
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::tool_call(
+                    "call",
+                    "submit",
+                    json!({"claims": [{"claim": "Flurbos are green"}]}),
+                )),
+                usage: None,
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_errors() {
+        let model = FlakyModel::default();
+        let verifier = GroundingVerifier::new(model.clone());
+        assert!(matches!(
+            verifier.verify("Flurbos are green.", &[]).await,
+            Err(ExtractionError::PromptError(_))
+        ));
+
+        // Invalid verifications are retried, and reported with all the attempts
+        match verifier.verify("Flurbos are green.", &[]).await {
+            Err(ExtractionError::ValidationError { attempts }) => {
+                assert_eq!(attempts.len(), 3);
+                assert_eq!(
+                    attempts[0].data,
+                    json!({"claims": [{"claim": "Flurbos are green"}]})
+                );
+            }
+            result => panic!("Unexpected verification: {result:?}"),
+        }
+        assert_eq!(*model.0.lock().unwrap(), 4);
+    }
+}
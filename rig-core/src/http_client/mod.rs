@@ -0,0 +1,115 @@
+//! Configuration of the HTTP clients of the providers.
+//!
+//! - the [tls] settings (requiring the `native-tls` or `rustls-tls` feature) select the TLS
+//!   implementation of a client and the certificates it trusts
+//! - the [pool] settings tune the connection pool and the keep-alive of a client
+//!
+//! These settings are not available on wasm, where the requests are sent by the host.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pool;
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    any(feature = "native-tls", feature = "rustls-tls")
+))]
+pub mod tls;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use pool::PoolConfig;
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    any(feature = "native-tls", feature = "rustls-tls")
+))]
+pub use tls::{TlsBackend, TlsConfig};
+
+/// Settings of the HTTP client of a provider client, set by its builder
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HttpConfig {
+    #[cfg(all(
+        not(target_arch = "wasm32"),
+        any(feature = "native-tls", feature = "rustls-tls")
+    ))]
+    pub(crate) tls: TlsConfig,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) pool: PoolConfig,
+}
+
+impl HttpConfig {
+    /// Apply the settings to the builder of a client
+    #[cfg_attr(not(feature = "providers"), allow(dead_code))]
+    pub(crate) fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        #[cfg(all(
+            not(target_arch = "wasm32"),
+            any(feature = "native-tls", feature = "rustls-tls")
+        ))]
+        let builder = self.tls.apply(builder);
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = self.pool.apply(builder);
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Address of a server answering each connection with a HTTP/1 response
+    fn http1_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                );
+            }
+        });
+        format!("http://{address}")
+    }
+
+    #[tokio::test]
+    async fn test_apply() {
+        let url = http1_server();
+
+        let client = HttpConfig::default()
+            .apply(reqwest::Client::builder())
+            .build()
+            .unwrap();
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        // Settings of the configuration are applied, and their failures returned by the requests
+        let config = HttpConfig {
+            pool: PoolConfig::new().http2_prior_knowledge(),
+            ..Default::default()
+        };
+        let client = config.apply(reqwest::Client::builder()).build().unwrap();
+        assert!(client.get(&url).send().await.is_err());
+
+        // The TLS settings are applied, and the failed handshakes returned by the requests
+        #[cfg(all(
+            not(target_arch = "wasm32"),
+            any(feature = "native-tls", feature = "rustls-tls")
+        ))]
+        {
+            let config = HttpConfig {
+                tls: TlsConfig::new().pin_root_certificates(),
+                ..Default::default()
+            };
+            let client = config.apply(reqwest::Client::builder()).build().unwrap();
+            let error = client
+                .get(url.replace("http://", "https://"))
+                .send()
+                .await
+                .unwrap_err();
+            assert!(error.is_connect());
+        }
+    }
+}
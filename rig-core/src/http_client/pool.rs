@@ -0,0 +1,174 @@
+//! Connection pool settings of the HTTP clients of the providers.
+//!
+//! The models and agents created from a provider client share the connection pool of the client
+//! (cloning a client is cheap and reuses its pool): high-QPS services should create one client per
+//! provider and tune its pool with a [PoolConfig] (e.g.: with
+//! [openai::ClientBuilder::pool](crate::providers::openai::ClientBuilder::pool)), rather than
+//! create clients per request.
+//!
+//! HTTP/2 is negotiated with the servers supporting it over TLS when using the `rustls-tls`
+//! feature, or can be forced with [PoolConfig::http2_prior_knowledge] (e.g.: for a self-hosted
+//! inference server behind a TLS-terminating proxy).
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{http_client::PoolConfig, providers::openai};
+//!
+//! let pool = PoolConfig::new()
+//!     .max_idle_per_host(32)
+//!     .idle_timeout(Duration::from_secs(90))
+//!     .tcp_keepalive(Duration::from_secs(30))
+//!     .http2_adaptive_window(true)
+//!     .http2_keep_alive_interval(Duration::from_secs(20));
+//!
+//! let client = openai::Client::builder("your-api-key").pool(pool).build();
+//! ```
+use std::time::Duration;
+
+/// Connection pool and keep-alive settings of a provider client. Unset settings keep the
+/// defaults of `reqwest`.
+#[derive(Clone, Debug, Default)]
+pub struct PoolConfig {
+    max_idle_per_host: Option<usize>,
+    idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    http2_prior_knowledge: bool,
+    http2_adaptive_window: Option<bool>,
+    http2_keep_alive_interval: Option<Duration>,
+    http2_keep_alive_timeout: Option<Duration>,
+    http2_keep_alive_while_idle: Option<bool>,
+}
+
+impl PoolConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of idle connections kept open to each host.
+    pub fn max_idle_per_host(mut self, max: usize) -> Self {
+        self.max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Set the time after which idle connections are closed.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Send TCP keep-alive probes on the connections at the given interval.
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Only use HTTP/2, without negotiating it with the server.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http2_prior_knowledge = true;
+        self
+    }
+
+    /// Adapt the flow control windows of HTTP/2 connections to the bandwidth and latency of the
+    /// connection (e.g.: for large streamed responses).
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2_adaptive_window = Some(enabled);
+        self
+    }
+
+    /// Send HTTP/2 pings on the connections at the given interval, to keep them open through
+    /// proxies and load balancers and detect broken connections.
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.http2_keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Set the time after which a connection whose HTTP/2 ping is not acknowledged is closed.
+    pub fn http2_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.http2_keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// Also send HTTP/2 pings on idle connections (without pending requests).
+    pub fn http2_keep_alive_while_idle(mut self, enabled: bool) -> Self {
+        self.http2_keep_alive_while_idle = Some(enabled);
+        self
+    }
+
+    /// Apply the settings to the builder of a client
+    pub(crate) fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Some(max) = self.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = self.idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(interval) = self.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(enabled) = self.http2_adaptive_window {
+            builder = builder.http2_adaptive_window(enabled);
+        }
+        if let Some(interval) = self.http2_keep_alive_interval {
+            builder = builder.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.http2_keep_alive_timeout {
+            builder = builder.http2_keep_alive_timeout(timeout);
+        }
+        if let Some(enabled) = self.http2_keep_alive_while_idle {
+            builder = builder.http2_keep_alive_while_idle(enabled);
+        }
+        builder
+    }
+}
+
+#[cfg(all(test, feature = "providers"))]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{embeddings::EmbeddingModel, providers::openai};
+
+    #[tokio::test]
+    async fn test_pool_config() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/embeddings");
+            then.status(200).json_body(json!({
+                "object": "list",
+                "data": [{"object": "embedding", "embedding": [0.5], "index": 0}],
+                "model": openai::TEXT_EMBEDDING_3_SMALL,
+                "usage": {"prompt_tokens": 1, "total_tokens": 1}
+            }));
+        });
+
+        let client = openai::Client::builder("key")
+            .base_url(&server.base_url())
+            .pool(
+                PoolConfig::new()
+                    .max_idle_per_host(4)
+                    .idle_timeout(Duration::from_secs(30))
+                    .tcp_keepalive(Duration::from_secs(10))
+                    .http2_adaptive_window(true),
+            )
+            .build();
+
+        // Models of the same client share its connection pool
+        for model in [
+            openai::TEXT_EMBEDDING_3_SMALL,
+            openai::TEXT_EMBEDDING_3_LARGE,
+        ] {
+            let embedding = client
+                .embedding_model(model)
+                .embed_text("Hello")
+                .await
+                .unwrap();
+            assert_eq!(embedding.vec, vec![0.5]);
+        }
+        mock.assert_hits(2);
+    }
+}
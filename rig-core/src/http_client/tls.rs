@@ -0,0 +1,141 @@
+//! TLS settings of the HTTP clients of the providers.
+//!
+//! A [TlsConfig] given to a provider client (e.g.: with
+//! [openai::ClientBuilder::tls](crate::providers::openai::ClientBuilder::tls)) selects the TLS
+//! implementation of the client and the certificates it trusts, e.g.: to reach a self-hosted
+//! inference server whose certificate is issued by a private certificate authority, or to only
+//! trust the certificate of a corporate proxy.
+//!
+//! The TLS implementations available are the ones enabled by the `native-tls` (the default) and
+//! `rustls-tls` features. When both are enabled, the native one is used unless specified
+//! otherwise.
+//!
+//! # Example
+//! ```rust
+//! use rig::{http_client::TlsConfig, providers::openai};
+//!
+//! let tls = TlsConfig::new()
+//!     .add_ca_bundle(&std::fs::read("/etc/ssl/company-ca.pem")?)?
+//!     // Do not trust the public certificate authorities
+//!     .pin_root_certificates();
+//!
+//! let client = openai::Client::builder("your-api-key")
+//!     .base_url("https://inference.internal/v1")
+//!     .tls(tls)
+//!     .build();
+//! ```
+use reqwest::{tls::Version, Certificate};
+
+/// TLS implementation of a client
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsBackend {
+    /// The TLS implementation of the platform (OpenSSL, Schannel or Secure Transport)
+    #[cfg(feature = "native-tls")]
+    NativeTls,
+    /// The pure Rust `rustls` implementation
+    #[cfg(feature = "rustls-tls")]
+    Rustls,
+}
+
+/// TLS settings of a provider client
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    backend: Option<TlsBackend>,
+    root_certificates: Vec<Certificate>,
+    pinned: bool,
+    min_version: Option<Version>,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the TLS implementation of the client.
+    pub fn backend(mut self, backend: TlsBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Trust an additional root certificate (e.g.: of a private certificate authority).
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Trust the root certificates of a PEM bundle (e.g.: the contents of a `ca-bundle.pem` file).
+    pub fn add_ca_bundle(mut self, pem: &[u8]) -> Result<Self, reqwest::Error> {
+        self.root_certificates
+            .extend(Certificate::from_pem_bundle(pem)?);
+        Ok(self)
+    }
+
+    /// Only trust the root certificates added to the configuration, and not the ones built in the
+    /// TLS implementation or the system, so that the client only connects to servers whose
+    /// certificate is issued by (or is) one of them.
+    pub fn pin_root_certificates(mut self) -> Self {
+        self.pinned = true;
+        self
+    }
+
+    /// Set the minimum TLS version accepted by the client.
+    pub fn min_version(mut self, version: Version) -> Self {
+        self.min_version = Some(version);
+        self
+    }
+
+    /// Apply the settings to the builder of a client
+    pub(crate) fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        match self.backend {
+            #[cfg(feature = "native-tls")]
+            Some(TlsBackend::NativeTls) => builder = builder.use_native_tls(),
+            #[cfg(feature = "rustls-tls")]
+            Some(TlsBackend::Rustls) => builder = builder.use_rustls_tls(),
+            None => (),
+        }
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if self.pinned {
+            builder = builder.tls_built_in_root_certs(false);
+        }
+        if let Some(version) = self.min_version {
+            builder = builder.min_tls_version(version);
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBkTCCATegAwIBAgIUMkCbog+l6KRVoKUKH4wEQW94LjIwCgYIKoZIzj0EAwIw
+HTEbMBkGA1UEAwwSaW5mZXJlbmNlLmludGVybmFsMCAXDTI2MTAxNDEwMDIxN1oY
+DzIxMjYwOTIwMTAwMjE3WjAdMRswGQYDVQQDDBJpbmZlcmVuY2UuaW50ZXJuYWww
+WTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARpcCZVMacwnBBcoVRlB2QO0/Z5DsB+
+0DMfliUvryNrjHuxIDFB3yaYopmWVXoB3HjfKC09X/gcuTXGHNFKsIjco1MwUTAd
+BgNVHQ4EFgQU245RmrlyCghDn4bhXb73a9bBSyIwHwYDVR0jBBgwFoAU245Rmrly
+CghDn4bhXb73a9bBSyIwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBF
+AiB6/sv9eJqEBOoZ2UfwahV21JJkWvY5IFApr9S7uHUCrwIhAPp2FcY80azHJFzb
+7LrhtLRIHomTuwVIm1FALBCyfxgh
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_tls_config() {
+        let bundle = format!("{CERTIFICATE}{CERTIFICATE}");
+        let tls = TlsConfig::new()
+            .add_ca_bundle(bundle.as_bytes())
+            .unwrap()
+            .pin_root_certificates()
+            .min_version(Version::TLS_1_2);
+        assert_eq!(tls.root_certificates.len(), 2);
+        assert!(tls.apply(reqwest::Client::builder()).build().is_ok());
+
+        assert!(TlsConfig::new()
+            .add_ca_bundle(b"-----BEGIN CERTIFICATE-----\nnot base64\n")
+            .is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! This module provides functionality for generating images from text.
+//!
+//! An [ImageGenerationModel] (e.g.: OpenAI DALL·E) generates images from a prompt, given as an
+//! [ImageGenerationRequest]. Requests are usually created with the [ImageGenerationRequestBuilder]
+//! returned by [ImageGenerationModel::image_generation_request]. The generated images are
+//! [Image]s, given by their URL or base64-encoded data, which can be saved
+//! (see [Image::to_bytes]) or sent to an agent in a message.
+//!
+//! # Example
+//! ```rust
+//! use rig::{image_generation::ImageGenerationModel, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let dall_e = openai.image_generation_model(openai::DALL_E_3);
+//!
+//! let generation = dall_e
+//!     .image_generation_request()
+//!     .prompt("A crab made of rusty metal, in the style of a woodcut")
+//!     .size(1024, 1024)
+//!     .additional_params(json!({"response_format": "b64_json"}))
+//!     .send()
+//!     .await?;
+//!
+//! std::fs::write("crab.png", generation.images[0].to_bytes()?)?;
+//! ```
+use std::future::Future;
+
+use serde_json::Value;
+
+use crate::message::Image;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImageGenerationError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[cfg(feature = "http")]
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error building the image generation request
+    #[error("RequestError: {0}")]
+    RequestError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Error parsing the image generation response
+    #[error("ResponseError: {0}")]
+    ResponseError(String),
+
+    /// Error returned by the image generation model provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+/// Request to generate images from a prompt.
+#[derive(Clone, Debug)]
+pub struct ImageGenerationRequest {
+    /// Description of the images to generate
+    pub prompt: String,
+    /// Number of images to generate
+    pub n: usize,
+    /// Width and height of the images, in pixels (the sizes supported depend on the model)
+    pub size: Option<(u32, u32)>,
+    /// Additional provider-specific parameters (e.g.: quality, style)
+    pub additional_params: Option<Value>,
+}
+
+/// Images generated by a model, along with the raw response of the provider.
+#[derive(Debug)]
+pub struct ImageGenerationResponse<T> {
+    pub images: Vec<Image>,
+    pub response: T,
+}
+
+/// Trait for models generating images from text.
+pub trait ImageGenerationModel: Clone + Send + Sync {
+    /// Raw response type returned by the provider
+    type Response: Send + Sync;
+
+    /// Generate the images described by `request`.
+    fn image_generation(
+        &self,
+        request: ImageGenerationRequest,
+    ) -> impl Future<Output = Result<ImageGenerationResponse<Self::Response>, ImageGenerationError>> + Send;
+
+    /// Create an image generation request builder for this model.
+    fn image_generation_request(&self) -> ImageGenerationRequestBuilder<Self> {
+        ImageGenerationRequestBuilder::new(self.clone())
+    }
+}
+
+/// Builder for image generation requests.
+///
+/// # Example
+/// ```rust
+/// use rig::image_generation::ImageGenerationModel;
+///
+/// let request = model
+///     .image_generation_request()
+///     .prompt("Logo of a Rust library for LLM applications")
+///     .n(2)
+///     .size(512, 512)
+///     .build();
+///
+/// let generation = model.image_generation(request).await?;
+/// ```
+pub struct ImageGenerationRequestBuilder<M: ImageGenerationModel> {
+    model: M,
+    prompt: String,
+    n: usize,
+    size: Option<(u32, u32)>,
+    additional_params: Option<Value>,
+}
+
+impl<M: ImageGenerationModel> ImageGenerationRequestBuilder<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            prompt: String::new(),
+            n: 1,
+            size: None,
+            additional_params: None,
+        }
+    }
+
+    /// Set the description of the images to generate.
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    /// Set the number of images to generate (defaults to 1).
+    pub fn n(mut self, n: usize) -> Self {
+        self.n = n;
+        self
+    }
+
+    /// Set the width and height of the images, in pixels.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    /// Add additional provider-specific parameters to the request.
+    pub fn additional_params(mut self, additional_params: Value) -> Self {
+        self.additional_params = match self.additional_params {
+            Some(params) => Some(crate::json_utils::merge(params, additional_params)),
+            None => Some(additional_params),
+        };
+        self
+    }
+
+    pub fn build(self) -> ImageGenerationRequest {
+        ImageGenerationRequest {
+            prompt: self.prompt,
+            n: self.n,
+            size: self.size,
+            additional_params: self.additional_params,
+        }
+    }
+
+    /// Build the request and send it to the model.
+    pub async fn send(self) -> Result<ImageGenerationResponse<M::Response>, ImageGenerationError> {
+        let model = self.model.clone();
+        model.image_generation(self.build()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use serde_json::json;
+
+    use super::*;
+    use crate::message::ImageMediaType;
+
+    /// Model "generating" images whose data is their prompt
+    #[derive(Clone)]
+    struct EchoModel;
+
+    impl ImageGenerationModel for EchoModel {
+        type Response = ImageGenerationRequest;
+
+        async fn image_generation(
+            &self,
+            request: ImageGenerationRequest,
+        ) -> Result<ImageGenerationResponse<ImageGenerationRequest>, ImageGenerationError> {
+            let image = Image::base64(BASE64_STANDARD.encode(&request.prompt), ImageMediaType::PNG);
+            Ok(ImageGenerationResponse {
+                images: vec![image; request.n],
+                response: request,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_image_generation_request() {
+        let generation = EchoModel
+            .image_generation_request()
+            .prompt("A crab")
+            .n(2)
+            .size(256, 512)
+            .additional_params(json!({"quality": "hd"}))
+            .additional_params(json!({"style": "natural"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(generation.images.len(), 2);
+        assert_eq!(generation.images[0].to_bytes().unwrap(), b"A crab");
+        assert_eq!(generation.response.size, Some((256, 512)));
+        assert_eq!(
+            generation.response.additional_params,
+            Some(json!({"quality": "hd", "style": "natural"}))
+        );
+
+        assert!(Image::url("https://example.com/crab.png")
+            .to_bytes()
+            .is_err());
+    }
+}
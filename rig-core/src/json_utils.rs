@@ -1,3 +1,6 @@
+// Most helpers are only used by the provider integrations
+#![cfg_attr(not(feature = "providers"), allow(dead_code))]
+
 use serde::de::{self, Deserializer, SeqAccess, Visitor};
 use serde::Deserialize;
 use std::convert::Infallible;
@@ -5,6 +5,8 @@
 //! - [Simple Example](#simple-example)
 //! - [Core Concepts](#core-concepts)
 //! - [Integrations](#integrations)
+//! - [Cargo features](#cargo-features)
+//! - [WebAssembly](#webassembly)
 //!
 //! # High-level features
 //! - Full support for LLM completion and embedding workflows
@@ -35,6 +37,10 @@
 //! Note: using `#[tokio::main]` requires you enable tokio's `macros` and `rt-multi-thread` features
 //! or just `full` to enable all features (`cargo add tokio --features macros,rt-multi-thread`).
 //!
+//! Rig itself is not tied to tokio (see the [runtime] module) and can be used from async-std or
+//! smol applications, with the exception of the HTTP client of the provider integrations (`reqwest`),
+//! which needs to run in a tokio context (e.g.: using the `async-compat` crate).
+//!
 //! # Core concepts
 //! ## Completion and embedding models
 //! Rig provides a consistent API for working with LLMs and embeddings. Specifically,
@@ -43,11 +49,33 @@
 //! and [EmbeddingModel](crate::embeddings::EmbeddingModel) traits respectively, which provide a common,
 //! low-level interface for creating completion and embedding requests and executing them.
 //!
+//! When the model is only known at runtime (e.g.: read from a configuration file), a
+//! [ModelRegistry](crate::registry::ModelRegistry) creates models from `provider:model` strings,
+//! type-erased as [DynCompletionModel](crate::completion::DynCompletionModel) and
+//! [DynEmbeddingModel](crate::embeddings::DynEmbeddingModel).
+//!
+//! The tail latency of interactive applications can be cut by wrapping a model in a
+//! [HedgedModel](crate::completion::HedgedModel), which sends a duplicate of the slow requests. A
+//! [FallbackCompletionModel](crate::completion::FallbackCompletionModel) fails over to the
+//! models of other providers when a provider is down.
+//! The requests of models sharing a rate limit can be prioritized with a
+//! [Scheduler](crate::scheduler::Scheduler), so that background jobs don't delay interactive
+//! requests. The recovery of an application from provider outages (e.g.: its retries and
+//! fallbacks) can be tested by injecting faults into its models and tools with
+//! [chaos mode](crate::chaos).
+//!
 //! ## Agents
 //! Rig also provides high-level abstractions over LLMs in the form of the [Agent](crate::agent::Agent) type.
 //!
 //! The [Agent](crate::agent::Agent) type can be used to create anything from simple agents that use vanilla models to full blown
-//! RAG systems that can be used to answer questions using a knowledge base.
+//! RAG systems that can be used to answer questions using a knowledge base. Their preambles can be
+//! built from [PromptTemplate](crate::prompt::PromptTemplate)s, with variables, conditional
+//! sections and few-shot examples. Their prompts and responses can be validated, redacted or
+//! rejected by [guardrails], and the [grounding] of their answers in the retrieved documents can
+//! be verified. In conversations, the follow-up prompts can be rewritten into standalone
+//! retrieval queries ([query_rewriting]), and the retrieved documents can be stripped of their
+//! parts irrelevant to the prompts ([compression]). The typed [state] of multi-turn workflows
+//! can be injected in their templates and updated from the outputs of the model.
 //!
 //! ## Vector stores and indexes
 //! Rig provides a common interface for working with vector stores and indexes. Specifically, the library
@@ -55,6 +83,30 @@
 //! trait, which can be implemented to define vector stores and indices respectively.
 //! Those can then be used as the knowledge base for a RAG enabled [Agent](crate::agent::Agent), or
 //! as a source of context documents in a custom architecture that use multiple LLMs or agents.
+//! The documents retrieved from an index can also be re-ranked by a
+//! [Reranker](crate::rerank::Reranker) (e.g. Cohere Rerank). Indexes can also be kept in sync with
+//! a stream of document changes by an [EventIngestor](crate::vector_store::events::EventIngestor).
+//! Keyword search is available without external services with the
+//! [Bm25Index](crate::vector_store::Bm25Index), combined with vector search by a
+//! [HybridIndex](crate::vector_store::HybridIndex) or used by a
+//! [FallbackIndex](crate::vector_store::FallbackIndex) when vector search fails. The recall of
+//! terse queries can be improved by a [TransformedIndex](crate::vector_store::TransformedIndex),
+//! searching a hypothetical answer (HyDE) or several paraphrases of each query.
+//!
+//! ## Transcription models
+//! Audio files can be transcribed to text by models implementing the
+//! [TranscriptionModel](crate::transcription::TranscriptionModel) trait (e.g. OpenAI Whisper), so
+//! that voice notes or recordings can be used as prompts or as documents of a knowledge base.
+//!
+//! ## Speech models
+//! Texts (e.g.: the responses of agents) can be converted to speech by models implementing the
+//! [SpeechModel](crate::tts::SpeechModel) trait (e.g. OpenAI TTS), as audio files or streams of
+//! audio chunks.
+//!
+//! ## Image generation models
+//! Images can be generated from text by models implementing the
+//! [ImageGenerationModel](crate::image_generation::ImageGenerationModel) trait (e.g. OpenAI DALL·E).
+//! The generated images can be saved, or sent in messages to agents backed by vision models.
 //!
 //! # Integrations
 //! ## Model Providers
@@ -70,6 +122,9 @@
 //! You can also implement your own model provider integration by defining types that
 //! implement the [CompletionModel](crate::completion::CompletionModel) and [EmbeddingModel](crate::embeddings::EmbeddingModel) traits.
 //!
+//! The requests and responses of the OpenAI and Anthropic clients can be inspected and modified
+//! (e.g.: to add headers, redact prompts or log the requests) by [middleware].
+//!
 //! ## Vector Stores
 //! Rig currently supports the following vector store integrations via companion crates:
 //! - `rig-mongodb`: Vector store implementation for MongoDB
@@ -79,25 +134,117 @@
 //!
 //! You can also implement your own vector store integration by defining types that
 //! implement the [VectorStoreIndex](crate::vector_store::VectorStoreIndex) trait.
+//!
+//! # Cargo features
+//! The default features are `providers`, `native-tls` and `builtin-tools`. With
+//! `default-features = false`, only the core traits and types (completion, embeddings, agents,
+//! vector stores, tools, pipelines, ...) are compiled, without any HTTP client or async runtime.
+//! The pure traits and types (the [Embed] and
+//! [VectorDistance](crate::embeddings::distance::VectorDistance) traits, the
+//! [Embedding](crate::embeddings::Embedding) vectors, the [completion::message] types and
+//! [OneOrMany]) are defined in the `rig-types` crate and re-exported here at the same paths.
+//! `rig-types` only depends on `serde`, `base64` and `thiserror`, so that alternative runtimes
+//! and test harnesses can depend on the abstractions of Rig without its HTTP stack.
+//! - `providers`: the model provider integrations of the [providers] module (its
+//!   [mock](providers::mock) models are always compiled)
+//! - `bedrock`: the AWS Bedrock provider
+//! - `native-tls` / `rustls-tls`: the TLS implementations of the HTTP client, selected (along with
+//!   the trusted certificates and the connection pool) by the [http_client] settings of the
+//!   provider clients
+//! - `builtin-tools`: the calculator, date/time and webhook (with `http`) tools of the [tool] module
+//! - `derive`: the `Embed` and `ConversationState` derive macros, and the `prompt!` macro
+//! - `pdf`, `epub`, `docx`, `html`, `csv`: the corresponding document loaders
+//! - `pdf-ocr`: text recognition of scanned PDF documents with Tesseract
+//! - `rayon`: parallel computation of embedding distances
+//! - `tokenizer`: the BPE tokenizers of the OpenAI models, of the [tokenizer] module
+//! - `regex`: the redaction guard of the [guardrails] module
+//! - `hnsw`, `zstd`: the approximate nearest neighbor index and the compressed files of the
+//!   [in-memory vector store](crate::vector_store::in_memory_store)
+//! - `redis`, `s3`: the corresponding [storage] backends
+//! - `encryption`: encryption at rest of the [storage] values and of the saved in-memory vector
+//!   stores
+//! - `test-kit`: the conformance checks of provider integrations, against a mock HTTP server
+//! - `bench`: the `criterion` helpers of the [bench](mod@bench) module, to benchmark vector stores
+//! - `otel`: export of the [telemetry] spans of agents and models to OpenTelemetry (OTLP)
+//! - `blocking`: the synchronous facade of the [blocking] module, for scripts and CLI tools
+//! - `tower`: the tower services of the agents and models, of the [service] module
+//! - `worker`: support for the HTTP clients on wasm (e.g.: in Cloudflare Workers), see [WebAssembly](#webassembly)
+//!
+//! # WebAssembly
+//! Rig compiles to `wasm32-unknown-unknown`, so that agents can run in browser apps and in
+//! Cloudflare Workers. On wasm, the HTTP client of the providers sends its requests with the
+//! `fetch` API of the JavaScript host, whose futures are not `Send`: the `worker` feature is
+//! required with the `providers` and `http` features, to run them where rig expects `Send`
+//! futures. The [runtime] uses the timers, the event loop and the clock of the JavaScript host.
+//!
+//! The filesystem and the child processes are not available on wasm, so the [loaders] cannot
+//! read files from glob patterns or directories, and the file [storage], file system and shell
+//! [tools](tool), the MCP transports and the [middleware::cassette] are not compiled. The
+//! `native-tls` and `rustls-tls` features have no effect (`fetch` uses the TLS of the host),
+//! and the `redis`, `s3`, `otel`, `zstd`, `encryption` and `rayon` features, which rely on tokio,
+//! threads or C libraries, are not supported. For example:
+//! ```toml
+//! rig-core = { version = "0.9", default-features = false, features = ["providers", "worker"] }
+//! ```
+
+#[cfg(all(target_arch = "wasm32", feature = "http", not(feature = "worker")))]
+compile_error!("The `http` and `providers` features require the `worker` feature on wasm");
 
 pub mod agent;
+pub mod anonymization;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+pub mod budget;
+pub mod chaos;
+pub mod chunking;
 pub mod cli_chatbot;
 pub mod completion;
+pub mod compression;
 pub mod embeddings;
 pub mod extractor;
+pub mod grounding;
+pub mod guardrails;
+#[cfg(feature = "http")]
+pub mod http_client;
+pub mod image_generation;
 pub(crate) mod json_utils;
 pub mod loaders;
-pub mod one_or_many;
+pub mod memory;
+#[cfg(feature = "http")]
+pub mod middleware;
+pub mod orchestrator;
+pub mod outbox;
 pub mod pipeline;
+pub mod prompt;
 pub mod providers;
+pub mod query_rewriting;
+pub mod registry;
+pub mod rerank;
+pub mod router;
+pub mod runtime;
+pub mod scheduler;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod session;
+pub mod state;
+pub mod storage;
 pub mod streaming;
+pub mod telemetry;
+pub mod tokenizer;
 pub mod tool;
+pub mod trace;
+pub mod transcription;
+pub mod tts;
 pub mod vector_store;
 
+pub use rig_types::one_or_many;
+
 // Re-export commonly used types and traits
 pub use completion::message;
 pub use embeddings::Embed;
 pub use one_or_many::{EmptyListError, OneOrMany};
 
 #[cfg(feature = "derive")]
-pub use rig_derive::Embed;
+pub use rig_derive::{prompt, ConversationState, Embed};
@@ -0,0 +1,295 @@
+use std::path::PathBuf;
+
+use serde_json::Value;
+use thiserror::Error;
+
+use super::{
+    file::FileLoaderError,
+    record::{Record, RecordSchema},
+};
+
+#[derive(Error, Debug)]
+pub enum CsvLoaderError {
+    #[error("{0}")]
+    FileLoaderError(#[from] FileLoaderError),
+
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+
+    #[error("Missing field {field:?} in {path:?} at line {line}")]
+    MissingField {
+        path: PathBuf,
+        line: u64,
+        field: String,
+    },
+}
+
+// ================================================================
+// CsvFileLoader definitions and implementations
+// ================================================================
+
+/// [CsvFileLoader] is a utility for loading CSV files from the filesystem using glob patterns
+///  or directory paths, mapping each row to a [Record] according to a [RecordSchema]. The
+///  first row of each file must contain the column names.
+///
+/// # Example Usage
+///
+/// ```rust
+/// use rig::loaders::{CsvFileLoader, RecordSchema};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let schema = RecordSchema::new()
+///         .text_fields(["title", "description"])
+///         .metadata_fields(["id"]);
+///
+///     let records = CsvFileLoader::with_glob("tests/data/*.csv")?
+///         .load(schema)
+///         .ignore_errors()
+///         .into_iter()
+///         .collect::<Vec<_>>();
+///
+///     // The records can be passed to an `EmbeddingsBuilder`, only their text is embedded
+///     Ok(())
+/// }
+/// ```
+///
+/// [CsvFileLoader] uses strict typing between the iterator methods to ensure that transitions
+///  between different implementations of the loaders and it's methods are handled properly by
+///  the compiler.
+pub struct CsvFileLoader<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+    delimiter: u8,
+}
+
+type RecordResult = Result<(PathBuf, Record), CsvLoaderError>;
+
+fn read_rows(
+    path: PathBuf,
+    delimiter: u8,
+    schema: &RecordSchema,
+) -> Result<Vec<RecordResult>, CsvLoaderError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_path(&path)?;
+    let headers = reader.headers()?.clone();
+
+    Ok(reader
+        .records()
+        .map(|row| {
+            let row = row?;
+            let fields = headers
+                .iter()
+                .zip(row.iter())
+                .map(|(name, value)| (name.to_string(), Value::String(value.to_string())))
+                .collect::<Vec<_>>();
+            let get = |name: &str| {
+                fields
+                    .iter()
+                    .find(|(field, _)| field == name)
+                    .map(|(_, value)| value.clone())
+            };
+
+            let record =
+                schema
+                    .build(&fields, get)
+                    .map_err(|field| CsvLoaderError::MissingField {
+                        path: path.clone(),
+                        line: row.position().map(|pos| pos.line()).unwrap_or_default(),
+                        field,
+                    })?;
+            Ok((path.clone(), record))
+        })
+        .collect())
+}
+
+impl<'a> CsvFileLoader<'a, Result<PathBuf, CsvLoaderError>> {
+    /// Sets the field delimiter of the files (defaults to `,`).
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Loads the rows of the files within the iterator returned by [CsvFileLoader::with_glob] or
+    ///  [CsvFileLoader::with_dir] as [Record]s.
+    pub fn load(self, schema: RecordSchema) -> CsvFileLoader<'a, Result<Record, CsvLoaderError>> {
+        let delimiter = self.delimiter;
+        CsvFileLoader {
+            iterator: Box::new(
+                self.load_with_path(schema)
+                    .iterator
+                    .map(|res| res.map(|(_, record)| record)),
+            ),
+            delimiter,
+        }
+    }
+
+    /// Loads the rows of the files within the iterator returned by [CsvFileLoader::with_glob] or
+    ///  [CsvFileLoader::with_dir] as [Record]s along with the path of the file they come from.
+    pub fn load_with_path(
+        self,
+        schema: RecordSchema,
+    ) -> CsvFileLoader<'a, Result<(PathBuf, Record), CsvLoaderError>> {
+        let delimiter = self.delimiter;
+        CsvFileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |path| {
+                match path.and_then(|path| read_rows(path, delimiter, &schema)) {
+                    Ok(rows) => rows,
+                    Err(e) => vec![Err(e)],
+                }
+            })),
+            delimiter,
+        }
+    }
+}
+
+impl<'a, T: 'a> CsvFileLoader<'a, Result<T, CsvLoaderError>> {
+    /// Ignores errors in the iterator, returning only successful results. This can be used on any
+    ///  [CsvFileLoader] state of iterator whose items are results.
+    pub fn ignore_errors(self) -> CsvFileLoader<'a, T> {
+        CsvFileLoader {
+            iterator: Box::new(self.iterator.filter_map(|res| res.ok())),
+            delimiter: self.delimiter,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl CsvFileLoader<'_, Result<PathBuf, FileLoaderError>> {
+    /// Creates a new [CsvFileLoader] using a glob pattern to match files.
+    ///
+    /// # Example
+    /// Create a [CsvFileLoader] for all `.csv` files that match the glob "tests/data/*.csv".
+    ///
+    /// ```rust
+    /// let loader = CsvFileLoader::with_glob("tests/data/*.csv")?;
+    /// ```
+    pub fn with_glob(
+        pattern: &str,
+    ) -> Result<CsvFileLoader<'_, Result<PathBuf, CsvLoaderError>>, CsvLoaderError> {
+        let paths = glob::glob(pattern).map_err(FileLoaderError::PatternError)?;
+        Ok(CsvFileLoader {
+            iterator: Box::new(paths.into_iter().map(|path| {
+                path.map_err(FileLoaderError::GlobError)
+                    .map_err(CsvLoaderError::FileLoaderError)
+            })),
+            delimiter: b',',
+        })
+    }
+
+    /// Creates a new [CsvFileLoader] on all files within a directory.
+    ///
+    /// # Example
+    /// Create a [CsvFileLoader] for all files that are in the directory "files".
+    ///
+    /// ```rust
+    /// let loader = CsvFileLoader::with_dir("files")?;
+    /// ```
+    pub fn with_dir(
+        directory: &str,
+    ) -> Result<CsvFileLoader<'_, Result<PathBuf, CsvLoaderError>>, CsvLoaderError> {
+        Ok(CsvFileLoader {
+            iterator: Box::new(
+                std::fs::read_dir(directory)
+                    .map_err(FileLoaderError::IoError)?
+                    .map(|entry| Ok(entry.map_err(FileLoaderError::IoError)?.path())),
+            ),
+            delimiter: b',',
+        })
+    }
+}
+
+// ================================================================
+// CsvFileLoader iterator implementations
+// ================================================================
+
+pub struct IntoIter<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+impl<'a, T> IntoIterator for CsvFileLoader<'a, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            iterator: self.iterator,
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::{CsvFileLoader, CsvLoaderError};
+    use crate::loaders::RecordSchema;
+
+    #[test]
+    fn test_csv_loader() {
+        let schema = RecordSchema::new()
+            .text_fields(["name", "description"])
+            .metadata_fields(["id", "price"]);
+
+        let records = CsvFileLoader::with_glob("tests/data/products.csv")
+            .unwrap()
+            .load_with_path(schema)
+            .ignore_errors()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].0, PathBuf::from("tests/data/products.csv"));
+        assert_eq!(records[0].1.text, "Widget\nA small, useful widget.");
+        assert_eq!(
+            records[0].1.metadata,
+            *json!({"id": "1", "price": "9.99"}).as_object().unwrap()
+        );
+        assert_eq!(
+            records[2].1.text,
+            "Doohickey\nSays \"hello\"\non two lines."
+        );
+
+        let results = CsvFileLoader::with_glob("tests/data/products.csv")
+            .unwrap()
+            .load(RecordSchema::new().text_fields(["summary"]))
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            &results[0],
+            Err(CsvLoaderError::MissingField { line: 2, field, .. }) if field == "summary"
+        ));
+    }
+
+    #[test]
+    fn test_csv_loader_errors() {
+        use assert_fs::{prelude::*, TempDir};
+
+        let dir = TempDir::new().unwrap();
+        dir.child("products.csv")
+            .write_str("name;price\nWidget;9.99\nGadget;19.99;extra\n")
+            .unwrap();
+
+        let results = CsvFileLoader::with_dir(dir.path().to_str().unwrap())
+            .unwrap()
+            .delimiter(b';')
+            .load(RecordSchema::new().text_fields(["name"]))
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().text, "Widget");
+        assert!(matches!(results[1], Err(CsvLoaderError::CsvError(_))));
+
+        assert!(CsvFileLoader::with_glob("tests/data/[").is_err());
+        assert!(CsvFileLoader::with_dir("tests/missing").is_err());
+    }
+}
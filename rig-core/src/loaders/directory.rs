@@ -0,0 +1,441 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::file::FileLoaderError;
+use crate::{
+    chunking::TextSplitter,
+    embeddings::embed::{Embed, EmbedError, TextEmbedder},
+};
+
+#[derive(Error, Debug)]
+pub enum DirectoryLoaderError {
+    #[error("{0}")]
+    FileLoaderError(#[from] FileLoaderError),
+
+    #[error("Pattern error: {0}")]
+    PatternError(#[from] glob::PatternError),
+
+    #[cfg(feature = "pdf")]
+    #[error("{0}")]
+    PdfLoaderError(#[from] super::pdf::PdfLoaderError),
+
+    #[cfg(feature = "epub")]
+    #[error("{0}")]
+    EpubLoaderError(#[from] super::epub::EpubLoaderError),
+
+    #[cfg(feature = "docx")]
+    #[error("{0}")]
+    DocxLoaderError(#[from] super::docx::DocxLoaderError),
+
+    #[cfg(feature = "html")]
+    #[error("{0}")]
+    HtmlLoaderError(#[from] super::html::HtmlLoaderError),
+}
+
+/// Document loaded by the [DirectoryLoader]. When embedded, only the text is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDocument {
+    /// Path of the file, including the directory the loader was created with
+    pub path: PathBuf,
+    pub file_name: String,
+    /// Index of the chunk within the file, if the loader splits files into chunks
+    pub chunk: Option<usize>,
+    pub text: String,
+}
+
+impl Embed for FileDocument {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.text.clone());
+        Ok(())
+    }
+}
+
+/// Extensions of plain text files.
+const TEXT_EXTENSIONS: [&str; 5] = ["txt", "md", "markdown", "mdx", "rst"];
+
+/// [DirectoryLoader] walks a directory tree and loads every matching file with the loader
+///  corresponding to its type:
+/// - `pdf` files with the [PdfFileLoader](super::PdfFileLoader) (requires the `pdf` feature)
+/// - `epub` files with the [EpubFileLoader](super::EpubFileLoader) (requires the `epub` feature)
+/// - `docx` files with the [DocxFileLoader](super::DocxFileLoader) (requires the `docx` feature)
+/// - `html` and `htm` files with the [HtmlFileLoader](super::HtmlFileLoader) (requires the
+///   `html` feature)
+/// - any other file is read as plain text
+///
+/// By default, the tree is traversed recursively, hidden files and directories are skipped and
+///  only files of a supported type (plain text files being `txt`, `md`, `markdown`, `mdx` and
+///  `rst` files) are loaded.
+///
+/// # Example Usage
+///
+/// ```rust
+/// use rig::{chunking::MarkdownSplitter, loaders::DirectoryLoader};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let documents = DirectoryLoader::new("docs")
+///         .exclude("drafts/**")?
+///         .extensions(["md", "pdf"])
+///         .chunk(MarkdownSplitter::new(1000, 100))
+///         .load()?
+///         .filter_map(Result::ok)
+///         .collect::<Vec<_>>();
+///
+///     // The documents can be passed to an `EmbeddingsBuilder`
+///     Ok(())
+/// }
+/// ```
+pub struct DirectoryLoader<'a> {
+    root: PathBuf,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    extensions: Option<Vec<String>>,
+    max_depth: Option<usize>,
+    include_hidden: bool,
+    splitter: Option<Box<dyn TextSplitter + 'a>>,
+}
+
+impl<'a> DirectoryLoader<'a> {
+    /// Create a new [DirectoryLoader] for the directory at `root`.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            include: vec![],
+            exclude: vec![],
+            extensions: None,
+            max_depth: None,
+            include_hidden: false,
+            splitter: None,
+        }
+    }
+
+    /// Only load files whose path relative to the root matches the glob `pattern` (e.g.:
+    ///  `guides/**/*.md`). If called multiple times, files matching any of the patterns are loaded.
+    pub fn glob(mut self, pattern: &str) -> Result<Self, DirectoryLoaderError> {
+        self.include.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Skip files and directories whose path relative to the root matches the glob `pattern`.
+    pub fn exclude(mut self, pattern: &str) -> Result<Self, DirectoryLoaderError> {
+        self.exclude.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Only load files with one of the given extensions (case insensitive, without the leading
+    ///  dot). Files with an extension that is not of a supported type are read as plain text.
+    pub fn extensions(mut self, extensions: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.extensions = Some(
+            extensions
+                .into_iter()
+                .map(|ext| ext.into().trim_start_matches('.').to_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Whether to traverse subdirectories (defaults to `true`).
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.max_depth = if recursive { None } else { Some(0) };
+        self
+    }
+
+    /// Maximum depth of subdirectories to traverse, `0` meaning only the root directory.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Whether to load hidden files and traverse hidden directories (defaults to `false`).
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Split the text of each file into chunks using the given [TextSplitter], each chunk
+    ///  becoming its own [FileDocument].
+    pub fn chunk(mut self, splitter: impl TextSplitter + 'a) -> Self {
+        self.splitter = Some(Box::new(splitter));
+        self
+    }
+
+    /// Walk the directory tree and return an iterator loading the matching files, sorted by path.
+    ///  Errors listing the root directory are returned immediately, errors listing subdirectories
+    ///  or loading files are yielded by the iterator.
+    pub fn load(
+        self,
+    ) -> Result<
+        impl Iterator<Item = Result<FileDocument, DirectoryLoaderError>> + 'a,
+        DirectoryLoaderError,
+    > {
+        let mut paths = vec![];
+        self.walk(&self.root, 0, &mut paths)?;
+        paths.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => a.cmp(b),
+            (Err(_), Ok(_)) => std::cmp::Ordering::Less,
+            (Ok(_), Err(_)) => std::cmp::Ordering::Greater,
+            (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+        });
+
+        let splitter = self.splitter;
+        Ok(paths.into_iter().flat_map(move |path| {
+            let documents = path.and_then(|path| {
+                let text = read(&path)?;
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                Ok(match &splitter {
+                    Some(splitter) => splitter
+                        .split(&text)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, chunk)| FileDocument {
+                            path: path.clone(),
+                            file_name: file_name.clone(),
+                            chunk: Some(i),
+                            text: chunk,
+                        })
+                        .collect(),
+                    None => vec![FileDocument {
+                        path,
+                        file_name,
+                        chunk: None,
+                        text,
+                    }],
+                })
+            });
+
+            match documents {
+                Ok(documents) => documents.into_iter().map(Ok).collect::<Vec<_>>(),
+                Err(e) => vec![Err(e)],
+            }
+        }))
+    }
+
+    fn walk(
+        &self,
+        dir: &Path,
+        depth: usize,
+        paths: &mut Vec<Result<PathBuf, DirectoryLoaderError>>,
+    ) -> Result<(), DirectoryLoaderError> {
+        for entry in fs::read_dir(dir).map_err(FileLoaderError::IoError)? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    paths.push(Err(FileLoaderError::IoError(e).into()));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if (hidden && !self.include_hidden) || self.matches(&self.exclude, relative) {
+                continue;
+            }
+
+            // Symlinked directories are not traversed to avoid cycles
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            if is_dir {
+                if self.max_depth.is_none_or(|max_depth| depth < max_depth) {
+                    if let Err(e) = self.walk(&path, depth + 1, paths) {
+                        paths.push(Err(e));
+                    }
+                }
+            } else if path.is_file()
+                && (self.include.is_empty() || self.matches(&self.include, relative))
+                && self.has_extension(&path)
+            {
+                paths.push(Ok(path));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn matches(&self, patterns: &[Pattern], path: &Path) -> bool {
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: false,
+            require_literal_leading_dot: false,
+        };
+        patterns
+            .iter()
+            .any(|pattern| pattern.matches_path_with(path, options))
+    }
+
+    fn has_extension(&self, path: &Path) -> bool {
+        let extension = extension(path);
+        match &self.extensions {
+            Some(extensions) => extensions.contains(&extension),
+            None => is_supported(&extension),
+        }
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn is_supported(extension: &str) -> bool {
+    TEXT_EXTENSIONS.contains(&extension)
+        || (cfg!(feature = "pdf") && extension == "pdf")
+        || (cfg!(feature = "epub") && extension == "epub")
+        || (cfg!(feature = "docx") && extension == "docx")
+        || (cfg!(feature = "html") && matches!(extension, "html" | "htm"))
+}
+
+/// Read the text of a file with the loader corresponding to its type.
+fn read(path: &Path) -> Result<String, DirectoryLoaderError> {
+    // The concrete loaders take glob patterns, which must match this exact path
+    #[cfg(any(feature = "pdf", feature = "epub", feature = "docx", feature = "html"))]
+    let pattern = Pattern::escape(&path.to_string_lossy());
+
+    match extension(path).as_str() {
+        #[cfg(feature = "pdf")]
+        "pdf" => Ok(super::PdfFileLoader::with_glob(&pattern)?
+            .read()
+            .into_iter()
+            .next()
+            .ok_or_else(|| not_found(path))??),
+        #[cfg(feature = "epub")]
+        "epub" => Ok(
+            super::EpubFileLoader::<_, super::StripXmlProcessor>::with_glob(&pattern)?
+                .read()
+                .into_iter()
+                .next()
+                .ok_or_else(|| not_found(path))??,
+        ),
+        #[cfg(feature = "docx")]
+        "docx" => Ok(super::DocxFileLoader::with_glob(&pattern)?
+            .read()
+            .into_iter()
+            .next()
+            .ok_or_else(|| not_found(path))??),
+        #[cfg(feature = "html")]
+        "html" | "htm" => Ok(super::HtmlFileLoader::with_glob(&pattern)?
+            .read()
+            .into_iter()
+            .next()
+            .ok_or_else(|| not_found(path))??),
+        _ => Ok(fs::read_to_string(path).map_err(FileLoaderError::IoError)?),
+    }
+}
+
+#[cfg(any(feature = "pdf", feature = "epub", feature = "docx", feature = "html"))]
+fn not_found(path: &Path) -> DirectoryLoaderError {
+    FileLoaderError::IoError(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("{} not found", path.display()),
+    ))
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::{FileTouch, FileWriteStr, PathChild};
+
+    use super::DirectoryLoader;
+    use crate::chunking::FixedSizeSplitter;
+
+    #[test]
+    fn test_directory_loader() {
+        let temp = assert_fs::TempDir::new().expect("Failed to create temp dir");
+        temp.child("readme.md").write_str("# Readme").unwrap();
+        temp.child("notes.txt").write_str("Some notes").unwrap();
+        temp.child("image.png").touch().unwrap();
+        temp.child(".hidden.md").write_str("Hidden").unwrap();
+        temp.child("guides/intro.md").write_str("Intro").unwrap();
+        temp.child("guides/deep/advanced.md")
+            .write_str("Advanced")
+            .unwrap();
+        temp.child("drafts/wip.md").write_str("WIP").unwrap();
+
+        let load = |loader: DirectoryLoader| {
+            loader
+                .load()
+                .unwrap()
+                .map(|doc| {
+                    let doc = doc.unwrap();
+                    let relative = doc.path.strip_prefix(temp.path()).unwrap();
+                    relative.to_string_lossy().replace('\\', "/")
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            load(DirectoryLoader::new(temp.path())),
+            vec![
+                "drafts/wip.md",
+                "guides/deep/advanced.md",
+                "guides/intro.md",
+                "notes.txt",
+                "readme.md"
+            ]
+        );
+        assert_eq!(
+            load(
+                DirectoryLoader::new(temp.path())
+                    .exclude("drafts")
+                    .unwrap()
+                    .extensions([".MD"])
+                    .max_depth(1)
+            ),
+            vec!["guides/intro.md", "readme.md"]
+        );
+        assert_eq!(
+            load(
+                DirectoryLoader::new(temp.path())
+                    .glob("guides/*.md")
+                    .unwrap()
+            ),
+            vec!["guides/deep/advanced.md", "guides/intro.md"]
+        );
+        assert_eq!(
+            load(
+                DirectoryLoader::new(temp.path())
+                    .recursive(false)
+                    .include_hidden(true)
+            ),
+            vec![".hidden.md", "notes.txt", "readme.md"]
+        );
+
+        let docs = DirectoryLoader::new(temp.path())
+            .glob("notes.txt")
+            .unwrap()
+            .chunk(FixedSizeSplitter::new(5, 0))
+            .load()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[1].file_name, "notes.txt");
+        assert_eq!(docs[1].chunk, Some(1));
+        assert_eq!(docs[1].text, "notes");
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn test_directory_loader_pdf() {
+        let docs = DirectoryLoader::new("tests/data")
+            .extensions(["pdf"])
+            .recursive(false)
+            .load()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].file_name, "dummy.pdf");
+        assert_eq!(docs[0].text, "Test\nPDF\nDocument\n");
+    }
+}
@@ -0,0 +1,574 @@
+use std::{
+    fs,
+    io::{Cursor, Read},
+    path::PathBuf,
+};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zip::{result::ZipError, ZipArchive};
+
+use super::{
+    file::FileLoaderError,
+    markdown::{MarkdownDocument, MarkdownSection},
+};
+use crate::{
+    chunking::TextSplitter,
+    embeddings::embed::{Embed, EmbedError, TextEmbedder},
+};
+
+#[derive(Error, Debug)]
+pub enum DocxLoaderError {
+    #[error("{0}")]
+    FileLoaderError(#[from] FileLoaderError),
+
+    #[error("Zip error: {0}")]
+    ZipError(#[from] ZipError),
+
+    #[error("XML error: {0}")]
+    XmlError(#[from] quick_xml::Error),
+}
+
+// ================================================================
+// Text extraction
+// ================================================================
+
+/// Text of a Word (`.docx`) document, as extracted by the [DocxFileLoader].
+/// When embedded, only the text of the document is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocxDocument {
+    /// Path of the file the document was loaded from, if any
+    pub source: Option<String>,
+    /// Title of the document properties, or else the first paragraph of the `Title` style
+    pub title: Option<String>,
+    /// Text of the document, as Markdown: headings are prefixed with `#`, list items with `-`,
+    ///  and the cells of each table row are separated by `|`
+    pub text: String,
+}
+
+impl DocxDocument {
+    /// Extracts the text of a Word document from the bytes of its `.docx` file.
+    pub fn parse(bytes: &[u8]) -> Result<Self, DocxLoaderError> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+        let document = read_entry(&mut archive, "word/document.xml")?;
+        let (text, title_paragraph) = extract_text(&document)?;
+
+        let title = match read_entry(&mut archive, "docProps/core.xml") {
+            Ok(core) => extract_title(&core)?,
+            Err(DocxLoaderError::ZipError(ZipError::FileNotFound)) => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(DocxDocument {
+            source: None,
+            title: title.or(title_paragraph),
+            text,
+        })
+    }
+
+    /// Sections of the document delimited by its headings, keeping track of the heading
+    ///  hierarchy (see [MarkdownDocument::parse]).
+    pub fn sections(&self) -> Vec<MarkdownSection> {
+        MarkdownDocument::parse(&self.text)
+            .sections
+            .into_iter()
+            .map(|section| MarkdownSection {
+                source: self.source.clone(),
+                ..section
+            })
+            .collect()
+    }
+
+    fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
+
+impl Embed for DocxDocument {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.text.clone());
+        Ok(())
+    }
+}
+
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<String, DocxLoaderError> {
+    let mut contents = String::new();
+    archive
+        .by_name(name)?
+        .read_to_string(&mut contents)
+        .map_err(FileLoaderError::IoError)?;
+    Ok(contents)
+}
+
+/// `w:val` attribute of an element
+fn val(element: &BytesStart) -> Result<Option<String>, DocxLoaderError> {
+    Ok(
+        match element
+            .try_get_attribute("w:val")
+            .map_err(quick_xml::Error::from)?
+        {
+            Some(attribute) => Some(attribute.unescape_value()?.into_owned()),
+            None => None,
+        },
+    )
+}
+
+/// Heading level of a paragraph style, e.g.: `Heading2` or `heading 2`
+fn heading_level(style: &str) -> Option<usize> {
+    if style == "Title" {
+        return Some(1);
+    }
+    let level = style
+        .strip_prefix("Heading")
+        .or_else(|| style.strip_prefix("heading"))?
+        .trim()
+        .parse::<usize>()
+        .ok()?;
+    Some(level.clamp(1, 6))
+}
+
+#[derive(Default)]
+struct Paragraph {
+    text: String,
+    level: Option<usize>,
+    title: bool,
+    list_item: bool,
+}
+
+/// Text of `word/document.xml` as Markdown, with the first paragraph of the `Title` style
+fn extract_text(xml: &str) -> Result<(String, Option<String>), DocxLoaderError> {
+    let mut reader = Reader::from_str(xml);
+
+    let mut blocks: Vec<String> = vec![];
+    let mut title = None;
+    let mut paragraph: Option<Paragraph> = None;
+    let mut in_run = false;
+    let mut in_text = false;
+    let mut table_depth = 0;
+    let mut rows: Vec<Vec<String>> = vec![];
+    let mut cell = String::new();
+
+    loop {
+        let event = reader.read_event()?;
+        let start = matches!(event, Event::Start(_));
+        match event {
+            Event::Start(element) | Event::Empty(element) => {
+                match element.local_name().as_ref() {
+                    b"p" => paragraph = Some(Paragraph::default()),
+                    b"r" => in_run = start,
+                    b"t" => in_text = start,
+                    // Tabs outside of runs are tab stops
+                    b"tab" if in_run => {
+                        if let Some(paragraph) = &mut paragraph {
+                            paragraph.text.push('\t');
+                        }
+                    }
+                    b"br" | b"cr" if in_run => {
+                        if let Some(paragraph) = &mut paragraph {
+                            paragraph.text.push('\n');
+                        }
+                    }
+                    b"pStyle" => {
+                        if let (Some(paragraph), Some(style)) = (&mut paragraph, val(&element)?) {
+                            paragraph.title = style == "Title";
+                            paragraph.level = paragraph.level.or(heading_level(&style));
+                        }
+                    }
+                    b"outlineLvl" => {
+                        let level = val(&element)?.and_then(|level| level.parse::<usize>().ok());
+                        if let (Some(paragraph), Some(level)) = (&mut paragraph, level) {
+                            // Level 9 is the body text
+                            if level < 9 {
+                                paragraph.level = Some((level + 1).min(6));
+                            }
+                        }
+                    }
+                    b"numPr" => {
+                        if let Some(paragraph) = &mut paragraph {
+                            paragraph.list_item = true;
+                        }
+                    }
+                    b"tbl" => table_depth += 1,
+                    b"tr" if table_depth == 1 => rows.push(vec![]),
+                    b"tc" if table_depth == 1 => cell.clear(),
+                    _ => {}
+                }
+            }
+            Event::Text(text) if in_text => {
+                if let Some(paragraph) = &mut paragraph {
+                    paragraph.text.push_str(&text.unescape()?);
+                }
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"r" => in_run = false,
+                b"t" => in_text = false,
+                b"p" => {
+                    let Some(paragraph) = paragraph.take() else {
+                        continue;
+                    };
+                    let text = paragraph.text.trim();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if table_depth > 0 {
+                        if !cell.is_empty() {
+                            cell.push(' ');
+                        }
+                        cell.push_str(&text.replace('\n', " "));
+                        continue;
+                    }
+                    if paragraph.title && title.is_none() {
+                        title = Some(text.to_string());
+                    }
+                    blocks.push(match (paragraph.level, paragraph.list_item) {
+                        (Some(level), _) => format!("{} {}", "#".repeat(level), text),
+                        (None, true) => format!("- {text}"),
+                        (None, false) => text.to_string(),
+                    });
+                }
+                b"tc" if table_depth == 1 => {
+                    if let Some(row) = rows.last_mut() {
+                        row.push(std::mem::take(&mut cell));
+                    }
+                }
+                b"tbl" => {
+                    table_depth -= 1;
+                    if table_depth == 0 {
+                        let table = std::mem::take(&mut rows)
+                            .into_iter()
+                            .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+                            .map(|row| row.join(" | "))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        if !table.is_empty() {
+                            blocks.push(table);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    // List items are kept together
+    let mut text = String::new();
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            let list = block.starts_with("- ") && blocks[i - 1].starts_with("- ");
+            text.push_str(if list { "\n" } else { "\n\n" });
+        }
+        text.push_str(block);
+    }
+    Ok((text, title))
+}
+
+/// `dc:title` of `docProps/core.xml`
+fn extract_title(xml: &str) -> Result<Option<String>, DocxLoaderError> {
+    let mut reader = Reader::from_str(xml);
+    let mut in_title = false;
+    let mut title = String::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) if element.local_name().as_ref() == b"title" => in_title = true,
+            Event::End(element) if element.local_name().as_ref() == b"title" => in_title = false,
+            Event::Text(text) if in_title => title.push_str(&text.unescape()?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let title = title.trim();
+    Ok((!title.is_empty()).then(|| title.to_string()))
+}
+
+// ================================================================
+// DocxFileLoader definitions and implementations
+// ================================================================
+
+/// [DocxFileLoader] is a utility for loading Word (`.docx`) files from the filesystem using glob
+///  patterns or directory paths and extracting their text, keeping their headings, lists and
+///  tables.
+///
+/// # Example Usage
+///
+/// ```rust
+/// use rig::loaders::DocxFileLoader;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     // Load the sections of the docx files, with their heading hierarchy, ignoring any errors
+///     let sections = DocxFileLoader::with_glob("reports/*.docx")?
+///         .load()
+///         .ignore_errors()
+///         .sections()
+///         .into_iter()
+///         .collect::<Vec<_>>();
+///
+///     // The sections can be passed to an `EmbeddingsBuilder`
+///     Ok(())
+/// }
+/// ```
+///
+/// [DocxFileLoader] uses strict typing between the iterator methods to ensure that transitions
+///  between different implementations of the loaders and it's methods are handled properly by
+///  the compiler.
+pub struct DocxFileLoader<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+fn load_file(
+    path: Result<PathBuf, DocxLoaderError>,
+) -> Result<(PathBuf, DocxDocument), DocxLoaderError> {
+    let path = path?;
+    let bytes = fs::read(&path).map_err(FileLoaderError::IoError)?;
+    let doc = DocxDocument::parse(&bytes)?.with_source(path.to_string_lossy());
+    Ok((path, doc))
+}
+
+impl<'a> DocxFileLoader<'a, Result<PathBuf, DocxLoaderError>> {
+    /// Loads the docx files within the iterator returned by [DocxFileLoader::with_glob] or
+    ///  [DocxFileLoader::with_dir] as [DocxDocument]s.
+    pub fn load(self) -> DocxFileLoader<'a, Result<DocxDocument, DocxLoaderError>> {
+        DocxFileLoader {
+            iterator: Box::new(self.iterator.map(|res| load_file(res).map(|(_, doc)| doc))),
+        }
+    }
+
+    /// Loads the docx files within the iterator returned by [DocxFileLoader::with_glob] or
+    ///  [DocxFileLoader::with_dir] as [DocxDocument]s along with their path.
+    pub fn load_with_path(
+        self,
+    ) -> DocxFileLoader<'a, Result<(PathBuf, DocxDocument), DocxLoaderError>> {
+        DocxFileLoader {
+            iterator: Box::new(self.iterator.map(load_file)),
+        }
+    }
+
+    /// Directly reads the text of the docx files within the iterator returned by
+    ///  [DocxFileLoader::with_glob] or [DocxFileLoader::with_dir].
+    pub fn read(self) -> DocxFileLoader<'a, Result<String, DocxLoaderError>> {
+        DocxFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .map(|res| load_file(res).map(|(_, doc)| doc.text)),
+            ),
+        }
+    }
+
+    /// Directly reads the text of the docx files within the iterator returned by
+    ///  [DocxFileLoader::with_glob] or [DocxFileLoader::with_dir] and returns the path along
+    ///  with the content.
+    pub fn read_with_path(self) -> DocxFileLoader<'a, Result<(PathBuf, String), DocxLoaderError>> {
+        DocxFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .map(|res| load_file(res).map(|(path, doc)| (path, doc.text))),
+            ),
+        }
+    }
+}
+
+impl<'a> DocxFileLoader<'a, DocxDocument> {
+    /// Splits each docx document into its sections (see [DocxDocument::sections]).
+    pub fn sections(self) -> DocxFileLoader<'a, MarkdownSection> {
+        DocxFileLoader {
+            iterator: Box::new(self.iterator.flat_map(|doc| doc.sections())),
+        }
+    }
+}
+
+impl<'a> DocxFileLoader<'a, String> {
+    /// Splits the text of each docx file into chunks using the given [TextSplitter].
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> DocxFileLoader<'a, String> {
+        DocxFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .flat_map(move |contents| splitter.split(&contents)),
+            ),
+        }
+    }
+}
+
+impl<'a> DocxFileLoader<'a, (PathBuf, String)> {
+    /// Splits the text of each docx file into chunks using the given [TextSplitter], keeping
+    ///  track of the path of the file each chunk comes from.
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> DocxFileLoader<'a, (PathBuf, String)> {
+        DocxFileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |(path, contents)| {
+                splitter
+                    .split(&contents)
+                    .into_iter()
+                    .map(move |chunk| (path.clone(), chunk))
+            })),
+        }
+    }
+}
+
+impl<'a, T: 'a> DocxFileLoader<'a, Result<T, DocxLoaderError>> {
+    /// Ignores errors in the iterator, returning only successful results. This can be used on any
+    ///  [DocxFileLoader] state of iterator whose items are results.
+    pub fn ignore_errors(self) -> DocxFileLoader<'a, T> {
+        DocxFileLoader {
+            iterator: Box::new(self.iterator.filter_map(|res| res.ok())),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DocxFileLoader<'_, Result<PathBuf, FileLoaderError>> {
+    /// Creates a new [DocxFileLoader] using a glob pattern to match files.
+    ///
+    /// # Example
+    /// Create a [DocxFileLoader] for all `.docx` files that match the glob "reports/*.docx".
+    ///
+    /// ```rust
+    /// let loader = DocxFileLoader::with_glob("reports/*.docx")?;
+    /// ```
+    pub fn with_glob(
+        pattern: &str,
+    ) -> Result<DocxFileLoader<'_, Result<PathBuf, DocxLoaderError>>, DocxLoaderError> {
+        let paths = glob::glob(pattern).map_err(FileLoaderError::PatternError)?;
+        Ok(DocxFileLoader {
+            iterator: Box::new(paths.into_iter().map(|path| {
+                path.map_err(FileLoaderError::GlobError)
+                    .map_err(DocxLoaderError::FileLoaderError)
+            })),
+        })
+    }
+
+    /// Creates a new [DocxFileLoader] on all files within a directory.
+    ///
+    /// # Example
+    /// Create a [DocxFileLoader] for all files that are in the directory "reports".
+    ///
+    /// ```rust
+    /// let loader = DocxFileLoader::with_dir("reports")?;
+    /// ```
+    pub fn with_dir(
+        directory: &str,
+    ) -> Result<DocxFileLoader<'_, Result<PathBuf, DocxLoaderError>>, DocxLoaderError> {
+        Ok(DocxFileLoader {
+            iterator: Box::new(
+                fs::read_dir(directory)
+                    .map_err(FileLoaderError::IoError)?
+                    .map(|entry| Ok(entry.map_err(FileLoaderError::IoError)?.path())),
+            ),
+        })
+    }
+}
+
+// ================================================================
+// DocxFileLoader iterator implementations
+// ================================================================
+
+pub struct IntoIter<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+impl<'a, T> IntoIterator for DocxFileLoader<'a, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            iterator: self.iterator,
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Cursor, Write},
+        path::PathBuf,
+    };
+
+    use zip::{result::ZipError, write::SimpleFileOptions, ZipWriter};
+
+    use super::{DocxDocument, DocxFileLoader, DocxLoaderError};
+
+    /// Bytes of a `.docx` file with the given entries
+    fn docx(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(vec![]));
+        for (name, contents) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_docx_loader() {
+        let actual = DocxFileLoader::with_glob("tests/data/*.docx")
+            .unwrap()
+            .load_with_path()
+            .ignore_errors()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        assert_eq!(actual.len(), 1);
+        let (path, doc) = &actual[0];
+        assert_eq!(path, &PathBuf::from("tests/data/report.docx"));
+        assert_eq!(doc.title.as_deref(), Some("Quarterly Report"));
+        assert_eq!(
+            doc.text,
+            "# Quarterly Report\n\n## Sales\n\nSales grew by 10% & more.\n\n\
+            - Europe\n- Asia\n\nRegion | Sales\nEurope | 120\n\n## Outlook\n\nStable."
+        );
+
+        let sections = doc.sections();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].headings, vec!["Quarterly Report", "Sales"]);
+        assert_eq!(
+            sections[0].source.as_deref(),
+            Some("tests/data/report.docx")
+        );
+        assert_eq!(sections[1].text, "Stable.");
+    }
+
+    #[test]
+    fn test_docx_errors() {
+        // Documents without properties are titled by their `Title` paragraph, if any
+        let document = DocxDocument::parse(&docx(&[(
+            "word/document.xml",
+            "<w:document><w:body><w:p><w:r><w:t>Text</w:t></w:r></w:p></w:body></w:document>",
+        )]))
+        .unwrap();
+        assert_eq!(document.title, None);
+        assert_eq!(document.text, "Text");
+
+        assert!(matches!(
+            DocxDocument::parse(b"not a zip"),
+            Err(DocxLoaderError::ZipError(_))
+        ));
+        assert!(matches!(
+            DocxDocument::parse(&docx(&[("docProps/core.xml", "<cp:coreProperties/>")])),
+            Err(DocxLoaderError::ZipError(ZipError::FileNotFound))
+        ));
+        assert!(matches!(
+            DocxDocument::parse(&docx(&[(
+                "word/document.xml",
+                "<w:document><w:body></w:p></w:document>"
+            )])),
+            Err(DocxLoaderError::XmlError(_))
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::chunking::TextSplitter;
+#[cfg(not(target_arch = "wasm32"))]
 use crate::loaders::file::FileLoaderError;
 use epub::doc::EpubDoc;
 
@@ -333,6 +335,72 @@ impl<'a, P, T: 'a> EpubFileLoader<'a, Result<T, EpubLoaderError>, P> {
     }
 }
 
+impl<'a, P: 'a> EpubFileLoader<'a, String, P> {
+    /// Splits the contents of each epub (or chapter) into chunks using the given [TextSplitter].
+    ///
+    /// # Example
+    /// ```rust
+    /// let chunks = EpubFileLoader::<_, StripXmlProcessor>::with_glob("tests/data/*.epub")?
+    ///     .read()
+    ///     .ignore_errors()
+    ///     .chunk(RecursiveSplitter::new(1000, 200));
+    /// ```
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> EpubFileLoader<'a, String, P> {
+        EpubFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .flat_map(move |contents| splitter.split(&contents)),
+            ),
+            _processor: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: 'a> EpubFileLoader<'a, (PathBuf, String), P> {
+    /// Splits the contents of each epub into chunks using the given [TextSplitter], keeping
+    ///  track of the path of the epub each chunk comes from.
+    pub fn chunk(
+        self,
+        splitter: impl TextSplitter + 'a,
+    ) -> EpubFileLoader<'a, (PathBuf, String), P> {
+        EpubFileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |(path, contents)| {
+                splitter
+                    .split(&contents)
+                    .into_iter()
+                    .map(move |chunk| (path.clone(), chunk))
+            })),
+            _processor: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: 'a> EpubFileLoader<'a, (PathBuf, Vec<(usize, String)>), P> {
+    /// Splits each chapter into chunks using the given [TextSplitter], keeping track of the
+    ///  chapter number each chunk comes from. Chunks never span multiple chapters.
+    pub fn chunk(
+        self,
+        splitter: impl TextSplitter + 'a,
+    ) -> EpubFileLoader<'a, (PathBuf, Vec<(usize, String)>), P> {
+        EpubFileLoader {
+            iterator: Box::new(self.iterator.map(move |(path, chapters)| {
+                let chunks = chapters
+                    .into_iter()
+                    .flat_map(|(chapter_no, content)| {
+                        splitter
+                            .split(&content)
+                            .into_iter()
+                            .map(move |chunk| (chapter_no, chunk))
+                    })
+                    .collect();
+                (path, chunks)
+            })),
+            _processor: PhantomData,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 impl<P> EpubFileLoader<'_, Result<PathBuf, FileLoaderError>, P> {
     /// Creates a new [EpubFileLoader] using a glob pattern to match files.
     ///
@@ -344,7 +412,7 @@ impl<P> EpubFileLoader<'_, Result<PathBuf, FileLoaderError>, P> {
     /// ```
     pub fn with_glob(
         pattern: &str,
-    ) -> Result<EpubFileLoader<Result<PathBuf, EpubLoaderError>, P>, EpubLoaderError> {
+    ) -> Result<EpubFileLoader<'_, Result<PathBuf, EpubLoaderError>, P>, EpubLoaderError> {
         let paths = glob::glob(pattern).map_err(FileLoaderError::PatternError)?;
 
         Ok(EpubFileLoader {
@@ -366,7 +434,7 @@ impl<P> EpubFileLoader<'_, Result<PathBuf, FileLoaderError>, P> {
     /// ```
     pub fn with_dir(
         directory: &str,
-    ) -> Result<EpubFileLoader<Result<PathBuf, EpubLoaderError>, P>, EpubLoaderError> {
+    ) -> Result<EpubFileLoader<'_, Result<PathBuf, EpubLoaderError>, P>, EpubLoaderError> {
         let paths = std::fs::read_dir(directory).map_err(FileLoaderError::IoError)?;
 
         Ok(EpubFileLoader {
@@ -484,7 +552,7 @@ mod tests {
         assert_eq!(chapters.len(), 3);
 
         for chapter in chapters {
-            assert!(matches!(chapter.1, Ok(_)));
+            assert!(chapter.1.is_ok());
         }
     }
 
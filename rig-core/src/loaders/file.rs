@@ -1,8 +1,9 @@
 use std::{fs, path::PathBuf};
 
-use glob::glob;
 use thiserror::Error;
 
+use crate::chunking::TextSplitter;
+
 #[derive(Error, Debug)]
 pub enum FileLoaderError {
     #[error("Invalid glob pattern: {0}")]
@@ -162,6 +163,52 @@ impl<'a, T: 'a> FileLoader<'a, Result<T, FileLoaderError>> {
     }
 }
 
+impl<'a> FileLoader<'a, String> {
+    /// Splits the contents of each file into chunks using the given [TextSplitter].
+    ///
+    /// # Example
+    /// Read files in directory "files/*.txt" and split them into chunks of up to 1000 characters.
+    ///
+    /// ```rust
+    /// let chunks = FileLoader::with_glob("files/*.txt")?
+    ///     .read()
+    ///     .ignore_errors()
+    ///     .chunk(RecursiveSplitter::new(1000, 200));
+    /// ```
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> FileLoader<'a, String> {
+        FileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .flat_map(move |contents| splitter.split(&contents)),
+            ),
+        }
+    }
+}
+
+impl<'a> FileLoader<'a, (PathBuf, String)> {
+    /// Splits the contents of each file into chunks using the given [TextSplitter],
+    ///  keeping track of the path of the file each chunk comes from.
+    ///
+    /// # Example
+    /// ```rust
+    /// let chunks = FileLoader::with_glob("files/*.txt")?
+    ///     .read_with_path()
+    ///     .ignore_errors()
+    ///     .chunk(RecursiveSplitter::new(1000, 200));
+    /// ```
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> FileLoader<'a, (PathBuf, String)> {
+        FileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |(path, contents)| {
+                splitter
+                    .split(&contents)
+                    .into_iter()
+                    .map(move |chunk| (path.clone(), chunk))
+            })),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 impl FileLoader<'_, Result<PathBuf, FileLoaderError>> {
     /// Creates a new [FileLoader] using a glob pattern to match files.
     ///
@@ -173,8 +220,8 @@ impl FileLoader<'_, Result<PathBuf, FileLoaderError>> {
     /// ```
     pub fn with_glob(
         pattern: &str,
-    ) -> Result<FileLoader<Result<PathBuf, FileLoaderError>>, FileLoaderError> {
-        let paths = glob(pattern)?;
+    ) -> Result<FileLoader<'_, Result<PathBuf, FileLoaderError>>, FileLoaderError> {
+        let paths = glob::glob(pattern)?;
         Ok(FileLoader {
             iterator: Box::new(
                 paths
@@ -194,7 +241,7 @@ impl FileLoader<'_, Result<PathBuf, FileLoaderError>> {
     /// ```
     pub fn with_dir(
         directory: &str,
-    ) -> Result<FileLoader<Result<PathBuf, FileLoaderError>>, FileLoaderError> {
+    ) -> Result<FileLoader<'_, Result<PathBuf, FileLoaderError>>, FileLoaderError> {
         Ok(FileLoader {
             iterator: Box::new(fs::read_dir(directory)?.filter_map(|entry| {
                 let path = entry.ok()?.path();
@@ -240,6 +287,7 @@ mod tests {
     use assert_fs::prelude::{FileTouch, FileWriteStr, PathChild};
 
     use super::FileLoader;
+    use crate::chunking::RecursiveSplitter;
 
     #[test]
     fn test_file_loader() {
@@ -270,4 +318,37 @@ mod tests {
         assert!(!actual.is_empty());
         assert!(expected == actual)
     }
+
+    #[test]
+    fn test_file_loader_chunk() {
+        let temp = assert_fs::TempDir::new().expect("Failed to create temp dir");
+        let foo_file = temp.child("foo.txt");
+        foo_file
+            .write_str("First paragraph.\n\nSecond paragraph.")
+            .expect("Failed to write to foo");
+
+        let glob = temp.path().to_string_lossy().to_string() + "/*.txt";
+
+        let chunks = FileLoader::with_glob(&glob)
+            .unwrap()
+            .read_with_path()
+            .ignore_errors()
+            .chunk(RecursiveSplitter::new(20, 0))
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            chunks,
+            vec![
+                (
+                    foo_file.path().to_path_buf(),
+                    "First paragraph.".to_string()
+                ),
+                (
+                    foo_file.path().to_path_buf(),
+                    "Second paragraph.".to_string()
+                ),
+            ]
+        );
+    }
 }
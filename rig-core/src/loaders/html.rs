@@ -0,0 +1,624 @@
+use std::{fs, path::PathBuf};
+
+use html5ever::tokenizer::{
+    states::RawKind, BufferQueue, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer,
+    TokenizerOpts,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::file::FileLoaderError;
+use crate::{
+    chunking::TextSplitter,
+    embeddings::embed::{Embed, EmbedError, TextEmbedder},
+};
+
+#[derive(Error, Debug)]
+pub enum HtmlLoaderError {
+    #[error("{0}")]
+    FileLoaderError(#[from] FileLoaderError),
+
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("Unexpected HTTP status {status} for {url}")]
+    StatusError {
+        url: String,
+        status: reqwest::StatusCode,
+    },
+}
+
+// ================================================================
+// Text extraction
+// ================================================================
+
+/// Readable contents of an HTML page, as extracted by the [HtmlFileLoader] and [WebLoader].
+/// When embedded, only the text of the page is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HtmlDocument {
+    /// Path or URL the page was loaded from, if any
+    pub source: Option<String>,
+    /// Contents of the `<title>` element
+    pub title: Option<String>,
+    /// Readable text of the page, one block element per line
+    pub text: String,
+}
+
+impl HtmlDocument {
+    /// Extracts the readable text and title of an HTML page.
+    ///
+    /// Boilerplate elements (scripts, styles, navigation, footers, sidebars, forms, etc.) are
+    ///  dropped. If the page has `<main>` or `<article>` elements, only their contents are kept.
+    ///
+    /// # Example
+    /// ```rust
+    /// let doc = HtmlDocument::parse("<title>Hi</title><nav>Home</nav><p>Hello &amp; bye</p>");
+    /// assert_eq!(doc.title, Some("Hi".to_string()));
+    /// assert_eq!(doc.text, "Hello & bye");
+    /// ```
+    pub fn parse(html: &str) -> Self {
+        let mut input = BufferQueue::default();
+        input.push_back(html.into());
+
+        let mut tokenizer = Tokenizer::new(TextExtractor::default(), TokenizerOpts::default());
+        let _ = tokenizer.feed(&mut input);
+        tokenizer.end();
+
+        tokenizer.sink.finish()
+    }
+
+    fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
+
+impl Embed for HtmlDocument {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.text.clone());
+        Ok(())
+    }
+}
+
+/// Elements whose contents are never part of the readable text.
+const SKIPPED: [&str; 13] = [
+    "script", "style", "noscript", "template", "svg", "iframe", "object", "nav", "footer", "aside",
+    "form", "button", "select",
+];
+
+/// Elements delimiting blocks of text.
+const BLOCKS: [&str; 27] = [
+    "address",
+    "article",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+#[derive(Default)]
+struct TextExtractor {
+    /// Stack of currently open skipped elements
+    skipped: Vec<String>,
+    in_title: bool,
+    /// Depth of open `<main>` or `<article>` elements
+    main_depth: usize,
+    pre_depth: usize,
+    title: String,
+    text: String,
+    main_text: String,
+}
+
+impl TextExtractor {
+    fn push(&mut self, contents: &str) {
+        if self.main_depth > 0 {
+            self.main_text.push_str(contents);
+        }
+        self.text.push_str(contents);
+    }
+
+    fn finish(self) -> HtmlDocument {
+        let text = if self.main_text.trim().is_empty() {
+            self.text
+        } else {
+            self.main_text
+        };
+
+        let title = collapse_whitespace(&self.title);
+
+        HtmlDocument {
+            source: None,
+            title: (!title.is_empty()).then_some(title),
+            text: text
+                .lines()
+                .map(|line| line.trim_end())
+                .filter(|line| !line.trim().is_empty())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+impl TokenSink for TextExtractor {
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        match token {
+            Token::TagToken(tag) => {
+                let name: &str = &tag.name;
+                let start = tag.kind == TagKind::StartTag;
+
+                // Raw text elements must be tokenized as such, otherwise their contents
+                // (e.g.: `<` in scripts) would be interpreted as markup.
+                let raw_kind = match name {
+                    "script" => Some(RawKind::ScriptData),
+                    "style" | "noscript" | "iframe" => Some(RawKind::Rawtext),
+                    "title" | "textarea" => Some(RawKind::Rcdata),
+                    _ => None,
+                };
+
+                if SKIPPED.contains(&name) {
+                    if start && !tag.self_closing {
+                        self.skipped.push(name.to_string());
+                    } else if !start && self.skipped.last().is_some_and(|last| last == name) {
+                        self.skipped.pop();
+                    }
+                } else if name == "title" && self.skipped.is_empty() {
+                    self.in_title = start;
+                } else if self.skipped.is_empty() {
+                    if BLOCKS.contains(&name) {
+                        self.push("\n");
+                    } else if matches!(name, "td" | "th") {
+                        self.push(" ");
+                    }
+
+                    match (name, start) {
+                        ("main" | "article", true) => self.main_depth += 1,
+                        ("main" | "article", false) => {
+                            self.main_depth = self.main_depth.saturating_sub(1)
+                        }
+                        ("pre", true) => self.pre_depth += 1,
+                        ("pre", false) => self.pre_depth = self.pre_depth.saturating_sub(1),
+                        _ => {}
+                    }
+                }
+
+                match raw_kind {
+                    Some(kind) if start && !tag.self_closing => TokenSinkResult::RawData(kind),
+                    _ => TokenSinkResult::Continue,
+                }
+            }
+            Token::CharacterTokens(contents) => {
+                if self.in_title {
+                    self.title.push_str(&contents);
+                } else if self.skipped.is_empty() {
+                    if self.pre_depth > 0 {
+                        self.push(&contents);
+                    } else {
+                        let collapsed = collapse_whitespace(&contents);
+                        let buffer = if self.main_depth > 0 {
+                            &self.main_text
+                        } else {
+                            &self.text
+                        };
+                        let needs_space = contents.starts_with(char::is_whitespace)
+                            && !buffer.ends_with(char::is_whitespace);
+
+                        if needs_space {
+                            self.push(" ");
+                        }
+                        self.push(&collapsed);
+                        if !collapsed.is_empty() && contents.ends_with(char::is_whitespace) {
+                            self.push(" ");
+                        }
+                    }
+                }
+                TokenSinkResult::Continue
+            }
+            _ => TokenSinkResult::Continue,
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// ================================================================
+// HtmlFileLoader definitions and implementations
+// ================================================================
+
+/// [HtmlFileLoader] is a utility for loading HTML files from the filesystem using glob patterns
+///  or directory paths and extracting their readable text and title.
+///
+/// # Example Usage
+///
+/// ```rust
+/// use rig::loaders::HtmlFileLoader;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     // Load the readable text of html files, ignoring any errors
+///     let contents: Vec<String> = HtmlFileLoader::with_glob("tests/data/*.html")?
+///         .read()
+///         .ignore_errors()
+///         .into_iter()
+///         .collect();
+///
+///     // The documents (or their chunks) can be passed to an `EmbeddingsBuilder`
+///     Ok(())
+/// }
+/// ```
+///
+/// [HtmlFileLoader] uses strict typing between the iterator methods to ensure that transitions
+///  between different implementations of the loaders and it's methods are handled properly by
+///  the compiler.
+pub struct HtmlFileLoader<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+fn load_file(
+    path: Result<PathBuf, HtmlLoaderError>,
+) -> Result<(PathBuf, HtmlDocument), HtmlLoaderError> {
+    let path = path?;
+    let html = fs::read_to_string(&path).map_err(FileLoaderError::IoError)?;
+    let doc = HtmlDocument::parse(&html).with_source(path.to_string_lossy());
+    Ok((path, doc))
+}
+
+impl<'a> HtmlFileLoader<'a, Result<PathBuf, HtmlLoaderError>> {
+    /// Loads the html files within the iterator returned by [HtmlFileLoader::with_glob] or
+    ///  [HtmlFileLoader::with_dir] as [HtmlDocument]s, which carry the page title alongside the
+    ///  readable text.
+    pub fn load(self) -> HtmlFileLoader<'a, Result<HtmlDocument, HtmlLoaderError>> {
+        HtmlFileLoader {
+            iterator: Box::new(self.iterator.map(|res| load_file(res).map(|(_, doc)| doc))),
+        }
+    }
+
+    /// Loads the html files within the iterator returned by [HtmlFileLoader::with_glob] or
+    ///  [HtmlFileLoader::with_dir] as [HtmlDocument]s along with their path.
+    pub fn load_with_path(
+        self,
+    ) -> HtmlFileLoader<'a, Result<(PathBuf, HtmlDocument), HtmlLoaderError>> {
+        HtmlFileLoader {
+            iterator: Box::new(self.iterator.map(load_file)),
+        }
+    }
+
+    /// Directly reads the readable text of the html files within the iterator returned by
+    ///  [HtmlFileLoader::with_glob] or [HtmlFileLoader::with_dir].
+    ///
+    /// # Example
+    /// ```rust
+    /// let content = HtmlFileLoader::with_glob("tests/data/*.html")?.read().into_iter();
+    /// for result in content {
+    ///     match result {
+    ///         Ok(content) => println!("{}", content),
+    ///         Err(e) => eprintln!("Error reading html: {}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn read(self) -> HtmlFileLoader<'a, Result<String, HtmlLoaderError>> {
+        HtmlFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .map(|res| load_file(res).map(|(_, doc)| doc.text)),
+            ),
+        }
+    }
+
+    /// Directly reads the readable text of the html files within the iterator returned by
+    ///  [HtmlFileLoader::with_glob] or [HtmlFileLoader::with_dir] and returns the path along
+    ///  with the content.
+    pub fn read_with_path(self) -> HtmlFileLoader<'a, Result<(PathBuf, String), HtmlLoaderError>> {
+        HtmlFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .map(|res| load_file(res).map(|(path, doc)| (path, doc.text))),
+            ),
+        }
+    }
+}
+
+impl<'a> HtmlFileLoader<'a, String> {
+    /// Splits the text of each html file into chunks using the given [TextSplitter].
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> HtmlFileLoader<'a, String> {
+        HtmlFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .flat_map(move |contents| splitter.split(&contents)),
+            ),
+        }
+    }
+}
+
+impl<'a> HtmlFileLoader<'a, (PathBuf, String)> {
+    /// Splits the text of each html file into chunks using the given [TextSplitter], keeping
+    ///  track of the path of the file each chunk comes from.
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> HtmlFileLoader<'a, (PathBuf, String)> {
+        HtmlFileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |(path, contents)| {
+                splitter
+                    .split(&contents)
+                    .into_iter()
+                    .map(move |chunk| (path.clone(), chunk))
+            })),
+        }
+    }
+}
+
+impl<'a, T: 'a> HtmlFileLoader<'a, Result<T, HtmlLoaderError>> {
+    /// Ignores errors in the iterator, returning only successful results. This can be used on any
+    ///  [HtmlFileLoader] state of iterator whose items are results.
+    pub fn ignore_errors(self) -> HtmlFileLoader<'a, T> {
+        HtmlFileLoader {
+            iterator: Box::new(self.iterator.filter_map(|res| res.ok())),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HtmlFileLoader<'_, Result<PathBuf, FileLoaderError>> {
+    /// Creates a new [HtmlFileLoader] using a glob pattern to match files.
+    ///
+    /// # Example
+    /// Create a [HtmlFileLoader] for all `.html` files that match the glob "tests/data/*.html".
+    ///
+    /// ```rust
+    /// let loader = HtmlFileLoader::with_glob("tests/data/*.html")?;
+    /// ```
+    pub fn with_glob(
+        pattern: &str,
+    ) -> Result<HtmlFileLoader<'_, Result<PathBuf, HtmlLoaderError>>, HtmlLoaderError> {
+        let paths = glob::glob(pattern).map_err(FileLoaderError::PatternError)?;
+        Ok(HtmlFileLoader {
+            iterator: Box::new(paths.into_iter().map(|path| {
+                path.map_err(FileLoaderError::GlobError)
+                    .map_err(HtmlLoaderError::FileLoaderError)
+            })),
+        })
+    }
+
+    /// Creates a new [HtmlFileLoader] on all files within a directory.
+    ///
+    /// # Example
+    /// Create a [HtmlFileLoader] for all files that are in the directory "files".
+    ///
+    /// ```rust
+    /// let loader = HtmlFileLoader::with_dir("files")?;
+    /// ```
+    pub fn with_dir(
+        directory: &str,
+    ) -> Result<HtmlFileLoader<'_, Result<PathBuf, HtmlLoaderError>>, HtmlLoaderError> {
+        Ok(HtmlFileLoader {
+            iterator: Box::new(
+                fs::read_dir(directory)
+                    .map_err(FileLoaderError::IoError)?
+                    .map(|entry| Ok(entry.map_err(FileLoaderError::IoError)?.path())),
+            ),
+        })
+    }
+}
+
+// ================================================================
+// HtmlFileLoader iterator implementations
+// ================================================================
+
+pub struct IntoIter<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+impl<'a, T> IntoIterator for HtmlFileLoader<'a, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            iterator: self.iterator,
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.next()
+    }
+}
+
+// ================================================================
+// WebLoader definitions and implementations
+// ================================================================
+
+/// [WebLoader] fetches web pages over HTTP and extracts their readable text and title the same
+///  way the [HtmlFileLoader] does for files.
+///
+/// # Example Usage
+///
+/// ```rust
+/// use rig::loaders::WebLoader;
+///
+/// let loader = WebLoader::new();
+/// let doc = loader.fetch("https://example.com").await?;
+/// println!("{:?}: {}", doc.title, doc.text);
+///
+/// // Pages are fetched concurrently, failed requests are returned as errors
+/// let docs = loader
+///     .fetch_all(["https://example.com", "https://example.org"])
+///     .await;
+/// ```
+#[derive(Clone, Default)]
+pub struct WebLoader {
+    client: reqwest::Client,
+}
+
+impl WebLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given HTTP client (e.g.: to set a user agent, timeouts or proxies).
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    /// Fetches the page at `url` and extracts its contents. The source of the returned
+    ///  [HtmlDocument] is the final URL, after redirects.
+    pub async fn fetch(&self, url: &str) -> Result<HtmlDocument, HtmlLoaderError> {
+        let response = self.client.get(url).send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(HtmlLoaderError::StatusError {
+                url: url.to_string(),
+                status,
+            });
+        }
+
+        let source = response.url().to_string();
+        let html = response.text().await?;
+
+        Ok(HtmlDocument::parse(&html).with_source(source))
+    }
+
+    /// Fetches all the pages concurrently, returning the results in the same order as the urls.
+    pub async fn fetch_all(
+        &self,
+        urls: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Vec<Result<HtmlDocument, HtmlLoaderError>> {
+        let urls = urls
+            .into_iter()
+            .map(|url| url.as_ref().to_string())
+            .collect::<Vec<_>>();
+
+        futures::future::join_all(urls.iter().map(|url| self.fetch(url))).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        path::PathBuf,
+    };
+
+    use super::{HtmlDocument, HtmlFileLoader, HtmlLoaderError, WebLoader};
+
+    #[test]
+    fn test_parse() {
+        let doc = HtmlDocument::parse(
+            r#"<!DOCTYPE html>
+            <html>
+            <head>
+                <title>  The   Title </title>
+                <style>p { color: red; }</style>
+                <script>if (a < b && c) { document.write("<p>nope</p>"); }</script>
+            </head>
+            <body>
+                <nav><a href="/">Home</a> | <a href="/about">About</a></nav>
+                <h1>Heading</h1>
+                <p>Some <b>bold</b>   text &amp; an <a href="/link">inline link</a>.</p>
+                <ul><li>One</li><li>Two</li></ul>
+                <pre>let x  = 1;
+let y = 2;</pre>
+                <footer>Copyright</footer>
+            </body>
+            </html>"#,
+        );
+
+        assert_eq!(doc.title, Some("The Title".to_string()));
+        assert_eq!(
+            doc.text,
+            "Heading\nSome bold text & an inline link.\nOne\nTwo\nlet x  = 1;\nlet y = 2;"
+        );
+
+        let doc = HtmlDocument::parse(
+            "<body><div>Sidebar</div><main><article><p>Content</p></article></main></body>",
+        );
+        assert_eq!(doc.title, None);
+        assert_eq!(doc.text, "Content");
+    }
+
+    #[test]
+    fn test_html_loader() {
+        let mut actual = HtmlFileLoader::with_glob("tests/data/*.html")
+            .unwrap()
+            .load_with_path()
+            .ignore_errors()
+            .into_iter()
+            .collect::<Vec<_>>();
+        actual.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(actual.len(), 1);
+        let (path, doc) = &actual[0];
+        assert_eq!(path, &PathBuf::from("tests/data/article.html"));
+        assert_eq!(doc.source.as_deref(), Some("tests/data/article.html"));
+        assert_eq!(doc.title.as_deref(), Some("Rig Test Article"));
+        assert_eq!(
+            doc.text,
+            "Rig Test Article\nThis is the first paragraph.\nThis is the second paragraph."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_web_loader() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            for (i, stream) in listener.incoming().take(2).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).unwrap();
+
+                let (status, body) = if i == 0 {
+                    ("200 OK", "<title>Page</title><p>Hello</p>")
+                } else {
+                    ("404 Not Found", "")
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+
+        let loader = WebLoader::with_client(reqwest::Client::builder().no_proxy().build().unwrap());
+        let url = format!("http://{addr}/page");
+
+        let doc = loader.fetch(&url).await.unwrap();
+        assert_eq!(doc.source, Some(url.clone()));
+        assert_eq!(doc.title.as_deref(), Some("Page"));
+        assert_eq!(doc.text, "Hello");
+
+        assert!(matches!(
+            loader.fetch(&url).await,
+            Err(HtmlLoaderError::StatusError { status, .. }) if status == 404
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+use std::{fs, path::PathBuf};
+
+use serde_json::Value;
+use thiserror::Error;
+
+use super::{
+    file::FileLoaderError,
+    record::{Record, RecordSchema},
+};
+
+#[derive(Error, Debug)]
+pub enum JsonlLoaderError {
+    #[error("{0}")]
+    FileLoaderError(#[from] FileLoaderError),
+
+    #[error("JSON error in {path:?} at line {line}: {source}")]
+    JsonError {
+        path: PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("Expected a JSON object in {path:?} at line {line}")]
+    NotAnObject { path: PathBuf, line: usize },
+
+    #[error("Missing field {field:?} in {path:?} at line {line}")]
+    MissingField {
+        path: PathBuf,
+        line: usize,
+        field: String,
+    },
+}
+
+// ================================================================
+// JsonlFileLoader definitions and implementations
+// ================================================================
+
+/// [JsonlFileLoader] is a utility for loading JSON Lines files (one JSON object per line) from
+///  the filesystem using glob patterns or directory paths, mapping each line to a [Record]
+///  according to a [RecordSchema]. Empty lines are skipped.
+///
+/// Nested fields can be selected using dots as separators (e.g.: `author.name`).
+///
+/// # Example Usage
+///
+/// ```rust
+/// use rig::loaders::{JsonlFileLoader, RecordSchema};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let schema = RecordSchema::new()
+///         .text_fields(["question", "answer"])
+///         .metadata_fields(["id", "author.name"]);
+///
+///     let records = JsonlFileLoader::with_glob("tests/data/*.jsonl")?
+///         .load(schema)
+///         .ignore_errors()
+///         .into_iter()
+///         .collect::<Vec<_>>();
+///
+///     // The records can be passed to an `EmbeddingsBuilder`, only their text is embedded
+///     Ok(())
+/// }
+/// ```
+///
+/// [JsonlFileLoader] uses strict typing between the iterator methods to ensure that transitions
+///  between different implementations of the loaders and it's methods are handled properly by
+///  the compiler.
+pub struct JsonlFileLoader<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+type RecordResult = Result<(PathBuf, Record), JsonlLoaderError>;
+
+fn read_lines(path: PathBuf, schema: &RecordSchema) -> Result<Vec<RecordResult>, JsonlLoaderError> {
+    let contents = fs::read_to_string(&path).map_err(FileLoaderError::IoError)?;
+
+    Ok(contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let line_no = i + 1;
+            let value = serde_json::from_str::<Value>(line).map_err(|source| {
+                JsonlLoaderError::JsonError {
+                    path: path.clone(),
+                    line: line_no,
+                    source,
+                }
+            })?;
+            let object = value
+                .as_object()
+                .ok_or_else(|| JsonlLoaderError::NotAnObject {
+                    path: path.clone(),
+                    line: line_no,
+                })?;
+
+            let fields = object
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect::<Vec<_>>();
+            let get = |name: &str| {
+                let pointer = format!("/{}", name.replace('~', "~0").replace('/', "~1"));
+                value.pointer(&pointer.replace('.', "/")).cloned()
+            };
+
+            let record =
+                schema
+                    .build(&fields, get)
+                    .map_err(|field| JsonlLoaderError::MissingField {
+                        path: path.clone(),
+                        line: line_no,
+                        field,
+                    })?;
+            Ok((path.clone(), record))
+        })
+        .collect())
+}
+
+impl<'a> JsonlFileLoader<'a, Result<PathBuf, JsonlLoaderError>> {
+    /// Loads the lines of the files within the iterator returned by [JsonlFileLoader::with_glob]
+    ///  or [JsonlFileLoader::with_dir] as [Record]s.
+    pub fn load(
+        self,
+        schema: RecordSchema,
+    ) -> JsonlFileLoader<'a, Result<Record, JsonlLoaderError>> {
+        JsonlFileLoader {
+            iterator: Box::new(
+                self.load_with_path(schema)
+                    .iterator
+                    .map(|res| res.map(|(_, record)| record)),
+            ),
+        }
+    }
+
+    /// Loads the lines of the files within the iterator returned by [JsonlFileLoader::with_glob]
+    ///  or [JsonlFileLoader::with_dir] as [Record]s along with the path of the file they come from.
+    pub fn load_with_path(
+        self,
+        schema: RecordSchema,
+    ) -> JsonlFileLoader<'a, Result<(PathBuf, Record), JsonlLoaderError>> {
+        JsonlFileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |path| {
+                match path.and_then(|path| read_lines(path, &schema)) {
+                    Ok(lines) => lines,
+                    Err(e) => vec![Err(e)],
+                }
+            })),
+        }
+    }
+}
+
+impl<'a, T: 'a> JsonlFileLoader<'a, Result<T, JsonlLoaderError>> {
+    /// Ignores errors in the iterator, returning only successful results. This can be used on any
+    ///  [JsonlFileLoader] state of iterator whose items are results.
+    pub fn ignore_errors(self) -> JsonlFileLoader<'a, T> {
+        JsonlFileLoader {
+            iterator: Box::new(self.iterator.filter_map(|res| res.ok())),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl JsonlFileLoader<'_, Result<PathBuf, FileLoaderError>> {
+    /// Creates a new [JsonlFileLoader] using a glob pattern to match files.
+    ///
+    /// # Example
+    /// Create a [JsonlFileLoader] for all `.jsonl` files that match the glob "tests/data/*.jsonl".
+    ///
+    /// ```rust
+    /// let loader = JsonlFileLoader::with_glob("tests/data/*.jsonl")?;
+    /// ```
+    pub fn with_glob(
+        pattern: &str,
+    ) -> Result<JsonlFileLoader<'_, Result<PathBuf, JsonlLoaderError>>, JsonlLoaderError> {
+        let paths = glob::glob(pattern).map_err(FileLoaderError::PatternError)?;
+        Ok(JsonlFileLoader {
+            iterator: Box::new(paths.into_iter().map(|path| {
+                path.map_err(FileLoaderError::GlobError)
+                    .map_err(JsonlLoaderError::FileLoaderError)
+            })),
+        })
+    }
+
+    /// Creates a new [JsonlFileLoader] on all files within a directory.
+    ///
+    /// # Example
+    /// Create a [JsonlFileLoader] for all files that are in the directory "files".
+    ///
+    /// ```rust
+    /// let loader = JsonlFileLoader::with_dir("files")?;
+    /// ```
+    pub fn with_dir(
+        directory: &str,
+    ) -> Result<JsonlFileLoader<'_, Result<PathBuf, JsonlLoaderError>>, JsonlLoaderError> {
+        Ok(JsonlFileLoader {
+            iterator: Box::new(
+                fs::read_dir(directory)
+                    .map_err(FileLoaderError::IoError)?
+                    .map(|entry| Ok(entry.map_err(FileLoaderError::IoError)?.path())),
+            ),
+        })
+    }
+}
+
+// ================================================================
+// JsonlFileLoader iterator implementations
+// ================================================================
+
+pub struct IntoIter<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+impl<'a, T> IntoIterator for JsonlFileLoader<'a, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            iterator: self.iterator,
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{JsonlFileLoader, JsonlLoaderError};
+    use crate::loaders::RecordSchema;
+
+    #[test]
+    fn test_jsonl_loader() {
+        let schema = RecordSchema::new()
+            .text_fields(["question", "answer"])
+            .metadata_fields(["id", "author.name"]);
+
+        let results = JsonlFileLoader::with_glob("tests/data/faq.jsonl")
+            .unwrap()
+            .load(schema)
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        assert_eq!(results.len(), 3);
+
+        let record = results[0].as_ref().unwrap();
+        assert_eq!(
+            record.text,
+            "What is Rig?\nA Rust library for LLM applications."
+        );
+        assert_eq!(
+            record.metadata,
+            *json!({"id": 1, "author.name": "Alice"})
+                .as_object()
+                .unwrap()
+        );
+
+        let record = results[1].as_ref().unwrap();
+        assert_eq!(record.metadata, *json!({"id": 2}).as_object().unwrap());
+
+        assert!(matches!(
+            results[2],
+            Err(JsonlLoaderError::MissingField { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_jsonl_loader_errors() {
+        use assert_fs::{prelude::*, TempDir};
+
+        let dir = TempDir::new().unwrap();
+        dir.child("faq.jsonl")
+            .write_str("{\"question\": \"Why?\"}\nnot json\n\n[1, 2]\n")
+            .unwrap();
+
+        let results = JsonlFileLoader::with_dir(dir.path().to_str().unwrap())
+            .unwrap()
+            .load(RecordSchema::new())
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().text, "question: Why?");
+        assert!(matches!(
+            results[1],
+            Err(JsonlLoaderError::JsonError { line: 2, .. })
+        ));
+        assert!(matches!(
+            results[2],
+            Err(JsonlLoaderError::NotAnObject { line: 4, .. })
+        ));
+
+        assert!(JsonlFileLoader::with_glob("tests/data/[").is_err());
+        assert!(JsonlFileLoader::with_dir("tests/missing").is_err());
+    }
+}
@@ -0,0 +1,550 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::file::FileLoaderError;
+use crate::{
+    chunking::TextSplitter,
+    embeddings::embed::{Embed, EmbedError, TextEmbedder},
+};
+
+// ================================================================
+// Markdown parsing
+// ================================================================
+
+/// Section of a Markdown document: the text under a heading, up to the next heading.
+/// When embedded, the headings of the section are embedded along with its text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkdownSection {
+    /// Path of the file the section was loaded from, if any
+    pub source: Option<String>,
+    /// Headings of the section, from the top-level heading down to its own. Empty for the text
+    ///  before the first heading.
+    pub headings: Vec<String>,
+    /// Level of the heading of the section (`1` for `#`), `0` for the text before the first
+    ///  heading
+    pub level: usize,
+    pub text: String,
+}
+
+impl MarkdownSection {
+    /// Headings of the section joined with ` > `, e.g.: `Guide > Installation`
+    pub fn breadcrumb(&self) -> String {
+        self.headings.join(" > ")
+    }
+}
+
+impl Embed for MarkdownSection {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(match self.headings.is_empty() {
+            true => self.text.clone(),
+            false => format!("{}\n\n{}", self.breadcrumb(), self.text),
+        });
+        Ok(())
+    }
+}
+
+/// Markdown document, as loaded by the [MarkdownFileLoader], with its sections.
+/// When embedded, the whole text is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkdownDocument {
+    /// Path of the file the document was loaded from, if any
+    pub source: Option<String>,
+    /// Title of the front matter, or else the first top-level heading
+    pub title: Option<String>,
+    /// Text of the document, without its front matter
+    pub text: String,
+    /// Sections of the document, in order. Headings directly followed by a sub-heading have no
+    ///  section of their own, but are part of the headings of the next sections.
+    pub sections: Vec<MarkdownSection>,
+}
+
+impl MarkdownDocument {
+    /// Parses a Markdown document into its sections, delimited by ATX (`# Heading`) and setext
+    ///  (`Heading` underlined with `===` or `---`) headings. Lines of fenced code blocks are
+    ///  never headings.
+    ///
+    /// # Example
+    /// ```rust
+    /// let doc = MarkdownDocument::parse("# Guide\n\n## Install\n\nRun `cargo add rig-core`.");
+    /// assert_eq!(doc.title, Some("Guide".to_string()));
+    /// assert_eq!(doc.sections[0].headings, vec!["Guide", "Install"]);
+    /// assert_eq!(doc.sections[0].text, "Run `cargo add rig-core`.");
+    /// ```
+    pub fn parse(markdown: &str) -> Self {
+        let (front_title, text) = split_front_matter(markdown);
+
+        let mut sections = vec![];
+        let mut headings: Vec<(usize, String)> = vec![];
+        let mut lines: Vec<&str> = vec![];
+        let mut fence: Option<(char, usize)> = None;
+
+        let mut close = |headings: &[(usize, String)], lines: &mut Vec<&str>| {
+            let text = lines.join("\n").trim_matches('\n').trim_end().to_string();
+            lines.clear();
+            if !text.trim().is_empty() {
+                sections.push(MarkdownSection {
+                    source: None,
+                    headings: headings
+                        .iter()
+                        .map(|(_, heading)| heading.clone())
+                        .collect(),
+                    level: headings.last().map(|(level, _)| *level).unwrap_or_default(),
+                    text,
+                });
+            }
+        };
+
+        for line in text.lines() {
+            if let Some(marker) = fence_marker(line) {
+                fence = match fence {
+                    None => Some(marker),
+                    Some((c, len)) if marker.0 == c && marker.1 >= len => None,
+                    open => open,
+                };
+                lines.push(line);
+                continue;
+            }
+            if fence.is_some() {
+                lines.push(line);
+                continue;
+            }
+
+            let heading = match atx_heading(line) {
+                Some(heading) => Some(heading),
+                None => setext_level(line)
+                    .filter(|_| lines.last().is_some_and(|last| is_paragraph(last)))
+                    .and_then(|level| {
+                        let title = lines.pop()?.trim().to_string();
+                        Some((level, title))
+                    }),
+            };
+
+            match heading {
+                Some((level, title)) => {
+                    close(&headings, &mut lines);
+                    while headings.last().is_some_and(|(last, _)| *last >= level) {
+                        headings.pop();
+                    }
+                    headings.push((level, title));
+                }
+                None => lines.push(line),
+            }
+        }
+        close(&headings, &mut lines);
+
+        let title = front_title.or_else(|| {
+            atx_titles(text)
+                .find(|(level, _)| *level == 1)
+                .map(|(_, title)| title)
+        });
+
+        MarkdownDocument {
+            source: None,
+            title,
+            text: text.trim().to_string(),
+            sections,
+        }
+    }
+
+    fn with_source(mut self, source: impl Into<String>) -> Self {
+        let source = source.into();
+        for section in &mut self.sections {
+            section.source = Some(source.clone());
+        }
+        self.source = Some(source);
+        self
+    }
+}
+
+impl Embed for MarkdownDocument {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.text.clone());
+        Ok(())
+    }
+}
+
+/// Title of the YAML front matter of `markdown`, if any, and the text after the front matter
+fn split_front_matter(markdown: &str) -> (Option<String>, &str) {
+    let Some(rest) = markdown
+        .strip_prefix("---\n")
+        .or_else(|| markdown.strip_prefix("---\r\n"))
+    else {
+        return (None, markdown);
+    };
+    let Some(end) = rest
+        .match_indices('\n')
+        .map(|(i, _)| i + 1)
+        .find(|&i| matches!(rest[i..].lines().next(), Some("---" | "...")))
+    else {
+        return (None, markdown);
+    };
+
+    let title = rest[..end].lines().find_map(|line| {
+        let title = line
+            .strip_prefix("title:")?
+            .trim()
+            .trim_matches(['"', '\'']);
+        (!title.is_empty()).then(|| title.to_string())
+    });
+    let text = rest[end..].split_once('\n').map_or("", |(_, text)| text);
+    (title, text)
+}
+
+/// Level and title of an ATX heading line (`## Title ##`)
+fn atx_heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#').trim_end();
+    Some((level, title.to_string()))
+}
+
+/// Level of a setext heading underline (`===` or `---`)
+fn setext_level(line: &str) -> Option<usize> {
+    let underline = line.trim();
+    if line.len() - line.trim_start().len() > 3 || underline.is_empty() {
+        return None;
+    }
+    if underline.chars().all(|c| c == '=') {
+        Some(1)
+    } else if underline.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Whether a line can be the text of a setext heading
+fn is_paragraph(line: &str) -> bool {
+    let trimmed = line.trim();
+    let ordered_item = trimmed
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .starts_with(['.', ')'])
+        && trimmed.starts_with(|c: char| c.is_ascii_digit());
+    !trimmed.is_empty()
+        && !line.starts_with("    ")
+        && !trimmed.starts_with(['>', '-', '*', '+', '|', '`', '~', '<'])
+        && !ordered_item
+}
+
+/// Character and length of a code fence line (` ``` ` or `~~~`)
+fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = trimmed.chars().take_while(|x| *x == c).count();
+    (len >= 3).then_some((c, len))
+}
+
+/// ATX headings of `text` outside of code blocks
+fn atx_titles(text: &str) -> impl Iterator<Item = (usize, String)> + '_ {
+    let mut fence: Option<(char, usize)> = None;
+    text.lines().filter_map(move |line| {
+        if let Some(marker) = fence_marker(line) {
+            fence = match fence {
+                None => Some(marker),
+                Some((c, len)) if marker.0 == c && marker.1 >= len => None,
+                open => open,
+            };
+            return None;
+        }
+        fence.is_none().then(|| atx_heading(line)).flatten()
+    })
+}
+
+// ================================================================
+// MarkdownFileLoader definitions and implementations
+// ================================================================
+
+/// [MarkdownFileLoader] is a utility for loading Markdown files from the filesystem using glob
+///  patterns or directory paths, and splitting them into sections that keep track of their
+///  heading hierarchy.
+///
+/// # Example Usage
+///
+/// ```rust
+/// use rig::loaders::MarkdownFileLoader;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     // Load the sections of the markdown files, ignoring any errors
+///     let sections = MarkdownFileLoader::with_glob("docs/**/*.md")?
+///         .load()
+///         .ignore_errors()
+///         .sections()
+///         .into_iter()
+///         .collect::<Vec<_>>();
+///
+///     // The sections can be passed to an `EmbeddingsBuilder`, their headings are embedded
+///     //  along with their text
+///     Ok(())
+/// }
+/// ```
+///
+/// [MarkdownFileLoader] uses strict typing between the iterator methods to ensure that
+///  transitions between different implementations of the loaders and it's methods are handled
+///  properly by the compiler.
+pub struct MarkdownFileLoader<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+fn load_file(
+    path: Result<PathBuf, FileLoaderError>,
+) -> Result<(PathBuf, MarkdownDocument), FileLoaderError> {
+    let path = path?;
+    let markdown = fs::read_to_string(&path)?;
+    let doc = MarkdownDocument::parse(&markdown).with_source(path.to_string_lossy());
+    Ok((path, doc))
+}
+
+impl<'a> MarkdownFileLoader<'a, Result<PathBuf, FileLoaderError>> {
+    /// Loads the markdown files within the iterator returned by [MarkdownFileLoader::with_glob]
+    ///  or [MarkdownFileLoader::with_dir] as [MarkdownDocument]s.
+    pub fn load(self) -> MarkdownFileLoader<'a, Result<MarkdownDocument, FileLoaderError>> {
+        MarkdownFileLoader {
+            iterator: Box::new(self.iterator.map(|res| load_file(res).map(|(_, doc)| doc))),
+        }
+    }
+
+    /// Loads the markdown files within the iterator returned by [MarkdownFileLoader::with_glob]
+    ///  or [MarkdownFileLoader::with_dir] as [MarkdownDocument]s along with their path.
+    pub fn load_with_path(
+        self,
+    ) -> MarkdownFileLoader<'a, Result<(PathBuf, MarkdownDocument), FileLoaderError>> {
+        MarkdownFileLoader {
+            iterator: Box::new(self.iterator.map(load_file)),
+        }
+    }
+
+    /// Directly reads the text of the markdown files (without their front matter) within the
+    ///  iterator returned by [MarkdownFileLoader::with_glob] or [MarkdownFileLoader::with_dir].
+    pub fn read(self) -> MarkdownFileLoader<'a, Result<String, FileLoaderError>> {
+        MarkdownFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .map(|res| load_file(res).map(|(_, doc)| doc.text)),
+            ),
+        }
+    }
+
+    /// Directly reads the text of the markdown files within the iterator returned by
+    ///  [MarkdownFileLoader::with_glob] or [MarkdownFileLoader::with_dir] and returns the path
+    ///  along with the content.
+    pub fn read_with_path(
+        self,
+    ) -> MarkdownFileLoader<'a, Result<(PathBuf, String), FileLoaderError>> {
+        MarkdownFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .map(|res| load_file(res).map(|(path, doc)| (path, doc.text))),
+            ),
+        }
+    }
+}
+
+impl<'a> MarkdownFileLoader<'a, MarkdownDocument> {
+    /// Splits each markdown document into its [MarkdownSection]s.
+    pub fn sections(self) -> MarkdownFileLoader<'a, MarkdownSection> {
+        MarkdownFileLoader {
+            iterator: Box::new(self.iterator.flat_map(|doc| doc.sections)),
+        }
+    }
+}
+
+impl<'a> MarkdownFileLoader<'a, String> {
+    /// Splits the text of each markdown file into chunks using the given [TextSplitter].
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> MarkdownFileLoader<'a, String> {
+        MarkdownFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .flat_map(move |contents| splitter.split(&contents)),
+            ),
+        }
+    }
+}
+
+impl<'a> MarkdownFileLoader<'a, (PathBuf, String)> {
+    /// Splits the text of each markdown file into chunks using the given [TextSplitter],
+    ///  keeping track of the path of the file each chunk comes from.
+    pub fn chunk(
+        self,
+        splitter: impl TextSplitter + 'a,
+    ) -> MarkdownFileLoader<'a, (PathBuf, String)> {
+        MarkdownFileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |(path, contents)| {
+                splitter
+                    .split(&contents)
+                    .into_iter()
+                    .map(move |chunk| (path.clone(), chunk))
+            })),
+        }
+    }
+}
+
+impl<'a, T: 'a> MarkdownFileLoader<'a, Result<T, FileLoaderError>> {
+    /// Ignores errors in the iterator, returning only successful results. This can be used on
+    ///  any [MarkdownFileLoader] state of iterator whose items are results.
+    pub fn ignore_errors(self) -> MarkdownFileLoader<'a, T> {
+        MarkdownFileLoader {
+            iterator: Box::new(self.iterator.filter_map(|res| res.ok())),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MarkdownFileLoader<'_, Result<PathBuf, FileLoaderError>> {
+    /// Creates a new [MarkdownFileLoader] using a glob pattern to match files.
+    ///
+    /// # Example
+    /// Create a [MarkdownFileLoader] for all `.md` files that match the glob "docs/*.md".
+    ///
+    /// ```rust
+    /// let loader = MarkdownFileLoader::with_glob("docs/*.md")?;
+    /// ```
+    pub fn with_glob(
+        pattern: &str,
+    ) -> Result<MarkdownFileLoader<'_, Result<PathBuf, FileLoaderError>>, FileLoaderError> {
+        let paths = glob::glob(pattern)?;
+        Ok(MarkdownFileLoader {
+            iterator: Box::new(
+                paths
+                    .into_iter()
+                    .map(|path| path.map_err(FileLoaderError::GlobError)),
+            ),
+        })
+    }
+
+    /// Creates a new [MarkdownFileLoader] on all files within a directory.
+    ///
+    /// # Example
+    /// Create a [MarkdownFileLoader] for all files that are in the directory "docs".
+    ///
+    /// ```rust
+    /// let loader = MarkdownFileLoader::with_dir("docs")?;
+    /// ```
+    pub fn with_dir(
+        directory: &str,
+    ) -> Result<MarkdownFileLoader<'_, Result<PathBuf, FileLoaderError>>, FileLoaderError> {
+        Ok(MarkdownFileLoader {
+            iterator: Box::new(
+                fs::read_dir(directory)?
+                    .map(|entry| Ok(entry.map_err(FileLoaderError::IoError)?.path())),
+            ),
+        })
+    }
+}
+
+// ================================================================
+// MarkdownFileLoader iterator implementations
+// ================================================================
+
+pub struct IntoIter<'a, T> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+impl<'a, T> IntoIterator for MarkdownFileLoader<'a, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            iterator: self.iterator,
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iterator.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{MarkdownDocument, MarkdownFileLoader};
+
+    #[test]
+    fn test_parse() {
+        let doc = MarkdownDocument::parse(
+            "---\ntitle: \"The Guide\"\ntags: [rig]\n---\nIntro text.\n\n\
+            # Guide\n\n## Install\n\nRun:\n\n```sh\n# not a heading\ncargo add rig-core\n```\n\n\
+            ### From source ###\nClone the repo.\n\nUsage\n-----\nCall `prompt`.\n\n---\n\n\
+            Back to *Guide*\n===\nThe end.\n",
+        );
+
+        assert_eq!(doc.title.as_deref(), Some("The Guide"));
+        assert!(doc.text.starts_with("Intro text."));
+        let sections = doc
+            .sections
+            .iter()
+            .map(|section| {
+                (
+                    section.headings.join(" > "),
+                    section.level,
+                    section.text.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            vec![
+                ("".to_string(), 0, "Intro text."),
+                (
+                    "Guide > Install".to_string(),
+                    2,
+                    "Run:\n\n```sh\n# not a heading\ncargo add rig-core\n```"
+                ),
+                (
+                    "Guide > Install > From source".to_string(),
+                    3,
+                    "Clone the repo."
+                ),
+                ("Guide > Usage".to_string(), 2, "Call `prompt`.\n\n---"),
+                ("Back to *Guide*".to_string(), 1, "The end."),
+            ]
+        );
+
+        let doc = MarkdownDocument::parse("Text\n\n#hashtag\n\n# Title\n");
+        assert_eq!(doc.title.as_deref(), Some("Title"));
+        assert_eq!(doc.sections.len(), 1);
+        assert_eq!(doc.sections[0].text, "Text\n\n#hashtag");
+    }
+
+    #[test]
+    fn test_markdown_loader() {
+        let sections = MarkdownFileLoader::with_glob("tests/data/*.md")
+            .unwrap()
+            .load()
+            .ignore_errors()
+            .sections()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].source.as_deref(), Some("tests/data/guide.md"));
+        assert_eq!(sections[0].breadcrumb(), "Rig Guide > Installation");
+        assert_eq!(sections[1].breadcrumb(), "Rig Guide > Agents");
+
+        let (path, text) = MarkdownFileLoader::with_glob("tests/data/*.md")
+            .unwrap()
+            .read_with_path()
+            .ignore_errors()
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(path, PathBuf::from("tests/data/guide.md"));
+        assert!(text.starts_with("# Rig Guide"));
+    }
+}
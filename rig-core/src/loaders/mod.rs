@@ -6,7 +6,9 @@
 //!
 //! The [PdfFileLoader] works similarly to the [FileLoader], but is specifically designed to load PDF
 //! files. This loader also provides PDF-specific preprocessing methods for splitting the PDF into pages
-//! and keeping track of the page numbers along with their contents.
+//! and keeping track of the page numbers along with their contents. Its [PdfDocument]s split PDF files
+//! into [PdfPage]s, or into [PdfSection]s following their outline, and the text of scanned pages can be
+//! recognized by an OCR engine (see [pdf::PdfOcr]).
 //!
 //! Note: The [PdfFileLoader] requires the `pdf` feature to be enabled in the `Cargo.toml` file.
 //!
@@ -15,19 +17,75 @@
 //! and keeping track of the chapter numbers along with their contents.
 //!
 //! Note: The [EpubFileLoader] requires the `epub` feature to be enabled in the `Cargo.toml` file.
+//!
+//! The [DocxFileLoader] extracts the text of Word documents as Markdown, keeping their headings, lists
+//! and tables (as rows of cells separated by `|`), and the [MarkdownFileLoader] splits Markdown files
+//! into sections keeping track of their heading hierarchy. The documents of both loaders can be split
+//! into such [MarkdownSection]s.
+//!
+//! Note: The [DocxFileLoader] requires the `docx` feature to be enabled in the `Cargo.toml` file.
+//!
+//! The [HtmlFileLoader] works similarly to the [FileLoader], but extracts the readable text and title
+//! of HTML files, dropping boilerplate such as scripts, styles and navigation. The [WebLoader] does the
+//! same for web pages fetched over HTTP.
+//!
+//! Note: The [HtmlFileLoader] and [WebLoader] require the `html` feature to be enabled in the `Cargo.toml` file.
+//!
+//! The [JsonlFileLoader] and [CsvFileLoader] load structured data, mapping each line or row to a [Record]
+//! whose text is made of selected fields and whose metadata preserves other fields, as described by a
+//! [RecordSchema].
+//!
+//! Note: The [CsvFileLoader] requires the `csv` feature to be enabled in the `Cargo.toml` file.
+//!
+//! The [DirectoryLoader] walks a directory tree, filtering files by glob patterns and extensions, and
+//! loads each file with the loader corresponding to its type, keeping track of the file it comes from.
+//!
+//! On wasm, which has no filesystem, the loaders cannot read files from glob patterns or directories,
+//! and the [DirectoryLoader] is not available: the documents can still be parsed from their content
+//! (e.g.: with [MarkdownDocument::parse]).
 
 pub mod file;
 
 pub use file::FileLoader;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod directory;
+pub mod jsonl;
+pub mod markdown;
+pub mod record;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use directory::{DirectoryLoader, FileDocument};
+pub use jsonl::JsonlFileLoader;
+pub use markdown::{MarkdownDocument, MarkdownFileLoader, MarkdownSection};
+pub use record::{Record, RecordSchema};
+
+#[cfg(feature = "csv")]
+pub mod csv;
+
+#[cfg(feature = "csv")]
+pub use csv::CsvFileLoader;
+
 #[cfg(feature = "pdf")]
 pub mod pdf;
 
 #[cfg(feature = "pdf")]
-pub use pdf::PdfFileLoader;
+pub use pdf::{PdfDocument, PdfFileLoader, PdfPage, PdfSection};
+
+#[cfg(feature = "docx")]
+pub mod docx;
+
+#[cfg(feature = "docx")]
+pub use docx::{DocxDocument, DocxFileLoader};
 
 #[cfg(feature = "epub")]
 pub mod epub;
 
 #[cfg(feature = "epub")]
 pub use epub::{EpubFileLoader, RawTextProcessor, StripXmlProcessor, TextProcessor};
+
+#[cfg(feature = "html")]
+pub mod html;
+
+#[cfg(feature = "html")]
+pub use html::{HtmlDocument, HtmlFileLoader, WebLoader};
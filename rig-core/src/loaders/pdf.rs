@@ -1,10 +1,14 @@
-use std::{fs, path::PathBuf};
+use std::path::{Path, PathBuf};
 
-use glob::glob;
 use lopdf::{Document, Error as LopdfError};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use super::file::FileLoaderError;
+use crate::{
+    chunking::TextSplitter,
+    embeddings::embed::{Embed, EmbedError, TextEmbedder},
+};
 
 #[derive(Error, Debug)]
 pub enum PdfLoaderError {
@@ -16,6 +20,313 @@ pub enum PdfLoaderError {
 
     #[error("IO error: {0}")]
     PdfError(#[from] LopdfError),
+
+    /// The document has no text, e.g.: a scanned document, whose text can only be recognized by
+    ///  a [PdfOcr] engine
+    #[error("No text in {0:?}")]
+    NoText(PathBuf),
+
+    #[error("OCR error: {0}")]
+    OcrError(String),
+}
+
+// ================================================================
+// PDF documents, pages and sections
+// ================================================================
+
+/// Page of a PDF document, as loaded by the [PdfFileLoader]. When embedded, its text is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdfPage {
+    /// Path of the file the page was loaded from
+    pub source: Option<String>,
+    /// Number of the page, starting at 1
+    pub page: usize,
+    pub text: String,
+    /// Whether the text was recognized by a [PdfOcr] engine
+    pub ocr: bool,
+}
+
+impl Embed for PdfPage {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.text.clone());
+        Ok(())
+    }
+}
+
+/// Section of a PDF document: the pages from a top-level entry of its outline (its bookmarks)
+///  up to the next one. When embedded, its title is embedded along with its text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdfSection {
+    /// Path of the file the section was loaded from
+    pub source: Option<String>,
+    /// Title of the outline entry of the section. `None` for the pages before the first entry,
+    ///  and for the sections of documents without outline (one per page).
+    pub title: Option<String>,
+    /// Number of the first page of the section, starting at 1
+    pub first_page: usize,
+    /// Number of the last page of the section
+    pub last_page: usize,
+    pub text: String,
+}
+
+impl Embed for PdfSection {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(match &self.title {
+            Some(title) => format!("{title}\n\n{}", self.text),
+            None => self.text.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// PDF document, as loaded by [PdfFileLoader::documents], with its pages and sections.
+/// When embedded, the whole text is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdfDocument {
+    /// Path of the file the document was loaded from
+    pub source: Option<String>,
+    /// Title of the document information dictionary, if any
+    pub title: Option<String>,
+    /// Pages of the document which have text, in order. Blank pages are skipped.
+    pub pages: Vec<PdfPage>,
+    /// Sections of the top-level entries of the outline of the document, or one section per
+    ///  page for documents without outline.
+    pub sections: Vec<PdfSection>,
+}
+
+impl PdfDocument {
+    /// Load the PDF file at `path`. Fails with [PdfLoaderError::NoText] if none of its pages
+    ///  has text.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PdfLoaderError> {
+        Self::load_with(path.as_ref(), None)
+    }
+
+    /// Same as [PdfDocument::load], but the text of the pages without text (e.g.: scanned
+    ///  pages) is recognized by `ocr`.
+    pub fn load_with_ocr(path: impl AsRef<Path>, ocr: &dyn PdfOcr) -> Result<Self, PdfLoaderError> {
+        Self::load_with(path.as_ref(), Some(ocr))
+    }
+
+    fn load_with(path: &Path, ocr: Option<&dyn PdfOcr>) -> Result<Self, PdfLoaderError> {
+        let doc = Document::load(path)?;
+        let source = Some(path.to_string_lossy().to_string());
+
+        let mut pages = vec![];
+        for page in 1..=doc.get_pages().len() {
+            let mut text = doc.extract_text(&[page as u32])?;
+            let mut recognized = false;
+            if text.trim().is_empty() {
+                match ocr {
+                    Some(ocr) => {
+                        text = ocr.recognize(path, page)?;
+                        recognized = true;
+                    }
+                    None => continue,
+                }
+            }
+            if !text.trim().is_empty() {
+                pages.push(PdfPage {
+                    source: source.clone(),
+                    page,
+                    text,
+                    ocr: recognized,
+                });
+            }
+        }
+        if pages.is_empty() {
+            return Err(PdfLoaderError::NoText(path.to_path_buf()));
+        }
+
+        Ok(Self {
+            sections: sections(&doc, &pages),
+            title: title(&doc),
+            source,
+            pages,
+        })
+    }
+
+    /// Text of the pages of the document, separated by blank lines
+    pub fn text(&self) -> String {
+        join_pages(&self.pages)
+    }
+}
+
+impl Embed for PdfDocument {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.text());
+        Ok(())
+    }
+}
+
+fn join_pages<'a>(pages: impl IntoIterator<Item = &'a PdfPage>) -> String {
+    pages
+        .into_iter()
+        .map(|page| page.text.trim())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Title of the information dictionary of `doc`
+fn title(doc: &Document) -> Option<String> {
+    let info = doc.trailer.get(b"Info").ok()?.as_reference().ok()?;
+    let title = doc.get_dictionary(info).ok()?.get(b"Title").ok()?;
+    lopdf::decode_text_string(title)
+        .ok()
+        .filter(|title| !title.trim().is_empty())
+}
+
+/// Sections of the top-level outline entries of `doc`, or one section per page
+fn sections(doc: &Document, pages: &[PdfPage]) -> Vec<PdfSection> {
+    let source = pages.first().and_then(|page| page.source.clone());
+    let mut entries = doc
+        .get_toc()
+        .map(|toc| {
+            toc.toc
+                .into_iter()
+                .filter(|entry| entry.level == 1)
+                .map(|entry| (entry.page, Some(entry.title)))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if entries.is_empty() {
+        return pages
+            .iter()
+            .map(|page| PdfSection {
+                source: source.clone(),
+                title: None,
+                first_page: page.page,
+                last_page: page.page,
+                text: page.text.trim().to_string(),
+            })
+            .collect();
+    }
+
+    // Entries starting on the same page would be empty sections
+    entries.sort_by_key(|(page, _)| *page);
+    entries.dedup_by_key(|(page, _)| *page);
+    if entries[0].0 > 1 {
+        entries.insert(0, (1, None));
+    }
+
+    let page_count = doc.get_pages().len();
+    entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (first_page, title))| {
+            let last_page = entries
+                .get(i + 1)
+                .map(|(next, _)| next - 1)
+                .unwrap_or(page_count);
+            let text = join_pages(
+                pages
+                    .iter()
+                    .filter(|page| (*first_page..=last_page).contains(&page.page)),
+            );
+            (!text.is_empty()).then(|| PdfSection {
+                source: source.clone(),
+                title: title.clone(),
+                first_page: *first_page,
+                last_page,
+                text,
+            })
+        })
+        .collect()
+}
+
+// ================================================================
+// OCR of scanned PDF documents
+// ================================================================
+
+/// Text recognition of the pages of scanned PDF documents, which have no text of their own.
+/// The `pdf-ocr` feature provides the `TesseractOcr` engine.
+pub trait PdfOcr {
+    /// Recognize the text of the page number `page` (starting at 1) of the PDF file at `path`.
+    fn recognize(&self, path: &Path, page: usize) -> Result<String, PdfLoaderError>;
+}
+
+/// [PdfOcr] engine running the `pdftoppm` (from Poppler) and `tesseract` command line tools,
+///  which must be installed: each page is rendered as an image, whose text is then recognized
+///  by Tesseract.
+#[cfg(feature = "pdf-ocr")]
+#[derive(Debug, Clone)]
+pub struct TesseractOcr {
+    language: String,
+    dpi: u32,
+}
+
+#[cfg(feature = "pdf-ocr")]
+impl Default for TesseractOcr {
+    fn default() -> Self {
+        Self {
+            language: "eng".to_string(),
+            dpi: 300,
+        }
+    }
+}
+
+#[cfg(feature = "pdf-ocr")]
+impl TesseractOcr {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the Tesseract languages of the documents, e.g.: `eng+fra` (defaults to `eng`).
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = language.to_string();
+        self
+    }
+
+    /// Set the resolution of the rendered pages (defaults to 300 DPI).
+    pub fn dpi(mut self, dpi: u32) -> Self {
+        self.dpi = dpi;
+        self
+    }
+}
+
+#[cfg(feature = "pdf-ocr")]
+impl PdfOcr for TesseractOcr {
+    fn recognize(&self, path: &Path, page: usize) -> Result<String, PdfLoaderError> {
+        use std::{
+            process::Command,
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        static IMAGES: AtomicUsize = AtomicUsize::new(0);
+
+        fn run(command: &mut Command) -> Result<Vec<u8>, PdfLoaderError> {
+            let program = command.get_program().to_string_lossy().to_string();
+            let output = command
+                .output()
+                .map_err(|error| PdfLoaderError::OcrError(format!("{program}: {error}")))?;
+            if !output.status.success() {
+                return Err(PdfLoaderError::OcrError(format!(
+                    "{program}: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            Ok(output.stdout)
+        }
+
+        let image = std::env::temp_dir().join(format!(
+            "rig-ocr-{}-{}",
+            std::process::id(),
+            IMAGES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let page = page.to_string();
+        run(Command::new("pdftoppm")
+            .args(["-f", &page, "-l", &page, "-r", &self.dpi.to_string()])
+            .args(["-png", "-singlefile"])
+            .arg(path)
+            .arg(&image))?;
+
+        let image = image.with_extension("png");
+        let text =
+            run(Command::new("tesseract")
+                .arg(&image)
+                .args(["stdout", "-l", &self.language]));
+        let _ = std::fs::remove_file(&image);
+        Ok(String::from_utf8_lossy(&text?).to_string())
+    }
 }
 
 // ================================================================
@@ -153,17 +464,8 @@ impl<'a> PdfFileLoader<'a, Result<PathBuf, PdfLoaderError>> {
     pub fn read(self) -> PdfFileLoader<'a, Result<String, PdfLoaderError>> {
         PdfFileLoader {
             iterator: Box::new(self.iterator.map(|res| {
-                let doc = res.load()?;
-                Ok(doc
-                    .page_iter()
-                    .enumerate()
-                    .map(|(page_no, _)| {
-                        doc.extract_text(&[page_no as u32 + 1])
-                            .map_err(PdfLoaderError::PdfError)
-                    })
-                    .collect::<Result<Vec<String>, PdfLoaderError>>()?
-                    .into_iter()
-                    .collect::<String>())
+                let (path, doc) = res.load_with_path()?;
+                read_text(&path, &doc)
             })),
         }
     }
@@ -188,23 +490,174 @@ impl<'a> PdfFileLoader<'a, Result<PathBuf, PdfLoaderError>> {
         PdfFileLoader {
             iterator: Box::new(self.iterator.map(|res| {
                 let (path, doc) = res.load_with_path()?;
-                println!(
-                    "Loaded {:?} PDF: {:?}",
-                    path,
-                    doc.page_iter().collect::<Vec<_>>()
-                );
-                let content = doc
-                    .page_iter()
-                    .enumerate()
-                    .map(|(page_no, _)| {
-                        doc.extract_text(&[page_no as u32 + 1])
-                            .map_err(PdfLoaderError::PdfError)
+                let content = read_text(&path, &doc)?;
+                Ok((path, content))
+            })),
+        }
+    }
+
+    /// Loads the pdfs as [PdfDocument]s, with their pages and sections. Documents without any
+    ///  text (e.g.: scanned documents) fail with [PdfLoaderError::NoText].
+    ///
+    /// # Example
+    /// Read the pages of the pdfs in directory "tests/data/*.pdf", with their page numbers.
+    ///
+    /// ```rust
+    /// let pages = PdfFileLoader::with_glob("tests/data/*.pdf")?
+    ///     .documents()
+    ///     .ignore_errors()
+    ///     .pages()
+    ///     .chunk(RecursiveSplitter::new(1000, 200));
+    ///
+    /// for page in pages {
+    ///     println!("{:?} page {}: {}", page.source, page.page, page.text);
+    /// }
+    /// ```
+    pub fn documents(self) -> PdfFileLoader<'a, Result<PdfDocument, PdfLoaderError>> {
+        PdfFileLoader {
+            iterator: Box::new(self.iterator.map(|res| PdfDocument::load(res?))),
+        }
+    }
+
+    /// Same as [PdfFileLoader::documents], but the text of the pages without text (e.g.:
+    ///  scanned pages) is recognized by `ocr`.
+    ///
+    /// # Example
+    /// ```rust
+    /// let documents = PdfFileLoader::with_glob("scans/*.pdf")?
+    ///     .documents_with_ocr(TesseractOcr::new().language("eng+fra"))
+    ///     .ignore_errors();
+    /// ```
+    pub fn documents_with_ocr(
+        self,
+        ocr: impl PdfOcr + 'a,
+    ) -> PdfFileLoader<'a, Result<PdfDocument, PdfLoaderError>> {
+        PdfFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .map(move |res| PdfDocument::load_with_ocr(res?, &ocr)),
+            ),
+        }
+    }
+}
+
+/// Text of all the pages of `doc`, failing if there is none
+fn read_text(path: &Path, doc: &Document) -> Result<String, PdfLoaderError> {
+    let content = doc
+        .page_iter()
+        .enumerate()
+        .map(|(page_no, _)| {
+            doc.extract_text(&[page_no as u32 + 1])
+                .map_err(PdfLoaderError::PdfError)
+        })
+        .collect::<Result<String, PdfLoaderError>>()?;
+    match content.trim().is_empty() {
+        true => Err(PdfLoaderError::NoText(path.to_path_buf())),
+        false => Ok(content),
+    }
+}
+
+impl<'a> PdfFileLoader<'a, PdfDocument> {
+    /// Splits each pdf document into its [PdfPage]s.
+    pub fn pages(self) -> PdfFileLoader<'a, PdfPage> {
+        PdfFileLoader {
+            iterator: Box::new(self.iterator.flat_map(|doc| doc.pages)),
+        }
+    }
+
+    /// Splits each pdf document into its [PdfSection]s.
+    pub fn sections(self) -> PdfFileLoader<'a, PdfSection> {
+        PdfFileLoader {
+            iterator: Box::new(self.iterator.flat_map(|doc| doc.sections)),
+        }
+    }
+}
+
+impl<'a> PdfFileLoader<'a, PdfPage> {
+    /// Splits each page into chunks using the given [TextSplitter]. The chunks are returned as
+    ///  pages with the number of the page they come from, and never span multiple pages.
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> PdfFileLoader<'a, PdfPage> {
+        PdfFileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |page| {
+                splitter
+                    .split(&page.text)
+                    .into_iter()
+                    .map(move |text| PdfPage {
+                        text,
+                        ..page.clone()
                     })
-                    .collect::<Result<Vec<String>, PdfLoaderError>>()?
+                    .collect::<Vec<_>>()
+            })),
+        }
+    }
+}
+
+impl<'a> PdfFileLoader<'a, String> {
+    /// Splits the contents of each pdf (or page) into chunks using the given [TextSplitter].
+    ///
+    /// # Example
+    /// Read pdfs in directory "tests/data/*.pdf" and split them into chunks of up to 1000 characters.
+    ///
+    /// ```rust
+    /// let chunks = PdfFileLoader::with_glob("tests/data/*.pdf")?
+    ///     .read()
+    ///     .ignore_errors()
+    ///     .chunk(RecursiveSplitter::new(1000, 200));
+    /// ```
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> PdfFileLoader<'a, String> {
+        PdfFileLoader {
+            iterator: Box::new(
+                self.iterator
+                    .flat_map(move |contents| splitter.split(&contents)),
+            ),
+        }
+    }
+}
+
+impl<'a> PdfFileLoader<'a, (PathBuf, String)> {
+    /// Splits the contents of each pdf into chunks using the given [TextSplitter], keeping
+    ///  track of the path of the pdf each chunk comes from.
+    pub fn chunk(self, splitter: impl TextSplitter + 'a) -> PdfFileLoader<'a, (PathBuf, String)> {
+        PdfFileLoader {
+            iterator: Box::new(self.iterator.flat_map(move |(path, contents)| {
+                splitter
+                    .split(&contents)
                     .into_iter()
-                    .collect::<String>();
+                    .map(move |chunk| (path.clone(), chunk))
+            })),
+        }
+    }
+}
 
-                Ok((path, content))
+impl<'a> PdfFileLoader<'a, (PathBuf, Vec<(usize, String)>)> {
+    /// Splits each page into chunks using the given [TextSplitter], keeping track of the page
+    ///  number each chunk comes from. Chunks never span multiple pages.
+    ///
+    /// # Example
+    /// ```rust
+    /// let chunks = PdfFileLoader::with_glob("tests/data/*.pdf")?
+    ///     .load_with_path()
+    ///     .ignore_errors()
+    ///     .by_page()
+    ///     .ignore_errors()
+    ///     .chunk(RecursiveSplitter::new(1000, 200));
+    /// ```
+    pub fn chunk(
+        self,
+        splitter: impl TextSplitter + 'a,
+    ) -> PdfFileLoader<'a, (PathBuf, Vec<(usize, String)>)> {
+        PdfFileLoader {
+            iterator: Box::new(self.iterator.map(move |(path, pages)| {
+                let chunks = pages
+                    .into_iter()
+                    .flat_map(|(page_no, content)| {
+                        splitter
+                            .split(&content)
+                            .into_iter()
+                            .map(move |chunk| (page_no, chunk))
+                    })
+                    .collect();
+                (path, chunks)
             })),
         }
     }
@@ -335,6 +788,7 @@ impl<'a, T: 'a> PdfFileLoader<'a, Result<T, PdfLoaderError>> {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl PdfFileLoader<'_, Result<PathBuf, FileLoaderError>> {
     /// Creates a new [PdfFileLoader] using a glob pattern to match files.
     ///
@@ -346,8 +800,8 @@ impl PdfFileLoader<'_, Result<PathBuf, FileLoaderError>> {
     /// ```
     pub fn with_glob(
         pattern: &str,
-    ) -> Result<PdfFileLoader<Result<PathBuf, PdfLoaderError>>, PdfLoaderError> {
-        let paths = glob(pattern).map_err(FileLoaderError::PatternError)?;
+    ) -> Result<PdfFileLoader<'_, Result<PathBuf, PdfLoaderError>>, PdfLoaderError> {
+        let paths = glob::glob(pattern).map_err(FileLoaderError::PatternError)?;
         Ok(PdfFileLoader {
             iterator: Box::new(paths.into_iter().map(|path| {
                 path.map_err(FileLoaderError::GlobError)
@@ -366,10 +820,10 @@ impl PdfFileLoader<'_, Result<PathBuf, FileLoaderError>> {
     /// ```
     pub fn with_dir(
         directory: &str,
-    ) -> Result<PdfFileLoader<Result<PathBuf, PdfLoaderError>>, PdfLoaderError> {
+    ) -> Result<PdfFileLoader<'_, Result<PathBuf, PdfLoaderError>>, PdfLoaderError> {
         Ok(PdfFileLoader {
             iterator: Box::new(
-                fs::read_dir(directory)
+                std::fs::read_dir(directory)
                     .map_err(FileLoaderError::IoError)?
                     .map(|entry| Ok(entry.map_err(FileLoaderError::IoError)?.path())),
             ),
@@ -406,9 +860,19 @@ impl<T> Iterator for IntoIter<'_, T> {
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
-    use super::PdfFileLoader;
+    use super::{PdfDocument, PdfFileLoader, PdfLoaderError, PdfOcr, PdfSection};
+    use crate::chunking::FixedSizeSplitter;
+
+    /// OCR engine recognizing the number of the pages
+    struct PageOcr;
+
+    impl PdfOcr for PageOcr {
+        fn recognize(&self, _path: &Path, page: usize) -> Result<String, PdfLoaderError> {
+            Ok(format!("Scanned page {page}"))
+        }
+    }
 
     #[test]
     fn test_pdf_loader() {
@@ -453,4 +917,97 @@ mod tests {
         assert!(!actual.is_empty());
         assert!(expected == actual)
     }
+
+    #[test]
+    fn test_pdf_documents() {
+        let doc = PdfDocument::load("tests/data/pdf/report.pdf").unwrap();
+        assert_eq!(doc.title.as_deref(), Some("Rig report"));
+        // The third page is blank
+        assert_eq!(
+            doc.pages.iter().map(|page| page.page).collect::<Vec<_>>(),
+            vec![1, 2, 4]
+        );
+        assert_eq!(
+            doc.sections,
+            vec![
+                PdfSection {
+                    source: Some("tests/data/pdf/report.pdf".to_string()),
+                    title: Some("Agents".to_string()),
+                    first_page: 1,
+                    last_page: 1,
+                    text: "Rig agents answer prompts".to_string(),
+                },
+                PdfSection {
+                    source: Some("tests/data/pdf/report.pdf".to_string()),
+                    title: Some("Embeddings".to_string()),
+                    first_page: 2,
+                    last_page: 4,
+                    text: "Embeddings are vectors\n\nVector stores index them".to_string(),
+                },
+            ]
+        );
+
+        // Without outline, one section per page
+        let doc = PdfDocument::load("tests/data/pages.pdf").unwrap();
+        assert_eq!(doc.title.as_deref(), Some("Untitled document"));
+        assert_eq!(doc.sections.len(), 3);
+        assert_eq!(doc.sections[1].title, None);
+        assert_eq!(doc.sections[1].first_page, 2);
+        assert_eq!(doc.sections[1].text, "Page\n2");
+
+        // Scanned document
+        assert!(matches!(
+            PdfDocument::load("tests/data/pdf/scanned.pdf"),
+            Err(PdfLoaderError::NoText(_))
+        ));
+        assert!(matches!(
+            PdfFileLoader::with_glob("tests/data/pdf/scanned.pdf")
+                .unwrap()
+                .read()
+                .into_iter()
+                .next(),
+            Some(Err(PdfLoaderError::NoText(_)))
+        ));
+        let doc = PdfDocument::load_with_ocr("tests/data/pdf/scanned.pdf", &PageOcr).unwrap();
+        assert_eq!(doc.text(), "Scanned page 1\n\nScanned page 2");
+        assert!(doc.pages.iter().all(|page| page.ocr));
+    }
+
+    #[test]
+    fn test_pdf_pages() {
+        let pages = PdfFileLoader::with_glob("tests/data/pdf/*.pdf")
+            .unwrap()
+            .documents_with_ocr(PageOcr)
+            .ignore_errors()
+            .pages()
+            .chunk(FixedSizeSplitter::new(16, 0))
+            .into_iter()
+            .collect::<Vec<_>>();
+        let mut pages = pages
+            .iter()
+            .map(|page| {
+                let source = Path::new(page.source.as_deref().unwrap());
+                (source.file_name().unwrap(), page.page, page.text.trim())
+            })
+            .collect::<Vec<_>>();
+        pages.sort();
+
+        let report = |page, text| ("report.pdf".as_ref(), page, text);
+        let scanned = |page, text| ("scanned.pdf".as_ref(), page, text);
+        assert_eq!(
+            pages,
+            vec![
+                report(1, "Rig agents answe"),
+                report(1, "r prompts"),
+                report(2, "Embeddings are v"),
+                report(2, "ectors"),
+                // The blank page is recognized as well
+                report(3, "Scanned page 3"),
+                report(4, "Vector stores in"),
+                report(4, "dex them"),
+                scanned(1, "Scanned page 1"),
+                scanned(2, "Scanned page 2"),
+            ]
+        );
+    }
 }
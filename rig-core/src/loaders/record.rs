@@ -0,0 +1,170 @@
+//! Shared types for the structured data loaders ([CsvFileLoader](super::CsvFileLoader) and
+//! [JsonlFileLoader](super::JsonlFileLoader)), which map each row or line of a file to a [Record].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::embeddings::embed::{Embed, EmbedError, TextEmbedder};
+
+/// Document built from a row of structured data. Only the text is embedded, the metadata is
+///  carried along so that it can be stored next to the embedding in a vector store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub text: String,
+    pub metadata: Map<String, Value>,
+}
+
+impl Embed for Record {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.text.clone());
+        Ok(())
+    }
+}
+
+/// Describes how rows are mapped to [Record]s: which fields make up the text and which fields
+///  are preserved as metadata.
+///
+/// If no text fields are selected, the text is made of all the fields that are not metadata
+///  fields, formatted as `field: value` lines.
+///
+/// # Example
+/// ```rust
+/// use rig::loaders::RecordSchema;
+///
+/// let schema = RecordSchema::new()
+///     .text_fields(["title", "description"])
+///     .metadata_fields(["id", "price"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordSchema {
+    text_fields: Vec<String>,
+    metadata_fields: Vec<String>,
+    separator: String,
+}
+
+impl Default for RecordSchema {
+    fn default() -> Self {
+        Self {
+            text_fields: vec![],
+            metadata_fields: vec![],
+            separator: "\n".to_string(),
+        }
+    }
+}
+
+impl RecordSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fields whose values are concatenated (in order) to make up the text of the record.
+    ///  A record missing one of these fields is an error.
+    pub fn text_fields(mut self, fields: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.text_fields = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Fields preserved as metadata. Fields missing from a record are omitted from its metadata.
+    pub fn metadata_fields(mut self, fields: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.metadata_fields = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Separator between the values of the text fields (defaults to a newline).
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Builds a record from the `fields` of a row, using `get` to look up a field by name.
+    ///  Returns the name of the first missing text field on failure.
+    pub(crate) fn build(
+        &self,
+        fields: &[(String, Value)],
+        get: impl Fn(&str) -> Option<Value>,
+    ) -> Result<Record, String> {
+        let text = if self.text_fields.is_empty() {
+            fields
+                .iter()
+                .filter(|(name, _)| !self.metadata_fields.contains(name))
+                .map(|(name, value)| format!("{name}: {}", value_to_text(value)))
+                .collect::<Vec<_>>()
+        } else {
+            self.text_fields
+                .iter()
+                .map(|field| get(field).map(|value| value_to_text(&value)).ok_or(field))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .filter(|value| !value.is_empty())
+                .collect()
+        };
+
+        let metadata = self
+            .metadata_fields
+            .iter()
+            .filter_map(|field| get(field).map(|value| (field.clone(), value)))
+            .collect();
+
+        Ok(Record {
+            text: text.join(&self.separator),
+            metadata,
+        })
+    }
+}
+
+fn value_to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::RecordSchema;
+
+    #[test]
+    fn test_build() {
+        let fields = vec![
+            ("id".to_string(), json!(1)),
+            ("title".to_string(), json!("Title")),
+            ("tags".to_string(), json!(["a", "b"])),
+            ("empty".to_string(), json!(null)),
+        ];
+        let get = |name: &str| {
+            fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+        };
+
+        let record = RecordSchema::new()
+            .text_fields(["title", "empty", "tags"])
+            .metadata_fields(["id", "missing"])
+            .separator(" | ")
+            .build(&fields, get)
+            .unwrap();
+        assert_eq!(record.text, "Title | [\"a\",\"b\"]");
+        assert_eq!(
+            record.metadata,
+            json!({"id": 1}).as_object().unwrap().clone()
+        );
+
+        let record = RecordSchema::new()
+            .metadata_fields(["id"])
+            .build(&fields, get)
+            .unwrap();
+        assert_eq!(record.text, "title: Title\ntags: [\"a\",\"b\"]\nempty: ");
+
+        assert_eq!(
+            RecordSchema::new()
+                .text_fields(["missing"])
+                .build(&fields, get)
+                .unwrap_err(),
+            "missing"
+        );
+    }
+}
@@ -0,0 +1,689 @@
+//! Conversational memory for agents.
+//!
+//! A [ChatHistory] stores the turns (i.e.: the prompt and the response) of a conversation.
+//! An agent built with [AgentBuilder::memory](crate::agent::AgentBuilder::memory) sends the
+//! remembered messages along with every prompt and records each new turn, so that multi-turn
+//! conversations don't require threading the chat history manually.
+//!
+//! The module provides the following implementations:
+//! - [BufferHistory]: keeps the most recent turns
+//! - [TokenWindowHistory]: keeps the most recent turns fitting in a token budget
+//! - [SummarizingHistory]: summarizes the oldest turns with a completion model
+//! - [StoredHistory]: keeps the most recent turns in a [KvStore], e.g.: to resume a session
+//!
+//! A [RetrievalMemory] complements the history of RAG agents, remembering the documents of the
+//! dynamic context already injected in the conversation so that they are not sent again.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, memory::BufferHistory, providers::openai};
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .memory(BufferHistory::new(10))
+//!     .build();
+//!
+//! agent.prompt("My name is John.").await?;
+//! let response = agent.prompt("What is my name?").await?;
+//! ```
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    future::Future,
+    hash::{Hash, Hasher},
+    pin::Pin,
+};
+
+use crate::{
+    completion::{AssistantContent, CompletionError, CompletionModel, Document, Message},
+    message::{ToolResultContent, UserContent},
+    storage::{KvStore, StorageError},
+    tokenizer::TokenCounter,
+};
+
+/// Storage of the turns of a conversation.
+pub trait ChatHistory: Send + Sync {
+    /// Messages to send along with the next prompt, oldest first.
+    fn messages(&self) -> Vec<Message>;
+
+    /// Summary of the turns that are no longer part of the [messages](ChatHistory::messages), if any.
+    /// It is added to the context of the next prompt.
+    fn summary(&self) -> Option<String> {
+        None
+    }
+
+    /// Record the messages of a turn of the conversation.
+    fn push(
+        &mut self,
+        turn: Vec<Message>,
+    ) -> impl Future<Output = Result<(), CompletionError>> + Send;
+
+    /// Forget the whole conversation.
+    fn clear(&mut self);
+
+    /// Turns of the conversation remembered by the history, oldest first, e.g.: to save them
+    /// in a [Session](crate::session::Session).
+    fn turns(&self) -> Vec<Vec<Message>>;
+
+    /// Replace the conversation with `turns` (oldest first) and `summary`, e.g.: to resume a
+    /// saved [Session](crate::session::Session). The limits of the history apply to the
+    /// restored turns.
+    fn restore(&mut self, turns: Vec<Vec<Message>>, summary: Option<String>);
+}
+
+/// Wrapper trait to allow for dynamic dispatch of chat histories
+pub trait ChatHistoryDyn: Send + Sync {
+    fn messages(&self) -> Vec<Message>;
+
+    fn summary(&self) -> Option<String>;
+
+    fn push(
+        &mut self,
+        turn: Vec<Message>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), CompletionError>> + Send + '_>>;
+
+    fn clear(&mut self);
+
+    fn turns(&self) -> Vec<Vec<Message>>;
+
+    fn restore(&mut self, turns: Vec<Vec<Message>>, summary: Option<String>);
+}
+
+impl<T: ChatHistory> ChatHistoryDyn for T {
+    fn messages(&self) -> Vec<Message> {
+        <Self as ChatHistory>::messages(self)
+    }
+
+    fn summary(&self) -> Option<String> {
+        <Self as ChatHistory>::summary(self)
+    }
+
+    fn push(
+        &mut self,
+        turn: Vec<Message>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), CompletionError>> + Send + '_>> {
+        Box::pin(<Self as ChatHistory>::push(self, turn))
+    }
+
+    fn clear(&mut self) {
+        <Self as ChatHistory>::clear(self)
+    }
+
+    fn turns(&self) -> Vec<Vec<Message>> {
+        <Self as ChatHistory>::turns(self)
+    }
+
+    fn restore(&mut self, turns: Vec<Vec<Message>>, summary: Option<String>) {
+        <Self as ChatHistory>::restore(self, turns, summary)
+    }
+}
+
+/// In-memory ring buffer of the most recent turns of a conversation.
+#[derive(Debug, Clone)]
+pub struct BufferHistory {
+    capacity: usize,
+    turns: VecDeque<Vec<Message>>,
+}
+
+impl BufferHistory {
+    /// Create a history keeping the `capacity` most recent turns.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            turns: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn push_turn(&mut self, turn: Vec<Message>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.turns.len() == self.capacity {
+            self.turns.pop_front();
+        }
+        self.turns.push_back(turn);
+    }
+}
+
+impl ChatHistory for BufferHistory {
+    fn messages(&self) -> Vec<Message> {
+        self.turns.iter().flatten().cloned().collect()
+    }
+
+    async fn push(&mut self, turn: Vec<Message>) -> Result<(), CompletionError> {
+        self.push_turn(turn);
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.turns.clear();
+    }
+
+    fn turns(&self) -> Vec<Vec<Message>> {
+        self.turns.iter().cloned().collect()
+    }
+
+    fn restore(&mut self, turns: Vec<Vec<Message>>, _summary: Option<String>) {
+        self.turns.clear();
+        turns.into_iter().for_each(|turn| self.push_turn(turn));
+    }
+}
+
+/// In-memory history keeping the most recent turns whose messages fit in a token budget.
+/// Tokens are estimated with [estimate_tokens].
+#[derive(Debug, Clone)]
+pub struct TokenWindowHistory {
+    max_tokens: usize,
+    tokens: usize,
+    turns: VecDeque<(Vec<Message>, usize)>,
+}
+
+impl TokenWindowHistory {
+    /// Create a history keeping the most recent turns totalling at most `max_tokens` tokens.
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            tokens: 0,
+            turns: VecDeque::new(),
+        }
+    }
+
+    fn push_turn(&mut self, turn: Vec<Message>) {
+        let tokens = turn.iter().map(estimate_tokens).sum::<usize>();
+        self.tokens += tokens;
+        self.turns.push_back((turn, tokens));
+
+        while self.tokens > self.max_tokens {
+            match self.turns.pop_front() {
+                Some((_, tokens)) => self.tokens -= tokens,
+                None => break,
+            }
+        }
+    }
+}
+
+impl ChatHistory for TokenWindowHistory {
+    fn messages(&self) -> Vec<Message> {
+        self.turns
+            .iter()
+            .flat_map(|(turn, _)| turn)
+            .cloned()
+            .collect()
+    }
+
+    async fn push(&mut self, turn: Vec<Message>) -> Result<(), CompletionError> {
+        self.push_turn(turn);
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.turns.clear();
+        self.tokens = 0;
+    }
+
+    fn turns(&self) -> Vec<Vec<Message>> {
+        self.turns.iter().map(|(turn, _)| turn.clone()).collect()
+    }
+
+    fn restore(&mut self, turns: Vec<Vec<Message>>, _summary: Option<String>) {
+        <Self as ChatHistory>::clear(self);
+        turns.into_iter().for_each(|turn| self.push_turn(turn));
+    }
+}
+
+/// History keeping the most recent turns of a conversation and summarizing the older ones with
+/// a completion model, once the conversation exceeds a number of turns.
+pub struct SummarizingHistory<M: CompletionModel> {
+    model: M,
+    max_turns: usize,
+    keep_turns: usize,
+    summary: Option<String>,
+    turns: VecDeque<Vec<Message>>,
+}
+
+impl<M: CompletionModel> SummarizingHistory<M> {
+    /// Create a history summarizing the oldest turns with `model` when there are more than
+    /// `max_turns` turns. Only the most recent turn is kept as is, unless set otherwise
+    /// with [SummarizingHistory::keep_turns].
+    pub fn new(model: M, max_turns: usize) -> Self {
+        Self {
+            model,
+            max_turns,
+            keep_turns: 1,
+            summary: None,
+            turns: VecDeque::new(),
+        }
+    }
+
+    /// Set the number of most recent turns kept as is when summarizing (at most `max_turns`).
+    pub fn keep_turns(mut self, keep_turns: usize) -> Self {
+        self.keep_turns = keep_turns.min(self.max_turns);
+        self
+    }
+
+    async fn summarize(&mut self) -> Result<(), CompletionError> {
+        let summarized = self.turns.len() - self.keep_turns;
+        let transcript = self
+            .turns
+            .iter()
+            .take(summarized)
+            .flatten()
+            .map(transcript_line)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let prompt = match &self.summary {
+            Some(summary) => format!(
+                "Summary of the earlier conversation:\n{summary}\n\nFollow-up of the conversation:\n{transcript}"
+            ),
+            None => format!("Conversation:\n{transcript}"),
+        };
+
+        let response = self
+            .model
+            .completion_request(Message::user(prompt))
+            .preamble(
+                "Summarize the conversation below in a few sentences, keeping every fact, \
+                decision and open question that may matter in the rest of the conversation."
+                    .to_string(),
+            )
+            .send()
+            .await?;
+
+        let summary = response
+            .choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.clone()),
+                AssistantContent::ToolCall(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        self.summary = Some(summary);
+        self.turns.drain(..summarized);
+        Ok(())
+    }
+}
+
+impl<M: CompletionModel> ChatHistory for SummarizingHistory<M> {
+    fn messages(&self) -> Vec<Message> {
+        self.turns.iter().flatten().cloned().collect()
+    }
+
+    fn summary(&self) -> Option<String> {
+        self.summary.clone()
+    }
+
+    async fn push(&mut self, turn: Vec<Message>) -> Result<(), CompletionError> {
+        self.turns.push_back(turn);
+        if self.turns.len() > self.max_turns {
+            self.summarize().await?;
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.turns.clear();
+        self.summary = None;
+    }
+
+    fn turns(&self) -> Vec<Vec<Message>> {
+        self.turns.iter().cloned().collect()
+    }
+
+    /// The restored turns are summarized with the next turn if there are more than `max_turns`.
+    fn restore(&mut self, turns: Vec<Vec<Message>>, summary: Option<String>) {
+        self.turns = turns.into();
+        self.summary = summary;
+    }
+}
+
+/// History keeping the most recent turns of a conversation (like [BufferHistory]) and saving
+/// them in a [KvStore] after every turn, so that the conversation can be resumed with
+/// [StoredHistory::load] (e.g.: after a restart or on another server).
+///
+/// Clearing or restoring the history only takes effect in the store when the next turn is saved.
+pub struct StoredHistory<S: KvStore> {
+    store: S,
+    key: String,
+    buffer: BufferHistory,
+}
+
+impl<S: KvStore> StoredHistory<S> {
+    /// Load the turns saved under `key` in `store` (if any), keeping the `capacity` most recent ones.
+    pub async fn load(store: S, key: &str, capacity: usize) -> Result<Self, StorageError> {
+        let mut buffer = BufferHistory::new(capacity);
+        let turns: Vec<Vec<Message>> = store.load(key).await?.unwrap_or_default();
+        let skipped = turns.len().saturating_sub(capacity);
+        buffer.turns.extend(turns.into_iter().skip(skipped));
+
+        Ok(Self {
+            store,
+            key: key.to_string(),
+            buffer,
+        })
+    }
+}
+
+impl<S: KvStore> ChatHistory for StoredHistory<S> {
+    fn messages(&self) -> Vec<Message> {
+        <BufferHistory as ChatHistory>::messages(&self.buffer)
+    }
+
+    async fn push(&mut self, turn: Vec<Message>) -> Result<(), CompletionError> {
+        <BufferHistory as ChatHistory>::push(&mut self.buffer, turn).await?;
+        self.store
+            .save(&self.key, &self.buffer.turns)
+            .await
+            .map_err(|e| CompletionError::RequestError(e.into()))
+    }
+
+    fn clear(&mut self) {
+        <BufferHistory as ChatHistory>::clear(&mut self.buffer);
+    }
+
+    fn turns(&self) -> Vec<Vec<Message>> {
+        <BufferHistory as ChatHistory>::turns(&self.buffer)
+    }
+
+    fn restore(&mut self, turns: Vec<Vec<Message>>, summary: Option<String>) {
+        <BufferHistory as ChatHistory>::restore(&mut self.buffer, turns, summary);
+    }
+}
+
+/// Note ending the excerpts of the documents compressed by a [RetrievalMemory]
+const REPEATED_NOTE: &str = "[...] (provided in full earlier in the conversation)";
+
+/// What a [RetrievalMemory] does with the documents already injected in the conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatedDocuments {
+    /// Leave the documents out of the dynamic context
+    Skip,
+    /// Replace the text of the documents with an excerpt of at most the given number of tokens
+    /// (its first sentences), and a note that they were provided earlier in the conversation
+    Compress(usize),
+}
+
+/// Documents of the dynamic context injected in the prompts of a conversation.
+///
+/// An agent built with [AgentBuilder::retrieval_memory](crate::agent::AgentBuilder::retrieval_memory)
+/// skips or compresses the documents retrieved again in the next turns, as the model still sees
+/// them in the chat history, instead of paying for their tokens at every turn. A document whose
+/// text changed since it was injected is injected again in full.
+#[derive(Debug, Clone)]
+pub struct RetrievalMemory {
+    policy: RepeatedDocuments,
+    forget_after: Option<usize>,
+    turn: usize,
+    /// Hash of the text of the injected documents, with the turn they were injected at, by id
+    injected: HashMap<String, (u64, usize)>,
+}
+
+impl RetrievalMemory {
+    pub fn new(policy: RepeatedDocuments) -> Self {
+        Self {
+            policy,
+            forget_after: None,
+            turn: 0,
+            injected: HashMap::new(),
+        }
+    }
+
+    /// Inject again in full the documents injected more than `turns` turns ago, e.g.: when the
+    /// chat history only keeps the most recent turns.
+    pub fn forget_after(mut self, turns: usize) -> Self {
+        self.forget_after = Some(turns);
+        self
+    }
+
+    /// Whether `document` was already injected, with the same text, in the remembered turns
+    pub fn is_injected(&self, document: &Document) -> bool {
+        self.injected
+            .get(&document.id)
+            .is_some_and(|(hash, turn)| *hash == text_hash(&document.text) && self.remembers(*turn))
+    }
+
+    fn remembers(&self, turn: usize) -> bool {
+        self.forget_after
+            .is_none_or(|turns| self.turn - turn <= turns)
+    }
+
+    /// Ids of the documents injected in the remembered turns, sorted
+    pub fn injected(&self) -> Vec<String> {
+        let mut ids = self
+            .injected
+            .iter()
+            .filter(|(_, (_, turn))| self.remembers(*turn))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    /// Skip or compress the `documents` already injected, according to the policy.
+    pub fn apply(&self, documents: Vec<Document>, counter: &dyn TokenCounter) -> Vec<Document> {
+        documents
+            .into_iter()
+            .filter_map(|mut doc| {
+                if !self.is_injected(&doc) {
+                    return Some(doc);
+                }
+                match self.policy {
+                    RepeatedDocuments::Skip => None,
+                    RepeatedDocuments::Compress(max_tokens) => {
+                        let excerpt = counter.truncate_sentences(&doc.text, max_tokens);
+                        doc.text = match excerpt.is_empty() {
+                            true => REPEATED_NOTE.to_string(),
+                            false => format!("{excerpt} {REPEATED_NOTE}"),
+                        };
+                        Some(doc)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Record the `documents` injected in a turn of the conversation (as returned by
+    /// [apply](Self::apply)), and start the next turn.
+    pub fn record(&mut self, documents: &[Document]) {
+        for doc in documents {
+            // Compressed documents keep the text and the turn they were injected in full at
+            if !doc.text.ends_with(REPEATED_NOTE) {
+                self.injected
+                    .insert(doc.id.clone(), (text_hash(&doc.text), self.turn));
+            }
+        }
+        self.turn += 1;
+    }
+
+    /// Forget the injected documents, e.g.: when starting a new conversation.
+    pub fn clear(&mut self) {
+        self.injected.clear();
+        self.turn = 0;
+    }
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Rough estimate of the number of tokens of a message (about 4 characters per token).
+pub fn estimate_tokens(message: &Message) -> usize {
+    message_text(message).chars().count().div_ceil(4)
+}
+
+pub(crate) fn transcript_line(message: &Message) -> String {
+    match message {
+        Message::User { .. } => format!("User: {}", message_text(message)),
+        Message::Assistant { .. } => format!("Assistant: {}", message_text(message)),
+    }
+}
+
+/// Text of a message, with placeholders for non-text content
+pub(crate) fn message_text(message: &Message) -> String {
+    let parts = match message {
+        Message::User { content } => content
+            .iter()
+            .map(|content| match content {
+                UserContent::Text(text) => text.text.clone(),
+                UserContent::ToolResult(result) => result
+                    .content
+                    .iter()
+                    .map(|content| match content {
+                        ToolResultContent::Text(text) => text.text.clone(),
+                        ToolResultContent::Image(_) => "[image]".to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                UserContent::Image(_) => "[image]".to_string(),
+                UserContent::Audio(_) => "[audio]".to_string(),
+                UserContent::Document(_) => "[document]".to_string(),
+            })
+            .collect::<Vec<_>>(),
+        Message::Assistant { content } => content
+            .iter()
+            .map(|content| match content {
+                AssistantContent::Text(text) => text.text.clone(),
+                AssistantContent::ToolCall(tool_call) => format!(
+                    "[called `{}` with {}]",
+                    tool_call.function.name, tool_call.function.arguments
+                ),
+            })
+            .collect::<Vec<_>>(),
+    };
+    parts.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{
+        message_text, BufferHistory, ChatHistory, StoredHistory, SummarizingHistory,
+        TokenWindowHistory,
+    };
+    use crate::{
+        completion::{
+            AssistantContent, CompletionError, CompletionModel, CompletionRequest,
+            CompletionResponse, Message,
+        },
+        storage::InMemoryStore,
+        OneOrMany,
+    };
+
+    fn turn(prompt: &str, response: &str) -> Vec<Message> {
+        vec![Message::user(prompt), Message::assistant(response)]
+    }
+
+    #[tokio::test]
+    async fn test_buffer_history() {
+        let mut history = BufferHistory::new(2);
+        history.push(turn("a", "b")).await.unwrap();
+        history.push(turn("c", "d")).await.unwrap();
+        history.push(turn("e", "f")).await.unwrap();
+
+        assert_eq!(
+            history.messages(),
+            [turn("c", "d"), turn("e", "f")].concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_token_window_history() {
+        // 2 tokens per turn
+        let mut history = TokenWindowHistory::new(5);
+        history.push(turn("abcd", "efgh")).await.unwrap();
+        history.push(turn("ijkl", "mnop")).await.unwrap();
+        assert_eq!(history.messages().len(), 4);
+
+        history.push(turn("qrst", "uvwx")).await.unwrap();
+        assert_eq!(
+            history.messages(),
+            [turn("ijkl", "mnop"), turn("qrst", "uvwx")].concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_history() {
+        let turns = vec![turn("a", "b"), turn("c", "d"), turn("e", "f")];
+        let mut history = BufferHistory::new(2);
+        history.push(turn("g", "h")).await.unwrap();
+        history.restore(turns.clone(), None);
+        assert_eq!(history.turns(), turns[1..]);
+
+        // 2 tokens per turn
+        let mut history = TokenWindowHistory::new(3);
+        history.restore(turns.clone(), None);
+        assert_eq!(history.turns(), turns[2..]);
+
+        let mut history = SummarizingHistory::new(SummaryModel::default(), 2);
+        history.restore(turns[1..].to_vec(), Some("earlier".to_string()));
+        assert_eq!(history.turns(), turns[1..]);
+        assert_eq!(history.summary(), Some("earlier".to_string()));
+    }
+
+    /// Model answering with a fixed summary and recording the prompts it receives
+    #[derive(Clone, Default)]
+    struct SummaryModel {
+        prompts: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl CompletionModel for SummaryModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            self.prompts
+                .lock()
+                .unwrap()
+                .push(message_text(&request.prompt));
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text("summary")),
+                usage: None,
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summarizing_history() {
+        let model = SummaryModel::default();
+        let mut history = SummarizingHistory::new(model.clone(), 2);
+        history.push(turn("a", "b")).await.unwrap();
+        history.push(turn("c", "d")).await.unwrap();
+        assert!(history.summary().is_none());
+
+        history.push(turn("e", "f")).await.unwrap();
+        assert_eq!(history.summary(), Some("summary".to_string()));
+        assert_eq!(history.messages(), turn("e", "f"));
+        assert_eq!(
+            model.prompts.lock().unwrap()[0],
+            "Conversation:\nUser: a\nAssistant: b\nUser: c\nAssistant: d"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stored_history() {
+        let store = InMemoryStore::new();
+        let mut history = StoredHistory::load(store.clone(), "session", 2)
+            .await
+            .unwrap();
+        history.push(turn("a", "b")).await.unwrap();
+        history.push(turn("c", "d")).await.unwrap();
+
+        // The turns are resumed from the store
+        let history = StoredHistory::load(store.clone(), "session", 1)
+            .await
+            .unwrap();
+        assert_eq!(history.messages(), turn("c", "d"));
+        assert!(StoredHistory::load(store, "other", 2)
+            .await
+            .unwrap()
+            .messages()
+            .is_empty());
+    }
+}
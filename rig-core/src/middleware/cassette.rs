@@ -0,0 +1,389 @@
+//! Record/replay ("VCR") of the HTTP traffic of provider clients, to make the integration tests
+//! of agents reproducible, offline and free.
+//!
+//! A [Cassette] is a [Middleware] storing the requests of a client and the responses they
+//! received in a JSON file. In [CassetteMode::Auto] (the default), the first run sends the
+//! requests to the provider and records them, and the next runs (e.g.: in CI) replay the
+//! recorded responses without calling the provider. Delete the file, or use
+//! [CassetteMode::Record], to record the cassette again.
+//!
+//! Requests are matched on their method, the path and query of their URL, and their body, in
+//! the order they were recorded. The headers of the requests (e.g.: API keys) are not recorded.
+//! Requests whose body is a stream, or changes between runs (e.g.: multipart forms with random
+//! boundaries), cannot be replayed.
+//!
+//! # Example
+//! ```rust
+//! use rig::{middleware::cassette::Cassette, providers::openai};
+//!
+//! let client = openai::Client::builder(&std::env::var("OPENAI_API_KEY").unwrap_or_default())
+//!     .with_middleware(Cassette::new("tests/cassettes/summarize.json")?)
+//!     .build();
+//!
+//! let agent = client.agent(openai::GPT_4O).build();
+//! let summary = agent.prompt("Summarize the plot of Hamlet").await?;
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use reqwest::{Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::Middleware;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CassetteError {
+    /// Error reading or writing the cassette file
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// The cassette file is not a valid cassette
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Whether a [Cassette] records or replays the traffic of its client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CassetteMode {
+    /// Replay the cassette if its file exists, and record it otherwise
+    #[default]
+    Auto,
+    /// Send the requests and record them, replacing the interactions of the cassette
+    Record,
+    /// Only replay the recorded interactions, answering the other requests with a
+    /// `404 Not Found` error
+    Replay,
+}
+
+/// Request recorded in a cassette
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query of the URL of the request
+    pub url: String,
+    /// Body of the request, as JSON if it is JSON and as a string otherwise
+    #[serde(default)]
+    pub body: Value,
+}
+
+/// Response recorded in a cassette
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// Content type of the response, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Body of the response, as JSON if it is JSON and as a string otherwise (e.g.: for
+    /// streaming responses)
+    pub body: Value,
+}
+
+/// Request of a cassette with the response it received
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// [Middleware] recording the traffic of a client to a file, and replaying it (see the
+/// [module documentation](self)).
+pub struct Cassette {
+    path: PathBuf,
+    replay: bool,
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// Open the cassette stored at `path` in [CassetteMode::Auto].
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+        Self::with_mode(path, CassetteMode::Auto)
+    }
+
+    /// Open the cassette stored at `path`, which must exist to replay it.
+    pub fn with_mode(path: impl AsRef<Path>, mode: CassetteMode) -> Result<Self, CassetteError> {
+        let path = path.as_ref().to_path_buf();
+        let replay = match mode {
+            CassetteMode::Auto => path.exists(),
+            CassetteMode::Record => false,
+            CassetteMode::Replay => true,
+        };
+        let interactions = match replay {
+            true => serde_json::from_slice::<CassetteFile>(&std::fs::read(&path)?)?.interactions,
+            false => vec![],
+        };
+
+        Ok(Self {
+            path,
+            replay,
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+        })
+    }
+
+    /// Whether the cassette replays its interactions (instead of recording them)
+    pub fn is_replaying(&self) -> bool {
+        self.replay
+    }
+
+    /// Interactions of the cassette, recorded or loaded
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.interactions
+            .lock()
+            .expect("cassette lock poisoned")
+            .iter()
+            .map(|(interaction, _)| interaction.clone())
+            .collect()
+    }
+
+    fn save(&self, interactions: &[(Interaction, bool)]) -> Result<(), CassetteError> {
+        let file = CassetteFile {
+            interactions: interactions.iter().map(|(i, _)| i.clone()).collect(),
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&file)?)?;
+        Ok(())
+    }
+}
+
+/// Body as JSON if it is JSON, and as a string otherwise
+fn body_value(body: &[u8]) -> Value {
+    match body {
+        [] => Value::Null,
+        body => serde_json::from_slice(body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned())),
+    }
+}
+
+fn body_bytes(body: &Value) -> Vec<u8> {
+    match body {
+        Value::Null => vec![],
+        Value::String(text) => text.clone().into_bytes(),
+        json => json.to_string().into_bytes(),
+    }
+}
+
+fn recorded_request(request: &Request) -> RecordedRequest {
+    let url = request.url();
+    RecordedRequest {
+        method: request.method().to_string(),
+        url: match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        },
+        body: request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(body_value)
+            .unwrap_or_default(),
+    }
+}
+
+fn response(status: u16, content_type: Option<&str>, body: Vec<u8>) -> Response {
+    let mut response = http::Response::builder().status(status);
+    if let Some(content_type) = content_type {
+        response = response.header(reqwest::header::CONTENT_TYPE, content_type);
+    }
+    response
+        .body(body)
+        .expect("recorded responses are valid")
+        .into()
+}
+
+impl Middleware for Cassette {
+    async fn respond(&self, request: &Request) -> Option<Response> {
+        if !self.replay {
+            return None;
+        }
+        let request = recorded_request(request);
+        let mut interactions = self.interactions.lock().expect("cassette lock poisoned");
+        // Replay the first unused interaction matching the request, or the last one if they
+        // were all replayed already
+        let matching = interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, (interaction, _))| interaction.request == request)
+            .map(|(position, (_, used))| (position, *used))
+            .collect::<Vec<_>>();
+        let position = matching
+            .iter()
+            .find(|(_, used)| !used)
+            .or(matching.last())
+            .map(|(position, _)| *position);
+
+        Some(match position {
+            Some(position) => {
+                interactions[position].1 = true;
+                let recorded = &interactions[position].0.response;
+                response(
+                    recorded.status,
+                    recorded.content_type.as_deref(),
+                    body_bytes(&recorded.body),
+                )
+            }
+            None => {
+                tracing::warn!(target: "rig",
+                    "No interaction of cassette {} matches {} {}",
+                    self.path.display(),
+                    request.method,
+                    request.url
+                );
+                response(
+                    404,
+                    Some("text/plain"),
+                    format!(
+                        "No interaction of cassette {} matches {} {}",
+                        self.path.display(),
+                        request.method,
+                        request.url
+                    )
+                    .into_bytes(),
+                )
+            }
+        })
+    }
+
+    async fn on_exchange(
+        &self,
+        request: &Request,
+        response: Response,
+    ) -> reqwest::Result<Response> {
+        if self.replay {
+            return Ok(response);
+        }
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string);
+        let body = response.bytes().await?.to_vec();
+
+        let interaction = Interaction {
+            request: recorded_request(request),
+            response: RecordedResponse {
+                status,
+                content_type: content_type.clone(),
+                body: body_value(&body),
+            },
+        };
+        let mut interactions = self.interactions.lock().expect("cassette lock poisoned");
+        interactions.push((interaction, true));
+        if let Err(e) = self.save(&interactions) {
+            tracing::warn!(target: "rig", "Failed to save cassette {}: {e}", self.path.display());
+        }
+
+        Ok(self::response(status, content_type.as_deref(), body))
+    }
+}
+
+#[cfg(all(test, feature = "providers"))]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        embeddings::{EmbeddingError, EmbeddingModel},
+        providers::openai,
+    };
+
+    fn client(base_url: &str, cassette: Cassette) -> openai::Client {
+        openai::Client::builder("key")
+            .base_url(base_url)
+            .with_middleware(cassette)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_cassette() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("cassettes/embeddings.json");
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/embeddings");
+            then.status(200).json_body(json!({
+                "object": "list",
+                "data": [{"object": "embedding", "embedding": [0.5], "index": 0}],
+                "model": openai::TEXT_EMBEDDING_3_SMALL,
+                "usage": {"prompt_tokens": 2, "total_tokens": 2}
+            }));
+        });
+
+        // The first run records the interaction
+        let cassette = Cassette::new(&path).unwrap();
+        assert!(!cassette.is_replaying());
+        let model =
+            client(&server.base_url(), cassette).embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+        assert_eq!(model.embed_text("Hello").await.unwrap().vec, vec![0.5]);
+        mock.assert_hits(1);
+
+        let cassette = Cassette::new(&path).unwrap();
+        assert!(cassette.is_replaying());
+        let interactions = cassette.interactions();
+        assert_eq!(interactions.len(), 1);
+        assert_eq!(interactions[0].request.url, "/embeddings");
+        assert_eq!(
+            interactions[0].request.body,
+            json!({"model": openai::TEXT_EMBEDDING_3_SMALL, "input": ["Hello"]})
+        );
+
+        // The next runs replay it, even without the server
+        let model =
+            client("http://localhost:9", cassette).embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+        for _ in 0..2 {
+            assert_eq!(model.embed_text("Hello").await.unwrap().vec, vec![0.5]);
+        }
+        mock.assert_hits(1);
+
+        assert!(matches!(
+            model.embed_text("Goodbye").await,
+            Err(EmbeddingError::ProviderError(message)) if message.contains("No interaction")
+        ));
+        assert!(
+            Cassette::with_mode(dir.path().join("missing.json"), CassetteMode::Replay).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cassette_errors() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("embeddings.json");
+        std::fs::write(&path, "not a cassette").unwrap();
+        assert!(matches!(
+            Cassette::new(&path),
+            Err(CassetteError::JsonError(_))
+        ));
+
+        // Error responses are recorded and replayed as well, over the previous cassette
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/embeddings");
+            then.status(500).body("Internal error");
+        });
+        let cassette = Cassette::with_mode(&path, CassetteMode::Record).unwrap();
+        let model =
+            client(&server.base_url(), cassette).embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+        assert!(model.embed_text("Hello").await.is_err());
+
+        let cassette = Cassette::new(&path).unwrap();
+        let response = &cassette.interactions()[0].response;
+        assert_eq!(response.status, 500);
+        assert_eq!(response.body, json!("Internal error"));
+        let model =
+            client("http://localhost:9", cassette).embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+        assert!(matches!(
+            model.embed_text("Hello").await,
+            Err(EmbeddingError::ProviderError(message)) if message == "Internal error"
+        ));
+    }
+}
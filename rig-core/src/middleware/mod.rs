@@ -0,0 +1,414 @@
+//! Middleware of the HTTP clients of the providers, to inspect and modify their requests and
+//! responses without forking the provider code.
+//!
+//! A [Middleware] added to a provider client (e.g.: with
+//! [openai::ClientBuilder::with_middleware](crate::providers::openai::ClientBuilder::with_middleware))
+//! runs on every request the client sends, before it is sent, and on every response it receives,
+//! before the provider parses it. This can be used to e.g.:
+//! - add custom headers (e.g.: a tenant id, or the credentials of a corporate API gateway)
+//! - rewrite the URL of the requests (e.g.: to route them through a gateway)
+//! - redact sensitive information from the prompts
+//! - log the requests and responses (e.g.: to an audit trail)
+//!
+//! Middleware can also answer requests without sending them (e.g.: from a cache), and observe
+//! the requests sent with the responses they received (e.g.: to record them), as the
+//! [Cassette](cassette::Cassette) does to record and replay the traffic of a client in tests.
+//!
+//! The [RunHeaders] middleware adds the ids of the current
+//! [run](crate::telemetry#session-and-run-ids) to the headers of the requests.
+//!
+//! Middleware run in the order in which they were added for requests, and in reverse order for
+//! responses. Note that consuming the body of a response (e.g.: to log it) buffers streaming
+//! responses: the middleware should then only read the body of non-streaming responses.
+//!
+//! # Example
+//! ```rust
+//! use rig::{middleware::Middleware, providers::openai};
+//!
+//! struct TenantHeader(&'static str);
+//!
+//! impl Middleware for TenantHeader {
+//!     async fn on_request(&self, request: &mut reqwest::Request) {
+//!         request
+//!             .headers_mut()
+//!             .insert("x-tenant-id", self.0.parse().unwrap());
+//!     }
+//! }
+//!
+//! let client = openai::Client::builder("your-open-ai-api-key")
+//!     .with_middleware(TenantHeader("acme"))
+//!     .build();
+//! ```
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use reqwest::{IntoUrl, Request, Response};
+use serde::Serialize;
+
+use crate::runtime::WasmCompatSend;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cassette;
+
+/// Future of a [MiddlewareDyn] hook
+#[cfg(not(target_arch = "wasm32"))]
+pub type MiddlewareFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[cfg(target_arch = "wasm32")]
+pub type MiddlewareFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Hooks run on the requests sent and the responses received by a provider client.
+///
+/// The futures of the hooks are `Send`, except on wasm where the responses of the `fetch` API
+/// are not (see [WasmCompatSend]).
+pub trait Middleware: Send + Sync {
+    /// Inspect or modify a request before it is sent (e.g.: its headers, URL or body).
+    fn on_request(&self, _request: &mut Request) -> impl Future<Output = ()> + WasmCompatSend {
+        async {}
+    }
+
+    /// Inspect or modify a response before it is parsed by the provider, returning the
+    /// (possibly replaced) response.
+    fn on_response(&self, response: Response) -> impl Future<Output = Response> + WasmCompatSend {
+        async { response }
+    }
+
+    /// Answer a request without sending it (e.g.: from a cache or a recording). The first
+    /// middleware answering a request skips the HTTP call, and its response is still run
+    /// through [Middleware::on_response].
+    fn respond(
+        &self,
+        _request: &Request,
+    ) -> impl Future<Output = Option<Response>> + WasmCompatSend {
+        async { None }
+    }
+
+    /// Observe a request that was sent with the response it received from the network, before
+    /// the response is run through [Middleware::on_response], returning the (possibly replaced)
+    /// response. Not called for requests whose body is a stream.
+    fn on_exchange(
+        &self,
+        _request: &Request,
+        response: Response,
+    ) -> impl Future<Output = reqwest::Result<Response>> + WasmCompatSend {
+        async { Ok(response) }
+    }
+}
+
+/// Wrapper trait to allow for dynamic dispatch of middleware
+pub trait MiddlewareDyn: Send + Sync {
+    fn on_request<'a>(&'a self, request: &'a mut Request) -> MiddlewareFuture<'a, ()>;
+
+    fn on_response(&self, response: Response) -> MiddlewareFuture<'_, Response>;
+
+    fn respond<'a>(&'a self, request: &'a Request) -> MiddlewareFuture<'a, Option<Response>>;
+
+    fn on_exchange<'a>(
+        &'a self,
+        request: &'a Request,
+        response: Response,
+    ) -> MiddlewareFuture<'a, reqwest::Result<Response>>;
+}
+
+impl<T: Middleware> MiddlewareDyn for T {
+    fn on_request<'a>(&'a self, request: &'a mut Request) -> MiddlewareFuture<'a, ()> {
+        Box::pin(<Self as Middleware>::on_request(self, request))
+    }
+
+    fn on_response(&self, response: Response) -> MiddlewareFuture<'_, Response> {
+        Box::pin(<Self as Middleware>::on_response(self, response))
+    }
+
+    fn respond<'a>(&'a self, request: &'a Request) -> MiddlewareFuture<'a, Option<Response>> {
+        Box::pin(<Self as Middleware>::respond(self, request))
+    }
+
+    fn on_exchange<'a>(
+        &'a self,
+        request: &'a Request,
+        response: Response,
+    ) -> MiddlewareFuture<'a, reqwest::Result<Response>> {
+        Box::pin(<Self as Middleware>::on_exchange(self, request, response))
+    }
+}
+
+/// [Middleware] adding the session id and the run id of the current
+/// [RunContext](crate::telemetry::RunContext) to the headers of the requests (`x-rig-session-id`
+/// and `x-rig-run-id` by default), so that the provider requests (e.g.: to a gateway) can be
+/// correlated with the user interaction they are part of.
+#[derive(Clone, Debug)]
+pub struct RunHeaders {
+    session_header: String,
+    run_header: String,
+}
+
+impl Default for RunHeaders {
+    fn default() -> Self {
+        Self {
+            session_header: "x-rig-session-id".to_string(),
+            run_header: "x-rig-run-id".to_string(),
+        }
+    }
+}
+
+impl RunHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the names of the session id and run id headers
+    pub fn headers(mut self, session_header: &str, run_header: &str) -> Self {
+        self.session_header = session_header.to_string();
+        self.run_header = run_header.to_string();
+        self
+    }
+}
+
+impl Middleware for RunHeaders {
+    async fn on_request(&self, request: &mut Request) {
+        let Some(run) = crate::telemetry::RunContext::current() else {
+            return;
+        };
+        for (name, value) in [
+            (&self.session_header, run.session_id()),
+            (&self.run_header, run.run_id()),
+        ] {
+            match (
+                reqwest::header::HeaderName::try_from(name.as_str()),
+                reqwest::header::HeaderValue::try_from(value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    request.headers_mut().insert(name, value);
+                }
+                _ => tracing::warn!(target: "rig", "Invalid run header {name}: {value}"),
+            }
+        }
+    }
+}
+
+/// HTTP client of a provider, running its requests and responses through its middleware
+#[derive(Clone)]
+#[cfg_attr(not(feature = "providers"), allow(dead_code))]
+pub(crate) struct HttpClient {
+    client: reqwest::Client,
+    middleware: Vec<Arc<dyn MiddlewareDyn>>,
+}
+
+#[cfg_attr(not(feature = "providers"), allow(dead_code))]
+impl HttpClient {
+    pub(crate) fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            middleware: vec![],
+        }
+    }
+
+    pub(crate) fn with_middleware(mut self, middleware: Vec<Arc<dyn MiddlewareDyn>>) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    pub(crate) fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        RequestBuilder {
+            client: self.client.clone(),
+            inner: self.client.post(url),
+            middleware: self.middleware.clone(),
+        }
+    }
+
+    pub(crate) fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        RequestBuilder {
+            client: self.client.clone(),
+            inner: self.client.get(url),
+            middleware: self.middleware.clone(),
+        }
+    }
+}
+
+/// Builder of a request of a provider client, sent through the middleware of the client.
+pub struct RequestBuilder {
+    client: reqwest::Client,
+    inner: reqwest::RequestBuilder,
+    middleware: Vec<Arc<dyn MiddlewareDyn>>,
+}
+
+impl RequestBuilder {
+    /// Add a header to the request
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.inner = self.inner.header(name, value);
+        self
+    }
+
+    /// Set the JSON body of the request
+    pub fn json<T: Serialize + ?Sized>(mut self, json: &T) -> Self {
+        self.inner = self.inner.json(json);
+        self
+    }
+
+    /// Set the raw body of the request
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.inner = self.inner.body(body);
+        self
+    }
+
+    /// Set the multipart/form-data body of the request
+    pub fn multipart(mut self, form: reqwest::multipart::Form) -> Self {
+        self.inner = self.inner.multipart(form);
+        self
+    }
+
+    /// Run the request through the middleware, send it (unless a middleware answers it), and
+    /// run the response through the middleware in reverse order.
+    #[allow(clippy::manual_async_fn)] // `Send` on native targets only, see WasmCompatSend
+    pub fn send(self) -> impl Future<Output = reqwest::Result<Response>> + WasmCompatSend {
+        async move {
+            let mut request = self.inner.build()?;
+            for middleware in &self.middleware {
+                middleware.on_request(&mut request).await;
+            }
+
+            let mut answered = None;
+            for middleware in &self.middleware {
+                answered = middleware.respond(&request).await;
+                if answered.is_some() {
+                    break;
+                }
+            }
+            let mut response = match answered {
+                Some(response) => response,
+                None => {
+                    let sent = request.try_clone();
+                    let mut response = self.client.execute(request).await?;
+                    if let Some(sent) = &sent {
+                        for middleware in self.middleware.iter().rev() {
+                            response = middleware.on_exchange(sent, response).await?;
+                        }
+                    }
+                    response
+                }
+            };
+            for middleware in self.middleware.iter().rev() {
+                response = middleware.on_response(response).await;
+            }
+            Ok(response)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "providers"))]
+mod tests {
+    use std::sync::Mutex;
+
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{embeddings::EmbeddingModel, providers::openai};
+
+    /// Redacts a secret from the bodies of the requests
+    struct Redact(&'static str);
+
+    impl Middleware for Redact {
+        async fn on_request(&self, request: &mut Request) {
+            let Some(body) = request.body().and_then(|body| body.as_bytes()) else {
+                return;
+            };
+            let redacted = String::from_utf8_lossy(body).replace(self.0, "[REDACTED]");
+            *request.body_mut() = Some(redacted.into());
+        }
+    }
+
+    /// Logs the paths of the requests and the statuses of the responses
+    #[derive(Default)]
+    struct Audit(Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Audit {
+        async fn on_request(&self, request: &mut Request) {
+            request
+                .headers_mut()
+                .insert("x-audited", "true".parse().unwrap());
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("request {}", request.url().path()));
+        }
+
+        async fn on_response(&self, response: Response) -> Response {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("response {}", response.status()));
+            response
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/embeddings")
+                .header("x-audited", "true")
+                .json_body(json!({
+                    "model": openai::TEXT_EMBEDDING_3_SMALL,
+                    "input": ["My password is [REDACTED]"]
+                }));
+            then.status(200).json_body(json!({
+                "object": "list",
+                "data": [{"object": "embedding", "embedding": [0.5], "index": 0}],
+                "model": openai::TEXT_EMBEDDING_3_SMALL,
+                "usage": {"prompt_tokens": 6, "total_tokens": 6}
+            }));
+        });
+
+        let audit = Audit::default();
+        let log = audit.0.clone();
+        let client = openai::Client::builder("key")
+            .base_url(&server.base_url())
+            .with_middleware(Redact("hunter2"))
+            .with_middleware(audit)
+            .build();
+
+        let embedding = client
+            .embedding_model(openai::TEXT_EMBEDDING_3_SMALL)
+            .embed_text("My password is hunter2")
+            .await
+            .unwrap();
+        assert_eq!(embedding.vec, vec![0.5]);
+        mock.assert();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["request /embeddings", "response 200 OK"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_headers() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/embeddings")
+                .header("x-rig-session-id", "session")
+                .header("x-rig-run-id", "run");
+            then.status(200).json_body(json!({
+                "object": "list",
+                "data": [{"object": "embedding", "embedding": [0.5], "index": 0}],
+                "model": openai::TEXT_EMBEDDING_3_SMALL,
+                "usage": {"prompt_tokens": 1, "total_tokens": 1}
+            }));
+        });
+        let model = openai::Client::builder("key")
+            .base_url(&server.base_url())
+            .with_middleware(RunHeaders::new())
+            .build()
+            .embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+
+        crate::telemetry::RunContext::with_run_id("session", "run")
+            .scope(model.embed_text("Hi"))
+            .await
+            .unwrap();
+        mock.assert();
+
+        // Requests outside of a run have no run headers
+        assert!(model.embed_text("Hi").await.is_err());
+    }
+}
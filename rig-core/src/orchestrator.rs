@@ -0,0 +1,531 @@
+//! Multi-agent orchestration.
+//!
+//! An [Orchestrator] holds a set of named agents (or anything implementing [Prompt]), each with a
+//! description of what it is good at, and dispatches every incoming prompt to the best agent
+//! according to a [Router]:
+//! - [ModelRouter] asks a completion model to pick the agent;
+//! - [EmbeddingRouter] picks the agent whose description is the most similar to the prompt.
+//!
+//! Routers return their confidence in the agent they picked. If it is below the threshold of
+//! the orchestrator (or if no agent was picked), the prompt is dispatched to the fallback agent,
+//! if any.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, orchestrator::{ModelRouter, Orchestrator}, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let hub = Orchestrator::builder(ModelRouter::new(openai.completion_model("gpt-4o-mini")))
+//!     .agent("billing", "Answers questions about invoices and payments", billing_agent)
+//!     .agent("support", "Troubleshoots technical issues", support_agent)
+//!     .agent("general", "Handles any other request", general_agent)
+//!     .fallback("general")
+//!     .threshold(0.6)
+//!     .build()?;
+//!
+//! let dispatch = hub.dispatch("I was charged twice this month").await?;
+//! println!("{} answered: {}", dispatch.agent, dispatch.response);
+//! ```
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    completion::{CompletionError, CompletionModel, Message, Prompt, PromptError, ToolDefinition},
+    embeddings::{distance::VectorDistance, Embedding, EmbeddingError, EmbeddingModel},
+    message::AssistantContent,
+};
+
+/// Name of the function called by the model of a [ModelRouter] to pick an agent.
+pub const ROUTE_TOOL_NAME: &str = "route";
+
+#[derive(Debug, thiserror::Error)]
+pub enum OrchestratorError {
+    /// No agent was picked with enough confidence and there is no fallback agent
+    #[error("No agent found for the prompt")]
+    NoRoute,
+
+    #[error("Unknown agent: {0}")]
+    UnknownAgent(String),
+
+    #[error("Duplicate agent: {0}")]
+    DuplicateAgent(String),
+
+    #[error("PromptError: {0}")]
+    PromptError(#[from] PromptError),
+
+    #[error("CompletionError: {0}")]
+    CompletionError(#[from] CompletionError),
+
+    #[error("EmbeddingError: {0}")]
+    EmbeddingError(#[from] EmbeddingError),
+}
+
+/// Agent of an orchestrator, as seen by its router.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub name: String,
+    pub description: String,
+}
+
+/// Agent picked by a router, with the router's confidence in its choice (from 0 to 1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteChoice {
+    pub agent: String,
+    pub confidence: f64,
+}
+
+/// Strategy picking the agent a prompt is dispatched to.
+pub trait Router: Send + Sync {
+    /// Pick the best agent of `routes` for `prompt`, if any.
+    fn route(
+        &self,
+        prompt: &str,
+        routes: &[Route],
+    ) -> impl Future<Output = Result<Option<RouteChoice>, OrchestratorError>> + Send;
+}
+
+/// Router asking a completion model to pick the agent.
+pub struct ModelRouter<M: CompletionModel> {
+    model: M,
+}
+
+impl<M: CompletionModel> ModelRouter<M> {
+    pub fn new(model: M) -> Self {
+        Self { model }
+    }
+}
+
+#[derive(Deserialize)]
+struct RouteArgs {
+    agent: String,
+    confidence: f64,
+}
+
+impl<M: CompletionModel> Router for ModelRouter<M> {
+    async fn route(
+        &self,
+        prompt: &str,
+        routes: &[Route],
+    ) -> Result<Option<RouteChoice>, OrchestratorError> {
+        let agents = routes
+            .iter()
+            .map(|route| format!("- {}: {}", route.name, route.description))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let names = routes
+            .iter()
+            .map(|route| route.name.clone())
+            .collect::<Vec<_>>();
+
+        let response = self
+            .model
+            .completion_request(Message::user(prompt))
+            .preamble(format!(
+                "You dispatch user requests to the agent best suited to handle them. \
+                Do not answer the request: call the `{ROUTE_TOOL_NAME}` function with the name of the \
+                agent and your confidence that it is the right agent.\n\nAgents:\n{agents}"
+            ))
+            .tool(ToolDefinition {
+                name: ROUTE_TOOL_NAME.to_string(),
+                description: "Dispatch the request to an agent.".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "agent": {
+                            "type": "string",
+                            "enum": names,
+                            "description": "Name of the agent"
+                        },
+                        "confidence": {
+                            "type": "number",
+                            "minimum": 0,
+                            "maximum": 1,
+                            "description": "Confidence that the agent is the right one, from 0 to 1"
+                        }
+                    },
+                    "required": ["agent", "confidence"]
+                }),
+            })
+            .send()
+            .await?;
+
+        let args = match response.choice.first() {
+            AssistantContent::ToolCall(tool_call) => {
+                serde_json::from_value::<RouteArgs>(tool_call.function.arguments).ok()
+            }
+            AssistantContent::Text(text) => serde_json::from_str::<RouteArgs>(&text.text).ok(),
+        };
+
+        Ok(args
+            .filter(|args| names.contains(&args.agent))
+            .map(|args| RouteChoice {
+                agent: args.agent,
+                confidence: args.confidence.clamp(0.0, 1.0),
+            }))
+    }
+}
+
+/// Router picking the agent whose description is the most similar to the prompt, by cosine
+/// similarity of their embeddings. The embeddings of the descriptions are computed once.
+pub struct EmbeddingRouter<E: EmbeddingModel> {
+    model: E,
+    descriptions: Mutex<HashMap<String, Embedding>>,
+}
+
+impl<E: EmbeddingModel> EmbeddingRouter<E> {
+    pub fn new(model: E) -> Self {
+        Self {
+            model,
+            descriptions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<E: EmbeddingModel> Router for EmbeddingRouter<E> {
+    async fn route(
+        &self,
+        prompt: &str,
+        routes: &[Route],
+    ) -> Result<Option<RouteChoice>, OrchestratorError> {
+        let missing = {
+            let descriptions = self
+                .descriptions
+                .lock()
+                .expect("router embeddings lock poisoned");
+            routes
+                .iter()
+                .map(|route| route.description.clone())
+                .filter(|description| !descriptions.contains_key(description))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        if !missing.is_empty() {
+            let embeddings = self.model.embed_texts(missing.clone()).await?;
+            self.descriptions
+                .lock()
+                .expect("router embeddings lock poisoned")
+                .extend(missing.into_iter().zip(embeddings));
+        }
+
+        let prompt = self.model.embed_text(prompt).await?;
+        let descriptions = self
+            .descriptions
+            .lock()
+            .expect("router embeddings lock poisoned");
+
+        Ok(routes
+            .iter()
+            .filter_map(|route| {
+                descriptions
+                    .get(&route.description)
+                    .map(|description| RouteChoice {
+                        agent: route.name.clone(),
+                        confidence: prompt.cosine_similarity(description, false).clamp(0.0, 1.0),
+                    })
+            })
+            .max_by(|a, b| a.confidence.total_cmp(&b.confidence)))
+    }
+}
+
+/// Wrapper trait to dispatch prompts to agents of different types
+trait PromptDyn: Send + Sync {
+    fn prompt_dyn(
+        &self,
+        prompt: Message,
+    ) -> Pin<Box<dyn Future<Output = Result<String, PromptError>> + Send + '_>>;
+}
+
+impl<P: Prompt> PromptDyn for P {
+    fn prompt_dyn(
+        &self,
+        prompt: Message,
+    ) -> Pin<Box<dyn Future<Output = Result<String, PromptError>> + Send + '_>> {
+        Box::pin(<Self as Prompt>::prompt(self, prompt))
+    }
+}
+
+/// Response of the agent a prompt was dispatched to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dispatch {
+    /// Name of the agent
+    pub agent: String,
+    /// Confidence of the router in its choice, if the agent was picked by the router
+    /// (i.e.: it is not the fallback agent)
+    pub confidence: Option<f64>,
+    pub response: String,
+}
+
+/// Set of named agents, dispatching each prompt to the best agent for it.
+pub struct Orchestrator<R: Router> {
+    router: R,
+    routes: Vec<Route>,
+    agents: HashMap<String, Box<dyn PromptDyn>>,
+    fallback: Option<String>,
+    threshold: f64,
+}
+
+impl<R: Router> Orchestrator<R> {
+    pub fn builder(router: R) -> OrchestratorBuilder<R> {
+        OrchestratorBuilder::new(router)
+    }
+
+    /// Agents of the orchestrator, in the order they were added.
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Name of the agent `prompt` would be dispatched to, with the router's confidence
+    /// (`None` for the fallback agent).
+    pub async fn route(&self, prompt: &str) -> Result<(String, Option<f64>), OrchestratorError> {
+        let choice = self
+            .router
+            .route(prompt, &self.routes)
+            .await?
+            .filter(|choice| choice.confidence >= self.threshold);
+
+        match (choice, &self.fallback) {
+            (Some(choice), _) if self.agents.contains_key(&choice.agent) => {
+                Ok((choice.agent, Some(choice.confidence)))
+            }
+            (Some(choice), None) => Err(OrchestratorError::UnknownAgent(choice.agent)),
+            (_, Some(fallback)) => {
+                tracing::debug!(target: "rig", "Dispatching prompt to fallback agent {fallback}");
+                Ok((fallback.clone(), None))
+            }
+            (None, None) => Err(OrchestratorError::NoRoute),
+        }
+    }
+
+    /// Dispatch `prompt` to the best agent for it and return the agent's response.
+    pub async fn dispatch(
+        &self,
+        prompt: impl Into<Message> + Send,
+    ) -> Result<Dispatch, OrchestratorError> {
+        let prompt = prompt.into();
+        let (agent, confidence) = self.route(&prompt.rag_text().unwrap_or_default()).await?;
+
+        let response = self.agents[&agent].prompt_dyn(prompt).await?;
+        Ok(Dispatch {
+            agent,
+            confidence,
+            response,
+        })
+    }
+}
+
+impl<R: Router> Prompt for Orchestrator<R> {
+    async fn prompt(&self, prompt: impl Into<Message> + Send) -> Result<String, PromptError> {
+        match self.dispatch(prompt).await {
+            Ok(dispatch) => Ok(dispatch.response),
+            Err(OrchestratorError::PromptError(e)) => Err(e),
+            Err(OrchestratorError::CompletionError(e)) => Err(e.into()),
+            Err(e) => Err(CompletionError::RequestError(Box::new(e)).into()),
+        }
+    }
+}
+
+/// Builder for the [Orchestrator]
+pub struct OrchestratorBuilder<R: Router> {
+    router: R,
+    routes: Vec<Route>,
+    agents: Vec<(String, Box<dyn PromptDyn>)>,
+    fallback: Option<String>,
+    threshold: f64,
+}
+
+impl<R: Router> OrchestratorBuilder<R> {
+    pub fn new(router: R) -> Self {
+        Self {
+            router,
+            routes: vec![],
+            agents: vec![],
+            fallback: None,
+            threshold: 0.0,
+        }
+    }
+
+    /// Add an agent named `name`. The `description` is used by the router to pick the agent.
+    pub fn agent(mut self, name: &str, description: &str, agent: impl Prompt + 'static) -> Self {
+        self.routes.push(Route {
+            name: name.to_string(),
+            description: description.to_string(),
+        });
+        self.agents.push((name.to_string(), Box::new(agent)));
+        self
+    }
+
+    /// Dispatch the prompts for which the router picks no agent with enough confidence to the
+    /// agent named `name`.
+    pub fn fallback(mut self, name: &str) -> Self {
+        self.fallback = Some(name.to_string());
+        self
+    }
+
+    /// Set the minimum confidence of the router for its choice to be followed (defaults to 0).
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Build the orchestrator. Fails if two agents have the same name or if the fallback agent
+    /// is unknown.
+    pub fn build(self) -> Result<Orchestrator<R>, OrchestratorError> {
+        let mut agents = HashMap::new();
+        for (name, agent) in self.agents {
+            if agents.insert(name.clone(), agent).is_some() {
+                return Err(OrchestratorError::DuplicateAgent(name));
+            }
+        }
+        if let Some(fallback) = &self.fallback {
+            if !agents.contains_key(fallback) {
+                return Err(OrchestratorError::UnknownAgent(fallback.clone()));
+            }
+        }
+
+        Ok(Orchestrator {
+            router: self.router,
+            routes: self.routes,
+            agents,
+            fallback: self.fallback,
+            threshold: self.threshold,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Agent answering with its name
+    struct Named(&'static str);
+
+    impl Prompt for Named {
+        async fn prompt(&self, _prompt: impl Into<Message> + Send) -> Result<String, PromptError> {
+            Ok(self.0.to_string())
+        }
+    }
+
+    /// Router picking the agent whose name is in the prompt, with full confidence if the prompt
+    /// ends with `!`
+    struct KeywordRouter;
+
+    impl Router for KeywordRouter {
+        async fn route(
+            &self,
+            prompt: &str,
+            routes: &[Route],
+        ) -> Result<Option<RouteChoice>, OrchestratorError> {
+            Ok(routes
+                .iter()
+                .find(|route| prompt.contains(&route.name))
+                .map(|route| RouteChoice {
+                    agent: route.name.clone(),
+                    confidence: if prompt.ends_with('!') { 1.0 } else { 0.5 },
+                }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator() {
+        let orchestrator = Orchestrator::builder(KeywordRouter)
+            .agent("billing", "Invoices", Named("billing"))
+            .agent("support", "Technical issues", Named("support"))
+            .fallback("support")
+            .threshold(0.8)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            orchestrator.dispatch("billing!").await.unwrap(),
+            Dispatch {
+                agent: "billing".to_string(),
+                confidence: Some(1.0),
+                response: "billing".to_string(),
+            }
+        );
+
+        // Below the threshold or without a route, the fallback agent is used
+        assert_eq!(
+            orchestrator.route("billing?").await.unwrap(),
+            ("support".to_string(), None)
+        );
+        assert_eq!(orchestrator.prompt("Hello").await.unwrap(), "support");
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_without_fallback() {
+        let orchestrator = Orchestrator::builder(KeywordRouter)
+            .agent("billing", "Invoices", Named("billing"))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            orchestrator.dispatch("Hello").await,
+            Err(OrchestratorError::NoRoute)
+        ));
+
+        assert!(matches!(
+            Orchestrator::builder(KeywordRouter)
+                .agent("billing", "Invoices", Named("billing"))
+                .fallback("general")
+                .build(),
+            Err(OrchestratorError::UnknownAgent(name)) if name == "general"
+        ));
+    }
+
+    /// Embedding model embedding texts as the counts of `a` and `b` they contain
+    #[derive(Clone)]
+    struct CountModel;
+
+    impl EmbeddingModel for CountModel {
+        const MAX_DOCUMENTS: usize = 10;
+
+        fn ndims(&self) -> usize {
+            2
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|text| Embedding {
+                    vec: vec![
+                        text.matches('a').count() as f64,
+                        text.matches('b').count() as f64,
+                    ],
+                    document: text,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embedding_router() {
+        let routes = vec![
+            Route {
+                name: "a".to_string(),
+                description: "aaa".to_string(),
+            },
+            Route {
+                name: "b".to_string(),
+                description: "bbb".to_string(),
+            },
+        ];
+        let router = EmbeddingRouter::new(CountModel);
+
+        let choice = router.route("abbb", &routes).await.unwrap().unwrap();
+        assert_eq!(choice.agent, "b");
+        assert!(choice.confidence > 0.9);
+        assert_eq!(router.descriptions.lock().unwrap().len(), 2);
+    }
+}
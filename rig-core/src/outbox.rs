@@ -0,0 +1,302 @@
+//! Outbox of agent activity, for event-driven applications.
+//!
+//! An agent built with [AgentBuilder::outbox](crate::agent::AgentBuilder::outbox) publishes an
+//! [AgentEvent] to an [EventBus] when it receives a prompt, calls a tool, and produces a
+//! response (or fails to), so that other services (e.g.: audit logs, analytics, workflows
+//! triggered by tool calls) can react to the activity of the agent asynchronously.
+//!
+//! The module provides the following buses:
+//! - unbounded [channels](futures::channel::mpsc::UnboundedSender) of events, e.g.: to process
+//!   the events in a background task of the application
+//! - [WebhookEventBus]: posts the events as JSON to a URL (requires the `http` feature)
+//!
+//! Events published by the agent's [Prompt](crate::completion::Prompt) and
+//! [Chat](crate::completion::Chat) methods are delivered in order. A failure to publish an event
+//! is logged and doesn't fail the prompt.
+//!
+//! # Example
+//! ```rust
+//! use futures::{channel::mpsc, StreamExt};
+//! use rig::{completion::Prompt, outbox::AgentEventKind, providers::openai};
+//!
+//! let (sender, mut events) = mpsc::unbounded();
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .tool(calculator)
+//!     .max_turns(3)
+//!     .outbox(sender)
+//!     .build();
+//!
+//! tokio::spawn(async move {
+//!     while let Some(event) = events.next().await {
+//!         if let AgentEventKind::ToolCalled { name, .. } = event.kind {
+//!             println!("Turn {}: called {name}", event.turn);
+//!         }
+//!     }
+//! });
+//!
+//! agent.prompt("What is 3 + 5?").await?;
+//! ```
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use futures::channel::mpsc::UnboundedSender;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    completion::{Message, Usage},
+    telemetry::RunContext,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutboxError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[cfg(feature = "http")]
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error publishing the event to the bus (e.g.: closed channel, rejected message)
+    #[error("PublishError: {0}")]
+    PublishError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+/// Activity of an agent, published to its [EventBus].
+///
+/// Serialized as a flat JSON object with a `type` tag, e.g.:
+/// `{"turn": 3, "type": "tool_called", "call_id": "call_1", "name": "add", ...}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentEvent {
+    /// Sequence number of the prompt (or chat) of the agent the event is part of, shared by all
+    /// the events of the prompt
+    pub turn: u64,
+    /// Ids of the session and of the run of the prompt (see
+    /// [telemetry](crate::telemetry#session-and-run-ids))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(flatten)]
+    pub kind: AgentEventKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEventKind {
+    /// The agent received a prompt
+    PromptReceived { prompt: Message },
+    /// The agent called a tool requested by the model, which returned `output` or failed with
+    /// `error`
+    ToolCalled {
+        call_id: String,
+        name: String,
+        arguments: Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// The agent answered the prompt, using `usage` tokens over the completions of the turn
+    ResponseProduced { response: String, usage: Usage },
+    /// The agent failed to answer the prompt
+    PromptFailed { error: String },
+}
+
+/// Destination of the events of an agent.
+pub trait EventBus: Send + Sync {
+    /// Publish an event. Slow buses should buffer the events, as the agent waits for them to be
+    /// published before going on.
+    fn publish(&self, event: AgentEvent) -> impl Future<Output = Result<(), OutboxError>> + Send;
+}
+
+/// Wrapper trait to allow for dynamic dispatch of event buses
+pub trait EventBusDyn: Send + Sync {
+    fn publish(
+        &self,
+        event: AgentEvent,
+    ) -> Pin<Box<dyn Future<Output = Result<(), OutboxError>> + Send + '_>>;
+}
+
+impl<T: EventBus> EventBusDyn for T {
+    fn publish(
+        &self,
+        event: AgentEvent,
+    ) -> Pin<Box<dyn Future<Output = Result<(), OutboxError>> + Send + '_>> {
+        Box::pin(<Self as EventBus>::publish(self, event))
+    }
+}
+
+impl EventBus for UnboundedSender<AgentEvent> {
+    async fn publish(&self, event: AgentEvent) -> Result<(), OutboxError> {
+        self.unbounded_send(event)
+            .map_err(|e| OutboxError::PublishError(e.into_send_error().into()))
+    }
+}
+
+/// [EventBus] posting the events as JSON to a URL.
+#[cfg(feature = "http")]
+#[derive(Clone)]
+pub struct WebhookEventBus {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[cfg(feature = "http")]
+impl WebhookEventBus {
+    pub fn new(url: &str) -> Self {
+        Self::with_client(reqwest::Client::new(), url)
+    }
+
+    /// Create a bus posting the events with `client`, e.g.: to authenticate to the webhook with
+    /// default headers.
+    pub fn with_client(client: reqwest::Client, url: &str) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl EventBus for WebhookEventBus {
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn publish(&self, event: AgentEvent) -> Result<(), OutboxError> {
+        self.client
+            .post(&self.url)
+            .json(&event)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Outbox of an agent: its bus and the sequence of its turns
+pub(crate) struct Outbox {
+    bus: Box<dyn EventBusDyn>,
+    turns: AtomicU64,
+}
+
+impl Outbox {
+    pub(crate) fn new(bus: Box<dyn EventBusDyn>) -> Self {
+        Self {
+            bus,
+            turns: AtomicU64::new(0),
+        }
+    }
+
+    /// Start a new turn, returning its sequence number
+    pub(crate) fn next_turn(&self) -> u64 {
+        self.turns.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Publish an event of a turn, logging publishing failures
+    pub(crate) async fn emit(&self, turn: u64, kind: AgentEventKind) {
+        let run = RunContext::current();
+        let event = AgentEvent {
+            turn,
+            session_id: run.as_ref().map(|run| run.session_id().to_string()),
+            run_id: run.map(|run| run.run_id().to_string()),
+            kind,
+        };
+        if let Err(e) = self.bus.publish(event).await {
+            tracing::warn!(target: "rig", "Failed to publish agent event: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_agent_event_serialization() {
+        let event = AgentEvent {
+            turn: 3,
+            session_id: Some("session_1".into()),
+            run_id: None,
+            kind: AgentEventKind::ToolCalled {
+                call_id: "call_1".into(),
+                name: "add".into(),
+                arguments: json!({"x": 1, "y": 2}),
+                output: Some("3".into()),
+                error: None,
+            },
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "turn": 3,
+                "session_id": "session_1",
+                "type": "tool_called",
+                "call_id": "call_1",
+                "name": "add",
+                "arguments": {"x": 1, "y": 2},
+                "output": "3"
+            })
+        );
+        assert_eq!(serde_json::from_value::<AgentEvent>(value).unwrap(), event);
+    }
+
+    #[tokio::test]
+    async fn test_closed_channel() {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        drop(receiver);
+        let event = AgentEvent {
+            turn: 1,
+            session_id: None,
+            run_id: None,
+            kind: AgentEventKind::PromptFailed {
+                error: "Overloaded".into(),
+            },
+        };
+        assert!(matches!(
+            EventBus::publish(&sender, event).await,
+            Err(OutboxError::PublishError(_))
+        ));
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    async fn test_webhook_event_bus() {
+        use httpmock::{Method::POST, MockServer};
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/events")
+                .json_body(json!({"turn": 1, "type": "prompt_failed", "error": "Overloaded"}));
+            then.status(204);
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/down");
+            then.status(503);
+        });
+
+        let event = AgentEvent {
+            turn: 1,
+            session_id: None,
+            run_id: None,
+            kind: AgentEventKind::PromptFailed {
+                error: "Overloaded".into(),
+            },
+        };
+        let bus = WebhookEventBus::new(&server.url("/events"));
+        EventBus::publish(&bus, event.clone()).await.unwrap();
+        mock.assert();
+        assert!(matches!(
+            EventBus::publish(&WebhookEventBus::new(&server.url("/down")), event).await,
+            Err(OutboxError::HttpError(_))
+        ));
+    }
+}
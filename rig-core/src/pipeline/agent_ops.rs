@@ -1,4 +1,5 @@
 use crate::{
+    anonymization::{AnonymizationError, Anonymized, Anonymizer},
     completion::{self, CompletionModel},
     extractor::{ExtractionError, Extractor},
     vector_store,
@@ -149,6 +150,43 @@ where
     Extract::new(extractor)
 }
 
+pub struct Anonymize<In> {
+    anonymizer: Anonymizer,
+    _in: std::marker::PhantomData<In>,
+}
+
+impl<In> Anonymize<In> {
+    pub(crate) fn new(anonymizer: Anonymizer) -> Self {
+        Self {
+            anonymizer,
+            _in: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<In> Op for Anonymize<In>
+where
+    In: Into<String> + Send + Sync,
+{
+    type Input = In;
+    type Output = Result<Anonymized, AnonymizationError>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        self.anonymizer.anonymize(&input.into()).await
+    }
+}
+
+/// Create a new anonymize operation.
+///
+/// The op will replace the personal data of the input with tokens using the provided
+/// `anonymizer`, and return the anonymized text with the original texts of the tokens.
+pub fn anonymize<In>(anonymizer: Anonymizer) -> Anonymize<In>
+where
+    In: Into<String> + Send + Sync,
+{
+    Anonymize::new(anonymizer)
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -228,4 +266,20 @@ pub mod tests {
         let result = prompt.call("hello".to_string()).await.unwrap();
         assert_eq!(result, "Mock response: hello");
     }
+
+    #[cfg(feature = "regex")]
+    #[tokio::test]
+    async fn test_anonymize() {
+        let anonymize = anonymize::<String>(Anonymizer::pii());
+
+        let result = anonymize
+            .call("Mail jane@example.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(result.text, "Mail <EMAIL_1>");
+        assert_eq!(
+            result.tokens.original("<EMAIL_1>"),
+            Some("jane@example.com")
+        );
+    }
 }
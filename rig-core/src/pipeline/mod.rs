@@ -86,6 +86,19 @@
 //!             ▼              
 //!          Output           
 //! ```
+//!
+//! ## Fan-out
+//! The [fan_out](Op::fan_out) combinator runs an op on each element of a list, concurrently,
+//! and joins the results in a list, e.g.: to prompt an agent once per document chunk:
+//! ```rust
+//! use rig::pipeline::{self, agent_ops, Op, TryOp};
+//!
+//! let pipeline = pipeline::new()
+//!     .map(|text: String| chunk(&text))
+//!     .fan_out(agent_ops::prompt(summarizer), 4)
+//!     .map(|summaries| summaries.into_iter().collect::<Result<Vec<_>, _>>())
+//!     .map_ok(|summaries| summaries.join("\n"));
+//! ```
 
 pub mod agent_ops;
 pub mod op;
@@ -97,7 +110,7 @@ pub mod conditional;
 
 use std::future::Future;
 
-pub use op::{map, passthrough, then, Op};
+pub use op::{fan_out, map, passthrough, then, Op};
 pub use try_op::TryOp;
 
 use crate::{completion, extractor::Extractor, vector_store};
@@ -277,6 +290,28 @@ impl<E> PipelineBuilder<E> {
     {
         agent_ops::Extract::new(extractor)
     }
+
+    /// Add a fan-out operation to the current pipeline/op. The fan-out operation expects a
+    /// list of inputs of `op`, runs `op` on each of them with at most `n` concurrent calls and
+    /// returns the outputs, in the order of the inputs.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::pipeline::{self, Op};
+    ///
+    /// let pipeline = pipeline::new()
+    ///     .fan_out(pipeline::new().map(|x: i32| x * 2), 4);
+    ///
+    /// let result = pipeline.call(vec![1, 2, 3]).await;
+    /// assert_eq!(result, vec![2, 4, 6]);
+    /// ```
+    pub fn fan_out<T>(self, op: T, n: usize) -> op::FanOut<T>
+    where
+        T: Op,
+        Self: Sized,
+    {
+        op::FanOut::new(op, n)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -304,7 +339,6 @@ pub fn with_error<E>() -> PipelineBuilder<E> {
 mod tests {
     use super::*;
     use agent_ops::tests::{Foo, MockIndex, MockModel};
-    use parallel::parallel;
 
     #[tokio::test]
     async fn test_prompt_pipeline() {
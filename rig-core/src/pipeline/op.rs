@@ -167,6 +167,60 @@ pub trait Op: Send + Sync {
     {
         Sequential::new(self, Prompt::new(prompt))
     }
+
+    /// Chain an extract operation to the current chain. The extract operation expects the
+    /// current chain to output a string. The extract operation will use the given `extractor`
+    /// to extract information from the string in the form of the type `T` and return it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::pipeline::{self, Op};
+    ///
+    /// let extractor = openai_client.extractor::<Sentiment>("gpt-4").build();
+    ///
+    /// let chain = pipeline::new()
+    ///    .prompt(agent)
+    ///    .map(|response| response.unwrap_or_default())
+    ///    .extract(extractor);
+    ///
+    /// let result = chain.call("How do you feel about ice cream?".to_string()).await?;
+    /// ```
+    fn extract<M, T>(
+        self,
+        extractor: Extractor<M, T>,
+    ) -> Sequential<Self, Extract<M, Self::Output, T>>
+    where
+        M: completion::CompletionModel,
+        T: schemars::JsonSchema + for<'a> serde::Deserialize<'a> + Send + Sync,
+        Self::Output: Into<String>,
+        Self: Sized,
+    {
+        Sequential::new(self, Extract::new(extractor))
+    }
+
+    /// Chain a fan-out operation to the current chain. The fan-out operation expects the
+    /// current chain to output a list of inputs of `op`, runs `op` on each of them with at most
+    /// `n` concurrent calls and joins the outputs, in the order of the inputs.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::pipeline::{self, agent_ops, Op};
+    ///
+    /// let chain = pipeline::new()
+    ///     .map(|text: String| text.split("\n\n").map(str::to_string).collect::<Vec<_>>())
+    ///     // Summarize each paragraph, 4 at a time
+    ///     .fan_out(agent_ops::prompt(summarizer), 4)
+    ///     .map(|summaries| summaries.into_iter().collect::<Result<Vec<_>, _>>());
+    ///
+    /// let result = chain.call(document).await?;
+    /// ```
+    fn fan_out<T>(self, op: T, n: usize) -> Sequential<Self, FanOut<T>>
+    where
+        T: Op,
+        Self: Op<Output = Vec<T::Input>> + Sized,
+    {
+        Sequential::new(self, FanOut::new(op, n))
+    }
 }
 
 impl<T: Op> Op for &T {
@@ -208,9 +262,9 @@ where
     }
 }
 
-use crate::{completion, vector_store};
+use crate::{completion, extractor::Extractor, vector_store};
 
-use super::agent_ops::{Lookup, Prompt};
+use super::agent_ops::{Extract, Lookup, Prompt};
 
 // ================================================================
 // Core Op implementations
@@ -324,6 +378,38 @@ where
     Then::new(f)
 }
 
+pub struct FanOut<O> {
+    op: O,
+    n: usize,
+}
+
+impl<O> FanOut<O> {
+    pub(crate) fn new(op: O, n: usize) -> Self {
+        Self { op, n }
+    }
+}
+
+impl<O> Op for FanOut<O>
+where
+    O: Op,
+{
+    type Input = Vec<O::Input>;
+    type Output = Vec<O::Output>;
+
+    #[inline]
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        self.op.batch_call(self.n, input).await
+    }
+}
+
+/// Create a new fan-out operation.
+///
+/// The op will run `op` on each input of the list, with at most `n` concurrent calls, and
+/// return the outputs in the order of the inputs.
+pub fn fan_out<O: Op>(op: O, n: usize) -> FanOut<O> {
+    FanOut::new(op, n)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -350,6 +436,23 @@ mod tests {
         assert_eq!(result, 12);
     }
 
+    #[tokio::test]
+    async fn test_fan_out() {
+        let pipeline = map(|n: usize| (0..n).collect::<Vec<_>>())
+            .fan_out(
+                then(|x: usize| async move {
+                    // Later inputs finish first
+                    tokio::time::sleep(std::time::Duration::from_millis(10 * (3 - x) as u64)).await;
+                    x * 2
+                }),
+                3,
+            )
+            .map(|outputs| outputs.into_iter().sum::<usize>());
+
+        let result = fan_out(&pipeline, 2).call(vec![3, 1]).await;
+        assert_eq!(result, vec![6, 0]);
+    }
+
     // #[tokio::test]
     // async fn test_flatten() {
     //     let op = Parallel::new(
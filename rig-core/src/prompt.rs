@@ -0,0 +1,451 @@
+//! Prompt templates, to build preambles and prompts from variables instead of `format!` strings.
+//!
+//! A [PromptTemplate] is parsed from a template string containing:
+//! - variables, `{name}`, replaced by the value of the variable `name`
+//! - conditional sections, `{#if name}...{#else}...{/if}` (the `{#else}` branch being optional),
+//!   rendered depending on whether the variable `name` is set: variables that are missing, `null`,
+//!   `false`, or empty strings or arrays are unset
+//! - a few-shot examples block, `{#examples}...{/examples}`, whose content is rendered once for
+//!   each of the [Example]s of the template, with their `{input}` and `{output}`
+//!
+//! Literal braces are written `{{` and `}}`.
+//!
+//! Templates are rendered with the variables of any value serializing to a JSON object (e.g.: a
+//! struct deriving `Serialize`, a `HashMap`, or a `json!` object). Strings are inserted as is, and
+//! other values as JSON.
+//!
+//! Prompts whose template is known at compile time can instead be built with the `prompt!`
+//! macro (with the `derive` feature), whose builder only compiles once all the variables of
+//! the template are set.
+//!
+//! # Example
+//! ```rust
+//! use rig::prompt::PromptTemplate;
+//! use serde_json::json;
+//!
+//! let template = PromptTemplate::new(
+//!     "You are a support assistant for {company}.\n\
+//!     {#if tone}Answer in a {tone} tone.\n{/if}\
+//!     Classify the sentiment of the messages:\n\
+//!     {#examples}Message: {input}\nSentiment: {output}\n{/examples}",
+//! )?
+//! .example("I love it!", "positive")
+//! .example("It broke after a day.", "negative");
+//!
+//! let agent = openai_client.agent("gpt-4o")
+//!     .preamble_template(&template, json!({"company": "Acme", "tone": "friendly"}))?
+//!     .build();
+//! ```
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PromptTemplateError {
+    /// The template string is malformed (e.g.: unclosed brace or section)
+    #[error("Template syntax error: {0}")]
+    SyntaxError(String),
+
+    /// A variable of the template is missing from the variables it is rendered with
+    #[error("Missing template variable: {0}")]
+    MissingVariable(String),
+
+    /// The variables of the template do not serialize to a JSON object
+    #[error("Template variables must be a JSON object")]
+    InvalidVariables,
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Few-shot example of a [PromptTemplate], rendered in its `{#examples}` block.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Example {
+    pub input: String,
+    pub output: String,
+}
+
+impl Example {
+    pub fn new(input: &str, output: &str) -> Self {
+        Self {
+            input: input.to_string(),
+            output: output.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Variable(String),
+    If {
+        variable: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Examples(Vec<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    Variable(String),
+    If(String),
+    Else,
+    EndIf,
+    Examples,
+    EndExamples,
+}
+
+/// Template of a prompt, with variables, conditional sections and few-shot examples (see the
+/// [module documentation](self) for its syntax).
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    template: String,
+    nodes: Vec<Node>,
+    examples: Vec<Example>,
+}
+
+impl PromptTemplate {
+    /// Parse a template string.
+    pub fn new(template: &str) -> Result<Self, PromptTemplateError> {
+        let tokens = tokenize(template)?;
+        let mut tokens = tokens.into_iter();
+        let (nodes, end) = parse(&mut tokens)?;
+        if let Some(token) = end {
+            return Err(unexpected(&token));
+        }
+
+        Ok(Self {
+            template: template.to_string(),
+            nodes,
+            examples: vec![],
+        })
+    }
+
+    /// Add a few-shot example to the template.
+    pub fn example(mut self, input: &str, output: &str) -> Self {
+        self.examples.push(Example::new(input, output));
+        self
+    }
+
+    /// Add multiple few-shot examples to the template.
+    pub fn examples(mut self, examples: impl IntoIterator<Item = Example>) -> Self {
+        self.examples.extend(examples);
+        self
+    }
+
+    /// The template string
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Names of the variables used by the template (including in conditions), in order of
+    /// first appearance.
+    pub fn variables(&self) -> Vec<&str> {
+        fn add<'a>(variable: &'a str, in_examples: bool, variables: &mut Vec<&'a str>) {
+            // The fields of the examples are not variables of the template
+            let example_field = in_examples && is_example_field(variable);
+            if !example_field && !variables.contains(&variable) {
+                variables.push(variable);
+            }
+        }
+
+        fn collect<'a>(nodes: &'a [Node], in_examples: bool, variables: &mut Vec<&'a str>) {
+            for node in nodes {
+                match node {
+                    Node::Text(_) => {}
+                    Node::Variable(variable) => add(variable, in_examples, variables),
+                    Node::If {
+                        variable,
+                        then,
+                        otherwise,
+                    } => {
+                        add(variable, in_examples, variables);
+                        collect(then, in_examples, variables);
+                        collect(otherwise, in_examples, variables);
+                    }
+                    Node::Examples(nodes) => collect(nodes, true, variables),
+                }
+            }
+        }
+
+        let mut variables = vec![];
+        collect(&self.nodes, false, &mut variables);
+        variables
+    }
+
+    /// Render the template with `variables`, which must serialize to a JSON object.
+    pub fn render(&self, variables: impl Serialize) -> Result<String, PromptTemplateError> {
+        let variables = match serde_json::to_value(variables)? {
+            Value::Object(variables) => variables,
+            Value::Null => Map::new(),
+            _ => return Err(PromptTemplateError::InvalidVariables),
+        };
+
+        let mut output = String::new();
+        self.render_nodes(&self.nodes, &[&variables], &mut output)?;
+        Ok(output)
+    }
+
+    fn render_nodes(
+        &self,
+        nodes: &[Node],
+        scopes: &[&Map<String, Value>],
+        output: &mut String,
+    ) -> Result<(), PromptTemplateError> {
+        // Innermost scope first
+        let lookup = |name: &str| scopes.iter().rev().find_map(|scope| scope.get(name));
+
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Variable(name) => match lookup(name) {
+                    Some(Value::String(value)) => output.push_str(value),
+                    Some(Value::Null) | None => {
+                        return Err(PromptTemplateError::MissingVariable(name.clone()))
+                    }
+                    Some(value) => output.push_str(&value.to_string()),
+                },
+                Node::If {
+                    variable,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = if lookup(variable).is_some_and(is_set) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    self.render_nodes(branch, scopes, output)?;
+                }
+                Node::Examples(nodes) => {
+                    for example in &self.examples {
+                        let Value::Object(example) = serde_json::to_value(example)? else {
+                            unreachable!("Examples serialize to objects")
+                        };
+                        let scopes = [scopes, &[&example]].concat();
+                        self.render_nodes(nodes, &scopes, output)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for PromptTemplate {
+    type Err = PromptTemplateError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        Self::new(template)
+    }
+}
+
+fn is_example_field(variable: &str) -> bool {
+    variable == "input" || variable == "output"
+}
+
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => false,
+        Value::String(value) => !value.is_empty(),
+        Value::Array(values) => !values.is_empty(),
+        _ => true,
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn tokenize(template: &str) -> Result<Vec<Token>, PromptTemplateError> {
+    let mut tokens = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => {
+                return Err(PromptTemplateError::SyntaxError(
+                    "Unmatched `}` (use `}}` for a literal brace)".into(),
+                ))
+            }
+            '{' => {
+                let mut tag = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => tag.push(c),
+                        None => {
+                            return Err(PromptTemplateError::SyntaxError(format!(
+                                "Unclosed `{{{tag}`"
+                            )))
+                        }
+                    }
+                }
+
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(parse_tag(tag.trim())?);
+            }
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
+
+fn parse_tag(tag: &str) -> Result<Token, PromptTemplateError> {
+    let token = match tag {
+        "#else" => Token::Else,
+        "/if" => Token::EndIf,
+        "#examples" => Token::Examples,
+        "/examples" => Token::EndExamples,
+        _ => match tag.strip_prefix("#if ") {
+            Some(variable) if is_identifier(variable.trim()) => {
+                Token::If(variable.trim().to_string())
+            }
+            None if is_identifier(tag) => Token::Variable(tag.to_string()),
+            _ => {
+                return Err(PromptTemplateError::SyntaxError(format!(
+                    "Invalid tag `{{{tag}}}`"
+                )))
+            }
+        },
+    };
+    Ok(token)
+}
+
+/// Parse nodes until the end of the tokens or a closing (or `{#else}`) token, which is returned.
+fn parse(
+    tokens: &mut impl Iterator<Item = Token>,
+) -> Result<(Vec<Node>, Option<Token>), PromptTemplateError> {
+    let mut nodes = vec![];
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => nodes.push(Node::Text(text)),
+            Token::Variable(variable) => nodes.push(Node::Variable(variable)),
+            Token::If(variable) => {
+                let (then, end) = parse(tokens)?;
+                let otherwise = match end {
+                    Some(Token::EndIf) => vec![],
+                    Some(Token::Else) => match parse(tokens)? {
+                        (otherwise, Some(Token::EndIf)) => otherwise,
+                        (_, Some(token)) => return Err(unexpected(&token)),
+                        (_, None) => return Err(unclosed("{#if}")),
+                    },
+                    Some(token) => return Err(unexpected(&token)),
+                    None => return Err(unclosed("{#if}")),
+                };
+                nodes.push(Node::If {
+                    variable,
+                    then,
+                    otherwise,
+                });
+            }
+            Token::Examples => match parse(tokens)? {
+                (examples, Some(Token::EndExamples)) => nodes.push(Node::Examples(examples)),
+                (_, Some(token)) => return Err(unexpected(&token)),
+                (_, None) => return Err(unclosed("{#examples}")),
+            },
+            token @ (Token::Else | Token::EndIf | Token::EndExamples) => {
+                return Ok((nodes, Some(token)))
+            }
+        }
+    }
+    Ok((nodes, None))
+}
+
+fn unexpected(token: &Token) -> PromptTemplateError {
+    let tag = match token {
+        Token::Else => "{#else}",
+        Token::EndIf => "{/if}",
+        Token::EndExamples => "{/examples}",
+        _ => "tag",
+    };
+    PromptTemplateError::SyntaxError(format!("Unexpected `{tag}`"))
+}
+
+fn unclosed(section: &str) -> PromptTemplateError {
+    PromptTemplateError::SyntaxError(format!("Unclosed `{section}` section"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let template = PromptTemplate::new(
+            "You are {name}, {{an assistant}}.{#if tone} Be {tone}.{#else} Be neutral.{/if}\n\
+            {#examples}Q: {input}\nA: {output} ({name})\n{/examples}Turns: {turns}",
+        )
+        .unwrap()
+        .example("2 + 2?", "4")
+        .example("3 * 3?", "9");
+
+        assert_eq!(template.variables(), vec!["name", "tone", "turns"]);
+        assert_eq!(
+            template
+                .render(json!({"name": "Rig", "tone": "concise", "turns": 3}))
+                .unwrap(),
+            "You are Rig, {an assistant}. Be concise.\n\
+            Q: 2 + 2?\nA: 4 (Rig)\nQ: 3 * 3?\nA: 9 (Rig)\nTurns: 3"
+        );
+        assert_eq!(
+            template
+                .render(json!({"name": "Rig", "tone": "", "turns": 1}))
+                .unwrap(),
+            "You are Rig, {an assistant}. Be neutral.\n\
+            Q: 2 + 2?\nA: 4 (Rig)\nQ: 3 * 3?\nA: 9 (Rig)\nTurns: 1"
+        );
+
+        #[derive(Serialize)]
+        struct Variables {
+            name: &'static str,
+        }
+        assert!(matches!(
+            template.render(Variables { name: "Rig" }),
+            Err(PromptTemplateError::MissingVariable(variable)) if variable == "turns"
+        ));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        for template in [
+            "Hello {name",
+            "Hello name}",
+            "{#if name}Hello",
+            "{#if name}{#else}{#else}{/if}",
+            "Hello{/if}",
+            "{#examples}{/if}",
+            "{not a variable}",
+        ] {
+            assert!(
+                matches!(
+                    PromptTemplate::new(template),
+                    Err(PromptTemplateError::SyntaxError(_))
+                ),
+                "{template}"
+            );
+        }
+    }
+}
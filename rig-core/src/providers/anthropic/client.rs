@@ -1,6 +1,13 @@
 //! Anthropic client api implementation
 
-use crate::{agent::AgentBuilder, extractor::ExtractorBuilder};
+use std::sync::Arc;
+
+use crate::{
+    agent::AgentBuilder,
+    extractor::ExtractorBuilder,
+    http_client::HttpConfig,
+    middleware::{HttpClient, Middleware, MiddlewareDyn, RequestBuilder},
+};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -18,6 +25,8 @@ pub struct ClientBuilder<'a> {
     base_url: &'a str,
     anthropic_version: &'a str,
     anthropic_betas: Option<Vec<&'a str>>,
+    middleware: Vec<Arc<dyn MiddlewareDyn>>,
+    http: HttpConfig,
 }
 
 /// Create a new anthropic client using the builder
@@ -39,6 +48,8 @@ impl<'a> ClientBuilder<'a> {
             base_url: ANTHROPIC_API_BASE_URL,
             anthropic_version: ANTHROPIC_VERSION_LATEST,
             anthropic_betas: None,
+            middleware: vec![],
+            http: HttpConfig::default(),
         }
     }
 
@@ -62,20 +73,47 @@ impl<'a> ClientBuilder<'a> {
         self
     }
 
+    /// Add a [Middleware] to the client, run on its requests after the middleware added
+    /// before it.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Set the TLS settings of the client (e.g.: to trust a private certificate authority).
+    #[cfg(all(
+        not(target_arch = "wasm32"),
+        any(feature = "native-tls", feature = "rustls-tls")
+    ))]
+    pub fn tls(mut self, tls: crate::http_client::TlsConfig) -> Self {
+        self.http.tls = tls;
+        self
+    }
+
+    /// Set the connection pool settings of the client (e.g.: for high-QPS services).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool(mut self, pool: crate::http_client::PoolConfig) -> Self {
+        self.http.pool = pool;
+        self
+    }
+
     pub fn build(self) -> Client {
-        Client::new(
+        let mut client = Client::from_builder(
+            self.http.apply(reqwest::Client::builder()),
             self.api_key,
             self.base_url,
             self.anthropic_betas,
             self.anthropic_version,
-        )
+        );
+        client.http_client = client.http_client.with_middleware(self.middleware);
+        client
     }
 }
 
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
-    http_client: reqwest::Client,
+    http_client: HttpClient,
 }
 
 impl Client {
@@ -87,29 +125,47 @@ impl Client {
     ///   - This should really never happen.
     /// - If the reqwest client cannot be built (if the TLS backend cannot be initialized).
     pub fn new(api_key: &str, base_url: &str, betas: Option<Vec<&str>>, version: &str) -> Self {
+        Self::from_builder(
+            reqwest::Client::builder(),
+            api_key,
+            base_url,
+            betas,
+            version,
+        )
+    }
+
+    fn from_builder(
+        builder: reqwest::ClientBuilder,
+        api_key: &str,
+        base_url: &str,
+        betas: Option<Vec<&str>>,
+        version: &str,
+    ) -> Self {
         Self {
             base_url: base_url.to_string(),
-            http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert("x-api-key", api_key.parse().expect("API key should parse"));
-                    headers.insert(
-                        "anthropic-version",
-                        version.parse().expect("Anthropic version should parse"),
-                    );
-                    if let Some(betas) = betas {
+            http_client: HttpClient::new(
+                builder
+                    .default_headers({
+                        let mut headers = reqwest::header::HeaderMap::new();
+                        headers.insert("x-api-key", api_key.parse().expect("API key should parse"));
                         headers.insert(
-                            "anthropic-beta",
-                            betas
-                                .join(",")
-                                .parse()
-                                .expect("Anthropic betas should parse"),
+                            "anthropic-version",
+                            version.parse().expect("Anthropic version should parse"),
                         );
-                    }
-                    headers
-                })
-                .build()
-                .expect("Anthropic reqwest client should build"),
+                        if let Some(betas) = betas {
+                            headers.insert(
+                                "anthropic-beta",
+                                betas
+                                    .join(",")
+                                    .parse()
+                                    .expect("Anthropic betas should parse"),
+                            );
+                        }
+                        headers
+                    })
+                    .build()
+                    .expect("Anthropic reqwest client should build"),
+            ),
         }
     }
 
@@ -120,7 +176,7 @@ impl Client {
         ClientBuilder::new(&api_key).build()
     }
 
-    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+    pub fn post(&self, path: &str) -> RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
     }
@@ -37,7 +37,7 @@ pub const ANTHROPIC_VERSION_2023_01_01: &str = "2023-01-01";
 pub const ANTHROPIC_VERSION_2023_06_01: &str = "2023-06-01";
 pub const ANTHROPIC_VERSION_LATEST: &str = ANTHROPIC_VERSION_2023_06_01;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CompletionResponse {
     pub content: Vec<Content>,
     pub id: String,
@@ -118,6 +118,10 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
 
         Ok(completion::CompletionResponse {
             choice,
+            usage: Some(completion::Usage::new(
+                response.usage.input_tokens,
+                response.usage.output_tokens,
+            )),
             raw_response: response,
         })
     }
@@ -511,11 +515,6 @@ fn calculate_max_tokens(model: &str) -> Option<u64> {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct Metadata {
-    user_id: Option<String>,
-}
-
 #[derive(Default, Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ToolChoice {
@@ -530,6 +529,10 @@ pub enum ToolChoice {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -716,94 +719,85 @@ mod tests {
             })
         };
 
-        match assistant_message {
-            Message { role, content } => {
-                assert_eq!(role, Role::Assistant);
-                assert_eq!(
-                    content.first(),
-                    Content::Text {
-                        text: "\n\nHello there, how may I assist you today?".to_owned()
-                    }
-                );
+        let Message { role, content } = assistant_message;
+        assert_eq!(role, Role::Assistant);
+        assert_eq!(
+            content.first(),
+            Content::Text {
+                text: "\n\nHello there, how may I assist you today?".to_owned()
             }
-        }
-
-        match assistant_message2 {
-            Message { role, content } => {
-                assert_eq!(role, Role::Assistant);
-                assert_eq!(content.len(), 2);
+        );
 
-                let mut iter = content.into_iter();
+        let Message { role, content } = assistant_message2;
+        assert_eq!(role, Role::Assistant);
+        assert_eq!(content.len(), 2);
 
-                match iter.next().unwrap() {
-                    Content::Text { text } => {
-                        assert_eq!(text, "\n\nHello there, how may I assist you today?");
-                    }
-                    _ => panic!("Expected text content"),
-                }
+        let mut iter = content.into_iter();
 
-                match iter.next().unwrap() {
-                    Content::ToolUse { id, name, input } => {
-                        assert_eq!(id, "toolu_01A09q90qw90lq917835lq9");
-                        assert_eq!(name, "get_weather");
-                        assert_eq!(input, json!({"location": "San Francisco, CA"}));
-                    }
-                    _ => panic!("Expected tool use content"),
-                }
+        match iter.next().unwrap() {
+            Content::Text { text } => {
+                assert_eq!(text, "\n\nHello there, how may I assist you today?");
+            }
+            _ => panic!("Expected text content"),
+        }
 
-                assert_eq!(iter.next(), None);
+        match iter.next().unwrap() {
+            Content::ToolUse { id, name, input } => {
+                assert_eq!(id, "toolu_01A09q90qw90lq917835lq9");
+                assert_eq!(name, "get_weather");
+                assert_eq!(input, json!({"location": "San Francisco, CA"}));
             }
+            _ => panic!("Expected tool use content"),
         }
 
-        match user_message {
-            Message { role, content } => {
-                assert_eq!(role, Role::User);
-                assert_eq!(content.len(), 3);
+        assert_eq!(iter.next(), None);
 
-                let mut iter = content.into_iter();
+        let Message { role, content } = user_message;
+        assert_eq!(role, Role::User);
+        assert_eq!(content.len(), 3);
 
-                match iter.next().unwrap() {
-                    Content::Image { source } => {
-                        assert_eq!(
-                            source,
-                            ImageSource {
-                                data: "/9j/4AAQSkZJRg...".to_owned(),
-                                media_type: ImageFormat::JPEG,
-                                r#type: SourceType::BASE64,
-                            }
-                        );
-                    }
-                    _ => panic!("Expected image content"),
-                }
+        let mut iter = content.into_iter();
 
-                match iter.next().unwrap() {
-                    Content::Text { text } => {
-                        assert_eq!(text, "What is in this image?");
+        match iter.next().unwrap() {
+            Content::Image { source } => {
+                assert_eq!(
+                    source,
+                    ImageSource {
+                        data: "/9j/4AAQSkZJRg...".to_owned(),
+                        media_type: ImageFormat::JPEG,
+                        r#type: SourceType::BASE64,
                     }
-                    _ => panic!("Expected text content"),
-                }
+                );
+            }
+            _ => panic!("Expected image content"),
+        }
 
-                match iter.next().unwrap() {
-                    Content::ToolResult {
-                        tool_use_id,
-                        content,
-                        is_error,
-                    } => {
-                        assert_eq!(tool_use_id, "toolu_01A09q90qw90lq917835lq9");
-                        assert_eq!(
-                            content.first(),
-                            ToolResultContent::Text {
-                                text: "15 degrees".to_owned()
-                            }
-                        );
-                        assert_eq!(is_error, None);
-                    }
-                    _ => panic!("Expected tool result content"),
-                }
+        match iter.next().unwrap() {
+            Content::Text { text } => {
+                assert_eq!(text, "What is in this image?");
+            }
+            _ => panic!("Expected text content"),
+        }
 
-                assert_eq!(iter.next(), None);
+        match iter.next().unwrap() {
+            Content::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => {
+                assert_eq!(tool_use_id, "toolu_01A09q90qw90lq917835lq9");
+                assert_eq!(
+                    content.first(),
+                    ToolResultContent::Text {
+                        text: "15 degrees".to_owned()
+                    }
+                );
+                assert_eq!(is_error, None);
             }
+            _ => panic!("Expected tool result content"),
         }
+
+        assert_eq!(iter.next(), None);
     }
 
     #[test]
@@ -66,6 +66,7 @@ struct ToolCallState {
 }
 
 impl StreamingCompletionModel for CompletionModel {
+    #[cfg_attr(feature = "worker", worker::send)]
     async fn stream(
         &self,
         completion_request: CompletionRequest,
@@ -142,7 +143,7 @@ impl StreamingCompletionModel for CompletionModel {
             return Err(CompletionError::ProviderError(response.text().await?));
         }
 
-        Ok(Box::pin(stream! {
+        let stream: StreamingResult = Box::pin(stream! {
             let mut current_tool_call: Option<ToolCallState> = None;
             let mut stream = response.bytes_stream();
 
@@ -218,6 +219,12 @@ impl StreamingCompletionModel for CompletionModel {
                     }
                 }
             }
-        }))
+        });
+        Ok(stream)
+    }
+
+    /// Anthropic continues the assistant messages ending the conversation.
+    fn supports_prefill(&self) -> bool {
+        true
     }
 }
@@ -249,6 +249,18 @@ impl std::fmt::Display for Usage {
     }
 }
 
+impl From<&Usage> for completion::Usage {
+    fn from(usage: &Usage) -> Self {
+        let prompt_tokens = usage.prompt_tokens as u64;
+        let total_tokens = usage.total_tokens as u64;
+        completion::Usage {
+            input_tokens: prompt_tokens,
+            output_tokens: total_tokens.saturating_sub(prompt_tokens),
+            total_tokens,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EmbeddingModel {
     client: Client,
@@ -259,16 +271,35 @@ pub struct EmbeddingModel {
 impl embeddings::EmbeddingModel for EmbeddingModel {
     const MAX_DOCUMENTS: usize = 1024;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     fn ndims(&self) -> usize {
         self.ndims
     }
 
+    fn limits(&self) -> embeddings::EmbeddingLimits {
+        embeddings::EmbeddingLimits::new(Self::MAX_DOCUMENTS)
+            .max_input_tokens(8191)
+            .max_request_tokens(300_000)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn embed_texts(
         &self,
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
         let documents = documents.into_iter().collect::<Vec<_>>();
+        Ok(self.embed_texts_with_usage(documents).await?.0)
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn embed_texts_with_usage(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<(Vec<embeddings::Embedding>, Option<completion::Usage>), EmbeddingError> {
+        let documents = documents.into_iter().collect::<Vec<_>>();
 
         let response = self
             .client
@@ -293,15 +324,17 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                         ));
                     }
 
-                    Ok(response
+                    let usage = (&response.usage).into();
+                    let embeddings = response
                         .data
                         .into_iter()
-                        .zip(documents.into_iter())
+                        .zip(documents)
                         .map(|(embedding, document)| embeddings::Embedding {
                             document,
                             vec: embedding.embedding,
                         })
-                        .collect())
+                        .collect();
+                    Ok((embeddings, Some(usage)))
                 }
                 ApiResponse::Err(err) => Err(EmbeddingError::ProviderError(err.message)),
             }
@@ -370,6 +403,22 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = openai::CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        openai::structured_output(name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        Some(openai::json_mode())
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -382,13 +431,14 @@ impl completion::CompletionModel for CompletionModel {
         };
 
         // Convert prompt to user message
-        let prompt: Vec<openai::Message> = completion_request.prompt_with_context().try_into()?;
+        let prompt: Vec<openai::Message> =
+            openai::Message::try_from_message(completion_request.prompt_with_context())?;
 
         // Convert existing chat history
         let chat_history: Vec<openai::Message> = completion_request
             .chat_history
             .into_iter()
-            .map(|message| message.try_into())
+            .map(openai::Message::try_from_message)
             .collect::<Result<Vec<Vec<openai::Message>>, _>>()?
             .into_iter()
             .flatten()
@@ -486,6 +536,7 @@ mod azure_tests {
                 temperature: Some(0.0),
                 tools: vec![],
                 additional_params: None,
+                document_format: Default::default(),
             })
             .await
             .unwrap();
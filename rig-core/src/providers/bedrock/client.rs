@@ -0,0 +1,386 @@
+//! AWS Bedrock client api implementation
+
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent::AgentBuilder,
+    embeddings::EmbeddingsBuilder,
+    extractor::ExtractorBuilder,
+    http_client::HttpConfig,
+    middleware::{HttpClient, Middleware, MiddlewareDyn, RequestBuilder},
+    Embed,
+};
+
+use super::{
+    completion::CompletionModel,
+    embedding::EmbeddingModel,
+    sigv4::{uri_encode, Signer},
+};
+
+/// Name of the Bedrock service, as signed in the requests
+const SERVICE: &str = "bedrock";
+
+/// AWS credentials signing the requests of a client
+#[derive(Clone)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Session token of temporary credentials (e.g.: of an assumed IAM role)
+    pub session_token: Option<String>,
+}
+
+impl Credentials {
+    pub fn new(access_key_id: &str, secret_access_key: &str) -> Self {
+        Self {
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string(),
+            session_token: None,
+        }
+    }
+
+    /// Set the session token of temporary credentials.
+    pub fn session_token(mut self, session_token: &str) -> Self {
+        self.session_token = Some(session_token.to_string());
+        self
+    }
+
+    /// Create credentials from the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and (optional)
+    /// `AWS_SESSION_TOKEN` environment variables.
+    /// Panics if the environment variables are not set.
+    pub fn from_env() -> Self {
+        let access_key_id = std::env::var("AWS_ACCESS_KEY_ID").expect("AWS_ACCESS_KEY_ID not set");
+        let secret_access_key =
+            std::env::var("AWS_SECRET_ACCESS_KEY").expect("AWS_SECRET_ACCESS_KEY not set");
+        Self {
+            access_key_id,
+            secret_access_key,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        }
+    }
+}
+
+// Keep the secrets out of the logs
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"***")
+            .field("session_token", &self.session_token.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+/// Create a new Bedrock client using the builder, e.g.: to add [Middleware] to the client.
+///
+/// # Example
+/// ```
+/// use rig::providers::bedrock::{ClientBuilder, Credentials};
+///
+/// let bedrock = ClientBuilder::new(Credentials::from_env(), "eu-west-1")
+///     .with_middleware(audit_log)
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct ClientBuilder<'a> {
+    credentials: Credentials,
+    region: &'a str,
+    base_url: Option<&'a str>,
+    middleware: Vec<Arc<dyn MiddlewareDyn>>,
+    http: HttpConfig,
+}
+
+impl<'a> ClientBuilder<'a> {
+    pub fn new(credentials: Credentials, region: &'a str) -> Self {
+        Self {
+            credentials,
+            region,
+            base_url: None,
+            middleware: vec![],
+            http: HttpConfig::default(),
+        }
+    }
+
+    /// Set the base URL of the Bedrock runtime API (defaults to the endpoint of the region),
+    /// e.g.: for a VPC endpoint.
+    pub fn base_url(mut self, base_url: &'a str) -> Self {
+        self.base_url = Some(base_url);
+        self
+    }
+
+    /// Add a middleware to the client, run on its requests after the middleware added before it.
+    /// The requests are signed before running through the middleware.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Set the TLS settings of the client (e.g.: to trust a private certificate authority).
+    #[cfg(all(
+        not(target_arch = "wasm32"),
+        any(feature = "native-tls", feature = "rustls-tls")
+    ))]
+    pub fn tls(mut self, tls: crate::http_client::TlsConfig) -> Self {
+        self.http.tls = tls;
+        self
+    }
+
+    /// Set the connection pool settings of the client (e.g.: for high-QPS services).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool(mut self, pool: crate::http_client::PoolConfig) -> Self {
+        self.http.pool = pool;
+        self
+    }
+
+    pub fn build(self) -> Client {
+        Client {
+            base_url: match self.base_url {
+                Some(base_url) => base_url.trim_end_matches('/').to_string(),
+                None => format!("https://bedrock-runtime.{}.amazonaws.com", self.region),
+            },
+            region: self.region.to_string(),
+            credentials: Arc::new(self.credentials),
+            http_client: HttpClient::new(
+                self.http
+                    .apply(reqwest::Client::builder())
+                    .build()
+                    .expect("Bedrock reqwest client should build"),
+            )
+            .with_middleware(self.middleware),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Client {
+    base_url: String,
+    region: String,
+    credentials: Arc<Credentials>,
+    http_client: HttpClient,
+}
+
+impl Client {
+    /// Create a new Bedrock client of the given region.
+    pub fn new(credentials: Credentials, region: &str) -> Self {
+        ClientBuilder::new(credentials, region).build()
+    }
+
+    /// Create a new Bedrock client builder of the given region.
+    pub fn builder(credentials: Credentials, region: &str) -> ClientBuilder<'_> {
+        ClientBuilder::new(credentials, region)
+    }
+
+    /// Create a new Bedrock client from the credentials of the environment (see
+    /// [Credentials::from_env]) and the `AWS_REGION` (or `AWS_DEFAULT_REGION`) environment
+    /// variable.
+    /// Panics if the environment variables are not set.
+    pub fn from_env() -> Self {
+        let region = std::env::var("AWS_REGION")
+            .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+            .expect("AWS_REGION not set");
+        Self::new(Credentials::from_env(), &region)
+    }
+
+    /// Signed request posting `body` to the `action` endpoint of `model`
+    pub(crate) fn post_model(
+        &self,
+        model: &str,
+        action: &str,
+        body: &serde_json::Value,
+    ) -> RequestBuilder {
+        let url = format!("{}/model/{}/{action}", self.base_url, uri_encode(model));
+        let body = serde_json::to_vec(body).expect("JSON values should serialize");
+
+        let mut request = self
+            .http_client
+            .post(&url)
+            .header("content-type", "application/json");
+        match reqwest::Url::parse(&url) {
+            Ok(parsed) => {
+                let signer = Signer {
+                    credentials: &self.credentials,
+                    region: &self.region,
+                    service: SERVICE,
+                };
+                let headers = signer.sign(
+                    "POST",
+                    &parsed,
+                    &[("content-type", "application/json")],
+                    &body,
+                    chrono::Utc::now(),
+                );
+                for (name, value) in headers {
+                    request = request.header(name, &value);
+                }
+            }
+            // Sent unsigned to report the invalid URL as the error of the request
+            Err(e) => tracing::warn!(target: "rig", "Invalid Bedrock URL {url}: {e}"),
+        }
+        request.body(body)
+    }
+
+    /// Create a completion model with the given model id (or inference profile), using the
+    /// Converse API.
+    ///
+    /// # Example
+    /// ```
+    /// use rig::providers::bedrock::{self, Client};
+    ///
+    /// let bedrock = Client::from_env();
+    ///
+    /// let claude = bedrock.completion_model(bedrock::CLAUDE_3_5_SONNET);
+    /// ```
+    pub fn completion_model(&self, model: &str) -> CompletionModel {
+        CompletionModel::new(self.clone(), model)
+    }
+
+    /// Create an embedding model with the given model id (e.g.: a Titan embedding model).
+    /// Note: a default embedding dimension of 0 is used if the model is not known, use
+    /// `embedding_model_with_ndims` for other models.
+    pub fn embedding_model(&self, model: &str) -> EmbeddingModel {
+        EmbeddingModel::new(self.clone(), model, None)
+    }
+
+    /// Create an embedding model with the given model id and number of dimensions, e.g.: 256
+    /// or 512 for [TITAN_EMBED_TEXT_V2](super::TITAN_EMBED_TEXT_V2).
+    pub fn embedding_model_with_ndims(&self, model: &str, ndims: usize) -> EmbeddingModel {
+        EmbeddingModel::new(self.clone(), model, Some(ndims))
+    }
+
+    /// Create an embedding builder with the given embedding model.
+    pub fn embeddings<D: Embed>(&self, model: &str) -> EmbeddingsBuilder<EmbeddingModel, D> {
+        EmbeddingsBuilder::new(self.embedding_model(model))
+    }
+
+    /// Create an agent builder with the given completion model.
+    ///
+    /// # Example
+    /// ```
+    /// use rig::providers::bedrock::{self, Client};
+    ///
+    /// let bedrock = Client::from_env();
+    ///
+    /// let agent = bedrock.agent(bedrock::CLAUDE_3_5_SONNET)
+    ///    .preamble("You are comedian AI with a mission to make people laugh.")
+    ///    .temperature(0.0)
+    ///    .build();
+    /// ```
+    pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
+        AgentBuilder::new(self.completion_model(model))
+    }
+
+    /// Create an extractor builder with the given completion model.
+    pub fn extractor<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync>(
+        &self,
+        model: &str,
+    ) -> ExtractorBuilder<T, CompletionModel> {
+        ExtractorBuilder::new(self.completion_model(model))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApiErrorResponse {
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_base_url() {
+        let client = Client::new(Credentials::new("AKID", "secret"), "eu-west-1");
+        assert_eq!(
+            client.base_url,
+            "https://bedrock-runtime.eu-west-1.amazonaws.com"
+        );
+
+        let client = ClientBuilder::new(Credentials::new("AKID", "secret"), "eu-west-1")
+            .base_url("https://vpce-1234.bedrock-runtime.eu-west-1.vpce.amazonaws.com/")
+            .build();
+        assert_eq!(
+            client.base_url,
+            "https://vpce-1234.bedrock-runtime.eu-west-1.vpce.amazonaws.com"
+        );
+    }
+
+    #[test]
+    fn test_credentials_debug() {
+        let credentials = Credentials::new("AKID", "secret").session_token("token");
+        assert_eq!(
+            format!("{credentials:?}"),
+            r#"Credentials { access_key_id: "AKID", secret_access_key: "***", session_token: Some("***") }"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_model() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/model/amazon.titan-embed-text-v2%3A0/invoke")
+                .header("content-type", "application/json")
+                .header("x-amz-security-token", "token")
+                .header_exists("x-amz-date")
+                .matches(|request| {
+                    request.headers.iter().flatten().any(|(name, value)| {
+                        name == "authorization"
+                            && value.starts_with("AWS4-HMAC-SHA256 Credential=AKID/")
+                            && value.contains("/us-west-2/bedrock/aws4_request")
+                            && value.contains(
+                                "SignedHeaders=content-type;host;x-amz-date;x-amz-security-token,",
+                            )
+                    })
+                })
+                .json_body(json!({"inputText": "Hello"}));
+            then.status(200).json_body(json!({"embedding": [0.1, 0.2]}));
+        });
+        let error = server.mock(|when, then| {
+            when.method(POST).path("/model/unknown/invoke");
+            then.status(400)
+                .json_body(json!({"message": "The provided model identifier is invalid."}));
+        });
+
+        let base_url = server.base_url();
+        let client = ClientBuilder::new(
+            Credentials::new("AKID", "secret").session_token("token"),
+            "us-west-2",
+        )
+        .base_url(&base_url)
+        .build();
+
+        let response = client
+            .post_model(
+                "amazon.titan-embed-text-v2:0",
+                "invoke",
+                &json!({"inputText": "Hello"}),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(
+            response.json::<serde_json::Value>().await.unwrap(),
+            json!({"embedding": [0.1, 0.2]})
+        );
+        mock.assert();
+
+        let response = client
+            .post_model("unknown", "invoke", &json!({}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        let response = response.json::<ApiErrorResponse>().await.unwrap();
+        assert_eq!(
+            response.message,
+            "The provided model identifier is invalid."
+        );
+        error.assert();
+    }
+}
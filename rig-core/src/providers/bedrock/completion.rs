@@ -0,0 +1,579 @@
+//! AWS Bedrock completion api implementation, using the Converse API
+//! From [Bedrock Converse API Reference](https://docs.aws.amazon.com/bedrock/latest/APIReference/API_runtime_Converse.html)
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    completion::{self, CompletionError},
+    json_utils,
+    message::{self, MessageError},
+    OneOrMany,
+};
+
+use super::client::{ApiErrorResponse, Client};
+
+// ================================================================
+// Bedrock Completion API
+// ================================================================
+/// `anthropic.claude-3-5-sonnet-20240620-v1:0` completion model
+pub const CLAUDE_3_5_SONNET: &str = "anthropic.claude-3-5-sonnet-20240620-v1:0";
+/// `anthropic.claude-3-5-haiku-20241022-v1:0` completion model
+pub const CLAUDE_3_5_HAIKU: &str = "anthropic.claude-3-5-haiku-20241022-v1:0";
+/// `anthropic.claude-3-haiku-20240307-v1:0` completion model
+pub const CLAUDE_3_HAIKU: &str = "anthropic.claude-3-haiku-20240307-v1:0";
+/// `meta.llama3-1-70b-instruct-v1:0` completion model
+pub const LLAMA_3_1_70B_INSTRUCT: &str = "meta.llama3-1-70b-instruct-v1:0";
+/// `meta.llama3-1-8b-instruct-v1:0` completion model
+pub const LLAMA_3_1_8B_INSTRUCT: &str = "meta.llama3-1-8b-instruct-v1:0";
+/// `amazon.titan-text-premier-v1:0` completion model
+pub const TITAN_TEXT_PREMIER: &str = "amazon.titan-text-premier-v1:0";
+/// `amazon.nova-pro-v1:0` completion model
+pub const NOVA_PRO: &str = "amazon.nova-pro-v1:0";
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConverseResponse {
+    pub output: ConverseOutput,
+    pub stop_reason: String,
+    pub usage: TokenUsage,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConverseOutput {
+    pub message: Message,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl std::fmt::Display for TokenUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Input tokens: {}\nOutput tokens: {}",
+            self.input_tokens, self.output_tokens
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Message {
+    pub role: Role,
+    pub content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentBlock {
+    Text(String),
+    Image(ImageBlock),
+    Document(DocumentBlock),
+    ToolUse(ToolUseBlock),
+    ToolResult(ToolResultBlock),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ImageBlock {
+    /// `png`, `jpeg`, `gif` or `webp`
+    pub format: String,
+    pub source: Source,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct DocumentBlock {
+    /// `pdf`, `csv`, `html`, `txt` or `md` (among others)
+    pub format: String,
+    pub name: String,
+    pub source: Source,
+}
+
+/// Base64-encoded content of an image or document
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Source {
+    pub bytes: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolUseBlock {
+    pub tool_use_id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolResultBlock {
+    pub tool_use_id: String,
+    pub content: Vec<ToolResultContentBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolResultContentBlock {
+    Text(String),
+    Json(serde_json::Value),
+    Image(ImageBlock),
+}
+
+impl TryFrom<message::Image> for ImageBlock {
+    type Error = MessageError;
+
+    fn try_from(image: message::Image) -> Result<Self, Self::Error> {
+        if image.format == Some(message::ContentFormat::String) {
+            return Err(MessageError::ConversionError(
+                "Image urls are not supported in Bedrock".to_owned(),
+            ));
+        }
+        let format = match image.media_type {
+            Some(message::ImageMediaType::PNG) => "png",
+            Some(message::ImageMediaType::JPEG) => "jpeg",
+            Some(message::ImageMediaType::GIF) => "gif",
+            Some(message::ImageMediaType::WEBP) => "webp",
+            media_type => {
+                return Err(MessageError::ConversionError(format!(
+                    "Unsupported image media type: {media_type:?}"
+                )))
+            }
+        };
+        Ok(ImageBlock {
+            format: format.to_string(),
+            source: Source { bytes: image.data },
+        })
+    }
+}
+
+impl From<ImageBlock> for message::Image {
+    fn from(image: ImageBlock) -> Self {
+        message::Image {
+            data: image.source.bytes,
+            format: Some(message::ContentFormat::Base64),
+            media_type: match image.format.as_str() {
+                "png" => Some(message::ImageMediaType::PNG),
+                "jpeg" => Some(message::ImageMediaType::JPEG),
+                "gif" => Some(message::ImageMediaType::GIF),
+                "webp" => Some(message::ImageMediaType::WEBP),
+                _ => None,
+            },
+            detail: None,
+        }
+    }
+}
+
+impl TryFrom<message::Message> for Message {
+    type Error = MessageError;
+
+    fn try_from(message: message::Message) -> Result<Self, Self::Error> {
+        Ok(match message {
+            message::Message::User { content } => Message {
+                role: Role::User,
+                content: content
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, content)| match content {
+                        message::UserContent::Text(message::Text { text }) => {
+                            Ok(ContentBlock::Text(text))
+                        }
+                        message::UserContent::Image(image) => {
+                            Ok(ContentBlock::Image(image.try_into()?))
+                        }
+                        message::UserContent::Document(message::Document {
+                            data,
+                            format,
+                            media_type,
+                        }) => {
+                            if format == Some(message::ContentFormat::String) {
+                                return Err(MessageError::ConversionError(
+                                    "Document urls are not supported in Bedrock".to_owned(),
+                                ));
+                            }
+                            let format = match media_type {
+                                None | Some(message::DocumentMediaType::PDF) => "pdf",
+                                Some(message::DocumentMediaType::TXT) => "txt",
+                                Some(message::DocumentMediaType::HTML) => "html",
+                                Some(message::DocumentMediaType::MARKDOWN) => "md",
+                                Some(message::DocumentMediaType::CSV) => "csv",
+                                Some(media_type) => {
+                                    return Err(MessageError::ConversionError(format!(
+                                        "Unsupported document media type: {media_type:?}"
+                                    )))
+                                }
+                            };
+                            Ok(ContentBlock::Document(DocumentBlock {
+                                format: format.to_string(),
+                                // The names of the documents of a request must be unique
+                                name: format!("document-{i}"),
+                                source: Source { bytes: data },
+                            }))
+                        }
+                        message::UserContent::ToolResult(message::ToolResult { id, content }) => {
+                            Ok(ContentBlock::ToolResult(ToolResultBlock {
+                                tool_use_id: id,
+                                content: content
+                                    .into_iter()
+                                    .map(|content| match content {
+                                        message::ToolResultContent::Text(message::Text {
+                                            text,
+                                        }) => Ok(ToolResultContentBlock::Text(text)),
+                                        message::ToolResultContent::Image(image) => {
+                                            Ok(ToolResultContentBlock::Image(image.try_into()?))
+                                        }
+                                    })
+                                    .collect::<Result<Vec<_>, _>>()?,
+                                status: None,
+                            }))
+                        }
+                        message::UserContent::Audio(_) => Err(MessageError::ConversionError(
+                            "Audio is not supported in Bedrock".to_owned(),
+                        )),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            },
+            message::Message::Assistant { content } => Message {
+                role: Role::Assistant,
+                content: content
+                    .into_iter()
+                    .map(|content| match content {
+                        message::AssistantContent::Text(message::Text { text }) => {
+                            ContentBlock::Text(text)
+                        }
+                        message::AssistantContent::ToolCall(message::ToolCall { id, function }) => {
+                            ContentBlock::ToolUse(ToolUseBlock {
+                                tool_use_id: id,
+                                name: function.name,
+                                input: function.arguments,
+                            })
+                        }
+                    })
+                    .collect(),
+            },
+        })
+    }
+}
+
+impl TryFrom<ContentBlock> for message::AssistantContent {
+    type Error = MessageError;
+
+    fn try_from(content: ContentBlock) -> Result<Self, Self::Error> {
+        Ok(match content {
+            ContentBlock::Text(text) => message::AssistantContent::text(text),
+            ContentBlock::ToolUse(ToolUseBlock {
+                tool_use_id,
+                name,
+                input,
+            }) => message::AssistantContent::tool_call(tool_use_id, name, input),
+            _ => {
+                return Err(MessageError::ConversionError(format!(
+                    "Unsupported content type for Assistant role: {content:?}"
+                )))
+            }
+        })
+    }
+}
+
+impl TryFrom<ConverseResponse> for completion::CompletionResponse<ConverseResponse> {
+    type Error = CompletionError;
+
+    fn try_from(response: ConverseResponse) -> Result<Self, Self::Error> {
+        let content = response
+            .output
+            .message
+            .content
+            .iter()
+            .cloned()
+            .map(|content| {
+                content
+                    .try_into()
+                    .map_err(|e: MessageError| CompletionError::ResponseError(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let choice = OneOrMany::many(content).map_err(|_| {
+            CompletionError::ResponseError(
+                "Response contained no message or tool call (empty)".to_owned(),
+            )
+        })?;
+
+        Ok(completion::CompletionResponse {
+            choice,
+            usage: Some(completion::Usage::new(
+                response.usage.input_tokens,
+                response.usage.output_tokens,
+            )),
+            raw_response: response,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct CompletionModel {
+    pub(crate) client: Client,
+    pub model: String,
+}
+
+impl CompletionModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+}
+
+impl completion::CompletionModel for CompletionModel {
+    type Response = ConverseResponse;
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    /// Send the request to the Converse API. The `additional_params` of the request are merged
+    /// into the body of the request, e.g.: `{"additionalModelRequestFields": {"top_k": 10}}`
+    /// for the parameters specific to the model.
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn completion(
+        &self,
+        completion_request: completion::CompletionRequest,
+    ) -> Result<completion::CompletionResponse<ConverseResponse>, CompletionError> {
+        let prompt_message: Message = completion_request
+            .prompt_with_context()
+            .try_into()
+            .map_err(|e: MessageError| CompletionError::RequestError(e.into()))?;
+
+        let mut messages = completion_request
+            .chat_history
+            .into_iter()
+            .map(|message| {
+                message
+                    .try_into()
+                    .map_err(|e: MessageError| CompletionError::RequestError(e.into()))
+            })
+            .collect::<Result<Vec<Message>, _>>()?;
+        messages.push(prompt_message);
+
+        let mut request = json!({ "messages": messages });
+
+        if let Some(preamble) = completion_request.preamble.filter(|p| !p.is_empty()) {
+            json_utils::merge_inplace(&mut request, json!({ "system": [{ "text": preamble }] }));
+        }
+
+        let mut inference_config = json!({});
+        if let Some(max_tokens) = completion_request.max_tokens {
+            json_utils::merge_inplace(&mut inference_config, json!({ "maxTokens": max_tokens }));
+        }
+        if let Some(temperature) = completion_request.temperature {
+            json_utils::merge_inplace(&mut inference_config, json!({ "temperature": temperature }));
+        }
+        if inference_config != json!({}) {
+            json_utils::merge_inplace(&mut request, json!({ "inferenceConfig": inference_config }));
+        }
+
+        if !completion_request.tools.is_empty() {
+            json_utils::merge_inplace(
+                &mut request,
+                json!({
+                    "toolConfig": {
+                        "tools": completion_request
+                            .tools
+                            .into_iter()
+                            .map(|tool| json!({
+                                "toolSpec": {
+                                    "name": tool.name,
+                                    "description": tool.description,
+                                    "inputSchema": { "json": tool.parameters },
+                                }
+                            }))
+                            .collect::<Vec<_>>(),
+                    }
+                }),
+            );
+        }
+
+        if let Some(ref params) = completion_request.additional_params {
+            json_utils::merge_inplace(&mut request, params.clone())
+        }
+
+        tracing::debug!("Bedrock completion request: {request}");
+
+        let response = self
+            .client
+            .post_model(&self.model, "converse", &request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let response = response.json::<ConverseResponse>().await?;
+            tracing::info!(target: "rig",
+                "Bedrock completion token usage: {}",
+                response.usage
+            );
+            response.try_into()
+        } else {
+            let text = response.text().await?;
+            Err(CompletionError::ProviderError(
+                match serde_json::from_str::<ApiErrorResponse>(&text) {
+                    Ok(error) => error.message,
+                    Err(_) => text,
+                },
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+
+    use super::*;
+    use crate::{
+        completion::{CompletionModel as _, Prompt, ToolDefinition},
+        providers::bedrock::{ClientBuilder, Credentials},
+    };
+
+    #[test]
+    fn test_message_conversion() {
+        let message = message::Message::User {
+            content: OneOrMany::many(vec![
+                message::UserContent::text("What is in this image?"),
+                message::UserContent::image(
+                    "aGVsbG8=",
+                    Some(message::ContentFormat::Base64),
+                    Some(message::ImageMediaType::PNG),
+                    None,
+                ),
+                message::UserContent::tool_result(
+                    "tooluse_1",
+                    OneOrMany::one(message::ToolResultContent::text("15 degrees")),
+                ),
+            ])
+            .unwrap(),
+        };
+        let converted: Message = message.try_into().unwrap();
+        assert_eq!(
+            serde_json::to_value(&converted).unwrap(),
+            json!({
+                "role": "user",
+                "content": [
+                    {"text": "What is in this image?"},
+                    {"image": {"format": "png", "source": {"bytes": "aGVsbG8="}}},
+                    {"toolResult": {"toolUseId": "tooluse_1", "content": [{"text": "15 degrees"}]}}
+                ]
+            })
+        );
+
+        let message = message::Message::Assistant {
+            content: OneOrMany::one(message::AssistantContent::tool_call(
+                "tooluse_1",
+                "get_weather",
+                json!({"city": "Paris"}),
+            )),
+        };
+        let converted: Message = message.try_into().unwrap();
+        assert_eq!(
+            serde_json::to_value(&converted).unwrap(),
+            json!({
+                "role": "assistant",
+                "content": [{"toolUse": {
+                    "toolUseId": "tooluse_1",
+                    "name": "get_weather",
+                    "input": {"city": "Paris"}
+                }}]
+            })
+        );
+
+        let audio = message::Message::User {
+            content: OneOrMany::one(message::UserContent::audio(
+                "aGVsbG8=",
+                None,
+                Some(message::AudioMediaType::WAV),
+            )),
+        };
+        assert!(Message::try_from(audio).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_converse() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/model/anthropic.claude-3-haiku-20240307-v1%3A0/converse")
+                .header_exists("x-amz-date")
+                .matches(|request| {
+                    request.headers.iter().flatten().any(|(name, value)| {
+                        name == "authorization"
+                            && value.starts_with("AWS4-HMAC-SHA256 Credential=AKID/")
+                            && value.contains("/us-east-1/bedrock/aws4_request")
+                    })
+                })
+                .json_body(json!({
+                    "messages": [{"role": "user", "content": [{"text": "What's the weather?"}]}],
+                    "system": [{"text": "Be brief"}],
+                    "inferenceConfig": {"maxTokens": 100},
+                    "toolConfig": {"tools": [{"toolSpec": {
+                        "name": "get_weather",
+                        "description": "Get the weather",
+                        "inputSchema": {"json": {"type": "object"}}
+                    }}]}
+                }));
+            then.status(200).json_body(json!({
+                "output": {"message": {"role": "assistant", "content": [
+                    {"text": "Sunny."}
+                ]}},
+                "stopReason": "end_turn",
+                "usage": {"inputTokens": 10, "outputTokens": 2, "totalTokens": 12},
+                "metrics": {"latencyMs": 100}
+            }));
+        });
+
+        let base_url = server.base_url();
+        let client = ClientBuilder::new(Credentials::new("AKID", "secret"), "us-east-1")
+            .base_url(&base_url)
+            .build();
+        let model = client.completion_model(CLAUDE_3_HAIKU);
+        let response = model
+            .completion_request("What's the weather?")
+            .preamble("Be brief".to_string())
+            .max_tokens(100)
+            .tool(ToolDefinition {
+                name: "get_weather".to_string(),
+                description: "Get the weather".to_string(),
+                parameters: json!({"type": "object"}),
+            })
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.choice,
+            OneOrMany::one(message::AssistantContent::text("Sunny."))
+        );
+        assert_eq!(response.usage, Some(completion::Usage::new(10, 2)));
+        mock.assert();
+
+        let error = server.mock(|when, then| {
+            when.method(POST).path("/model/unknown/converse");
+            then.status(400)
+                .json_body(json!({"message": "The provided model identifier is invalid."}));
+        });
+        assert!(matches!(
+            client.agent("unknown").build().prompt("Hi").await,
+            Err(completion::PromptError::CompletionError(CompletionError::ProviderError(message)))
+                if message == "The provided model identifier is invalid."
+        ));
+        error.assert();
+    }
+}
@@ -0,0 +1,185 @@
+//! AWS Bedrock embedding api implementation, for the Titan text embedding models
+//! From [Amazon Titan Text Embeddings](https://docs.aws.amazon.com/bedrock/latest/userguide/model-parameters-titan-embed-text.html)
+
+use futures::future::try_join_all;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::embeddings::{self, EmbeddingError};
+
+use super::client::{ApiErrorResponse, Client};
+
+/// `amazon.titan-embed-text-v2:0` embedding model (1024 dimensions by default, or 256 or 512)
+pub const TITAN_EMBED_TEXT_V2: &str = "amazon.titan-embed-text-v2:0";
+/// `amazon.titan-embed-text-v1` embedding model
+pub const TITAN_EMBED_TEXT_V1: &str = "amazon.titan-embed-text-v1";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingResponse {
+    embedding: Vec<f64>,
+    input_text_token_count: u64,
+}
+
+#[derive(Clone)]
+pub struct EmbeddingModel {
+    client: Client,
+    model: String,
+    ndims: Option<usize>,
+}
+
+impl EmbeddingModel {
+    pub fn new(client: Client, model: &str, ndims: Option<usize>) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            ndims,
+        }
+    }
+
+    async fn embed_text(&self, document: String) -> Result<embeddings::Embedding, EmbeddingError> {
+        let mut request = json!({ "inputText": document });
+        if let Some(ndims) = self.ndims {
+            request["dimensions"] = json!(ndims);
+        }
+
+        let response = self
+            .client
+            .post_model(&self.model, "invoke", &request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let response = response.json::<EmbeddingResponse>().await?;
+            tracing::debug!(target: "rig",
+                "Bedrock embedding token usage: {}",
+                response.input_text_token_count
+            );
+            Ok(embeddings::Embedding {
+                document,
+                vec: response.embedding,
+            })
+        } else {
+            let text = response.text().await?;
+            Err(EmbeddingError::ProviderError(
+                match serde_json::from_str::<ApiErrorResponse>(&text) {
+                    Ok(error) => error.message,
+                    Err(_) => text,
+                },
+            ))
+        }
+    }
+}
+
+impl embeddings::EmbeddingModel for EmbeddingModel {
+    // The Titan models embed one text per request, sent concurrently
+    const MAX_DOCUMENTS: usize = 16;
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn ndims(&self) -> usize {
+        match (self.ndims, self.model.as_str()) {
+            (Some(ndims), _) => ndims,
+            (None, TITAN_EMBED_TEXT_V2) => 1024,
+            (None, TITAN_EMBED_TEXT_V1) => 1536,
+            _ => 0, // Default to 0 for unknown models
+        }
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn embed_texts(
+        &self,
+        documents: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        try_join_all(
+            documents
+                .into_iter()
+                .map(|document| self.embed_text(document)),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+
+    use super::*;
+    use crate::{embeddings::EmbeddingModel as _, providers::bedrock::Credentials};
+
+    #[tokio::test]
+    async fn test_titan_embeddings() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/model/amazon.titan-embed-text-v2%3A0/invoke")
+                .header_exists("authorization")
+                .json_body(json!({"inputText": "Hello", "dimensions": 2}));
+            then.status(200)
+                .json_body(json!({"embedding": [0.5, 0.5], "inputTextTokenCount": 1}));
+        });
+
+        let base_url = server.base_url();
+        let client = Client::builder(Credentials::new("AKID", "secret"), "us-east-1")
+            .base_url(&base_url)
+            .build();
+        let model = client.embedding_model_with_ndims(TITAN_EMBED_TEXT_V2, 2);
+        assert_eq!(model.ndims(), 2);
+        assert_eq!(client.embedding_model(TITAN_EMBED_TEXT_V2).ndims(), 1024);
+
+        let embeddings = model
+            .embed_texts(vec!["Hello".to_string(), "Hello".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embeddings.len(), 2);
+        assert_eq!(embeddings[1].vec, vec![0.5, 0.5]);
+        mock.assert_hits(2);
+    }
+
+    #[tokio::test]
+    async fn test_titan_embedding_errors() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/model/amazon.titan-embed-text-v1/invoke")
+                .json_body(json!({"inputText": "Hello"}));
+            then.status(200)
+                .json_body(json!({"embedding": [0.5, 0.5], "inputTextTokenCount": 1}));
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/model/amazon.titan-embed-text-v1/invoke")
+                .json_body(json!({"inputText": "Throttled"}));
+            then.status(429)
+                .json_body(json!({"message": "Too many requests"}));
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/model/amazon.titan-embed-text-v3/invoke");
+            then.status(404).body("Not found");
+        });
+
+        let client = Client::builder(Credentials::new("AKID", "secret"), "us-east-1")
+            .base_url(&server.base_url())
+            .build();
+
+        // Texts are embedded in concurrent requests, any of which fails the whole batch
+        let model = client.embedding_model(TITAN_EMBED_TEXT_V1);
+        assert!(matches!(
+            model
+                .embed_texts(vec!["Hello".to_string(), "Throttled".to_string()])
+                .await,
+            Err(EmbeddingError::ProviderError(message)) if message == "Too many requests"
+        ));
+
+        // Errors which are not Bedrock errors are returned as is
+        let model = client.embedding_model("amazon.titan-embed-text-v3");
+        assert_eq!(model.ndims(), 0);
+        assert!(matches!(
+            model.embed_texts(vec!["Hello".to_string()]).await,
+            Err(EmbeddingError::ProviderError(message)) if message == "Not found"
+        ));
+    }
+}
@@ -0,0 +1,28 @@
+//! AWS Bedrock API client and Rig integration (with the `bedrock` feature)
+//!
+//! The requests are signed with AWS Signature Version 4, with the credentials of an IAM user or
+//! role. Completion models (e.g.: Claude, Llama, Titan or Nova) use the Converse API, which
+//! supports tool use, and embedding models are the Titan text embedding models. Streaming is
+//! not supported yet.
+//!
+//! # Example
+//! ```
+//! use rig::providers::bedrock::{self, Credentials};
+//!
+//! let client = bedrock::Client::new(Credentials::from_env(), "us-east-1");
+//!
+//! let claude = client.completion_model(bedrock::CLAUDE_3_5_SONNET);
+//! let titan = client.embedding_model(bedrock::TITAN_EMBED_TEXT_V2);
+//! ```
+
+pub mod client;
+pub mod completion;
+pub mod embedding;
+mod sigv4;
+
+pub use client::{Client, ClientBuilder, Credentials};
+pub use completion::{
+    CLAUDE_3_5_HAIKU, CLAUDE_3_5_SONNET, CLAUDE_3_HAIKU, LLAMA_3_1_70B_INSTRUCT,
+    LLAMA_3_1_8B_INSTRUCT, NOVA_PRO, TITAN_TEXT_PREMIER,
+};
+pub use embedding::{TITAN_EMBED_TEXT_V1, TITAN_EMBED_TEXT_V2};
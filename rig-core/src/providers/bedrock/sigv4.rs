@@ -0,0 +1,225 @@
+//! AWS Signature Version 4 signing of requests
+//! From [AWS Signature Version 4](https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_sigv-create-signed-request.html)
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use super::client::Credentials;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// Signer of the requests to an AWS service in a region
+pub(crate) struct Signer<'a> {
+    pub credentials: &'a Credentials,
+    pub region: &'a str,
+    pub service: &'a str,
+}
+
+impl Signer<'_> {
+    /// Headers signing a request, to add to the request: `x-amz-date`, `x-amz-security-token`
+    /// (for temporary credentials) and `authorization`.
+    ///
+    /// `headers` are the other headers to sign (e.g.: `content-type`), besides the host of `url`.
+    pub(crate) fn sign(
+        &self,
+        method: &str,
+        url: &reqwest::Url,
+        headers: &[(&str, &str)],
+        payload: &[u8],
+        time: DateTime<Utc>,
+    ) -> Vec<(&'static str, String)> {
+        let Self {
+            credentials,
+            region,
+            service,
+        } = self;
+        let amz_date = time.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = time.format("%Y%m%d").to_string();
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let mut signed_headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+            .chain([
+                ("host".to_string(), host),
+                ("x-amz-date".to_string(), amz_date.clone()),
+            ])
+            .chain(
+                credentials
+                    .session_token
+                    .clone()
+                    .map(|token| ("x-amz-security-token".to_string(), token)),
+            )
+            .collect::<Vec<_>>();
+        signed_headers.sort();
+
+        let canonical_headers = signed_headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect::<String>();
+        let signed_header_names = signed_headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let canonical_request = [
+            method,
+            &canonical_uri(url),
+            &canonical_query(url),
+            &canonical_headers,
+            &signed_header_names,
+            &hex(&Sha256::digest(payload)),
+        ]
+        .join("\n");
+
+        let scope = format!("{date}/{region}/{service}/aws4_request");
+        let string_to_sign = [
+            ALGORITHM,
+            &amz_date,
+            &scope,
+            &hex(&Sha256::digest(canonical_request.as_bytes())),
+        ]
+        .join("\n");
+
+        let key = signing_key(&credentials.secret_access_key, &date, region, service);
+        let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+
+        let mut headers = vec![("x-amz-date", amz_date)];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization = format!(
+            "{ALGORITHM} Credential={}/{scope}, SignedHeaders={signed_header_names}, Signature={signature}",
+            credentials.access_key_id
+        );
+        headers.push(("authorization", authorization));
+        headers
+    }
+}
+
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(
+        format!("AWS4{secret_access_key}").as_bytes(),
+        date.as_bytes(),
+    );
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Percent-encode all the characters of `text` but the unreserved ones
+pub(super) fn uri_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Path of `url` with its (already encoded) segments encoded again, as signed by the services
+/// other than S3
+fn canonical_uri(url: &reqwest::Url) -> String {
+    match url.path() {
+        "" | "/" => "/".to_string(),
+        path => path
+            .split('/')
+            .map(uri_encode)
+            .collect::<Vec<_>>()
+            .join("/"),
+    }
+}
+
+/// Query parameters of `url` encoded and sorted by name
+fn canonical_query(url: &reqwest::Url) -> String {
+    let mut params = url
+        .query_pairs()
+        .map(|(name, value)| (uri_encode(&name), uri_encode(&value)))
+        .collect::<Vec<_>>();
+    params.sort();
+    params
+        .into_iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        // Example of the AWS documentation
+        let credentials =
+            Credentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY");
+        assert_eq!(
+            hex(&signing_key(
+                &credentials.secret_access_key,
+                "20150830",
+                "us-east-1",
+                "iam"
+            )),
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9"
+        );
+
+        let url = "https://iam.amazonaws.com/?Version=2010-05-08&Action=ListUsers"
+            .parse()
+            .unwrap();
+        let signer = Signer {
+            credentials: &credentials,
+            region: "us-east-1",
+            service: "iam",
+        };
+        let headers = signer.sign(
+            "GET",
+            &url,
+            &[(
+                "Content-Type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )],
+            b"",
+            Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap(),
+        );
+        assert_eq!(
+            headers,
+            vec![
+                ("x-amz-date", "20150830T123600Z".to_string()),
+                (
+                    "authorization",
+                    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+                    SignedHeaders=content-type;host;x-amz-date, \
+                    Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+                        .to_string()
+                ),
+            ]
+        );
+
+        let url = "https://bedrock-runtime.us-east-1.amazonaws.com/model/amazon.titan-embed-text-v2%3A0/invoke"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            canonical_uri(&url),
+            "/model/amazon.titan-embed-text-v2%253A0/invoke"
+        );
+    }
+}
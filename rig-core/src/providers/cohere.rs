@@ -15,7 +15,9 @@ use crate::{
     completion::{self, CompletionError},
     embeddings::{self, EmbeddingError, EmbeddingsBuilder},
     extractor::ExtractorBuilder,
-    json_utils, message, Embed, OneOrMany,
+    json_utils, message,
+    rerank::{self, RerankError},
+    Embed, OneOrMany,
 };
 
 use schemars::JsonSchema;
@@ -100,6 +102,11 @@ impl Client {
         EmbeddingsBuilder::new(self.embedding_model(model, input_type))
     }
 
+    /// Create a rerank model with the given name (e.g.: [RERANK_ENGLISH_V3]).
+    pub fn rerank_model(&self, model: &str) -> RerankModel {
+        RerankModel::new(self.clone(), model)
+    }
+
     pub fn completion_model(&self, model: &str) -> CompletionModel {
         CompletionModel::new(self.clone(), model)
     }
@@ -207,10 +214,18 @@ pub struct EmbeddingModel {
 impl embeddings::EmbeddingModel for EmbeddingModel {
     const MAX_DOCUMENTS: usize = 96;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     fn ndims(&self) -> usize {
         self.ndims
     }
 
+    fn limits(&self) -> embeddings::EmbeddingLimits {
+        embeddings::EmbeddingLimits::new(Self::MAX_DOCUMENTS).max_input_tokens(512)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn embed_texts(
         &self,
@@ -256,7 +271,7 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                     Ok(response
                         .embeddings
                         .into_iter()
-                        .zip(documents.into_iter())
+                        .zip(documents)
                         .map(|(embedding, document)| embeddings::Embedding {
                             document,
                             vec: embedding,
@@ -282,6 +297,89 @@ impl EmbeddingModel {
     }
 }
 
+// ================================================================
+// Cohere Rerank API
+// ================================================================
+/// `rerank-v3.5` rerank model
+pub const RERANK_V3_5: &str = "rerank-v3.5";
+/// `rerank-english-v3.0` rerank model
+pub const RERANK_ENGLISH_V3: &str = "rerank-english-v3.0";
+/// `rerank-multilingual-v3.0` rerank model
+pub const RERANK_MULTILINGUAL_V3: &str = "rerank-multilingual-v3.0";
+
+#[derive(Deserialize)]
+pub struct RerankResponse {
+    pub id: String,
+    pub results: Vec<RerankResult>,
+    #[serde(default)]
+    pub meta: Option<Meta>,
+}
+
+#[derive(Deserialize)]
+pub struct RerankResult {
+    pub index: usize,
+    pub relevance_score: f64,
+}
+
+#[derive(Clone)]
+pub struct RerankModel {
+    client: Client,
+    pub model: String,
+}
+
+impl RerankModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+}
+
+impl rerank::Reranker for RerankModel {
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn rerank(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+        top_n: usize,
+    ) -> Result<Vec<(f64, usize)>, RerankError> {
+        let response = self
+            .client
+            .post("/v2/rerank")
+            .json(&json!({
+                "model": self.model,
+                "query": query,
+                "documents": documents,
+                "top_n": top_n,
+            }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            match response.json::<ApiResponse<RerankResponse>>().await? {
+                ApiResponse::Ok(response) => {
+                    if let Some(meta) = response.meta {
+                        tracing::info!(target: "rig",
+                            "Cohere rerank billed units: {}",
+                            meta.billed_units,
+                        );
+                    }
+
+                    Ok(response
+                        .results
+                        .into_iter()
+                        .map(|result| (result.relevance_score, result.index))
+                        .collect())
+                }
+                ApiResponse::Err(error) => Err(RerankError::ProviderError(error.message)),
+            }
+        } else {
+            Err(RerankError::ProviderError(response.text().await?))
+        }
+    }
+}
+
 // ================================================================
 // Cohere Completion API
 // ================================================================
@@ -298,7 +396,7 @@ pub const COMMAND_LIGHT: &str = "command-light";
 /// `command-light-nightly` completion model
 pub const COMMAND_LIGHT_NIGHTLY: &str = "command-light-nightly";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CompletionResponse {
     pub text: String,
     pub generation_id: String,
@@ -342,12 +440,13 @@ impl From<CompletionResponse> for completion::CompletionResponse<CompletionRespo
 
         completion::CompletionResponse {
             choice: OneOrMany::many(model_response).expect("There is atleast one content"),
+            usage: None,
             raw_response: response,
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Citation {
     pub start: u32,
     pub end: u32,
@@ -355,20 +454,20 @@ pub struct Citation {
     pub document_ids: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Document {
     pub id: String,
     #[serde(flatten)]
     pub additional_prop: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct SearchQuery {
     pub text: String,
     pub generation_id: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct SearchResult {
     pub search_query: SearchQuery,
     pub connector: Connector,
@@ -379,7 +478,7 @@ pub struct SearchResult {
     pub continue_on_failure: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Connector {
     pub id: String,
 }
@@ -390,7 +489,7 @@ pub struct ToolCall {
     pub parameters: serde_json::Value,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ChatHistory {
     pub role: String,
     pub message: String,
@@ -511,10 +610,10 @@ pub struct ToolResult {
     pub outputs: Vec<serde_json::Value>,
 }
 
-impl TryFrom<message::Message> for Vec<Message> {
-    type Error = message::MessageError;
-
-    fn try_from(message: message::Message) -> Result<Self, Self::Error> {
+impl Message {
+    /// Converts a message of Rig to the messages of the provider, which may take several messages
+    /// (e.g.: one per tool result).
+    pub fn try_from_message(message: message::Message) -> Result<Vec<Self>, message::MessageError> {
         match message {
             message::Message::User { content } => content
                 .into_iter()
@@ -557,6 +656,10 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -565,7 +668,7 @@ impl completion::CompletionModel for CompletionModel {
         let chat_history = completion_request
             .chat_history
             .into_iter()
-            .map(Vec::<Message>::try_from)
+            .map(Message::try_from_message)
             .collect::<Result<Vec<Vec<_>>, _>>()?
             .into_iter()
             .flatten()
@@ -621,3 +724,57 @@ impl completion::CompletionModel for CompletionModel {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::json;
+
+    use super::*;
+    use crate::rerank::Reranker;
+
+    #[tokio::test]
+    async fn test_rerank() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/v2/rerank")
+                .json_body_partial(r#"{"query": "build error", "top_n": 1}"#);
+            then.status(200).json_body(json!({
+                "id": "rerank-1",
+                "results": [{"index": 1, "relevance_score": 0.9}]
+            }));
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/v2/rerank")
+                .json_body_partial(r#"{"query": "invalid"}"#);
+            then.status(200)
+                .json_body(json!({"message": "invalid request: documents must not be empty"}));
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/v2/rerank")
+                .json_body_partial(r#"{"query": "unauthorized"}"#);
+            then.status(401).body("invalid api token");
+        });
+
+        let model = Client::from_url("key", &server.base_url()).rerank_model(RERANK_V3_5);
+        let documents = vec!["build".to_string(), "build error".to_string()];
+        assert_eq!(
+            model
+                .rerank("build error", documents.clone(), 1)
+                .await
+                .unwrap(),
+            vec![(0.9, 1)]
+        );
+        assert!(matches!(
+            model.rerank("invalid", vec![], 1).await,
+            Err(RerankError::ProviderError(message)) if message.starts_with("invalid request")
+        ));
+        assert!(matches!(
+            model.rerank("unauthorized", documents, 1).await,
+            Err(RerankError::ProviderError(message)) if message == "invalid api token"
+        ));
+    }
+}
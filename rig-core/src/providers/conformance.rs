@@ -0,0 +1,625 @@
+//! Conformance tests of model provider integrations.
+//!
+//! The checks of this module run the models of a provider integration (whether it is
+//! maintained in this repository or out-of-tree) against a mock HTTP server serving fixtures in
+//! the wire format of the provider, and verify that they follow the semantics of the
+//! [CompletionModel], [StreamingCompletionModel] and [EmbeddingModel] traits:
+//! - the contents of the responses (texts, tool calls, embeddings) are returned in order,
+//! - the token usage reported by the provider is returned as an [Usage],
+//! - the errors of the provider are returned with their message, the rate limit errors being
+//!   [rate limited](crate::completion::CompletionError::is_rate_limited), and malformed
+//!   responses are errors.
+//!
+//! The integration describes its wire format by implementing [CompletionFixtures] (and
+//! [StreamingFixtures]) or [EmbeddingFixtures]. The checks panic with a description of the
+//! broken contract, like the assertions of a test.
+//!
+//! # Example
+//! ```rust
+//! use rig::providers::conformance::{self, CompletionFixtures};
+//!
+//! struct MyProvider;
+//!
+//! impl CompletionFixtures for MyProvider {
+//!     type Model = my_provider::CompletionModel;
+//!
+//!     fn model(&self, base_url: &str) -> Self::Model {
+//!         my_provider::Client::from_url("key", base_url).completion_model("my-model")
+//!     }
+//!
+//!     fn completion_path(&self) -> &str {
+//!         "/v1/generate"
+//!     }
+//!
+//!     // Responses in the format of the provider
+//!     ...
+//! }
+//!
+//! #[tokio::test]
+//! async fn conformance() {
+//!     conformance::check_completion_model(&MyProvider).await;
+//! }
+//! ```
+use httpmock::{Method::POST, MockServer};
+use serde_json::{json, Value};
+
+use crate::{
+    completion::{CompletionModel, ToolDefinition, Usage},
+    embeddings::EmbeddingModel,
+    message::AssistantContent,
+    streaming::{StreamingChoice, StreamingCompletionModel},
+};
+
+pub use httpmock;
+
+/// Message of the rate limit errors of the fixtures, served with a `429 Too Many Requests` status
+const RATE_LIMIT_MESSAGE: &str = "Rate limit reached for requests";
+
+/// Message of the other errors of the fixtures, served with a `500 Internal Server Error` status
+const ERROR_MESSAGE: &str = "The server had an error while processing your request";
+
+const PROMPT: &str = "What is the capital of France?";
+
+/// Wire format of the completion API of a provider.
+pub trait CompletionFixtures {
+    type Model: CompletionModel;
+
+    /// Model of the provider sending its requests to `base_url` (the URL of the mock server)
+    fn model(&self, base_url: &str) -> Self::Model;
+
+    /// Path of the completion endpoint on the mock server (e.g.: `/chat/completions`)
+    fn completion_path(&self) -> &str;
+
+    /// Body of a response answering `text`, using `usage` tokens
+    fn text_response(&self, text: &str, usage: Usage) -> Value;
+
+    /// Body of a response calling the tool `name` with `arguments`, using `usage` tokens
+    fn tool_call_response(&self, id: &str, name: &str, arguments: &Value, usage: Usage) -> Value;
+
+    /// Body of an error response of the provider with `message`
+    fn error_response(&self, message: &str) -> Value;
+}
+
+/// Wire format of the streaming completion API of a provider.
+pub trait StreamingFixtures: CompletionFixtures<Model: StreamingCompletionModel> {
+    /// Path of the streaming endpoint (defaults to the completion endpoint)
+    fn stream_path(&self) -> &str {
+        self.completion_path()
+    }
+
+    /// Body of a streamed response (i.e.: server-sent events) of the text `chunks`
+    fn stream_response(&self, chunks: &[&str]) -> String;
+}
+
+/// Wire format of the embedding API of a provider.
+pub trait EmbeddingFixtures {
+    type Model: EmbeddingModel;
+
+    /// Model of the provider sending its requests to `base_url` (the URL of the mock server)
+    fn model(&self, base_url: &str) -> Self::Model;
+
+    /// Path of the embedding endpoint on the mock server (e.g.: `/embeddings`)
+    fn embedding_path(&self) -> &str;
+
+    /// Body of a response with `embeddings`, in order, using `usage` tokens
+    fn embedding_response(&self, embeddings: &[Vec<f64>], usage: Usage) -> Value;
+
+    /// Body of an error response of the provider with `message`
+    fn error_response(&self, message: &str) -> Value;
+
+    /// Whether the provider reports the token usage of the embedding requests
+    fn reports_usage(&self) -> bool {
+        true
+    }
+}
+
+/// Start a mock server answering the POST requests to `path` with `status` and `body`
+async fn serve(path: &str, status: u16, content_type: &str, body: String) -> MockServer {
+    let server = MockServer::start_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(POST).path(path);
+            then.status(status)
+                .header("content-type", content_type)
+                .body(body);
+        })
+        .await;
+    server
+}
+
+async fn serve_json(path: &str, status: u16, body: &Value) -> MockServer {
+    serve(path, status, "application/json", body.to_string()).await
+}
+
+fn tool() -> ToolDefinition {
+    ToolDefinition {
+        name: "get_weather".to_string(),
+        description: "Get the weather of a city".to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}},
+            "required": ["city"]
+        }),
+    }
+}
+
+/// Check the completions of the models of a provider:
+/// - the prompt is sent to the completion endpoint, and its answer is returned,
+/// - tool calls are returned with their id, name and arguments,
+/// - the token usage of the responses is returned,
+/// - the errors of the provider are returned with their message ([check_completion_errors]).
+pub async fn check_completion_model<F: CompletionFixtures>(fixtures: &F) {
+    let usage = Usage::new(12, 5);
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path(fixtures.completion_path())
+                .body_contains(PROMPT);
+            then.status(200)
+                .json_body(fixtures.text_response("Paris", usage));
+        })
+        .await;
+    let response = fixtures
+        .model(&server.base_url())
+        .completion_request(PROMPT)
+        .preamble("Answer in one word.".to_string())
+        .max_tokens(256)
+        .send()
+        .await
+        .expect("The completion should succeed");
+    mock.assert_async().await;
+    match response.choice.first() {
+        AssistantContent::Text(text) => assert_eq!(text.text, "Paris"),
+        content => panic!("The completion should be the text of the response: {content:?}"),
+    }
+    assert_eq!(
+        response.usage,
+        Some(usage),
+        "The completion should return the token usage of the response"
+    );
+
+    let arguments = json!({"city": "Paris"});
+    let server = serve_json(
+        fixtures.completion_path(),
+        200,
+        &fixtures.tool_call_response("call_1", "get_weather", &arguments, usage),
+    )
+    .await;
+    let response = fixtures
+        .model(&server.base_url())
+        .completion_request("What is the weather in Paris?")
+        .tool(tool())
+        .max_tokens(256)
+        .send()
+        .await
+        .expect("The completion should succeed");
+    match response.choice.first() {
+        AssistantContent::ToolCall(tool_call) => {
+            assert_eq!(tool_call.id, "call_1");
+            assert_eq!(tool_call.function.name, "get_weather");
+            assert_eq!(tool_call.function.arguments, arguments);
+        }
+        content => panic!("The completion should be the tool call of the response: {content:?}"),
+    }
+    assert_eq!(response.usage, Some(usage));
+
+    check_completion_errors(fixtures).await;
+}
+
+/// Check the errors of the completions of the models of a provider:
+/// - the errors of the provider are returned with their message,
+/// - the rate limit errors (`429 Too Many Requests`) are
+///   [rate limited](crate::completion::CompletionError::is_rate_limited), unlike the other errors,
+/// - malformed responses are errors.
+pub async fn check_completion_errors<F: CompletionFixtures>(fixtures: &F) {
+    let complete = |server: MockServer| async move {
+        fixtures
+            .model(&server.base_url())
+            .completion_request(PROMPT)
+            .max_tokens(256)
+            .send()
+            .await
+            .map(|_| ())
+    };
+
+    let server = serve_json(
+        fixtures.completion_path(),
+        500,
+        &fixtures.error_response(ERROR_MESSAGE),
+    )
+    .await;
+    let error = complete(server)
+        .await
+        .expect_err("Error responses should be errors");
+    check_error_message(&error, ERROR_MESSAGE);
+    assert!(
+        !error.is_rate_limited(),
+        "{error} is not a rate limit error"
+    );
+
+    let server = serve_json(
+        fixtures.completion_path(),
+        429,
+        &fixtures.error_response(RATE_LIMIT_MESSAGE),
+    )
+    .await;
+    let error = complete(server)
+        .await
+        .expect_err("Error responses should be errors");
+    assert!(
+        error.is_rate_limited(),
+        "{error} should be a rate limit error"
+    );
+
+    let server = serve(
+        fixtures.completion_path(),
+        200,
+        "application/json",
+        "{\"choices\": [".to_string(),
+    )
+    .await;
+    complete(server)
+        .await
+        .expect_err("Malformed responses should be errors");
+}
+
+/// Check the streamed completions of the models of a provider:
+/// - the text chunks of the streamed responses are returned in order,
+/// - the errors of the provider are returned with their message, either by
+///   [stream](StreamingCompletionModel::stream) or as the first item of the stream.
+pub async fn check_streaming_model<F: StreamingFixtures>(fixtures: &F) {
+    use futures::StreamExt;
+
+    let chunks = ["The capital", " of France", " is Paris."];
+    let server = serve(
+        fixtures.stream_path(),
+        200,
+        "text/event-stream",
+        fixtures.stream_response(&chunks),
+    )
+    .await;
+    let mut stream = fixtures
+        .model(&server.base_url())
+        .completion_request(PROMPT)
+        .max_tokens(256)
+        .stream()
+        .await
+        .expect("The streamed completion should succeed");
+    let mut texts = vec![];
+    while let Some(choice) = stream.next().await {
+        match choice.expect("The chunks of the stream should be valid") {
+            StreamingChoice::Message(text) => texts.push(text),
+            choice => panic!("The stream should only contain text chunks: {choice:?}"),
+        }
+    }
+    assert_eq!(
+        texts.concat(),
+        chunks.concat(),
+        "The stream should return the text chunks of the response, in order"
+    );
+
+    let server = serve_json(
+        fixtures.stream_path(),
+        500,
+        &fixtures.error_response(ERROR_MESSAGE),
+    )
+    .await;
+    let result = fixtures
+        .model(&server.base_url())
+        .completion_request(PROMPT)
+        .max_tokens(256)
+        .stream()
+        .await;
+    let error = match result {
+        Ok(mut stream) => match stream.next().await {
+            Some(Err(error)) => error,
+            choice => panic!("Error responses should be errors: {choice:?}"),
+        },
+        Err(error) => error,
+    };
+    check_error_message(&error, ERROR_MESSAGE);
+}
+
+/// Check the embeddings of the models of a provider:
+/// - the texts are sent to the embedding endpoint, and their embeddings are returned in order,
+///   along with the texts,
+/// - the token usage of the responses is returned (if the provider
+///   [reports it](EmbeddingFixtures::reports_usage)),
+/// - responses with less embeddings than texts are errors,
+/// - the errors of the provider are returned with their message, the rate limit errors being
+///   [rate limited](crate::embeddings::EmbeddingError::is_rate_limited).
+pub async fn check_embedding_model<F: EmbeddingFixtures>(fixtures: &F) {
+    let texts = ["The cat sleeps", "The dog barks", "Paris is in France"];
+    let ndims = fixtures.model("http://localhost").ndims();
+    let vectors = (0..texts.len())
+        .map(|i| {
+            (0..ndims)
+                .map(|j| (i * ndims + j) as f64 / 100.0)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let usage = Usage::new(9, 0);
+
+    let server = MockServer::start_async().await;
+    let mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path(fixtures.embedding_path())
+                .body_contains(texts[1]);
+            then.status(200)
+                .json_body(fixtures.embedding_response(&vectors, usage));
+        })
+        .await;
+    let (embeddings, response_usage) = fixtures
+        .model(&server.base_url())
+        .embed_texts_with_usage(texts.map(str::to_string))
+        .await
+        .expect("The embeddings should succeed");
+    mock.assert_async().await;
+    assert_eq!(
+        embeddings
+            .iter()
+            .map(|embedding| embedding.document.as_str())
+            .collect::<Vec<_>>(),
+        texts,
+        "The embeddings should be returned with their texts, in order"
+    );
+    assert_eq!(
+        embeddings
+            .into_iter()
+            .map(|embedding| embedding.vec)
+            .collect::<Vec<_>>(),
+        vectors,
+        "The embeddings should be the vectors of the response, in order"
+    );
+    if fixtures.reports_usage() {
+        assert_eq!(
+            response_usage,
+            Some(usage),
+            "The embeddings should return the token usage of the response"
+        );
+    }
+
+    let embed = |server: MockServer| async move {
+        fixtures
+            .model(&server.base_url())
+            .embed_texts(texts.map(str::to_string))
+            .await
+    };
+
+    let server = serve_json(
+        fixtures.embedding_path(),
+        200,
+        &fixtures.embedding_response(&vectors[..2], usage),
+    )
+    .await;
+    embed(server)
+        .await
+        .expect_err("Responses with less embeddings than texts should be errors");
+
+    let server = serve_json(
+        fixtures.embedding_path(),
+        500,
+        &fixtures.error_response(ERROR_MESSAGE),
+    )
+    .await;
+    let error = embed(server)
+        .await
+        .expect_err("Error responses should be errors");
+    check_error_message(&error, ERROR_MESSAGE);
+    assert!(
+        !error.is_rate_limited(),
+        "{error} is not a rate limit error"
+    );
+
+    let server = serve_json(
+        fixtures.embedding_path(),
+        429,
+        &fixtures.error_response(RATE_LIMIT_MESSAGE),
+    )
+    .await;
+    let error = embed(server)
+        .await
+        .expect_err("Error responses should be errors");
+    assert!(
+        error.is_rate_limited(),
+        "{error} should be a rate limit error"
+    );
+}
+
+fn check_error_message(error: &impl std::fmt::Display, message: &str) {
+    assert!(
+        error.to_string().contains(message),
+        "The error should contain the message of the provider: {error}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{anthropic, openai};
+
+    struct OpenAI;
+
+    impl CompletionFixtures for OpenAI {
+        type Model = openai::CompletionModel;
+
+        fn model(&self, base_url: &str) -> Self::Model {
+            openai::Client::from_url("key", base_url).completion_model(openai::GPT_4O)
+        }
+
+        fn completion_path(&self) -> &str {
+            "/chat/completions"
+        }
+
+        fn text_response(&self, text: &str, usage: Usage) -> Value {
+            self.response(json!({"role": "assistant", "content": text}), usage)
+        }
+
+        fn tool_call_response(
+            &self,
+            id: &str,
+            name: &str,
+            arguments: &Value,
+            usage: Usage,
+        ) -> Value {
+            self.response(
+                json!({
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": id,
+                        "type": "function",
+                        "function": {"name": name, "arguments": arguments.to_string()}
+                    }]
+                }),
+                usage,
+            )
+        }
+
+        fn error_response(&self, message: &str) -> Value {
+            json!({"error": {"message": message, "type": "server_error"}})
+        }
+    }
+
+    impl OpenAI {
+        fn response(&self, message: Value, usage: Usage) -> Value {
+            json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "gpt-4o",
+                "choices": [{"index": 0, "message": message, "finish_reason": "stop"}],
+                "usage": {
+                    "prompt_tokens": usage.input_tokens,
+                    "completion_tokens": usage.output_tokens,
+                    "total_tokens": usage.total_tokens
+                }
+            })
+        }
+    }
+
+    impl EmbeddingFixtures for OpenAI {
+        type Model = openai::EmbeddingModel;
+
+        fn model(&self, base_url: &str) -> Self::Model {
+            openai::Client::from_url("key", base_url)
+                .embedding_model(openai::TEXT_EMBEDDING_3_SMALL)
+        }
+
+        fn embedding_path(&self) -> &str {
+            "/embeddings"
+        }
+
+        fn embedding_response(&self, embeddings: &[Vec<f64>], usage: Usage) -> Value {
+            json!({
+                "object": "list",
+                "data": embeddings
+                    .iter()
+                    .enumerate()
+                    .map(|(index, embedding)| {
+                        json!({"object": "embedding", "embedding": embedding, "index": index})
+                    })
+                    .collect::<Vec<_>>(),
+                "model": "text-embedding-3-small",
+                "usage": {"prompt_tokens": usage.input_tokens, "total_tokens": usage.total_tokens}
+            })
+        }
+
+        fn error_response(&self, message: &str) -> Value {
+            CompletionFixtures::error_response(self, message)
+        }
+    }
+
+    struct Anthropic;
+
+    impl CompletionFixtures for Anthropic {
+        type Model = anthropic::completion::CompletionModel;
+
+        fn model(&self, base_url: &str) -> Self::Model {
+            anthropic::ClientBuilder::new("key")
+                .base_url(base_url)
+                .build()
+                .completion_model(anthropic::CLAUDE_3_5_SONNET)
+        }
+
+        fn completion_path(&self) -> &str {
+            "/v1/messages"
+        }
+
+        fn text_response(&self, text: &str, usage: Usage) -> Value {
+            self.response(json!({"type": "text", "text": text}), usage)
+        }
+
+        fn tool_call_response(
+            &self,
+            id: &str,
+            name: &str,
+            arguments: &Value,
+            usage: Usage,
+        ) -> Value {
+            self.response(
+                json!({"type": "tool_use", "id": id, "name": name, "input": arguments}),
+                usage,
+            )
+        }
+
+        fn error_response(&self, message: &str) -> Value {
+            json!({"type": "error", "error": {"type": "api_error", "message": message}})
+        }
+    }
+
+    impl Anthropic {
+        fn response(&self, content: Value, usage: Usage) -> Value {
+            json!({
+                "type": "message",
+                "id": "msg_1",
+                "model": "claude-3-5-sonnet-latest",
+                "role": "assistant",
+                "content": [content],
+                "stop_reason": "end_turn",
+                "stop_sequence": null,
+                "usage": {"input_tokens": usage.input_tokens, "output_tokens": usage.output_tokens}
+            })
+        }
+    }
+
+    impl StreamingFixtures for Anthropic {
+        fn stream_response(&self, chunks: &[&str]) -> String {
+            let mut events = vec![
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            ];
+            events.extend(chunks.iter().map(|text| {
+                json!({
+                    "type": "content_block_delta",
+                    "index": 0,
+                    "delta": {"type": "text_delta", "text": text}
+                })
+            }));
+            events.push(json!({"type": "content_block_stop", "index": 0}));
+            events.push(json!({"type": "message_stop"}));
+            events
+                .iter()
+                .map(|event| {
+                    format!(
+                        "event: {}\ndata: {event}\n\n",
+                        event["type"].as_str().unwrap()
+                    )
+                })
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openai_conformance() {
+        check_completion_model(&OpenAI).await;
+        check_embedding_model(&OpenAI).await;
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_conformance() {
+        check_completion_model(&Anthropic).await;
+        check_streaming_model(&Anthropic).await;
+    }
+}
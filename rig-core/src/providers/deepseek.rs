@@ -112,7 +112,25 @@ impl From<ApiErrorResponse> for CompletionError {
 pub struct CompletionResponse {
     // We'll match the JSON:
     pub choices: Vec<Choice>,
-    // you may want usage or other fields
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl From<&Usage> for completion::Usage {
+    fn from(usage: &Usage) -> Self {
+        completion::Usage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -188,10 +206,10 @@ impl From<message::ToolCall> for ToolCall {
     }
 }
 
-impl TryFrom<message::Message> for Vec<Message> {
-    type Error = message::MessageError;
-
-    fn try_from(message: message::Message) -> Result<Self, Self::Error> {
+impl Message {
+    /// Converts a message of Rig to the messages of the provider, which may take several messages
+    /// (e.g.: one per tool result).
+    pub fn try_from_message(message: message::Message) -> Result<Vec<Self>, message::MessageError> {
         match message {
             message::Message::User { content } => {
                 // extract tool results
@@ -354,6 +372,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
 
         Ok(completion::CompletionResponse {
             choice,
+            usage: response.usage.as_ref().map(Into::into),
             raw_response: response,
         })
     }
@@ -369,6 +388,14 @@ pub struct DeepSeekCompletionModel {
 impl CompletionModel for DeepSeekCompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        Some(super::openai::json_mode())
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -384,13 +411,14 @@ impl CompletionModel for DeepSeekCompletionModel {
         };
 
         // Convert prompt to user message
-        let prompt: Vec<Message> = completion_request.prompt_with_context().try_into()?;
+        let prompt: Vec<Message> =
+            Message::try_from_message(completion_request.prompt_with_context())?;
 
         // Convert existing chat history
         let chat_history: Vec<Message> = completion_request
             .chat_history
             .into_iter()
-            .map(|message| message.try_into())
+            .map(Message::try_from_message)
             .collect::<Result<Vec<Vec<Message>>, _>>()?
             .into_iter()
             .flatten()
@@ -142,7 +142,7 @@ enum ApiResponse<T> {
     Err(ApiErrorResponse),
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Usage {
     pub prompt_tokens: usize,
     pub total_tokens: usize,
@@ -158,6 +158,18 @@ impl std::fmt::Display for Usage {
     }
 }
 
+impl From<&Usage> for completion::Usage {
+    fn from(usage: &Usage) -> Self {
+        let prompt_tokens = usage.prompt_tokens as u64;
+        let total_tokens = usage.total_tokens as u64;
+        completion::Usage {
+            input_tokens: prompt_tokens,
+            output_tokens: total_tokens.saturating_sub(prompt_tokens),
+            total_tokens,
+        }
+    }
+}
+
 // ================================================================
 // Galadriel Completion API
 // ================================================================
@@ -204,7 +216,7 @@ pub const GPT_35_TURBO_1106: &str = "gpt-3.5-turbo-1106";
 /// `gpt-3.5-turbo-instruct` completion model
 pub const GPT_35_TURBO_INSTRUCT: &str = "gpt-3.5-turbo-instruct";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CompletionResponse {
     pub id: String,
     pub object: String,
@@ -251,12 +263,13 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
 
         Ok(completion::CompletionResponse {
             choice,
+            usage: response.usage.as_ref().map(Into::into),
             raw_response: response,
         })
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Choice {
     pub index: usize,
     pub message: Message,
@@ -301,8 +314,7 @@ impl TryFrom<Message> for message::Message {
                         .chain(
                             message
                                 .content
-                                .map(|content| message::AssistantContent::text(&content))
-                                .into_iter(),
+                                .map(|content| message::AssistantContent::text(&content)),
                         ),
                 )
                 .map_err(|_| {
@@ -403,6 +415,10 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
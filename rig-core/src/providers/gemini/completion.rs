@@ -50,6 +50,14 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = GenerateContentResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn json_mode(&self) -> Option<Value> {
+        Some(serde_json::json!({"responseMimeType": "application/json"}))
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -187,6 +195,14 @@ impl TryFrom<GenerateContentResponse> for completion::CompletionResponse<Generat
 
         Ok(completion::CompletionResponse {
             choice,
+            usage: response
+                .usage_metadata
+                .as_ref()
+                .map(|usage| completion::Usage {
+                    input_tokens: usage.prompt_token_count as u64,
+                    output_tokens: usage.candidates_token_count as u64,
+                    total_tokens: usage.total_token_count as u64,
+                }),
             raw_response: response,
         })
     }
@@ -216,7 +232,7 @@ pub mod gemini_api_types {
     ///     - Returns either all requested candidates or none of them
     ///     - Returns no candidates at all only if there was something wrong with the prompt (check promptFeedback)
     ///     - Reports feedback on each candidate in finishReason and safetyRatings.
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct GenerateContentResponse {
         /// Candidate responses from the model.
@@ -229,7 +245,7 @@ pub mod gemini_api_types {
     }
 
     /// A response candidate generated from the model.
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ContentCandidate {
         /// Output only. Generated content returned from the model.
@@ -596,7 +612,7 @@ pub mod gemini_api_types {
         HarmCategoryCivicIntegrity,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct UsageMetadata {
         pub prompt_token_count: i32,
@@ -623,7 +639,7 @@ pub mod gemini_api_types {
     }
 
     /// A set of the feedback metadata the prompt specified in [GenerateContentRequest.contents](GenerateContentRequest).
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct PromptFeedback {
         /// Optional. If set, the prompt was blocked and no candidates are returned. Rephrase the prompt.
@@ -633,7 +649,7 @@ pub mod gemini_api_types {
     }
 
     /// Reason why a prompt was blocked by the model
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
     pub enum BlockReason {
         /// Default value. This value is unused.
@@ -648,7 +664,7 @@ pub mod gemini_api_types {
         ProhibitedContent,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
     pub enum FinishReason {
         /// Default value. This value is unused.
@@ -675,13 +691,13 @@ pub mod gemini_api_types {
         MalformedFunctionCall,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct CitationMetadata {
         pub citation_sources: Vec<CitationSource>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct CitationSource {
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -694,19 +710,19 @@ pub mod gemini_api_types {
         pub license: Option<String>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct LogprobsResult {
         pub top_candidate: Vec<TopCandidate>,
         pub chosen_candidate: Vec<LogProbCandidate>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct TopCandidate {
         pub candidates: Vec<LogProbCandidate>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct LogProbCandidate {
         pub token: String,
@@ -33,6 +33,10 @@ impl EmbeddingModel {
 impl embeddings::EmbeddingModel for EmbeddingModel {
     const MAX_DOCUMENTS: usize = 1024;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     fn ndims(&self) -> usize {
         match self.model.as_str() {
             EMBEDDING_001 => 768,
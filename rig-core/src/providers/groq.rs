@@ -261,6 +261,14 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        Some(super::openai::json_mode())
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -133,7 +133,7 @@ pub struct EmbeddingData {
     pub index: usize,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Usage {
     pub prompt_tokens: usize,
     pub total_tokens: usize,
@@ -149,6 +149,18 @@ impl std::fmt::Display for Usage {
     }
 }
 
+impl From<&Usage> for completion::Usage {
+    fn from(usage: &Usage) -> Self {
+        let prompt_tokens = usage.prompt_tokens as u64;
+        let total_tokens = usage.total_tokens as u64;
+        completion::Usage {
+            input_tokens: prompt_tokens,
+            output_tokens: total_tokens.saturating_sub(prompt_tokens),
+            total_tokens,
+        }
+    }
+}
+
 // ================================================================
 // Hyperbolic Completion API
 // ================================================================
@@ -180,7 +192,7 @@ pub const DEEPSEEK_R1: &str = "deepseek-ai/DeepSeek-R1";
 /// A Hyperbolic completion object.
 ///
 /// For more information, see this link: <https://docs.hyperbolic.xyz/reference/create_chat_completion_v1_chat_completions_post>
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CompletionResponse {
     pub id: String,
     pub object: String,
@@ -247,12 +259,13 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
 
         Ok(completion::CompletionResponse {
             choice,
+            usage: response.usage.as_ref().map(Into::into),
             raw_response: response,
         })
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Choice {
     pub index: usize,
     pub message: Message,
@@ -278,6 +291,10 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -290,13 +307,14 @@ impl completion::CompletionModel for CompletionModel {
         };
 
         // Convert prompt to user message
-        let prompt: Vec<Message> = completion_request.prompt_with_context().try_into()?;
+        let prompt: Vec<Message> =
+            Message::try_from_message(completion_request.prompt_with_context())?;
 
         // Convert existing chat history
         let chat_history: Vec<Message> = completion_request
             .chat_history
             .into_iter()
-            .map(|message| message.try_into())
+            .map(Message::try_from_message)
             .collect::<Result<Vec<Vec<Message>>, _>>()?
             .into_iter()
             .flatten()
@@ -0,0 +1,505 @@
+//! Deterministic mock models, to test agents, extractors and RAG pipelines without calling a
+//! provider.
+//!
+//! A [MockCompletionModel] answers with scripted responses, in order, and records the requests
+//! it receives. A [MockEmbeddingModel] embeds texts with canned vectors, with a bag of words
+//! hashed into its dimensions (so that texts sharing words are similar) or with the keywords
+//! they contain, and records the texts it embeds. Clones of a mock model share its script and
+//! recordings, so that a model can be given to an agent and inspected afterwards. A
+//! [latency](MockCompletionModel::latency) can be given to the completion model, e.g.: to test
+//! timeouts.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, providers::mock::MockCompletionModel};
+//!
+//! let model = MockCompletionModel::new()
+//!     .tool_call("add", json!({"x": 1, "y": 2}))
+//!     .text("1 + 2 = 3");
+//!
+//! let agent = AgentBuilder::new(model.clone()).tool(Adder).max_turns(1).build();
+//! assert_eq!(agent.prompt("What is 1 + 2?").await?, "1 + 2 = 3");
+//! assert_eq!(model.requests().len(), 2);
+//! ```
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{stream, StreamExt};
+
+use crate::{
+    completion::{self, CompletionError, CompletionRequest, Usage},
+    embeddings::{self, Embedding, EmbeddingError},
+    message::AssistantContent,
+    runtime,
+    streaming::{self, StreamingChoice, StreamingResult},
+    OneOrMany,
+};
+
+// ================================================================
+// Mock Completion Model
+// ================================================================
+/// Scripted response: the content of the response, or the message of a provider error
+type ScriptedResponse = Result<OneOrMany<AssistantContent>, String>;
+
+/// Completion model answering with scripted responses, in the order they were added.
+///
+/// Once the script is exhausted, the model answers with its [repeat](Self::repeat) response if
+/// any, and fails with a [CompletionError::ProviderError] otherwise.
+#[derive(Clone, Default)]
+pub struct MockCompletionModel {
+    script: Arc<Mutex<VecDeque<ScriptedResponse>>>,
+    repeat: Option<OneOrMany<AssistantContent>>,
+    usage: Option<Usage>,
+    latency: Option<Duration>,
+    structured_outputs: bool,
+    requests: Arc<Mutex<Vec<CompletionRequest>>>,
+}
+
+impl MockCompletionModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the next request with `content`.
+    pub fn response(self, content: OneOrMany<AssistantContent>) -> Self {
+        self.push(Ok(content))
+    }
+
+    /// Answer the next request with a text.
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.response(OneOrMany::one(AssistantContent::text(text)))
+    }
+
+    /// Answer the next request with a call of the tool `name`.
+    pub fn tool_call(self, name: &str, arguments: serde_json::Value) -> Self {
+        let id = format!("call_{}", self.script().len());
+        self.response(OneOrMany::one(AssistantContent::tool_call(
+            id, name, arguments,
+        )))
+    }
+
+    /// Fail the next request with a [CompletionError::ProviderError].
+    pub fn error(self, message: impl Into<String>) -> Self {
+        self.push(Err(message.into()))
+    }
+
+    /// Answer with `text` once the script is exhausted.
+    pub fn repeat(mut self, text: impl Into<String>) -> Self {
+        self.repeat = Some(OneOrMany::one(AssistantContent::text(text)));
+        self
+    }
+
+    /// Report `usage` as the token usage of each response.
+    pub fn usage(mut self, usage: Usage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Wait for `latency` before each response (and before each chunk of the streamed
+    /// responses). The requests are recorded right away.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Support structured outputs: the model asks for them with a `response_format` additional
+    /// param holding the name and the schema of the output.
+    pub fn structured_outputs(mut self) -> Self {
+        self.structured_outputs = true;
+        self
+    }
+
+    /// Requests received by the model (and its clones), in order.
+    pub fn requests(&self) -> Vec<CompletionRequest> {
+        self.requests
+            .lock()
+            .expect("mock requests lock poisoned")
+            .clone()
+    }
+
+    /// Last request received by the model, if any.
+    pub fn last_request(&self) -> Option<CompletionRequest> {
+        self.requests
+            .lock()
+            .expect("mock requests lock poisoned")
+            .last()
+            .cloned()
+    }
+
+    /// Number of scripted responses not used yet.
+    pub fn remaining(&self) -> usize {
+        self.script().len()
+    }
+
+    fn push(self, response: ScriptedResponse) -> Self {
+        self.script().push_back(response);
+        self
+    }
+
+    fn script(&self) -> std::sync::MutexGuard<'_, VecDeque<ScriptedResponse>> {
+        self.script.lock().expect("mock script lock poisoned")
+    }
+
+    /// Record `request` and return the next response of the script
+    fn next(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<OneOrMany<AssistantContent>, CompletionError> {
+        self.requests
+            .lock()
+            .expect("mock requests lock poisoned")
+            .push(request);
+        match self.script().pop_front() {
+            Some(Ok(content)) => Ok(content),
+            Some(Err(message)) => Err(CompletionError::ProviderError(message)),
+            None => self
+                .repeat
+                .clone()
+                .ok_or_else(|| CompletionError::ProviderError("No scripted response left".into())),
+        }
+    }
+}
+
+impl completion::CompletionModel for MockCompletionModel {
+    type Response = ();
+
+    fn model_name(&self) -> Option<&str> {
+        Some("mock")
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.structured_outputs
+            .then(|| serde_json::json!({"response_format": {"name": name, "schema": schema}}))
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<()>, CompletionError> {
+        let choice = self.next(request);
+        if let Some(latency) = self.latency {
+            runtime::sleep(latency).await;
+        }
+        Ok(completion::CompletionResponse {
+            choice: choice?,
+            usage: self.usage,
+            raw_response: (),
+        })
+    }
+}
+
+/// Streams each content of the scripted response as a chunk.
+impl streaming::StreamingCompletionModel for MockCompletionModel {
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingResult, CompletionError> {
+        let chunks = self
+            .next(request)?
+            .into_iter()
+            .map(|content| {
+                Ok(match content {
+                    AssistantContent::Text(text) => StreamingChoice::Message(text.text),
+                    AssistantContent::ToolCall(tool_call) => StreamingChoice::ToolCall(
+                        tool_call.function.name,
+                        tool_call.id,
+                        tool_call.function.arguments,
+                    ),
+                })
+            })
+            .collect::<Vec<_>>();
+        let latency = self.latency;
+        Ok(Box::pin(stream::iter(chunks).then(
+            move |chunk| async move {
+                if let Some(latency) = latency {
+                    runtime::sleep(latency).await;
+                }
+                chunk
+            },
+        )))
+    }
+}
+
+// ================================================================
+// Mock Embedding Model
+// ================================================================
+/// Embedding model embedding texts with canned vectors, with the hashes of their words or with
+/// the keywords they contain.
+///
+/// The model reports the number of words of the texts as the input tokens of its requests.
+#[derive(Clone)]
+pub struct MockEmbeddingModel {
+    ndims: usize,
+    embeddings: HashMap<String, Vec<f64>>,
+    keywords: Option<Vec<String>>,
+    limits: embeddings::EmbeddingLimits,
+    failing: Option<String>,
+    batches: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl MockEmbeddingModel {
+    pub fn new(ndims: usize) -> Self {
+        Self {
+            ndims,
+            embeddings: HashMap::new(),
+            keywords: None,
+            limits: embeddings::EmbeddingLimits::new(
+                <Self as embeddings::EmbeddingModel>::MAX_DOCUMENTS,
+            ),
+            failing: None,
+            batches: Default::default(),
+        }
+    }
+
+    /// Model embedding texts by the `keywords` they contain: each keyword has its dimension,
+    /// 1 if the (lowercase) text contains the keyword and 0.01 otherwise (so that no embedding
+    /// is null).
+    pub fn keywords(keywords: &[&str]) -> Self {
+        Self {
+            keywords: Some(keywords.iter().map(|keyword| keyword.to_string()).collect()),
+            ..Self::new(keywords.len())
+        }
+    }
+
+    /// Embed `text` as `vector` instead of the hashes of its words.
+    pub fn embedding(mut self, text: impl Into<String>, vector: Vec<f64>) -> Self {
+        self.embeddings.insert(text.into(), vector);
+        self
+    }
+
+    /// Set the limits of the requests to the model (defaults to 1024 texts per request).
+    pub fn limits(mut self, limits: embeddings::EmbeddingLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Fail the requests embedding a text containing `pattern` with an
+    /// [EmbeddingError::ProviderError].
+    pub fn fail_on(mut self, pattern: impl Into<String>) -> Self {
+        self.failing = Some(pattern.into());
+        self
+    }
+
+    /// Texts embedded by the model (and its clones), in order.
+    pub fn texts(&self) -> Vec<String> {
+        self.batches().into_iter().flatten().collect()
+    }
+
+    /// Texts of each request to the model (and its clones), in order.
+    pub fn batches(&self) -> Vec<Vec<String>> {
+        self.batches
+            .lock()
+            .expect("mock batches lock poisoned")
+            .clone()
+    }
+
+    fn embed(&self, text: &str) -> Vec<f64> {
+        if let Some(vector) = self.embeddings.get(text) {
+            return vector.clone();
+        }
+        match &self.keywords {
+            Some(keywords) => {
+                let text = text.to_lowercase();
+                keywords
+                    .iter()
+                    .map(|keyword| if text.contains(keyword) { 1.0 } else { 0.01 })
+                    .collect()
+            }
+            None => self.hash_embedding(text),
+        }
+    }
+
+    /// Normalized counts of the lowercase words of `text`, each word counted in the dimension
+    /// of its (FNV-1a) hash
+    fn hash_embedding(&self, text: &str) -> Vec<f64> {
+        let mut vec = vec![0.0; self.ndims];
+        if self.ndims == 0 {
+            return vec;
+        }
+        for word in words(text) {
+            let hash = word
+                .to_lowercase()
+                .bytes()
+                .fold(0xcbf29ce484222325u64, |hash, byte| {
+                    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+                });
+            vec[(hash % self.ndims as u64) as usize] += 1.0;
+        }
+        let norm = vec.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            vec.iter_mut().for_each(|x| *x /= norm);
+        }
+        vec
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+impl embeddings::EmbeddingModel for MockEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    fn limits(&self) -> embeddings::EmbeddingLimits {
+        self.limits
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some("mock")
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+        self.batches
+            .lock()
+            .expect("mock batches lock poisoned")
+            .push(texts.clone());
+        if let Some(text) = self
+            .failing
+            .as_ref()
+            .and_then(|pattern| texts.iter().find(|text| text.contains(pattern.as_str())))
+        {
+            return Err(EmbeddingError::ProviderError(format!(
+                "Invalid text: {text}"
+            )));
+        }
+        Ok(texts
+            .into_iter()
+            .map(|text| Embedding {
+                vec: self.embed(&text),
+                document: text,
+            })
+            .collect())
+    }
+
+    async fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<(Vec<Embedding>, Option<Usage>), EmbeddingError> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+        let tokens = texts.iter().map(|text| words(text).count() as u64).sum();
+        let embeddings = self.embed_texts(texts).await?;
+        Ok((embeddings, Some(Usage::new(tokens, 0))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{CompletionModel, Prompt, PromptError},
+        embeddings::{distance::VectorDistance, EmbeddingModel},
+        streaming::StreamingCompletionModel,
+    };
+
+    #[tokio::test]
+    async fn test_mock_completion_model() {
+        let model = MockCompletionModel::new()
+            .text("Hello")
+            .error("overloaded")
+            .usage(Usage::new(3, 1));
+        let agent = AgentBuilder::new(model.clone()).preamble("Be nice").build();
+
+        assert_eq!(agent.prompt("Hi").await.unwrap(), "Hello");
+        assert!(matches!(
+            agent.prompt("Hi again").await,
+            Err(PromptError::CompletionError(CompletionError::ProviderError(message)))
+                if message == "overloaded"
+        ));
+        assert!(agent.prompt("Still there?").await.is_err());
+        assert_eq!(agent.usage(), Usage::new(3, 1));
+
+        let requests = model.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].preamble.as_deref(), Some("Be nice"));
+        assert_eq!(
+            model.last_request().unwrap().prompt,
+            completion::Message::user("Still there?")
+        );
+
+        let model = MockCompletionModel::new()
+            .tool_call("add", json!({"x": 1}))
+            .repeat("Done");
+        assert_eq!(model.remaining(), 1);
+        let mut chunks = model
+            .stream(model.completion_request("Add").build())
+            .await
+            .unwrap();
+        assert_eq!(
+            chunks.next().await.unwrap().unwrap(),
+            StreamingChoice::ToolCall("add".into(), "call_0".into(), json!({"x": 1}))
+        );
+        for _ in 0..2 {
+            let response = model
+                .completion(model.completion_request("Again").build())
+                .await
+                .unwrap();
+            assert_eq!(
+                response.choice,
+                OneOrMany::one(AssistantContent::text("Done"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_embedding_model() {
+        let model = MockEmbeddingModel::new(64).embedding("canned", vec![1.0; 64]);
+        let embeddings = model
+            .embed_texts(vec![
+                "The cat sat".to_string(),
+                "the CAT sat!".to_string(),
+                "Stock prices fell".to_string(),
+                "canned".to_string(),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(embeddings[0].vec, embeddings[1].vec);
+        assert!((embeddings[0].cosine_similarity(&embeddings[1], false) - 1.0).abs() < 1e-9);
+        assert!(embeddings[0].cosine_similarity(&embeddings[2], false) < 1.0);
+        assert_eq!(embeddings[3].vec, vec![1.0; 64]);
+        assert_eq!(model.clone().texts().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_mock_keyword_embedding_model() {
+        let model = MockEmbeddingModel::keywords(&["cat", "dog"]).fail_on("bad");
+        assert_eq!(model.ndims(), 2);
+
+        let (embeddings, usage) = model
+            .embed_texts_with_usage(vec!["The Cat sat".to_string(), "Fish".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embeddings[0].vec, vec![1.0, 0.01]);
+        assert_eq!(embeddings[1].vec, vec![0.01, 0.01]);
+        assert_eq!(usage, Some(Usage::new(4, 0)));
+
+        assert!(matches!(
+            model.embed_texts(vec!["bad dog".to_string()]).await,
+            Err(EmbeddingError::ProviderError(message)) if message == "Invalid text: bad dog"
+        ));
+        assert_eq!(
+            model.batches(),
+            vec![
+                vec!["The Cat sat".to_string(), "Fish".to_string()],
+                vec!["bad dog".to_string()]
+            ]
+        );
+    }
+}
@@ -10,6 +10,7 @@
 //! - EternalAI
 //! - DeepSeek
 //! - Azure OpenAI
+//! - AWS Bedrock (with the `bedrock` feature)
 //!
 //! Each provider has its own module, which contains a `Client` implementation that can
 //! be used to initialize completion and embedding models and execute requests to those models.
@@ -44,17 +45,50 @@
 //! ```
 //! Note: The example above uses the OpenAI provider client, but the same pattern can
 //! be used with the Cohere provider client.
+//!
+//! The [openai_compatible] module provides a client for any other provider or server
+//! implementing the OpenAI API (e.g.: Fireworks AI, OpenRouter or vLLM), with a configurable base
+//! URL and headers.
+//!
+//! The [mock] module provides deterministic models answering with scripted responses, to test
+//! agents and RAG pipelines without calling a provider. Unlike the provider integrations, it is
+//! available without the `providers` feature.
+//!
+//! Provider integrations (including out-of-tree ones) can check that their models behave like
+//! the models of Rig with the `conformance` module (`test-kit` feature), which runs them against
+//! a mock HTTP server.
+#[cfg(feature = "providers")]
 pub mod anthropic;
+#[cfg(feature = "providers")]
 pub mod azure;
+#[cfg(feature = "bedrock")]
+pub mod bedrock;
+#[cfg(feature = "providers")]
 pub mod cohere;
+#[cfg(feature = "test-kit")]
+pub mod conformance;
+#[cfg(feature = "providers")]
 pub mod deepseek;
+#[cfg(feature = "providers")]
 pub mod galadriel;
+#[cfg(feature = "providers")]
 pub mod gemini;
+#[cfg(feature = "providers")]
 pub mod groq;
+#[cfg(feature = "providers")]
 pub mod hyperbolic;
+pub mod mock;
+#[cfg(feature = "providers")]
 pub mod moonshot;
+#[cfg(feature = "providers")]
 pub mod ollama;
+#[cfg(feature = "providers")]
 pub mod openai;
+#[cfg(feature = "providers")]
+pub mod openai_compatible;
+#[cfg(feature = "providers")]
 pub mod perplexity;
+#[cfg(feature = "providers")]
 pub mod together;
+#[cfg(feature = "providers")]
 pub mod xai;
@@ -176,6 +176,10 @@ impl EmbeddingModel {
 
 impl embeddings::EmbeddingModel for EmbeddingModel {
     const MAX_DOCUMENTS: usize = 1024;
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
     fn ndims(&self) -> usize {
         self.ndims
     }
@@ -209,7 +213,7 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
             Ok(api_resp
                 .embeddings
                 .into_iter()
-                .zip(docs.into_iter())
+                .zip(docs)
                 .map(|(vec, document)| embeddings::Embedding { document, vec })
                 .collect())
         } else {
@@ -272,6 +276,12 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
                 let choice = OneOrMany::many(assistant_contents).map_err(|_| {
                     CompletionError::ResponseError("No content provided".to_owned())
                 })?;
+                let usage = match (resp.prompt_eval_count, resp.eval_count) {
+                    (Some(input_tokens), Some(output_tokens)) => {
+                        Some(completion::Usage::new(input_tokens, output_tokens))
+                    }
+                    _ => None,
+                };
                 let raw_response = CompletionResponse {
                     model: resp.model,
                     created_at: resp.created_at,
@@ -292,6 +302,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
                 };
                 Ok(completion::CompletionResponse {
                     choice,
+                    usage,
                     raw_response,
                 })
             }
@@ -324,6 +335,10 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -8,17 +8,21 @@
 //!
 //! let gpt4o = client.completion_model(openai::GPT_4O);
 //! ```
-use std::{convert::Infallible, str::FromStr};
+use std::{collections::HashMap, convert::Infallible, future::Future, str::FromStr, sync::Arc};
 
 use crate::{
     agent::AgentBuilder,
     completion::{self, CompletionError, CompletionRequest},
     embeddings::{self, EmbeddingError, EmbeddingsBuilder},
     extractor::ExtractorBuilder,
-    json_utils,
+    guardrails::Guard,
+    http_client::HttpConfig,
+    image_generation, json_utils,
     message::{self, AudioMediaType, ImageDetail},
+    middleware::{HttpClient, Middleware, MiddlewareDyn, RequestBuilder},
     one_or_many::string_or_one_or_many,
-    Embed, OneOrMany,
+    runtime::WasmCompatSend,
+    transcription, tts, Embed, OneOrMany,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -29,10 +33,88 @@ use serde_json::json;
 // ================================================================
 const OPENAI_API_BASE_URL: &str = "https://api.openai.com/v1";
 
+/// Create a new OpenAI client using the builder, e.g.: to add [Middleware] to the client.
+///
+/// # Example
+/// ```
+/// use rig::providers::openai;
+///
+/// // Initialize the OpenAI client
+/// let openai = openai::Client::builder("your-open-ai-api-key")
+///     .base_url("https://my-gateway.example.com/v1")
+///     .with_middleware(audit_log)
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct ClientBuilder<'a> {
+    api_key: &'a str,
+    base_url: &'a str,
+    headers: Vec<(&'a str, &'a str)>,
+    middleware: Vec<Arc<dyn MiddlewareDyn>>,
+    http: HttpConfig,
+}
+
+impl<'a> ClientBuilder<'a> {
+    pub fn new(api_key: &'a str) -> Self {
+        Self {
+            api_key,
+            base_url: OPENAI_API_BASE_URL,
+            headers: vec![],
+            middleware: vec![],
+            http: HttpConfig::default(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: &'a str) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// Add a header to the requests of the client (e.g.: required by an OpenAI-compatible API).
+    pub fn header(mut self, name: &'a str, value: &'a str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Add a middleware to the client, run on its requests after the middleware added before it.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Set the TLS settings of the client (e.g.: to trust a private certificate authority).
+    #[cfg(all(
+        not(target_arch = "wasm32"),
+        any(feature = "native-tls", feature = "rustls-tls")
+    ))]
+    pub fn tls(mut self, tls: crate::http_client::TlsConfig) -> Self {
+        self.http.tls = tls;
+        self
+    }
+
+    /// Set the connection pool settings of the client (e.g.: for high-QPS services).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool(mut self, pool: crate::http_client::PoolConfig) -> Self {
+        self.http.pool = pool;
+        self
+    }
+
+    pub fn build(self) -> Client {
+        let mut client = Client::from_builder(
+            self.http.apply(reqwest::Client::builder()),
+            self.api_key,
+            self.base_url,
+            &self.headers,
+        );
+        client.http_client = client.http_client.with_middleware(self.middleware);
+        client
+    }
+}
+
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
-    http_client: reqwest::Client,
+    http_client: HttpClient,
 }
 
 impl Client {
@@ -41,23 +123,49 @@ impl Client {
         Self::from_url(api_key, OPENAI_API_BASE_URL)
     }
 
+    /// Create a new OpenAI client builder with the given API key.
+    pub fn builder(api_key: &str) -> ClientBuilder<'_> {
+        ClientBuilder::new(api_key)
+    }
+
     /// Create a new OpenAI client with the given API key and base API URL.
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        Self::from_builder(reqwest::Client::builder(), api_key, base_url, &[])
+    }
+
+    /// Client sending `api_key` as bearer token, unless it is empty (e.g.: for local servers)
+    fn from_builder(
+        builder: reqwest::ClientBuilder,
+        api_key: &str,
+        base_url: &str,
+        extra_headers: &[(&str, &str)],
+    ) -> Self {
         Self {
             base_url: base_url.to_string(),
-            http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
-                .build()
-                .expect("OpenAI reqwest client should build"),
+            http_client: HttpClient::new(
+                builder
+                    .default_headers({
+                        let mut headers = reqwest::header::HeaderMap::new();
+                        if !api_key.is_empty() {
+                            headers.insert(
+                                "Authorization",
+                                format!("Bearer {}", api_key)
+                                    .parse()
+                                    .expect("Bearer token should parse"),
+                            );
+                        }
+                        for (name, value) in extra_headers {
+                            headers.insert(
+                                reqwest::header::HeaderName::from_str(name)
+                                    .expect("Header name should parse"),
+                                value.parse().expect("Header value should parse"),
+                            );
+                        }
+                        headers
+                    })
+                    .build()
+                    .expect("OpenAI reqwest client should build"),
+            ),
         }
     }
 
@@ -68,11 +176,16 @@ impl Client {
         Self::new(&api_key)
     }
 
-    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+    fn post(&self, path: &str) -> RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
     }
 
+    fn get(&self, path: &str) -> RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path).replace("//", "/");
+        self.http_client.get(url)
+    }
+
     /// Create an embedding model with the given name.
     /// Note: default embedding dimension of 0 will be used if model is not known.
     /// If this is the case, it's better to use function `embedding_model_with_ndims`
@@ -170,6 +283,98 @@ impl Client {
     ) -> ExtractorBuilder<T, CompletionModel> {
         ExtractorBuilder::new(self.completion_model(model))
     }
+
+    /// Create a transcription model with the given name.
+    ///
+    /// # Example
+    /// ```
+    /// use rig::providers::openai::{Client, self};
+    ///
+    /// // Initialize the OpenAI client
+    /// let openai = Client::new("your-open-ai-api-key");
+    ///
+    /// let whisper = openai.transcription_model(openai::WHISPER_1);
+    /// ```
+    pub fn transcription_model(&self, model: &str) -> TranscriptionModel {
+        TranscriptionModel::new(self.clone(), model)
+    }
+
+    /// Create a text-to-speech model with the given name.
+    ///
+    /// # Example
+    /// ```
+    /// use rig::providers::openai::{Client, self};
+    ///
+    /// // Initialize the OpenAI client
+    /// let openai = Client::new("your-open-ai-api-key");
+    ///
+    /// let tts = openai.speech_model(openai::TTS_1);
+    /// ```
+    pub fn speech_model(&self, model: &str) -> SpeechModel {
+        SpeechModel::new(self.clone(), model)
+    }
+
+    /// Create an image generation model with the given name.
+    ///
+    /// # Example
+    /// ```
+    /// use rig::providers::openai::{Client, self};
+    ///
+    /// // Initialize the OpenAI client
+    /// let openai = Client::new("your-open-ai-api-key");
+    ///
+    /// let dall_e = openai.image_generation_model(openai::DALL_E_3);
+    /// ```
+    pub fn image_generation_model(&self, model: &str) -> ImageGenerationModel {
+        ImageGenerationModel::new(self.clone(), model)
+    }
+
+    /// Create a moderation model with the given name.
+    ///
+    /// # Example
+    /// ```
+    /// use rig::providers::openai::{Client, self};
+    ///
+    /// // Initialize the OpenAI client
+    /// let openai = Client::new("your-open-ai-api-key");
+    ///
+    /// let moderation = openai.moderation_model(openai::OMNI_MODERATION_LATEST);
+    /// let result = moderation.moderate("I will hurt you").await?;
+    /// ```
+    pub fn moderation_model(&self, model: &str) -> ModerationModel {
+        ModerationModel::new(self.clone(), model)
+    }
+
+    /// List the ids of the models available from the API.
+    #[allow(clippy::manual_async_fn)] // `Send` on native targets only, see WasmCompatSend
+    pub fn list_models(
+        &self,
+    ) -> impl Future<Output = Result<Vec<String>, CompletionError>> + WasmCompatSend + '_ {
+        async move {
+            let response = self.get("/models").send().await?;
+
+            if response.status().is_success() {
+                match response.json::<ApiResponse<ModelList>>().await? {
+                    ApiResponse::Ok(list) => {
+                        Ok(list.data.into_iter().map(|model| model.id).collect())
+                    }
+                    ApiResponse::Err(err) => Err(CompletionError::ProviderError(err.message)),
+                }
+            } else {
+                Err(CompletionError::ProviderError(response.text().await?))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    id: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -224,7 +429,7 @@ pub struct EmbeddingData {
     pub index: usize,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Usage {
     pub prompt_tokens: usize,
     pub total_tokens: usize,
@@ -240,6 +445,18 @@ impl std::fmt::Display for Usage {
     }
 }
 
+impl From<&Usage> for completion::Usage {
+    fn from(usage: &Usage) -> Self {
+        let prompt_tokens = usage.prompt_tokens as u64;
+        let total_tokens = usage.total_tokens as u64;
+        completion::Usage {
+            input_tokens: prompt_tokens,
+            output_tokens: total_tokens.saturating_sub(prompt_tokens),
+            total_tokens,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EmbeddingModel {
     client: Client,
@@ -250,16 +467,35 @@ pub struct EmbeddingModel {
 impl embeddings::EmbeddingModel for EmbeddingModel {
     const MAX_DOCUMENTS: usize = 1024;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     fn ndims(&self) -> usize {
         self.ndims
     }
 
+    fn limits(&self) -> embeddings::EmbeddingLimits {
+        embeddings::EmbeddingLimits::new(Self::MAX_DOCUMENTS)
+            .max_input_tokens(8191)
+            .max_request_tokens(300_000)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn embed_texts(
         &self,
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
         let documents = documents.into_iter().collect::<Vec<_>>();
+        Ok(self.embed_texts_with_usage(documents).await?.0)
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn embed_texts_with_usage(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<(Vec<embeddings::Embedding>, Option<completion::Usage>), EmbeddingError> {
+        let documents = documents.into_iter().collect::<Vec<_>>();
 
         let response = self
             .client
@@ -285,15 +521,17 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                         ));
                     }
 
-                    Ok(response
+                    let usage = (&response.usage).into();
+                    let embeddings = response
                         .data
                         .into_iter()
-                        .zip(documents.into_iter())
+                        .zip(documents)
                         .map(|(embedding, document)| embeddings::Embedding {
                             document,
                             vec: embedding.embedding,
                         })
-                        .collect())
+                        .collect();
+                    Ok((embeddings, Some(usage)))
                 }
                 ApiResponse::Err(err) => Err(EmbeddingError::ProviderError(err.message)),
             }
@@ -369,7 +607,7 @@ pub const GPT_35_TURBO_1106: &str = "gpt-3.5-turbo-1106";
 /// `gpt-3.5-turbo-instruct` completion model
 pub const GPT_35_TURBO_INSTRUCT: &str = "gpt-3.5-turbo-instruct";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CompletionResponse {
     pub id: String,
     pub object: String,
@@ -437,6 +675,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
 
         Ok(completion::CompletionResponse {
             choice,
+            usage: response.usage.as_ref().map(Into::into),
             raw_response: response,
         })
     }
@@ -522,9 +761,16 @@ pub enum AssistantContent {
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum UserContent {
-    Text { text: String },
-    Image { image_url: ImageUrl },
-    Audio { input_audio: InputAudio },
+    Text {
+        text: String,
+    },
+    #[serde(rename = "image_url", alias = "image")]
+    Image {
+        image_url: ImageUrl,
+    },
+    Audio {
+        input_audio: InputAudio,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -608,10 +854,10 @@ pub struct Function {
     pub arguments: serde_json::Value,
 }
 
-impl TryFrom<message::Message> for Vec<Message> {
-    type Error = message::MessageError;
-
-    fn try_from(message: message::Message) -> Result<Self, Self::Error> {
+impl Message {
+    /// Converts a message of Rig to the messages of the provider, which may take several messages
+    /// (e.g.: one per tool result).
+    pub fn try_from_message(message: message::Message) -> Result<Vec<Self>, message::MessageError> {
         match message {
             message::Message::User { content } => {
                 let (tool_results, other_content): (Vec<_>, Vec<_>) = content
@@ -647,36 +893,36 @@ impl TryFrom<message::Message> for Vec<Message> {
                     );
 
                     Ok(vec![Message::User {
-                        content: other_content.map(|content| match content {
-                            message::UserContent::Text(message::Text { text }) => {
-                                UserContent::Text { text }
-                            }
-                            message::UserContent::Image(message::Image {
-                                data, detail, ..
-                            }) => UserContent::Image {
-                                image_url: ImageUrl {
-                                    url: data,
-                                    detail: detail.unwrap_or_default(),
+                        content: other_content.try_map(|content| {
+                            Ok::<_, message::MessageError>(match content {
+                                message::UserContent::Text(message::Text { text }) => {
+                                    UserContent::Text { text }
+                                }
+                                message::UserContent::Image(image) => UserContent::Image {
+                                    image_url: ImageUrl {
+                                        url: image.to_url()?,
+                                        detail: image.detail.unwrap_or_default(),
+                                    },
                                 },
-                            },
-                            message::UserContent::Document(message::Document { data, .. }) => {
-                                UserContent::Text { text: data }
-                            }
-                            message::UserContent::Audio(message::Audio {
-                                data,
-                                media_type,
-                                ..
-                            }) => UserContent::Audio {
-                                input_audio: InputAudio {
+                                message::UserContent::Document(message::Document {
+                                    data, ..
+                                }) => UserContent::Text { text: data },
+                                message::UserContent::Audio(message::Audio {
                                     data,
-                                    format: match media_type {
-                                        Some(media_type) => media_type,
-                                        None => AudioMediaType::MP3,
+                                    media_type,
+                                    ..
+                                }) => UserContent::Audio {
+                                    input_audio: InputAudio {
+                                        data,
+                                        format: match media_type {
+                                            Some(media_type) => media_type,
+                                            None => AudioMediaType::MP3,
+                                        },
                                     },
                                 },
-                            },
-                            _ => unreachable!(),
-                        }),
+                                _ => unreachable!(),
+                            })
+                        })?,
                         name: None,
                     }])
                 }
@@ -804,11 +1050,8 @@ impl From<UserContent> for message::UserContent {
     fn from(content: UserContent) -> Self {
         match content {
             UserContent::Text { text } => message::UserContent::text(text),
-            UserContent::Image { image_url } => message::UserContent::image(
-                image_url.url,
-                Some(message::ContentFormat::default()),
-                None,
-                Some(image_url.detail),
+            UserContent::Image { image_url } => message::UserContent::Image(
+                message::Image::from_url(image_url.url).detail(image_url.detail),
             ),
             UserContent::Audio { input_audio } => message::UserContent::audio(
                 input_audio.data,
@@ -889,6 +1132,22 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        structured_output(name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        Some(json_mode())
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -901,13 +1160,14 @@ impl completion::CompletionModel for CompletionModel {
         };
 
         // Convert prompt to user message
-        let prompt: Vec<Message> = completion_request.prompt_with_context().try_into()?;
+        let prompt: Vec<Message> =
+            Message::try_from_message(completion_request.prompt_with_context())?;
 
         // Convert existing chat history
         let chat_history: Vec<Message> = completion_request
             .chat_history
             .into_iter()
-            .map(|message| message.try_into())
+            .map(Message::try_from_message)
             .collect::<Result<Vec<Vec<Message>>, _>>()?
             .into_iter()
             .flatten()
@@ -978,6 +1238,677 @@ impl completion::CompletionModel for CompletionModel {
     }
 }
 
+// ================================================================
+// OpenAI Transcription API
+// ================================================================
+/// `whisper-1` transcription model
+pub const WHISPER_1: &str = "whisper-1";
+/// `gpt-4o-transcribe` transcription model
+pub const GPT_4O_TRANSCRIBE: &str = "gpt-4o-transcribe";
+/// `gpt-4o-mini-transcribe` transcription model
+pub const GPT_4O_MINI_TRANSCRIBE: &str = "gpt-4o-mini-transcribe";
+
+#[derive(Debug, Deserialize)]
+pub struct TranscriptionResponse {
+    pub text: String,
+}
+
+#[derive(Clone)]
+pub struct TranscriptionModel {
+    client: Client,
+    /// Name of the model (e.g.: whisper-1)
+    pub model: String,
+}
+
+impl TranscriptionModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+}
+
+impl transcription::TranscriptionModel for TranscriptionModel {
+    type Response = TranscriptionResponse;
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn transcription(
+        &self,
+        request: transcription::TranscriptionRequest,
+    ) -> Result<
+        transcription::TranscriptionResponse<TranscriptionResponse>,
+        transcription::TranscriptionError,
+    > {
+        let mut form = reqwest::multipart::Form::new()
+            .text("model", self.model.clone())
+            .text("response_format", "json")
+            .part(
+                "file",
+                reqwest::multipart::Part::bytes(request.data).file_name(request.filename),
+            );
+        if let Some(language) = request.language {
+            form = form.text("language", language);
+        }
+        if let Some(prompt) = request.prompt {
+            form = form.text("prompt", prompt);
+        }
+        if let Some(temperature) = request.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        if let Some(serde_json::Value::Object(params)) = request.additional_params {
+            for (key, value) in params {
+                form = match value {
+                    serde_json::Value::String(value) => form.text(key, value),
+                    value => form.text(key, value.to_string()),
+                };
+            }
+        }
+
+        let response = self
+            .client
+            .post("/audio/transcriptions")
+            .multipart(form)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            match response
+                .json::<ApiResponse<TranscriptionResponse>>()
+                .await?
+            {
+                ApiResponse::Ok(response) => Ok(transcription::TranscriptionResponse {
+                    text: response.text.clone(),
+                    response,
+                }),
+                ApiResponse::Err(err) => Err(transcription::TranscriptionError::ProviderError(
+                    err.message,
+                )),
+            }
+        } else {
+            Err(transcription::TranscriptionError::ProviderError(
+                response.text().await?,
+            ))
+        }
+    }
+}
+
+// ================================================================
+// OpenAI Text-to-Speech API
+// ================================================================
+/// `tts-1` text-to-speech model, optimized for latency
+pub const TTS_1: &str = "tts-1";
+/// `tts-1-hd` text-to-speech model, optimized for quality
+pub const TTS_1_HD: &str = "tts-1-hd";
+/// `gpt-4o-mini-tts` text-to-speech model, following instructions on the way to speak
+pub const GPT_4O_MINI_TTS: &str = "gpt-4o-mini-tts";
+
+/// Voice of the speech when the request sets none
+const DEFAULT_VOICE: &str = "alloy";
+
+#[derive(Clone)]
+pub struct SpeechModel {
+    client: Client,
+    /// Name of the model (e.g.: tts-1)
+    pub model: String,
+}
+
+impl SpeechModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+
+    /// Body of the request of `request`, with the format of its audio.
+    fn speech_body(
+        &self,
+        request: tts::SpeechRequest,
+    ) -> Result<(serde_json::Value, message::AudioMediaType), tts::SpeechError> {
+        let media_type = request.format.unwrap_or(message::AudioMediaType::MP3);
+        let format = match media_type {
+            message::AudioMediaType::MP3 => "mp3",
+            message::AudioMediaType::WAV => "wav",
+            message::AudioMediaType::AAC => "aac",
+            message::AudioMediaType::FLAC => "flac",
+            // Opus audio, in an Ogg container
+            message::AudioMediaType::OGG => "opus",
+            ref other => {
+                return Err(tts::SpeechError::RequestError(
+                    format!("Unsupported speech format: {other:?}").into(),
+                ))
+            }
+        };
+
+        let mut body = json!({
+            "model": self.model,
+            "input": request.text,
+            "voice": request.voice.as_deref().unwrap_or(DEFAULT_VOICE),
+            "response_format": format,
+        });
+        if let Some(speed) = request.speed {
+            body["speed"] = json!(speed);
+        }
+        if let Some(instructions) = request.instructions {
+            body["instructions"] = json!(instructions);
+        }
+        if let Some(params) = request.additional_params {
+            body = json_utils::merge(body, params);
+        }
+        Ok((body, media_type))
+    }
+
+    async fn speech_response(
+        &self,
+        request: tts::SpeechRequest,
+    ) -> Result<(reqwest::Response, message::AudioMediaType), tts::SpeechError> {
+        let (body, media_type) = self.speech_body(request)?;
+        let response = self.client.post("/audio/speech").json(&body).send().await?;
+
+        if response.status().is_success() {
+            Ok((response, media_type))
+        } else {
+            Err(tts::SpeechError::ProviderError(response.text().await?))
+        }
+    }
+}
+
+impl tts::SpeechModel for SpeechModel {
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn speech(
+        &self,
+        request: tts::SpeechRequest,
+    ) -> Result<tts::SpeechResponse, tts::SpeechError> {
+        let (response, media_type) = self.speech_response(request).await?;
+        Ok(tts::SpeechResponse {
+            audio: response.bytes().await?.to_vec(),
+            media_type,
+        })
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn speech_stream(
+        &self,
+        request: tts::SpeechRequest,
+    ) -> Result<tts::SpeechStream, tts::SpeechError> {
+        use futures::StreamExt;
+
+        let (response, _) = self.speech_response(request).await?;
+        let stream: tts::SpeechStream = Box::pin(
+            response
+                .bytes_stream()
+                .map(|chunk| chunk.map_err(tts::SpeechError::from)),
+        );
+        Ok(stream)
+    }
+}
+
+// ================================================================
+// OpenAI Image Generation API
+// ================================================================
+/// `dall-e-2` image generation model
+pub const DALL_E_2: &str = "dall-e-2";
+/// `dall-e-3` image generation model
+pub const DALL_E_3: &str = "dall-e-3";
+/// `gpt-image-1` image generation model
+pub const GPT_IMAGE_1: &str = "gpt-image-1";
+
+#[derive(Debug, Deserialize)]
+pub struct ImageGenerationResponse {
+    pub created: u64,
+    pub data: Vec<ImageData>,
+}
+
+/// Generated image, given by its URL or its base64-encoded data depending on the
+/// `response_format` of the request
+#[derive(Debug, Deserialize)]
+pub struct ImageData {
+    pub url: Option<String>,
+    pub b64_json: Option<String>,
+    /// Prompt used to generate the image, if it was revised by the model (dall-e-3)
+    pub revised_prompt: Option<String>,
+}
+
+impl ImageGenerationResponse {
+    /// Convert the generated images, whose base64-encoded data has the given media type.
+    fn images(
+        &self,
+        media_type: message::ImageMediaType,
+    ) -> Result<Vec<message::Image>, image_generation::ImageGenerationError> {
+        self.data
+            .iter()
+            .map(|image| match (&image.b64_json, &image.url) {
+                (Some(data), _) => Ok(message::Image::base64(data, media_type.clone())),
+                (None, Some(url)) => Ok(message::Image::url(url)),
+                (None, None) => Err(image_generation::ImageGenerationError::ResponseError(
+                    "Generated image has neither a URL nor data".into(),
+                )),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct ImageGenerationModel {
+    client: Client,
+    /// Name of the model (e.g.: dall-e-3)
+    pub model: String,
+}
+
+impl ImageGenerationModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+}
+
+impl image_generation::ImageGenerationModel for ImageGenerationModel {
+    type Response = ImageGenerationResponse;
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn image_generation(
+        &self,
+        request: image_generation::ImageGenerationRequest,
+    ) -> Result<
+        image_generation::ImageGenerationResponse<ImageGenerationResponse>,
+        image_generation::ImageGenerationError,
+    > {
+        let mut body = json!({
+            "model": self.model,
+            "prompt": request.prompt,
+            "n": request.n,
+        });
+        if let Some((width, height)) = request.size {
+            body["size"] = json!(format!("{width}x{height}"));
+        }
+        if let Some(params) = request.additional_params {
+            body = json_utils::merge(body, params);
+        }
+
+        // Images are PNGs unless another output format is requested (gpt-image-1)
+        let media_type = match body["output_format"].as_str() {
+            Some("jpeg") => message::ImageMediaType::JPEG,
+            Some("webp") => message::ImageMediaType::WEBP,
+            _ => message::ImageMediaType::PNG,
+        };
+
+        let response = self
+            .client
+            .post("/images/generations")
+            .json(&body)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            match response
+                .json::<ApiResponse<ImageGenerationResponse>>()
+                .await?
+            {
+                ApiResponse::Ok(response) => Ok(image_generation::ImageGenerationResponse {
+                    images: response.images(media_type)?,
+                    response,
+                }),
+                ApiResponse::Err(err) => Err(
+                    image_generation::ImageGenerationError::ProviderError(err.message),
+                ),
+            }
+        } else {
+            Err(image_generation::ImageGenerationError::ProviderError(
+                response.text().await?,
+            ))
+        }
+    }
+}
+
+// ================================================================
+// OpenAI Moderation API
+// ================================================================
+/// `omni-moderation-latest` moderation model, for text and images
+pub const OMNI_MODERATION_LATEST: &str = "omni-moderation-latest";
+/// `text-moderation-latest` moderation model (legacy)
+pub const TEXT_MODERATION_LATEST: &str = "text-moderation-latest";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModerationError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Error parsing the moderation response
+    #[error("ResponseError: {0}")]
+    ResponseError(String),
+
+    /// Error returned by the OpenAI API
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerationResponse {
+    pub id: String,
+    pub model: String,
+    pub results: Vec<ModerationResult>,
+}
+
+/// Moderation of a text, with the categories of harmful content (e.g.: `harassment`,
+/// `self-harm/intent`, `violence`) it was flagged for and their scores (from 0 to 1).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModerationResult {
+    /// Whether the text was flagged for any category
+    pub flagged: bool,
+    pub categories: HashMap<String, bool>,
+    pub category_scores: HashMap<String, f64>,
+}
+
+impl ModerationResult {
+    /// Categories the text was flagged for, sorted by name.
+    pub fn flagged_categories(&self) -> Vec<&str> {
+        let mut categories = self
+            .categories
+            .iter()
+            .filter(|(_, flagged)| **flagged)
+            .map(|(category, _)| category.as_str())
+            .collect::<Vec<_>>();
+        categories.sort_unstable();
+        categories
+    }
+
+    /// Categories whose score is at least `threshold`, sorted by name.
+    pub fn categories_above(&self, threshold: f64) -> Vec<&str> {
+        let mut categories = self
+            .category_scores
+            .iter()
+            .filter(|(_, score)| **score >= threshold)
+            .map(|(category, _)| category.as_str())
+            .collect::<Vec<_>>();
+        categories.sort_unstable();
+        categories
+    }
+}
+
+#[derive(Clone)]
+pub struct ModerationModel {
+    client: Client,
+    /// Name of the model (e.g.: omni-moderation-latest)
+    pub model: String,
+}
+
+impl ModerationModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+
+    /// Classify `input` as potentially harmful or not.
+    #[allow(clippy::manual_async_fn)] // `Send` on native targets only, see WasmCompatSend
+    pub fn moderate<'a>(
+        &'a self,
+        input: &'a str,
+    ) -> impl Future<Output = Result<ModerationResult, ModerationError>> + WasmCompatSend + 'a {
+        async move {
+            let response = self
+                .client
+                .post("/moderations")
+                .json(&json!({
+                    "model": self.model,
+                    "input": input,
+                }))
+                .send()
+                .await?;
+
+            if response.status().is_success() {
+                match response.json::<ApiResponse<ModerationResponse>>().await? {
+                    ApiResponse::Ok(response) => {
+                        response.results.into_iter().next().ok_or_else(|| {
+                            ModerationError::ResponseError(
+                                "Moderation response has no result".into(),
+                            )
+                        })
+                    }
+                    ApiResponse::Err(err) => Err(ModerationError::ProviderError(err.message)),
+                }
+            } else {
+                Err(ModerationError::ProviderError(response.text().await?))
+            }
+        }
+    }
+
+    /// Create a guard screening texts with this model, see [ModerationGuard].
+    pub fn guard(&self, policy: ModerationPolicy) -> ModerationGuard {
+        ModerationGuard {
+            model: self.clone(),
+            policy,
+        }
+    }
+}
+
+/// What a [ModerationGuard] does with harmful texts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModerationPolicy {
+    /// Reject the texts flagged by the model
+    Block,
+    /// Reject the texts with a category score of at least the threshold (from 0 to 1), e.g.: to
+    /// be stricter than the flags of the model
+    BlockAbove(f64),
+    /// Log a warning for the texts flagged by the model, without rejecting them
+    Log,
+}
+
+impl ModerationPolicy {
+    /// Reason to reject a text given its moderation, if any
+    fn violation(&self, result: &ModerationResult) -> Option<String> {
+        let categories = match self {
+            Self::Block | Self::Log if result.flagged => result.flagged_categories(),
+            Self::BlockAbove(threshold) => result.categories_above(*threshold),
+            _ => return None,
+        };
+        match (self, categories.is_empty()) {
+            (_, true) => None,
+            (Self::Log, false) => {
+                tracing::warn!(target: "rig", "Text flagged by moderation: {}", categories.join(", "));
+                None
+            }
+            (_, false) => Some(format!(
+                "the text was flagged for {}",
+                categories.join(", ")
+            )),
+        }
+    }
+}
+
+/// [Guard] screening the prompts or responses of an agent with the OpenAI moderation API.
+///
+/// The texts whose moderation fails are rejected, unless the policy is
+/// [ModerationPolicy::Log].
+#[derive(Clone)]
+pub struct ModerationGuard {
+    model: ModerationModel,
+    policy: ModerationPolicy,
+}
+
+impl Guard for ModerationGuard {
+    fn name(&self) -> &str {
+        "openai_moderation"
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn check(&self, text: String) -> Result<String, String> {
+        match self.model.moderate(&text).await {
+            Ok(result) => match self.policy.violation(&result) {
+                Some(reason) => Err(reason),
+                None => Ok(text),
+            },
+            Err(e) if self.policy == ModerationPolicy::Log => {
+                tracing::warn!(target: "rig", "Moderation failed: {e}");
+                Ok(text)
+            }
+            Err(e) => Err(format!("the moderation failed: {e}")),
+        }
+    }
+}
+
+impl AgentBuilder<CompletionModel> {
+    /// Screen the prompts and the responses of the agent with the
+    /// [OMNI_MODERATION_LATEST] moderation model of its client, e.g.:
+    /// `.moderation(ModerationPolicy::Block)` to reject harmful prompts and responses with a
+    /// [GuardrailViolation](crate::guardrails::GuardrailViolation).
+    ///
+    /// Use [ModerationModel::guard] as an input or output guard to only screen the prompts or
+    /// the responses, or to use another moderation model.
+    pub fn moderation(self, policy: ModerationPolicy) -> Self {
+        let guard = self
+            .model()
+            .client
+            .moderation_model(OMNI_MODERATION_LATEST)
+            .guard(policy);
+        self.input_guard(guard.clone()).output_guard(guard)
+    }
+}
+
+// ================================================================
+// Structured outputs
+// ================================================================
+/// Keywords not supported by strict structured outputs
+const UNSUPPORTED_STRICT_KEYWORDS: [&str; 13] = [
+    "$schema",
+    "default",
+    "format",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minItems",
+    "maxItems",
+];
+
+/// `response_format` parameter constraining the response to be a JSON object (i.e.: JSON mode).
+pub(crate) fn json_mode() -> serde_json::Value {
+    json!({
+        "response_format": {"type": "json_object"}
+    })
+}
+
+/// `response_format` parameter constraining the response to match `schema` in strict mode,
+/// or `None` if the schema cannot be used in strict mode (i.e.: its root is not an object).
+pub(crate) fn structured_output(
+    name: &str,
+    schema: &serde_json::Value,
+) -> Option<serde_json::Value> {
+    Some(json!({
+        "response_format": {
+            "type": "json_schema",
+            "json_schema": {
+                "name": name,
+                "schema": strict_schema(schema)?,
+                "strict": true,
+            }
+        }
+    }))
+}
+
+/// Convert a JSON schema (e.g.: generated by `schemars`) to a schema supported by OpenAI's strict
+/// structured outputs: every property of an object is required (optional properties are made
+/// nullable instead), additional properties are forbidden and unsupported keywords are removed.
+/// Returns `None` if the root of the schema is not an object.
+pub fn strict_schema(schema: &serde_json::Value) -> Option<serde_json::Value> {
+    schema.get("properties")?;
+
+    let mut schema = schema.clone();
+    if let Some(definitions) = schema
+        .as_object_mut()
+        .and_then(|schema| schema.remove("definitions"))
+    {
+        schema["$defs"] = definitions;
+    }
+    make_strict(&mut schema);
+    Some(schema)
+}
+
+fn make_strict(schema: &mut serde_json::Value) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+    UNSUPPORTED_STRICT_KEYWORDS.iter().for_each(|keyword| {
+        object.remove(*keyword);
+    });
+    if let Some(one_of) = object.remove("oneOf") {
+        object.insert("anyOf".to_string(), one_of);
+    }
+    if let Some(serde_json::Value::String(reference)) = object.get_mut("$ref") {
+        *reference = reference.replacen("#/definitions/", "#/$defs/", 1);
+    }
+
+    if object
+        .get("properties")
+        .is_some_and(serde_json::Value::is_object)
+    {
+        let required = object
+            .get("required")
+            .and_then(serde_json::Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let properties = object
+            .get_mut("properties")
+            .and_then(serde_json::Value::as_object_mut)
+            .expect("properties is an object");
+        for (name, property) in properties.iter_mut() {
+            if !required.contains(&serde_json::Value::String(name.clone())) {
+                make_nullable(property);
+            }
+        }
+        // Sorted, as the order of the properties depends on the `preserve_order` feature of
+        // serde_json
+        let mut names = properties.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        object.insert("required".to_string(), json!(names));
+        object.insert("additionalProperties".to_string(), json!(false));
+    }
+
+    for keyword in ["properties", "$defs"] {
+        if let Some(serde_json::Value::Object(schemas)) = object.get_mut(keyword) {
+            schemas.values_mut().for_each(make_strict);
+        }
+    }
+    for keyword in ["anyOf", "allOf", "items"] {
+        match object.get_mut(keyword) {
+            Some(serde_json::Value::Array(schemas)) => schemas.iter_mut().for_each(make_strict),
+            Some(schema) => make_strict(schema),
+            None => {}
+        }
+    }
+}
+
+/// Allow `null` values for a schema
+fn make_nullable(schema: &mut serde_json::Value) {
+    let null_type = json!("null");
+    match schema.get_mut("type") {
+        Some(serde_json::Value::Array(types)) if types.contains(&null_type) => return,
+        Some(serde_json::Value::Array(types)) => types.push(null_type),
+        Some(ty @ serde_json::Value::String(_)) => *ty = json!([ty.clone(), null_type]),
+        _ => {
+            let any_of = schema.get("anyOf").and_then(serde_json::Value::as_array);
+            if !any_of.is_some_and(|any_of| any_of.contains(&json!({"type": "null"}))) {
+                *schema = json!({"anyOf": [schema.clone(), {"type": "null"}]});
+            }
+            return;
+        }
+    }
+    if let Some(serde_json::Value::Array(values)) = schema.get_mut("enum") {
+        if !values.contains(&serde_json::Value::Null) {
+            values.push(serde_json::Value::Null);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1183,9 +2114,10 @@ mod tests {
             content: OneOrMany::one(message::AssistantContent::text("Hi there!")),
         };
 
-        let converted_user_message: Vec<Message> = user_message.clone().try_into().unwrap();
+        let converted_user_message: Vec<Message> =
+            Message::try_from_message(user_message.clone()).unwrap();
         let converted_assistant_message: Vec<Message> =
-            assistant_message.clone().try_into().unwrap();
+            Message::try_from_message(assistant_message.clone()).unwrap();
 
         match converted_user_message[0].clone() {
             Message::User { content, .. } => {
@@ -1260,11 +2192,318 @@ mod tests {
             _ => panic!("Expected assistant message"),
         }
 
-        let original_user_message: Vec<Message> = converted_user_message.try_into().unwrap();
+        let original_user_message: Vec<Message> =
+            Message::try_from_message(converted_user_message).unwrap();
         let original_assistant_message: Vec<Message> =
-            converted_assistant_message.try_into().unwrap();
+            Message::try_from_message(converted_assistant_message).unwrap();
 
         assert_eq!(original_user_message[0], user_message);
         assert_eq!(original_assistant_message[0], assistant_message);
     }
+
+    #[test]
+    fn test_strict_schema() {
+        let schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "nickname": {"type": "string"},
+                "kind": {"$ref": "#/definitions/Kind"},
+                "tags": {"type": "array", "items": {"type": "string", "format": "uri"}},
+            },
+            "required": ["name", "kind", "tags"],
+            "definitions": {
+                "Kind": {"oneOf": [{"type": "string", "enum": ["a", "b"]}]},
+            },
+        });
+
+        assert_eq!(
+            strict_schema(&schema).unwrap(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "nickname": {"type": ["string", "null"]},
+                    "kind": {"$ref": "#/$defs/Kind"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                },
+                "required": ["kind", "name", "nickname", "tags"],
+                "additionalProperties": false,
+                "$defs": {
+                    "Kind": {"anyOf": [{"type": "string", "enum": ["a", "b"]}]},
+                },
+            })
+        );
+        assert!(strict_schema(&json!({"type": "string"})).is_none());
+    }
+
+    #[test]
+    fn test_image_message_conversion() {
+        let message = message::Message::user_with_image(
+            "What's in this screenshot?",
+            message::Image::base64("iVBORw0KGgo", message::ImageMediaType::PNG)
+                .detail(ImageDetail::High),
+        );
+        let converted: Vec<Message> = Message::try_from_message(message.clone()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&converted).unwrap(),
+            serde_json::json!([{
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "What's in this screenshot?"},
+                    {
+                        "type": "image_url",
+                        "image_url": {"url": "data:image/png;base64,iVBORw0KGgo", "detail": "high"}
+                    }
+                ]
+            }])
+        );
+        let round_trip: message::Message =
+            converted.into_iter().next().unwrap().try_into().unwrap();
+        assert_eq!(round_trip, message);
+
+        let url = message::Message::from(message::Image::url("https://example.com/cat.jpg"));
+        let converted: Vec<Message> = Message::try_from_message(url).unwrap();
+        assert_eq!(
+            serde_json::to_value(&converted).unwrap()[0]["content"][0]["image_url"]["url"],
+            "https://example.com/cat.jpg"
+        );
+
+        let untyped = message::Message::from(message::Image {
+            data: "iVBORw0KGgo".into(),
+            ..Default::default()
+        });
+        assert!(Message::try_from_message(untyped).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transcription_errors() {
+        use httpmock::{Method::POST, MockServer};
+
+        use crate::transcription::{TranscriptionError, TranscriptionModel as _};
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/audio/transcriptions")
+                .body_contains("short.wav");
+            then.status(200)
+                .json_body(json!({"message": "Audio file is too short"}));
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/audio/transcriptions")
+                .body_contains("note.wav");
+            then.status(401).body("Invalid API key");
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/audio/transcriptions")
+                .body_contains("note.mp3");
+            then.status(200).body("Buy milk");
+        });
+
+        let model = Client::from_url("key", &server.base_url()).transcription_model(WHISPER_1);
+        let transcription = |filename| {
+            model
+                .transcription_request()
+                .data(vec![0; 8], filename)
+                .send()
+        };
+        // Errors are returned in the body of successful responses
+        assert!(matches!(
+            transcription("short.wav").await,
+            Err(TranscriptionError::ProviderError(message)) if message == "Audio file is too short"
+        ));
+        assert!(matches!(
+            transcription("note.wav").await,
+            Err(TranscriptionError::ProviderError(message)) if message == "Invalid API key"
+        ));
+        // Transcriptions requested as text instead of json can't be parsed
+        assert!(matches!(
+            transcription("note.mp3").await,
+            Err(TranscriptionError::HttpError(_))
+        ));
+    }
+
+    #[test]
+    fn test_image_generation_response() {
+        let response: ImageGenerationResponse = serde_json::from_value(json!({
+            "created": 1713833628,
+            "data": [
+                {"b64_json": "iVBORw0KGgo"},
+                {"url": "https://example.com/image.png", "revised_prompt": "A rusty crab"}
+            ]
+        }))
+        .unwrap();
+
+        let images = response.images(message::ImageMediaType::WEBP).unwrap();
+        assert_eq!(
+            images,
+            vec![
+                message::Image::base64("iVBORw0KGgo", message::ImageMediaType::WEBP),
+                message::Image::url("https://example.com/image.png"),
+            ]
+        );
+        assert_eq!(
+            response.data[1].revised_prompt.as_deref(),
+            Some("A rusty crab")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_image_generation_errors() {
+        use httpmock::{Method::POST, MockServer};
+
+        use crate::image_generation::{ImageGenerationError, ImageGenerationModel as _};
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/images/generations")
+                .json_body_partial(r#"{"prompt": "A crab"}"#);
+            then.status(200)
+                .json_body(json!({"created": 1713833628, "data": [{}]}));
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/images/generations")
+                .json_body_partial(r#"{"prompt": "A violent crab"}"#);
+            then.status(200)
+                .json_body(json!({"message": "Your request was rejected by the safety system"}));
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/images/generations")
+                .json_body_partial(r#"{"prompt": "A giant crab"}"#);
+            then.status(400).body("Invalid size");
+        });
+
+        let model = Client::from_url("key", &server.base_url()).image_generation_model(DALL_E_3);
+        let generate = |prompt| model.image_generation_request().prompt(prompt).send();
+        // Images without a URL nor data can't be returned
+        assert!(matches!(
+            generate("A crab").await,
+            Err(ImageGenerationError::ResponseError(_))
+        ));
+        assert!(matches!(
+            generate("A violent crab").await,
+            Err(ImageGenerationError::ProviderError(message)) if message.contains("safety system")
+        ));
+        assert!(matches!(
+            generate("A giant crab").await,
+            Err(ImageGenerationError::ProviderError(message)) if message == "Invalid size"
+        ));
+    }
+
+    #[test]
+    fn test_speech_body() {
+        use crate::tts::SpeechModel as _;
+
+        let model = Client::new("key").speech_model(GPT_4O_MINI_TTS);
+        let request = model
+            .speech_request()
+            .text("Hello")
+            .format(message::AudioMediaType::OGG)
+            .instructions("Whisper")
+            .additional_params(json!({"voice": "nova"}))
+            .build();
+        let (body, media_type) = model.speech_body(request).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "model": "gpt-4o-mini-tts",
+                "input": "Hello",
+                "voice": "nova",
+                "response_format": "opus",
+                "instructions": "Whisper",
+            })
+        );
+        assert_eq!(media_type, message::AudioMediaType::OGG);
+
+        let request = model.speech_request().text("Hello").build();
+        let (body, _) = model.speech_body(request).unwrap();
+        assert_eq!(body["voice"], "alloy");
+        assert_eq!(body["response_format"], "mp3");
+
+        let request = model
+            .speech_request()
+            .format(message::AudioMediaType::AIFF)
+            .build();
+        assert!(matches!(
+            model.speech_body(request),
+            Err(tts::SpeechError::RequestError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_speech_errors() {
+        use httpmock::{Method::POST, MockServer};
+
+        use crate::tts::SpeechModel as _;
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/audio/speech");
+            then.status(400).body("Invalid voice");
+        });
+
+        let model = Client::builder("key")
+            .base_url(&server.base_url())
+            .build()
+            .speech_model(TTS_1);
+        let request = || model.speech_request().text("Hello").voice("robot");
+        assert!(matches!(
+            request().send().await,
+            Err(tts::SpeechError::ProviderError(message)) if message == "Invalid voice"
+        ));
+        assert!(matches!(
+            request().stream().await,
+            Err(tts::SpeechError::ProviderError(message)) if message == "Invalid voice"
+        ));
+    }
+
+    #[test]
+    fn test_moderation_policy() {
+        let response: ModerationResponse = serde_json::from_str(
+            r#"
+            {
+                "id": "modr-123",
+                "model": "omni-moderation-latest",
+                "results": [
+                    {
+                        "flagged": true,
+                        "categories": {"violence": true, "harassment": true, "hate": false},
+                        "category_scores": {"violence": 0.91, "harassment": 0.62, "hate": 0.12},
+                        "category_applied_input_types": {"violence": ["text"]}
+                    }
+                ]
+            }
+            "#,
+        )
+        .unwrap();
+        let result = &response.results[0];
+        assert_eq!(result.flagged_categories(), vec!["harassment", "violence"]);
+
+        assert_eq!(
+            ModerationPolicy::Block.violation(result),
+            Some("the text was flagged for harassment, violence".to_string())
+        );
+        assert_eq!(
+            ModerationPolicy::BlockAbove(0.1).violation(result),
+            Some("the text was flagged for harassment, hate, violence".to_string())
+        );
+        assert_eq!(ModerationPolicy::Log.violation(result), None);
+
+        let result = ModerationResult {
+            flagged: false,
+            ..result.clone()
+        };
+        assert_eq!(ModerationPolicy::Block.violation(&result), None);
+        assert_eq!(
+            ModerationPolicy::BlockAbove(0.9).violation(&result),
+            Some("the text was flagged for violence".to_string())
+        );
+    }
 }
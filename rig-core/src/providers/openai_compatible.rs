@@ -0,0 +1,334 @@
+//! Client of the providers and servers implementing the OpenAI API (e.g.: Groq, Together AI,
+//! Fireworks AI, OpenRouter, xAI, or a vLLM server), using the OpenAI models of Rig.
+//!
+//! The client is an [openai::Client] with a configurable base URL, optional extra headers (e.g.:
+//! the attribution headers of OpenRouter), and an optional list of the models known to be
+//! served, so that any OpenAI-compatible API works without a dedicated provider module. The
+//! constructors named after providers (e.g.: [Client::groq]) preset their base URLs.
+//!
+//! # Example
+//! ```
+//! use rig::providers::openai_compatible;
+//!
+//! let groq = openai_compatible::Client::groq("YOUR_API_KEY");
+//! let llama = groq.agent("llama-3.3-70b-versatile").build();
+//!
+//! // Any other OpenAI-compatible API, e.g.: a local vLLM server without API key
+//! let vllm = openai_compatible::Client::builder("", "http://localhost:8000/v1")
+//!     .header("X-Tenant", "acme")
+//!     .models(&["meta-llama/Llama-3.1-8B-Instruct"])
+//!     .build();
+//! ```
+use std::future::Future;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agent::AgentBuilder,
+    completion::CompletionError,
+    embeddings::EmbeddingsBuilder,
+    extractor::ExtractorBuilder,
+    middleware::Middleware,
+    providers::openai::{self, CompletionModel, EmbeddingModel},
+    runtime::WasmCompatSend,
+    Embed,
+};
+
+pub const GROQ_API_BASE_URL: &str = "https://api.groq.com/openai/v1";
+pub const TOGETHER_API_BASE_URL: &str = "https://api.together.xyz/v1";
+pub const FIREWORKS_API_BASE_URL: &str = "https://api.fireworks.ai/inference/v1";
+pub const OPENROUTER_API_BASE_URL: &str = "https://openrouter.ai/api/v1";
+pub const XAI_API_BASE_URL: &str = "https://api.x.ai/v1";
+/// Default base URL of a local vLLM server
+pub const VLLM_API_BASE_URL: &str = "http://localhost:8000/v1";
+
+/// Create a new OpenAI-compatible client using the builder.
+///
+/// # Example
+/// ```
+/// use rig::providers::openai_compatible;
+///
+/// let openrouter = openai_compatible::Client::builder(
+///     "your-openrouter-api-key",
+///     openai_compatible::OPENROUTER_API_BASE_URL,
+/// )
+/// .header("HTTP-Referer", "https://myapp.example.com")
+/// .header("X-Title", "My App")
+/// .build();
+/// ```
+#[derive(Clone)]
+pub struct ClientBuilder<'a> {
+    inner: openai::ClientBuilder<'a>,
+    models: Vec<String>,
+}
+
+impl<'a> ClientBuilder<'a> {
+    /// Create a builder of a client of the API at `base_url`, authenticated with `api_key` as
+    /// bearer token (unless it is empty, e.g.: for local servers).
+    pub fn new(api_key: &'a str, base_url: &'a str) -> Self {
+        Self {
+            inner: openai::ClientBuilder::new(api_key).base_url(base_url),
+            models: vec![],
+        }
+    }
+
+    /// Add a header to the requests of the client.
+    pub fn header(mut self, name: &'a str, value: &'a str) -> Self {
+        self.inner = self.inner.header(name, value);
+        self
+    }
+
+    /// Set the models known to be served by the API. Creating a model missing from a non-empty
+    /// list logs a warning (e.g.: for a typo in its name).
+    pub fn models(mut self, models: &[&str]) -> Self {
+        self.models = models.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// Add a middleware to the client, run on its requests after the middleware added before it.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.inner = self.inner.with_middleware(middleware);
+        self
+    }
+
+    /// Set the TLS settings of the client (e.g.: to trust the certificate of a private server).
+    #[cfg(all(
+        not(target_arch = "wasm32"),
+        any(feature = "native-tls", feature = "rustls-tls")
+    ))]
+    pub fn tls(mut self, tls: crate::http_client::TlsConfig) -> Self {
+        self.inner = self.inner.tls(tls);
+        self
+    }
+
+    /// Set the connection pool settings of the client.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool(mut self, pool: crate::http_client::PoolConfig) -> Self {
+        self.inner = self.inner.pool(pool);
+        self
+    }
+
+    pub fn build(self) -> Client {
+        Client {
+            inner: self.inner.build(),
+            models: self.models,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Client {
+    inner: openai::Client,
+    models: Vec<String>,
+}
+
+impl Client {
+    /// Create a new client of the OpenAI-compatible API at `base_url`.
+    pub fn new(api_key: &str, base_url: &str) -> Self {
+        Self::builder(api_key, base_url).build()
+    }
+
+    /// Create a new builder of a client of the OpenAI-compatible API at `base_url`.
+    pub fn builder<'a>(api_key: &'a str, base_url: &'a str) -> ClientBuilder<'a> {
+        ClientBuilder::new(api_key, base_url)
+    }
+
+    /// Create a new client of the API at `base_url` from the `var` environment variable.
+    /// Panics if the environment variable is not set.
+    pub fn from_env(var: &str, base_url: &str) -> Self {
+        let api_key = std::env::var(var).unwrap_or_else(|_| panic!("{var} not set"));
+        Self::new(&api_key, base_url)
+    }
+
+    /// Create a new Groq client with the given API key.
+    pub fn groq(api_key: &str) -> Self {
+        Self::new(api_key, GROQ_API_BASE_URL)
+    }
+
+    /// Create a new Together AI client with the given API key.
+    pub fn together(api_key: &str) -> Self {
+        Self::new(api_key, TOGETHER_API_BASE_URL)
+    }
+
+    /// Create a new Fireworks AI client with the given API key.
+    pub fn fireworks(api_key: &str) -> Self {
+        Self::new(api_key, FIREWORKS_API_BASE_URL)
+    }
+
+    /// Create a new OpenRouter client with the given API key.
+    pub fn openrouter(api_key: &str) -> Self {
+        Self::new(api_key, OPENROUTER_API_BASE_URL)
+    }
+
+    /// Create a new xAI client with the given API key.
+    pub fn xai(api_key: &str) -> Self {
+        Self::new(api_key, XAI_API_BASE_URL)
+    }
+
+    /// Create a new client of a vLLM server at `base_url` (e.g.: [VLLM_API_BASE_URL]), started
+    /// without API key.
+    pub fn vllm(base_url: &str) -> Self {
+        Self::new("", base_url)
+    }
+
+    /// Models known to be served by the API, as set with [ClientBuilder::models]
+    pub fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    /// List the ids of the models served by the API, from its `/models` endpoint.
+    #[allow(clippy::manual_async_fn)] // `Send` on native targets only, see WasmCompatSend
+    pub fn list_models(
+        &self,
+    ) -> impl Future<Output = Result<Vec<String>, CompletionError>> + WasmCompatSend + '_ {
+        async move { self.inner.list_models().await }
+    }
+
+    /// The underlying OpenAI client, e.g.: to use the other OpenAI models supported by the API.
+    pub fn openai(&self) -> &openai::Client {
+        &self.inner
+    }
+
+    /// Warn if `model` is missing from the known models
+    fn check_model(&self, model: &str) {
+        if !self.models.is_empty() && !self.models.iter().any(|known| known == model) {
+            tracing::warn!(target: "rig",
+                "Model {model} is not one of the known models of the API: {}",
+                self.models.join(", ")
+            );
+        }
+    }
+
+    /// Create a completion model with the given name.
+    pub fn completion_model(&self, model: &str) -> CompletionModel {
+        self.check_model(model);
+        self.inner.completion_model(model)
+    }
+
+    /// Create an embedding model with the given name and number of dimensions.
+    pub fn embedding_model(&self, model: &str, ndims: usize) -> EmbeddingModel {
+        self.check_model(model);
+        self.inner.embedding_model_with_ndims(model, ndims)
+    }
+
+    /// Create an embedding builder with the given embedding model and number of dimensions.
+    pub fn embeddings<D: Embed>(
+        &self,
+        model: &str,
+        ndims: usize,
+    ) -> EmbeddingsBuilder<EmbeddingModel, D> {
+        EmbeddingsBuilder::new(self.embedding_model(model, ndims))
+    }
+
+    /// Create an agent builder with the given completion model.
+    pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
+        AgentBuilder::new(self.completion_model(model))
+    }
+
+    /// Create an extractor builder with the given completion model.
+    pub fn extractor<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync>(
+        &self,
+        model: &str,
+    ) -> ExtractorBuilder<T, CompletionModel> {
+        ExtractorBuilder::new(self.completion_model(model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::GET, Method::POST, MockServer};
+    use serde_json::json;
+
+    use super::*;
+    use crate::completion::Prompt;
+
+    #[tokio::test]
+    async fn test_openai_compatible_client() {
+        let server = MockServer::start();
+        let completion = server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/chat/completions")
+                .header("authorization", "Bearer key")
+                .header("x-title", "Rig")
+                .json_body_partial(r#"{"model": "llama-3.3-70b"}"#);
+            then.status(200).json_body(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "llama-3.3-70b",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hello!"},
+                    "finish_reason": "stop"
+                }]
+            }));
+        });
+        let models = server.mock(|when, then| {
+            when.method(GET).path("/v1/models");
+            then.status(200).json_body(json!({
+                "object": "list",
+                "data": [{"id": "llama-3.3-70b", "object": "model", "owned_by": "meta"}]
+            }));
+        });
+
+        let base_url = server.url("/v1");
+        let client = Client::builder("key", &base_url)
+            .header("X-Title", "Rig")
+            .models(&["llama-3.3-70b"])
+            .build();
+        assert_eq!(client.models(), ["llama-3.3-70b"]);
+        assert_eq!(client.list_models().await.unwrap(), ["llama-3.3-70b"]);
+        models.assert();
+
+        let agent = client.agent("llama-3.3-70b").build();
+        assert_eq!(agent.prompt("Hi").await.unwrap(), "Hello!");
+        completion.assert();
+
+        // Clients without API key send no authorization header
+        let local = server.mock(|when, then| {
+            when.method(GET).path("/local/models").matches(|request| {
+                !request
+                    .headers
+                    .iter()
+                    .flatten()
+                    .any(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+            });
+            then.status(200)
+                .json_body(json!({"object": "list", "data": []}));
+        });
+        let client = Client::vllm(&server.url("/local"));
+        assert!(client.list_models().await.unwrap().is_empty());
+        local.assert();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_models() {
+        let server = MockServer::start();
+        let completion = server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/chat/completions")
+                .json_body_partial(r#"{"model": "mixtral-8x7b"}"#);
+            then.status(503).body("Model is loading");
+        });
+
+        // Models missing from the known ones are only warned about, and still requested
+        let client = Client::builder("key", &server.url("/v1"))
+            .models(&["llama-3.3-70b"])
+            .build();
+        let agent = client.agent("mixtral-8x7b").build();
+        assert!(matches!(
+            agent.prompt("Hi").await,
+            Err(crate::completion::PromptError::CompletionError(
+                CompletionError::ProviderError(message)
+            )) if message == "Model is loading"
+        ));
+        completion.assert();
+    }
+
+    #[test]
+    #[should_panic(expected = "RIG_TEST_MISSING_API_KEY not set")]
+    fn test_from_env_without_key() {
+        Client::from_env("RIG_TEST_MISSING_API_KEY", "http://localhost:8000/v1");
+    }
+}
@@ -103,7 +103,7 @@ pub const SONAR_PRO: &str = "sonar-pro";
 /// `sonar` completion model
 pub const SONAR: &str = "sonar";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CompletionResponse {
     pub id: String,
     pub model: String,
@@ -128,13 +128,13 @@ pub enum Role {
     Assistant,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Serialize)]
 pub struct Delta {
     pub role: Role,
     pub content: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Serialize)]
 pub struct Choice {
     pub index: usize,
     pub finish_reason: String,
@@ -142,7 +142,7 @@ pub struct Choice {
     pub delta: Delta,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Serialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
@@ -159,6 +159,16 @@ impl std::fmt::Display for Usage {
     }
 }
 
+impl From<&Usage> for completion::Usage {
+    fn from(usage: &Usage) -> Self {
+        completion::Usage {
+            input_tokens: usage.prompt_tokens as u64,
+            output_tokens: usage.completion_tokens as u64,
+            total_tokens: usage.total_tokens as u64,
+        }
+    }
+}
+
 impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionResponse> {
     type Error = CompletionError;
 
@@ -173,6 +183,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
                 content,
             } => Ok(completion::CompletionResponse {
                 choice: OneOrMany::one(content.clone().into()),
+                usage: Some((&response.usage).into()),
                 raw_response: response,
             }),
             _ => Err(CompletionError::ResponseError(
@@ -260,6 +271,10 @@ impl From<Message> for message::Message {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -374,9 +389,8 @@ mod tests {
         assert_eq!(converted_assistant_message.role, Role::Assistant);
         assert_eq!(converted_assistant_message.content, "Assistant message");
 
-        let back_to_user_message: message::Message = converted_user_message.try_into().unwrap();
-        let back_to_assistant_message: message::Message =
-            converted_assistant_message.try_into().unwrap();
+        let back_to_user_message: message::Message = converted_user_message.into();
+        let back_to_assistant_message: message::Message = converted_assistant_message.into();
 
         assert_eq!(user_message, back_to_user_message);
         assert_eq!(assistant_message, back_to_assistant_message);
@@ -144,6 +144,14 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = openai::CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        Some(openai::json_mode())
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -155,13 +163,14 @@ impl completion::CompletionModel for CompletionModel {
         };
 
         // Convert prompt to user message
-        let prompt: Vec<openai::Message> = completion_request.prompt_with_context().try_into()?;
+        let prompt: Vec<openai::Message> =
+            openai::Message::try_from_message(completion_request.prompt_with_context())?;
 
         // Convert existing chat history
         let chat_history: Vec<openai::Message> = completion_request
             .chat_history
             .into_iter()
-            .map(|message| message.try_into())
+            .map(openai::Message::try_from_message)
             .collect::<Result<Vec<Vec<openai::Message>>, _>>()?
             .into_iter()
             .flatten()
@@ -72,6 +72,10 @@ pub struct EmbeddingModel {
 impl embeddings::EmbeddingModel for EmbeddingModel {
     const MAX_DOCUMENTS: usize = 1024; // This might need to be adjusted based on Together AI's actual limit
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     fn ndims(&self) -> usize {
         self.ndims
     }
@@ -105,7 +109,7 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                     Ok(response
                         .data
                         .into_iter()
-                        .zip(documents.into_iter())
+                        .zip(documents)
                         .map(|(embedding, document)| embeddings::Embedding {
                             document,
                             vec: embedding.embedding,
@@ -39,6 +39,14 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        Some(crate::providers::openai::json_mode())
+    }
+
     #[cfg_attr(feature = "worker", worker::send)]
     async fn completion(
         &self,
@@ -57,13 +65,14 @@ impl completion::CompletionModel for CompletionModel {
         };
 
         // Convert prompt to user message
-        let prompt: Vec<Message> = completion_request.prompt_with_context().try_into()?;
+        let prompt: Vec<Message> =
+            Message::try_from_message(completion_request.prompt_with_context())?;
 
         // Convert existing chat history
         let chat_history: Vec<Message> = completion_request
             .chat_history
             .into_iter()
-            .map(|message| message.try_into())
+            .map(Message::try_from_message)
             .collect::<Result<Vec<Vec<Message>>, _>>()?
             .into_iter()
             .flatten()
@@ -172,6 +181,7 @@ pub mod xai_api_types {
 
             Ok(completion::CompletionResponse {
                 choice,
+                usage: Some((&response.usage).into()),
                 raw_response: response,
             })
         }
@@ -198,7 +208,7 @@ pub mod xai_api_types {
         pub arguments: String,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct CompletionResponse {
         pub id: String,
         pub model: String,
@@ -209,17 +219,27 @@ pub mod xai_api_types {
         pub usage: Usage,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct Choice {
         pub finish_reason: String,
         pub index: i32,
         pub message: Message,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Deserialize, Serialize)]
     pub struct Usage {
         pub completion_tokens: i32,
         pub prompt_tokens: i32,
         pub total_tokens: i32,
     }
+
+    impl From<&Usage> for completion::Usage {
+        fn from(usage: &Usage) -> Self {
+            completion::Usage {
+                input_tokens: usage.prompt_tokens as u64,
+                output_tokens: usage.completion_tokens as u64,
+                total_tokens: usage.total_tokens as u64,
+            }
+        }
+    }
 }
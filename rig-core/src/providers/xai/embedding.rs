@@ -6,7 +6,10 @@
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::embeddings::{self, EmbeddingError};
+use crate::{
+    completion,
+    embeddings::{self, EmbeddingError},
+};
 
 use super::{
     client::xai_api_types::{ApiErrorResponse, ApiResponse},
@@ -55,6 +58,18 @@ pub struct Usage {
     pub total_tokens: usize,
 }
 
+impl From<&Usage> for completion::Usage {
+    fn from(usage: &Usage) -> Self {
+        let prompt_tokens = usage.prompt_tokens as u64;
+        let total_tokens = usage.total_tokens as u64;
+        completion::Usage {
+            input_tokens: prompt_tokens,
+            output_tokens: total_tokens.saturating_sub(prompt_tokens),
+            total_tokens,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EmbeddingModel {
     client: Client,
@@ -65,6 +80,10 @@ pub struct EmbeddingModel {
 impl embeddings::EmbeddingModel for EmbeddingModel {
     const MAX_DOCUMENTS: usize = 1024;
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     fn ndims(&self) -> usize {
         self.ndims
     }
@@ -75,6 +94,15 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
         let documents = documents.into_iter().collect::<Vec<_>>();
+        Ok(self.embed_texts_with_usage(documents).await?.0)
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn embed_texts_with_usage(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<(Vec<embeddings::Embedding>, Option<completion::Usage>), EmbeddingError> {
+        let documents = documents.into_iter().collect::<Vec<_>>();
 
         let response = self
             .client
@@ -95,15 +123,17 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                         ));
                     }
 
-                    Ok(response
+                    let usage = (&response.usage).into();
+                    let embeddings = response
                         .data
                         .into_iter()
-                        .zip(documents.into_iter())
+                        .zip(documents)
                         .map(|(embedding, document)| embeddings::Embedding {
                             document,
                             vec: embedding.embedding,
                         })
-                        .collect())
+                        .collect();
+                    Ok((embeddings, Some(usage)))
                 }
                 ApiResponse::Error(err) => Err(EmbeddingError::ProviderError(err.message())),
             }
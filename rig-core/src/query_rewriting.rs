@@ -0,0 +1,197 @@
+//! Rewriting of the follow-up prompts of a conversation into standalone retrieval queries.
+//!
+//! In a multi-turn RAG conversation, follow-up prompts such as "what about the second one?"
+//! retrieve unrelated documents, as they only make sense along with the conversation. A
+//! [QueryRewriter] asks a completion model to rewrite them into standalone queries using the
+//! chat history. An agent with a rewriter
+//! ([AgentBuilder::query_rewriter](crate::agent::AgentBuilder::query_rewriter)) retrieves its
+//! dynamic context and tools with the rewritten query, while its model still answers the
+//! original prompt.
+//!
+//! # Example
+//! ```rust
+//! use rig::{memory::BufferHistory, providers::openai, query_rewriting::QueryRewriter};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .dynamic_context(5, index)
+//!     .memory(BufferHistory::new(10))
+//!     .query_rewriter(QueryRewriter::new(openai.completion_model(openai::GPT_4O_MINI)))
+//!     .build();
+//! ```
+use futures::future::BoxFuture;
+
+use crate::{
+    completion::{AssistantContent, CompletionError, CompletionModel, Message},
+    memory::transcript_line,
+};
+
+const REWRITER_PREAMBLE: &str = "\
+You rewrite the last message of a conversation into a standalone search query, used to retrieve \
+the documents needed to answer it. Resolve the references to the earlier messages (e.g.: \
+pronouns, \"the second one\") and keep the terms of the message. Respond with the query only.";
+
+/// Rewriter of follow-up prompts into standalone queries, using a completion model.
+pub struct QueryRewriter<M: CompletionModel> {
+    model: M,
+    preamble: String,
+    max_messages: usize,
+}
+
+impl<M: CompletionModel> QueryRewriter<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            preamble: REWRITER_PREAMBLE.to_string(),
+            max_messages: 6,
+        }
+    }
+
+    /// Set the instructions of the model rewriting the queries, e.g.: to rewrite them in the
+    /// language of the documents.
+    pub fn preamble(mut self, preamble: &str) -> Self {
+        self.preamble = preamble.to_string();
+        self
+    }
+
+    /// Set the number of most recent messages of the chat history sent to the model (defaults
+    /// to 6).
+    pub fn max_messages(mut self, max_messages: usize) -> Self {
+        self.max_messages = max_messages;
+        self
+    }
+
+    /// Rewrite `query` into a standalone query using `chat_history`. The query is returned as
+    /// is when there is no chat history, or when the model answers with an empty query.
+    pub async fn rewrite(
+        &self,
+        query: &str,
+        chat_history: &[Message],
+    ) -> Result<String, CompletionError> {
+        let recent = &chat_history[chat_history.len().saturating_sub(self.max_messages)..];
+        if recent.is_empty() {
+            return Ok(query.to_string());
+        }
+        let transcript = recent
+            .iter()
+            .map(transcript_line)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let response = self
+            .model
+            .completion_request(Message::user(format!(
+                "Conversation:\n{transcript}\n\nLast message:\n{query}"
+            )))
+            .preamble(self.preamble.clone())
+            .temperature(0.0)
+            .send()
+            .await?;
+
+        let rewritten = response
+            .choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.trim()),
+                AssistantContent::ToolCall(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        match rewritten.trim() {
+            "" => Ok(query.to_string()),
+            rewritten => Ok(rewritten.to_string()),
+        }
+    }
+}
+
+/// Wrapper trait to store rewriters of any completion model
+pub(crate) trait QueryRewriterDyn: Send + Sync {
+    fn rewrite_dyn<'a>(
+        &'a self,
+        query: &'a str,
+        chat_history: &'a [Message],
+    ) -> BoxFuture<'a, Result<String, CompletionError>>;
+}
+
+impl<M: CompletionModel> QueryRewriterDyn for QueryRewriter<M> {
+    fn rewrite_dyn<'a>(
+        &'a self,
+        query: &'a str,
+        chat_history: &'a [Message],
+    ) -> BoxFuture<'a, Result<String, CompletionError>> {
+        Box::pin(self.rewrite(query, chat_history))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{memory::message_text, providers::mock::MockCompletionModel};
+
+    #[tokio::test]
+    async fn test_rewrite() {
+        let model = MockCompletionModel::new().text(" Population of Lyon\n");
+        let rewriter = QueryRewriter::new(model.clone()).max_messages(2);
+
+        // No chat history to resolve the query with
+        assert_eq!(
+            rewriter.rewrite("What about Lyon?", &[]).await.unwrap(),
+            "What about Lyon?"
+        );
+        assert!(model.requests().is_empty());
+
+        let chat_history = [
+            Message::user("Hi"),
+            Message::assistant("Hello!"),
+            Message::user("What is the population of Paris?"),
+            Message::assistant("About 2 million."),
+        ];
+        assert_eq!(
+            rewriter
+                .rewrite("What about Lyon?", &chat_history)
+                .await
+                .unwrap(),
+            "Population of Lyon"
+        );
+        let request = model.last_request().unwrap();
+        assert_eq!(
+            message_text(&request.prompt),
+            "Conversation:\nUser: What is the population of Paris?\nAssistant: About 2 \
+            million.\n\nLast message:\nWhat about Lyon?"
+        );
+        assert_eq!(request.temperature, Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_errors() {
+        let model = MockCompletionModel::new()
+            .error("Overloaded")
+            .text("  ")
+            .tool_call("search", json!({"query": "Lyon"}));
+        let rewriter = QueryRewriter::new(model.clone()).preamble("Rewrite in French.");
+        let chat_history = [Message::user("What is the population of Paris?")];
+
+        assert!(matches!(
+            rewriter.rewrite("What about Lyon?", &chat_history).await,
+            Err(CompletionError::ProviderError(message)) if message == "Overloaded"
+        ));
+        assert_eq!(
+            model.last_request().unwrap().preamble.as_deref(),
+            Some("Rewrite in French.")
+        );
+
+        // Empty queries, or answers without text, leave the query as is
+        for _ in 0..2 {
+            assert_eq!(
+                rewriter
+                    .rewrite("What about Lyon?", &chat_history)
+                    .await
+                    .unwrap(),
+                "What about Lyon?"
+            );
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! Registry of model providers, to pick models from configuration at runtime.
+//!
+//! A [ModelRegistry] maps provider names (e.g.: `openai`) to functions creating the models of
+//! the provider. Models are then identified by `provider:model` strings (e.g.:
+//! `openai:gpt-4o`, `anthropic:claude-3-5-sonnet-latest` or `ollama:llama3.2`), and are
+//! returned as [DynCompletionModel]s and [DynEmbeddingModel]s, so that the same application code
+//! works with any of them.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, registry::ModelRegistry};
+//!
+//! // Providers whose API key is set in the environment, e.g.: OPENAI_API_KEY
+//! let registry = ModelRegistry::from_env();
+//!
+//! let agent = registry
+//!     .agent(&config.model)? // e.g.: "anthropic:claude-3-5-sonnet-latest"
+//!     .preamble("You are a helpful assistant.")
+//!     .build();
+//! let response = agent.prompt("Hello!").await?;
+//!
+//! let embedding_model = registry.embedding_model("openai:text-embedding-3-small")?;
+//! ```
+use std::collections::HashMap;
+
+use crate::{
+    agent::AgentBuilder,
+    completion::{CompletionModel, DynCompletionModel},
+    embeddings::{DynEmbeddingModel, EmbeddingModel},
+};
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum RegistryError {
+    /// Model identifier not of the form `provider:model`
+    #[error("Invalid model id (expected `provider:model`): {0}")]
+    InvalidModelId(String),
+
+    /// No provider registered with this name
+    #[error("Unknown provider: {0}")]
+    UnknownProvider(String),
+}
+
+type CompletionFactory = Box<dyn Fn(&str) -> DynCompletionModel + Send + Sync>;
+type EmbeddingFactory = Box<dyn Fn(&str) -> DynEmbeddingModel + Send + Sync>;
+
+/// Registry of the completion and embedding model providers of an application.
+#[derive(Default)]
+pub struct ModelRegistry {
+    completion_providers: HashMap<String, CompletionFactory>,
+    embedding_providers: HashMap<String, EmbeddingFactory>,
+}
+
+impl ModelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry of the built-in providers whose API key is set in the environment (e.g.:
+    /// `openai` if `OPENAI_API_KEY` is set), along with `ollama`, which doesn't require one.
+    #[cfg(feature = "providers")]
+    pub fn from_env() -> Self {
+        use crate::providers::*;
+
+        let has_key = |var: &str| std::env::var(var).is_ok();
+        let mut registry = Self::new();
+
+        if has_key("OPENAI_API_KEY") {
+            let client = openai::Client::from_env();
+            registry = registry
+                .completion_provider("openai", {
+                    let client = client.clone();
+                    move |model| client.completion_model(model)
+                })
+                .embedding_provider("openai", move |model| client.embedding_model(model));
+        }
+        if has_key("ANTHROPIC_API_KEY") {
+            let client = anthropic::Client::from_env();
+            registry = registry
+                .completion_provider("anthropic", move |model| client.completion_model(model));
+        }
+        if has_key("COHERE_API_KEY") {
+            let client = cohere::Client::from_env();
+            registry = registry
+                .completion_provider("cohere", {
+                    let client = client.clone();
+                    move |model| client.completion_model(model)
+                })
+                .embedding_provider("cohere", move |model| {
+                    client.embedding_model(model, "search_document")
+                });
+        }
+        if has_key("GEMINI_API_KEY") {
+            let client = gemini::Client::from_env();
+            registry = registry
+                .completion_provider("gemini", {
+                    let client = client.clone();
+                    move |model| client.completion_model(model)
+                })
+                .embedding_provider("gemini", move |model| client.embedding_model(model));
+        }
+        if has_key("XAI_API_KEY") {
+            let client = xai::Client::from_env();
+            registry = registry
+                .completion_provider("xai", {
+                    let client = client.clone();
+                    move |model| client.completion_model(model)
+                })
+                .embedding_provider("xai", move |model| client.embedding_model(model));
+        }
+        if has_key("TOGETHER_API_KEY") {
+            let client = together::Client::from_env();
+            registry = registry
+                .completion_provider("together", {
+                    let client = client.clone();
+                    move |model| client.completion_model(model)
+                })
+                .embedding_provider("together", move |model| client.embedding_model(model));
+        }
+        if has_key("PERPLEXITY_API_KEY") {
+            let client = perplexity::Client::from_env();
+            registry = registry
+                .completion_provider("perplexity", move |model| client.completion_model(model));
+        }
+        if has_key("GROQ_API_KEY") {
+            let client = groq::Client::from_env();
+            registry =
+                registry.completion_provider("groq", move |model| client.completion_model(model));
+        }
+        if has_key("DEEPSEEK_API_KEY") {
+            let client = deepseek::Client::from_env();
+            registry = registry
+                .completion_provider("deepseek", move |model| client.completion_model(model));
+        }
+        if has_key("HYPERBOLIC_API_KEY") {
+            let client = hyperbolic::Client::from_env();
+            registry = registry
+                .completion_provider("hyperbolic", move |model| client.completion_model(model));
+        }
+        if has_key("MOONSHOT_API_KEY") {
+            let client = moonshot::Client::from_env();
+            registry = registry
+                .completion_provider("moonshot", move |model| client.completion_model(model));
+        }
+        if has_key("GALADRIEL_API_KEY") {
+            let client = galadriel::Client::from_env();
+            registry = registry
+                .completion_provider("galadriel", move |model| client.completion_model(model));
+        }
+
+        let client = ollama::Client::new();
+        registry
+            .completion_provider("ollama", {
+                let client = client.clone();
+                move |model| client.completion_model(model)
+            })
+            .embedding_provider("ollama", move |model| client.embedding_model(model))
+    }
+
+    /// Register (or replace) a completion model provider, creating the models from their names.
+    pub fn completion_provider<M>(
+        mut self,
+        provider: &str,
+        factory: impl Fn(&str) -> M + Send + Sync + 'static,
+    ) -> Self
+    where
+        M: CompletionModel + 'static,
+        M::Response: 'static,
+    {
+        self.completion_providers.insert(
+            provider.to_string(),
+            Box::new(move |model| DynCompletionModel::new(factory(model))),
+        );
+        self
+    }
+
+    /// Register (or replace) an embedding model provider, creating the models from their names.
+    pub fn embedding_provider<M: EmbeddingModel + 'static>(
+        mut self,
+        provider: &str,
+        factory: impl Fn(&str) -> M + Send + Sync + 'static,
+    ) -> Self {
+        self.embedding_providers.insert(
+            provider.to_string(),
+            Box::new(move |model| DynEmbeddingModel::new(factory(model))),
+        );
+        self
+    }
+
+    /// Names of the registered completion model providers
+    pub fn completion_providers(&self) -> impl Iterator<Item = &str> {
+        self.completion_providers.keys().map(String::as_str)
+    }
+
+    /// Names of the registered embedding model providers
+    pub fn embedding_providers(&self) -> impl Iterator<Item = &str> {
+        self.embedding_providers.keys().map(String::as_str)
+    }
+
+    /// Completion model identified by `id` (e.g.: `openai:gpt-4o`).
+    pub fn completion_model(&self, id: &str) -> Result<DynCompletionModel, RegistryError> {
+        let (provider, model) = parse_id(id)?;
+        self.completion_providers
+            .get(provider)
+            .map(|factory| factory(model))
+            .ok_or_else(|| RegistryError::UnknownProvider(provider.to_string()))
+    }
+
+    /// Embedding model identified by `id` (e.g.: `openai:text-embedding-3-small`).
+    pub fn embedding_model(&self, id: &str) -> Result<DynEmbeddingModel, RegistryError> {
+        let (provider, model) = parse_id(id)?;
+        self.embedding_providers
+            .get(provider)
+            .map(|factory| factory(model))
+            .ok_or_else(|| RegistryError::UnknownProvider(provider.to_string()))
+    }
+
+    /// Agent builder for the completion model identified by `id` (e.g.: `openai:gpt-4o`).
+    pub fn agent(&self, id: &str) -> Result<AgentBuilder<DynCompletionModel>, RegistryError> {
+        Ok(AgentBuilder::new(self.completion_model(id)?))
+    }
+}
+
+/// Split a `provider:model` id. Model names may contain colons (e.g.: `ollama:llama3.2:1b`).
+fn parse_id(id: &str) -> Result<(&str, &str), RegistryError> {
+    match id.split_once(':') {
+        Some((provider, model)) if !provider.is_empty() && !model.is_empty() => {
+            Ok((provider, model))
+        }
+        _ => Err(RegistryError::InvalidModelId(id.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::{
+            CompletionError, CompletionRequest, CompletionResponse, DynResponse, Prompt, Usage,
+        },
+        embeddings::{Embedding, EmbeddingError},
+        message::AssistantContent,
+        OneOrMany,
+    };
+
+    /// Model answering with its name
+    #[derive(Clone)]
+    struct NamedModel(String);
+
+    impl CompletionModel for NamedModel {
+        type Response = String;
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<String>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(&self.0)),
+                usage: Some(Usage::new(1, 1)),
+                raw_response: format!("raw {}", self.0),
+            })
+        }
+
+        fn structured_output(
+            &self,
+            name: &str,
+            _schema: &serde_json::Value,
+        ) -> Option<serde_json::Value> {
+            Some(json!({"name": name}))
+        }
+    }
+
+    /// Embedding model embedding at most 2 texts per request, by their length
+    #[derive(Clone)]
+    struct LengthModel;
+
+    impl EmbeddingModel for LengthModel {
+        const MAX_DOCUMENTS: usize = 2;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            let texts = texts.into_iter().collect::<Vec<_>>();
+            assert!(texts.len() <= Self::MAX_DOCUMENTS);
+            Ok(texts
+                .into_iter()
+                .map(|text| Embedding {
+                    vec: vec![text.len() as f64],
+                    document: text,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_model_registry() {
+        let registry = ModelRegistry::new()
+            .completion_provider("mock", |model| NamedModel(model.to_string()))
+            .embedding_provider("mock", |_| LengthModel);
+
+        let agent = registry.agent("mock:llama3.2:1b").unwrap().build();
+        assert_eq!(agent.prompt("Hi").await.unwrap(), "llama3.2:1b");
+        assert_eq!(agent.usage(), Usage::new(1, 1));
+
+        let model = registry.completion_model("mock:gpt").unwrap();
+        let response = model.completion_request("Hi").send().await.unwrap();
+        let raw: DynResponse = response.raw_response;
+        assert_eq!(raw.downcast_ref::<String>().unwrap(), "raw gpt");
+        assert_eq!(
+            model.structured_output("answer", &json!({})),
+            Some(json!({"name": "answer"}))
+        );
+
+        let model = registry.embedding_model("mock:length").unwrap();
+        let embeddings = model
+            .embed_texts(["a", "bb", "ccc"].map(String::from))
+            .await
+            .unwrap();
+        assert_eq!(
+            embeddings.iter().map(|e| e.vec[0]).collect::<Vec<_>>(),
+            vec![1.0, 2.0, 3.0]
+        );
+
+        assert_eq!(
+            registry.completion_model("other:gpt").err(),
+            Some(RegistryError::UnknownProvider("other".into()))
+        );
+        assert_eq!(
+            registry.embedding_model("gpt-4o").err(),
+            Some(RegistryError::InvalidModelId("gpt-4o".into()))
+        );
+    }
+
+    #[test]
+    fn test_registry_errors() {
+        let registry = ModelRegistry::new()
+            .completion_provider("mock", |model| NamedModel(model.to_string()))
+            .completion_provider("mock", |_| NamedModel("replaced".to_string()));
+        assert_eq!(
+            registry.completion_providers().collect::<Vec<_>>(),
+            ["mock"]
+        );
+        assert_eq!(registry.embedding_providers().count(), 0);
+
+        for id in ["mock", ":gpt", "mock:", ""] {
+            assert_eq!(
+                registry.completion_model(id).err(),
+                Some(RegistryError::InvalidModelId(id.into()))
+            );
+        }
+        // Completion and embedding providers are registered separately
+        assert_eq!(
+            registry.embedding_model("mock:length").err(),
+            Some(RegistryError::UnknownProvider("mock".into()))
+        );
+        assert!(matches!(
+            registry.agent("openai:gpt-4o"),
+            Err(RegistryError::UnknownProvider(provider)) if provider == "openai"
+        ));
+    }
+}
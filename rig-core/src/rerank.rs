@@ -0,0 +1,248 @@
+//! This module provides functionality for re-ranking the documents retrieved from a vector store.
+//!
+//! A [Reranker] (e.g.: a cross-encoder model such as Cohere Rerank) scores the relevance of each
+//! document to the query, which is usually more accurate than the similarity of their embeddings
+//! but too slow to apply to the whole vector store. A [RerankedIndex] therefore retrieves the
+//! `top_k` best candidates of an index and re-ranks them with a [Reranker].
+//!
+//! # Example
+//! ```rust
+//! use rig::{providers::cohere, vector_store::VectorStoreIndex};
+//!
+//! let cohere = cohere::Client::from_env();
+//! let reranker = cohere.rerank_model(cohere::RERANK_ENGLISH_V3);
+//!
+//! // Re-rank the 20 best matches of the vector store and keep the 3 most relevant
+//! let index = vector_store.index(embedding_model).with_reranker(reranker, 20);
+//! let agent = openai.agent("gpt-4o").dynamic_context(3, index).build();
+//! ```
+use std::future::Future;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::vector_store::{VectorStoreError, VectorStoreIndex};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RerankError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[cfg(feature = "http")]
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error parsing the rerank response
+    #[error("ResponseError: {0}")]
+    ResponseError(String),
+
+    /// Error returned by the rerank model provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+/// Trait for models scoring the relevance of documents to a query.
+pub trait Reranker: Send + Sync {
+    /// Score the relevance of `documents` to `query` and return the `top_n` most relevant ones,
+    /// from the most to the least relevant, as tuples of the form (relevance score, index of
+    /// the document in `documents`).
+    fn rerank(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+        top_n: usize,
+    ) -> impl Future<Output = Result<Vec<(f64, usize)>, RerankError>> + Send;
+}
+
+/// [RerankedIndex] retrieves the `top_k` best documents of an index and returns the most
+/// relevant of them according to a [Reranker], scored by the reranker.
+///
+/// Documents are passed to the reranker as text: string documents as is, other documents
+/// serialized as JSON.
+pub struct RerankedIndex<I: VectorStoreIndex, R: Reranker> {
+    index: I,
+    reranker: R,
+    top_k: usize,
+}
+
+impl<I: VectorStoreIndex, R: Reranker> RerankedIndex<I, R> {
+    pub fn new(index: I, reranker: R, top_k: usize) -> Self {
+        Self {
+            index,
+            reranker,
+            top_k,
+        }
+    }
+
+    /// Retrieve the candidates of the index and re-rank them, returning the `n` most relevant
+    /// ones with their reranker score.
+    async fn search(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, Value)>, VectorStoreError> {
+        let candidates = self.index.top_n::<Value>(query, self.top_k.max(n)).await?;
+        if candidates.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let texts = candidates
+            .iter()
+            .map(|(_, _, doc)| match doc {
+                Value::String(text) => text.clone(),
+                doc => doc.to_string(),
+            })
+            .collect();
+        let ranking = self.reranker.rerank(query, texts, n).await?;
+
+        let mut candidates = candidates.into_iter().map(Some).collect::<Vec<_>>();
+        ranking
+            .into_iter()
+            .take(n)
+            .map(|(score, index)| {
+                let (_, id, doc) = candidates
+                    .get_mut(index)
+                    .and_then(Option::take)
+                    .ok_or_else(|| {
+                        RerankError::ResponseError(format!("Invalid document index {index}"))
+                    })?;
+                Ok((score, id, doc))
+            })
+            .collect()
+    }
+}
+
+impl<I: VectorStoreIndex, R: Reranker> VectorStoreIndex for RerankedIndex<I, R> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search(query, n)
+            .await?
+            .into_iter()
+            .map(|(score, id, doc)| Ok((score, id, serde_json::from_value(doc)?)))
+            .collect()
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        Ok(self
+            .search(query, n)
+            .await?
+            .into_iter()
+            .map(|(score, id, _)| (score, id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RerankError, Reranker};
+    use crate::vector_store::{keyword::Bm25Index, VectorStoreError, VectorStoreIndex};
+
+    /// Reranker preferring the shortest documents
+    struct ShortestFirst;
+
+    impl Reranker for ShortestFirst {
+        async fn rerank(
+            &self,
+            _query: &str,
+            documents: Vec<String>,
+            top_n: usize,
+        ) -> Result<Vec<(f64, usize)>, RerankError> {
+            let mut ranking = documents
+                .iter()
+                .enumerate()
+                .map(|(index, doc)| (1.0 / doc.len() as f64, index))
+                .collect::<Vec<_>>();
+            ranking.sort_by(|a, b| b.0.total_cmp(&a.0));
+            ranking.truncate(top_n);
+            Ok(ranking)
+        }
+    }
+
+    /// Reranker returning a fixed ranking, or failing
+    struct FixedRanking(Option<Vec<(f64, usize)>>);
+
+    impl Reranker for FixedRanking {
+        async fn rerank(
+            &self,
+            _query: &str,
+            _documents: Vec<String>,
+            _top_n: usize,
+        ) -> Result<Vec<(f64, usize)>, RerankError> {
+            self.0
+                .clone()
+                .ok_or_else(|| RerankError::ProviderError("Overloaded".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reranked_index() {
+        let index = Bm25Index::from_documents(vec![
+            ("doc1", "build build error".to_string(), "a long document"),
+            ("doc2", "build error".to_string(), "short"),
+            ("doc3", "build".to_string(), "medium doc"),
+            ("doc4", "release".to_string(), "x"),
+        ]);
+        let index = index.with_reranker(ShortestFirst, 3);
+
+        let results = index.top_n::<String>("build error", 2).await.unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|(_, id, doc)| (id.as_str(), doc.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("doc2", "short"), ("doc3", "medium doc")]
+        );
+        assert_eq!(results[0].0, 1.0 / 5.0);
+
+        // Documents not matched by the index are never returned
+        let ids = index.top_n_ids("build error", 4).await.unwrap();
+        assert_eq!(
+            ids.iter().map(|(_, id)| id.as_str()).collect::<Vec<_>>(),
+            vec!["doc2", "doc3", "doc1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rerank_errors() {
+        let documents = vec![
+            ("doc1", "build error".to_string(), "first"),
+            ("doc2", "build".to_string(), "second"),
+        ];
+
+        let index =
+            Bm25Index::from_documents(documents.clone()).with_reranker(FixedRanking(None), 2);
+        assert!(matches!(
+            index.top_n_ids("build", 1).await,
+            Err(VectorStoreError::RerankError(RerankError::ProviderError(_)))
+        ));
+        // The reranker is not called without candidates
+        assert!(index.top_n_ids("release", 1).await.unwrap().is_empty());
+
+        // Rankings of unknown or repeated candidates are rejected
+        for ranking in [vec![(0.9, 2)], vec![(0.9, 0), (0.8, 0)]] {
+            let index = Bm25Index::from_documents(documents.clone())
+                .with_reranker(FixedRanking(Some(ranking)), 2);
+            assert!(matches!(
+                index.top_n_ids("build", 2).await,
+                Err(VectorStoreError::RerankError(RerankError::ResponseError(_)))
+            ));
+        }
+
+        // Documents which can't be deserialized are reported
+        let index = Bm25Index::from_documents(documents)
+            .with_reranker(FixedRanking(Some(vec![(0.9, 1)])), 2);
+        assert!(matches!(
+            index.top_n::<u32>("build", 1).await,
+            Err(VectorStoreError::JsonError(_))
+        ));
+    }
+}
@@ -0,0 +1,375 @@
+//! Semantic routing of utterances, e.g. to detect the intent of a user before invoking an
+//! expensive agent.
+//!
+//! A [SemanticRouter] holds a set of named routes, each defined by example utterances that are
+//! embedded once when the router is built. An utterance is routed to the route of its most
+//! similar example, if the similarity is above the threshold of the router, and to the fallback
+//! route otherwise.
+//!
+//! # Example
+//! ```rust
+//! use rig::{providers::openai, router::SemanticRouter};
+//!
+//! let openai = openai::Client::from_env();
+//! let model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! let router = SemanticRouter::builder(model)
+//!     .route("refund", ["I want my money back", "Can I get a refund for my order?"])
+//!     .route("shipping", ["Where is my package?", "When will my order arrive?"])
+//!     .fallback("general")
+//!     .threshold(0.75)
+//!     .build()
+//!     .await?;
+//!
+//! let route = router.route("My parcel still hasn't arrived").await?.unwrap();
+//! assert_eq!(route.name, "shipping");
+//! ```
+//!
+//! A [SemanticRouter] is also an [orchestrator::Router], which routes the prompts of an
+//! [Orchestrator](crate::orchestrator::Orchestrator) to the agents named as its routes.
+use std::collections::HashSet;
+
+use crate::{
+    embeddings::{distance::VectorDistance, Embedding, EmbeddingError, EmbeddingModel},
+    orchestrator::{self, OrchestratorError, RouteChoice},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RouterError {
+    #[error("EmbeddingError: {0}")]
+    EmbeddingError(#[from] EmbeddingError),
+
+    #[error("Duplicate route: {0}")]
+    DuplicateRoute(String),
+
+    /// A route was defined without example utterances
+    #[error("Route without examples: {0}")]
+    EmptyRoute(String),
+}
+
+/// Route an utterance was routed to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteMatch {
+    /// Name of the route
+    pub name: String,
+    /// Similarity of the utterance to the closest example of the best route (even for the
+    /// fallback route)
+    pub score: f64,
+    /// Whether the utterance was routed to the fallback route, i.e.: no route was similar enough
+    pub fallback: bool,
+}
+
+/// Route with the embeddings of its examples
+struct Route {
+    name: String,
+    examples: Vec<Embedding>,
+}
+
+/// Router mapping utterances to the route of their most similar example utterance.
+pub struct SemanticRouter<E: EmbeddingModel> {
+    model: E,
+    routes: Vec<Route>,
+    fallback: Option<String>,
+    threshold: f64,
+}
+
+impl<E: EmbeddingModel> SemanticRouter<E> {
+    pub fn builder(model: E) -> SemanticRouterBuilder<E> {
+        SemanticRouterBuilder::new(model)
+    }
+
+    /// Names of the routes, in the order they were added (without the fallback route).
+    pub fn routes(&self) -> Vec<&str> {
+        self.routes
+            .iter()
+            .map(|route| route.name.as_str())
+            .collect()
+    }
+
+    /// Similarity of `utterance` to each route (i.e.: to its closest example), best first.
+    pub async fn scores(&self, utterance: &str) -> Result<Vec<(String, f64)>, RouterError> {
+        Ok(self.route_scores(utterance).await?)
+    }
+
+    async fn route_scores(&self, utterance: &str) -> Result<Vec<(String, f64)>, EmbeddingError> {
+        let utterance = self.model.embed_text(utterance).await?;
+        let mut scores = self
+            .routes
+            .iter()
+            .map(|route| (route.name.clone(), route.score(&utterance)))
+            .collect::<Vec<_>>();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(scores)
+    }
+
+    /// Route `utterance` to the best route if its score is above the threshold, or to the
+    /// fallback route. Returns `None` if no route is similar enough and there is no fallback.
+    pub async fn route(&self, utterance: &str) -> Result<Option<RouteMatch>, RouterError> {
+        let best = self.scores(utterance).await?.into_iter().next();
+
+        Ok(match (best, &self.fallback) {
+            (Some((name, score)), _) if score >= self.threshold => Some(RouteMatch {
+                name,
+                score,
+                fallback: false,
+            }),
+            (best, Some(fallback)) => {
+                tracing::debug!(target: "rig", "Routing utterance to fallback route {fallback}");
+                Some(RouteMatch {
+                    name: fallback.clone(),
+                    score: best.map(|(_, score)| score).unwrap_or_default(),
+                    fallback: true,
+                })
+            }
+            (_, None) => None,
+        })
+    }
+}
+
+impl Route {
+    fn score(&self, utterance: &Embedding) -> f64 {
+        self.examples
+            .iter()
+            .map(|example| utterance.cosine_similarity(example, false))
+            // Empty embeddings have no similarity
+            .filter(|score| !score.is_nan())
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Picks the agent of the route of the prompt, among the routes named as agents of the
+/// orchestrator, with the similarity of the prompt as confidence. The threshold and fallback
+/// of the orchestrator are used instead of those of the router.
+impl<E: EmbeddingModel> orchestrator::Router for SemanticRouter<E> {
+    async fn route(
+        &self,
+        prompt: &str,
+        routes: &[orchestrator::Route],
+    ) -> Result<Option<RouteChoice>, OrchestratorError> {
+        Ok(self
+            .route_scores(prompt)
+            .await?
+            .into_iter()
+            .find(|(name, _)| routes.iter().any(|route| &route.name == name))
+            .map(|(agent, score)| RouteChoice {
+                agent,
+                confidence: score.clamp(0.0, 1.0),
+            }))
+    }
+}
+
+/// Builder for the [SemanticRouter]
+pub struct SemanticRouterBuilder<E: EmbeddingModel> {
+    model: E,
+    routes: Vec<(String, Vec<String>)>,
+    fallback: Option<String>,
+    threshold: f64,
+}
+
+impl<E: EmbeddingModel> SemanticRouterBuilder<E> {
+    pub fn new(model: E) -> Self {
+        Self {
+            model,
+            routes: vec![],
+            fallback: None,
+            threshold: 0.0,
+        }
+    }
+
+    /// Add a route named `name`, defined by example utterances.
+    pub fn route(
+        mut self,
+        name: &str,
+        examples: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.routes.push((
+            name.to_string(),
+            examples.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+
+    /// Route the utterances that are not similar enough to any route to the route named
+    /// `name`, which does not need examples.
+    pub fn fallback(mut self, name: &str) -> Self {
+        self.fallback = Some(name.to_string());
+        self
+    }
+
+    /// Set the minimum similarity (e.g.: 0.8) of an utterance to the examples of a route for it
+    /// to be routed to this route (defaults to 0).
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Embed the examples of the routes and build the router. Fails if two routes have the same
+    /// name or if a route has no examples.
+    pub async fn build(self) -> Result<SemanticRouter<E>, RouterError> {
+        let mut names = HashSet::new();
+        for (name, examples) in &self.routes {
+            if !names.insert(name) {
+                return Err(RouterError::DuplicateRoute(name.clone()));
+            }
+            if examples.is_empty() {
+                return Err(RouterError::EmptyRoute(name.clone()));
+            }
+        }
+
+        let texts = self
+            .routes
+            .iter()
+            .flat_map(|(_, examples)| examples.iter().cloned())
+            .collect::<Vec<_>>();
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(E::MAX_DOCUMENTS.max(1)) {
+            embeddings.extend(self.model.embed_texts(batch.to_vec()).await?);
+        }
+        if embeddings.len() != texts.len() {
+            return Err(EmbeddingError::ResponseError(format!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                embeddings.len()
+            ))
+            .into());
+        }
+
+        let mut embeddings = embeddings.into_iter();
+        let routes = self
+            .routes
+            .into_iter()
+            .map(|(name, examples)| Route {
+                name,
+                examples: embeddings.by_ref().take(examples.len()).collect(),
+            })
+            .collect();
+
+        Ok(SemanticRouter {
+            model: self.model,
+            routes,
+            fallback: self.fallback,
+            threshold: self.threshold,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Embedding model embedding texts as the counts of `a` and `b` they contain, failing on
+    /// texts containing `error`
+    #[derive(Clone)]
+    struct CountModel;
+
+    impl EmbeddingModel for CountModel {
+        const MAX_DOCUMENTS: usize = 2;
+
+        fn ndims(&self) -> usize {
+            2
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            let texts = texts.into_iter().collect::<Vec<_>>();
+            if let Some(text) = texts.iter().find(|text| text.contains("error")) {
+                return Err(EmbeddingError::ProviderError(format!(
+                    "Invalid text: {text}"
+                )));
+            }
+            Ok(texts
+                .into_iter()
+                .map(|text| Embedding {
+                    vec: vec![
+                        text.matches('a').count() as f64,
+                        text.matches('b').count() as f64,
+                    ],
+                    document: text,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantic_router() {
+        let router = SemanticRouter::builder(CountModel)
+            .route("billing", ["aaa", "aab"])
+            .route("support", ["bbb"])
+            .fallback("general")
+            .threshold(0.9)
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(router.routes(), vec!["billing", "support"]);
+
+        let route = router.route("ab").await.unwrap().unwrap();
+        assert_eq!((route.name.as_str(), route.fallback), ("billing", false));
+        assert!((route.score - 3.0 / 10f64.sqrt()).abs() < 1e-9);
+
+        // Below the threshold, the utterance goes to the fallback route
+        let route = router.route("abb").await.unwrap().unwrap();
+        assert_eq!((route.name.as_str(), route.fallback), ("general", true));
+        assert!((route.score - 2.0 / 5f64.sqrt()).abs() < 1e-9);
+
+        // As an orchestrator router, only the agents of the orchestrator are considered
+        let routes = [orchestrator::Route {
+            name: "support".to_string(),
+            description: "Technical issues".to_string(),
+        }];
+        let choice = orchestrator::Router::route(&router, "ab", &routes)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(choice.agent, "support");
+
+        assert!(matches!(
+            SemanticRouter::builder(CountModel)
+                .route("billing", ["a"])
+                .route("billing", ["b"])
+                .build()
+                .await,
+            Err(RouterError::DuplicateRoute(name)) if name == "billing"
+        ));
+        assert!(matches!(
+            SemanticRouter::builder(CountModel)
+                .route("billing", Vec::<String>::new())
+                .build()
+                .await,
+            Err(RouterError::EmptyRoute(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_router_errors() {
+        assert!(matches!(
+            SemanticRouter::builder(CountModel)
+                .route("support", ["an error"])
+                .build()
+                .await,
+            Err(RouterError::EmbeddingError(_))
+        ));
+
+        let router = SemanticRouter::builder(CountModel)
+            .route("billing", ["aaa"])
+            .route("support", ["bbb"])
+            .threshold(0.9)
+            .build()
+            .await
+            .unwrap();
+        assert!(matches!(
+            router.route("an error").await,
+            Err(RouterError::EmbeddingError(EmbeddingError::ProviderError(
+                _
+            )))
+        ));
+        assert!(matches!(
+            orchestrator::Router::route(&router, "an error", &[]).await,
+            Err(OrchestratorError::EmbeddingError(_))
+        ));
+
+        // Without fallback, utterances below the threshold have no route
+        assert!(router.route("ab").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,311 @@
+//! Runtime-agnostic helpers for spawning tasks and waiting on timers.
+//!
+//! Rig does not depend on a specific async runtime: the futures it returns can be driven by
+//! tokio, async-std, smol or any other executor. Code that needs to spawn background tasks or
+//! wait for a duration (e.g.: retries, polling, deadlines) should use this module rather than
+//! the APIs of a specific runtime. Timers are backed by [futures_timer], which runs its own
+//! timer thread (or uses the timers of the JavaScript host on wasm).
+//!
+//! On wasm, where threads and the system clock are not available, tasks are spawned on the
+//! event loop of the JavaScript host, and [Instant] and [SystemTime] are read from its clock
+//! (`std::time` panics on `wasm32-unknown-unknown`).
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use rig::runtime;
+//!
+//! // Give up on a slow search after 5 seconds
+//! let results = runtime::timeout(Duration::from_secs(5), index.top_n_ids("query", 3)).await??;
+//! ```
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, Weak},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use futures::{future::Either, Stream};
+
+/// Error returned by [timeout] when the future does not complete in time.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("Deadline of {} ms elapsed", .0.as_millis())]
+pub struct Elapsed(pub Duration);
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::{Instant, SystemTime, UNIX_EPOCH};
+#[cfg(target_arch = "wasm32")]
+pub use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// `Send` on native targets, and implemented by all types on wasm, where the futures of the
+/// JavaScript host (e.g.: the HTTP requests made with `fetch`) are not `Send`.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait WasmCompatSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> WasmCompatSend for T {}
+
+#[cfg(target_arch = "wasm32")]
+pub trait WasmCompatSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T> WasmCompatSend for T {}
+
+/// Run `future` to completion in the background, on a dedicated thread (or on the event loop of
+/// the JavaScript host on wasm, where it doesn't need to be `Send`, see [WasmCompatSend]).
+///
+/// Futures relying on a specific runtime (e.g.: HTTP requests made with `reqwest`, which
+/// require tokio) should be spawned with that runtime instead.
+pub fn spawn(future: impl Future<Output = ()> + WasmCompatSend + 'static) {
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(move || futures::executor::block_on(future));
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+/// Wait until `duration` has elapsed.
+pub fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+    futures_timer::Delay::new(duration)
+}
+
+/// Wait for `future` to complete, failing with [Elapsed] if it takes longer than `duration`.
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    let future = std::pin::pin!(future);
+    match futures::future::select(future, sleep(duration)).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed(duration)),
+    }
+}
+
+/// Token to cancel work in progress (e.g.: the prompts of agents, see
+/// [PromptOptions](crate::completion::PromptOptions)) from another task. Clones of a token share
+/// its state: cancelling one cancels them all.
+///
+/// The token of the current operation (e.g.: the run of an agent calling a tool) is returned by
+/// [CancellationToken::current] within its [scope](CancellationToken::scope), so that
+/// long-running work (e.g.: child processes, see [Tool](crate::tool::Tool#cancellation)) can be
+/// stopped when the operation is cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<Mutex<CancellationState>>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: bool,
+    /// Wakers of the tasks waiting for the cancellation
+    wakers: Vec<Waker>,
+    /// Child tokens, cancelled along with the token
+    children: Vec<Weak<Mutex<CancellationState>>>,
+}
+
+thread_local! {
+    static CURRENT_TOKEN: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token and its children, waking the tasks waiting for them. Cancelling a
+    /// cancelled token does nothing.
+    pub fn cancel(&self) {
+        let (wakers, children) = {
+            let mut state = self.state.lock().expect("cancellation lock poisoned");
+            state.cancelled = true;
+            (
+                std::mem::take(&mut state.wakers),
+                std::mem::take(&mut state.children),
+            )
+        };
+        wakers.into_iter().for_each(Waker::wake);
+        children
+            .iter()
+            .filter_map(Weak::upgrade)
+            .for_each(|state| Self { state }.cancel());
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state
+            .lock()
+            .expect("cancellation lock poisoned")
+            .cancelled
+    }
+
+    /// Wait until the token is cancelled.
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + 'static {
+        let state = self.state.clone();
+        futures::future::poll_fn(move |cx| {
+            let mut state = state.lock().expect("cancellation lock poisoned");
+            if state.cancelled {
+                return Poll::Ready(());
+            }
+            if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                state.wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+    }
+
+    /// New token cancelled along with this one (and cancelled right away if this one is), but
+    /// which can also be cancelled on its own.
+    pub fn child_token(&self) -> CancellationToken {
+        let child = CancellationToken::new();
+        let mut state = self.state.lock().expect("cancellation lock poisoned");
+        if state.cancelled {
+            drop(state);
+            child.cancel();
+        } else {
+            state.children.retain(|child| child.strong_count() > 0);
+            state.children.push(Arc::downgrade(&child.state));
+        }
+        child
+    }
+
+    /// Guard cancelling the token when dropped (unless [disarmed](CancelOnDrop::disarm)), e.g.:
+    /// to stop the work started by a future when the future is dropped.
+    pub fn drop_guard(self) -> CancelOnDrop {
+        CancelOnDrop(Some(self))
+    }
+
+    /// Token of the operation being polled, if it runs in the [scope](Self::scope) of a token
+    pub fn current() -> Option<CancellationToken> {
+        CURRENT_TOKEN.with(|token| token.borrow().clone())
+    }
+
+    /// Run `future` in the scope of this token: [CancellationToken::current] returns this token
+    /// while the future (and the futures it awaits) is polled. Tasks spawned by the future are
+    /// not in the scope of the token, unless they are scoped themselves.
+    pub fn scope<F: Future>(self, future: F) -> CancellationScoped<F> {
+        CancellationScoped {
+            token: self,
+            future: Box::pin(future),
+        }
+    }
+}
+
+/// Guard cancelling its token when dropped, returned by [CancellationToken::drop_guard]
+#[derive(Debug)]
+pub struct CancelOnDrop(Option<CancellationToken>);
+
+impl CancelOnDrop {
+    /// Drop the guard without cancelling its token, returning the token.
+    pub fn disarm(mut self) -> CancellationToken {
+        self.0.take().expect("cancellation guard already disarmed")
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.0.take() {
+            token.cancel();
+        }
+    }
+}
+
+/// Future running in the scope of a cancellation token, returned by [CancellationToken::scope]
+pub struct CancellationScoped<F> {
+    token: CancellationToken,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CancellationScoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        /// Restores the enclosing token once the future is polled, even if it panics
+        struct Restore(Option<CancellationToken>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_TOKEN.with(|token| *token.borrow_mut() = self.0.take());
+            }
+        }
+
+        let token = self.token.clone();
+        let _restore = Restore(CURRENT_TOKEN.with(|current| current.replace(Some(token))));
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Stream yielding every `period`, starting after the first period.
+pub fn interval(period: Duration) -> impl Stream<Item = ()> + Send {
+    futures::stream::unfold((), move |_| async move {
+        sleep(period).await;
+        Some(((), ()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use futures::{channel::oneshot, executor::block_on, StreamExt};
+
+    use super::{interval, sleep, spawn, timeout, CancellationToken, Elapsed};
+
+    // Driven by the executor of the `futures` crate to check that no runtime is required
+    #[test]
+    fn test_runtime_agnostic() {
+        block_on(async {
+            let start = Instant::now();
+            sleep(Duration::from_millis(20)).await;
+            assert!(start.elapsed() >= Duration::from_millis(20));
+
+            assert_eq!(timeout(Duration::from_secs(1), async { 42 }).await, Ok(42));
+            assert_eq!(
+                timeout(Duration::from_millis(10), sleep(Duration::from_secs(5))).await,
+                Err(Elapsed(Duration::from_millis(10)))
+            );
+
+            let ticks = interval(Duration::from_millis(5)).take(3).count().await;
+            assert_eq!(ticks, 3);
+
+            let (tx, rx) = oneshot::channel();
+            spawn(async move {
+                sleep(Duration::from_millis(5)).await;
+                tx.send("done").unwrap();
+            });
+            assert_eq!(rx.await, Ok("done"));
+
+            let token = CancellationToken::new();
+            let cancel = token.clone();
+            spawn(async move {
+                sleep(Duration::from_millis(5)).await;
+                cancel.cancel();
+            });
+            assert_eq!(
+                timeout(Duration::from_secs(1), token.cancelled()).await,
+                Ok(())
+            );
+            assert!(token.is_cancelled());
+        });
+    }
+
+    #[test]
+    fn test_cancellation_tokens() {
+        let parent = CancellationToken::new();
+        let child = parent.child_token();
+        let grandchild = child.child_token();
+        child.cancel();
+        assert!(grandchild.is_cancelled());
+        assert!(!parent.is_cancelled());
+        parent.cancel();
+        assert!(parent.child_token().is_cancelled());
+
+        let token = CancellationToken::new();
+        drop(token.clone().drop_guard());
+        assert!(token.is_cancelled());
+        let token = CancellationToken::new();
+        let guard = token.clone().drop_guard();
+        assert!(!guard.disarm().is_cancelled());
+
+        assert!(CancellationToken::current().is_none());
+        let current = block_on(token.clone().scope(async { CancellationToken::current() }));
+        token.cancel();
+        assert!(current.unwrap().is_cancelled());
+        assert!(CancellationToken::current().is_none());
+    }
+}
@@ -0,0 +1,836 @@
+//! Scheduling of provider calls by priority, under shared rate limits.
+//!
+//! Models wrapped by the same [Scheduler] share its limits: a maximum number of concurrent
+//! requests, a maximum number of requests per period and/or a maximum number of tokens per
+//! period (e.g.: the rate limits of the API key of a provider), so that agents and embedding
+//! jobs sharing a provider stay under its quotas instead of retrying after `429` responses.
+//! Requests over the limits wait in a queue, where [Priority::Interactive]
+//! requests (e.g.: the prompts of a chat) are served before [Priority::Background] requests
+//! (e.g.: the embeddings of an ingestion job), so that batch jobs running in the same process
+//! don't starve the user-facing requests. Requests of the same priority are served in order.
+//!
+//! Running requests are never interrupted: to keep interactive requests from waiting for
+//! background ones to finish, some of the concurrent requests can be reserved to them with
+//! [SchedulerLimits::interactive_reserve].
+//!
+//! Instead of a fixed maximum number of concurrent requests, the scheduler can tune it
+//! automatically with [AdaptiveConcurrency] (AIMD, as in TCP congestion control): the limit is
+//! increased by one after as many successful requests as the current limit, and multiplied by a
+//! factor lower than one when a request is rate limited (e.g.: a `429 Too Many Requests`
+//! response) or slower than a latency target. The requests of [ScheduledModel]s report their
+//! outcome automatically, other requests with [SchedulerPermit::complete].
+//!
+//! The tokens of the requests of [ScheduledModel]s are estimated before they are sent (from the
+//! length of their texts and the `max_tokens` of completions), and the estimates are corrected
+//! with the usage reported by the provider, if any. Other requests declare their tokens with
+//! [Scheduler::acquire_tokens] and [SchedulerPermit::used_tokens].
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{
+//!     embeddings::EmbeddingsBuilder,
+//!     providers::openai,
+//!     scheduler::{Priority, Scheduler, SchedulerLimits},
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let scheduler = Scheduler::new(
+//!     SchedulerLimits::new()
+//!         .max_concurrent(8)
+//!         .interactive_reserve(2)
+//!         .rate_limit(500, Duration::from_secs(60))
+//!         .token_rate_limit(200_000, Duration::from_secs(60)),
+//! );
+//!
+//! // Chat requests are served first...
+//! let agent = rig::agent::AgentBuilder::new(
+//!     scheduler.model(openai.completion_model(openai::GPT_4O), Priority::Interactive),
+//! )
+//! .build();
+//!
+//! // ...while the ingestion job uses the remaining capacity
+//! let embedding_model = scheduler.model(
+//!     openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL),
+//!     Priority::Background,
+//! );
+//! let embeddings = EmbeddingsBuilder::new(embedding_model)
+//!     .documents(documents)?
+//!     .build()
+//!     .await?;
+//! ```
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    future::Future,
+    pin::pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{channel::oneshot, future::select};
+
+use crate::{
+    completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Usage},
+    embeddings::{Embedding, EmbeddingError, EmbeddingLimits, EmbeddingModel},
+    memory::message_text,
+    runtime::{self, Instant},
+    tokenizer::{Estimate, TokenCounter},
+};
+
+/// Priority of the requests of a model in the queue of a [Scheduler].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Batch requests (e.g.: ingestion jobs), served when no interactive request is waiting
+    Background,
+    /// User-facing requests, served first
+    Interactive,
+}
+
+/// Outcome of a request, adjusting the concurrency limit of a scheduler with
+/// [AdaptiveConcurrency].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The request succeeded
+    Success,
+    /// The request was rejected because of a rate limit or an overloaded provider
+    RateLimited,
+    /// The request failed for another reason, which doesn't change the limit
+    Failure,
+}
+
+/// Automatic tuning of the maximum number of concurrent requests of a [Scheduler], increased
+/// additively while the requests succeed and decreased multiplicatively when they are rate
+/// limited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveConcurrency {
+    min: usize,
+    max: usize,
+    initial: usize,
+    decrease_factor: f64,
+    latency_target: Option<Duration>,
+}
+
+impl AdaptiveConcurrency {
+    /// Tune the concurrency limit between `min` and `max` (starting at `min`). `min` must be
+    /// greater than 0, and `max` at least `min`.
+    pub fn new(min: usize, max: usize) -> Self {
+        assert!(min > 0, "min must be greater than 0");
+        assert!(max >= min, "max must be at least min");
+        Self {
+            min,
+            max,
+            initial: min,
+            decrease_factor: 0.5,
+            latency_target: None,
+        }
+    }
+
+    /// Set the initial concurrency limit (clamped between the minimum and maximum).
+    pub fn initial(mut self, initial: usize) -> Self {
+        self.initial = initial.clamp(self.min, self.max);
+        self
+    }
+
+    /// Set the factor by which the limit is multiplied when a request is rate limited (between
+    /// 0 and 1, 0.5 by default).
+    pub fn decrease_factor(mut self, factor: f64) -> Self {
+        self.decrease_factor = factor.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Also decrease the limit when a successful request takes longer than `target`, e.g.: when
+    /// a provider queues the requests instead of rejecting them.
+    pub fn latency_target(mut self, target: Duration) -> Self {
+        self.latency_target = Some(target);
+        self
+    }
+}
+
+/// Limits shared by the requests of a [Scheduler]. No limit is applied by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SchedulerLimits {
+    max_concurrent: Option<usize>,
+    adaptive: Option<AdaptiveConcurrency>,
+    rate_limit: Option<(u32, Duration)>,
+    token_rate_limit: Option<(u64, Duration)>,
+    interactive_reserve: usize,
+}
+
+impl SchedulerLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of concurrent requests. Must be greater than 0.
+    pub fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        assert!(max_concurrent > 0, "max_concurrent must be greater than 0");
+        self.max_concurrent = Some(max_concurrent);
+        self
+    }
+
+    /// Tune the maximum number of concurrent requests automatically (see [AdaptiveConcurrency]),
+    /// instead of using [SchedulerLimits::max_concurrent].
+    pub fn adaptive_concurrency(mut self, adaptive: AdaptiveConcurrency) -> Self {
+        self.adaptive = Some(adaptive);
+        self
+    }
+
+    /// Maximum number of requests started per `period`, allowing bursts of up to `requests`
+    /// requests. Must be greater than 0.
+    pub fn rate_limit(mut self, requests: u32, period: Duration) -> Self {
+        assert!(requests > 0, "requests must be greater than 0");
+        self.rate_limit = Some((requests, period));
+        self
+    }
+
+    /// Maximum number of tokens (of the inputs and outputs of the requests) used per `period`,
+    /// allowing bursts of up to `tokens` tokens. A request waits until its estimated tokens are
+    /// available, or the full budget if it needs more. Must be greater than 0.
+    pub fn token_rate_limit(mut self, tokens: u64, period: Duration) -> Self {
+        assert!(tokens > 0, "tokens must be greater than 0");
+        self.token_rate_limit = Some((tokens, period));
+        self
+    }
+
+    /// Number of the concurrent requests reserved to interactive requests, i.e.: which
+    /// background requests can't use. Only applies with a maximum number of concurrent requests.
+    pub fn interactive_reserve(mut self, reserve: usize) -> Self {
+        self.interactive_reserve = reserve;
+        self
+    }
+}
+
+/// Position of a request in the queue: by priority, then by order of arrival
+type Key = (Reverse<Priority>, u64);
+
+struct State {
+    in_flight: usize,
+    /// Waiting requests, with the sender waking them up when they may be able to start
+    queue: BTreeMap<Key, Option<oneshot::Sender<()>>>,
+    next_seq: u64,
+    /// Available requests of the rate limit (token bucket)
+    tokens: f64,
+    /// Available tokens of the token rate limit, negative when the requests used more tokens
+    /// than available
+    token_budget: f64,
+    refilled: Instant,
+    /// Current limit of concurrent requests, with adaptive concurrency
+    limit: f64,
+    /// Number of decreases of the limit, so that the requests started before a decrease don't
+    /// decrease it again
+    decreases: u64,
+}
+
+impl State {
+    fn refill(&mut self, limits: &SchedulerLimits) {
+        let now = Instant::now();
+        if let Some((requests, period)) = limits.rate_limit {
+            let rate = requests as f64 / period.as_secs_f64();
+            let elapsed = now.duration_since(self.refilled).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(requests as f64);
+        }
+        if let Some((tokens, period)) = limits.token_rate_limit {
+            let rate = tokens as f64 / period.as_secs_f64();
+            let elapsed = now.duration_since(self.refilled).as_secs_f64();
+            self.token_budget = (self.token_budget + elapsed * rate).min(tokens as f64);
+        }
+        self.refilled = now;
+    }
+
+    fn max_concurrent(&self, limits: &SchedulerLimits) -> Option<usize> {
+        match limits.adaptive {
+            Some(_) => Some(self.limit as usize),
+            None => limits.max_concurrent,
+        }
+    }
+
+    fn has_capacity(&self, limits: &SchedulerLimits, priority: Priority) -> bool {
+        match self.max_concurrent(limits) {
+            Some(max) => {
+                let reserve = match priority {
+                    Priority::Interactive => 0,
+                    Priority::Background => limits.interactive_reserve,
+                };
+                self.in_flight < max.saturating_sub(reserve)
+            }
+            None => true,
+        }
+    }
+
+    /// Time until a request of the rate limit, and `tokens` tokens of the token rate limit, are
+    /// available, if they are not
+    fn token_delay(&self, limits: &SchedulerLimits, tokens: u64) -> Option<Duration> {
+        let requests_delay = limits.rate_limit.and_then(|(requests, period)| {
+            (self.tokens < 1.0).then(|| period.mul_f64((1.0 - self.tokens) / requests as f64))
+        });
+        let tokens_delay = limits.token_rate_limit.and_then(|(budget, period)| {
+            let needed = tokens.min(budget) as f64;
+            (self.token_budget < needed)
+                .then(|| period.mul_f64((needed - self.token_budget) / budget as f64))
+        });
+        requests_delay.max(tokens_delay)
+    }
+
+    /// Adjust the concurrency limit with the outcome of a request
+    fn record(&mut self, limits: &SchedulerLimits, permit: &SchedulerPermit, outcome: Outcome) {
+        let Some(adaptive) = limits.adaptive else {
+            return;
+        };
+        let too_slow = adaptive
+            .latency_target
+            .is_some_and(|target| permit.started.elapsed() > target);
+
+        match outcome {
+            Outcome::RateLimited => self.decrease(&adaptive, permit),
+            Outcome::Success if too_slow => self.decrease(&adaptive, permit),
+            Outcome::Success => {
+                self.limit = (self.limit + 1.0 / self.limit.floor()).min(adaptive.max as f64);
+            }
+            Outcome::Failure => {}
+        }
+    }
+
+    fn decrease(&mut self, adaptive: &AdaptiveConcurrency, permit: &SchedulerPermit) {
+        // The requests that were running during the last decrease already contributed to it
+        if permit.decreases == self.decreases {
+            self.limit = (self.limit * adaptive.decrease_factor)
+                .floor()
+                .max(adaptive.min as f64);
+            self.decreases += 1;
+            tracing::debug!(target: "rig",
+                "Request rate limited, decreasing the concurrency limit to {}",
+                self.limit
+            );
+        }
+    }
+
+    /// Wake up the first request of the queue
+    fn notify_head(&mut self) {
+        if let Some(mut head) = self.queue.first_entry() {
+            if let Some(sender) = head.get_mut().take() {
+                let _ = sender.send(());
+            }
+        }
+    }
+}
+
+struct Shared {
+    limits: SchedulerLimits,
+    state: Mutex<State>,
+}
+
+impl Shared {
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("scheduler lock poisoned")
+    }
+}
+
+/// Queue of requests sharing rate limits, served by priority. Clones of the scheduler share
+/// their queue and limits.
+#[derive(Clone)]
+pub struct Scheduler(Arc<Shared>);
+
+impl Scheduler {
+    pub fn new(limits: SchedulerLimits) -> Self {
+        Self(Arc::new(Shared {
+            limits,
+            state: Mutex::new(State {
+                in_flight: 0,
+                queue: BTreeMap::new(),
+                next_seq: 0,
+                tokens: limits
+                    .rate_limit
+                    .map_or(0.0, |(requests, _)| requests as f64),
+                token_budget: limits
+                    .token_rate_limit
+                    .map_or(0.0, |(tokens, _)| tokens as f64),
+                refilled: Instant::now(),
+                limit: limits
+                    .adaptive
+                    .map_or(0.0, |adaptive| adaptive.initial as f64),
+                decreases: 0,
+            }),
+        }))
+    }
+
+    /// Wait until a request of the given priority can start under the limits of the scheduler.
+    /// The request is considered running until the returned permit is dropped.
+    pub async fn acquire(&self, priority: Priority) -> SchedulerPermit {
+        self.acquire_tokens(priority, 0).await
+    }
+
+    /// Same as `acquire`, for a request using an estimated `tokens` tokens of the token rate
+    /// limit. The estimate can be corrected once the request completed with
+    /// [SchedulerPermit::used_tokens].
+    pub async fn acquire_tokens(&self, priority: Priority, tokens: u64) -> SchedulerPermit {
+        let key = {
+            let mut state = self.0.state();
+            state.next_seq += 1;
+            (Reverse(priority), state.next_seq)
+        };
+        let mut entry = QueueEntry {
+            shared: &self.0,
+            key,
+            queued: false,
+        };
+
+        loop {
+            let (receiver, delay) = {
+                let mut state = self.0.state();
+                state.refill(&self.0.limits);
+
+                let first = state.queue.keys().next().is_none_or(|head| key <= *head);
+                let mut delay = None;
+                if first && state.has_capacity(&self.0.limits, priority) {
+                    delay = state.token_delay(&self.0.limits, tokens);
+                    if delay.is_none() {
+                        if self.0.limits.rate_limit.is_some() {
+                            state.tokens -= 1.0;
+                        }
+                        if self.0.limits.token_rate_limit.is_some() {
+                            state.token_budget -= tokens as f64;
+                        }
+                        state.in_flight += 1;
+                        state.queue.remove(&key);
+                        entry.queued = false;
+                        // The next request may also be able to start
+                        state.notify_head();
+                        return SchedulerPermit {
+                            shared: self.0.clone(),
+                            started: Instant::now(),
+                            decreases: state.decreases,
+                            tokens,
+                            outcome: None,
+                        };
+                    }
+                }
+
+                let (sender, receiver) = oneshot::channel();
+                state.queue.insert(key, Some(sender));
+                entry.queued = true;
+                (receiver, delay)
+            };
+
+            match delay {
+                Some(delay) => {
+                    select(receiver, pin!(runtime::sleep(delay))).await;
+                }
+                None => {
+                    let _ = receiver.await;
+                }
+            }
+        }
+    }
+
+    /// Run `future` once a request of the given priority can start.
+    pub async fn run<F: Future>(&self, priority: Priority, future: F) -> F::Output {
+        let _permit = self.acquire(priority).await;
+        future.await
+    }
+
+    /// Wrap `model` so that its requests are scheduled with the given priority.
+    pub fn model<M>(&self, model: M, priority: Priority) -> ScheduledModel<M> {
+        ScheduledModel {
+            model,
+            scheduler: self.clone(),
+            priority,
+        }
+    }
+
+    /// Number of running requests
+    pub fn in_flight(&self) -> usize {
+        self.0.state().in_flight
+    }
+
+    /// Number of waiting requests
+    pub fn queued(&self) -> usize {
+        self.0.state().queue.len()
+    }
+
+    /// Available tokens of the token rate limit, if any (negative when the requests used more
+    /// tokens than available)
+    pub fn available_tokens(&self) -> Option<f64> {
+        let mut state = self.0.state();
+        state.refill(&self.0.limits);
+        self.0.limits.token_rate_limit.map(|_| state.token_budget)
+    }
+
+    /// Whether the requests are subject to a token rate limit, i.e.: whether their tokens need
+    /// to be estimated
+    fn limits_tokens(&self) -> bool {
+        self.0.limits.token_rate_limit.is_some()
+    }
+
+    /// Current maximum number of concurrent requests, if any
+    pub fn max_concurrent(&self) -> Option<usize> {
+        self.0.state().max_concurrent(&self.0.limits)
+    }
+}
+
+/// Removes a request from the queue if it is cancelled while waiting
+struct QueueEntry<'a> {
+    shared: &'a Shared,
+    key: Key,
+    queued: bool,
+}
+
+impl Drop for QueueEntry<'_> {
+    fn drop(&mut self) {
+        if self.queued {
+            let mut state = self.shared.state();
+            state.queue.remove(&self.key);
+            state.notify_head();
+        }
+    }
+}
+
+/// Running request of a [Scheduler], which lets the next request start when dropped.
+pub struct SchedulerPermit {
+    shared: Arc<Shared>,
+    started: Instant,
+    decreases: u64,
+    /// Tokens of the request taken from the token rate limit
+    tokens: u64,
+    outcome: Option<Outcome>,
+}
+
+impl SchedulerPermit {
+    /// Report the number of tokens actually used by the request (e.g.: the `total_tokens` of
+    /// its [Usage]), giving back the tokens it was estimated to use in excess, or taking the
+    /// missing ones from the token rate limit.
+    pub fn used_tokens(&mut self, tokens: u64) {
+        if self.shared.limits.token_rate_limit.is_some() {
+            let mut state = self.shared.state();
+            state.refill(&self.shared.limits);
+            state.token_budget += self.tokens as f64 - tokens as f64;
+        }
+        self.tokens = tokens;
+    }
+
+    /// Report the outcome of the request, to adjust the concurrency limit with
+    /// [AdaptiveConcurrency]. Dropping the permit without reporting an outcome (e.g.: when the
+    /// request is cancelled) doesn't change the limit.
+    pub fn complete(mut self, outcome: Outcome) {
+        self.outcome = Some(outcome);
+    }
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        let shared = self.shared.clone();
+        let mut state = shared.state();
+        state.in_flight -= 1;
+        if let Some(outcome) = self.outcome {
+            state.record(&shared.limits, self, outcome);
+        }
+        state.notify_head();
+    }
+}
+
+fn outcome<T, E>(result: &Result<T, E>, is_rate_limited: impl Fn(&E) -> bool) -> Outcome {
+    match result {
+        Ok(_) => Outcome::Success,
+        Err(e) if is_rate_limited(e) => Outcome::RateLimited,
+        Err(_) => Outcome::Failure,
+    }
+}
+
+/// Estimate of the tokens of a completion request: the tokens of its texts, and the maximum
+/// number of tokens of the completion
+fn completion_tokens(request: &CompletionRequest) -> u64 {
+    let messages = std::iter::once(&request.prompt)
+        .chain(&request.chat_history)
+        .map(|message| Estimate.count_tokens(&message_text(message)))
+        .sum::<usize>();
+    let documents = request
+        .documents
+        .iter()
+        .map(|doc| Estimate.count_tokens(&doc.to_string()))
+        .sum::<usize>();
+    let tools = request
+        .tools
+        .iter()
+        .map(|tool| Estimate.count_tokens(&serde_json::to_string(tool).unwrap_or_default()))
+        .sum::<usize>();
+    let preamble = Estimate.count_tokens(request.preamble.as_deref().unwrap_or_default());
+    (preamble + messages + documents + tools) as u64 + request.max_tokens.unwrap_or_default()
+}
+
+/// Completion or embedding model whose requests are scheduled by a [Scheduler], see
+/// [Scheduler::model].
+#[derive(Clone)]
+pub struct ScheduledModel<M> {
+    model: M,
+    scheduler: Scheduler,
+    priority: Priority,
+}
+
+impl<M> ScheduledModel<M> {
+    /// Estimate of the tokens of texts to embed
+    fn texts_tokens(&self, texts: &[String]) -> u64 {
+        match self.scheduler.limits_tokens() {
+            true => texts
+                .iter()
+                .map(|text| Estimate.count_tokens(text) as u64)
+                .sum(),
+            false => 0,
+        }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for ScheduledModel<M> {
+    type Response = M::Response;
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.model_name()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<M::Response>, CompletionError> {
+        let tokens = match self.scheduler.limits_tokens() {
+            true => completion_tokens(&request),
+            false => 0,
+        };
+        let mut permit = self.scheduler.acquire_tokens(self.priority, tokens).await;
+        let result = self.model.completion(request).await;
+        if let Ok(CompletionResponse {
+            usage: Some(usage), ..
+        }) = &result
+        {
+            permit.used_tokens(usage.total_tokens);
+        }
+        permit.complete(outcome(&result, CompletionError::is_rate_limited));
+        result
+    }
+
+    fn structured_output(
+        &self,
+        name: &str,
+        schema: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.model.structured_output(name, schema)
+    }
+
+    fn json_mode(&self) -> Option<serde_json::Value> {
+        self.model.json_mode()
+    }
+}
+
+impl<M: EmbeddingModel> EmbeddingModel for ScheduledModel<M> {
+    const MAX_DOCUMENTS: usize = M::MAX_DOCUMENTS;
+
+    fn ndims(&self) -> usize {
+        self.model.ndims()
+    }
+
+    fn limits(&self) -> EmbeddingLimits {
+        self.model.limits()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.model_name()
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+        let permit = self
+            .scheduler
+            .acquire_tokens(self.priority, self.texts_tokens(&texts))
+            .await;
+        let result = self.model.embed_texts(texts).await;
+        permit.complete(outcome(&result, EmbeddingError::is_rate_limited));
+        result
+    }
+
+    async fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<(Vec<Embedding>, Option<Usage>), EmbeddingError> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+        let mut permit = self
+            .scheduler
+            .acquire_tokens(self.priority, self.texts_tokens(&texts))
+            .await;
+        let result = self.model.embed_texts_with_usage(texts).await;
+        if let Ok((_, Some(usage))) = &result {
+            permit.used_tokens(usage.total_tokens);
+        }
+        permit.complete(outcome(&result, EmbeddingError::is_rate_limited));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{MockCompletionModel, MockEmbeddingModel};
+
+    #[tokio::test]
+    async fn test_priorities() {
+        let scheduler = Scheduler::new(SchedulerLimits::new().max_concurrent(1));
+        let order = Arc::new(Mutex::new(vec![]));
+        let running = scheduler.acquire(Priority::Background).await;
+
+        let mut tasks = vec![];
+        for (name, priority) in [
+            ("batch 1", Priority::Background),
+            ("batch 2", Priority::Background),
+            ("chat", Priority::Interactive),
+        ] {
+            let (shared, order) = (scheduler.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                shared
+                    .run(priority, async { order.lock().unwrap().push(name) })
+                    .await
+            }));
+            // Let the request join the queue
+            while scheduler.queued() < tasks.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        // A cancelled request leaves the queue
+        let cancelled = runtime::timeout(
+            Duration::from_millis(10),
+            scheduler.acquire(Priority::Interactive),
+        )
+        .await;
+        assert!(cancelled.is_err());
+        assert_eq!(scheduler.queued(), 3);
+        assert_eq!(scheduler.in_flight(), 1);
+
+        drop(running);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec!["chat", "batch 1", "batch 2"]);
+        assert_eq!(scheduler.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        // Background requests can't use the reserved request
+        let scheduler = Scheduler::new(
+            SchedulerLimits::new()
+                .max_concurrent(2)
+                .interactive_reserve(1),
+        );
+        let _batch = scheduler.acquire(Priority::Background).await;
+        assert!(runtime::timeout(
+            Duration::from_millis(10),
+            scheduler.acquire(Priority::Background)
+        )
+        .await
+        .is_err());
+        let _chat = scheduler.acquire(Priority::Interactive).await;
+        assert_eq!(scheduler.in_flight(), 2);
+
+        // Bursts of 2 requests, then 1 request every 50 ms
+        let scheduler =
+            Scheduler::new(SchedulerLimits::new().rate_limit(2, Duration::from_millis(100)));
+        let start = Instant::now();
+        for _ in 0..3 {
+            scheduler.run(Priority::Interactive, async {}).await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_token_rate_limit() {
+        let scheduler = Scheduler::new(
+            SchedulerLimits::new().token_rate_limit(100, Duration::from_millis(200)),
+        );
+
+        // The unused tokens of the estimate are given back
+        let mut permit = scheduler.acquire_tokens(Priority::Interactive, 100).await;
+        permit.used_tokens(50);
+        drop(permit);
+        let start = Instant::now();
+        scheduler.acquire_tokens(Priority::Interactive, 50).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // The budget is empty: the request waits for 20 tokens to be refilled
+        let start = Instant::now();
+        scheduler.acquire_tokens(Priority::Interactive, 20).await;
+        assert!(start.elapsed() >= Duration::from_millis(30));
+
+        // The tokens of the requests of models are estimated, then corrected with their usage
+        let scheduler =
+            Scheduler::new(SchedulerLimits::new().token_rate_limit(1000, Duration::from_secs(60)));
+        let model = scheduler.model(
+            MockCompletionModel::new()
+                .text("Hello!")
+                .usage(Usage::new(8, 2)),
+            Priority::Interactive,
+        );
+        model
+            .completion_request("Hi")
+            .max_tokens(100)
+            .send()
+            .await
+            .unwrap();
+        let available = scheduler.available_tokens().unwrap();
+        assert!((990.0..991.0).contains(&available), "{available}");
+
+        let model = scheduler.model(MockEmbeddingModel::new(2), Priority::Background);
+        model.embed_texts(["a".repeat(40)]).await.unwrap();
+        let available = scheduler.available_tokens().unwrap();
+        assert!((980.0..981.0).contains(&available), "{available}");
+    }
+
+    /// Model rejecting the requests with a rate limit error
+    #[derive(Clone)]
+    struct RateLimitedModel;
+
+    impl CompletionModel for RateLimitedModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Err(CompletionError::ProviderError(
+                r#"{"type":"error","error":{"type":"rate_limit_error"}}"#.into(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_concurrency() {
+        let scheduler = Scheduler::new(
+            SchedulerLimits::new().adaptive_concurrency(AdaptiveConcurrency::new(1, 4).initial(4)),
+        );
+        assert_eq!(scheduler.max_concurrent(), Some(4));
+
+        // Concurrent rate limited requests decrease the limit once
+        let permits = [
+            scheduler.acquire(Priority::Background).await,
+            scheduler.acquire(Priority::Background).await,
+            scheduler.acquire(Priority::Background).await,
+        ];
+        for permit in permits {
+            permit.complete(Outcome::RateLimited);
+        }
+        assert_eq!(scheduler.max_concurrent(), Some(2));
+
+        // The limit increases by 1 after as many successes as the limit
+        for outcome in [Outcome::Success, Outcome::Failure, Outcome::Success] {
+            scheduler
+                .acquire(Priority::Background)
+                .await
+                .complete(outcome);
+        }
+        assert_eq!(scheduler.max_concurrent(), Some(3));
+
+        let model = scheduler.model(RateLimitedModel, Priority::Interactive);
+        let error = model.completion_request("Hi").send().await.unwrap_err();
+        assert!(error.is_rate_limited());
+        assert_eq!(scheduler.max_concurrent(), Some(1));
+    }
+}
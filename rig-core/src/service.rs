@@ -0,0 +1,257 @@
+//! [tower](https://docs.rs/tower) services of the agents and completion models (with the `tower`
+//! feature), to compose them with the middleware of the tower ecosystem (e.g.: timeouts, load
+//! shedding, concurrency limits, instrumentation) in server stacks.
+//!
+//! An [AgentService] answers [PromptRequest]s with an [Agent], and a [ModelService] answers them
+//! (or raw [CompletionRequest]s) with a [CompletionModel]. Both are cheap to clone, as servers
+//! do for each connection.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use rig::{providers::openai, service::{AgentService, PromptRequest}};
+//! use tower::{ServiceBuilder, ServiceExt};
+//!
+//! let agent = openai::Client::from_env().agent("gpt-4o").build();
+//!
+//! let service = ServiceBuilder::new()
+//!     .timeout(Duration::from_secs(30))
+//!     .concurrency_limit(64)
+//!     .service(AgentService::new(agent));
+//!
+//! let response = service.oneshot(PromptRequest::new("Hello!")).await?;
+//! ```
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use tower_service::Service;
+
+use crate::{
+    agent::Agent,
+    completion::{
+        AssistantContent, Chat, CompletionError, CompletionModel, CompletionRequest,
+        CompletionResponse, Message, PromptError,
+    },
+};
+
+/// Prompt sent to an [AgentService] or a [ModelService], with the history of its conversation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptRequest {
+    pub prompt: Message,
+    /// Messages of the conversation preceding the prompt, oldest first
+    pub chat_history: Vec<Message>,
+}
+
+impl PromptRequest {
+    pub fn new(prompt: impl Into<Message>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            chat_history: vec![],
+        }
+    }
+
+    /// Set the history of the conversation preceding the prompt.
+    pub fn chat_history(mut self, chat_history: Vec<Message>) -> Self {
+        self.chat_history = chat_history;
+        self
+    }
+}
+
+impl From<&str> for PromptRequest {
+    fn from(prompt: &str) -> Self {
+        Self::new(prompt)
+    }
+}
+
+impl From<String> for PromptRequest {
+    fn from(prompt: String) -> Self {
+        Self::new(prompt)
+    }
+}
+
+/// [Service] answering [PromptRequest]s with the shared [Agent], as [Chat::chat] does. The
+/// service is always ready: the concurrency of the agent is limited by the layers wrapping it.
+pub struct AgentService<M: CompletionModel> {
+    agent: Arc<Agent<M>>,
+}
+
+impl<M: CompletionModel> AgentService<M> {
+    pub fn new(agent: Agent<M>) -> Self {
+        Self {
+            agent: Arc::new(agent),
+        }
+    }
+
+    /// Agent of the service
+    pub fn agent(&self) -> &Agent<M> {
+        &self.agent
+    }
+}
+
+impl<M: CompletionModel> Clone for AgentService<M> {
+    fn clone(&self) -> Self {
+        Self {
+            agent: self.agent.clone(),
+        }
+    }
+}
+
+impl<M: CompletionModel> From<Agent<M>> for AgentService<M> {
+    fn from(agent: Agent<M>) -> Self {
+        Self::new(agent)
+    }
+}
+
+impl<M: CompletionModel> From<Arc<Agent<M>>> for AgentService<M> {
+    fn from(agent: Arc<Agent<M>>) -> Self {
+        Self { agent }
+    }
+}
+
+impl<M: CompletionModel + 'static> Service<PromptRequest> for AgentService<M> {
+    type Response = String;
+    type Error = PromptError;
+    type Future = BoxFuture<'static, Result<String, PromptError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: PromptRequest) -> Self::Future {
+        let agent = self.agent.clone();
+        Box::pin(async move { agent.chat(request.prompt, request.chat_history).await })
+    }
+}
+
+/// [Service] answering [PromptRequest]s (with the text of the response) and
+/// [CompletionRequest]s (with the response itself) with a [CompletionModel], without the
+/// preamble, context and tools of an agent. The service is always ready.
+#[derive(Clone)]
+pub struct ModelService<M: CompletionModel> {
+    model: M,
+}
+
+impl<M: CompletionModel> ModelService<M> {
+    pub fn new(model: M) -> Self {
+        Self { model }
+    }
+
+    /// Model of the service
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+}
+
+impl<M: CompletionModel + 'static> Service<PromptRequest> for ModelService<M> {
+    type Response = String;
+    type Error = PromptError;
+    type Future = BoxFuture<'static, Result<String, PromptError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: PromptRequest) -> Self::Future {
+        let model = self.model.clone();
+        Box::pin(async move {
+            let response = model
+                .completion_request(request.prompt)
+                .messages(request.chat_history)
+                .send()
+                .await?;
+
+            Ok(response
+                .choice
+                .iter()
+                .filter_map(|content| match content {
+                    AssistantContent::Text(text) => Some(text.text.as_str()),
+                    AssistantContent::ToolCall(_) => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        })
+    }
+}
+
+impl<M: CompletionModel + 'static> Service<CompletionRequest> for ModelService<M> {
+    type Response = CompletionResponse<M::Response>;
+    type Error = CompletionError;
+    type Future = BoxFuture<'static, Result<CompletionResponse<M::Response>, CompletionError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: CompletionRequest) -> Self::Future {
+        let model = self.model.clone();
+        Box::pin(async move { model.completion(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tower::{timeout::error::Elapsed, ServiceBuilder, ServiceExt};
+
+    use super::*;
+    use crate::{agent::AgentBuilder, providers::mock::MockCompletionModel};
+
+    #[tokio::test]
+    async fn test_agent_service() {
+        let model = MockCompletionModel::new().text("Hello!");
+        let service = ServiceBuilder::new()
+            .timeout(Duration::from_millis(50))
+            .concurrency_limit(1)
+            .service(AgentService::new(
+                AgentBuilder::new(model.clone()).preamble("Be nice").build(),
+            ));
+
+        let response = service
+            .clone()
+            .oneshot(PromptRequest::new("Hi").chat_history(vec![Message::assistant("Hey")]))
+            .await
+            .unwrap();
+        assert_eq!(response, "Hello!");
+        let request = model.last_request().unwrap();
+        assert_eq!(request.preamble.as_deref(), Some("Be nice"));
+        assert_eq!(request.chat_history.len(), 1);
+
+        // The layers wrapping the agent apply to its requests
+        let slow = AgentService::new(
+            AgentBuilder::new(MockCompletionModel::new().latency(Duration::from_secs(1))).build(),
+        );
+        let error = ServiceBuilder::new()
+            .timeout(Duration::from_millis(10))
+            .service(slow)
+            .oneshot("Hi".into())
+            .await
+            .unwrap_err();
+        assert!(error.is::<Elapsed>());
+    }
+
+    #[tokio::test]
+    async fn test_model_service() {
+        let model = MockCompletionModel::new().text("Hello!").text("Bye!");
+        let mut service = ModelService::new(model.clone());
+
+        assert_eq!(
+            ServiceExt::<PromptRequest>::oneshot(service.clone(), "Hi".into())
+                .await
+                .unwrap(),
+            "Hello!"
+        );
+        let request = model.completion_request("Hi").build();
+        let response = Service::<CompletionRequest>::call(&mut service, request)
+            .await
+            .unwrap();
+        assert!(matches!(
+            response.choice.first(),
+            AssistantContent::Text(text) if text.text == "Bye!"
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+//! Persistence of conversations, to resume them across restarts.
+//!
+//! A [Session] is a serializable snapshot of the conversation of an agent: the turns remembered
+//! by its [memory](crate::memory) (including the tool calls and their results, for agents built
+//! with [AgentBuilder::remember_tool_calls](crate::agent::AgentBuilder::remember_tool_calls)),
+//! the summary of the older turns and arbitrary metadata (e.g.: the id of the user).
+//!
+//! A [SessionStore] saves the sessions in any [KvStore]: files, Redis, S3 or SQLite (with
+//! `rig-sqlite`'s `SqliteStore`).
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     completion::Prompt,
+//!     memory::BufferHistory,
+//!     providers::openai,
+//!     session::{Session, SessionStore},
+//!     storage::FileStore,
+//! };
+//!
+//! let sessions = SessionStore::new(FileStore::new("./data"));
+//! let session = sessions
+//!     .load(&user_id)
+//!     .await?
+//!     .unwrap_or_else(|| Session::new(&user_id));
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .memory(BufferHistory::new(20))
+//!     .session(session)
+//!     .build();
+//!
+//! let response = agent.prompt("Where were we?").await?;
+//! sessions.save(&agent.session().await).await?;
+//! ```
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::Message,
+    storage::{KvStore, StorageError},
+};
+
+/// Serializable snapshot of a conversation.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Session {
+    /// Id of the session, also the id of the session of the agent's runs (see
+    /// [telemetry](crate::telemetry#session-and-run-ids))
+    pub id: String,
+    /// Turns of the conversation, oldest first
+    #[serde(default)]
+    pub turns: Vec<Vec<Message>>,
+    /// Summary of the turns no longer part of the conversation, if any
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// Arbitrary metadata of the session
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl Session {
+    /// Create an empty session.
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the metadata `key` of the session.
+    pub fn metadata(mut self, key: &str, value: serde_json::Value) -> Self {
+        self.metadata.insert(key.to_string(), value);
+        self
+    }
+
+    /// Messages of the conversation, oldest first.
+    pub fn messages(&self) -> Vec<Message> {
+        self.turns.iter().flatten().cloned().collect()
+    }
+}
+
+/// Store of [Session]s, saved as JSON in a [KvStore] under the key `{prefix}{id}`. The prefix
+/// defaults to `"sessions/"`.
+#[derive(Clone)]
+pub struct SessionStore<S: KvStore> {
+    store: S,
+    prefix: String,
+}
+
+impl<S: KvStore> SessionStore<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            prefix: "sessions/".to_string(),
+        }
+    }
+
+    /// Set the prefix of the keys of the sessions.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Save `session`, replacing the saved session with the same id.
+    pub async fn save(&self, session: &Session) -> Result<(), StorageError> {
+        self.store.save(&self.key(&session.id)?, session).await
+    }
+
+    /// Load the session `id`, if it was saved.
+    pub async fn load(&self, id: &str) -> Result<Option<Session>, StorageError> {
+        self.store.load(&self.key(id)?).await
+    }
+
+    /// Delete the session `id` (deleting a missing session is not an error).
+    pub async fn delete(&self, id: &str) -> Result<(), StorageError> {
+        self.store.remove(&self.key(id)?).await
+    }
+
+    /// Ids of the saved sessions, sorted.
+    pub async fn ids(&self) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .store
+            .keys(&self.prefix)
+            .await?
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_string))
+            .collect())
+    }
+
+    fn key(&self, id: &str) -> Result<String, StorageError> {
+        if id.is_empty() {
+            return Err(StorageError::InvalidKey("empty session id".to_string()));
+        }
+        Ok(format!("{}{id}", self.prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        message::{AssistantContent, ToolResultContent, UserContent},
+        storage::{BlobStore, InMemoryStore},
+        OneOrMany,
+    };
+
+    #[tokio::test]
+    async fn test_session_store() {
+        let sessions = SessionStore::new(InMemoryStore::new());
+        let session = Session {
+            id: "42".to_string(),
+            turns: vec![vec![
+                Message::user("What time is it?"),
+                Message::Assistant {
+                    content: OneOrMany::one(AssistantContent::tool_call(
+                        "call_1",
+                        "time",
+                        json!({}),
+                    )),
+                },
+                Message::User {
+                    content: OneOrMany::one(UserContent::tool_result(
+                        "call_1",
+                        OneOrMany::one(ToolResultContent::text("12:00")),
+                    )),
+                },
+                Message::assistant("It is noon."),
+            ]],
+            summary: Some("The user asked for the time.".to_string()),
+            metadata: Default::default(),
+        }
+        .metadata("user", json!("john"));
+
+        sessions.save(&session).await.unwrap();
+        sessions.save(&Session::new("43")).await.unwrap();
+        assert_eq!(sessions.load("42").await.unwrap(), Some(session.clone()));
+        assert_eq!(sessions.ids().await.unwrap(), vec!["42", "43"]);
+        assert_eq!(session.messages().len(), 4);
+
+        sessions.delete("42").await.unwrap();
+        assert_eq!(sessions.load("42").await.unwrap(), None);
+        assert!(matches!(
+            sessions.load("").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_session_store_errors() {
+        let store = InMemoryStore::new();
+        let sessions = SessionStore::new(store.clone()).prefix("chat/");
+        store.put("chat/1", "not a session".into()).await.unwrap();
+        store.put("sessions/2", "{}".into()).await.unwrap();
+
+        assert!(matches!(
+            sessions.load("1").await,
+            Err(StorageError::JsonError(_))
+        ));
+        // Only the keys of the prefix are sessions
+        assert_eq!(sessions.ids().await.unwrap(), vec!["1"]);
+
+        assert!(matches!(
+            sessions.save(&Session::new("")).await,
+            Err(StorageError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            sessions.delete("").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+        sessions.delete("missing").await.unwrap();
+    }
+}
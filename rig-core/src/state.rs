@@ -0,0 +1,311 @@
+//! Typed state of multi-turn conversations, threaded through the prompts and the outputs of the
+//! model.
+//!
+//! A [ConversationState] maps a struct to the variables of the conversation (see the
+//! `ConversationState` derive, with the `derive` feature):
+//! - its [variables](ConversationState::to_variables) are injected in the templates of the
+//!   preambles and prompts (see [PromptTemplate::render](crate::prompt::PromptTemplate::render)),
+//! - the model reports its updates of the state in a `<state>{...}</state>` JSON block of its
+//!   outputs, as explained by the [instructions](ConversationState::instructions), which are
+//!   [extracted](ConversationState::extract) back into the struct,
+//! - the state is [saved](ConversationState::save) in the metadata of the [Session], to be
+//!   [loaded](ConversationState::load) with it in the next turns.
+//!
+//! # Example
+//! ```rust
+//! use rig::{prompt::PromptTemplate, state::ConversationState, ConversationState};
+//!
+//! #[derive(ConversationState, Default)]
+//! struct Booking {
+//!     /// Name of the customer
+//!     #[state(readonly)]
+//!     customer: String,
+//!     /// City of the trip, once known
+//!     city: Option<String>,
+//!     /// Number of nights of the trip, once known
+//!     nights: Option<u32>,
+//! }
+//!
+//! let mut booking = Booking::load(&session)?;
+//! let template = PromptTemplate::new("You book trips for {customer}.{#if city} They go to {city}.{/if}")?;
+//! let agent = openai.agent("gpt-4o")
+//!     .preamble(&format!("{}\n{}", template.render(booking.to_variables()?)?, Booking::instructions()))
+//!     .build();
+//!
+//! let output = agent.prompt("Two nights in Lisbon, please").await?;
+//! let answer = booking.extract(&output)?;
+//! booking.save(&mut session)?;
+//! ```
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::session::Session;
+
+/// Metadata key of the [Session]s under which the state is saved
+pub const STATE_KEY: &str = "state";
+
+const STATE_OPEN: &str = "<state>";
+const STATE_CLOSE: &str = "</state>";
+
+/// Variables of a [ConversationState], by name
+pub type Variables = Map<String, Value>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StateError {
+    /// A variable of the state does not (de)serialize to (or from) its JSON value
+    #[error("Invalid state variable `{0}`: {1}")]
+    InvalidVariable(String, serde_json::Error),
+
+    /// The update of the state reported by the model is not a JSON object
+    #[error("Invalid state update: {0}")]
+    InvalidUpdate(String),
+}
+
+/// Variable of a [ConversationState]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateVariable {
+    /// Name of the variable, in the templates and in the updates of the model
+    pub name: &'static str,
+    /// Description of the variable for the model (the doc comment of its field)
+    pub description: &'static str,
+    /// Whether the variable is updated from the outputs of the model, and not only read by it
+    pub extract: bool,
+}
+
+/// Trait for the typed states of conversations, usually derived with
+/// `#[derive(ConversationState)]` (see the [module](self) documentation).
+pub trait ConversationState: Sized {
+    /// Variables of the state, in the order of their fields
+    fn variables() -> Vec<StateVariable>;
+
+    /// Values of the variables of the state.
+    fn to_variables(&self) -> Result<Variables, StateError>;
+
+    /// Set the variables of the state present in `variables` (the other names being ignored),
+    /// returning the names of the updated variables.
+    fn update(&mut self, variables: &Variables) -> Result<Vec<String>, StateError>;
+
+    /// Create the state from `variables`, the missing ones keeping their default value.
+    fn from_variables(variables: &Variables) -> Result<Self, StateError>
+    where
+        Self: Default,
+    {
+        let mut state = Self::default();
+        state.update(variables)?;
+        Ok(state)
+    }
+
+    /// Instructions for the model to report its updates of the state, to append to the
+    /// preamble of the agent.
+    fn instructions() -> String {
+        let variables = Self::variables()
+            .into_iter()
+            .filter(|variable| variable.extract)
+            .map(|variable| match variable.description {
+                "" => format!("- {}", variable.name),
+                description => format!("- {}: {description}", variable.name),
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            "When the conversation changes the following variables, end your response with their \
+            new values as a JSON object in a {STATE_OPEN}...{STATE_CLOSE} block, e.g.: \
+            {STATE_OPEN}{{\"name\": \"value\"}}{STATE_CLOSE}. Variables:\n{}",
+            variables.join("\n")
+        )
+    }
+
+    /// Apply the update of the state reported in the `<state>` block of `output` (if any) to
+    /// the [extracted](StateVariable::extract) variables, returning the output without the
+    /// block.
+    fn extract(&mut self, output: &str) -> Result<String, StateError> {
+        let Some((start, end)) = output.rfind(STATE_OPEN).and_then(|start| {
+            output[start..]
+                .find(STATE_CLOSE)
+                .map(|end| (start, start + end))
+        }) else {
+            return Ok(output.to_string());
+        };
+
+        let block = output[start + STATE_OPEN.len()..end].trim();
+        let block = block
+            .strip_prefix("```json")
+            .or_else(|| block.strip_prefix("```"))
+            .and_then(|block| block.strip_suffix("```"))
+            .unwrap_or(block);
+        let mut update = match serde_json::from_str::<Value>(block) {
+            Ok(Value::Object(update)) => update,
+            _ => return Err(StateError::InvalidUpdate(block.to_string())),
+        };
+
+        let extracted = Self::variables()
+            .into_iter()
+            .filter(|variable| variable.extract)
+            .map(|variable| variable.name)
+            .collect::<Vec<_>>();
+        update.retain(|name, _| extracted.contains(&name.as_str()));
+        let updated = self.update(&update)?;
+        tracing::debug!(target: "rig", "Updated the conversation state variables {updated:?}");
+
+        Ok(
+            format!("{}{}", &output[..start], &output[end + STATE_CLOSE.len()..])
+                .trim()
+                .to_string(),
+        )
+    }
+
+    /// Load the state saved in `session`, or the default state if none was saved.
+    fn load(session: &Session) -> Result<Self, StateError>
+    where
+        Self: Default,
+    {
+        match session.metadata.get(STATE_KEY) {
+            Some(Value::Object(variables)) => Self::from_variables(variables),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Save the state in the metadata of `session`, under the key [STATE_KEY].
+    fn save(&self, session: &mut Session) -> Result<(), StateError> {
+        session
+            .metadata
+            .insert(STATE_KEY.to_string(), Value::Object(self.to_variables()?));
+        Ok(())
+    }
+}
+
+/// JSON value of the variable `name` (used by the `ConversationState` derive).
+#[doc(hidden)]
+pub fn to_variable<T: Serialize>(name: &str, value: &T) -> Result<Value, StateError> {
+    serde_json::to_value(value).map_err(|e| StateError::InvalidVariable(name.to_string(), e))
+}
+
+/// Value of the variable `name` from JSON (used by the `ConversationState` derive).
+#[doc(hidden)]
+pub fn from_variable<T: DeserializeOwned>(name: &str, value: &Value) -> Result<T, StateError> {
+    T::deserialize(value).map_err(|e| StateError::InvalidVariable(name.to_string(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Trip {
+        city: Option<String>,
+        nights: Option<u32>,
+    }
+
+    impl ConversationState for Trip {
+        fn variables() -> Vec<StateVariable> {
+            vec![
+                StateVariable {
+                    name: "city",
+                    description: "",
+                    extract: false,
+                },
+                StateVariable {
+                    name: "nights",
+                    description: "Number of nights",
+                    extract: true,
+                },
+            ]
+        }
+
+        fn to_variables(&self) -> Result<Variables, StateError> {
+            Ok(Variables::from_iter([
+                ("city".to_string(), to_variable("city", &self.city)?),
+                ("nights".to_string(), to_variable("nights", &self.nights)?),
+            ]))
+        }
+
+        fn update(&mut self, variables: &Variables) -> Result<Vec<String>, StateError> {
+            let mut updated = vec![];
+            if let Some(value) = variables.get("city") {
+                self.city = from_variable("city", value)?;
+                updated.push("city".to_string());
+            }
+            if let Some(value) = variables.get("nights") {
+                self.nights = from_variable("nights", value)?;
+                updated.push("nights".to_string());
+            }
+            Ok(updated)
+        }
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut trip = Trip::default();
+        assert!(Trip::instructions().ends_with("Variables:\n- nights: Number of nights"));
+
+        // The last block is applied, to the extracted variables only
+        let output = "<state>{\"nights\": 1}</state> Lisbon, 2 nights \
+            <state>{\"city\": \"Lisbon\", \"nights\": 2}</state>";
+        assert_eq!(
+            trip.extract(output).unwrap(),
+            "<state>{\"nights\": 1}</state> Lisbon, 2 nights"
+        );
+        assert_eq!(
+            trip,
+            Trip {
+                city: None,
+                nights: Some(2)
+            }
+        );
+
+        // Unterminated blocks are left in the output
+        assert_eq!(trip.extract("<state>{").unwrap(), "<state>{");
+
+        assert!(matches!(
+            trip.extract("<state>[1, 2]</state>"),
+            Err(StateError::InvalidUpdate(update)) if update == "[1, 2]"
+        ));
+        assert!(matches!(
+            trip.extract("<state>{\"nights\": -1}</state>"),
+            Err(StateError::InvalidVariable(name, _)) if name == "nights"
+        ));
+        assert_eq!(trip.nights, Some(2));
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let mut session = Session::new("42");
+        let trip = Trip {
+            city: Some("Lisbon".to_string()),
+            nights: Some(2),
+        };
+        trip.save(&mut session).unwrap();
+        assert_eq!(
+            session.metadata.get(STATE_KEY),
+            Some(&json!({"city": "Lisbon", "nights": 2}))
+        );
+        assert_eq!(Trip::load(&session).unwrap(), trip);
+
+        // States which are not objects are ignored, but invalid variables are reported
+        session
+            .metadata
+            .insert(STATE_KEY.to_string(), json!("corrupted"));
+        assert_eq!(Trip::load(&session).unwrap(), Trip::default());
+        session
+            .metadata
+            .insert(STATE_KEY.to_string(), json!({"nights": "two"}));
+        assert!(matches!(
+            Trip::load(&session),
+            Err(StateError::InvalidVariable(name, _)) if name == "nights"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_variable() {
+        // JSON objects only have string keys
+        let value = HashMap::from([(vec![1u8], 1)]);
+        assert!(matches!(
+            to_variable("map", &value),
+            Err(StateError::InvalidVariable(name, _)) if name == "map"
+        ));
+    }
+}
@@ -0,0 +1,333 @@
+//! Encryption at rest of the stored values (requires the `encryption` feature).
+//!
+//! An [EncryptedStore] wraps a [BlobStore] (e.g.: a [FileStore](super::FileStore) or
+//! `rig-sqlite`'s `SqliteStore`) and encrypts the values with AES-256-GCM before storing them,
+//! so that sensitive data (e.g.: the text of documents, or chat histories) is not stored in
+//! plaintext. The keys of the values are stored as is.
+//!
+//! The encryption keys are given by a [KeyProvider] (e.g.: fetching them from a secret manager).
+//! Each value records the id of the key encrypting it, so that keys can be rotated: new values
+//! are encrypted with the current key, and the values encrypted with older keys can still be
+//! decrypted as long as the provider knows these keys. [StaticKeys] provides fixed keys.
+//!
+//! # Example
+//! ```rust
+//! use rig::storage::{
+//!     encrypted::{EncryptedStore, EncryptionKey, StaticKeys},
+//!     FileStore, KvStore,
+//! };
+//!
+//! let key = EncryptionKey::new(secret_bytes);
+//! let store = EncryptedStore::new(FileStore::new("./data"), StaticKeys::new("2025-01", key));
+//!
+//! store.save("sessions/42/turns", &turns).await?;
+//! ```
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+
+use super::{BlobStore, StorageError};
+
+/// Magic bytes starting the encrypted values
+const MAGIC: &[u8] = b"RIGENC";
+const FORMAT_VERSION: u8 = 1;
+
+/// 256-bit AES key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// Key made of `bytes`, which must be 32 bytes long.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, StorageError> {
+        bytes.try_into().map(Self).map_err(|_| {
+            StorageError::EncryptionError(format!(
+                "Invalid key length: expected 32 bytes, found {}",
+                bytes.len()
+            ))
+        })
+    }
+}
+
+// The key is not printed
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// Provider of the encryption keys, identified by ids.
+pub trait KeyProvider: Send + Sync {
+    /// Id of the key encrypting the new values.
+    fn current_key_id(&self) -> String;
+
+    /// Key with the given id, e.g.: to decrypt a value encrypted before a key rotation.
+    fn key(&self, id: &str) -> Result<EncryptionKey, StorageError>;
+}
+
+/// Fixed keys: the current one, and the older ones still needed to decrypt the existing values.
+#[derive(Debug, Clone)]
+pub struct StaticKeys {
+    current: String,
+    keys: HashMap<String, EncryptionKey>,
+}
+
+impl StaticKeys {
+    /// Keys whose current key is `key`, with id `id`.
+    pub fn new(id: &str, key: EncryptionKey) -> Self {
+        Self {
+            current: id.to_string(),
+            keys: HashMap::from([(id.to_string(), key)]),
+        }
+    }
+
+    /// Add an older key, only used to decrypt the values it encrypted.
+    pub fn with_key(mut self, id: &str, key: EncryptionKey) -> Self {
+        self.keys.entry(id.to_string()).or_insert(key);
+        self
+    }
+}
+
+impl KeyProvider for StaticKeys {
+    fn current_key_id(&self) -> String {
+        self.current.clone()
+    }
+
+    fn key(&self, id: &str) -> Result<EncryptionKey, StorageError> {
+        self.keys
+            .get(id)
+            .cloned()
+            .ok_or_else(|| StorageError::EncryptionError(format!("Unknown key: {id}")))
+    }
+}
+
+/// Encrypt `plaintext` with the current key of `keys`. The additional data `aad` is
+/// authenticated but not encrypted, and must be given again to [decrypt] the value: it binds
+/// the value to its context (e.g.: its key in a store).
+pub fn encrypt(
+    keys: &impl KeyProvider,
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, StorageError> {
+    let key_id = keys.current_key_id();
+    let key_id_len = u8::try_from(key_id.len())
+        .map_err(|_| StorageError::EncryptionError("Key id longer than 255 bytes".to_string()))?;
+    let key = aead_key(&keys.key(&key_id)?)?;
+
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| encryption_error("Failed to generate a nonce"))?;
+    let mut ciphertext = plaintext.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(aad),
+        &mut ciphertext,
+    )
+    .map_err(|_| encryption_error("Failed to encrypt the value"))?;
+
+    let mut bytes =
+        Vec::with_capacity(MAGIC.len() + 2 + key_id.len() + NONCE_LEN + ciphertext.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(FORMAT_VERSION);
+    bytes.push(key_id_len);
+    bytes.extend_from_slice(key_id.as_bytes());
+    bytes.extend_from_slice(&nonce);
+    bytes.extend_from_slice(&ciphertext);
+    Ok(bytes)
+}
+
+/// Decrypt a value encrypted by [encrypt] with the same additional data `aad`, failing if the
+/// value was tampered with.
+pub fn decrypt(keys: &impl KeyProvider, aad: &[u8], bytes: &[u8]) -> Result<Vec<u8>, StorageError> {
+    let bytes = bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| encryption_error("The value is not encrypted"))?;
+    let truncated = || encryption_error("Truncated encrypted value");
+    let (&version, bytes) = bytes.split_first().ok_or_else(truncated)?;
+    if version != FORMAT_VERSION {
+        return Err(StorageError::EncryptionError(format!(
+            "Unsupported encryption format version {version}"
+        )));
+    }
+    let (&key_id_len, bytes) = bytes.split_first().ok_or_else(truncated)?;
+    let (key_id, bytes) = bytes
+        .split_at_checked(key_id_len as usize)
+        .ok_or_else(truncated)?;
+    let (nonce, ciphertext) = bytes.split_at_checked(NONCE_LEN).ok_or_else(truncated)?;
+
+    let key_id = std::str::from_utf8(key_id).map_err(|_| encryption_error("Invalid key id"))?;
+    let key = aead_key(&keys.key(key_id)?)?;
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| truncated())?;
+    let mut plaintext = ciphertext.to_vec();
+    let len = key
+        .open_in_place(nonce, Aad::from(aad), &mut plaintext)
+        .map_err(|_| encryption_error("Failed to decrypt the value (wrong key or tampered value)"))?
+        .len();
+    plaintext.truncate(len);
+    Ok(plaintext)
+}
+
+/// Whether `bytes` is a value encrypted by [encrypt].
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn aead_key(key: &EncryptionKey) -> Result<LessSafeKey, StorageError> {
+    UnboundKey::new(&AES_256_GCM, &key.0)
+        .map(LessSafeKey::new)
+        .map_err(|_| encryption_error("Invalid key"))
+}
+
+fn encryption_error(message: &str) -> StorageError {
+    StorageError::EncryptionError(message.to_string())
+}
+
+/// Store encrypting the values of another store (see the [module](self) documentation). Each
+/// value is bound to its key: a value copied to another key fails to decrypt.
+#[derive(Debug, Clone)]
+pub struct EncryptedStore<S: BlobStore, K: KeyProvider> {
+    store: S,
+    keys: K,
+}
+
+impl<S: BlobStore, K: KeyProvider> EncryptedStore<S, K> {
+    pub fn new(store: S, keys: K) -> Self {
+        Self { store, keys }
+    }
+
+    /// The wrapped store, storing the encrypted values.
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+}
+
+impl<S: BlobStore, K: KeyProvider> BlobStore for EncryptedStore<S, K> {
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError> {
+        self.store
+            .get(key)
+            .await?
+            .map(|value| decrypt(&self.keys, key.as_bytes(), &value).map(Bytes::from))
+            .transpose()
+    }
+
+    async fn put(&self, key: &str, value: Bytes) -> Result<(), StorageError> {
+        let value = encrypt(&self.keys, key.as_bytes(), &value)?;
+        self.store.put(key, value.into()).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.store.delete(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        self.store.list(prefix).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{InMemoryStore, KvStore};
+
+    #[tokio::test]
+    async fn test_encrypted_store() {
+        let inner = InMemoryStore::new();
+        let old_keys = StaticKeys::new("v1", EncryptionKey::new([1; 32]));
+        let store = EncryptedStore::new(inner.clone(), old_keys.clone());
+        store.save("docs/1", &"Top secret").await.unwrap();
+
+        // The value is stored encrypted
+        let stored = inner.get("docs/1").await.unwrap().unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!String::from_utf8_lossy(&stored).contains("secret"));
+        assert_eq!(
+            store.load::<String>("docs/1").await.unwrap().unwrap(),
+            "Top secret"
+        );
+
+        // After a rotation, the old values can still be read, and new values use the new key
+        let keys = StaticKeys::new("v2", EncryptionKey::new([2; 32]))
+            .with_key("v1", EncryptionKey::new([1; 32]));
+        let store = EncryptedStore::new(inner.clone(), keys);
+        store.save("docs/2", &"Classified").await.unwrap();
+        assert_eq!(
+            store.load::<String>("docs/1").await.unwrap().unwrap(),
+            "Top secret"
+        );
+        let old_store = EncryptedStore::new(inner.clone(), old_keys);
+        assert!(matches!(
+            old_store.load::<String>("docs/2").await,
+            Err(StorageError::EncryptionError(_))
+        ));
+
+        // Values are bound to their key, and plaintext values are rejected
+        inner.put("docs/3", stored).await.unwrap();
+        inner.put("docs/4", "\"plain\"".into()).await.unwrap();
+        for key in ["docs/3", "docs/4"] {
+            assert!(matches!(
+                store.get(key).await,
+                Err(StorageError::EncryptionError(_))
+            ));
+        }
+        assert_eq!(
+            store.keys("docs/").await.unwrap(),
+            vec!["docs/1", "docs/2", "docs/3", "docs/4"]
+        );
+    }
+
+    #[test]
+    fn test_encryption_errors() {
+        let error = |result: Result<Vec<u8>, StorageError>| match result {
+            Err(StorageError::EncryptionError(message)) => message,
+            result => panic!("Unexpected result: {result:?}"),
+        };
+
+        assert!(EncryptionKey::from_slice(&[1; 16]).is_err());
+        assert!(EncryptionKey::from_slice(&[1; 32]).is_ok());
+        let keys = StaticKeys::new(&"k".repeat(256), EncryptionKey::new([1; 32]));
+        assert_eq!(
+            error(encrypt(&keys, b"", b"value")),
+            "Key id longer than 255 bytes"
+        );
+
+        let keys = StaticKeys::new("v1", EncryptionKey::new([1; 32]));
+        let encrypted = encrypt(&keys, b"docs/1", b"value").unwrap();
+        assert_eq!(decrypt(&keys, b"docs/1", &encrypted).unwrap(), b"value");
+
+        // Truncated values, at any point of their header or payload
+        for len in [
+            MAGIC.len(),
+            MAGIC.len() + 1,
+            MAGIC.len() + 4,
+            MAGIC.len() + 10,
+        ] {
+            assert_eq!(
+                error(decrypt(&keys, b"docs/1", &encrypted[..len])),
+                "Truncated encrypted value",
+                "{len}"
+            );
+        }
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(error(decrypt(&keys, b"docs/1", &tampered)).starts_with("Failed to decrypt"));
+
+        let mut future = encrypted.clone();
+        future[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert!(error(decrypt(&keys, b"docs/1", &future)).starts_with("Unsupported"));
+
+        let mut invalid_id = encrypted;
+        invalid_id[MAGIC.len() + 2] = 0xff;
+        assert_eq!(
+            error(decrypt(&keys, b"docs/1", &invalid_id)),
+            "Invalid key id"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Filesystem implementation of a store.
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+
+use super::{BlobStore, StorageError};
+
+/// [FileStore] stores every value in a file of a root directory, at the path given by its key
+/// (e.g.: the value of `"sessions/42/history"` is stored in `<root>/sessions/42/history`).
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    root: PathBuf,
+}
+
+impl FileStore {
+    /// Create a store of the files of `root` (the directory is created when writing a value).
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Path of the file storing the value of `key`
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let valid = !key.is_empty()
+            && !key.contains('\\')
+            && key
+                .split('/')
+                .all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+        if !valid {
+            return Err(StorageError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+impl BlobStore for FileStore {
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError> {
+        match std::fs::read(self.path(key)?) {
+            Ok(value) => Ok(Some(value.into())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn put(&self, key: &str, value: Bytes) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(std::fs::write(path, value)?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match std::fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let mut keys = vec![];
+        if self.root.is_dir() {
+            list_files(&self.root, "", &mut keys)?;
+        }
+        keys.retain(|key| key.starts_with(prefix));
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// Add the keys of the files of `dir` (recursively) to `keys`
+fn list_files(dir: &Path, prefix: &str, keys: &mut Vec<String>) -> Result<(), StorageError> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let key = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            list_files(&entry.path(), &format!("{key}/"), keys)?;
+        } else {
+            keys.push(key);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::FileStore;
+    use crate::storage::{BlobStore, KvStore, StorageError};
+
+    #[tokio::test]
+    async fn test_file_store() {
+        let dir = TempDir::new().unwrap();
+        let store = FileStore::new(dir.path().join("store"));
+        assert!(store.keys("").await.unwrap().is_empty());
+
+        store.save("sessions/1/turns", &vec![1, 2]).await.unwrap();
+        store.put("sessions/2", "raw".into()).await.unwrap();
+        assert_eq!(
+            store.load::<Vec<u8>>("sessions/1/turns").await.unwrap(),
+            Some(vec![1, 2])
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("store/sessions/2")).unwrap(),
+            "raw"
+        );
+        assert_eq!(
+            store.keys("sessions/").await.unwrap(),
+            vec!["sessions/1/turns", "sessions/2"]
+        );
+
+        store.remove("sessions/1/turns").await.unwrap();
+        store.remove("sessions/1/turns").await.unwrap();
+        assert_eq!(store.get("sessions/1/turns").await.unwrap(), None);
+
+        assert!(matches!(
+            store.get("../secrets").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_file_store_errors() {
+        let dir = TempDir::new().unwrap();
+        let store = FileStore::new(dir.path());
+
+        for key in ["", "/etc/passwd", "a//b", "a\\b", "./a", "a/"] {
+            assert!(
+                matches!(
+                    store.put(key, "value".into()).await,
+                    Err(StorageError::InvalidKey(_))
+                ),
+                "{key}"
+            );
+        }
+
+        // Keys of directories, or under the key of a file, can't be read or written
+        store.put("sessions/1", "value".into()).await.unwrap();
+        assert!(matches!(
+            store.get("sessions").await,
+            Err(StorageError::IoError(_))
+        ));
+        assert!(matches!(
+            store.delete("sessions").await,
+            Err(StorageError::IoError(_))
+        ));
+        assert!(matches!(
+            store.put("sessions/1/turns", "value".into()).await,
+            Err(StorageError::IoError(_))
+        ));
+        assert!(matches!(
+            store.load::<Vec<u8>>("sessions/1").await,
+            Err(StorageError::JsonError(_))
+        ));
+    }
+}
@@ -0,0 +1,237 @@
+//! Pluggable storage for agent artifacts (e.g.: chat histories, caches, checkpoints, sessions).
+//!
+//! The module defines two traits:
+//! - [BlobStore]: stores raw bytes by key (e.g.: documents or serialized artifacts)
+//! - [KvStore]: stores serializable values by key, encoded as JSON. Every [BlobStore]
+//!   is a [KvStore].
+//!
+//! Keys are `/`-separated paths (e.g.: `"sessions/42/history"`), so that related artifacts
+//! can be listed by prefix.
+//!
+//! The following implementations are provided:
+//! - [InMemoryStore]: stores the values in memory
+//! - [FileStore]: stores the values as files of a directory
+//! - [RedisStore](redis::RedisStore): stores the values in Redis (requires the `redis` feature)
+//! - [S3Store](s3::S3Store): stores the values as objects of an S3 bucket (requires the `s3` feature)
+//!
+//! The `rig-sqlite` crate provides a `SqliteStore`, storing the values in a table of a SQLite database.
+//!
+//! Any of these stores can be wrapped in an [EncryptedStore](encrypted::EncryptedStore), which
+//! encrypts the values at rest with AES-GCM (requires the `encryption` feature).
+//!
+//! # Example
+//! ```rust
+//! use rig::storage::{InMemoryStore, KvStore};
+//!
+//! let store = InMemoryStore::new();
+//! store.save("sessions/42/turns", &vec!["Hello!"]).await?;
+//!
+//! let turns: Option<Vec<String>> = store.load("sessions/42/turns").await?;
+//! assert_eq!(store.keys("sessions/").await?, vec!["sessions/42/turns"]);
+//! ```
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(feature = "encryption")]
+pub mod encrypted;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fs;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "s3")]
+pub mod s3;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use fs::FileStore;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    /// Io error (e.g.: reading or writing a file)
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Json error (e.g.: serialization, deserialization, etc.)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    /// Error encrypting or decrypting a value (e.g.: unknown key, or tampered value)
+    #[error("EncryptionError: {0}")]
+    EncryptionError(String),
+
+    /// Error returned by the storage backend
+    #[error("BackendError: {0}")]
+    BackendError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+/// Storage of raw bytes by key.
+pub trait BlobStore: Send + Sync {
+    /// Get the value of `key`, if any.
+    fn get(&self, key: &str) -> impl Future<Output = Result<Option<Bytes>, StorageError>> + Send;
+
+    /// Set the value of `key`, replacing the existing one.
+    fn put(&self, key: &str, value: Bytes)
+        -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Delete `key` (deleting a missing key is not an error).
+    fn delete(&self, key: &str) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Keys starting with `prefix`, sorted.
+    fn list(&self, prefix: &str) -> impl Future<Output = Result<Vec<String>, StorageError>> + Send;
+}
+
+/// Storage of serializable values by key.
+pub trait KvStore: Send + Sync {
+    /// Load the value of `key`, if any.
+    fn load<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> impl Future<Output = Result<Option<T>, StorageError>> + Send;
+
+    /// Save `value` as the value of `key`, replacing the existing one.
+    fn save<T: Serialize + Sync>(
+        &self,
+        key: &str,
+        value: &T,
+    ) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Remove `key` (removing a missing key is not an error).
+    fn remove(&self, key: &str) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Keys starting with `prefix`, sorted.
+    fn keys(&self, prefix: &str) -> impl Future<Output = Result<Vec<String>, StorageError>> + Send;
+}
+
+impl<B: BlobStore> KvStore for B {
+    async fn load<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, StorageError> {
+        Ok(self
+            .get(key)
+            .await?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()?)
+    }
+
+    async fn save<T: Serialize + Sync>(&self, key: &str, value: &T) -> Result<(), StorageError> {
+        let value = serde_json::to_vec(value)?;
+        self.put(key, value.into()).await
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), StorageError> {
+        self.delete(key).await
+    }
+
+    async fn keys(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        self.list(prefix).await
+    }
+}
+
+/// In-memory store. Clones of the store share the same values.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    values: Arc<RwLock<BTreeMap<String, Bytes>>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlobStore for InMemoryStore {
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError> {
+        Ok(self
+            .values
+            .read()
+            .expect("lock is not poisoned")
+            .get(key)
+            .cloned())
+    }
+
+    async fn put(&self, key: &str, value: Bytes) -> Result<(), StorageError> {
+        self.values
+            .write()
+            .expect("lock is not poisoned")
+            .insert(key.to_string(), value);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.values
+            .write()
+            .expect("lock is not poisoned")
+            .remove(key);
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .values
+            .read()
+            .expect("lock is not poisoned")
+            .range(prefix.to_string()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlobStore, InMemoryStore, KvStore, StorageError};
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryStore::new();
+        store.save("sessions/1/turns", &vec![1, 2]).await.unwrap();
+        store.save("sessions/2/turns", &vec![3]).await.unwrap();
+        store.put("other", "raw".into()).await.unwrap();
+
+        assert_eq!(
+            store.load::<Vec<u8>>("sessions/1/turns").await.unwrap(),
+            Some(vec![1, 2])
+        );
+        assert_eq!(store.get("other").await.unwrap(), Some("raw".into()));
+        assert_eq!(
+            store.keys("sessions/").await.unwrap(),
+            vec!["sessions/1/turns", "sessions/2/turns"]
+        );
+
+        store.remove("sessions/1/turns").await.unwrap();
+        assert_eq!(
+            store.load::<Vec<u8>>("sessions/1/turns").await.unwrap(),
+            None
+        );
+        // Clones share the values
+        assert_eq!(
+            store.clone().keys("").await.unwrap(),
+            vec!["other", "sessions/2/turns"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_values() {
+        let store = InMemoryStore::new();
+        store.put("raw", "not json".into()).await.unwrap();
+        store.save("numbers", &vec![1, 2]).await.unwrap();
+
+        assert!(matches!(
+            store.load::<Vec<u8>>("raw").await,
+            Err(StorageError::JsonError(_))
+        ));
+        assert!(matches!(
+            store.load::<String>("numbers").await,
+            Err(StorageError::JsonError(_))
+        ));
+        // Removing a missing value is not an error
+        store.remove("missing").await.unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+//! Redis implementation of a store (requires the `redis` feature).
+use bytes::Bytes;
+use redis::{aio::ConnectionManager, AsyncCommands};
+
+use super::{BlobStore, StorageError};
+
+/// [RedisStore] stores every value as a Redis string, optionally namespacing the keys
+/// with a prefix (e.g.: `"rig:"`).
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: ConnectionManager,
+    namespace: String,
+}
+
+impl RedisStore {
+    /// Create a store using the given connection.
+    pub fn new(connection: ConnectionManager) -> Self {
+        Self {
+            connection,
+            namespace: String::new(),
+        }
+    }
+
+    /// Create a store connected to the Redis server at `url` (e.g.: `redis://127.0.0.1/`).
+    pub async fn from_url(url: &str) -> Result<Self, StorageError> {
+        let client = redis::Client::open(url).map_err(backend_error)?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .map_err(backend_error)?;
+        Ok(Self::new(connection))
+    }
+
+    /// Prefix the Redis keys of the values with `namespace`.
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.namespace)
+    }
+}
+
+impl BlobStore for RedisStore {
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError> {
+        let value: Option<Vec<u8>> = self
+            .connection
+            .clone()
+            .get(self.key(key))
+            .await
+            .map_err(backend_error)?;
+        Ok(value.map(Bytes::from))
+    }
+
+    async fn put(&self, key: &str, value: Bytes) -> Result<(), StorageError> {
+        self.connection
+            .clone()
+            .set::<_, _, ()>(self.key(key), value.as_ref())
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.connection
+            .clone()
+            .del::<_, ()>(self.key(key))
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let pattern = format!("{}*", escape_pattern(&self.key(prefix)));
+        let mut connection = self.connection.clone();
+        let mut keys = vec![];
+        {
+            let mut iter = connection
+                .scan_match::<_, String>(pattern)
+                .await
+                .map_err(backend_error)?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key[self.namespace.len()..].to_string());
+            }
+        }
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+}
+
+/// Escape the special characters of a Redis glob-style pattern
+fn escape_pattern(pattern: &str) -> String {
+    pattern
+        .chars()
+        .flat_map(|c| match c {
+            '*' | '?' | '[' | ']' | '\\' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+fn backend_error(error: redis::RedisError) -> StorageError {
+    StorageError::BackendError(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_pattern;
+
+    #[test]
+    fn test_escape_pattern() {
+        assert_eq!(escape_pattern("rig:a*b?[c]"), r"rig:a\*b\?\[c\]");
+    }
+}
@@ -0,0 +1,233 @@
+//! S3 implementation of a store (requires the `s3` feature).
+use aws_sdk_s3::{primitives::ByteStream, Client};
+use bytes::Bytes;
+
+use super::{BlobStore, StorageError};
+
+/// [S3Store] stores every value as an object of an S3 bucket, optionally prefixing the object
+/// keys (e.g.: `"rig/"`).
+#[derive(Debug, Clone)]
+pub struct S3Store {
+    client: Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Store {
+    /// Create a store of the objects of `bucket`.
+    pub fn new(client: Client, bucket: &str) -> Self {
+        Self {
+            client,
+            bucket: bucket.to_string(),
+            prefix: String::new(),
+        }
+    }
+
+    /// Create a store of the objects of `bucket`, using the AWS configuration of the environment.
+    pub async fn from_env(bucket: &str) -> Self {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        Self::new(Client::new(&config), bucket)
+    }
+
+    /// Prefix the object keys of the values with `prefix`.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+}
+
+impl BlobStore for S3Store {
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.key(key))
+            .send()
+            .await;
+
+        match output {
+            Ok(output) => {
+                let body = output.body.collect().await.map_err(backend_error)?;
+                Ok(Some(body.into_bytes()))
+            }
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+            Err(e) => Err(backend_error(e)),
+        }
+    }
+
+    async fn put(&self, key: &str, value: Bytes) -> Result<(), StorageError> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.key(key))
+            .body(ByteStream::from(value))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(self.key(key))
+            .send()
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(self.key(prefix))
+            .into_paginator()
+            .send();
+
+        let mut keys = vec![];
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(backend_error)?;
+            keys.extend(
+                page.contents()
+                    .iter()
+                    .filter_map(|object| object.key())
+                    .map(|key| key[self.prefix.len()..].to_string()),
+            );
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+fn backend_error(error: impl std::error::Error + Send + Sync + 'static) -> StorageError {
+    StorageError::BackendError(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_s3::config::{retry::RetryConfig, BehaviorVersion, Credentials, Region};
+    use httpmock::{
+        Method::{DELETE, GET, PUT},
+        MockServer,
+    };
+
+    use super::*;
+
+    fn store(server: &MockServer) -> S3Store {
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("key", "secret", None, None, "test"))
+            .endpoint_url(server.base_url())
+            .force_path_style(true)
+            .retry_config(RetryConfig::disabled())
+            .build();
+        S3Store::new(Client::from_conf(config), "bucket").prefix("rig/")
+    }
+
+    fn error(code: &str) -> String {
+        format!("<Error><Code>{code}</Code><Message>{code}</Message></Error>")
+    }
+
+    #[tokio::test]
+    async fn test_get() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/bucket/rig/a");
+            then.status(200).body("value");
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/bucket/rig/missing");
+            then.status(404).body(error("NoSuchKey"));
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/bucket/rig/denied");
+            then.status(403).body(error("AccessDenied"));
+        });
+
+        let store = store(&server);
+        assert_eq!(
+            store.get("a").await.unwrap(),
+            Some(Bytes::from_static(b"value"))
+        );
+        assert_eq!(store.get("missing").await.unwrap(), None);
+        assert!(matches!(
+            store.get("denied").await,
+            Err(StorageError::BackendError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_and_delete() {
+        let server = MockServer::start();
+        let put = server.mock(|when, then| {
+            when.method(PUT).path("/bucket/rig/a").body("value");
+            then.status(200);
+        });
+        server.mock(|when, then| {
+            when.method(PUT).path("/bucket/rig/denied");
+            then.status(403).body(error("AccessDenied"));
+        });
+        let delete = server.mock(|when, then| {
+            when.method(DELETE).path("/bucket/rig/a");
+            then.status(204);
+        });
+        server.mock(|when, then| {
+            when.method(DELETE).path("/bucket/rig/denied");
+            then.status(500).body(error("InternalError"));
+        });
+
+        let store = store(&server);
+        store.put("a", Bytes::from_static(b"value")).await.unwrap();
+        store.delete("a").await.unwrap();
+        put.assert();
+        delete.assert();
+
+        assert!(matches!(
+            store.put("denied", Bytes::new()).await,
+            Err(StorageError::BackendError(_))
+        ));
+        assert!(matches!(
+            store.delete("denied").await,
+            Err(StorageError::BackendError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET)
+                .path("/bucket/")
+                .query_param("list-type", "2")
+                .query_param("prefix", "rig/docs/");
+            then.status(200).body(
+                "<ListBucketResult><Name>bucket</Name><IsTruncated>false</IsTruncated>\
+                <Contents><Key>rig/docs/b</Key></Contents>\
+                <Contents><Key>rig/docs/a</Key></Contents>\
+                </ListBucketResult>",
+            );
+        });
+        server.mock(|when, then| {
+            when.method(GET)
+                .path("/bucket/")
+                .query_param("prefix", "rig/denied/");
+            then.status(403).body(error("AccessDenied"));
+        });
+
+        let store = store(&server);
+        assert_eq!(store.list("docs/").await.unwrap(), vec!["docs/a", "docs/b"]);
+        assert!(matches!(
+            store.list("denied/").await,
+            Err(StorageError::BackendError(_))
+        ));
+    }
+}
@@ -14,13 +14,15 @@ use crate::completion::{
     CompletionError, CompletionModel, CompletionRequest, CompletionRequestBuilder, Message,
 };
 use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
 use std::boxed::Box;
 use std::fmt::{Display, Formatter};
 use std::future::Future;
 use std::pin::Pin;
 
 /// Enum representing a streaming chunk from the model
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "content", rename_all = "snake_case")]
 pub enum StreamingChoice {
     /// A text chunk from a message response
     Message(String),
@@ -82,7 +84,14 @@ pub trait StreamingCompletionModel: CompletionModel {
     fn stream(
         &self,
         request: CompletionRequest,
-    ) -> impl Future<Output = Result<StreamingResult, CompletionError>>;
+    ) -> impl Future<Output = Result<StreamingResult, CompletionError>> + Send;
+
+    /// Whether the model continues an assistant response given as the last message of the
+    /// request (a "prefill"), which [ResumableModel](crate::completion::ResumableModel) uses to
+    /// resume the interrupted responses.
+    fn supports_prefill(&self) -> bool {
+        false
+    }
 }
 
 /// helper function to stream a completion request to stdout
@@ -102,7 +111,7 @@ pub async fn stream_to_stdout<M: StreamingCompletionModel>(
                     .tools
                     .call(&name, params.to_string())
                     .await
-                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
                 println!("\nResult: {}", res);
             }
             Err(e) => {
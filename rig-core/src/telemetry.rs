@@ -0,0 +1,459 @@
+//! Tracing instrumentation of agents and models, for observability of LLM pipelines.
+//!
+//! Rig records the following [tracing] spans (with the `rig` target), whose durations are the
+//! latencies of the corresponding operations:
+//! - `agent.prompt`: a prompt (or chat) answered by an [Agent](crate::agent::Agent), with the
+//!   `gen_ai.request.model`, `rig.turn` and total `gen_ai.usage.input_tokens` /
+//!   `gen_ai.usage.output_tokens` of its completions, and the error if it failed
+//! - `completion`: a request sent to a completion model, with its `gen_ai.request.model` and
+//!   `gen_ai.usage.input_tokens` / `gen_ai.usage.output_tokens`
+//! - `agent.retrieve`: the retrieval of the dynamic context of a prompt, with the number of
+//!   retrieved documents (`rig.documents`) and their comma-separated `rig.document_ids`
+//! - `agent.tool`: a tool called by an agent, with its `gen_ai.tool.name`,
+//!   `gen_ai.tool.call.id`, and the `error` if the call failed
+//! - `embeddings.build`: the embedding of the documents of an
+//!   [EmbeddingsBuilder](crate::embeddings::EmbeddingsBuilder), with its `gen_ai.request.model`,
+//!   number of `rig.documents` and `gen_ai.usage.input_tokens`
+//!
+//! The `agent.prompt` and `completion` spans also record the `rig.session_id` and `rig.run_id`
+//! of the [RunContext] they are part of (see below).
+//!
+//! The model names are the ones reported by [CompletionModel::model_name] and
+//! [EmbeddingModel::model_name], and the field names follow the OpenTelemetry semantic
+//! conventions for generative AI where applicable.
+//!
+//! The spans can be collected by any `tracing` subscriber. With the `otel` feature, [layer] and
+//! [otlp_tracer_provider] export them to an OpenTelemetry collector over OTLP (gRPC).
+//! Without OpenTelemetry infrastructure, the runs of agents can also be exported as JSON Lines
+//! with the [trace](crate::trace) module.
+//!
+//! # Example
+//! ```rust
+//! use rig::telemetry;
+//! use tracing_subscriber::prelude::*;
+//!
+//! let provider = telemetry::otlp_tracer_provider("support-bot", "http://localhost:4317")?;
+//! tracing_subscriber::registry()
+//!     .with(telemetry::layer(&provider))
+//!     .with(tracing_subscriber::fmt::layer())
+//!     .init();
+//!
+//! let response = agent.prompt("Where is my order?").await?;
+//!
+//! // Export the pending spans before exiting
+//! provider.shutdown()?;
+//! ```
+//!
+//! # Session and run ids
+//! The activity of one user interaction can be correlated across services with the ids of its
+//! [RunContext]: a session id, shared by the interactions of a conversation, and a run id,
+//! unique to each prompt. Each prompt (or chat) of an [Agent](crate::agent::Agent) runs in its
+//! own run of the agent's [session](crate::agent::AgentBuilder::session_id), unless it is
+//! already part of a run (e.g.: a run started by the application for an incoming request, or the
+//! run of a pipeline or of another agent calling it as a tool), which it then joins.
+//!
+//! The ids are recorded in the spans, in the [AgentEvent](crate::outbox::AgentEvent)s of the
+//! outbox and, with the [RunHeaders](crate::middleware::RunHeaders) middleware, in the headers
+//! of the provider requests. Tools and middleware can read them with [RunContext::current].
+//! ```rust
+//! use rig::telemetry::RunContext;
+//!
+//! let run = RunContext::new(&request.session_id);
+//! tracing::info!(run_id = run.run_id(), "Handling request");
+//!
+//! // The completions and tool calls of the agents of the pipeline share the run
+//! let answer = run.scope(pipeline.call(request.question)).await;
+//! ```
+//!
+//! [CompletionModel::model_name]: crate::completion::CompletionModel::model_name
+//! [EmbeddingModel::model_name]: crate::embeddings::EmbeddingModel::model_name
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock,
+    },
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "otel")]
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::WithExportConfig;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::{
+    runtime,
+    trace::{Tracer, TracerProvider},
+    Resource,
+};
+#[cfg(feature = "otel")]
+use tracing_opentelemetry::OpenTelemetryLayer;
+#[cfg(feature = "otel")]
+use tracing_subscriber::registry::LookupSpan;
+
+/// Tracer provider exporting spans in batches to the OTLP (gRPC) `endpoint` of a collector (e.g.:
+/// `http://localhost:4317`), on behalf of the service called `service_name`.
+///
+/// Must be called from a tokio runtime, which runs the exports in the background.
+#[cfg(feature = "otel")]
+pub fn otlp_tracer_provider(
+    service_name: &str,
+    endpoint: &str,
+) -> Result<TracerProvider, opentelemetry::trace::TraceError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            service_name.to_string(),
+        )]))
+        .build())
+}
+
+/// `tracing` layer exporting the spans to OpenTelemetry with the tracer of `provider`.
+#[cfg(feature = "otel")]
+pub fn layer<S>(provider: &TracerProvider) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer("rig"))
+}
+
+/// Ids of the user interaction an operation is part of: the id of the session (e.g.: a
+/// conversation) and the id of the run (e.g.: a prompt and the completions, retrievals and tool
+/// calls answering it).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RunContext {
+    session_id: String,
+    run_id: String,
+}
+
+thread_local! {
+    static CURRENT_RUN: RefCell<Option<RunContext>> = const { RefCell::new(None) };
+}
+
+impl RunContext {
+    /// New run of the session `session_id`, with a generated run id
+    pub fn new(session_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            run_id: generate_id(),
+        }
+    }
+
+    /// Run of the session `session_id` with the given `run_id` (e.g.: the id of a request
+    /// received from another service)
+    pub fn with_run_id(session_id: &str, run_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            run_id: run_id.to_string(),
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Run of the future being polled, if it runs in the [scope](Self::scope) of a run
+    pub fn current() -> Option<RunContext> {
+        CURRENT_RUN.with(|run| run.borrow().clone())
+    }
+
+    /// Run `future` as part of this run: [RunContext::current] returns this run while the
+    /// future (and the futures it awaits) is polled. Tasks spawned by the future are not part of
+    /// the run, unless they are scoped themselves.
+    pub fn scope<F: Future>(self, future: F) -> Scoped<F> {
+        Scoped {
+            run: self,
+            future: Box::pin(future),
+        }
+    }
+}
+
+/// Future running in the scope of a run, returned by [RunContext::scope]
+pub struct Scoped<F> {
+    run: RunContext,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        /// Restores the enclosing run once the future is polled, even if it panics
+        struct Restore(Option<RunContext>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_RUN.with(|run| *run.borrow_mut() = self.0.take());
+            }
+        }
+
+        let run = self.run.clone();
+        let _restore = Restore(CURRENT_RUN.with(|current| current.replace(Some(run))));
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Random 128 bits id, as 32 hexadecimal characters (like the trace ids of OpenTelemetry)
+pub(crate) fn generate_id() -> String {
+    static STATE: LazyLock<RandomState> = LazyLock::new(RandomState::new);
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let [high, low] = [0u8, 1].map(|half| {
+        let mut hasher = STATE.build_hasher();
+        hasher.write_u64(count);
+        hasher.write_u8(half);
+        hasher.finish()
+    });
+    format!("{high:016x}{low:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use serde_json::json;
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+    use super::RunContext;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Prompt, Usage,
+        },
+        embeddings::{Embedding, EmbeddingError, EmbeddingModel, EmbeddingsBuilder},
+        message::AssistantContent,
+        vector_store::in_memory_store::InMemoryVectorStore,
+        OneOrMany,
+    };
+
+    type Fields = HashMap<String, String>;
+
+    /// Layer recording the names and fields of the spans
+    #[derive(Clone, Default)]
+    struct Spans(Arc<Mutex<Vec<(String, Fields)>>>);
+
+    struct Visitor<'a>(&'a mut Fields);
+
+    impl Visit for Visitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    impl Spans {
+        fn get(&self, name: &str) -> Vec<Fields> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(span, _)| span == name)
+                .map(|(_, fields)| fields.clone())
+                .collect()
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Spans {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = Fields::new();
+            attrs.record(&mut Visitor(&mut fields));
+            let mut spans = self.0.lock().unwrap();
+            spans.push((attrs.metadata().name().to_string(), fields));
+            ctx.span(id)
+                .unwrap()
+                .extensions_mut()
+                .insert(spans.len() - 1);
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            let span = ctx.span(id).unwrap();
+            let index = *span.extensions().get::<usize>().unwrap();
+            values.record(&mut Visitor(&mut self.0.lock().unwrap()[index].1));
+        }
+    }
+
+    /// Model calling a missing tool, then answering once it gets the result of the call
+    #[derive(Clone)]
+    struct ToolCallingModel;
+
+    impl CompletionModel for ToolCallingModel {
+        type Response = ();
+
+        fn model_name(&self) -> Option<&str> {
+            Some("mock-model")
+        }
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let choice = if request.chat_history.is_empty() {
+                AssistantContent::tool_call("call_1", "missing", json!({}))
+            } else {
+                AssistantContent::text("Done")
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(choice),
+                usage: Some(Usage::new(10, 2)),
+                raw_response: (),
+            })
+        }
+    }
+
+    /// Embedding model embedding texts by their length
+    #[derive(Clone)]
+    struct LengthModel;
+
+    impl EmbeddingModel for LengthModel {
+        const MAX_DOCUMENTS: usize = 8;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        fn model_name(&self) -> Option<&str> {
+            Some("mock-embedding")
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|text| Embedding {
+                    vec: vec![text.len() as f64],
+                    document: text,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spans() {
+        let spans = Spans::default();
+        let _guard = tracing_subscriber::registry()
+            .with(spans.clone())
+            .set_default();
+
+        let embeddings = EmbeddingsBuilder::new(LengthModel)
+            .documents(["Cats", "Dogs"].map(String::from))
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let index = InMemoryVectorStore::from_documents_with_ids(
+            embeddings
+                .into_iter()
+                .map(|(doc, embeddings)| (doc.clone(), doc, embeddings)),
+        )
+        .index(LengthModel);
+
+        let agent = AgentBuilder::new(ToolCallingModel)
+            .dynamic_context(1, index)
+            .max_turns(1)
+            .build();
+        assert_eq!(agent.prompt("Cows").await.unwrap(), "Done");
+
+        let build = &spans.get("embeddings.build")[0];
+        assert_eq!(build["gen_ai.request.model"], "mock-embedding");
+        assert_eq!(build["rig.documents"], "2");
+
+        let prompt = &spans.get("agent.prompt")[0];
+        assert_eq!(prompt["gen_ai.request.model"], "mock-model");
+        assert_eq!(prompt["gen_ai.usage.input_tokens"], "20");
+        assert_eq!(prompt["gen_ai.usage.output_tokens"], "4");
+
+        let completions = spans.get("completion");
+        assert_eq!(completions.len(), 2);
+        assert_eq!(completions[0]["gen_ai.usage.input_tokens"], "10");
+
+        let retrievals = spans.get("agent.retrieve");
+        assert_eq!(retrievals.len(), 2);
+        assert_eq!(retrievals[0]["rig.documents"], "1");
+        assert!(["Cats", "Dogs"].contains(&retrievals[0]["rig.document_ids"].as_str()));
+
+        let tool = &spans.get("agent.tool")[0];
+        assert_eq!(tool["gen_ai.tool.name"], "missing");
+        assert_eq!(tool["gen_ai.tool.call.id"], "call_1");
+        assert!(tool["error"].contains("missing"));
+    }
+
+    #[tokio::test]
+    async fn test_run_context() {
+        let spans = Spans::default();
+        let _guard = tracing_subscriber::registry()
+            .with(spans.clone())
+            .set_default();
+
+        assert_eq!(RunContext::current(), None);
+        let run = RunContext::new("session");
+        assert_eq!(run.run_id().len(), 32);
+        assert_ne!(run.run_id(), RunContext::new("session").run_id());
+
+        // Nested scopes, restored once polled
+        let outer = run.clone();
+        run.clone()
+            .scope(async move {
+                assert_eq!(RunContext::current(), Some(outer.clone()));
+                let inner = RunContext::with_run_id("session", "inner");
+                inner
+                    .clone()
+                    .scope(async move { assert_eq!(RunContext::current(), Some(inner)) })
+                    .await;
+                assert_eq!(RunContext::current(), Some(outer));
+            })
+            .await;
+        assert_eq!(RunContext::current(), None);
+
+        // Each prompt gets a new run of the session of the agent
+        let agent = AgentBuilder::new(ToolCallingModel)
+            .max_turns(1)
+            .session_id("conversation")
+            .build();
+        assert_eq!(agent.session_id(), "conversation");
+        agent.prompt("Cows").await.unwrap();
+        agent.prompt("Cows").await.unwrap();
+        let prompts = spans.get("agent.prompt");
+        assert_eq!(prompts[0]["rig.session_id"], "conversation");
+        assert_ne!(prompts[0]["rig.run_id"], prompts[1]["rig.run_id"]);
+        let completions = spans.get("completion");
+        assert_eq!(completions[0]["rig.run_id"], prompts[0]["rig.run_id"]);
+        assert_eq!(completions[2]["rig.run_id"], prompts[1]["rig.run_id"]);
+
+        // Prompts which are part of a run join it
+        run.clone()
+            .scope(async { agent.prompt("Cows").await.unwrap() })
+            .await;
+        let prompt = &spans.get("agent.prompt")[2];
+        assert_eq!(prompt["rig.session_id"], "session");
+        assert_eq!(prompt["rig.run_id"], run.run_id());
+    }
+}
@@ -0,0 +1,389 @@
+//! Token counting, e.g. to fit prompts and documents in the context window of a model.
+//!
+//! The [TokenCounter] trait counts the tokens of texts and truncates texts to a number of
+//! tokens. It is implemented by:
+//! - [Estimate]: a rough estimate of 4 characters per token, without vocabulary
+//! - `Tokenizer` (with the `tokenizer` feature): the byte pair encoding (BPE) tokenizers of the
+//!   OpenAI models, compatible with `tiktoken` and loaded from its vocabulary files
+//!
+//! Agents use a token counter to trim their dynamic context to its token budget and to the
+//! context window of their model, see
+//! [RagConfig::context_budget](crate::agent::RagConfig::context_budget) and
+//! [RagConfig::context_window](crate::agent::RagConfig::context_window).
+//!
+//! # Example
+//! ```rust
+//! use rig::tokenizer::{Encoding, TokenCounter, Tokenizer};
+//!
+//! let encoding = Encoding::for_model("gpt-4o").unwrap();
+//! let tokenizer = Tokenizer::download(encoding).await?;
+//! // Or, from a vocabulary file downloaded beforehand:
+//! let tokenizer = Tokenizer::from_file("o200k_base.tiktoken", encoding)?;
+//!
+//! assert_eq!(tokenizer.count_tokens("Hello world!"), 3);
+//! ```
+#[cfg(feature = "tokenizer")]
+use std::{collections::HashMap, path::Path};
+
+#[cfg(feature = "tokenizer")]
+use base64::{prelude::BASE64_STANDARD, Engine};
+
+/// Trait for token counters
+pub trait TokenCounter: Send + Sync {
+    /// Number of tokens of `text`
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Longest prefix of `text` of at most `max_tokens` tokens
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str;
+
+    /// Longest prefix of `text` of at most `max_tokens` tokens ending with a sentence, i.e.: at
+    /// a line break or a `.`, `!` or `?` followed by a whitespace. Empty if the first sentence of
+    /// `text` is longer than `max_tokens`.
+    fn truncate_sentences<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        let truncated = self.truncate(text, max_tokens);
+        if truncated.len() == text.len() {
+            return text;
+        }
+        let end = truncated
+            .char_indices()
+            .rev()
+            .find(|(i, c)| {
+                *c == '\n'
+                    || (matches!(c, '.' | '!' | '?')
+                        && text[i + 1..]
+                            .chars()
+                            .next()
+                            .is_some_and(char::is_whitespace))
+            })
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or_default();
+        text[..end].trim_end()
+    }
+}
+
+/// Rough estimate of the tokens of texts, counting 4 characters per token.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Estimate;
+
+impl TokenCounter for Estimate {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        match text.char_indices().nth(4 * max_tokens) {
+            Some((end, _)) => &text[..end],
+            None => text,
+        }
+    }
+}
+
+#[cfg(feature = "tokenizer")]
+#[derive(Debug, thiserror::Error)]
+pub enum TokenizerError {
+    /// Malformed vocabulary file
+    #[error("Invalid vocabulary: {0}")]
+    InvalidVocabulary(String),
+
+    #[error("Io error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[cfg(feature = "http")]
+    #[error("Http error: {0}")]
+    HttpError(#[from] reqwest::Error),
+}
+
+/// Byte pair encodings of the OpenAI models
+#[cfg(feature = "tokenizer")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Encoding of GPT-4, GPT-3.5 and of the `text-embedding-3` and `text-embedding-ada-002`
+    /// embedding models
+    Cl100kBase,
+    /// Encoding of GPT-4o, GPT-4.1 and of the o-series models
+    O200kBase,
+}
+
+#[cfg(feature = "tokenizer")]
+impl Encoding {
+    /// Encoding of the given OpenAI model, if known
+    pub fn for_model(model: &str) -> Option<Self> {
+        const O200K: [&str; 8] = [
+            "gpt-4o",
+            "gpt-4.1",
+            "gpt-4.5",
+            "gpt-5",
+            "chatgpt-4o",
+            "o1",
+            "o3",
+            "o4",
+        ];
+        const CL100K: [&str; 4] = ["gpt-4", "gpt-3.5", "text-embedding-3", "text-embedding-ada"];
+
+        if O200K.iter().any(|prefix| model.starts_with(prefix)) {
+            Some(Self::O200kBase)
+        } else if CL100K.iter().any(|prefix| model.starts_with(prefix)) {
+            Some(Self::Cl100kBase)
+        } else {
+            None
+        }
+    }
+
+    /// Name of the encoding in `tiktoken`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Cl100kBase => "cl100k_base",
+            Self::O200kBase => "o200k_base",
+        }
+    }
+
+    /// URL of the vocabulary file of the encoding, published by OpenAI
+    pub fn vocabulary_url(&self) -> String {
+        format!(
+            "https://openaipublic.blob.core.windows.net/encodings/{}.tiktoken",
+            self.name()
+        )
+    }
+
+    /// Pattern splitting texts in pieces that are encoded separately. The `\s+(?!\S)`
+    /// alternative of `tiktoken` (not supported by the `regex` crate) is emulated by
+    /// [Tokenizer::pieces].
+    fn pattern(&self) -> &'static str {
+        match self {
+            Self::Cl100kBase => concat!(
+                r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}",
+                r"| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+",
+            ),
+            Self::O200kBase => concat!(
+                r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+",
+                r"(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+                r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*",
+                r"(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+                r"|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+",
+            ),
+        }
+    }
+}
+
+/// Byte pair encoding tokenizer, compatible with `tiktoken`.
+///
+/// Special tokens (e.g.: `<|endoftext|>`) are not supported: they are encoded as ordinary text,
+/// like `tiktoken`'s `encode_ordinary`.
+#[cfg(feature = "tokenizer")]
+#[derive(Clone)]
+pub struct Tokenizer {
+    encoder: HashMap<Vec<u8>, u32>,
+    decoder: HashMap<u32, Vec<u8>>,
+    pattern: regex::Regex,
+}
+
+#[cfg(feature = "tokenizer")]
+impl Tokenizer {
+    /// Create a tokenizer from the content of a `tiktoken` vocabulary file (each line holding a
+    /// base64-encoded token and its rank) of the given encoding.
+    pub fn from_tiktoken(vocabulary: &str, encoding: Encoding) -> Result<Self, TokenizerError> {
+        let mut encoder = HashMap::new();
+        for (number, line) in vocabulary.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || TokenizerError::InvalidVocabulary(format!("line {}", number + 1));
+            let (token, rank) = line.split_once(' ').ok_or_else(invalid)?;
+            let token = BASE64_STANDARD.decode(token).map_err(|_| invalid())?;
+            let rank = rank.trim().parse::<u32>().map_err(|_| invalid())?;
+            encoder.insert(token, rank);
+        }
+
+        let decoder = encoder
+            .iter()
+            .map(|(token, rank)| (*rank, token.clone()))
+            .collect::<HashMap<_, _>>();
+        if decoder.len() != encoder.len() {
+            return Err(TokenizerError::InvalidVocabulary(
+                "duplicate token ranks".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            encoder,
+            decoder,
+            pattern: regex::Regex::new(encoding.pattern()).expect("pattern is valid"),
+        })
+    }
+
+    /// Create a tokenizer from a `tiktoken` vocabulary file (see [Encoding::vocabulary_url]).
+    pub fn from_file(path: impl AsRef<Path>, encoding: Encoding) -> Result<Self, TokenizerError> {
+        Self::from_tiktoken(&std::fs::read_to_string(path)?, encoding)
+    }
+
+    /// Download the vocabulary of `encoding` published by OpenAI, and create its tokenizer.
+    #[cfg(feature = "http")]
+    pub async fn download(encoding: Encoding) -> Result<Self, TokenizerError> {
+        let vocabulary = reqwest::get(encoding.vocabulary_url())
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Self::from_tiktoken(&vocabulary, encoding)
+    }
+
+    /// Tokens of `text`
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        self.pieces(text)
+            .flat_map(|piece| self.encode_piece(piece.as_bytes()))
+            .collect()
+    }
+
+    /// Text of `tokens` (unknown tokens are skipped and invalid UTF-8 is replaced)
+    pub fn decode(&self, tokens: &[u32]) -> String {
+        let bytes = tokens
+            .iter()
+            .filter_map(|token| self.decoder.get(token))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    /// Pieces of `text` matched by the pattern of the encoding. A run of whitespace followed by
+    /// a non-whitespace character leaves its last whitespace character to the next piece, as
+    /// with the `\s+(?!\S)` alternative of `tiktoken`.
+    fn pieces<'a>(&'a self, text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let mut start = 0;
+        std::iter::from_fn(move || {
+            let found = self.pattern.find_at(text, start)?;
+            let piece = found.as_str();
+            let mut end = found.end();
+
+            let whitespace = piece.chars().all(char::is_whitespace);
+            if whitespace && !piece.ends_with(['\r', '\n']) && end < text.len() {
+                if let Some((last, _)) = piece.char_indices().last().filter(|(last, _)| *last > 0) {
+                    end = found.start() + last;
+                }
+            }
+
+            start = end;
+            Some(&text[found.start()..end])
+        })
+    }
+
+    /// Byte pair encoding of a piece: the adjacent parts of the piece with the lowest rank are
+    /// merged until no merge is in the vocabulary.
+    fn encode_piece(&self, piece: &[u8]) -> Vec<u32> {
+        if let Some(rank) = self.encoder.get(piece) {
+            return vec![*rank];
+        }
+
+        // Start offsets of the parts of the piece
+        let mut parts = (0..piece.len()).collect::<Vec<_>>();
+        let part = |parts: &[usize], i: usize| {
+            let end = parts.get(i + 1).copied().unwrap_or(piece.len());
+            &piece[parts[i]..end]
+        };
+        loop {
+            let merge = (0..parts.len().saturating_sub(1))
+                .filter_map(|i| {
+                    let end = parts.get(i + 2).copied().unwrap_or(piece.len());
+                    self.encoder
+                        .get(&piece[parts[i]..end])
+                        .map(|rank| (*rank, i))
+                })
+                .min();
+            match merge {
+                Some((_, i)) => {
+                    parts.remove(i + 1);
+                }
+                None => break,
+            }
+        }
+
+        (0..parts.len())
+            .filter_map(|i| self.encoder.get(part(&parts, i)).copied())
+            .collect()
+    }
+}
+
+#[cfg(feature = "tokenizer")]
+impl TokenCounter for Tokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.pieces(text)
+            .map(|piece| self.encode_piece(piece.as_bytes()).len())
+            .sum()
+    }
+
+    fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> &'a str {
+        let mut end = self
+            .encode(text)
+            .iter()
+            .take(max_tokens)
+            .map(|token| self.decoder[token].len())
+            .sum::<usize>();
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        &text[..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vocabulary of the single bytes, and of a few merges
+    #[cfg(feature = "tokenizer")]
+    fn vocabulary() -> String {
+        let merges: [&[u8]; 5] = [b"th", b"the", b" c", b" ca", b" cat"];
+        (0..=255u8)
+            .map(|byte| vec![byte])
+            .chain(merges.iter().map(|merge| merge.to_vec()))
+            .enumerate()
+            .map(|(rank, token)| format!("{} {rank}", BASE64_STANDARD.encode(token)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn test_tokenizer() {
+        let tokenizer = Tokenizer::from_tiktoken(&vocabulary(), Encoding::Cl100kBase).unwrap();
+
+        assert_eq!(tokenizer.encode("the cat"), vec![257, 260]);
+        assert_eq!(tokenizer.encode("thy"), vec![256, b'y' as u32]);
+        assert_eq!(tokenizer.decode(&tokenizer.encode("the cat")), "the cat");
+        assert_eq!(
+            tokenizer.pieces("Hi  there!\n\nI'm").collect::<Vec<_>>(),
+            vec!["Hi", " ", " there", "!\n\n", "I", "'m"]
+        );
+
+        assert_eq!(tokenizer.count_tokens("the cat sat"), 6);
+        assert_eq!(tokenizer.truncate("the cat sat", 2), "the cat");
+        assert_eq!(tokenizer.truncate("é", 1), "");
+        assert!(Tokenizer::from_tiktoken("dGhl x", Encoding::Cl100kBase).is_err());
+
+        assert_eq!(
+            Encoding::for_model("gpt-4o-mini"),
+            Some(Encoding::O200kBase)
+        );
+        assert_eq!(
+            Encoding::for_model("gpt-4-turbo"),
+            Some(Encoding::Cl100kBase)
+        );
+        assert_eq!(Encoding::for_model("claude-3-5-sonnet"), None);
+    }
+
+    #[test]
+    fn test_estimate() {
+        assert_eq!(Estimate.count_tokens("Hello world!"), 3);
+        assert_eq!(Estimate.truncate("Hello world!", 2), "Hello wo");
+        assert_eq!(Estimate.truncate("Hello", 2), "Hello");
+
+        let text = "Dr. Who? A time lord.\nBorn on Gallifrey. Two hearts.";
+        assert_eq!(Estimate.truncate_sentences(text, 100), text);
+        assert_eq!(
+            Estimate.truncate_sentences(text, 9),
+            "Dr. Who? A time lord."
+        );
+        assert_eq!(Estimate.truncate_sentences(text, 2), "Dr. Who?");
+        assert_eq!(Estimate.truncate_sentences("Hello world", 2), "");
+    }
+}
@@ -0,0 +1,317 @@
+//! Tool result caching.
+//!
+//! Wrapping a tool in a [CachedTool] (see [ToolCache::wrap]) memoizes its successful results,
+//! keyed by the tool name and its canonicalized arguments (i.e.: `{"a": 1, "b": 2}` and
+//! `{"b":2,"a":1}` are the same call). Entries expire after a time-to-live, if any.
+//!
+//! A [ToolCache] can be cloned and shared between tools and agents, so that identical calls
+//! made in different sessions are only executed once. Caching is opt-in and should only be
+//! used for tools without side effects.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{providers::openai, tool::cache::ToolCache};
+//!
+//! let cache = ToolCache::new().ttl(Duration::from_secs(600));
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .cached_tool(WeatherLookup, &cache)
+//!     .build();
+//! ```
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::Future;
+use serde_json::Value;
+
+use super::{ToolDyn, ToolError};
+use crate::{completion::ToolDefinition, runtime::Instant};
+
+struct CacheEntry {
+    output: String,
+    expires_at: Option<Instant>,
+}
+
+impl CacheEntry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Shared store of tool results. Cloning the cache is cheap and clones share the same entries.
+#[derive(Clone, Default)]
+pub struct ToolCache {
+    entries: Arc<Mutex<HashMap<String, CacheEntry>>>,
+    ttl: Option<Duration>,
+}
+
+impl ToolCache {
+    /// Create a new cache whose entries never expire.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the default time-to-live of the entries of the tools wrapped by this cache.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Wrap `tool` so that its results are memoized, using the cache's default time-to-live.
+    pub fn wrap(&self, tool: impl ToolDyn + 'static) -> CachedTool {
+        CachedTool {
+            tool: Box::new(tool),
+            cache: self.clone(),
+            ttl: self.ttl,
+        }
+    }
+
+    /// Wrap `tool` so that its results are memoized for `ttl`.
+    pub fn wrap_with_ttl(&self, tool: impl ToolDyn + 'static, ttl: Duration) -> CachedTool {
+        CachedTool {
+            tool: Box::new(tool),
+            cache: self.clone(),
+            ttl: Some(ttl),
+        }
+    }
+
+    /// Number of entries in the cache, including expired entries not yet evicted.
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("tool cache lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the entries of the tool named `toolname`.
+    pub fn invalidate(&self, toolname: &str) {
+        let prefix = format!("{toolname}:");
+        self.entries
+            .lock()
+            .expect("tool cache lock poisoned")
+            .retain(|key, _| !key.starts_with(&prefix));
+    }
+
+    /// Remove all the entries.
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .expect("tool cache lock poisoned")
+            .clear();
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        let mut entries = self.entries.lock().expect("tool cache lock poisoned");
+        match entries.get(key) {
+            Some(entry) if entry.is_expired(Instant::now()) => {
+                entries.remove(key);
+                None
+            }
+            Some(entry) => Some(entry.output.clone()),
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: String, output: String, ttl: Option<Duration>) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("tool cache lock poisoned");
+        entries.retain(|_, entry| !entry.is_expired(now));
+        entries.insert(
+            key,
+            CacheEntry {
+                output,
+                expires_at: ttl.map(|ttl| now + ttl),
+            },
+        );
+    }
+}
+
+/// Tool wrapper returned by [ToolCache::wrap]. It has the same name and definition as the
+/// wrapped tool. Errors are never cached.
+pub struct CachedTool {
+    tool: Box<dyn ToolDyn>,
+    cache: ToolCache,
+    ttl: Option<Duration>,
+}
+
+impl CachedTool {
+    fn key(&self, args: &str) -> String {
+        // Arguments that are not valid JSON are left to the tool to reject
+        let args = serde_json::from_str::<Value>(args)
+            .map(|args| canonicalize(&args))
+            .unwrap_or_else(|_| args.to_string());
+        format!("{}:{}", self.tool.name(), args)
+    }
+}
+
+impl ToolDyn for CachedTool {
+    fn name(&self) -> String {
+        self.tool.name()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        self.tool.definition(prompt)
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            let key = self.key(&args);
+            if let Some(output) = self.cache.get(&key) {
+                tracing::debug!(target: "rig", "Tool {} cache hit", self.tool.name());
+                return Ok(output);
+            }
+
+            let output = self.tool.call(args).await?;
+            self.cache.insert(key, output.clone(), self.ttl);
+            Ok(output)
+        })
+    }
+}
+
+/// Serialize a JSON value with object keys sorted recursively.
+pub(crate) fn canonicalize(value: &Value) -> String {
+    match value {
+        Value::Object(object) => {
+            let mut fields = object.iter().collect::<Vec<_>>();
+            fields.sort_by_key(|(key, _)| *key);
+            let fields = fields
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", Value::String(key.clone()), canonicalize(value))
+                })
+                .collect::<Vec<_>>();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .map(canonicalize)
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::tool::Tool;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Lookup error")]
+    struct LookupError;
+
+    #[derive(Deserialize)]
+    struct LookupArgs {
+        city: String,
+        #[allow(dead_code)]
+        units: Option<String>,
+    }
+
+    #[derive(Clone, Default)]
+    struct Lookup {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl Tool for Lookup {
+        const NAME: &'static str = "lookup";
+
+        type Error = LookupError;
+        type Args = LookupArgs;
+        type Output = String;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Lookup".to_string(),
+                parameters: json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if args.city.is_empty() {
+                return Err(LookupError);
+            }
+            Ok(format!("sunny in {}", args.city))
+        }
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(
+            canonicalize(&json!({"b": [{"d": 1, "c": null}], "a": "x"})),
+            r#"{"a":"x","b":[{"c":null,"d":1}]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_tool() {
+        let lookup = Lookup::default();
+        let cache = ToolCache::new();
+        let tool = cache.wrap(lookup.clone());
+
+        let first = tool
+            .call(r#"{"city": "Paris", "units": "metric"}"#.to_string())
+            .await
+            .unwrap();
+        let second = tool
+            .call(r#"{"units":"metric","city":"Paris"}"#.to_string())
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(lookup.calls.load(Ordering::SeqCst), 1);
+
+        // Sessions sharing the cache share the results
+        let other_session = cache.wrap(lookup.clone());
+        other_session
+            .call(r#"{"city": "Paris", "units": "metric"}"#.to_string())
+            .await
+            .unwrap();
+        assert_eq!(lookup.calls.load(Ordering::SeqCst), 1);
+
+        // Errors are not cached
+        assert!(tool.call(r#"{"city": ""}"#.to_string()).await.is_err());
+        assert!(tool.call(r#"{"city": ""}"#.to_string()).await.is_err());
+        assert_eq!(lookup.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.len(), 1);
+
+        cache.invalidate("lookup");
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl() {
+        let lookup = Lookup::default();
+        let cache = ToolCache::new().ttl(Duration::from_millis(50));
+        let tool = cache.wrap(lookup.clone());
+
+        tool.call(r#"{"city": "Rome"}"#.to_string()).await.unwrap();
+        tool.call(r#"{"city": "Rome"}"#.to_string()).await.unwrap();
+        assert_eq!(lookup.calls.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        tool.call(r#"{"city": "Rome"}"#.to_string()).await.unwrap();
+        assert_eq!(lookup.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,894 @@
+//! Deterministic calculator tool with unit conversion.
+//!
+//! The [Calculator] tool evaluates arithmetic expressions using arbitrary precision
+//! decimals, so that agents can delegate math to the tool instead of approximating it.
+//! Quantities can carry physical units, which are checked for dimensional consistency
+//! and can be converted with the `to` (or `in`) keyword.
+//!
+//! Supported syntax:
+//! - Numbers: `42`, `3.14`, `1.5e-3`
+//! - Operators: `+`, `-`, `*`, `/`, `^` (integer exponents) and parentheses
+//! - Functions: `sqrt(x)`, `abs(x)`
+//! - Constants: `pi`, `e`
+//! - Units: `5 km`, `3 ft + 2 inch`, `60 km/h`, `2 m^2`
+//! - Conversions: `5 km to mi`, `100 degF to degC`, `1 GiB in MB`
+//!
+//! Temperatures with an offset (`degC`, `degF`) attached to a number are interpreted as
+//! absolute temperatures.
+//!
+//! # Example
+//! ```rust
+//! use rig::tool::calculator::Calculator;
+//!
+//! let calculator = Calculator::new();
+//! assert_eq!(calculator.evaluate("(1 + 2) * 3").unwrap(), "9");
+//! assert_eq!(calculator.evaluate("1 mi to km").unwrap(), "1.609344 km");
+//! ```
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{completion::ToolDefinition, tool::Tool};
+
+/// Default number of decimal places in results.
+pub const DEFAULT_PRECISION: i64 = 10;
+
+/// Largest magnitude of an exponent, as in `2 ^ 1000`.
+const MAX_EXPONENT: i64 = 1000;
+/// Largest number of digits of a number, on either side of the decimal point.
+const MAX_DIGITS: u64 = 4000;
+
+const PI: &str = "3.14159265358979323846264338327950288419716939937510582097494459";
+const E: &str = "2.71828182845904523536028747135266249775724709369995957496696763";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CalculatorError {
+    #[error("Syntax error: {0}")]
+    SyntaxError(String),
+
+    #[error("Unknown identifier: {0}")]
+    UnknownIdentifier(String),
+
+    #[error("Incompatible units: {0} and {1}")]
+    IncompatibleUnits(String, String),
+
+    #[error("Division by zero")]
+    DivisionByZero,
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+/// Number of base dimensions tracked: length, mass, time, temperature, current,
+/// amount of substance and information.
+const DIMS: usize = 7;
+const BASE_UNITS: [&str; DIMS] = ["m", "kg", "s", "K", "A", "mol", "B"];
+
+type Dims = [i8; DIMS];
+
+const DIMENSIONLESS: Dims = [0; DIMS];
+const LENGTH: Dims = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dims = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dims = [0, 0, 1, 0, 0, 0, 0];
+const TEMPERATURE: Dims = [0, 0, 0, 1, 0, 0, 0];
+const CURRENT: Dims = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dims = [0, 0, 0, 0, 0, 1, 0];
+const DATA: Dims = [0, 0, 0, 0, 0, 0, 1];
+const AREA: Dims = [2, 0, 0, 0, 0, 0, 0];
+const VOLUME: Dims = [3, 0, 0, 0, 0, 0, 0];
+const SPEED: Dims = [1, 0, -1, 0, 0, 0, 0];
+const FREQUENCY: Dims = [0, 0, -1, 0, 0, 0, 0];
+const FORCE: Dims = [1, 1, -2, 0, 0, 0, 0];
+const PRESSURE: Dims = [-1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dims = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dims = [2, 1, -3, 0, 0, 0, 0];
+
+struct Unit {
+    names: &'static [&'static str],
+    /// Factor to the SI base units, either a decimal or a `numerator/denominator` fraction.
+    factor: &'static str,
+    dims: Dims,
+    /// For affine units (temperatures): `si = (value + offset) * factor`.
+    offset: Option<&'static str>,
+}
+
+macro_rules! unit {
+    ([$($name:literal),+], $factor:literal, $dims:expr) => {
+        Unit { names: &[$($name),+], factor: $factor, dims: $dims, offset: None }
+    };
+    ([$($name:literal),+], $factor:literal, $dims:expr, $offset:literal) => {
+        Unit { names: &[$($name),+], factor: $factor, dims: $dims, offset: Some($offset) }
+    };
+}
+
+const UNITS: &[Unit] = &[
+    // Length
+    unit!(["m", "meter", "meters", "metre", "metres"], "1", LENGTH),
+    unit!(["km", "kilometer", "kilometers"], "1000", LENGTH),
+    unit!(["cm", "centimeter", "centimeters"], "0.01", LENGTH),
+    unit!(["mm", "millimeter", "millimeters"], "0.001", LENGTH),
+    unit!(["um", "micrometer", "micrometers"], "0.000001", LENGTH),
+    unit!(["nm", "nanometer", "nanometers"], "0.000000001", LENGTH),
+    unit!(["mi", "mile", "miles"], "1609.344", LENGTH),
+    unit!(["yd", "yard", "yards"], "0.9144", LENGTH),
+    unit!(["ft", "foot", "feet"], "0.3048", LENGTH),
+    unit!(["in", "inch", "inches"], "0.0254", LENGTH),
+    unit!(["nmi"], "1852", LENGTH),
+    // Mass
+    unit!(["kg", "kilogram", "kilograms"], "1", MASS),
+    unit!(["g", "gram", "grams"], "0.001", MASS),
+    unit!(["mg", "milligram", "milligrams"], "0.000001", MASS),
+    unit!(["t", "tonne", "tonnes"], "1000", MASS),
+    unit!(["lb", "lbs", "pound", "pounds"], "0.45359237", MASS),
+    unit!(["oz", "ounce", "ounces"], "0.028349523125", MASS),
+    unit!(["st", "stone"], "6.35029318", MASS),
+    // Time
+    unit!(["s", "sec", "second", "seconds"], "1", TIME),
+    unit!(["ms", "millisecond", "milliseconds"], "0.001", TIME),
+    unit!(["us", "microsecond", "microseconds"], "0.000001", TIME),
+    unit!(["ns", "nanosecond", "nanoseconds"], "0.000000001", TIME),
+    unit!(["min", "minute", "minutes"], "60", TIME),
+    unit!(["h", "hr", "hour", "hours"], "3600", TIME),
+    unit!(["day", "days"], "86400", TIME),
+    unit!(["week", "weeks"], "604800", TIME),
+    unit!(["year", "years", "yr"], "31557600", TIME),
+    // Temperature
+    unit!(["K", "kelvin"], "1", TEMPERATURE),
+    unit!(["degC", "celsius"], "1", TEMPERATURE, "273.15"),
+    unit!(["degF", "fahrenheit"], "5/9", TEMPERATURE, "459.67"),
+    // Current and amount of substance
+    unit!(["A", "ampere", "amperes"], "1", CURRENT),
+    unit!(["mol", "mole", "moles"], "1", AMOUNT),
+    // Area and volume
+    unit!(["ha", "hectare", "hectares"], "10000", AREA),
+    unit!(["acre", "acres"], "4046.8564224", AREA),
+    unit!(
+        ["l", "L", "liter", "liters", "litre", "litres"],
+        "0.001",
+        VOLUME
+    ),
+    unit!(
+        ["ml", "mL", "milliliter", "milliliters"],
+        "0.000001",
+        VOLUME
+    ),
+    unit!(["gal", "gallon", "gallons"], "0.003785411784", VOLUME),
+    // Speed and frequency
+    unit!(["mph"], "0.44704", SPEED),
+    unit!(["kph"], "5/18", SPEED),
+    unit!(["knot", "knots", "kn"], "463/900", SPEED),
+    unit!(["Hz", "hertz"], "1", FREQUENCY),
+    unit!(["kHz"], "1000", FREQUENCY),
+    unit!(["MHz"], "1000000", FREQUENCY),
+    unit!(["GHz"], "1000000000", FREQUENCY),
+    // Force, pressure, energy and power
+    unit!(["N", "newton", "newtons"], "1", FORCE),
+    unit!(["Pa", "pascal", "pascals"], "1", PRESSURE),
+    unit!(["kPa"], "1000", PRESSURE),
+    unit!(["bar"], "100000", PRESSURE),
+    unit!(["atm"], "101325", PRESSURE),
+    unit!(["psi"], "6894.757293168361", PRESSURE),
+    unit!(["J", "joule", "joules"], "1", ENERGY),
+    unit!(["kJ"], "1000", ENERGY),
+    unit!(["cal", "calorie", "calories"], "4.184", ENERGY),
+    unit!(["kcal"], "4184", ENERGY),
+    unit!(["Wh"], "3600", ENERGY),
+    unit!(["kWh"], "3600000", ENERGY),
+    unit!(["W", "watt", "watts"], "1", POWER),
+    unit!(["kW"], "1000", POWER),
+    unit!(["MW"], "1000000", POWER),
+    // Information
+    unit!(["B", "byte", "bytes"], "1", DATA),
+    unit!(["bit", "bits"], "0.125", DATA),
+    unit!(["KB", "kB"], "1000", DATA),
+    unit!(["MB"], "1000000", DATA),
+    unit!(["GB"], "1000000000", DATA),
+    unit!(["TB"], "1000000000000", DATA),
+    unit!(["KiB"], "1024", DATA),
+    unit!(["MiB"], "1048576", DATA),
+    unit!(["GiB"], "1073741824", DATA),
+    unit!(["TiB"], "1099511627776", DATA),
+];
+
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    UNITS.iter().find(|unit| unit.names.contains(&name))
+}
+
+impl Unit {
+    fn factor(&self) -> BigDecimal {
+        match self.factor.split_once('/') {
+            Some((num, den)) => decimal(num) / decimal(den),
+            None => decimal(self.factor),
+        }
+    }
+
+    fn offset(&self) -> Option<BigDecimal> {
+        self.offset.map(decimal)
+    }
+}
+
+fn decimal(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).expect("unit table contains valid decimals")
+}
+
+/// A value expressed in SI base units along with its dimensions.
+#[derive(Debug, Clone, PartialEq)]
+struct Quantity {
+    value: BigDecimal,
+    dims: Dims,
+}
+
+impl Quantity {
+    fn number(value: BigDecimal) -> Self {
+        Self {
+            value,
+            dims: DIMENSIONLESS,
+        }
+    }
+
+    fn unit_string(&self) -> String {
+        format_dims(&self.dims)
+    }
+
+    fn check_same_dims(&self, other: &Self) -> Result<(), CalculatorError> {
+        if self.dims != other.dims {
+            return Err(CalculatorError::IncompatibleUnits(
+                self.unit_string(),
+                other.unit_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn powi(self, exp: i64) -> Result<Self, CalculatorError> {
+        if exp < 0 && self.value.is_zero() {
+            return Err(CalculatorError::DivisionByZero);
+        }
+        let mut dims = self.dims;
+        for dim in dims.iter_mut() {
+            *dim = i64::from(*dim)
+                .checked_mul(exp)
+                .and_then(|d| i8::try_from(d).ok())
+                .ok_or_else(|| CalculatorError::InvalidArgument("exponent too large".into()))?;
+        }
+        // Estimate the digits of the result before computing it
+        let digits = self
+            .value
+            .digits()
+            .max(magnitude(&self.value).unsigned_abs());
+        if digits.saturating_mul(exp.unsigned_abs()) > MAX_DIGITS {
+            return Err(too_many_digits());
+        }
+        let value = if exp == 0 {
+            BigDecimal::from(1)
+        } else {
+            bounded(self.value.powi(exp))?
+        };
+        Ok(Self { value, dims })
+    }
+}
+
+fn format_dims(dims: &Dims) -> String {
+    let format = |unit: &str, exp: i8| {
+        if exp == 1 {
+            unit.to_string()
+        } else {
+            format!("{unit}^{exp}")
+        }
+    };
+
+    let numerator = dims
+        .iter()
+        .zip(BASE_UNITS)
+        .filter(|(exp, _)| **exp > 0)
+        .map(|(exp, unit)| format(unit, *exp))
+        .collect::<Vec<_>>();
+    let denominator = dims
+        .iter()
+        .zip(BASE_UNITS)
+        .filter(|(exp, _)| **exp < 0)
+        .map(|(exp, unit)| format(unit, -*exp))
+        .collect::<Vec<_>>();
+
+    match (numerator.is_empty(), denominator.is_empty()) {
+        (true, true) => String::new(),
+        (false, true) => numerator.join("*"),
+        (true, false) => format!("1/{}", denominator.join("/")),
+        (false, false) => format!("{}/{}", numerator.join("*"), denominator.join("/")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(BigDecimal),
+    Ident(String),
+    Op(char),
+}
+
+struct Spanned {
+    token: Token,
+    start: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Spanned>, CalculatorError> {
+    let chars = input.char_indices().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = i;
+            while end < chars.len() && (chars[end].1.is_ascii_digit() || chars[end].1 == '.') {
+                end += 1;
+            }
+            // Scientific notation, only if the exponent is actually followed by digits
+            if end < chars.len() && matches!(chars[end].1, 'e' | 'E') {
+                let mut exp_end = end + 1;
+                if exp_end < chars.len() && matches!(chars[exp_end].1, '+' | '-') {
+                    exp_end += 1;
+                }
+                if exp_end < chars.len() && chars[exp_end].1.is_ascii_digit() {
+                    while exp_end < chars.len() && chars[exp_end].1.is_ascii_digit() {
+                        exp_end += 1;
+                    }
+                    end = exp_end;
+                }
+            }
+            let text = chars[i..end].iter().map(|(_, c)| c).collect::<String>();
+            let number = BigDecimal::from_str(&text)
+                .map_err(|_| CalculatorError::SyntaxError(format!("invalid number `{text}`")))?;
+            let number = bounded(number)?;
+            tokens.push(Spanned {
+                token: Token::Number(number),
+                start,
+            });
+            i = end;
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = i;
+            while end < chars.len() && (chars[end].1.is_alphanumeric() || chars[end].1 == '_') {
+                end += 1;
+            }
+            let ident = chars[i..end].iter().map(|(_, c)| c).collect::<String>();
+            tokens.push(Spanned {
+                token: Token::Ident(ident),
+                start,
+            });
+            i = end;
+        } else if "+-*/^(),".contains(c) {
+            tokens.push(Spanned {
+                token: Token::Op(c),
+                start,
+            });
+            i += 1;
+        } else {
+            return Err(CalculatorError::SyntaxError(format!(
+                "unexpected character `{c}`"
+            )));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser evaluating the expression as it goes.
+///
+/// ```text
+/// conversion := sum [("to" | "in") sum]
+/// sum        := product (("+" | "-") product)*
+/// product    := unary (("*" | "/") unary)*
+/// unary      := "-" unary | power
+/// power      := primary ["^" unary]
+/// primary    := number [unit ["^" integer]] | ident ["(" sum ")"] | "(" sum ")"
+/// ```
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Spanned>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|spanned| &spanned.token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.pos + offset)
+            .map(|spanned| &spanned.token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .map(|spanned| spanned.token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), CalculatorError> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(CalculatorError::SyntaxError(format!("expected `{op}`")))
+        }
+    }
+
+    /// Whether the token at `offset` is a conversion keyword. `in` is ambiguous with
+    /// inches, so it is only a keyword when followed by a conversion target.
+    fn is_conversion_keyword(&self, offset: usize) -> bool {
+        match self.peek_at(offset) {
+            Some(Token::Ident(ident)) if ident == "to" => true,
+            Some(Token::Ident(ident)) if ident == "in" => match self.peek_at(offset + 1) {
+                Some(Token::Ident(next)) => next != "to" && next != "in",
+                Some(Token::Op('(')) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn conversion(&mut self) -> Result<(Quantity, Option<String>), CalculatorError> {
+        let quantity = self.sum()?;
+
+        if !self.is_conversion_keyword(0) {
+            return Ok((quantity, None));
+        }
+        self.pos += 1;
+
+        let target_start = self.tokens.get(self.pos).map(|spanned| spanned.start);
+        let target_name = target_start
+            .map(|start| self.input[start..].trim().to_string())
+            .ok_or_else(|| CalculatorError::SyntaxError("missing conversion target".into()))?;
+
+        // Affine units can only be converted to on their own
+        if let (Some(Token::Ident(name)), None) = (self.peek(), self.peek_at(1)) {
+            if let Some(unit) = find_unit(name).filter(|unit| unit.offset.is_some()) {
+                self.pos += 1;
+                let target = Quantity {
+                    value: unit.factor(),
+                    dims: unit.dims,
+                };
+                quantity.check_same_dims(&target)?;
+                let value = &quantity.value / &target.value - unit.offset().unwrap_or_default();
+                return Ok((Quantity::number(value), Some(target_name)));
+            }
+        }
+
+        let target = self.sum()?;
+        quantity.check_same_dims(&target)?;
+        if target.value.is_zero() {
+            return Err(CalculatorError::DivisionByZero);
+        }
+        Ok((
+            Quantity::number(&quantity.value / &target.value),
+            Some(target_name),
+        ))
+    }
+
+    fn sum(&mut self) -> Result<Quantity, CalculatorError> {
+        let mut lhs = self.product()?;
+        loop {
+            if self.eat('+') {
+                let rhs = self.product()?;
+                lhs.check_same_dims(&rhs)?;
+                lhs.value = bounded(lhs.value + rhs.value)?;
+            } else if self.eat('-') {
+                let rhs = self.product()?;
+                lhs.check_same_dims(&rhs)?;
+                lhs.value = bounded(lhs.value - rhs.value)?;
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Quantity, CalculatorError> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.eat('*') {
+                let rhs = self.unary()?;
+                lhs.value = bounded(lhs.value * rhs.value)?;
+                lhs.dims = combine(lhs.dims, rhs.dims, 1)?;
+            } else if self.eat('/') {
+                let rhs = self.unary()?;
+                if rhs.value.is_zero() {
+                    return Err(CalculatorError::DivisionByZero);
+                }
+                lhs.value = bounded(&lhs.value / &rhs.value)?;
+                lhs.dims = combine(lhs.dims, rhs.dims, -1)?;
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Quantity, CalculatorError> {
+        if self.eat('-') {
+            let mut quantity = self.unary()?;
+            quantity.value = -quantity.value;
+            Ok(quantity)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Quantity, CalculatorError> {
+        let base = self.primary()?;
+        if !self.eat('^') {
+            return Ok(base);
+        }
+        let exponent = self.unary()?;
+        if exponent.dims != DIMENSIONLESS {
+            return Err(CalculatorError::InvalidArgument(
+                "exponents must be dimensionless".into(),
+            ));
+        }
+        base.powi(integer(&exponent.value)?)
+    }
+
+    fn primary(&mut self) -> Result<Quantity, CalculatorError> {
+        match self.next() {
+            Some(Token::Number(number)) => self.number_with_unit(number),
+            Some(Token::Ident(ident)) => self.identifier(ident),
+            Some(Token::Op('(')) => {
+                let quantity = self.sum()?;
+                self.expect(')')?;
+                Ok(quantity)
+            }
+            Some(token) => Err(CalculatorError::SyntaxError(format!(
+                "unexpected token {token:?}"
+            ))),
+            None => Err(CalculatorError::SyntaxError(
+                "unexpected end of expression".into(),
+            )),
+        }
+    }
+
+    /// A number optionally followed by a unit, e.g. `5 km` or `3 m^2`.
+    fn number_with_unit(&mut self, number: BigDecimal) -> Result<Quantity, CalculatorError> {
+        let unit = match self.peek() {
+            Some(Token::Ident(ident)) if !self.is_conversion_keyword(0) => find_unit(ident),
+            _ => None,
+        };
+        let Some(unit) = unit else {
+            return Ok(Quantity::number(number));
+        };
+        self.pos += 1;
+
+        // The exponent applies to the unit only: `3 m^2` is 3 square meters
+        if self.peek() == Some(&Token::Op('^')) {
+            self.pos += 1;
+            let negative = self.eat('-');
+            let exp = match self.next() {
+                Some(Token::Number(exp)) => integer(&exp)?,
+                _ => {
+                    return Err(CalculatorError::SyntaxError(
+                        "expected an integer exponent".into(),
+                    ))
+                }
+            };
+            let unit = Quantity {
+                value: unit.factor(),
+                dims: unit.dims,
+            }
+            .powi(if negative { -exp } else { exp })?;
+            return Ok(Quantity {
+                value: number * unit.value,
+                dims: unit.dims,
+            });
+        }
+
+        let value = match unit.offset() {
+            Some(offset) => (number + offset) * unit.factor(),
+            None => number * unit.factor(),
+        };
+        Ok(Quantity {
+            value,
+            dims: unit.dims,
+        })
+    }
+
+    fn identifier(&mut self, ident: String) -> Result<Quantity, CalculatorError> {
+        if self.eat('(') {
+            let arg = self.sum()?;
+            self.expect(')')?;
+            return match ident.as_str() {
+                "sqrt" => {
+                    if arg.dims.iter().any(|dim| dim % 2 != 0) {
+                        return Err(CalculatorError::InvalidArgument(format!(
+                            "cannot take the square root of {}",
+                            arg.unit_string()
+                        )));
+                    }
+                    let value = arg.value.sqrt().ok_or_else(|| {
+                        CalculatorError::InvalidArgument(
+                            "cannot take the square root of a negative number".into(),
+                        )
+                    })?;
+                    Ok(Quantity {
+                        value,
+                        dims: arg.dims.map(|dim| dim / 2),
+                    })
+                }
+                "abs" => Ok(Quantity {
+                    value: arg.value.abs(),
+                    dims: arg.dims,
+                }),
+                _ => Err(CalculatorError::UnknownIdentifier(ident)),
+            };
+        }
+
+        match ident.as_str() {
+            "pi" => Ok(Quantity::number(decimal(PI))),
+            "e" => Ok(Quantity::number(decimal(E))),
+            _ => find_unit(&ident)
+                .map(|unit| Quantity {
+                    value: unit.factor(),
+                    dims: unit.dims,
+                })
+                .ok_or(CalculatorError::UnknownIdentifier(ident)),
+        }
+    }
+}
+
+fn combine(lhs: Dims, rhs: Dims, sign: i8) -> Result<Dims, CalculatorError> {
+    let mut dims = lhs;
+    for (dim, other) in dims.iter_mut().zip(rhs) {
+        *dim = other
+            .checked_mul(sign)
+            .and_then(|other| dim.checked_add(other))
+            .ok_or_else(|| CalculatorError::InvalidArgument("unit exponent too large".into()))?;
+    }
+    Ok(dims)
+}
+
+fn integer(value: &BigDecimal) -> Result<i64, CalculatorError> {
+    use bigdecimal::ToPrimitive;
+
+    if !value.is_integer() {
+        return Err(CalculatorError::InvalidArgument(format!(
+            "exponent must be an integer, got {value} (use sqrt for square roots)"
+        )));
+    }
+    value
+        .to_i64()
+        .filter(|exp| exp.abs() <= MAX_EXPONENT)
+        .ok_or_else(|| {
+            CalculatorError::InvalidArgument(format!(
+                "exponent must be between -{MAX_EXPONENT} and {MAX_EXPONENT}"
+            ))
+        })
+}
+
+/// Position of the most significant digit relative to the decimal point.
+fn magnitude(value: &BigDecimal) -> i64 {
+    value.digits() as i64 - value.fractional_digit_count()
+}
+
+fn too_many_digits() -> CalculatorError {
+    CalculatorError::InvalidArgument(format!("result exceeds {MAX_DIGITS} digits"))
+}
+
+/// Reject numbers too large or too precise to be computed with and formatted quickly.
+fn bounded(value: BigDecimal) -> Result<BigDecimal, CalculatorError> {
+    if !value.is_zero()
+        && (value.digits() > MAX_DIGITS || magnitude(&value).unsigned_abs() > MAX_DIGITS)
+    {
+        return Err(too_many_digits());
+    }
+    Ok(value)
+}
+
+fn format_value(value: &BigDecimal, precision: i64) -> String {
+    let mut output = value
+        .with_scale_round(precision, RoundingMode::HalfEven)
+        .to_plain_string();
+    if output.contains('.') {
+        output = output
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string();
+    }
+    if output == "-0" {
+        output = "0".to_string();
+    }
+    output
+}
+
+#[derive(Deserialize)]
+pub struct CalculatorArgs {
+    pub expression: String,
+}
+
+/// Tool evaluating math expressions with arbitrary precision and unit support.
+#[derive(Debug, Clone)]
+pub struct Calculator {
+    precision: i64,
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self {
+            precision: DEFAULT_PRECISION,
+        }
+    }
+}
+
+impl Calculator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of decimal places in results (default: 10).
+    pub fn precision(mut self, precision: i64) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Evaluate the expression and format the result, including its unit if any.
+    pub fn evaluate(&self, expression: &str) -> Result<String, CalculatorError> {
+        let mut parser = Parser {
+            input: expression,
+            tokens: tokenize(expression)?,
+            pos: 0,
+        };
+
+        let (quantity, target) = parser.conversion()?;
+        if let Some(token) = parser.peek() {
+            return Err(CalculatorError::SyntaxError(format!(
+                "unexpected token {token:?}"
+            )));
+        }
+
+        let value = format_value(&quantity.value, self.precision);
+        let unit = target.unwrap_or_else(|| quantity.unit_string());
+        if unit.is_empty() {
+            Ok(value)
+        } else {
+            Ok(format!("{value} {unit}"))
+        }
+    }
+}
+
+impl Tool for Calculator {
+    const NAME: &'static str = "calculator";
+
+    type Error = CalculatorError;
+    type Args = CalculatorArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Evaluate a math expression exactly. Supports + - * / ^, parentheses, \
+                sqrt(x), abs(x), pi, e, physical units (e.g. `3 ft + 2 inch`, `60 km/h`) and unit \
+                conversions with `to` (e.g. `5 km to mi`, `100 degF to degC`)."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "expression": {
+                        "type": "string",
+                        "description": "The expression to evaluate"
+                    }
+                },
+                "required": ["expression"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.evaluate(&args.expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str) -> String {
+        Calculator::new().evaluate(expression).unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("1 + 2 * 3"), "7");
+        assert_eq!(eval("(1 + 2) * 3"), "9");
+        assert_eq!(eval("-2 ^ 2"), "-4");
+        assert_eq!(eval("2 ^ -1"), "0.5");
+        assert_eq!(eval("1 / 3"), "0.3333333333");
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("1.5e3 / 2"), "750");
+        assert_eq!(eval("sqrt(16) + abs(-1)"), "5");
+        assert_eq!(eval("2 ^ 100"), "1267650600228229401496703205376");
+        assert_eq!(
+            Calculator::new().precision(20).evaluate("pi").unwrap(),
+            "3.14159265358979323846"
+        );
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(eval("5 km to mi"), "3.1068559612 mi");
+        assert_eq!(eval("3 ft + 2 inch in cm"), "96.52 cm");
+        assert_eq!(eval("5 in to cm"), "12.7 cm");
+        assert_eq!(eval("100 degF to degC"), "37.7777777778 degC");
+        assert_eq!(eval("0 degC to K"), "273.15 K");
+        assert_eq!(eval("120 km / 2 h to mph"), "37.2822715342 mph");
+        assert_eq!(eval("1 GiB to MB"), "1073.741824 MB");
+        assert_eq!(eval("3 m^2 to ft^2"), "32.2917312501 ft^2");
+        assert_eq!(eval("2 m * 3 m"), "6 m^2");
+        assert_eq!(eval("10 N / 2 kg"), "5 m/s^2");
+        assert_eq!(eval("sqrt(4 m^2)"), "2 m");
+    }
+
+    #[test]
+    fn test_errors() {
+        let calculator = Calculator::new();
+        assert!(matches!(
+            calculator.evaluate("1 m + 1 s"),
+            Err(CalculatorError::IncompatibleUnits(_, _))
+        ));
+        assert!(matches!(
+            calculator.evaluate("5 kg to m"),
+            Err(CalculatorError::IncompatibleUnits(_, _))
+        ));
+        assert!(matches!(
+            calculator.evaluate("1 / 0"),
+            Err(CalculatorError::DivisionByZero)
+        ));
+        assert!(matches!(
+            calculator.evaluate("2 ^ 0.5"),
+            Err(CalculatorError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            calculator.evaluate("foo + 1"),
+            Err(CalculatorError::UnknownIdentifier(_))
+        ));
+        assert!(matches!(
+            calculator.evaluate("(1 + 2"),
+            Err(CalculatorError::SyntaxError(_))
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        let calculator = Calculator::new();
+        for expression in [
+            "1e100000000",
+            "1e-100000000",
+            "9 ^ 99999999",
+            "(9 ^ 1000) ^ 1000",
+            "1e3000 * 1e3000",
+            "1e3000 + 1e-3000",
+            "m ^ 99999999",
+        ] {
+            assert!(
+                matches!(
+                    calculator.evaluate(expression),
+                    Err(CalculatorError::InvalidArgument(_))
+                ),
+                "{expression}"
+            );
+        }
+        assert_eq!(calculator.evaluate("1e3 ^ 2").unwrap(), "1000000");
+        assert_eq!(calculator.evaluate("9 ^ 1000").unwrap().len(), 955);
+    }
+}
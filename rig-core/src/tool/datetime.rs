@@ -0,0 +1,651 @@
+//! Date, time and timezone tool.
+//!
+//! The [DateTimeTool] gives agents access to the current time, timezone conversions and
+//! calendar arithmetic, which language models are notoriously unreliable at computing
+//! on their own (e.g.: "what's 90 days from next Tuesday in UTC?").
+//!
+//! The current time is read from a [Clock], which defaults to the system clock but can be
+//! replaced with a [FixedClock] (or any custom implementation) to make results
+//! deterministic, for instance in tests.
+//!
+//! Timezones are IANA names (e.g.: `UTC`, `Europe/Paris`, `America/New_York`). Date times
+//! are accepted either in RFC 3339 format (`2024-03-01T09:30:00+01:00`), in which case
+//! the offset is respected, or as local date times (`2024-03-01 09:30`, `2024-03-01`)
+//! interpreted in the requested timezone.
+//!
+//! # Example
+//! ```rust
+//! use chrono::{TimeZone, Utc};
+//! use rig::tool::datetime::{DateTimeTool, FixedClock};
+//!
+//! let tool = DateTimeTool::with_clock(FixedClock::new(
+//!     Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+//! ));
+//! let next_tuesday = tool.next_weekday("tuesday", None, None).unwrap();
+//! assert_eq!(next_tuesday.datetime, "2024-03-05T12:00:00Z");
+//! ```
+use chrono::{
+    DateTime, Datelike, Days, Duration, LocalResult, Months, NaiveDate, NaiveDateTime, TimeZone,
+    Utc, Weekday,
+};
+pub use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{completion::ToolDefinition, tool::Tool};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DateTimeError {
+    #[error("Unknown timezone: {0}")]
+    InvalidTimezone(String),
+
+    #[error("Invalid date time: {0}")]
+    InvalidDateTime(String),
+
+    #[error("Invalid weekday: {0}")]
+    InvalidWeekday(String),
+
+    /// The local time does not exist in the timezone (e.g.: skipped by a DST transition)
+    #[error("Local time {0} does not exist in timezone {1}")]
+    NonexistentLocalTime(NaiveDateTime, Tz),
+
+    #[error("Date time out of range")]
+    OutOfRange,
+}
+
+/// Source of the current time used by the [DateTimeTool].
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// [Clock] reading the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// [Clock] always returning the same instant.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(DateTime<Utc>);
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self(now)
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// Calendar and exact offsets applied by [DateTimeTool::add].
+/// Years, months, weeks and days are calendar units applied to the local date (e.g.:
+/// adding 1 month to January 31st yields the last day of February), while hours, minutes
+/// and seconds are exact durations.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Offset {
+    pub years: i32,
+    pub months: i32,
+    pub weeks: i64,
+    pub days: i64,
+    pub hours: i64,
+    pub minutes: i64,
+    pub seconds: i64,
+}
+
+/// Instant returned by the [DateTimeTool], expressed in a given timezone.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Instant {
+    /// RFC 3339 representation, including the UTC offset
+    pub datetime: String,
+    pub timezone: String,
+    pub date: String,
+    pub time: String,
+    pub weekday: String,
+    pub unix_timestamp: i64,
+}
+
+impl Instant {
+    fn new(datetime: DateTime<Tz>) -> Self {
+        Self {
+            datetime: datetime.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            timezone: datetime.timezone().name().to_string(),
+            date: datetime.format("%Y-%m-%d").to_string(),
+            time: datetime.format("%H:%M:%S").to_string(),
+            weekday: datetime.format("%A").to_string(),
+            unix_timestamp: datetime.timestamp(),
+        }
+    }
+}
+
+/// Elapsed time between two instants returned by [DateTimeTool::difference].
+/// Values are negative if the end is before the start.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Elapsed {
+    pub seconds: i64,
+    /// Number of whole days
+    pub days: i64,
+    /// Human readable breakdown (e.g.: "2 days 3 hours 15 minutes")
+    pub human: String,
+}
+
+impl Elapsed {
+    fn new(duration: Duration) -> Self {
+        let seconds = duration.num_seconds();
+        let sign = if seconds < 0 { "-" } else { "" };
+        let abs = seconds.unsigned_abs();
+
+        let parts = [
+            (abs / 86_400, "day"),
+            (abs % 86_400 / 3_600, "hour"),
+            (abs % 3_600 / 60, "minute"),
+            (abs % 60, "second"),
+        ]
+        .into_iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{value} {unit}{}", if value == 1 { "" } else { "s" }))
+        .collect::<Vec<_>>();
+
+        Self {
+            seconds,
+            days: duration.num_days(),
+            human: if parts.is_empty() {
+                "0 seconds".to_string()
+            } else {
+                format!("{sign}{}", parts.join(" "))
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum DateTimeOutput {
+    Instant(Instant),
+    Elapsed(Elapsed),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum DateTimeArgs {
+    /// Current date and time
+    Now { timezone: Option<String> },
+    /// Express a date time in another timezone
+    Convert {
+        datetime: String,
+        from_timezone: Option<String>,
+        to_timezone: String,
+    },
+    /// Add (or subtract, with negative values) an offset to a date time
+    Add {
+        datetime: Option<String>,
+        timezone: Option<String>,
+        #[serde(flatten)]
+        offset: Offset,
+    },
+    /// Elapsed time between two date times
+    Difference {
+        start: String,
+        end: String,
+        timezone: Option<String>,
+    },
+    /// Next occurrence of a weekday, strictly after the date time
+    NextWeekday {
+        weekday: String,
+        datetime: Option<String>,
+        timezone: Option<String>,
+    },
+}
+
+/// Tool exposing the current time, timezone conversions and date arithmetic.
+/// Operations that don't specify a date time are relative to the [Clock]'s current time
+/// and operations that don't specify a timezone use the default timezone (UTC unless
+/// changed with [DateTimeTool::default_timezone]).
+pub struct DateTimeTool<C = SystemClock> {
+    clock: C,
+    default_timezone: Tz,
+}
+
+impl Default for DateTimeTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DateTimeTool {
+    /// Create a new tool reading the system clock.
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl<C: Clock> DateTimeTool<C> {
+    /// Create a new tool reading the given clock.
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            clock,
+            default_timezone: Tz::UTC,
+        }
+    }
+
+    /// Set the timezone used when none is specified.
+    pub fn default_timezone(mut self, timezone: Tz) -> Self {
+        self.default_timezone = timezone;
+        self
+    }
+
+    /// Current date and time in the given timezone.
+    pub fn now(&self, timezone: Option<&str>) -> Result<Instant, DateTimeError> {
+        let tz = self.timezone(timezone)?;
+        Ok(Instant::new(self.clock.now().with_timezone(&tz)))
+    }
+
+    /// Express `datetime` (interpreted in `from_timezone` if it has no offset) in `to_timezone`.
+    pub fn convert(
+        &self,
+        datetime: &str,
+        from_timezone: Option<&str>,
+        to_timezone: &str,
+    ) -> Result<Instant, DateTimeError> {
+        let from = self.timezone(from_timezone)?;
+        let to = self.timezone(Some(to_timezone))?;
+        Ok(Instant::new(parse(datetime, from)?.with_timezone(&to)))
+    }
+
+    /// Apply `offset` to `datetime` (or the current time) in the given timezone.
+    pub fn add(
+        &self,
+        datetime: Option<&str>,
+        timezone: Option<&str>,
+        offset: Offset,
+    ) -> Result<Instant, DateTimeError> {
+        let tz = self.timezone(timezone)?;
+        let start = self.resolve(datetime, tz)?;
+
+        let months = offset.years as i64 * 12 + offset.months as i64;
+        let days = offset.weeks * 7 + offset.days;
+
+        let local = start.naive_local();
+        let local = shift_months(local, months)?;
+        let local = shift_days(local, days)?;
+        let shifted = localize(local, tz)?;
+
+        let exact = Duration::try_hours(offset.hours)
+            .zip(Duration::try_minutes(offset.minutes))
+            .zip(Duration::try_seconds(offset.seconds))
+            .and_then(|((h, m), s)| h.checked_add(&m)?.checked_add(&s))
+            .ok_or(DateTimeError::OutOfRange)?;
+
+        shifted
+            .checked_add_signed(exact)
+            .map(Instant::new)
+            .ok_or(DateTimeError::OutOfRange)
+    }
+
+    /// Elapsed time from `start` to `end`.
+    pub fn difference(
+        &self,
+        start: &str,
+        end: &str,
+        timezone: Option<&str>,
+    ) -> Result<Elapsed, DateTimeError> {
+        let tz = self.timezone(timezone)?;
+        Ok(Elapsed::new(parse(end, tz)? - parse(start, tz)?))
+    }
+
+    /// Next occurrence of `weekday` strictly after `datetime` (or the current time), keeping
+    /// the time of day.
+    pub fn next_weekday(
+        &self,
+        weekday: &str,
+        datetime: Option<&str>,
+        timezone: Option<&str>,
+    ) -> Result<Instant, DateTimeError> {
+        let weekday = weekday
+            .trim()
+            .parse::<Weekday>()
+            .map_err(|_| DateTimeError::InvalidWeekday(weekday.to_string()))?;
+        let tz = self.timezone(timezone)?;
+        let start = self.resolve(datetime, tz)?;
+
+        let current = start.weekday().num_days_from_monday() as i64;
+        let target = weekday.num_days_from_monday() as i64;
+        let days = match (target - current).rem_euclid(7) {
+            0 => 7,
+            days => days,
+        };
+
+        let local = shift_days(start.naive_local(), days)?;
+        localize(local, tz).map(Instant::new)
+    }
+
+    fn timezone(&self, timezone: Option<&str>) -> Result<Tz, DateTimeError> {
+        match timezone.map(str::trim) {
+            None | Some("") => Ok(self.default_timezone),
+            Some(name) => name
+                .parse::<Tz>()
+                .map_err(|_| DateTimeError::InvalidTimezone(name.to_string())),
+        }
+    }
+
+    fn resolve(&self, datetime: Option<&str>, tz: Tz) -> Result<DateTime<Tz>, DateTimeError> {
+        match datetime.map(str::trim) {
+            None | Some("") | Some("now") => Ok(self.clock.now().with_timezone(&tz)),
+            Some(datetime) => parse(datetime, tz),
+        }
+    }
+}
+
+const LOCAL_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Parse an RFC 3339 date time, or a local date time interpreted in `tz`.
+fn parse(datetime: &str, tz: Tz) -> Result<DateTime<Tz>, DateTimeError> {
+    let datetime = datetime.trim();
+
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(datetime) {
+        return Ok(parsed.with_timezone(&tz));
+    }
+
+    let local = LOCAL_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(datetime, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(datetime, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| DateTimeError::InvalidDateTime(datetime.to_string()))?;
+
+    localize(local, tz)
+}
+
+/// Resolve a local date time in `tz`. Ambiguous times (e.g.: repeated by a DST transition)
+/// resolve to the earliest instant.
+fn localize(local: NaiveDateTime, tz: Tz) -> Result<DateTime<Tz>, DateTimeError> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(datetime) => Ok(datetime),
+        LocalResult::Ambiguous(earliest, _) => Ok(earliest),
+        LocalResult::None => Err(DateTimeError::NonexistentLocalTime(local, tz)),
+    }
+}
+
+fn shift_months(local: NaiveDateTime, months: i64) -> Result<NaiveDateTime, DateTimeError> {
+    let abs = u32::try_from(months.unsigned_abs()).map_err(|_| DateTimeError::OutOfRange)?;
+    if months >= 0 {
+        local.checked_add_months(Months::new(abs))
+    } else {
+        local.checked_sub_months(Months::new(abs))
+    }
+    .ok_or(DateTimeError::OutOfRange)
+}
+
+fn shift_days(local: NaiveDateTime, days: i64) -> Result<NaiveDateTime, DateTimeError> {
+    if days >= 0 {
+        local.checked_add_days(Days::new(days as u64))
+    } else {
+        local.checked_sub_days(Days::new(days.unsigned_abs()))
+    }
+    .ok_or(DateTimeError::OutOfRange)
+}
+
+impl<C: Clock> Tool for DateTimeTool<C> {
+    const NAME: &'static str = "datetime";
+
+    type Error = DateTimeError;
+    type Args = DateTimeArgs;
+    type Output = DateTimeOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Get the current date and time, convert between timezones and do date \
+                arithmetic. Operations: `now`, `convert`, `add` (negative values subtract), \
+                `difference` and `next_weekday`. Date times are RFC 3339 (e.g. \
+                `2024-03-01T09:30:00Z`) or local (e.g. `2024-03-01 09:30`, `2024-03-01`) in \
+                the given timezone, and default to now. Timezones are IANA names (e.g. \
+                `Europe/Paris`) and default to {}.",
+                self.default_timezone.name()
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "operation": {
+                        "type": "string",
+                        "enum": ["now", "convert", "add", "difference", "next_weekday"],
+                        "description": "The operation to perform"
+                    },
+                    "datetime": {
+                        "type": "string",
+                        "description": "Reference date time (convert, add, next_weekday)"
+                    },
+                    "timezone": {
+                        "type": "string",
+                        "description": "Timezone of the operation (now, add, difference, next_weekday)"
+                    },
+                    "from_timezone": {
+                        "type": "string",
+                        "description": "Timezone of `datetime` if it has no offset (convert)"
+                    },
+                    "to_timezone": {
+                        "type": "string",
+                        "description": "Target timezone (convert)"
+                    },
+                    "start": {
+                        "type": "string",
+                        "description": "Start date time (difference)"
+                    },
+                    "end": {
+                        "type": "string",
+                        "description": "End date time (difference)"
+                    },
+                    "weekday": {
+                        "type": "string",
+                        "description": "Weekday name, e.g. `tuesday` (next_weekday)"
+                    },
+                    "years": { "type": "integer", "description": "Years to add (add)" },
+                    "months": { "type": "integer", "description": "Months to add (add)" },
+                    "weeks": { "type": "integer", "description": "Weeks to add (add)" },
+                    "days": { "type": "integer", "description": "Days to add (add)" },
+                    "hours": { "type": "integer", "description": "Hours to add (add)" },
+                    "minutes": { "type": "integer", "description": "Minutes to add (add)" },
+                    "seconds": { "type": "integer", "description": "Seconds to add (add)" }
+                },
+                "required": ["operation"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match args {
+            DateTimeArgs::Now { timezone } => self.now(timezone.as_deref()).map(Into::into),
+            DateTimeArgs::Convert {
+                datetime,
+                from_timezone,
+                to_timezone,
+            } => self
+                .convert(&datetime, from_timezone.as_deref(), &to_timezone)
+                .map(Into::into),
+            DateTimeArgs::Add {
+                datetime,
+                timezone,
+                offset,
+            } => self
+                .add(datetime.as_deref(), timezone.as_deref(), offset)
+                .map(Into::into),
+            DateTimeArgs::Difference {
+                start,
+                end,
+                timezone,
+            } => self
+                .difference(&start, &end, timezone.as_deref())
+                .map(Into::into),
+            DateTimeArgs::NextWeekday {
+                weekday,
+                datetime,
+                timezone,
+            } => self
+                .next_weekday(&weekday, datetime.as_deref(), timezone.as_deref())
+                .map(Into::into),
+        }
+    }
+}
+
+impl From<Instant> for DateTimeOutput {
+    fn from(instant: Instant) -> Self {
+        DateTimeOutput::Instant(instant)
+    }
+}
+
+impl From<Elapsed> for DateTimeOutput {
+    fn from(elapsed: Elapsed) -> Self {
+        DateTimeOutput::Elapsed(elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool() -> DateTimeTool<FixedClock> {
+        // Friday
+        DateTimeTool::with_clock(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn test_now_and_convert() {
+        let tool = tool();
+
+        let now = tool.now(None).unwrap();
+        assert_eq!(now.datetime, "2024-03-01T12:00:00Z");
+        assert_eq!(now.weekday, "Friday");
+        assert_eq!(now.unix_timestamp, 1709294400);
+
+        let tokyo = tool.now(Some("Asia/Tokyo")).unwrap();
+        assert_eq!(tokyo.datetime, "2024-03-01T21:00:00+09:00");
+        assert_eq!(tokyo.timezone, "Asia/Tokyo");
+
+        let converted = tool
+            .convert("2024-07-01 09:30", Some("Europe/Paris"), "America/New_York")
+            .unwrap();
+        assert_eq!(converted.datetime, "2024-07-01T03:30:00-04:00");
+
+        let converted = tool
+            .convert("2024-01-01T00:00:00+05:30", None, "UTC")
+            .unwrap();
+        assert_eq!(converted.datetime, "2023-12-31T18:30:00Z");
+
+        assert!(matches!(
+            tool.now(Some("Mars/Olympus_Mons")),
+            Err(DateTimeError::InvalidTimezone(_))
+        ));
+    }
+
+    #[test]
+    fn test_add() {
+        let tool = tool();
+
+        let offset = Offset {
+            days: 90,
+            ..Default::default()
+        };
+        let next_tuesday = tool.next_weekday("Tuesday", None, None).unwrap();
+        assert_eq!(next_tuesday.date, "2024-03-05");
+        let result = tool
+            .add(Some(&next_tuesday.datetime), None, offset)
+            .unwrap();
+        assert_eq!(result.date, "2024-06-03");
+        assert_eq!(result.weekday, "Monday");
+
+        let offset = Offset {
+            months: 1,
+            ..Default::default()
+        };
+        let result = tool.add(Some("2024-01-31"), None, offset).unwrap();
+        assert_eq!(result.date, "2024-02-29");
+
+        // Calendar days keep the local time across DST, hours are exact
+        let day = Offset {
+            days: 1,
+            ..Default::default()
+        };
+        let hours = Offset {
+            hours: 24,
+            ..Default::default()
+        };
+        let start = Some("2024-03-30 12:00");
+        let paris = Some("Europe/Paris");
+        assert_eq!(
+            tool.add(start, paris, day).unwrap().datetime,
+            "2024-03-31T12:00:00+02:00"
+        );
+        assert_eq!(
+            tool.add(start, paris, hours).unwrap().datetime,
+            "2024-03-31T13:00:00+02:00"
+        );
+
+        let offset = Offset {
+            weeks: -1,
+            hours: -1,
+            ..Default::default()
+        };
+        assert_eq!(
+            tool.add(None, None, offset).unwrap().datetime,
+            "2024-02-23T11:00:00Z"
+        );
+
+        assert!(matches!(
+            tool.add(Some("2024-03-31 02:30"), paris, Offset::default()),
+            Err(DateTimeError::NonexistentLocalTime(..))
+        ));
+    }
+
+    #[test]
+    fn test_difference_and_args() {
+        let tool = tool();
+
+        let elapsed = tool
+            .difference("2024-03-01", "2024-03-03T03:15:00Z", None)
+            .unwrap();
+        assert_eq!(elapsed.seconds, 184_500);
+        assert_eq!(elapsed.days, 2);
+        assert_eq!(elapsed.human, "2 days 3 hours 15 minutes");
+
+        let elapsed = tool
+            .difference("2024-03-01 01:00", "2024-03-01", None)
+            .unwrap();
+        assert_eq!(elapsed.human, "-1 hour");
+
+        let args: DateTimeArgs = serde_json::from_value(json!({
+            "operation": "add",
+            "timezone": "UTC",
+            "days": 2
+        }))
+        .unwrap();
+        let output = tokio_test::block_on(tool.call(args)).unwrap();
+        assert_eq!(
+            serde_json::to_value(output).unwrap()["datetime"],
+            "2024-03-03T12:00:00Z"
+        );
+
+        assert_eq!(
+            tool.next_weekday("fri", None, None).unwrap().date,
+            "2024-03-08"
+        );
+        assert!(tool.next_weekday("someday", None, None).is_err());
+    }
+}
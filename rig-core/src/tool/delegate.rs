@@ -0,0 +1,197 @@
+//! Delegation of tasks to sub-agents.
+//!
+//! An [AgentTool] wraps an agent (or anything implementing [Prompt]) as a tool taking a single
+//! `prompt` argument: a supervisor agent calls the tool to delegate a task to the specialist
+//! agent, whose answer is the output of the tool. Since sub-agents are tools like any other,
+//! they can themselves delegate to their own sub-agents, shaping hierarchies of agents.
+//!
+//! The description of the tool tells the supervisor what the sub-agent is good at, and what it
+//! should be prompted with. The runs of the sub-agents are cancelled along with the run of the
+//! supervisor (see [Tool#cancellation](crate::tool::Tool#cancellation)), stopping their own tool
+//! calls.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, providers::openai, tool::delegate::AgentTool};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let researcher = openai.agent("gpt-4o")
+//!     .preamble("You research topics and summarize your findings with sources.")
+//!     .tool(WebSearch)
+//!     .max_turns(5)
+//!     .build();
+//!
+//! let supervisor = openai.agent("gpt-4o")
+//!     .preamble("You write reports. Delegate the research to the researcher.")
+//!     .tool(AgentTool::new(
+//!         "researcher",
+//!         "Researches a topic and summarizes the findings. Prompt it with the topic.",
+//!         researcher,
+//!     ))
+//!     .max_turns(3)
+//!     .build();
+//!
+//! let report = supervisor.prompt("Write a report on solid-state batteries").await?;
+//! ```
+use std::{future::Future, sync::Mutex};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::Tool;
+use crate::completion::{Prompt, PromptError, ToolDefinition};
+
+/// Arguments of an [AgentTool].
+#[derive(Deserialize)]
+pub struct AgentToolArgs {
+    /// Prompt sent to the sub-agent
+    pub prompt: String,
+}
+
+/// Tool delegating its calls to a sub-agent: the `prompt` argument of the call is sent to the
+/// agent, whose answer is the output of the tool. Errors of the agent are tool errors.
+pub struct AgentTool<P> {
+    name: String,
+    description: String,
+    agent: P,
+}
+
+impl<P: Prompt> AgentTool<P> {
+    pub fn new(name: &str, description: &str, agent: P) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            agent,
+        }
+    }
+
+    /// The wrapped agent.
+    pub fn agent(&self) -> &P {
+        &self.agent
+    }
+}
+
+impl<P: Prompt> Tool for AgentTool<P> {
+    const NAME: &'static str = "agent";
+
+    type Error = PromptError;
+    type Args = AgentToolArgs;
+    type Output = String;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "prompt": {
+                        "type": "string",
+                        "description": "The task or question for the agent, with the context it needs"
+                    }
+                },
+                "required": ["prompt"]
+            }),
+        }
+    }
+
+    fn call(
+        &self,
+        args: Self::Args,
+    ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send + Sync {
+        tracing::debug!(target: "rig", "Delegating to agent {}", self.name);
+        sync_future(self.agent.prompt(args.prompt))
+    }
+}
+
+/// Make a `Send` future `Sync` (as required of tool calls) by polling it through a mutex. The
+/// future is only ever polled, never shared, so the mutex is not contended.
+fn sync_future<F: Future + Send>(future: F) -> impl Future<Output = F::Output> + Send + Sync {
+    let future = Mutex::new(Box::pin(future));
+    futures::future::poll_fn(move |cx| {
+        future
+            .lock()
+            .expect("future lock poisoned")
+            .as_mut()
+            .poll(cx)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{CompletionError, Message},
+        message::{ToolResultContent, UserContent},
+        providers::mock::MockCompletionModel,
+        tool::{ToolSet, ToolSetError},
+        OneOrMany,
+    };
+
+    #[tokio::test]
+    async fn test_agent_tool() {
+        let specialist_model = MockCompletionModel::new().text("42");
+        let specialist = AgentBuilder::new(specialist_model.clone())
+            .preamble("You are a specialist.")
+            .build();
+
+        let supervisor_model = MockCompletionModel::new()
+            .tool_call("specialist", json!({"prompt": "What is the answer?"}))
+            .text("The specialist says 42");
+        let supervisor = AgentBuilder::new(supervisor_model.clone())
+            .tool(AgentTool::new(
+                "specialist",
+                "Answers hard questions",
+                specialist,
+            ))
+            .max_turns(1)
+            .build();
+
+        assert_eq!(
+            supervisor.prompt("Ask the specialist").await.unwrap(),
+            "The specialist says 42"
+        );
+        // The specialist was prompted with the argument of the call, and its answer was sent
+        // back to the supervisor
+        let request = specialist_model.last_request().unwrap();
+        assert_eq!(request.prompt, Message::user("What is the answer?"));
+        let requests = supervisor_model.requests();
+        assert_eq!(requests[0].tools[0].name, "specialist");
+        assert_eq!(requests[0].tools[0].description, "Answers hard questions");
+        assert_eq!(
+            requests[1].prompt,
+            Message::User {
+                content: OneOrMany::one(UserContent::tool_result(
+                    "call_0",
+                    OneOrMany::one(ToolResultContent::text("\"42\""))
+                ))
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_agent_tool_error() {
+        let specialist = AgentBuilder::new(MockCompletionModel::new().error("Overloaded")).build();
+        let tools = ToolSet::from_tools(vec![AgentTool::new("specialist", "", specialist)]);
+
+        let error = tools
+            .call("specialist", r#"{"prompt":"Hi"}"#.into())
+            .await
+            .unwrap_err();
+        let ToolSetError::ToolCallError(crate::tool::ToolError::ToolCallError(error)) = error
+        else {
+            panic!("Unexpected error: {error}");
+        };
+        assert!(matches!(
+            error.downcast_ref::<PromptError>(),
+            Some(PromptError::CompletionError(CompletionError::ProviderError(message)))
+                if message == "Overloaded"
+        ));
+    }
+}
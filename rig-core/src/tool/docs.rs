@@ -0,0 +1,183 @@
+//! Human-readable tool documentation.
+//!
+//! [tools_section] renders the definitions of a set of tools as a markdown section meant to be
+//! included in a system prompt, for models which are instructed to call tools through the
+//! prompt rather than through native tool definitions.
+//! Agents can add it to their preamble automatically with
+//! [AgentBuilder::tool_docs](crate::agent::AgentBuilder::tool_docs).
+use serde_json::Value;
+
+use crate::completion::ToolDefinition;
+
+/// Render the definitions of `tools` as a markdown section, e.g.:
+/// ```text
+/// # Tools
+///
+/// The following tools are available:
+///
+/// ## add
+/// Add x and y together
+///
+/// Parameters:
+/// - `x` (number, required): The first number to add
+/// - `y` (number, required): The second number to add
+/// ```
+pub fn tools_section(tools: &[ToolDefinition]) -> String {
+    let mut section = "# Tools\n\nThe following tools are available:".to_string();
+    for tool in tools {
+        section.push_str(&format!(
+            "\n\n## {}\n{}",
+            tool.name,
+            tool.description.trim()
+        ));
+
+        let parameters = parameters(&tool.parameters);
+        if !parameters.is_empty() {
+            section.push_str("\n\nParameters:");
+            parameters
+                .iter()
+                .for_each(|parameter| section.push_str(&format!("\n- {parameter}")));
+        }
+    }
+    section
+}
+
+/// One line per top-level property of the parameters schema
+fn parameters(schema: &Value) -> Vec<String> {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return vec![];
+    };
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let mut attributes = vec![type_name(property)];
+            if required.contains(&name.as_str()) {
+                attributes.push("required".to_string());
+            }
+            let mut line = format!("`{name}` ({})", attributes.join(", "));
+
+            if let Some(description) = property.get("description").and_then(Value::as_str) {
+                line.push_str(&format!(": {}", description.trim()));
+            }
+            if let Some(values) = property.get("enum").and_then(Value::as_array) {
+                let values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+                line.push_str(&format!(" (one of {})", values.join(", ")));
+            }
+            line
+        })
+        .collect()
+}
+
+fn type_name(property: &Value) -> String {
+    match property.get("type") {
+        Some(Value::String(ty)) if ty == "array" => match property.get("items") {
+            Some(items) => format!("array of {}", type_name(items)),
+            None => "array".to_string(),
+        },
+        Some(Value::String(ty)) => ty.clone(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" or "),
+        _ => "any".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_tools_section() {
+        let tools = vec![
+            ToolDefinition {
+                name: "add".to_string(),
+                description: "Add x and y together".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "mode": {"type": "string", "enum": ["fast", "exact"]},
+                        "tags": {"type": "array", "items": {"type": "string"}},
+                        "x": {"type": "number", "description": "The first number"}
+                    },
+                    "required": ["x"]
+                }),
+            },
+            ToolDefinition {
+                name: "now".to_string(),
+                description: "Current time\n".to_string(),
+                parameters: json!({}),
+            },
+        ];
+
+        assert_eq!(
+            tools_section(&tools),
+            "# Tools\n\n\
+            The following tools are available:\n\n\
+            ## add\n\
+            Add x and y together\n\n\
+            Parameters:\n\
+            - `mode` (string) (one of \"fast\", \"exact\")\n\
+            - `tags` (array of string)\n\
+            - `x` (number, required): The first number\n\n\
+            ## now\n\
+            Current time"
+        );
+    }
+
+    #[test]
+    fn test_tools_section_edge_cases() {
+        assert_eq!(
+            tools_section(&[]),
+            "# Tools\n\nThe following tools are available:"
+        );
+
+        let tools = vec![
+            ToolDefinition {
+                name: "invalid".to_string(),
+                description: "Malformed schema".to_string(),
+                parameters: json!({"properties": ["x"], "required": "x"}),
+            },
+            ToolDefinition {
+                name: "loose".to_string(),
+                description: "Loosely typed".to_string(),
+                parameters: json!({
+                    "properties": {
+                        "any": {},
+                        "list": {"type": "array"},
+                        "value": {"type": ["string", "null"], "enum": [1, null]}
+                    },
+                    "required": [1, "any"]
+                }),
+            },
+        ];
+
+        assert_eq!(
+            tools_section(&tools),
+            "# Tools\n\n\
+            The following tools are available:\n\n\
+            ## invalid\n\
+            Malformed schema\n\n\
+            ## loose\n\
+            Loosely typed\n\n\
+            Parameters:\n\
+            - `any` (any, required)\n\
+            - `list` (array)\n\
+            - `value` (string or null) (one of 1, null)"
+        );
+    }
+}
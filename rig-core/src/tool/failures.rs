@@ -0,0 +1,186 @@
+//! Memory of tool failures.
+//!
+//! An agent configured with [AgentBuilder::remember_tool_failures](crate::agent::AgentBuilder::remember_tool_failures)
+//! keeps track of its most recent failed tool calls and adds a short summary of them to the
+//! context of its next requests, so that the model doesn't retry a broken tool with the same
+//! arguments.
+//!
+//! With [AgentBuilder::tool_circuit_breaker](crate::agent::AgentBuilder::tool_circuit_breaker),
+//! a tool is also disabled (i.e.: no longer sent to the model nor callable) after a number of
+//! consecutive failures, until the failures are [reset](crate::agent::Agent::reset_tool_failures).
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::completion::Document;
+
+/// Failed tool call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolFailure {
+    pub tool: String,
+    pub args: String,
+    pub error: String,
+}
+
+/// Recent tool failures and consecutive failure counts of an agent.
+#[derive(Debug, Default)]
+pub(crate) struct FailureMemory {
+    /// Number of failures kept in memory and summarized in the context
+    capacity: usize,
+    /// Number of consecutive failures after which a tool is disabled
+    threshold: Option<usize>,
+    recent: VecDeque<ToolFailure>,
+    consecutive: HashMap<String, usize>,
+}
+
+impl FailureMemory {
+    pub fn new(capacity: usize, threshold: Option<usize>) -> Self {
+        Self {
+            capacity,
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    pub fn record_failure(&mut self, failure: ToolFailure) {
+        *self.consecutive.entry(failure.tool.clone()).or_default() += 1;
+
+        if self.capacity > 0 {
+            if self.recent.len() == self.capacity {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(failure);
+        }
+    }
+
+    pub fn record_success(&mut self, tool: &str) {
+        self.consecutive.remove(tool);
+    }
+
+    pub fn recent(&self) -> Vec<ToolFailure> {
+        self.recent.iter().cloned().collect()
+    }
+
+    /// Tools disabled by the circuit breaker
+    pub fn broken_tools(&self) -> HashSet<String> {
+        match self.threshold {
+            Some(threshold) => self
+                .consecutive
+                .iter()
+                .filter(|(_, failures)| **failures >= threshold)
+                .map(|(tool, _)| tool.clone())
+                .collect(),
+            None => HashSet::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.recent.clear();
+        self.consecutive.clear();
+    }
+
+    /// Context document summarizing the recent failures, if any
+    pub fn summary(&self) -> Option<Document> {
+        if self.recent.is_empty() {
+            return None;
+        }
+
+        let failures = self
+            .recent
+            .iter()
+            .map(|failure| {
+                format!(
+                    "- `{}` called with {} failed: {}",
+                    failure.tool, failure.args, failure.error
+                )
+            })
+            .collect::<Vec<_>>();
+        let broken_tools = self.broken_tools();
+
+        let mut text = format!(
+            "Recent tool failures (do not retry a tool with the same arguments):\n{}",
+            failures.join("\n")
+        );
+        if !broken_tools.is_empty() {
+            let mut broken_tools = broken_tools.into_iter().collect::<Vec<_>>();
+            broken_tools.sort();
+            text.push_str(&format!(
+                "\nThe following tools are disabled after repeated failures: {}",
+                broken_tools.join(", ")
+            ));
+        }
+
+        Some(Document {
+            id: "tool_failures".to_string(),
+            text,
+            additional_props: HashMap::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(tool: &str) -> ToolFailure {
+        ToolFailure {
+            tool: tool.to_string(),
+            args: "{}".to_string(),
+            error: "boom".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_failure_memory() {
+        let mut memory = FailureMemory::new(2, Some(2));
+        assert!(memory.summary().is_none());
+
+        memory.record_failure(failure("search"));
+        memory.record_failure(failure("fetch"));
+        assert!(memory.broken_tools().is_empty());
+
+        memory.record_failure(failure("search"));
+        assert_eq!(memory.recent(), vec![failure("fetch"), failure("search")]);
+        assert_eq!(memory.broken_tools(), HashSet::from(["search".to_string()]));
+        assert_eq!(
+            memory.summary().unwrap().text,
+            "Recent tool failures (do not retry a tool with the same arguments):\n\
+            - `fetch` called with {} failed: boom\n\
+            - `search` called with {} failed: boom\n\
+            The following tools are disabled after repeated failures: search"
+        );
+
+        // A success resets the consecutive failures of the tool
+        memory.record_success("search");
+        memory.record_failure(failure("search"));
+        assert!(memory.broken_tools().is_empty());
+
+        memory.reset();
+        assert!(memory.summary().is_none());
+    }
+
+    #[test]
+    fn test_failure_memory_limits() {
+        // Without capacity, failures are only counted
+        let mut memory = FailureMemory::new(0, Some(1));
+        memory.record_failure(failure("search"));
+        assert!(memory.recent().is_empty());
+        assert!(memory.summary().is_none());
+        assert_eq!(memory.broken_tools(), HashSet::from(["search".to_string()]));
+
+        // Without threshold, tools are never disabled
+        let mut memory = FailureMemory::new(1, None);
+        (0..5).for_each(|_| memory.record_failure(failure("search")));
+        assert_eq!(memory.recent(), vec![failure("search")]);
+        assert!(memory.broken_tools().is_empty());
+        assert!(!memory
+            .summary()
+            .unwrap()
+            .text
+            .contains("disabled after repeated failures"));
+
+        // Successes of tools which never failed are ignored
+        memory.record_success("fetch");
+        assert_eq!(memory.recent(), vec![failure("search")]);
+    }
+}
@@ -0,0 +1,637 @@
+//! Filesystem tools confined to a sandbox root directory.
+//!
+//! The [Sandbox] struct describes which part of the filesystem the tools are allowed to
+//! touch: a root directory, optional allow/deny glob patterns (matched against paths
+//! relative to the root) and size limits for reads and writes.
+//!
+//! The [ReadFile], [ListDirectory] and [WriteFile] tools can then be added to an agent
+//! to let it inspect and edit files without being able to escape the sandbox.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rig::tool::fs::{ListDirectory, ReadFile, Sandbox, WriteFile};
+//!
+//! let sandbox = Sandbox::new("./workspace")?
+//!     .deny("**/.git/**")?
+//!     .max_write_bytes(64 * 1024);
+//!
+//! let agent = openai_client.agent("gpt-4o")
+//!     .preamble("You are a helpful coding assistant.")
+//!     .tool(ReadFile::new(sandbox.clone()))
+//!     .tool(ListDirectory::new(sandbox.clone()))
+//!     .tool(WriteFile::new(sandbox))
+//!     .build();
+//! ```
+use std::{
+    fs,
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{completion::ToolDefinition, tool::Tool};
+
+/// Default maximum number of bytes a [ReadFile] tool will read (1 MiB).
+pub const DEFAULT_MAX_READ_BYTES: u64 = 1024 * 1024;
+
+/// Default maximum number of bytes a [WriteFile] tool will write (1 MiB).
+pub const DEFAULT_MAX_WRITE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FsToolError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Pattern error: {0}")]
+    PatternError(#[from] glob::PatternError),
+
+    #[error("Path is outside of the sandbox: {0}")]
+    OutsideSandbox(String),
+
+    #[error("Access denied by sandbox policy: {0}")]
+    AccessDenied(String),
+
+    #[error("File too large: {size} bytes exceeds the limit of {limit} bytes")]
+    TooLarge { size: u64, limit: u64 },
+}
+
+/// Sandbox configuration shared by the filesystem tools.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    root: PathBuf,
+    allow: Vec<Pattern>,
+    deny: Vec<Pattern>,
+    max_read_bytes: u64,
+    max_write_bytes: u64,
+}
+
+impl Sandbox {
+    /// Create a new sandbox rooted at `root`. The root directory must exist.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, FsToolError> {
+        Ok(Self {
+            root: root.as_ref().canonicalize()?,
+            allow: vec![],
+            deny: vec![],
+            max_read_bytes: DEFAULT_MAX_READ_BYTES,
+            max_write_bytes: DEFAULT_MAX_WRITE_BYTES,
+        })
+    }
+
+    /// Only allow access to paths matching the given glob pattern.
+    /// Can be called multiple times. If no allow pattern is set, every path is allowed.
+    pub fn allow(mut self, pattern: &str) -> Result<Self, FsToolError> {
+        self.allow.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Deny access to paths matching the given glob pattern. Deny patterns take
+    /// precedence over allow patterns.
+    pub fn deny(mut self, pattern: &str) -> Result<Self, FsToolError> {
+        self.deny.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Set the maximum size of a file that can be read.
+    pub fn max_read_bytes(mut self, max_read_bytes: u64) -> Self {
+        self.max_read_bytes = max_read_bytes;
+        self
+    }
+
+    /// Set the maximum size of a file that can be written. When appending, the existing
+    /// content of the file counts towards the limit.
+    pub fn max_write_bytes(mut self, max_write_bytes: u64) -> Self {
+        self.max_write_bytes = max_write_bytes;
+        self
+    }
+
+    /// The (canonicalized) root directory of the sandbox.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve a path given by the model (relative to the sandbox root) into an absolute
+    /// path, making sure it does not escape the sandbox and is permitted by the policy.
+    ///
+    /// Symlinks are resolved before matching the allow/deny patterns, so both the given
+    /// path and the path it points to must be permitted.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, FsToolError> {
+        let relative = normalize(path)?;
+
+        if !self.is_permitted(&relative) {
+            return Err(FsToolError::AccessDenied(path.to_string()));
+        }
+
+        let resolved = canonicalize(&self.root.join(&relative))?
+            .filter(|resolved| resolved.starts_with(&self.root))
+            .ok_or_else(|| FsToolError::OutsideSandbox(path.to_string()))?;
+
+        if !self.is_permitted(self.relative(&resolved)) {
+            return Err(FsToolError::AccessDenied(path.to_string()));
+        }
+
+        Ok(resolved)
+    }
+
+    fn is_permitted(&self, relative: &Path) -> bool {
+        if self
+            .deny
+            .iter()
+            .any(|pattern| pattern.matches_path(relative))
+        {
+            return false;
+        }
+        // The root itself is always reachable so that it can be listed.
+        relative.as_os_str().is_empty()
+            || self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| pattern.matches_path(relative))
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+}
+
+/// Canonicalize a path that may not exist yet by resolving the symlinks of its deepest
+/// ancestor that already exists on disk and appending the remaining components.
+/// Returns `None` for a dangling symlink, since writing through it would create its target.
+fn canonicalize(path: &Path) -> Result<Option<PathBuf>, FsToolError> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.symlink_metadata().is_ok())
+        .unwrap_or(path);
+    let canonical = match existing.canonicalize() {
+        Ok(canonical) => canonical,
+        Err(_) if existing.is_symlink() => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    match path.strip_prefix(existing) {
+        Ok(remaining) if !remaining.as_os_str().is_empty() => Ok(Some(canonical.join(remaining))),
+        _ => Ok(Some(canonical)),
+    }
+}
+
+/// Lexically normalize a sandbox-relative path, rejecting absolute paths and any
+/// `..` component that would climb above the root.
+fn normalize(path: &str) -> Result<PathBuf, FsToolError> {
+    let mut normalized = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return Err(FsToolError::OutsideSandbox(path.to_string()));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(FsToolError::OutsideSandbox(path.to_string()))
+            }
+        }
+    }
+    Ok(normalized)
+}
+
+#[derive(Deserialize)]
+pub struct ReadFileArgs {
+    pub path: String,
+}
+
+/// Tool that reads the content of a UTF-8 file inside the sandbox.
+#[derive(Debug, Clone)]
+pub struct ReadFile {
+    sandbox: Sandbox,
+}
+
+impl ReadFile {
+    pub fn new(sandbox: Sandbox) -> Self {
+        Self { sandbox }
+    }
+}
+
+impl Tool for ReadFile {
+    const NAME: &'static str = "read_file";
+
+    type Error = FsToolError;
+    type Args = ReadFileArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Read the content of a text file".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the file to read, relative to the workspace root"
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let path = self.sandbox.resolve(&args.path)?;
+
+        let size = fs::metadata(&path)?.len();
+        if size > self.sandbox.max_read_bytes {
+            return Err(FsToolError::TooLarge {
+                size,
+                limit: self.sandbox.max_read_bytes,
+            });
+        }
+
+        Ok(fs::read_to_string(path)?)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListDirectoryArgs {
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// A single entry returned by the [ListDirectory] tool.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DirectoryEntry {
+    /// Path of the entry, relative to the sandbox root.
+    pub path: String,
+    pub is_dir: bool,
+    /// Size of the entry in bytes (0 for directories).
+    pub size: u64,
+}
+
+/// Tool that lists the entries of a directory inside the sandbox.
+/// Entries denied by the sandbox policy (or linking outside of it) are omitted.
+#[derive(Debug, Clone)]
+pub struct ListDirectory {
+    sandbox: Sandbox,
+}
+
+impl ListDirectory {
+    pub fn new(sandbox: Sandbox) -> Self {
+        Self { sandbox }
+    }
+}
+
+impl Tool for ListDirectory {
+    const NAME: &'static str = "list_directory";
+
+    type Error = FsToolError;
+    type Args = ListDirectoryArgs;
+    type Output = Vec<DirectoryEntry>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "List the files and directories contained in a directory".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the directory to list, relative to the workspace root. Defaults to the root."
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let dir = self.sandbox.resolve(args.path.as_deref().unwrap_or("."))?;
+
+        let mut entries = fs::read_dir(dir)?
+            .map(|entry| {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                Ok((entry.path(), metadata))
+            })
+            .collect::<Result<Vec<_>, std::io::Error>>()?
+            .into_iter()
+            .filter(|(path, _)| {
+                self.sandbox
+                    .resolve(&self.sandbox.relative(path).to_string_lossy())
+                    .is_ok()
+            })
+            .map(|(path, metadata)| DirectoryEntry {
+                path: self.sandbox.relative(&path).to_string_lossy().into_owned(),
+                is_dir: metadata.is_dir(),
+                size: if metadata.is_dir() { 0 } else { metadata.len() },
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(entries)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WriteFileArgs {
+    pub path: String,
+    pub content: String,
+    #[serde(default)]
+    pub append: bool,
+}
+
+/// Tool that writes (or appends) text to a file inside the sandbox.
+/// Missing parent directories are created.
+#[derive(Debug, Clone)]
+pub struct WriteFile {
+    sandbox: Sandbox,
+}
+
+impl WriteFile {
+    pub fn new(sandbox: Sandbox) -> Self {
+        Self { sandbox }
+    }
+}
+
+impl Tool for WriteFile {
+    const NAME: &'static str = "write_file";
+
+    type Error = FsToolError;
+    type Args = WriteFileArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Write text content to a file, creating it if it does not exist"
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the file to write, relative to the workspace root"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "The content to write"
+                    },
+                    "append": {
+                        "type": "boolean",
+                        "description": "Append to the file instead of overwriting it"
+                    }
+                },
+                "required": ["path", "content"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let path = self.sandbox.resolve(&args.path)?;
+
+        let size = args.content.len() as u64;
+        let existing = match fs::metadata(&path) {
+            Ok(metadata) if args.append => metadata.len(),
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => 0,
+        };
+        if existing + size > self.sandbox.max_write_bytes {
+            return Err(FsToolError::TooLarge {
+                size: existing + size,
+                limit: self.sandbox.max_write_bytes,
+            });
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(args.append)
+            .truncate(!args.append)
+            .open(&path)?;
+        file.write_all(args.content.as_bytes())?;
+
+        Ok(format!("Wrote {size} bytes to {}", args.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::prelude::*;
+
+    use super::*;
+
+    fn sandbox(temp: &assert_fs::TempDir) -> Sandbox {
+        Sandbox::new(temp.path()).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_rejects_escapes() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let sandbox = sandbox(&temp);
+
+        assert!(matches!(
+            sandbox.resolve("../secret.txt"),
+            Err(FsToolError::OutsideSandbox(_))
+        ));
+        assert!(matches!(
+            sandbox.resolve("/etc/passwd"),
+            Err(FsToolError::OutsideSandbox(_))
+        ));
+        assert_eq!(
+            sandbox.resolve("a/../b.txt").unwrap(),
+            sandbox.root().join("b.txt")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resolve_rejects_dangling_symlinks() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let outside = assert_fs::TempDir::new().unwrap();
+        let target = outside.child("created.txt");
+        std::os::unix::fs::symlink(target.path(), temp.child("link.txt").path()).unwrap();
+        let sandbox = sandbox(&temp);
+
+        assert!(matches!(
+            sandbox.resolve("link.txt"),
+            Err(FsToolError::OutsideSandbox(_))
+        ));
+        let result = WriteFile::new(sandbox)
+            .call(WriteFileArgs {
+                path: "link.txt".into(),
+                content: "escaped".into(),
+                append: false,
+            })
+            .await;
+        assert!(matches!(result, Err(FsToolError::OutsideSandbox(_))));
+        assert!(!target.path().exists());
+    }
+
+    #[test]
+    fn test_allow_deny_globs() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let sandbox = sandbox(&temp)
+            .allow("src/**")
+            .unwrap()
+            .deny("src/secret/**")
+            .unwrap();
+
+        assert!(sandbox.resolve("src/main.rs").is_ok());
+        assert!(matches!(
+            sandbox.resolve("src/secret/key.pem"),
+            Err(FsToolError::AccessDenied(_))
+        ));
+        assert!(matches!(
+            sandbox.resolve("Cargo.toml"),
+            Err(FsToolError::AccessDenied(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_cannot_bypass_deny_globs() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("secret/key.pem").write_str("PRIVATE").unwrap();
+        temp.child("notes.txt").write_str("hello").unwrap();
+        std::os::unix::fs::symlink(temp.child("secret").path(), temp.child("public").path())
+            .unwrap();
+        let sandbox = sandbox(&temp).deny("secret/**").unwrap();
+
+        assert!(matches!(
+            sandbox.resolve("public/key.pem"),
+            Err(FsToolError::AccessDenied(_))
+        ));
+        let result = ReadFile::new(sandbox.clone())
+            .call(ReadFileArgs {
+                path: "public/key.pem".into(),
+            })
+            .await;
+        assert!(matches!(result, Err(FsToolError::AccessDenied(_))));
+        let result = WriteFile::new(sandbox.clone())
+            .call(WriteFileArgs {
+                path: "public/new.pem".into(),
+                content: "PRIVATE".into(),
+                append: false,
+            })
+            .await;
+        assert!(matches!(result, Err(FsToolError::AccessDenied(_))));
+        assert!(!temp.child("secret/new.pem").path().exists());
+
+        let entries = ListDirectory::new(sandbox)
+            .call(ListDirectoryArgs {
+                path: Some("public".into()),
+            })
+            .await
+            .unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_write_list() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("notes.txt").write_str("hello").unwrap();
+        temp.child(".env").write_str("SECRET=1").unwrap();
+        let sandbox = sandbox(&temp).deny(".env").unwrap();
+
+        let content = ReadFile::new(sandbox.clone())
+            .call(ReadFileArgs {
+                path: "notes.txt".into(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(content, "hello");
+
+        WriteFile::new(sandbox.clone())
+            .call(WriteFileArgs {
+                path: "docs/new.md".into(),
+                content: "# Title".into(),
+                append: false,
+            })
+            .await
+            .unwrap();
+        temp.child("docs/new.md").assert("# Title");
+
+        let entries = ListDirectory::new(sandbox)
+            .call(ListDirectoryArgs { path: None })
+            .await
+            .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                DirectoryEntry {
+                    path: "docs".into(),
+                    is_dir: true,
+                    size: 0
+                },
+                DirectoryEntry {
+                    path: "notes.txt".into(),
+                    is_dir: false,
+                    size: 5
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_size_limits() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("big.txt").write_str("0123456789").unwrap();
+        let sandbox = sandbox(&temp).max_read_bytes(4).max_write_bytes(4);
+
+        let result = ReadFile::new(sandbox.clone())
+            .call(ReadFileArgs {
+                path: "big.txt".into(),
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(FsToolError::TooLarge { size: 10, limit: 4 })
+        ));
+
+        let result = WriteFile::new(sandbox)
+            .call(WriteFileArgs {
+                path: "out.txt".into(),
+                content: "too long".into(),
+                append: false,
+            })
+            .await;
+        assert!(matches!(result, Err(FsToolError::TooLarge { .. })));
+        assert!(!temp.child("out.txt").path().exists());
+    }
+
+    #[tokio::test]
+    async fn test_append_size_limit() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("log.txt").write_str("0123").unwrap();
+        let tool = WriteFile::new(sandbox(&temp).max_write_bytes(6));
+
+        let append = |content: &str| WriteFileArgs {
+            path: "log.txt".into(),
+            content: content.into(),
+            append: true,
+        };
+        tool.call(append("45")).await.unwrap();
+        let result = tool.call(append("6")).await;
+        assert!(matches!(
+            result,
+            Err(FsToolError::TooLarge { size: 7, limit: 6 })
+        ));
+        temp.child("log.txt").assert("012345");
+
+        // Overwriting replaces the existing content
+        tool.call(WriteFileArgs {
+            path: "log.txt".into(),
+            content: "abcdef".into(),
+            append: false,
+        })
+        .await
+        .unwrap();
+        temp.child("log.txt").assert("abcdef");
+    }
+}
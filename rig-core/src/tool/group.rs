@@ -0,0 +1,228 @@
+//! Tool groups.
+//!
+//! A [ToolGroup] is a named set of tools registered together, e.g.: all the tools coming from
+//! the same source (local tools, an MCP server, an OpenAPI spec). The tools of a group can be
+//! namespaced with a prefix to avoid name clashes between sources, and whole groups can be
+//! disabled for a given request (see [Agent::without_groups](crate::agent::Agent::without_groups)).
+//!
+//! Registering a group in a [ToolSet](super::ToolSet) fails if one of its tools has the same
+//! name as an already registered tool.
+//!
+//! # Example
+//! ```rust
+//! use rig::{providers::openai, tool::group::ToolGroup};
+//!
+//! let github = ToolGroup::new("github")
+//!     .prefix("gh")
+//!     .tool(SearchIssues)
+//!     .tool(CreateIssue);
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .tool_group(github)?
+//!     .build();
+//!
+//! // The tools are exposed to the model as `gh__search_issues` and `gh__create_issue`,
+//! // except for this request
+//! let response = agent.without_groups(["github"]).prompt("Hello!").await?;
+//! ```
+use std::pin::Pin;
+
+use futures::Future;
+
+use super::{ToolDyn, ToolError};
+use crate::completion::ToolDefinition;
+
+/// Separator between the prefix of a group and the name of its tools.
+pub const NAMESPACE_SEPARATOR: &str = "__";
+
+/// Named set of tools, optionally namespaced with a prefix.
+pub struct ToolGroup {
+    name: String,
+    prefix: Option<String>,
+    pub(crate) tools: Vec<Box<dyn ToolDyn>>,
+}
+
+impl ToolGroup {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            prefix: None,
+            tools: vec![],
+        }
+    }
+
+    /// Prefix the names of the tools of the group with `prefix` (e.g.: `prefix__tool`).
+    /// Must be set before adding tools to the group.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Add a tool to the group.
+    pub fn tool(mut self, tool: impl ToolDyn + 'static) -> Self {
+        match &self.prefix {
+            Some(prefix) => self.tools.push(Box::new(NamespacedTool {
+                name: format!("{prefix}{NAMESPACE_SEPARATOR}{}", tool.name()),
+                tool: Box::new(tool),
+            })),
+            None => self.tools.push(Box::new(tool)),
+        }
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Names of the tools of the group, prefix included.
+    pub fn tool_names(&self) -> Vec<String> {
+        self.tools.iter().map(|tool| tool.name()).collect()
+    }
+}
+
+/// Tool renamed with the prefix of its group. The model sees (and calls) the prefixed name.
+struct NamespacedTool {
+    name: String,
+    tool: Box<dyn ToolDyn>,
+}
+
+impl ToolDyn for NamespacedTool {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        Box::pin(async move {
+            ToolDefinition {
+                name: self.name.clone(),
+                ..self.tool.definition(prompt).await
+            }
+        })
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        self.tool.call(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::tool::{Tool, ToolSet, ToolSetError};
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Echo error")]
+    struct EchoError;
+
+    #[derive(Deserialize)]
+    struct EchoArgs {
+        text: String,
+    }
+
+    struct Echo;
+
+    impl Tool for Echo {
+        const NAME: &'static str = "echo";
+
+        type Error = EchoError;
+        type Args = EchoArgs;
+        type Output = String;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Echo the text".to_string(),
+                parameters: json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(args.text)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_group() {
+        let mut toolset = ToolSet::default();
+        toolset.add_tool(Echo);
+
+        let group = ToolGroup::new("remote").prefix("mcp").tool(Echo);
+        assert_eq!(group.tool_names(), vec!["mcp__echo"]);
+        toolset.add_group(group).unwrap();
+
+        assert_eq!(
+            toolset.group("remote"),
+            Some(&["mcp__echo".to_string()][..])
+        );
+        assert_eq!(
+            toolset
+                .get("mcp__echo")
+                .unwrap()
+                .definition("".into())
+                .await
+                .name,
+            "mcp__echo"
+        );
+        assert_eq!(
+            toolset
+                .call("mcp__echo", r#"{"text": "hi"}"#.to_string())
+                .await
+                .unwrap(),
+            "\"hi\""
+        );
+
+        // Conflicting names are rejected and nothing from the group is registered
+        let group = ToolGroup::new("local").tool(Echo);
+        assert!(matches!(
+            toolset.add_group(group),
+            Err(ToolSetError::DuplicateTool(name)) if name == "echo"
+        ));
+        assert!(toolset.group("local").is_none());
+
+        let group = ToolGroup::new("remote").prefix("other").tool(Echo);
+        assert!(matches!(
+            toolset.add_group(group),
+            Err(ToolSetError::DuplicateGroup(name)) if name == "remote"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tool_group_errors() {
+        // Tools with the same name within a group conflict with each other
+        let mut toolset = ToolSet::default();
+        let group = ToolGroup::new("remote").prefix("mcp").tool(Echo).tool(Echo);
+        assert!(matches!(
+            toolset.add_group(group),
+            Err(ToolSetError::DuplicateTool(name)) if name == "mcp__echo"
+        ));
+        assert!(toolset.group("remote").is_none());
+
+        toolset
+            .add_group(ToolGroup::new("remote").prefix("mcp").tool(Echo))
+            .unwrap();
+
+        // Namespaced tools are only callable with their prefixed name
+        assert!(matches!(
+            toolset.call("echo", r#"{"text": "hi"}"#.to_string()).await,
+            Err(ToolSetError::ToolNotFoundError(name)) if name == "echo"
+        ));
+
+        // Errors of the underlying tool are returned as is
+        assert!(matches!(
+            toolset
+                .call("mcp__echo", r#"{"txt": "hi"}"#.to_string())
+                .await,
+            Err(ToolSetError::ToolCallError(ToolError::JsonError(_)))
+        ));
+    }
+}
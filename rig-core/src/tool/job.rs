@@ -0,0 +1,512 @@
+//! Long-running tool jobs.
+//!
+//! Some tools (e.g.: triggering a build, a data pipeline or a slow search) take longer than
+//! a single turn of conversation. Wrapping such a tool with [JobRegistry::background] makes
+//! calling it return immediately with a [JobHandle], while the tool keeps running in the
+//! background. The model can then check on the job with the [JobStatusTool] pseudo-tool
+//! (see [JobRegistry::status_tool]), and the application can suspend until the job is done
+//! with [JobRegistry::wait] before resuming the conversation.
+//!
+//! Jobs are spawned with a [Spawner], which by default runs each job to completion on a
+//! dedicated thread (see [runtime::spawn](crate::runtime::spawn)). Tools relying on a specific
+//! async runtime (e.g.: tools making HTTP requests with `reqwest`) should use that runtime's
+//! spawner instead.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, providers::openai, tool::job::JobRegistry};
+//!
+//! let jobs = JobRegistry::with_spawner(|job| {
+//!     tokio::spawn(job);
+//! });
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .preamble("You are a release engineer.")
+//!     .background_tool(BuildTool, &jobs)
+//!     .build();
+//!
+//! // Returns a job handle, e.g.: {"job_id":"build-1","status":"running"}
+//! let handle = agent.prompt("Start a release build").await?;
+//!
+//! // Suspend until the build is done, then resume the conversation
+//! jobs.wait("build-1").await?;
+//! let summary = agent.prompt("Check on job build-1 and summarize the result").await?;
+//! ```
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures::{channel::oneshot, Future};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{Tool, ToolDyn, ToolError};
+use crate::{completion::ToolDefinition, runtime::Instant};
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error("Unknown job: {0}")]
+    NotFound(String),
+}
+
+/// Boxed job future passed to a [Spawner].
+pub type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Function spawning a job on an executor, letting it run to completion independently.
+pub type Spawner = Arc<dyn Fn(Job) + Send + Sync>;
+
+/// Status of a background job.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobStatus {
+    Running {
+        tool: String,
+        elapsed_secs: u64,
+    },
+    Completed {
+        tool: String,
+        output: serde_json::Value,
+    },
+    Failed {
+        tool: String,
+        error: String,
+    },
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Running { .. })
+    }
+}
+
+/// Returned to the model in place of the tool output when a background tool is called.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobHandle {
+    pub job_id: String,
+    pub status: String,
+}
+
+enum JobState {
+    Running(Instant),
+    Completed(serde_json::Value),
+    Failed(String),
+}
+
+struct JobEntry {
+    tool: String,
+    state: JobState,
+    waiters: Vec<oneshot::Sender<JobStatus>>,
+}
+
+impl JobEntry {
+    fn status(&self) -> JobStatus {
+        let tool = self.tool.clone();
+        match &self.state {
+            JobState::Running(started) => JobStatus::Running {
+                tool,
+                elapsed_secs: started.elapsed().as_secs(),
+            },
+            JobState::Completed(output) => JobStatus::Completed {
+                tool,
+                output: output.clone(),
+            },
+            JobState::Failed(error) => JobStatus::Failed {
+                tool,
+                error: error.clone(),
+            },
+        }
+    }
+}
+
+/// Registry keeping track of the jobs started by background tools.
+/// Cloning the registry is cheap and clones share the same jobs.
+#[derive(Clone)]
+pub struct JobRegistry {
+    jobs: Arc<Mutex<HashMap<String, JobEntry>>>,
+    counter: Arc<AtomicU64>,
+    spawner: Spawner,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self::with_spawner(crate::runtime::spawn)
+    }
+}
+
+impl JobRegistry {
+    /// Create a new registry running each job on a dedicated thread.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new registry spawning jobs with the given function (e.g.: `tokio::spawn`).
+    pub fn with_spawner(spawner: impl Fn(Job) + Send + Sync + 'static) -> Self {
+        Self {
+            jobs: Default::default(),
+            counter: Default::default(),
+            spawner: Arc::new(spawner),
+        }
+    }
+
+    /// Wrap `tool` so that calling it starts a background job and returns a [JobHandle].
+    pub fn background<T: Tool + 'static>(&self, tool: T) -> BackgroundTool {
+        BackgroundTool {
+            tool: Arc::new(tool),
+            registry: self.clone(),
+        }
+    }
+
+    /// Pseudo-tool allowing the model to check on jobs of this registry.
+    pub fn status_tool(&self) -> JobStatusTool {
+        JobStatusTool {
+            registry: self.clone(),
+        }
+    }
+
+    /// Current status of the job.
+    pub fn status(&self, job_id: &str) -> Result<JobStatus, JobError> {
+        self.jobs
+            .lock()
+            .expect("job registry lock poisoned")
+            .get(job_id)
+            .map(JobEntry::status)
+            .ok_or_else(|| JobError::NotFound(job_id.to_string()))
+    }
+
+    /// Wait until the job is finished and return its final status.
+    pub async fn wait(&self, job_id: &str) -> Result<JobStatus, JobError> {
+        let receiver = {
+            let mut jobs = self.jobs.lock().expect("job registry lock poisoned");
+            let entry = jobs
+                .get_mut(job_id)
+                .ok_or_else(|| JobError::NotFound(job_id.to_string()))?;
+
+            let status = entry.status();
+            if status.is_finished() {
+                return Ok(status);
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            entry.waiters.push(sender);
+            receiver
+        };
+
+        // The sender is only dropped without sending if the job is removed while running
+        receiver
+            .await
+            .map_err(|_| JobError::NotFound(job_id.to_string()))
+    }
+
+    /// Ids of all the jobs in the registry, in no particular order.
+    pub fn jobs(&self) -> Vec<String> {
+        self.jobs
+            .lock()
+            .expect("job registry lock poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Remove the job from the registry, returning its last status. A running job keeps
+    /// running but its result is discarded.
+    pub fn remove(&self, job_id: &str) -> Result<JobStatus, JobError> {
+        self.jobs
+            .lock()
+            .expect("job registry lock poisoned")
+            .remove(job_id)
+            .map(|entry| entry.status())
+            .ok_or_else(|| JobError::NotFound(job_id.to_string()))
+    }
+
+    fn start(&self, tool: Arc<dyn ToolDyn>, args: String) -> JobHandle {
+        let name = tool.name();
+        let job_id = format!(
+            "{name}-{}",
+            self.counter.fetch_add(1, Ordering::Relaxed) + 1
+        );
+
+        self.jobs
+            .lock()
+            .expect("job registry lock poisoned")
+            .insert(
+                job_id.clone(),
+                JobEntry {
+                    tool: name,
+                    state: JobState::Running(Instant::now()),
+                    waiters: vec![],
+                },
+            );
+
+        let jobs = self.jobs.clone();
+        let id = job_id.clone();
+        (self.spawner)(Box::pin(async move {
+            let state = match tool.call(args).await {
+                Ok(output) => JobState::Completed(
+                    serde_json::from_str(&output).unwrap_or(serde_json::Value::String(output)),
+                ),
+                Err(e) => JobState::Failed(e.to_string()),
+            };
+
+            let mut jobs = jobs.lock().expect("job registry lock poisoned");
+            if let Some(entry) = jobs.get_mut(&id) {
+                entry.state = state;
+                let status = entry.status();
+                entry.waiters.drain(..).for_each(|waiter| {
+                    let _ = waiter.send(status.clone());
+                });
+            }
+        }));
+
+        JobHandle {
+            job_id,
+            status: "running".to_string(),
+        }
+    }
+}
+
+/// Tool wrapper returned by [JobRegistry::background]. It has the same name and parameters as
+/// the wrapped tool, but returns a [JobHandle] instead of waiting for the tool's output.
+pub struct BackgroundTool {
+    tool: Arc<dyn ToolDyn>,
+    registry: JobRegistry,
+}
+
+impl ToolDyn for BackgroundTool {
+    fn name(&self) -> String {
+        self.tool.name()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        Box::pin(async move {
+            let mut definition = self.tool.definition(prompt).await;
+            definition.description = format!(
+                "{} This tool runs in the background: it immediately returns a `job_id`, use the \
+                `{}` tool to check on the job and get its output.",
+                definition.description,
+                JobStatusTool::NAME
+            );
+            definition
+        })
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            let handle = self.registry.start(self.tool.clone(), args);
+            serde_json::to_string(&handle).map_err(ToolError::JsonError)
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobStatusArgs {
+    job_id: String,
+}
+
+/// Pseudo-tool returned by [JobRegistry::status_tool], reporting the [JobStatus] of a job
+/// (including its output once completed).
+pub struct JobStatusTool {
+    registry: JobRegistry,
+}
+
+impl Tool for JobStatusTool {
+    const NAME: &'static str = "job_status";
+
+    type Error = JobError;
+    type Args = JobStatusArgs;
+    type Output = JobStatus;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Check the status of a background job. Returns `running` with the \
+                elapsed time, `completed` with the job output or `failed` with the error."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "job_id": {
+                        "type": "string",
+                        "description": "The id of the job, as returned when it was started"
+                    }
+                },
+                "required": ["job_id"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.registry.status(&args.job_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::channel::oneshot;
+    use serde_json::json;
+
+    use super::*;
+    use crate::tool::ToolSet;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Build failed: {0}")]
+    struct BuildError(String);
+
+    #[derive(Deserialize)]
+    struct BuildArgs {
+        target: String,
+    }
+
+    /// Tool blocking until it is released by the test
+    struct Build {
+        release: Mutex<Option<oneshot::Receiver<()>>>,
+    }
+
+    impl Tool for Build {
+        const NAME: &'static str = "build";
+
+        type Error = BuildError;
+        type Args = BuildArgs;
+        type Output = String;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Build a target.".to_string(),
+                parameters: json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            let release = self.release.lock().unwrap().take();
+            if let Some(release) = release {
+                let _ = release.await;
+            }
+            if args.target == "broken" {
+                Err(BuildError(args.target))
+            } else {
+                Ok(format!("built {}", args.target))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_background_tool() {
+        let (release, receiver) = oneshot::channel();
+        let jobs = JobRegistry::new();
+        let toolset = ToolSet::builder()
+            .static_tool(jobs.background(Build {
+                release: Mutex::new(Some(receiver)),
+            }))
+            .static_tool(jobs.status_tool())
+            .build();
+
+        let definition = toolset.get("build").unwrap().definition("".into()).await;
+        assert!(definition.description.contains("job_status"));
+
+        let handle: JobHandle = serde_json::from_str(
+            &toolset
+                .call("build", json!({"target": "app"}).to_string())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(handle.job_id, "build-1");
+
+        let status = toolset
+            .call("job_status", json!({"job_id": "build-1"}).to_string())
+            .await
+            .unwrap();
+        assert!(status.contains(r#""status":"running""#));
+
+        release.send(()).unwrap();
+        assert_eq!(
+            jobs.wait("build-1").await.unwrap(),
+            JobStatus::Completed {
+                tool: "build".to_string(),
+                output: json!("built app"),
+            }
+        );
+
+        let status = toolset
+            .call("job_status", json!({"job_id": "build-1"}).to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            status,
+            r#"{"status":"completed","tool":"build","output":"built app"}"#
+        );
+
+        toolset
+            .call("build", json!({"target": "broken"}).to_string())
+            .await
+            .unwrap();
+        assert!(matches!(
+            jobs.wait("build-2").await.unwrap(),
+            JobStatus::Failed { error, .. } if error.contains("Build failed: broken")
+        ));
+
+        assert!(toolset
+            .call("job_status", json!({"job_id": "build-3"}).to_string())
+            .await
+            .is_err());
+        assert!(jobs.remove("build-1").is_ok());
+        assert_eq!(jobs.jobs(), vec!["build-2".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_job_errors() {
+        let jobs = JobRegistry::with_spawner(|job| {
+            tokio::spawn(job);
+        });
+        assert!(matches!(jobs.status("build-1"), Err(JobError::NotFound(id)) if id == "build-1"));
+        assert!(matches!(
+            jobs.wait("build-1").await,
+            Err(JobError::NotFound(_))
+        ));
+        assert!(matches!(jobs.remove("build-1"), Err(JobError::NotFound(_))));
+
+        // Invalid arguments fail the job, not the call starting it
+        let tool = jobs.background(Build {
+            release: Mutex::new(None),
+        });
+        ToolDyn::call(&tool, json!({"name": "app"}).to_string())
+            .await
+            .unwrap();
+        assert!(matches!(
+            jobs.wait("build-1").await.unwrap(),
+            JobStatus::Failed { error, .. } if error.contains("missing field `target`")
+        ));
+
+        // Waiting on a job removed while running fails
+        let (release, receiver) = oneshot::channel();
+        let tool = jobs.background(Build {
+            release: Mutex::new(Some(receiver)),
+        });
+        ToolDyn::call(&tool, json!({"target": "app"}).to_string())
+            .await
+            .unwrap();
+        let wait = jobs.wait("build-2");
+        let remove = async {
+            assert!(matches!(
+                jobs.remove("build-2").unwrap(),
+                JobStatus::Running { .. }
+            ));
+            release.send(()).unwrap();
+        };
+        let (result, _) = futures::join!(wait, remove);
+        assert!(matches!(result, Err(JobError::NotFound(id)) if id == "build-2"));
+        assert!(jobs.status("build-2").is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! Per-tool execution limits.
+//!
+//! Wrapping a tool in a [LimitedTool] caps how hard an agent can use it, regardless of how
+//! many times the model asks for it:
+//! - the number of concurrent executions (additional calls wait for a running call to finish),
+//! - the total number of calls (per session, until the limits are [reset](LimitHandle::reset)),
+//! - the minimum delay between the start of two consecutive calls.
+//!
+//! Calls over the quota or during the cooldown fail with a [LimitError], which is reported to
+//! the model like any other tool error.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rig::{providers::openai, tool::limits::ToolLimits};
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .tool_with_limits(
+//!         ExpensiveSearch,
+//!         ToolLimits::new()
+//!             .max_concurrent(2)
+//!             .max_calls(20)
+//!             .cooldown(Duration::from_secs(1)),
+//!     )
+//!     .build();
+//! ```
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    lock::Mutex as AsyncMutex,
+    Future, StreamExt,
+};
+
+use super::{ToolDyn, ToolError};
+use crate::{completion::ToolDefinition, runtime::Instant};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LimitError {
+    #[error("Tool {tool} call quota of {limit} calls exceeded")]
+    QuotaExceeded { tool: String, limit: u64 },
+
+    #[error("Tool {tool} is cooling down, retry in {} ms", remaining.as_millis())]
+    Cooldown { tool: String, remaining: Duration },
+}
+
+/// Limits applied to a [LimitedTool]. No limit is applied by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ToolLimits {
+    max_concurrent: Option<usize>,
+    max_calls: Option<u64>,
+    cooldown: Option<Duration>,
+}
+
+impl ToolLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of concurrent executions. Calls over the limit wait for a running call to
+    /// finish. Must be greater than 0.
+    pub fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        assert!(max_concurrent > 0, "max_concurrent must be greater than 0");
+        self.max_concurrent = Some(max_concurrent);
+        self
+    }
+
+    /// Maximum number of calls until the limits are reset.
+    pub fn max_calls(mut self, max_calls: u64) -> Self {
+        self.max_calls = Some(max_calls);
+        self
+    }
+
+    /// Minimum delay between the start of two consecutive calls.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = Some(cooldown);
+        self
+    }
+}
+
+#[derive(Default)]
+struct Usage {
+    calls: u64,
+    last_call: Option<Instant>,
+}
+
+/// Counting semaphore usable with any async runtime: permits are tokens sent over a channel.
+struct Permits {
+    sender: UnboundedSender<()>,
+    receiver: AsyncMutex<UnboundedReceiver<()>>,
+}
+
+impl Permits {
+    fn new(permits: usize) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        (0..permits).for_each(|_| {
+            let _ = sender.unbounded_send(());
+        });
+        Self {
+            sender,
+            receiver: AsyncMutex::new(receiver),
+        }
+    }
+
+    async fn acquire(&self) -> Permit<'_> {
+        // The sender is owned by `self`, so the channel can't be closed while waiting
+        let _ = self.receiver.lock().await.next().await;
+        Permit(self)
+    }
+}
+
+struct Permit<'a>(&'a Permits);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.sender.unbounded_send(());
+    }
+}
+
+struct LimitState {
+    limits: ToolLimits,
+    usage: Mutex<Usage>,
+    permits: Option<Permits>,
+}
+
+/// Handle to the usage of a [LimitedTool], which remains usable after the tool has been added
+/// to an agent or a toolset.
+#[derive(Clone)]
+pub struct LimitHandle(Arc<LimitState>);
+
+impl LimitHandle {
+    /// Number of calls since the tool was created or last reset.
+    pub fn calls(&self) -> u64 {
+        self.0.usage.lock().expect("tool usage lock poisoned").calls
+    }
+
+    /// Number of calls left before the quota is exceeded, if the tool has one.
+    pub fn remaining_calls(&self) -> Option<u64> {
+        self.0
+            .limits
+            .max_calls
+            .map(|max_calls| max_calls.saturating_sub(self.calls()))
+    }
+
+    /// Reset the call quota and cooldown, e.g.: when starting a new session.
+    pub fn reset(&self) {
+        *self.0.usage.lock().expect("tool usage lock poisoned") = Usage::default();
+    }
+}
+
+/// Tool wrapper enforcing [ToolLimits]. It has the same name and definition as the wrapped tool.
+pub struct LimitedTool {
+    tool: Box<dyn ToolDyn>,
+    state: Arc<LimitState>,
+}
+
+impl LimitedTool {
+    pub fn new(tool: impl ToolDyn + 'static, limits: ToolLimits) -> Self {
+        Self {
+            tool: Box::new(tool),
+            state: Arc::new(LimitState {
+                limits,
+                usage: Default::default(),
+                permits: limits.max_concurrent.map(Permits::new),
+            }),
+        }
+    }
+
+    pub fn handle(&self) -> LimitHandle {
+        LimitHandle(self.state.clone())
+    }
+
+    /// Check the quota and cooldown, recording the call if it is allowed.
+    fn record_call(&self) -> Result<(), LimitError> {
+        let limits = &self.state.limits;
+        let mut usage = self.state.usage.lock().expect("tool usage lock poisoned");
+
+        if let Some(limit) = limits.max_calls {
+            if usage.calls >= limit {
+                return Err(LimitError::QuotaExceeded {
+                    tool: self.tool.name(),
+                    limit,
+                });
+            }
+        }
+
+        let now = Instant::now();
+        if let (Some(cooldown), Some(last_call)) = (limits.cooldown, usage.last_call) {
+            let elapsed = now.duration_since(last_call);
+            if elapsed < cooldown {
+                return Err(LimitError::Cooldown {
+                    tool: self.tool.name(),
+                    remaining: cooldown - elapsed,
+                });
+            }
+        }
+
+        usage.calls += 1;
+        usage.last_call = Some(now);
+        Ok(())
+    }
+}
+
+impl ToolDyn for LimitedTool {
+    fn name(&self) -> String {
+        self.tool.name()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        self.tool.definition(prompt)
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            let _permit = match &self.state.permits {
+                Some(permits) => Some(permits.acquire().await),
+                None => None,
+            };
+
+            self.record_call()
+                .map_err(|e| ToolError::ToolCallError(Box::new(e)))?;
+            self.tool.call(args).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::future::join_all;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::tool::Tool;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Search error")]
+    struct SearchError;
+
+    #[derive(Deserialize)]
+    struct SearchArgs {}
+
+    #[derive(Default)]
+    struct Search {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+    }
+
+    impl Tool for Search {
+        const NAME: &'static str = "search";
+
+        type Error = SearchError;
+        type Args = SearchArgs;
+        type Output = usize;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Search".to_string(),
+                parameters: json!({}),
+            }
+        }
+
+        async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(running)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_and_quota() {
+        let tool = LimitedTool::new(
+            Search::default(),
+            ToolLimits::new().max_concurrent(2).max_calls(5),
+        );
+        let handle = tool.handle();
+
+        let results = join_all((0..5).map(|_| tool.call("{}".to_string()))).await;
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert!(results
+            .iter()
+            .all(|result| result.as_ref().unwrap().parse::<usize>().unwrap() <= 2));
+        assert_eq!(handle.calls(), 5);
+        assert_eq!(handle.remaining_calls(), Some(0));
+
+        let error = tool.call("{}".to_string()).await.unwrap_err();
+        assert!(error.to_string().contains("quota of 5 calls exceeded"));
+
+        handle.reset();
+        assert!(tool.call("{}".to_string()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cooldown() {
+        let tool = LimitedTool::new(
+            Search::default(),
+            ToolLimits::new().cooldown(Duration::from_millis(100)),
+        );
+
+        assert!(tool.call("{}".to_string()).await.is_ok());
+        let error = tool.call("{}".to_string()).await.unwrap_err();
+        assert!(error.to_string().contains("cooling down"));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(tool.call("{}".to_string()).await.is_ok());
+        assert_eq!(tool.handle().calls(), 2);
+    }
+}
@@ -0,0 +1,672 @@
+//! Client of the [Model Context Protocol](https://modelcontextprotocol.io) (MCP).
+//!
+//! An [McpClient] connects to an MCP server through a [McpTransport]: the [stdio] transport runs
+//! the server as a child process, and the [sse] transport (requiring the `http` feature)
+//! connects to a remote server with HTTP and Server-Sent Events. Once connected, the tools of the
+//! server are listed with [McpClient::tools] and exposed as rig tools ([McpTool]), or registered
+//! all at once in an agent with [McpClient::tool_group]: the model calls them like any other tool,
+//! and the calls are forwarded to the server.
+//!
+//! The requests of a client are multiplexed over its connection, so the tools of a server can
+//! be called concurrently. The client does not spawn background tasks: the messages of the
+//! server are received by the pending requests themselves, whatever the async runtime.
+//!
+//! # Example
+//! ```rust
+//! use rig::{providers::openai, tool::mcp::{stdio::StdioTransport, McpClient}};
+//! use std::process::Command;
+//!
+//! let mut command = Command::new("npx");
+//! command.args(["-y", "@modelcontextprotocol/server-filesystem", "/tmp"]);
+//! let client = McpClient::connect(StdioTransport::spawn(command)?).await?;
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .tool_group(client.tool_group("filesystem").await?)?
+//!     .max_turns(5)
+//!     .build();
+//! ```
+
+// The transports are not available on wasm, which has no child processes and whose `fetch`
+// streams are not `Send`
+#[cfg(all(feature = "http", not(target_arch = "wasm32")))]
+pub mod sse;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stdio;
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+    lock::Mutex as AsyncMutex,
+    Future,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{group::ToolGroup, ToolDyn, ToolError};
+use crate::completion::ToolDefinition;
+
+/// Version of the protocol requested by the client.
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error code of the requests of methods the client does not implement.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+#[derive(Debug, thiserror::Error)]
+pub enum McpError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[cfg(feature = "http")]
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error response of the server to a request
+    #[error("ServerError: {message} ({code})")]
+    ServerError { code: i64, message: String },
+
+    /// Tool call the server reported as failed, with the output of the tool
+    #[error("ToolCallError: {0}")]
+    ToolCallError(String),
+
+    /// Message (or exchange) not conforming to the protocol
+    #[error("ProtocolError: {0}")]
+    ProtocolError(String),
+
+    #[error("ConnectionClosed: the MCP server closed the connection")]
+    ConnectionClosed,
+}
+
+/// Transport of the JSON-RPC messages exchanged with an MCP server.
+///
+/// Implementations must be cancel safe: a message must not be lost if the future returned by
+/// [McpTransport::receive] is dropped before completing.
+pub trait McpTransport: Send + Sync {
+    /// Send a message to the server.
+    fn send(
+        &self,
+        message: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<(), McpError>> + Send + Sync + '_>>;
+
+    /// Receive the next message of the server, `None` once the connection is closed.
+    fn receive(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Value>, McpError>> + Send + Sync + '_>>;
+}
+
+/// Name and version of the server, as reported when connecting to it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+/// Tool of an MCP server, as listed by the server.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON schema of the arguments of the tool
+    pub input_schema: Value,
+}
+
+/// Content of the result of a tool call (see [McpClient::call_tool]).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum McpContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Resource {
+        resource: Value,
+    },
+}
+
+/// Result of a tool call.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    #[serde(default)]
+    pub content: Vec<McpContent>,
+    /// Whether the tool failed, in which case the content describes the failure
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+impl CallToolResult {
+    /// Text of the result sent back to the model: the text contents, one per line. The other
+    /// contents are sent as JSON.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .map(|content| match content {
+                McpContent::Text { text } => text.clone(),
+                content => serde_json::to_string(content).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeResult {
+    protocol_version: String,
+    #[serde(default)]
+    server_info: ServerInfo,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListToolsResult {
+    tools: Vec<McpToolDefinition>,
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
+type Pending = HashMap<u64, oneshot::Sender<Result<Value, McpError>>>;
+
+struct Connection {
+    transport: Box<dyn McpTransport>,
+    next_id: AtomicU64,
+    /// Requests waiting for their response, by id
+    pending: Mutex<Pending>,
+    /// Held by the request receiving the messages of the server on behalf of the others
+    receiver: AsyncMutex<()>,
+    server: ServerInfo,
+}
+
+/// Client of an MCP server. Cloning the client is cheap and clones share the same connection.
+#[derive(Clone)]
+pub struct McpClient {
+    connection: Arc<Connection>,
+}
+
+impl McpClient {
+    /// Connect to the server at the other end of `transport`, negotiating the protocol.
+    pub async fn connect(transport: impl McpTransport + 'static) -> Result<Self, McpError> {
+        let mut connection = Connection {
+            transport: Box::new(transport),
+            next_id: AtomicU64::new(0),
+            pending: Mutex::new(HashMap::new()),
+            receiver: AsyncMutex::new(()),
+            server: ServerInfo::default(),
+        };
+
+        let result: InitializeResult = serde_json::from_value(
+            connection
+                .request(
+                    "initialize",
+                    json!({
+                        "protocolVersion": PROTOCOL_VERSION,
+                        "capabilities": {},
+                        "clientInfo": {"name": "rig", "version": env!("CARGO_PKG_VERSION")},
+                    }),
+                )
+                .await?,
+        )?;
+        tracing::debug!(
+            target: "rig",
+            "Connected to MCP server {} {} (protocol {})",
+            result.server_info.name,
+            result.server_info.version,
+            result.protocol_version
+        );
+        connection.server = result.server_info;
+        connection
+            .transport
+            .send(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await?;
+
+        Ok(Self {
+            connection: Arc::new(connection),
+        })
+    }
+
+    /// Name and version of the server.
+    pub fn server_info(&self) -> &ServerInfo {
+        &self.connection.server
+    }
+
+    /// List the tools of the server.
+    pub async fn list_tools(&self) -> Result<Vec<McpToolDefinition>, McpError> {
+        let mut tools = vec![];
+        let mut cursor = None;
+        loop {
+            let params = match cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result: ListToolsResult =
+                serde_json::from_value(self.connection.request("tools/list", params).await?)?;
+            tools.extend(result.tools);
+            match result.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tools),
+            }
+        }
+    }
+
+    /// Call the tool `name` of the server with `arguments`.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .connection
+            .request("tools/call", json!({"name": name, "arguments": arguments}))
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// The tools of the server, as rig tools calling the server.
+    pub async fn tools(&self) -> Result<Vec<McpTool>, McpError> {
+        Ok(self
+            .list_tools()
+            .await?
+            .into_iter()
+            .map(|definition| McpTool {
+                client: self.clone(),
+                definition,
+            })
+            .collect())
+    }
+
+    /// The tools of the server as a [ToolGroup] named `name`, to register them all in an agent
+    /// (see [AgentBuilder::tool_group](crate::agent::AgentBuilder::tool_group)). Use
+    /// [McpClient::tools] and [ToolGroup::prefix] to namespace the tools.
+    pub async fn tool_group(&self, name: &str) -> Result<ToolGroup, McpError> {
+        Ok(self
+            .tools()
+            .await?
+            .into_iter()
+            .fold(ToolGroup::new(name), |group, tool| group.tool(tool)))
+    }
+}
+
+impl Connection {
+    async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, mut response) = oneshot::channel();
+        self.pending
+            .lock()
+            .expect("MCP pending requests lock poisoned")
+            .insert(id, sender);
+        // Forget the request if it is cancelled
+        let _guard = PendingGuard {
+            connection: self,
+            id,
+        };
+
+        self.transport
+            .send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}))
+            .await?;
+
+        loop {
+            // Wait for the response, received by another request or by this one
+            let guard = match future::select(&mut response, self.receiver.lock()).await {
+                Either::Left((response, _)) => {
+                    return response.unwrap_or(Err(McpError::ConnectionClosed))
+                }
+                Either::Right((guard, _)) => guard,
+            };
+            if let Ok(Some(response)) = response.try_recv() {
+                return response;
+            }
+
+            let message = self.transport.receive().await;
+            drop(guard);
+            match message {
+                Ok(Some(message)) => self.dispatch(message).await?,
+                Ok(None) => {
+                    self.close(|| McpError::ConnectionClosed);
+                    return Err(McpError::ConnectionClosed);
+                }
+                Err(e) => {
+                    let error = e.to_string();
+                    self.close(|| McpError::ProtocolError(error.clone()));
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Handle a message of the server: responses are sent to their requests, and the requests
+    /// of the server are answered (only pings are supported). Notifications are ignored.
+    async fn dispatch(&self, message: Value) -> Result<(), McpError> {
+        let id = message.get("id").cloned();
+        match (message.get("method").and_then(Value::as_str), id) {
+            (Some("ping"), Some(id)) => {
+                self.transport
+                    .send(json!({"jsonrpc": "2.0", "id": id, "result": {}}))
+                    .await
+            }
+            (Some(method), Some(id)) => {
+                self.transport
+                    .send(json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("Method not supported by the client: {method}"),
+                        }
+                    }))
+                    .await
+            }
+            (Some(_), None) => Ok(()),
+            (None, Some(id)) => {
+                let sender = id.as_u64().and_then(|id| {
+                    self.pending
+                        .lock()
+                        .expect("MCP pending requests lock poisoned")
+                        .remove(&id)
+                });
+                if let Some(sender) = sender {
+                    let _ = sender.send(response_result(message));
+                }
+                Ok(())
+            }
+            (None, None) => Err(McpError::ProtocolError(format!(
+                "Message without method nor id: {message}"
+            ))),
+        }
+    }
+
+    /// Fail the pending requests once the connection is broken.
+    fn close(&self, error: impl Fn() -> McpError) {
+        let pending = std::mem::take(
+            &mut *self
+                .pending
+                .lock()
+                .expect("MCP pending requests lock poisoned"),
+        );
+        for (_, sender) in pending {
+            let _ = sender.send(Err(error()));
+        }
+    }
+}
+
+fn response_result(mut message: Value) -> Result<Value, McpError> {
+    if let Some(error) = message.get("error") {
+        return Err(McpError::ServerError {
+            code: error
+                .get("code")
+                .and_then(Value::as_i64)
+                .unwrap_or_default(),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    match message.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => Err(McpError::ProtocolError(format!(
+            "Response without result nor error: {message}"
+        ))),
+    }
+}
+
+struct PendingGuard<'a> {
+    connection: &'a Connection,
+    id: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.connection.pending.lock() {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+/// Tool of an MCP server, whose calls are forwarded to the server.
+pub struct McpTool {
+    client: McpClient,
+    definition: McpToolDefinition,
+}
+
+impl McpTool {
+    pub fn definition(&self) -> &McpToolDefinition {
+        &self.definition
+    }
+}
+
+impl ToolDyn for McpTool {
+    fn name(&self) -> String {
+        self.definition.name.clone()
+    }
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        Box::pin(async move {
+            ToolDefinition {
+                name: self.definition.name.clone(),
+                description: self.definition.description.clone().unwrap_or_default(),
+                parameters: self.definition.input_schema.clone(),
+            }
+        })
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            let arguments = serde_json::from_str(&args).map_err(ToolError::JsonError)?;
+            let result = self
+                .client
+                .call_tool(&self.definition.name, arguments)
+                .await
+                .map_err(|e| ToolError::ToolCallError(Box::new(e)))?;
+
+            match result.is_error {
+                true => Err(ToolError::ToolCallError(Box::new(McpError::ToolCallError(
+                    result.text(),
+                )))),
+                false => Ok(result.text()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{channel::mpsc, StreamExt};
+
+    use super::*;
+    use crate::tool::{ToolSet, ToolSetError};
+
+    /// In-memory server with an `echo` tool, a failing `fail` tool, and a `slow` tool answered
+    /// only after the next call (so that responses arrive out of order).
+    struct MockServer {
+        sent: Mutex<Vec<Value>>,
+        /// `slow` call waiting for the next call to be answered
+        held: Mutex<Option<Value>>,
+        messages: mpsc::UnboundedSender<Value>,
+        received: AsyncMutex<mpsc::UnboundedReceiver<Value>>,
+    }
+
+    impl MockServer {
+        fn new() -> Arc<Self> {
+            let (messages, received) = mpsc::unbounded();
+            Arc::new(Self {
+                sent: Mutex::new(vec![]),
+                held: Mutex::new(None),
+                messages,
+                received: AsyncMutex::new(received),
+            })
+        }
+
+        fn respond(&self, message: &Value) {
+            let id = message["id"].clone();
+            let result = match message["method"].as_str() {
+                Some("initialize") => json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": "mock", "version": "1.0"},
+                }),
+                Some("tools/list") if message["params"]["cursor"].is_null() => json!({
+                    "tools": [{
+                        "name": "echo",
+                        "description": "Echoes its text",
+                        "inputSchema": {"type": "object", "properties": {"text": {"type": "string"}}},
+                    }],
+                    "nextCursor": "2",
+                }),
+                Some("tools/list") => json!({
+                    "tools": [
+                        {"name": "fail", "inputSchema": {"type": "object"}},
+                        {"name": "slow", "inputSchema": {"type": "object"}},
+                    ],
+                }),
+                Some("tools/call") => match message["params"]["name"].as_str() {
+                    Some("echo") => {
+                        // Ping the client before answering
+                        self.messages
+                            .unbounded_send(
+                                json!({"jsonrpc": "2.0", "id": "ping", "method": "ping"}),
+                            )
+                            .unwrap();
+                        json!({"content": [{"type": "text", "text": message["params"]["arguments"]["text"]}]})
+                    }
+                    Some("fail") => {
+                        json!({"content": [{"type": "text", "text": "Out of order"}], "isError": true})
+                    }
+                    Some("slow") => {
+                        *self.held.lock().unwrap() = Some(message.clone());
+                        return;
+                    }
+                    _ => {
+                        self.send(json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": {"code": -32602, "message": "Unknown tool"},
+                        }));
+                        return;
+                    }
+                },
+                _ => return,
+            };
+            self.send(json!({"jsonrpc": "2.0", "id": id, "result": result}));
+
+            if let Some(held) = self.held.lock().unwrap().take() {
+                self.send(json!({
+                    "jsonrpc": "2.0",
+                    "id": held["id"],
+                    "result": {"content": [{"type": "text", "text": "slow"}]},
+                }));
+            }
+        }
+
+        fn send(&self, message: Value) {
+            self.messages.unbounded_send(message).unwrap();
+        }
+    }
+
+    impl McpTransport for Arc<MockServer> {
+        fn send(
+            &self,
+            message: Value,
+        ) -> Pin<Box<dyn Future<Output = Result<(), McpError>> + Send + Sync + '_>> {
+            self.sent.lock().unwrap().push(message.clone());
+            self.respond(&message);
+            Box::pin(async { Ok(()) })
+        }
+
+        fn receive(
+            &self,
+        ) -> Pin<Box<dyn Future<Output = Result<Option<Value>, McpError>> + Send + Sync + '_>>
+        {
+            Box::pin(async move { Ok(self.received.lock().await.next().await) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mcp_client() {
+        let server = MockServer::new();
+        let client = McpClient::connect(server.clone()).await.unwrap();
+        assert_eq!(
+            client.server_info(),
+            &ServerInfo {
+                name: "mock".into(),
+                version: "1.0".into()
+            }
+        );
+
+        // The tools of all the pages are listed
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(
+            tools
+                .iter()
+                .map(|tool| tool.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["echo", "fail", "slow"]
+        );
+        assert_eq!(tools[0].description.as_deref(), Some("Echoes its text"));
+
+        let mut toolset = ToolSet::default();
+        toolset
+            .add_group(client.tool_group("mock").await.unwrap())
+            .unwrap();
+        assert_eq!(toolset.group("mock").unwrap().len(), 3);
+        assert_eq!(
+            toolset
+                .call("echo", r#"{"text":"Hello"}"#.into())
+                .await
+                .unwrap(),
+            "Hello"
+        );
+        assert!(matches!(
+            toolset.call("fail", "{}".into()).await,
+            Err(ToolSetError::ToolCallError(ToolError::ToolCallError(e)))
+                if e.to_string() == "ToolCallError: Out of order"
+        ));
+        assert!(matches!(
+            client.call_tool("unknown", json!({})).await,
+            Err(McpError::ServerError { code: -32602, message }) if message == "Unknown tool"
+        ));
+
+        // The client answered the ping of the server, after the initialization
+        let sent = server.sent.lock().unwrap().clone();
+        assert_eq!(sent[1]["method"], "notifications/initialized");
+        assert!(sent.contains(&json!({"jsonrpc": "2.0", "id": "ping", "result": {}})));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_calls() {
+        let client = McpClient::connect(MockServer::new()).await.unwrap();
+
+        // The response of the slow call arrives after the response of the next call
+        let (slow, echo) = futures::join!(
+            client.call_tool("slow", json!({})),
+            client.call_tool("echo", json!({"text": "fast"})),
+        );
+        assert_eq!(slow.unwrap().text(), "slow");
+        assert_eq!(echo.unwrap().text(), "fast");
+    }
+}
@@ -0,0 +1,286 @@
+//! Transport to a remote MCP server, with HTTP and Server-Sent Events.
+//!
+//! The [SseTransport] opens an event stream with a `GET` request on the URL of the server, which
+//! answers with the endpoint the client must `POST` its messages to. The messages of the server
+//! (e.g.: the responses to the requests of the client) are received on the event stream.
+//!
+//! Headers (e.g.: an authorization token) are set on the [reqwest::Client] used by the transport.
+//!
+//! # Example
+//! ```rust
+//! use rig::tool::mcp::{sse::SseTransport, McpClient};
+//!
+//! let transport = SseTransport::connect("http://localhost:8000/sse").await?;
+//! let client = McpClient::connect(transport).await?;
+//! ```
+use std::{
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::{future, Future, Stream};
+use reqwest::Url;
+use serde_json::Value;
+
+use super::{McpError, McpTransport};
+
+/// Event of the stream announcing the endpoint of the messages of the client.
+const ENDPOINT_EVENT: &str = "endpoint";
+/// Event of the stream carrying a message of the server.
+const MESSAGE_EVENT: &str = "message";
+
+/// Transport to a remote MCP server, with HTTP and Server-Sent Events.
+pub struct SseTransport {
+    client: reqwest::Client,
+    /// Endpoint the messages of the client are posted to
+    endpoint: Url,
+    events: Mutex<EventStream>,
+}
+
+impl SseTransport {
+    /// Open the event stream of the server at `url`.
+    pub async fn connect(url: &str) -> Result<Self, McpError> {
+        Self::with_client(reqwest::Client::new(), url).await
+    }
+
+    /// Open the event stream of the server at `url` with `client` (e.g.: a client with
+    /// authorization headers).
+    pub async fn with_client(client: reqwest::Client, url: &str) -> Result<Self, McpError> {
+        let url = Url::parse(url).map_err(|e| McpError::ProtocolError(e.to_string()))?;
+        let response = client
+            .get(url.clone())
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let mut events = EventStream {
+            stream: Box::pin(response.bytes_stream()),
+            buffer: vec![],
+        };
+        let endpoint = loop {
+            match future::poll_fn(|cx| events.poll_event(cx)).await {
+                Some(Ok(event)) if event.name == ENDPOINT_EVENT => break event.data,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.into()),
+                None => return Err(McpError::ConnectionClosed),
+            }
+        };
+        let endpoint = url
+            .join(endpoint.trim())
+            .map_err(|e| McpError::ProtocolError(format!("Invalid endpoint {endpoint}: {e}")))?;
+
+        Ok(Self {
+            client,
+            endpoint,
+            events: Mutex::new(events),
+        })
+    }
+}
+
+impl McpTransport for SseTransport {
+    fn send(
+        &self,
+        message: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<(), McpError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            self.client
+                .post(self.endpoint.clone())
+                .json(&message)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+
+    fn receive(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Value>, McpError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            loop {
+                let event = future::poll_fn(|cx| {
+                    self.events
+                        .lock()
+                        .expect("MCP event stream lock poisoned")
+                        .poll_event(cx)
+                })
+                .await;
+                match event {
+                    Some(Ok(event)) if event.name == MESSAGE_EVENT => {
+                        return Ok(Some(serde_json::from_str(&event.data)?))
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                    None => return Ok(None),
+                }
+            }
+        })
+    }
+}
+
+struct Event {
+    name: String,
+    data: String,
+}
+
+/// Events parsed from the body of the event stream, as it is received.
+struct EventStream {
+    stream: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
+    /// Bytes received but not parsed yet
+    buffer: Vec<u8>,
+}
+
+impl EventStream {
+    /// Poll the next event of the stream. Events without data (e.g.: keep-alive comments) are
+    /// skipped.
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Option<reqwest::Result<Event>>> {
+        loop {
+            while let Some(event) = self.parse_event() {
+                if let Some(event) = event {
+                    return Poll::Ready(Some(Ok(event)));
+                }
+            }
+            match self.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self
+                    .buffer
+                    .extend(chunk.iter().filter(|byte| **byte != b'\r').copied()),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Parse the first complete event of the buffer: `None` if there is none, `Some(None)` if
+    /// it has no data.
+    fn parse_event(&mut self) -> Option<Option<Event>> {
+        let end = self
+            .buffer
+            .windows(2)
+            .position(|window| window == b"\n\n")?;
+        let block = self.buffer.drain(..end + 2).collect::<Vec<_>>();
+        let block = String::from_utf8_lossy(&block);
+
+        let mut name = MESSAGE_EVENT.to_string();
+        let mut data: Option<String> = None;
+        for line in block.lines() {
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => name = value.to_string(),
+                "data" => match &mut data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(value);
+                    }
+                    None => data = Some(value.to_string()),
+                },
+                _ => (),
+            }
+        }
+
+        Some(data.map(|data| Event { name, data }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::tool::mcp::{McpClient, PROTOCOL_VERSION};
+
+    #[tokio::test]
+    async fn test_sse_transport() {
+        let server = MockServer::start();
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": {"protocolVersion": PROTOCOL_VERSION, "serverInfo": {"name": "remote"}},
+        });
+        let tools = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {"tools": [{"name": "search", "inputSchema": {"type": "object"}}]},
+        });
+        let events = server.mock(|when, then| {
+            when.method(GET).path("/sse");
+            then.status(200)
+                .header("content-type", "text/event-stream")
+                .body(format!(
+                    ": keep-alive\r\n\r\nevent: endpoint\r\ndata: /messages?session=1\r\n\r\nevent: message\ndata: {initialize}\n\nevent: message\ndata: {tools}\n\n"
+                ));
+        });
+        let messages = server.mock(|when, then| {
+            when.method(POST)
+                .path("/messages")
+                .query_param("session", "1");
+            then.status(202);
+        });
+
+        let transport = SseTransport::connect(&server.url("/sse")).await.unwrap();
+        let client = McpClient::connect(transport).await.unwrap();
+        assert_eq!(client.server_info().name, "remote");
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "search");
+
+        events.assert();
+        // Initialization, its notification and the listing of the tools
+        messages.assert_hits(3);
+    }
+
+    #[tokio::test]
+    async fn test_sse_transport_errors() {
+        assert!(matches!(
+            SseTransport::connect("not a url").await,
+            Err(McpError::ProtocolError(_))
+        ));
+
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/unavailable");
+            then.status(503);
+        });
+        assert!(matches!(
+            SseTransport::connect(&server.url("/unavailable")).await,
+            Err(McpError::HttpError(e)) if e.status() == Some(reqwest::StatusCode::SERVICE_UNAVAILABLE)
+        ));
+
+        // The stream ends before announcing the endpoint
+        server.mock(|when, then| {
+            when.method(GET).path("/closed");
+            then.status(200)
+                .header("content-type", "text/event-stream")
+                .body("event: message\ndata: {}\n\n");
+        });
+        assert!(matches!(
+            SseTransport::connect(&server.url("/closed")).await,
+            Err(McpError::ConnectionClosed)
+        ));
+
+        server.mock(|when, then| {
+            when.method(GET).path("/sse");
+            then.status(200)
+                .header("content-type", "text/event-stream")
+                .body("event: endpoint\ndata: /messages\n\nevent: message\ndata: {invalid\n\n");
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/messages");
+            then.status(404);
+        });
+        let transport = SseTransport::connect(&server.url("/sse")).await.unwrap();
+        assert!(matches!(
+            transport.send(json!({"jsonrpc": "2.0"})).await,
+            Err(McpError::HttpError(_))
+        ));
+        assert!(matches!(
+            transport.receive().await,
+            Err(McpError::JsonError(_))
+        ));
+        assert!(matches!(transport.receive().await, Ok(None)));
+    }
+}
@@ -0,0 +1,155 @@
+//! Transport to an MCP server running as a child process.
+//!
+//! The [StdioTransport] spawns the server and exchanges newline-delimited JSON-RPC messages with
+//! it over its standard input and output. The standard error of the server (its logs) is
+//! inherited. The pipes are read and written on dedicated threads, so the transport does not
+//! depend on a specific async runtime. The server is killed when the transport is dropped.
+//!
+//! # Example
+//! ```rust
+//! use std::process::Command;
+//! use rig::tool::mcp::{stdio::StdioTransport, McpClient};
+//!
+//! let mut command = Command::new("uvx");
+//! command.arg("mcp-server-time");
+//! let client = McpClient::connect(StdioTransport::spawn(command)?).await?;
+//! ```
+use std::{
+    io::{BufRead, BufReader, Write},
+    pin::Pin,
+    process::{Child, Command, Stdio},
+    sync::{mpsc as std_mpsc, Mutex},
+    thread,
+};
+
+use futures::{channel::mpsc, lock::Mutex as AsyncMutex, Future, StreamExt};
+use serde_json::Value;
+
+use super::{McpError, McpTransport};
+
+/// Transport to an MCP server running as a child process.
+pub struct StdioTransport {
+    child: Mutex<Child>,
+    /// Messages written to the standard input of the server by the writer thread
+    input: Mutex<std_mpsc::Sender<String>>,
+    /// Messages read from the standard output of the server by the reader thread
+    output: AsyncMutex<mpsc::UnboundedReceiver<Result<Value, McpError>>>,
+}
+
+impl StdioTransport {
+    /// Spawn the server with `command`, whose standard input and output are piped.
+    pub fn spawn(mut command: Command) -> Result<Self, McpError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (input, inputs) = std_mpsc::channel::<String>();
+        thread::spawn(move || {
+            for message in inputs {
+                if writeln!(stdin, "{message}")
+                    .and_then(|_| stdin.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let (outputs, output) = mpsc::unbounded();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let message = match line {
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => serde_json::from_str(&line).map_err(McpError::from),
+                    Err(e) => Err(McpError::from(e)),
+                };
+                let failed = message.is_err();
+                if outputs.unbounded_send(message).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child: Mutex::new(child),
+            input: Mutex::new(input),
+            output: AsyncMutex::new(output),
+        })
+    }
+}
+
+impl McpTransport for StdioTransport {
+    fn send(
+        &self,
+        message: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<(), McpError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            self.input
+                .lock()
+                .expect("MCP stdio input lock poisoned")
+                .send(serde_json::to_string(&message)?)
+                .map_err(|_| McpError::ConnectionClosed)
+        })
+    }
+
+    fn receive(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<Value>, McpError>> + Send + Sync + '_>> {
+        Box::pin(async move { self.output.lock().await.next().await.transpose() })
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        if let Ok(child) = self.child.get_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::mcp::McpClient;
+
+    #[tokio::test]
+    async fn test_stdio_transport() {
+        // `cat` echoes the initialization request, which the client refuses to handle: the
+        // refusal is echoed back as the response of the request.
+        let transport = StdioTransport::spawn(Command::new("cat")).unwrap();
+        assert!(matches!(
+            McpClient::connect(transport).await,
+            Err(McpError::ServerError { code: -32601, .. })
+        ));
+
+        assert!(matches!(
+            StdioTransport::spawn(Command::new("rig-no-such-mcp-server")),
+            Err(McpError::IoError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stdio_transport_errors() {
+        // Invalid messages end the output of the server
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo; echo '{invalid'; echo '{}'"]);
+        let transport = StdioTransport::spawn(command).unwrap();
+        assert!(matches!(
+            transport.receive().await,
+            Err(McpError::JsonError(_))
+        ));
+        assert!(matches!(transport.receive().await, Ok(None)));
+
+        let transport = StdioTransport::spawn(Command::new("true")).unwrap();
+        assert!(matches!(transport.receive().await, Ok(None)));
+        assert!(matches!(
+            McpClient::connect(transport).await,
+            Err(McpError::ConnectionClosed)
+        ));
+    }
+}
@@ -7,17 +7,65 @@
 //! stored in a vector store and RAGged.
 //!
 //! The [ToolSet] struct is a collection of tools that can be used by an [Agent](crate::agent::Agent)
-//! and optionally RAGged.
+//! and optionally RAGged: [ToolSet::index] embeds the descriptions of its tools so that an agent
+//! only sends the tools most relevant to each prompt.
+//!
+//! The [fs] module provides ready-made filesystem tools confined to a sandbox directory,
+//! the [shell] module a command execution tool guarded by an explicit policy, the
+//! [calculator] module a deterministic math and unit conversion tool, the [datetime]
+//! module a date, time and timezone tool with an injectable clock and the [webhook] module a
+//! tool posting signed payloads to configured webhooks.
+//!
+//! The [job] module allows tools whose execution outlives a single turn to run as background
+//! jobs that the model can check on, the [limits] module allows capping the concurrency,
+//! number of calls and call rate of a tool and the [cache] module allows memoizing the results
+//! of a tool. The [output] module allows validating the outputs of a tool against an output
+//! schema and consuming them as typed values.
+//!
+//! The [group] module allows registering tools in named, optionally namespaced, groups which
+//! can be disabled as a whole for a given request, and the [docs] module renders tool
+//! definitions as documentation for a system prompt. The [failures] module defines the memory
+//! of tool failures agents use to avoid retrying broken tools. The [mcp] module connects to
+//! Model Context Protocol servers and exposes their tools as rig tools, and the [delegate] module
+//! wraps agents as tools, so that a supervisor agent can delegate tasks to sub-agents.
+
+pub mod cache;
+#[cfg(feature = "builtin-tools")]
+pub mod calculator;
+#[cfg(feature = "builtin-tools")]
+pub mod datetime;
+pub mod delegate;
+pub mod docs;
+pub mod failures;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fs;
+pub mod group;
+pub mod job;
+pub mod limits;
+pub mod mcp;
+pub mod output;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shell;
+#[cfg(all(feature = "builtin-tools", feature = "http"))]
+pub mod webhook;
 
 use std::{collections::HashMap, pin::Pin};
 
-use futures::Future;
+use futures::{
+    future::{self, Either},
+    Future,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     completion::{self, ToolDefinition},
-    embeddings::{embed::EmbedError, tool::ToolSchema},
+    embeddings::{
+        embed::EmbedError, tool::ToolSchema, EmbeddingError, EmbeddingModel, EmbeddingsBuilder,
+    },
+    runtime::CancellationToken,
+    vector_store::in_memory_store::{InMemoryVectorIndex, InMemoryVectorStore},
 };
+use group::ToolGroup;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ToolError {
@@ -84,6 +132,34 @@ pub enum ToolError {
 ///     }
 /// }
 /// ```
+///
+/// # Cancellation
+/// A tool call is cancelled by dropping its future (e.g.: when the prompt of the agent times out
+/// or is cancelled, or when the call exceeds its [timeout](crate::agent::AgentBuilder::tool_timeout)),
+/// so the futures of tools must be drop-safe: they must not leave inconsistent state behind
+/// when dropped at an `.await` point.
+///
+/// Dropping the future does not stop the work it started outside of itself (e.g.: a child
+/// process, a thread or a spawned task). Tools starting such work must observe the cancellation
+/// of their call, to stop the work when the call is cancelled: the [ToolSet] runs the calls in
+/// the scope of the [CancellationToken] of the operation calling them (the token of the run of
+/// an agent is cancelled when its prompt is cancelled, times out or is dropped), which is
+/// returned by [CancellationToken::current]. Calls made by a cancelled operation fail with
+/// [ToolSetError::Cancelled], even if the tool ignores the token.
+///
+/// ```
+/// use rig::runtime::CancellationToken;
+///
+/// async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+///     // Cancel the job when the call is cancelled, or when its future is dropped
+///     let token = CancellationToken::current().unwrap_or_default().child_token();
+///     let guard = token.clone().drop_guard();
+///     let job = self.scheduler.start(args, token).await?;
+///     let output = job.output().await?;
+///     guard.disarm();
+///     Ok(output)
+/// }
+/// ```
 pub trait Tool: Sized + Send + Sync {
     /// The name of the tool. This name should be unique.
     const NAME: &'static str;
@@ -239,6 +315,20 @@ pub enum ToolSetError {
     #[error("ToolNotFoundError: {0}")]
     ToolNotFoundError(String),
 
+    /// The tool did not answer in time, and its call was cancelled
+    #[error("TimeoutError: {0} did not answer within {1:?}")]
+    TimeoutError(String, std::time::Duration),
+
+    /// The operation calling the tool was cancelled (see [Tool#cancellation])
+    #[error("Cancelled: the call of {0} was cancelled")]
+    Cancelled(String),
+
+    #[error("DuplicateTool: a tool named {0} is already registered")]
+    DuplicateTool(String),
+
+    #[error("DuplicateGroup: a tool group named {0} is already registered")]
+    DuplicateGroup(String),
+
     // TODO: Revisit this
     #[error("JsonError: {0}")]
     JsonError(#[from] serde_json::Error),
@@ -248,6 +338,8 @@ pub enum ToolSetError {
 #[derive(Default)]
 pub struct ToolSet {
     pub(crate) tools: HashMap<String, ToolType>,
+    /// Names of the tools of each tool group
+    pub(crate) groups: HashMap<String, Vec<String>>,
 }
 
 impl ToolSet {
@@ -276,9 +368,50 @@ impl ToolSet {
             .insert(tool.name(), ToolType::Simple(Box::new(tool)));
     }
 
+    /// Add a tool to the toolset, failing if a tool with the same name is already registered
+    pub fn try_add_tool(&mut self, tool: impl ToolDyn + 'static) -> Result<(), ToolSetError> {
+        if self.contains(&tool.name()) {
+            return Err(ToolSetError::DuplicateTool(tool.name()));
+        }
+        self.add_tool(tool);
+        Ok(())
+    }
+
+    /// Add a group of tools to the toolset. Nothing is added if the group or one of its tools
+    /// conflicts with an already registered group or tool.
+    pub fn add_group(&mut self, group: ToolGroup) -> Result<(), ToolSetError> {
+        if self.groups.contains_key(group.name()) {
+            return Err(ToolSetError::DuplicateGroup(group.name().to_string()));
+        }
+
+        let names = group.tool_names();
+        for (i, name) in names.iter().enumerate() {
+            if self.contains(name) || names[..i].contains(name) {
+                return Err(ToolSetError::DuplicateTool(name.clone()));
+            }
+        }
+
+        self.groups.insert(group.name().to_string(), names);
+        group.tools.into_iter().for_each(|tool| {
+            self.tools.insert(tool.name(), ToolType::Simple(tool));
+        });
+        Ok(())
+    }
+
+    /// Names of the tools of the group `name`, if it exists
+    pub fn group(&self, name: &str) -> Option<&[String]> {
+        self.groups.get(name).map(Vec::as_slice)
+    }
+
+    /// Names of the tool groups of the toolset
+    pub fn groups(&self) -> impl Iterator<Item = &str> {
+        self.groups.keys().map(String::as_str)
+    }
+
     /// Merge another toolset into this one
     pub fn add_tools(&mut self, toolset: ToolSet) {
         self.tools.extend(toolset.tools);
+        self.groups.extend(toolset.groups);
     }
 
     pub(crate) fn get(&self, toolname: &str) -> Option<&ToolType> {
@@ -292,7 +425,17 @@ impl ToolSet {
                 "Calling tool {toolname} with args:\n{}",
                 serde_json::to_string_pretty(&args).unwrap_or_else(|_| args.clone())
             );
-            Ok(tool.call(args).await?)
+            let Some(token) = CancellationToken::current() else {
+                return Ok(tool.call(args).await?);
+            };
+            if token.is_cancelled() {
+                return Err(ToolSetError::Cancelled(toolname.to_string()));
+            }
+            let call = std::pin::pin!(tool.call(args));
+            match future::select(call, std::pin::pin!(token.cancelled())).await {
+                Either::Left((result, _)) => Ok(result?),
+                Either::Right(_) => Err(ToolSetError::Cancelled(toolname.to_string())),
+            }
         } else {
             Err(ToolSetError::ToolNotFoundError(toolname.to_string()))
         }
@@ -353,6 +496,63 @@ impl ToolSet {
             })
             .collect::<Result<Vec<_>, _>>()
     }
+
+    /// Convert all the tools in self to objects of type ToolSchema. Tools which do not
+    /// implement [ToolEmbedding] are embedded by the description of their definition (or by
+    /// their name if it is empty), and have a `null` context.
+    pub async fn all_schemas(&self) -> Result<Vec<ToolSchema>, EmbedError> {
+        let mut schemas = Vec::with_capacity(self.tools.len());
+        for tool in self.tools.values() {
+            match tool {
+                ToolType::Embedding(tool) => schemas.push(ToolSchema::try_from(&**tool)?),
+                ToolType::Simple(tool) => {
+                    let definition = tool.definition("".to_string()).await;
+                    let doc = if definition.description.is_empty() {
+                        tool.name()
+                    } else {
+                        definition.description
+                    };
+                    schemas.push(ToolSchema {
+                        name: tool.name(),
+                        context: serde_json::Value::Null,
+                        embedding_docs: vec![doc],
+                    });
+                }
+            }
+        }
+        Ok(schemas)
+    }
+
+    /// Embed all the tools in self (see [ToolSet::all_schemas]) with `model` and return an
+    /// in-memory index of them keyed by tool name. Passing the index and the toolset to
+    /// [AgentBuilder::dynamic_tools](crate::agent::AgentBuilder::dynamic_tools) lets the agent
+    /// send only the tools most relevant to each prompt, which keeps requests small when an
+    /// application has many tools.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// let index = toolset.index(embedding_model).await?;
+    /// let agent = client.agent("gpt-4o").dynamic_tools(4, index, toolset).build();
+    /// ```
+    pub async fn index<M: EmbeddingModel>(
+        &self,
+        model: M,
+    ) -> Result<InMemoryVectorIndex<M, ToolSchema>, EmbeddingError> {
+        let schemas = self
+            .all_schemas()
+            .await
+            .map_err(|e| EmbeddingError::DocumentError(Box::new(e)))?;
+        let embeddings = EmbeddingsBuilder::new(model.clone())
+            .documents(schemas)
+            .map_err(|e| EmbeddingError::DocumentError(Box::new(e)))?
+            .build()
+            .await?;
+
+        Ok(
+            InMemoryVectorStore::from_documents_with_id_f(embeddings, |tool| tool.name.clone())
+                .index(model),
+        )
+    }
 }
 
 #[derive(Default)]
@@ -378,6 +578,7 @@ impl ToolSetBuilder {
                 .into_iter()
                 .map(|tool| (tool.name(), tool))
                 .collect(),
+            groups: HashMap::new(),
         }
     }
 }
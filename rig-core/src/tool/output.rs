@@ -0,0 +1,510 @@
+//! Tool output schemas and typed tool outputs.
+//!
+//! Wrapping a tool in a [ValidatedTool] declares the JSON schema of its output (derived from
+//! the tool's `Output` type, or provided explicitly) and validates each result against it
+//! before it is returned to the model. Results that don't match the schema fail with an
+//! [OutputError], which is reported to the model like any other tool error.
+//!
+//! A validated tool can also record its outputs in a [ToolTrace], from which host code can
+//! get back the typed outputs of a tool once the agent is done.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, providers::openai, tool::output::ToolTrace};
+//!
+//! let trace = ToolTrace::new();
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent("gpt-4o")
+//!     .validated_tool(WeatherLookup, &trace)
+//!     .build();
+//!
+//! agent.prompt("What's the weather like in Paris?").await?;
+//!
+//! // Typed outputs of all the calls to the tool
+//! let reports: Vec<WeatherReport> = trace.outputs::<WeatherLookup>()?;
+//! ```
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use futures::Future;
+use schemars::{schema_for, JsonSchema};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{Tool, ToolDyn, ToolError};
+use crate::completion::ToolDefinition;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutputError {
+    #[error("Tool {tool} output does not match its schema: {}", errors.join("; "))]
+    SchemaViolation { tool: String, errors: Vec<String> },
+}
+
+/// Output of a tool call recorded in a [ToolTrace].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolOutput {
+    pub tool: String,
+    pub args: Value,
+    pub output: Value,
+}
+
+/// Record of the outputs of the [ValidatedTool]s sharing it. Cloning the trace is cheap and
+/// clones share the same records.
+#[derive(Clone, Default)]
+pub struct ToolTrace {
+    outputs: Arc<Mutex<Vec<ToolOutput>>>,
+}
+
+impl ToolTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All the recorded outputs, in call order.
+    pub fn entries(&self) -> Vec<ToolOutput> {
+        self.outputs
+            .lock()
+            .expect("tool trace lock poisoned")
+            .clone()
+    }
+
+    /// Recorded outputs of the tool named `toolname`, in call order.
+    pub fn outputs_of(&self, toolname: &str) -> Vec<Value> {
+        self.outputs
+            .lock()
+            .expect("tool trace lock poisoned")
+            .iter()
+            .filter(|output| output.tool == toolname)
+            .map(|output| output.output.clone())
+            .collect()
+    }
+
+    /// Recorded outputs of the tool `T`, deserialized to its output type.
+    pub fn outputs<T>(&self) -> Result<Vec<T::Output>, serde_json::Error>
+    where
+        T: Tool,
+        T::Output: DeserializeOwned,
+    {
+        self.outputs_of(T::NAME)
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect()
+    }
+
+    /// Last recorded output of the tool `T`, if any.
+    pub fn last_output<T>(&self) -> Result<Option<T::Output>, serde_json::Error>
+    where
+        T: Tool,
+        T::Output: DeserializeOwned,
+    {
+        self.outputs_of(T::NAME)
+            .pop()
+            .map(serde_json::from_value)
+            .transpose()
+    }
+
+    pub fn clear(&self) {
+        self.outputs
+            .lock()
+            .expect("tool trace lock poisoned")
+            .clear();
+    }
+
+    fn record(&self, output: ToolOutput) {
+        self.outputs
+            .lock()
+            .expect("tool trace lock poisoned")
+            .push(output);
+    }
+}
+
+/// Tool wrapper validating the output of a tool against its output schema. It has the same
+/// name and definition as the wrapped tool.
+pub struct ValidatedTool {
+    tool: Box<dyn ToolDyn>,
+    schema: Value,
+    trace: Option<ToolTrace>,
+}
+
+impl ValidatedTool {
+    /// Wrap `tool`, using the JSON schema of its output type as output schema.
+    pub fn new<T>(tool: T) -> Self
+    where
+        T: Tool + 'static,
+        T::Output: JsonSchema,
+    {
+        Self::with_schema(tool, json!(schema_for!(T::Output)))
+    }
+
+    /// Wrap `tool`, using `schema` as output schema.
+    pub fn with_schema(tool: impl ToolDyn + 'static, schema: Value) -> Self {
+        Self {
+            tool: Box::new(tool),
+            schema,
+            trace: None,
+        }
+    }
+
+    /// Record the validated outputs of the tool in `trace`.
+    pub fn trace(mut self, trace: &ToolTrace) -> Self {
+        self.trace = Some(trace.clone());
+        self
+    }
+
+    pub fn output_schema(&self) -> &Value {
+        &self.schema
+    }
+}
+
+impl ToolDyn for ValidatedTool {
+    fn name(&self) -> String {
+        self.tool.name()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        self.tool.definition(prompt)
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            let output = self.tool.call(args.clone()).await?;
+            let value = serde_json::from_str::<Value>(&output)?;
+
+            let errors = validate(&self.schema, &value);
+            if !errors.is_empty() {
+                return Err(ToolError::ToolCallError(Box::new(
+                    OutputError::SchemaViolation {
+                        tool: self.tool.name(),
+                        errors,
+                    },
+                )));
+            }
+
+            if let Some(trace) = &self.trace {
+                trace.record(ToolOutput {
+                    tool: self.tool.name(),
+                    args: serde_json::from_str(&args).unwrap_or(Value::String(args)),
+                    output: value,
+                });
+            }
+            Ok(output)
+        })
+    }
+}
+
+/// Validate `value` against `schema`, returning the list of violations.
+///
+/// Supports the subset of JSON schema generated by `schemars`: `type`, `enum`, `const`,
+/// `properties`, `required`, `additionalProperties`, `items`, `allOf`, `anyOf`, `oneOf`,
+/// numeric and length bounds, and local `$ref`s.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = vec![];
+    validate_at(schema, schema, value, "$", &mut errors);
+    errors
+}
+
+fn validate_at(root: &Value, schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            errors.push(format!("{path}: no value is allowed"));
+            return;
+        }
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+        {
+            Some(schema) => validate_at(root, schema, value, path, errors),
+            None => errors.push(format!("{path}: unresolved reference {reference}")),
+        }
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types = match types {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            types => types.as_str().into_iter().collect::<Vec<_>>(),
+        };
+        if !types.iter().any(|ty| has_type(value, ty)) {
+            errors.push(format!(
+                "{path}: expected {}, found {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if !values.contains(value) {
+            errors.push(format!("{path}: {value} is not one of the allowed values"));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{path}: expected {expected}, found {value}"));
+        }
+    }
+
+    if let Some(schemas) = schema.get("allOf").and_then(Value::as_array) {
+        schemas
+            .iter()
+            .for_each(|schema| validate_at(root, schema, value, path, errors));
+    }
+
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(schemas) = schema.get(keyword).and_then(Value::as_array) {
+            let matching = schemas
+                .iter()
+                .filter(|schema| {
+                    let mut errors = vec![];
+                    validate_at(root, schema, value, path, &mut errors);
+                    errors.is_empty()
+                })
+                .count();
+            if matching == 0 || (keyword == "oneOf" && matching > 1) {
+                errors.push(format!("{path}: does not match {keyword} schemas"));
+            }
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                required
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .filter(|field| !object.contains_key(*field))
+                    .for_each(|field| errors.push(format!("{path}: missing field `{field}`")));
+            }
+
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (field, field_value) in object {
+                let field_path = format!("{path}.{field}");
+                match properties.and_then(|properties| properties.get(field)) {
+                    Some(field_schema) => {
+                        validate_at(root, field_schema, field_value, &field_path, errors)
+                    }
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{path}: unexpected field `{field}`"))
+                        }
+                        Some(additional) => {
+                            validate_at(root, additional, field_value, &field_path, errors)
+                        }
+                        None => (),
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(items_schema) = schema.get("items") {
+                items.iter().enumerate().for_each(|(i, item)| {
+                    validate_at(root, items_schema, item, &format!("{path}[{i}]"), errors)
+                });
+            }
+            check_bounds(schema, "minItems", "maxItems", items.len(), path, errors);
+        }
+        Value::String(s) => {
+            check_bounds(
+                schema,
+                "minLength",
+                "maxLength",
+                s.chars().count(),
+                path,
+                errors,
+            );
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < minimum {
+                    errors.push(format!("{path}: {n} is less than {minimum}"));
+                }
+            }
+            if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > maximum {
+                    errors.push(format!("{path}: {n} is greater than {maximum}"));
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn check_bounds(
+    schema: &serde_json::Map<String, Value>,
+    min_keyword: &str,
+    max_keyword: &str,
+    len: usize,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(min) = schema.get(min_keyword).and_then(Value::as_u64) {
+        if (len as u64) < min {
+            errors.push(format!("{path}: length {len} is less than {min}"));
+        }
+    }
+    if let Some(max) = schema.get(max_keyword).and_then(Value::as_u64) {
+        if (len as u64) > max {
+            errors.push(format!("{path}: length {len} is greater than {max}"));
+        }
+    }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Weather error")]
+    struct WeatherError;
+
+    #[derive(Deserialize)]
+    struct WeatherArgs {
+        city: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+    struct WeatherReport {
+        city: String,
+        temperature: f64,
+        conditions: Vec<Conditions>,
+        humidity: Option<u8>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Conditions {
+        Sunny,
+        Cloudy,
+    }
+
+    struct Weather;
+
+    impl Tool for Weather {
+        const NAME: &'static str = "weather";
+
+        type Error = WeatherError;
+        type Args = WeatherArgs;
+        type Output = WeatherReport;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Weather".to_string(),
+                parameters: json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(WeatherReport {
+                city: args.city,
+                temperature: 21.5,
+                conditions: vec![Conditions::Sunny],
+                humidity: Some(40),
+            })
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = json!(schema_for!(WeatherReport));
+
+        let report = json!({
+            "city": "Paris",
+            "temperature": 21.5,
+            "conditions": ["sunny", "cloudy"],
+            "humidity": null
+        });
+        assert!(validate(&schema, &report).is_empty());
+
+        let report = json!({
+            "temperature": "hot",
+            "conditions": ["rainy"],
+            "humidity": -5
+        });
+        let mut errors = validate(&schema, &report);
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "$.conditions[0]: \"rainy\" is not one of the allowed values",
+                "$.humidity: -5 is less than 0",
+                "$.temperature: expected number, found string",
+                "$: missing field `city`",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validated_tool() {
+        let trace = ToolTrace::new();
+        let tool = ValidatedTool::new(Weather).trace(&trace);
+
+        tool.call(r#"{"city": "Paris"}"#.to_string()).await.unwrap();
+        tool.call(r#"{"city": "Rome"}"#.to_string()).await.unwrap();
+
+        let reports = trace.outputs::<Weather>().unwrap();
+        assert_eq!(
+            reports.iter().map(|r| r.city.as_str()).collect::<Vec<_>>(),
+            ["Paris", "Rome"]
+        );
+        assert_eq!(trace.entries()[0].args, json!({"city": "Paris"}));
+
+        // Outputs not matching the schema are not returned nor recorded
+        let tool = ValidatedTool::with_schema(
+            Weather,
+            json!({"type": "object", "properties": {"temperature": {"maximum": 20}}}),
+        )
+        .trace(&trace);
+        let error = tool
+            .call(r#"{"city": "Oslo"}"#.to_string())
+            .await
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("$.temperature: 21.5 is greater than 20"));
+        assert_eq!(trace.entries().len(), 2);
+    }
+}
@@ -0,0 +1,515 @@
+//! Shell command tool guarded by an explicit execution policy.
+//!
+//! The [ShellTool] lets an agent run commands on the host, but only those permitted by
+//! its [ShellPolicy]: an allow-list of executables, optional glob rules restricting the
+//! arguments each executable accepts, a timeout after which the process is killed and
+//! a limit on how much output is returned to the model. The process is also killed when the
+//! call is [cancelled](crate::tool::Tool#cancellation) (e.g.: when the prompt of the agent
+//! times out).
+//!
+//! Commands are executed directly (never through `sh -c`), so shell metacharacters in
+//! arguments are passed verbatim to the program rather than interpreted.
+//!
+//! # Example
+//! ```rust,ignore
+//! use std::time::Duration;
+//! use rig::tool::shell::{ShellPolicy, ShellTool};
+//!
+//! let policy = ShellPolicy::new()
+//!     .allow("uptime")
+//!     .allow_with_args("systemctl", &["status", "*.service"])?
+//!     .deny_arg("--force")?
+//!     .timeout(Duration::from_secs(10))
+//!     .max_output_bytes(16 * 1024);
+//!
+//! let agent = openai_client.agent("gpt-4o")
+//!     .preamble("You are an ops assistant.")
+//!     .tool(ShellTool::new(policy))
+//!     .build();
+//! ```
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{completion::ToolDefinition, runtime::CancellationToken, tool::Tool};
+
+/// Default time a command is allowed to run before being killed.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default maximum number of bytes of stdout (and stderr) returned to the model.
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShellToolError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Pattern error: {0}")]
+    PatternError(#[from] glob::PatternError),
+
+    #[error("Executable not allowed: {0}")]
+    ExecutableNotAllowed(String),
+
+    #[error("Argument not allowed for {executable}: {argument}")]
+    ArgumentNotAllowed {
+        executable: String,
+        argument: String,
+    },
+
+    #[error("Command timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Command execution was interrupted")]
+    Interrupted,
+
+    #[error("Command was cancelled")]
+    Cancelled,
+}
+
+/// Rules deciding which commands a [ShellTool] may run.
+#[derive(Debug, Clone)]
+pub struct ShellPolicy {
+    /// Allowed executables. `None` means any arguments are accepted, otherwise every
+    /// argument must match at least one of the patterns.
+    executables: HashMap<String, Option<Vec<Pattern>>>,
+    /// Arguments rejected for every executable.
+    denied_args: Vec<Pattern>,
+    timeout: Duration,
+    max_output_bytes: usize,
+    working_dir: Option<PathBuf>,
+}
+
+impl Default for ShellPolicy {
+    fn default() -> Self {
+        Self {
+            executables: HashMap::new(),
+            denied_args: vec![],
+            timeout: DEFAULT_TIMEOUT,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            working_dir: None,
+        }
+    }
+}
+
+impl ShellPolicy {
+    /// Create a new policy that denies everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `executable` to be run with any arguments (subject to [ShellPolicy::deny_arg]).
+    pub fn allow(mut self, executable: &str) -> Self {
+        self.executables.insert(executable.to_string(), None);
+        self
+    }
+
+    /// Allow `executable` to be run only with arguments matching one of the given glob patterns.
+    pub fn allow_with_args(
+        mut self,
+        executable: &str,
+        patterns: &[&str],
+    ) -> Result<Self, ShellToolError> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| Pattern::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.executables
+            .insert(executable.to_string(), Some(patterns));
+        Ok(self)
+    }
+
+    /// Reject any argument matching the given glob pattern, whatever the executable.
+    pub fn deny_arg(mut self, pattern: &str) -> Result<Self, ShellToolError> {
+        self.denied_args.push(Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Set the maximum time a command is allowed to run before being killed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the maximum number of bytes of stdout and stderr returned to the model.
+    pub fn max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Set the directory commands are run in. Defaults to the current directory.
+    pub fn working_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// Check that the command is permitted by the policy.
+    pub fn check(&self, executable: &str, args: &[String]) -> Result<(), ShellToolError> {
+        let rules = self
+            .executables
+            .get(executable)
+            .ok_or_else(|| ShellToolError::ExecutableNotAllowed(executable.to_string()))?;
+
+        for arg in args {
+            let denied = self.denied_args.iter().any(|pattern| pattern.matches(arg));
+            let allowed = rules
+                .as_ref()
+                .is_none_or(|patterns| patterns.iter().any(|pattern| pattern.matches(arg)));
+
+            if denied || !allowed {
+                return Err(ShellToolError::ArgumentNotAllowed {
+                    executable: executable.to_string(),
+                    argument: arg.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ShellArgs {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Result of a command executed by the [ShellTool].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShellOutput {
+    /// Exit code of the process, `None` if it was terminated by a signal.
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether stdout or stderr were cut to respect the output limit.
+    pub truncated: bool,
+}
+
+/// Tool that runs commands allowed by a [ShellPolicy].
+#[derive(Debug, Clone)]
+pub struct ShellTool {
+    policy: ShellPolicy,
+}
+
+impl ShellTool {
+    pub fn new(policy: ShellPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl Tool for ShellTool {
+    const NAME: &'static str = "shell";
+
+    type Error = ShellToolError;
+    type Args = ShellArgs;
+    type Output = ShellOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let mut executables = self.policy.executables.keys().cloned().collect::<Vec<_>>();
+        executables.sort();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Run a command on the host. The command is executed directly, not through a shell. Allowed commands: {}",
+                executables.join(", ")
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "The executable to run"
+                    },
+                    "args": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The arguments passed to the executable"
+                    }
+                },
+                "required": ["command"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.policy.check(&args.command, &args.args)?;
+
+        tracing::info!(target: "rig", "Running command: {} {}", args.command, args.args.join(" "));
+
+        let mut command = Command::new(&args.command);
+        command
+            .args(&args.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &self.policy.working_dir {
+            command.current_dir(dir);
+        }
+
+        // The child is supervised on a dedicated thread so that the tool does not depend
+        // on any particular async runtime. It is killed when the call is cancelled, or when the
+        // future of the call is dropped.
+        let (tx, rx) = futures::channel::oneshot::channel();
+        let timeout = self.policy.timeout;
+        let max_output_bytes = self.policy.max_output_bytes;
+        let token = CancellationToken::current()
+            .unwrap_or_default()
+            .child_token();
+        let guard = token.clone().drop_guard();
+        thread::spawn(move || {
+            let _ = tx.send(run(command, timeout, max_output_bytes, &token));
+        });
+
+        let result = rx.await.map_err(|_| ShellToolError::Interrupted)?;
+        guard.disarm();
+        result
+    }
+}
+
+fn run(
+    mut command: Command,
+    timeout: Duration,
+    max_output_bytes: usize,
+    token: &CancellationToken,
+) -> Result<ShellOutput, ShellToolError> {
+    let mut child = command.spawn()?;
+
+    // Drain the pipes concurrently to avoid the child blocking on a full pipe buffer.
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| Capture::spawn(pipe, max_output_bytes));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| Capture::spawn(pipe, max_output_bytes));
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Err(ShellToolError::Timeout(timeout));
+        }
+        if token.is_cancelled() {
+            child.kill()?;
+            child.wait()?;
+            return Err(ShellToolError::Cancelled);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    // A process spawned by the child may still hold the pipes open: wait for them to be
+    // closed until the timeout only, and return the output read so far.
+    let deadline = start + timeout;
+    let finish = |capture: Option<Capture>| {
+        capture
+            .map(|capture| capture.finish(deadline))
+            .unwrap_or_default()
+    };
+    let (stdout, stdout_truncated) = truncate(finish(stdout), max_output_bytes);
+    let (stderr, stderr_truncated) = truncate(finish(stderr), max_output_bytes);
+
+    Ok(ShellOutput {
+        exit_code: status.code(),
+        stdout,
+        stderr,
+        truncated: stdout_truncated || stderr_truncated,
+    })
+}
+
+/// Output of a pipe, read on a dedicated thread.
+struct Capture {
+    output: Arc<Mutex<Vec<u8>>>,
+    done: mpsc::Receiver<()>,
+}
+
+impl Capture {
+    fn spawn(pipe: impl Read + Send + 'static, limit: usize) -> Self {
+        let output = Arc::new(Mutex::new(vec![]));
+        let (tx, done) = mpsc::channel();
+        let buffer = output.clone();
+        thread::spawn(move || {
+            read_capped(pipe, limit, &buffer);
+            let _ = tx.send(());
+        });
+        Self { output, done }
+    }
+
+    /// Wait for the pipe to be closed until `deadline`, then take the output read so far.
+    fn finish(self, deadline: Instant) -> Vec<u8> {
+        let _ = self
+            .done
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()));
+        self.output
+            .lock()
+            .map(|mut output| std::mem::take(&mut *output))
+            .unwrap_or_default()
+    }
+}
+
+/// Read `pipe` until it is closed, keeping at most `limit + 1` bytes (enough to tell
+/// whether the output was truncated) and discarding the rest.
+fn read_capped(mut pipe: impl Read, limit: usize, output: &Mutex<Vec<u8>>) {
+    let mut chunk = [0; 8192];
+    loop {
+        let read = match pipe.read(&mut chunk) {
+            Ok(0) => return,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => return,
+        };
+        let Ok(mut output) = output.lock() else {
+            return;
+        };
+        let keep = (limit + 1).saturating_sub(output.len()).min(read);
+        output.extend_from_slice(&chunk[..keep]);
+    }
+}
+
+/// Lossily decode `bytes` as UTF-8, keeping at most `limit` bytes.
+fn truncate(mut bytes: Vec<u8>, limit: usize) -> (String, bool) {
+    let truncated = bytes.len() > limit;
+    bytes.truncate(limit);
+    let mut output = String::from_utf8_lossy(&bytes).into_owned();
+    if truncated {
+        // A multi-byte character may have been cut in half.
+        if output.ends_with(char::REPLACEMENT_CHARACTER) {
+            output.pop();
+        }
+        output.push_str("\n[output truncated]");
+    }
+    (output, truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &str, args: &[&str]) -> ShellArgs {
+        ShellArgs {
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_policy_check() {
+        let policy = ShellPolicy::new()
+            .allow("echo")
+            .allow_with_args("git", &["status", "log", "--oneline"])
+            .unwrap()
+            .deny_arg("*secret*")
+            .unwrap();
+
+        assert!(policy.check("echo", &["hello".into()]).is_ok());
+        assert!(policy
+            .check("git", &["log".into(), "--oneline".into()])
+            .is_ok());
+        assert!(matches!(
+            policy.check("git", &["push".into()]),
+            Err(ShellToolError::ArgumentNotAllowed { .. })
+        ));
+        assert!(matches!(
+            policy.check("echo", &["my-secret-file".into()]),
+            Err(ShellToolError::ArgumentNotAllowed { .. })
+        ));
+        assert!(matches!(
+            policy.check("rm", &["-rf".into(), "/".into()]),
+            Err(ShellToolError::ExecutableNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(
+            truncate(b"hello".to_vec(), 10),
+            ("hello".to_string(), false)
+        );
+        assert_eq!(
+            truncate("héllo".as_bytes().to_vec(), 2),
+            ("h\n[output truncated]".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_read_capped() {
+        let output = Mutex::new(vec![]);
+        read_capped(std::io::repeat(b'y').take(1 << 20), 10, &output);
+        assert_eq!(output.into_inner().unwrap(), b"yyyyyyyyyyy");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_command() {
+        let tool = ShellTool::new(ShellPolicy::new().allow("echo").max_output_bytes(5));
+
+        let output = tool.call(args("echo", &["hello world"])).await.unwrap();
+        assert_eq!(output.exit_code, Some(0));
+        assert_eq!(output.stdout, "hello\n[output truncated]");
+        assert!(output.truncated);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout() {
+        let tool = ShellTool::new(
+            ShellPolicy::new()
+                .allow("sleep")
+                .timeout(Duration::from_millis(100)),
+        );
+
+        let result = tool.call(args("sleep", &["5"])).await;
+        assert!(matches!(result, Err(ShellToolError::Timeout(_))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pipe_held_open_by_grandchild() {
+        let tool = ShellTool::new(
+            ShellPolicy::new()
+                .allow("sh")
+                .timeout(Duration::from_millis(500)),
+        );
+
+        let start = Instant::now();
+        let output = tool
+            .call(args("sh", &["-c", "echo started; sleep 5 &"]))
+            .await
+            .unwrap();
+        assert_eq!(output.exit_code, Some(0));
+        assert_eq!(output.stdout, "started\n");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancellation() {
+        let tool = ShellTool::new(ShellPolicy::new().allow("sleep"));
+
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        crate::runtime::spawn(async move {
+            crate::runtime::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+        let start = Instant::now();
+        let result = token.scope(tool.call(args("sleep", &["5"]))).await;
+        assert!(matches!(result, Err(ShellToolError::Cancelled)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
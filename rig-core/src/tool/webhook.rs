@@ -0,0 +1,389 @@
+//! Webhook tool, to let agents notify external systems.
+//!
+//! The [WebhookTool] posts JSON payloads produced by the model to a set of configured
+//! [Webhook]s (e.g.: a chat channel, a ticketing system, a deployment pipeline). The model
+//! only chooses the webhook, by its name, and the payload: the URLs, headers and secrets
+//! stay out of the prompt.
+//!
+//! Requests to webhooks configured with a secret are signed with HMAC-SHA256: the
+//! `X-Rig-Signature` header contains `sha256=<hex digest>` of the request body, so that the
+//! receiver can check that the payload was sent by the agent and was not tampered with.
+//! Failed deliveries (connection errors, `429` and `5xx` responses) are retried with an
+//! exponential backoff.
+//!
+//! # Example
+//! ```rust
+//! use rig::tool::webhook::{Webhook, WebhookTool};
+//!
+//! let tool = WebhookTool::new()
+//!     .webhook(
+//!         Webhook::new("ops_channel", "https://hooks.example.com/ops")
+//!             .description("Post a message to the ops team, as `{\"text\": \"...\"}`")
+//!             .secret("my-shared-secret"),
+//!     )
+//!     .max_retries(5);
+//!
+//! let agent = openai_client.agent("gpt-4o")
+//!     .preamble("You are a deployment assistant. Notify the ops team when you are done.")
+//!     .tool(tool)
+//!     .build();
+//! ```
+use std::{collections::BTreeMap, time::Duration};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::{completion::ToolDefinition, runtime, tool::Tool};
+
+/// Header containing the HMAC-SHA256 signature of the request body
+pub const SIGNATURE_HEADER: &str = "X-Rig-Signature";
+
+/// Default number of times a failed delivery is retried.
+pub const DEFAULT_MAX_RETRIES: usize = 3;
+
+/// Default delay before the first retry, doubled after each attempt.
+pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(500);
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Unknown webhook: {0}")]
+    UnknownWebhook(String),
+
+    /// The webhook rejected the payload (or kept failing until the retries were exhausted)
+    #[error("Webhook {webhook} responded with status {status}: {body}")]
+    Status {
+        webhook: String,
+        status: u16,
+        body: String,
+    },
+}
+
+/// Endpoint to which the [WebhookTool] can post payloads.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    name: String,
+    url: String,
+    description: Option<String>,
+    secret: Option<Vec<u8>>,
+    headers: Vec<(String, String)>,
+}
+
+impl Webhook {
+    /// Create a webhook, called `name` by the model, posting to `url`.
+    pub fn new(name: &str, url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            description: None,
+            secret: None,
+            headers: vec![],
+        }
+    }
+
+    /// Set the description of the webhook given to the model, e.g.: what it is for and the
+    /// payload it expects.
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Sign the requests with HMAC-SHA256 using `secret` (see [SIGNATURE_HEADER]).
+    pub fn secret(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.secret = Some(secret.as_ref().to_vec());
+        self
+    }
+
+    /// Add a header to the requests (e.g.: an authorization token).
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 signature of `body` with `secret`, as sent in the
+/// [SIGNATURE_HEADER] header (without the `sha256=` prefix).
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Tool posting JSON payloads to configured [Webhook]s.
+#[derive(Clone)]
+pub struct WebhookTool {
+    client: reqwest::Client,
+    // Sorted by name so that the definition of the tool is deterministic
+    webhooks: BTreeMap<String, Webhook>,
+    max_retries: usize,
+    backoff: Duration,
+}
+
+impl Default for WebhookTool {
+    fn default() -> Self {
+        Self::with_client(reqwest::Client::new())
+    }
+}
+
+impl WebhookTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a tool sending the requests with `client`, e.g.: to set a timeout or a proxy.
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            webhooks: BTreeMap::new(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+        }
+    }
+
+    /// Add (or replace) a webhook.
+    pub fn webhook(mut self, webhook: Webhook) -> Self {
+        self.webhooks.insert(webhook.name.clone(), webhook);
+        self
+    }
+
+    /// Set the number of times a failed delivery is retried.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry, doubled after each attempt.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Post `payload` to the webhook called `name`, retrying failed deliveries.
+    pub async fn send(&self, name: &str, payload: &Value) -> Result<WebhookDelivery, WebhookError> {
+        let webhook = self
+            .webhooks
+            .get(name)
+            .ok_or_else(|| WebhookError::UnknownWebhook(name.to_string()))?;
+        let body = serde_json::to_vec(payload)?;
+        let signature = webhook
+            .secret
+            .as_ref()
+            .map(|secret| format!("sha256={}", sign(secret, &body)));
+
+        let mut attempts = 0;
+        let mut backoff = self.backoff;
+        loop {
+            attempts += 1;
+            let mut request = self
+                .client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            for (header, value) in &webhook.headers {
+                request = request.header(header, value);
+            }
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+
+            let result = request.send().await;
+            let retryable = match &result {
+                Ok(response) => {
+                    response.status().is_server_error()
+                        || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => !e.is_builder(),
+            };
+            if retryable && attempts <= self.max_retries {
+                tracing::debug!(target: "rig",
+                    "Webhook {} delivery failed (attempt {}), retrying in {:?}",
+                    name, attempts, backoff
+                );
+                runtime::sleep(backoff).await;
+                backoff *= 2;
+                continue;
+            }
+
+            let response = result?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(WebhookError::Status {
+                    webhook: name.to_string(),
+                    status: status.as_u16(),
+                    body: response.text().await.unwrap_or_default(),
+                });
+            }
+            return Ok(WebhookDelivery {
+                status: status.as_u16(),
+                attempts,
+            });
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookArgs {
+    /// Name of the webhook
+    pub webhook: String,
+    /// JSON payload to post
+    pub payload: Value,
+}
+
+/// Outcome of a successful delivery
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    /// Status code of the response of the webhook
+    pub status: u16,
+    /// Number of requests made to deliver the payload
+    pub attempts: usize,
+}
+
+impl Tool for WebhookTool {
+    const NAME: &'static str = "send_webhook";
+
+    type Error = WebhookError;
+    type Args = WebhookArgs;
+    type Output = WebhookDelivery;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let webhooks = self
+            .webhooks
+            .values()
+            .map(|webhook| match &webhook.description {
+                Some(description) => format!("- `{}`: {}", webhook.name, description),
+                None => format!("- `{}`", webhook.name),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Send a JSON payload to one of the following webhooks, to notify an external \
+                system:\n{webhooks}"
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "webhook": {
+                        "type": "string",
+                        "enum": self.webhooks.keys().collect::<Vec<_>>(),
+                        "description": "Name of the webhook"
+                    },
+                    "payload": {
+                        "type": "object",
+                        "description": "JSON payload to send"
+                    }
+                },
+                "required": ["webhook", "payload"]
+            }),
+        }
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.send(&args.webhook, &args.payload).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign(b"secret", br#"{"text":"Deploy done"}"#),
+            "9ad0bda63d78b305738e3991323406e0042758c8ce1d10df20f25927544baaf9"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_tool() {
+        let server = MockServer::start();
+        let signed = server.mock(|when, then| {
+            when.method(POST)
+                .path("/ops")
+                .header("authorization", "Bearer token")
+                .header(
+                    SIGNATURE_HEADER,
+                    "sha256=9ad0bda63d78b305738e3991323406e0042758c8ce1d10df20f25927544baaf9",
+                )
+                .body(r#"{"text":"Deploy done"}"#);
+            then.status(204);
+        });
+        let flaky = server.mock(|when, then| {
+            when.method(POST).path("/flaky");
+            then.status(503);
+        });
+        let rejecting = server.mock(|when, then| {
+            when.method(POST).path("/rejecting");
+            then.status(400).body("Invalid payload");
+        });
+
+        let tool = WebhookTool::new()
+            .webhook(
+                Webhook::new("ops", &server.url("/ops"))
+                    .description("Ops channel")
+                    .secret("secret")
+                    .header("authorization", "Bearer token"),
+            )
+            .webhook(Webhook::new("flaky", &server.url("/flaky")))
+            .webhook(Webhook::new("rejecting", &server.url("/rejecting")))
+            .max_retries(2)
+            .backoff(Duration::from_millis(1));
+
+        let definition = tool.definition(String::new()).await;
+        assert!(definition.description.contains("- `ops`: Ops channel"));
+        assert_eq!(
+            definition.parameters["properties"]["webhook"]["enum"],
+            json!(["flaky", "ops", "rejecting"])
+        );
+
+        let delivery = tool
+            .call(WebhookArgs {
+                webhook: "ops".into(),
+                payload: json!({"text": "Deploy done"}),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            delivery,
+            WebhookDelivery {
+                status: 204,
+                attempts: 1
+            }
+        );
+        signed.assert();
+
+        // Server errors are retried, client errors are not
+        assert!(matches!(
+            tool.send("flaky", &json!({})).await,
+            Err(WebhookError::Status { status: 503, .. })
+        ));
+        flaky.assert_hits(3);
+        assert!(matches!(
+            tool.send("rejecting", &json!({})).await,
+            Err(WebhookError::Status { status: 400, body, .. }) if body == "Invalid payload"
+        ));
+        rejecting.assert_hits(1);
+
+        assert!(matches!(
+            tool.send("unknown", &json!({})).await,
+            Err(WebhookError::UnknownWebhook(_))
+        ));
+    }
+}
@@ -0,0 +1,368 @@
+//! Export of the traces to [Langfuse](https://langfuse.com) (or to a compatible API).
+//!
+//! The [LangfuseExporter] sends each [RunTrace] to the ingestion API as a Langfuse trace, whose
+//! id is the id of the run and whose session is the session of the run. The steps of the run
+//! are the observations of the trace: generations for the completions (with their model, token
+//! usage and, in transcripts, their request), and spans for the retrievals and the tool calls
+//! (with an `ERROR` level when the call failed). [Score]s are attached to the traces by the id
+//! of their run.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, telemetry::RunContext, trace::{langfuse::LangfuseExporter, Score}};
+//!
+//! // Reads LANGFUSE_PUBLIC_KEY, LANGFUSE_SECRET_KEY and optionally LANGFUSE_HOST
+//! let langfuse = LangfuseExporter::from_env();
+//! let agent = openai.agent("gpt-4o").trace_exporter(langfuse.clone()).build();
+//!
+//! let run = RunContext::new("session-42");
+//! let answer = run.clone().scope(agent.prompt("Where is my order?")).await?;
+//!
+//! // e.g.: once the user rated the answer
+//! langfuse.score(run.run_id(), Score::new("user_feedback", 1.0)).await?;
+//! ```
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{now_ms, rfc3339, RunTrace, Score, TraceError, TraceExporter, TraceStepKind};
+use crate::telemetry::generate_id;
+
+const DEFAULT_HOST: &str = "https://cloud.langfuse.com";
+
+/// [TraceExporter] sending the traces to the ingestion API of Langfuse (see the
+/// [module](self) documentation).
+#[derive(Clone)]
+pub struct LangfuseExporter {
+    client: reqwest::Client,
+    host: String,
+    public_key: String,
+    secret_key: String,
+    name: String,
+}
+
+impl LangfuseExporter {
+    /// Exporter authenticated with the API keys of a Langfuse project, sending the traces to
+    /// Langfuse Cloud (see [LangfuseExporter::host] for other instances).
+    pub fn new(public_key: &str, secret_key: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            host: DEFAULT_HOST.to_string(),
+            public_key: public_key.to_string(),
+            secret_key: secret_key.to_string(),
+            name: "agent".to_string(),
+        }
+    }
+
+    /// Exporter authenticated with the `LANGFUSE_PUBLIC_KEY` and `LANGFUSE_SECRET_KEY`
+    /// environment variables, sending the traces to the `LANGFUSE_HOST` instance if set.
+    pub fn from_env() -> Self {
+        let public_key = std::env::var("LANGFUSE_PUBLIC_KEY").expect("LANGFUSE_PUBLIC_KEY not set");
+        let secret_key = std::env::var("LANGFUSE_SECRET_KEY").expect("LANGFUSE_SECRET_KEY not set");
+        let exporter = Self::new(&public_key, &secret_key);
+        match std::env::var("LANGFUSE_HOST") {
+            Ok(host) => exporter.host(&host),
+            Err(_) => exporter,
+        }
+    }
+
+    /// Send the traces to the Langfuse instance at `host` (e.g.: a self-hosted instance, or
+    /// `https://us.cloud.langfuse.com`).
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = host.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Name of the traces (`agent` by default), e.g.: the name of the application.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Send the requests with `client`, e.g.: to configure its timeouts or proxy.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Attach `score` to the trace of the run `run_id`.
+    pub async fn score(&self, run_id: &str, score: Score) -> Result<(), TraceError> {
+        self.ingest(vec![event(
+            "score-create",
+            json!({
+                "id": generate_id(),
+                "traceId": run_id,
+                "name": score.name,
+                "value": score.value,
+                "comment": score.comment,
+            }),
+        )])
+        .await
+    }
+
+    /// Ingestion events of a run: its trace, then its observations
+    fn events(&self, trace: &RunTrace) -> Vec<Value> {
+        let trace_event = event(
+            "trace-create",
+            json!({
+                "id": trace.run_id,
+                "name": self.name,
+                "sessionId": trace.session_id,
+                "timestamp": rfc3339(trace.started_at),
+                "input": {"prompt": trace.prompt, "chat_history": trace.chat_history},
+                "output": trace.response,
+                "metadata": {
+                    "model": trace.model,
+                    "error": trace.error,
+                    "duration_ms": trace.duration_ms,
+                    "schema_version": trace.schema_version,
+                },
+            }),
+        );
+
+        let observations = trace.steps.iter().enumerate().map(|(i, step)| {
+            let mut body = json!({
+                // Stable ids, so that re-exporting a trace updates its observations
+                "id": format!("{}-{i}", trace.run_id),
+                "traceId": trace.run_id,
+                "startTime": rfc3339(step.started_at),
+                "endTime": rfc3339(step.started_at + step.duration_ms),
+            });
+            let (kind, fields) = match &step.kind {
+                TraceStepKind::Completion {
+                    model,
+                    request,
+                    choice,
+                    usage,
+                } => (
+                    "generation-create",
+                    json!({
+                        "name": "completion",
+                        "model": model,
+                        "input": request,
+                        "output": choice,
+                        "usage": usage.map(|usage| json!({
+                            "input": usage.input_tokens,
+                            "output": usage.output_tokens,
+                            "total": usage.total_tokens,
+                            "unit": "TOKENS",
+                        })),
+                    }),
+                ),
+                TraceStepKind::Retrieval { query, documents } => (
+                    "span-create",
+                    json!({
+                        "name": "retrieval",
+                        "input": {"query": query},
+                        "output": {"documents": documents},
+                    }),
+                ),
+                TraceStepKind::ToolCall {
+                    call_id,
+                    name,
+                    arguments,
+                    output,
+                    error,
+                } => (
+                    "span-create",
+                    json!({
+                        "name": name,
+                        "input": arguments,
+                        "output": output,
+                        "level": if error.is_some() { "ERROR" } else { "DEFAULT" },
+                        "statusMessage": error,
+                        "metadata": {"call_id": call_id},
+                    }),
+                ),
+            };
+            crate::json_utils::merge_inplace(&mut body, fields);
+            event(kind, body)
+        });
+
+        std::iter::once(trace_event).chain(observations).collect()
+    }
+
+    /// Send a batch of events to the ingestion API, failing if any of them is rejected
+    async fn ingest(&self, batch: Vec<Value>) -> Result<(), TraceError> {
+        #[derive(Deserialize)]
+        struct IngestionError {
+            id: String,
+            #[serde(default)]
+            message: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct IngestionResponse {
+            #[serde(default)]
+            errors: Vec<IngestionError>,
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/api/public/ingestion", self.host))
+            .basic_auth(&self.public_key, Some(&self.secret_key))
+            .json(&json!({ "batch": batch }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<IngestionResponse>()
+            .await?;
+
+        match response.errors.first() {
+            None => Ok(()),
+            Some(error) => Err(TraceError::ExportError(
+                format!(
+                    "Langfuse rejected {} event(s), e.g.: {}: {}",
+                    response.errors.len(),
+                    error.id,
+                    error.message.as_deref().unwrap_or("unknown error")
+                )
+                .into(),
+            )),
+        }
+    }
+}
+
+impl TraceExporter for LangfuseExporter {
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn export(&self, trace: RunTrace) -> Result<(), TraceError> {
+        self.ingest(self.events(&trace)).await
+    }
+}
+
+/// Ingestion event of type `kind`
+fn event(kind: &str, body: Value) -> Value {
+    json!({
+        "id": generate_id(),
+        "timestamp": rfc3339(now_ms()),
+        "type": kind,
+        "body": body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+
+    use super::*;
+    use crate::{
+        completion::{Message, Usage},
+        message::AssistantContent,
+        telemetry::RunContext,
+        trace::TraceStep,
+        OneOrMany,
+    };
+
+    #[tokio::test]
+    async fn test_langfuse_exporter() {
+        let server = MockServer::start();
+        let ingestion = server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/public/ingestion")
+                // Basic base64("pk:sk")
+                .header("authorization", "Basic cGs6c2s=")
+                .json_body_partial(
+                    r#"{"batch": [
+                        {"type": "trace-create", "body": {
+                            "id": "run", "name": "support", "sessionId": "session",
+                            "timestamp": "2025-01-15T10:00:00.000Z", "output": "Green"
+                        }},
+                        {"type": "generation-create", "body": {
+                            "id": "run-0", "traceId": "run", "model": "gpt-4o",
+                            "startTime": "2025-01-15T10:00:00.010Z",
+                            "endTime": "2025-01-15T10:00:01.010Z",
+                            "usage": {"input": 10, "output": 2, "total": 12, "unit": "TOKENS"}
+                        }}
+                    ]}"#,
+                );
+            then.status(207)
+                .json_body(json!({"successes": [], "errors": []}));
+        });
+        let score = server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/public/ingestion")
+                .json_body_partial(
+                    r#"{"batch": [{"type": "score-create", "body": {
+                    "traceId": "run", "name": "helpfulness", "value": 0.5
+                }}]}"#,
+                );
+            then.status(207).json_body(json!({
+                "successes": [],
+                "errors": [{"id": "1", "status": 400, "message": "Invalid score"}]
+            }));
+        });
+
+        let mut trace = RunTrace::new(
+            &RunContext::with_run_id("session", "run"),
+            Some("gpt-4o"),
+            Message::user("Flurbos?"),
+        );
+        trace.started_at = 1_736_935_200_000;
+        trace.response = Some("Green".into());
+        trace.steps.push(TraceStep {
+            started_at: 1_736_935_200_010,
+            duration_ms: 1000,
+            kind: TraceStepKind::Completion {
+                model: Some("gpt-4o".into()),
+                request: None,
+                choice: OneOrMany::one(AssistantContent::text("Green")),
+                usage: Some(Usage::new(10, 2)),
+            },
+        });
+
+        let exporter = LangfuseExporter::new("pk", "sk")
+            .host(&server.base_url())
+            .name("support");
+        TraceExporter::export(&exporter, trace).await.unwrap();
+        ingestion.assert();
+
+        // Rejected events fail the export
+        let result = exporter.score("run", Score::new("helpfulness", 0.5)).await;
+        score.assert();
+        assert!(
+            matches!(result, Err(TraceError::ExportError(e)) if e.to_string().contains("Invalid score"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_langfuse_exporter_errors() {
+        let server = MockServer::start();
+        let trace = RunTrace::new(
+            &RunContext::with_run_id("session", "run"),
+            None,
+            Message::user("Flurbos?"),
+        );
+
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/public/ingestion")
+                .header("authorization", "Basic aW52YWxpZDpzaw==");
+            then.status(401);
+        });
+        let exporter = LangfuseExporter::new("invalid", "sk").host(&server.base_url());
+        assert!(matches!(
+            TraceExporter::export(&exporter, trace.clone()).await,
+            Err(TraceError::HttpError(e)) if e.status() == Some(reqwest::StatusCode::UNAUTHORIZED)
+        ));
+
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/public/ingestion")
+                .header("authorization", "Basic cGs6c2s=");
+            then.status(207)
+                .json_body(json!({"errors": [{"id": "1"}, {"id": "2"}]}));
+        });
+        let exporter = LangfuseExporter::new("pk", "sk").host(&server.base_url());
+        assert!(matches!(
+            TraceExporter::export(&exporter, trace.clone()).await,
+            Err(TraceError::ExportError(e))
+                if e.to_string() == "Langfuse rejected 2 event(s), e.g.: 1: unknown error"
+        ));
+
+        // Unreachable host
+        let exporter = LangfuseExporter::new("pk", "sk").host("http://127.0.0.1:1");
+        assert!(matches!(
+            TraceExporter::export(&exporter, trace).await,
+            Err(TraceError::HttpError(_))
+        ));
+    }
+}
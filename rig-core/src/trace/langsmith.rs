@@ -0,0 +1,348 @@
+//! Export of the traces to [LangSmith](https://smith.langchain.com) (or to a compatible API).
+//!
+//! The [LangSmithExporter] sends each [RunTrace] to the batch API of LangSmith as a tree of
+//! runs: a `chain` run for the agent run, whose child runs are the steps of the run: `llm` runs
+//! for the completions (with their model, token usage and, in transcripts, their request),
+//! `retriever` runs for the retrievals and `tool` runs for the tool calls. LangSmith requires
+//! UUIDs, derived from the ids of the rig runs ([run_uuid]) so that [Score]s can be attached, as
+//! feedback, to the run they evaluate.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, telemetry::RunContext, trace::{langsmith::LangSmithExporter, Score}};
+//!
+//! // Reads LANGSMITH_API_KEY and optionally LANGSMITH_ENDPOINT and LANGSMITH_PROJECT
+//! let langsmith = LangSmithExporter::from_env();
+//! let agent = openai.agent("gpt-4o").trace_exporter(langsmith.clone()).build();
+//!
+//! let run = RunContext::new("session-42");
+//! let answer = run.clone().scope(agent.prompt("Where is my order?")).await?;
+//!
+//! // e.g.: once the user rated the answer
+//! let feedback = Score::new("user_feedback", 0.0).comment("Wrong order");
+//! langsmith.score(run.run_id(), feedback).await?;
+//! ```
+use serde_json::{json, Value};
+
+use super::{rfc3339, utc_datetime, RunTrace, Score, TraceError, TraceExporter, TraceStepKind};
+use crate::completion::cache::fnv1a;
+
+const DEFAULT_ENDPOINT: &str = "https://api.smith.langchain.com";
+
+/// [TraceExporter] sending the traces to the batch API of LangSmith (see the [module](self)
+/// documentation).
+#[derive(Clone)]
+pub struct LangSmithExporter {
+    client: reqwest::Client,
+    endpoint: String,
+    api_key: String,
+    project: Option<String>,
+    name: String,
+}
+
+impl LangSmithExporter {
+    /// Exporter authenticated with a LangSmith API key, sending the traces to the default
+    /// project of LangSmith Cloud (see [LangSmithExporter::endpoint] and
+    /// [LangSmithExporter::project]).
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            api_key: api_key.to_string(),
+            project: None,
+            name: "agent".to_string(),
+        }
+    }
+
+    /// Exporter authenticated with the `LANGSMITH_API_KEY` environment variable, sending the
+    /// traces to the `LANGSMITH_ENDPOINT` API and to the `LANGSMITH_PROJECT` project if set.
+    pub fn from_env() -> Self {
+        let api_key = std::env::var("LANGSMITH_API_KEY").expect("LANGSMITH_API_KEY not set");
+        let mut exporter = Self::new(&api_key);
+        if let Ok(endpoint) = std::env::var("LANGSMITH_ENDPOINT") {
+            exporter = exporter.endpoint(&endpoint);
+        }
+        if let Ok(project) = std::env::var("LANGSMITH_PROJECT") {
+            exporter = exporter.project(&project);
+        }
+        exporter
+    }
+
+    /// Send the traces to the LangSmith API at `endpoint` (e.g.: a self-hosted instance, or
+    /// `https://eu.api.smith.langchain.com`).
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send the traces to the `project` project, rather than to the default one.
+    pub fn project(mut self, project: &str) -> Self {
+        self.project = Some(project.to_string());
+        self
+    }
+
+    /// Name of the root runs (`agent` by default), e.g.: the name of the application.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Send the requests with `client`, e.g.: to configure its timeouts or proxy.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Attach `score` as feedback to the run `run_id`.
+    pub async fn score(&self, run_id: &str, score: Score) -> Result<(), TraceError> {
+        self.client
+            .post(format!("{}/feedback", self.endpoint))
+            .header("x-api-key", &self.api_key)
+            .json(&json!({
+                "run_id": run_uuid(run_id),
+                "key": score.name,
+                "score": score.value,
+                "comment": score.comment,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Runs of a trace: the root run, then the runs of its steps
+    fn runs(&self, trace: &RunTrace) -> Vec<Value> {
+        let root_id = run_uuid(&trace.run_id);
+        let root_order = dotted_order(trace.started_at, &root_id);
+        let root = json!({
+            "id": root_id,
+            "trace_id": root_id,
+            "dotted_order": root_order,
+            "name": self.name,
+            "run_type": "chain",
+            "start_time": rfc3339(trace.started_at),
+            "end_time": rfc3339(trace.started_at + trace.duration_ms),
+            "inputs": {"prompt": trace.prompt, "chat_history": trace.chat_history},
+            "outputs": trace.response.as_ref().map(|response| json!({"response": response})),
+            "error": trace.error,
+            "session_name": self.project,
+            "extra": {"metadata": {
+                "model": trace.model,
+                "session_id": trace.session_id,
+                "run_id": trace.run_id,
+                "schema_version": trace.schema_version,
+            }},
+        });
+
+        let steps = trace.steps.iter().enumerate().map(|(i, step)| {
+            let id = run_uuid(&format!("{}/{i}", trace.run_id));
+            let mut run = json!({
+                "dotted_order": format!("{root_order}.{}", dotted_order(step.started_at, &id)),
+                "id": id,
+                "trace_id": root_id,
+                "parent_run_id": root_id,
+                "start_time": rfc3339(step.started_at),
+                "end_time": rfc3339(step.started_at + step.duration_ms),
+                "session_name": self.project,
+            });
+            let fields = match &step.kind {
+                TraceStepKind::Completion {
+                    model,
+                    request,
+                    choice,
+                    usage,
+                } => json!({
+                    "name": "completion",
+                    "run_type": "llm",
+                    "inputs": request.as_ref().map_or(json!({}), |request| json!(request)),
+                    "outputs": {
+                        "choice": choice,
+                        "usage_metadata": usage,
+                    },
+                    "extra": {"metadata": {"ls_model_name": model}},
+                }),
+                TraceStepKind::Retrieval { query, documents } => json!({
+                    "name": "retrieval",
+                    "run_type": "retriever",
+                    "inputs": {"query": query},
+                    "outputs": {"documents": documents.iter().map(|doc| json!({
+                        "page_content": doc.text.as_deref().unwrap_or_default(),
+                        "metadata": {"id": doc.id, "score": doc.score},
+                    })).collect::<Vec<_>>()},
+                }),
+                TraceStepKind::ToolCall {
+                    call_id,
+                    name,
+                    arguments,
+                    output,
+                    error,
+                } => json!({
+                    "name": name,
+                    "run_type": "tool",
+                    "inputs": {"input": arguments},
+                    "outputs": output.as_ref().map(|output| json!({"output": output})),
+                    "error": error,
+                    "extra": {"metadata": {"call_id": call_id}},
+                }),
+            };
+            crate::json_utils::merge_inplace(&mut run, fields);
+            run
+        });
+
+        std::iter::once(root).chain(steps).collect()
+    }
+}
+
+impl TraceExporter for LangSmithExporter {
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn export(&self, trace: RunTrace) -> Result<(), TraceError> {
+        self.client
+            .post(format!("{}/runs/batch", self.endpoint))
+            .header("x-api-key", &self.api_key)
+            .json(&json!({ "post": self.runs(&trace), "patch": [] }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// UUID of the LangSmith run of the rig run `run_id` (see [RunContext](crate::telemetry::RunContext)),
+/// derived from the id of the run.
+pub fn run_uuid(run_id: &str) -> String {
+    let high = fnv1a(run_id.as_bytes());
+    let low = fnv1a(format!("{run_id}\0").as_bytes());
+    // Version 8 (custom) UUID, of the RFC 4122 variant
+    let high = (high & !0xf000) | 0x8000;
+    let low = (low & !(0xc << 60)) | (0x8 << 60);
+    let hex = format!("{high:016x}{low:016x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Part of the `dotted_order` of a run: its start time (with microseconds) and its id, which
+/// LangSmith uses to order the runs of a trace
+fn dotted_order(started_at: u64, id: &str) -> String {
+    let [year, month, day, hour, minute, second, ms] = utc_datetime(started_at);
+    format!(
+        "{year:04}{month:02}{day:02}T{hour:02}{minute:02}{second:02}{:06}Z{id}",
+        ms * 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{Method::POST, MockServer};
+
+    use super::*;
+    use crate::{completion::Message, telemetry::RunContext, trace::TraceStep};
+
+    #[tokio::test]
+    async fn test_langsmith_exporter() {
+        let root = run_uuid("run");
+        let child = run_uuid("run/0");
+        assert_eq!(root.len(), 36);
+        assert_eq!(&root[14..15], "8");
+        assert_ne!(root, child);
+
+        let server = MockServer::start();
+        let body = json!({"post": [
+            {
+                "id": root,
+                "trace_id": root,
+                "dotted_order": format!("20250115T100000000000Z{root}"),
+                "name": "support",
+                "run_type": "chain",
+                "session_name": "rig",
+                "outputs": {"response": "3"},
+            },
+            {
+                "id": child,
+                "parent_run_id": root,
+                "dotted_order": format!("20250115T100000000000Z{root}.20250115T100000010000Z{child}"),
+                "name": "add",
+                "run_type": "tool",
+                "inputs": {"input": {"x": 1, "y": 2}},
+                "outputs": {"output": "3"},
+            }
+        ]});
+        let batch = server.mock(|when, then| {
+            when.method(POST)
+                .path("/runs/batch")
+                .header("x-api-key", "key")
+                .json_body_partial(body.to_string());
+            then.status(202);
+        });
+        let feedback = server.mock(|when, then| {
+            when.method(POST).path("/feedback").json_body_partial(
+                json!({"run_id": root, "key": "correctness", "score": 1.0, "comment": "Exact"})
+                    .to_string(),
+            );
+            then.status(200);
+        });
+
+        let mut trace = RunTrace::new(
+            &RunContext::with_run_id("session", "run"),
+            Some("gpt-4o"),
+            Message::user("Add 1 and 2"),
+        );
+        trace.started_at = 1_736_935_200_000;
+        trace.response = Some("3".into());
+        trace.steps.push(TraceStep {
+            started_at: 1_736_935_200_010,
+            duration_ms: 5,
+            kind: TraceStepKind::ToolCall {
+                call_id: "call_1".into(),
+                name: "add".into(),
+                arguments: json!({"x": 1, "y": 2}),
+                output: Some("3".into()),
+                error: None,
+            },
+        });
+
+        let exporter = LangSmithExporter::new("key")
+            .endpoint(&server.base_url())
+            .project("rig")
+            .name("support");
+        TraceExporter::export(&exporter, trace).await.unwrap();
+        batch.assert();
+
+        let score = Score::new("correctness", 1.0).comment("Exact");
+        exporter.score("run", score).await.unwrap();
+        feedback.assert();
+    }
+
+    #[tokio::test]
+    async fn test_langsmith_exporter_errors() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/runs/batch");
+            then.status(403);
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/feedback");
+            then.status(404);
+        });
+
+        let exporter = LangSmithExporter::new("invalid").endpoint(&server.base_url());
+        let trace = RunTrace::new(
+            &RunContext::with_run_id("session", "run"),
+            None,
+            Message::user("Add 1 and 2"),
+        );
+        assert!(matches!(
+            TraceExporter::export(&exporter, trace).await,
+            Err(TraceError::HttpError(e)) if e.status() == Some(reqwest::StatusCode::FORBIDDEN)
+        ));
+        assert!(matches!(
+            exporter.score("run", Score::new("correctness", 0.0)).await,
+            Err(TraceError::HttpError(e)) if e.status() == Some(reqwest::StatusCode::NOT_FOUND)
+        ));
+    }
+}
@@ -0,0 +1,672 @@
+//! Export of agent runs as machine-readable traces, e.g.: for evaluation platforms and offline
+//! analysis tools, without OpenTelemetry infrastructure (see [telemetry](crate::telemetry)).
+//!
+//! An agent built with [AgentBuilder::trace_exporter](crate::agent::AgentBuilder::trace_exporter)
+//! records each run of its [Prompt](crate::completion::Prompt) and
+//! [Chat](crate::completion::Chat) methods as a [RunTrace]: the prompt and its chat history, the
+//! response (or the error), the total token usage, and the steps of the run with their timings:
+//! the retrievals of the dynamic context, the completions and the tool calls. Once the run is
+//! over, the trace is given to a [TraceExporter]:
+//! - [JsonlExporter]: writes the traces as JSON Lines, e.g.: to a file
+//! - unbounded [channels](futures::channel::mpsc::UnboundedSender) of traces, e.g.: to process
+//!   them in a background task of the application
+//! - [LangfuseExporter](langfuse::LangfuseExporter) and
+//!   [LangSmithExporter](langsmith::LangSmithExporter) (requiring the `http` feature): push the
+//!   traces to the Langfuse and LangSmith observability platforms (or to compatible APIs), along
+//!   with the [Score]s evaluating the runs (e.g.: user feedback, or the results of evaluators)
+//!
+//! A failure to export a trace is logged and doesn't fail the prompt. The traces of a JSONL
+//! export are read back with [read_jsonl], e.g.: to [replay] a run offline against mock
+//! providers answering with its recorded data.
+//!
+//! # Schema
+//! Each line of a JSONL export is a [RunTrace] serialized as a JSON object. The schema is
+//! versioned by the `schema_version` field ([SCHEMA_VERSION]): new optional fields may be added
+//! to a version, while renaming or removing fields bumps it. Timestamps are in milliseconds since
+//! the Unix epoch and durations in milliseconds. Messages are serialized as in the chat
+//! histories of [memory](crate::memory), and the steps are tagged by their `type`:
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "session_id": "5f1c...", "run_id": "9ab2...", "model": "gpt-4o",
+//!   "started_at": 1736935200000, "duration_ms": 1840,
+//!   "prompt": {"role": "user", "content": [{"type": "text", "text": "Flurbos?"}]},
+//!   "chat_history": [],
+//!   "response": "Flurbos are green.",
+//!   "usage": {"input_tokens": 120, "output_tokens": 12, "total_tokens": 132},
+//!   "steps": [
+//!     {"type": "retrieval", "started_at": 1736935200002, "duration_ms": 85,
+//!      "query": "Flurbos?", "documents": [{"id": "doc0", "score": 0.83}]},
+//!     {"type": "completion", "started_at": 1736935200090, "duration_ms": 1750,
+//!      "model": "gpt-4o", "choice": [{"type": "text", "text": "Flurbos are green."}],
+//!      "usage": {"input_tokens": 120, "output_tokens": 12, "total_tokens": 132}}
+//!   ]
+//! }
+//! ```
+//! Tool calls are `tool_call` steps with the `call_id`, `name`, `arguments` and the `output` or
+//! the `error` of the call. Failed runs have an `error` instead of a `response`.
+//!
+//! # Transcripts
+//! To debug why an agent produced a bad answer, an agent built with
+//! [AgentBuilder::transcript](crate::agent::AgentBuilder::transcript) records the full
+//! transcripts of its runs: the traces also hold the `text` of the retrieved documents, and the
+//! full `request` sent to the model by each completion (its preamble, messages, documents and
+//! tool definitions, serialized as a [CompletionRequest]).
+//!
+//! Traces, and transcripts in particular, hold the data of the users: a [RedactingExporter]
+//! redacts their texts with a [Redactor] (e.g.: a function, or the
+//! [Redact](crate::guardrails::Redact) guard with the `regex` feature) before exporting them.
+//! ```rust
+//! use rig::{guardrails::Redact, trace::{JsonlExporter, RedactingExporter}};
+//!
+//! let exporter = RedactingExporter::new(JsonlExporter::file("transcripts.jsonl")?, Redact::pii());
+//! let agent = openai.agent("gpt-4o").transcript(exporter).build();
+//! ```
+//!
+//! # Example
+//! ```rust
+//! use std::sync::Arc;
+//!
+//! use rig::{completion::Prompt, trace::JsonlExporter};
+//!
+//! // Shared by the agents, appending the traces to `traces.jsonl`
+//! let exporter = Arc::new(JsonlExporter::file("traces.jsonl")?);
+//! let agent = openai.agent("gpt-4o")
+//!     .dynamic_context(3, index)
+//!     .trace_exporter(exporter.clone())
+//!     .build();
+//!
+//! agent.prompt("Where is my order?").await?;
+//! ```
+use std::{
+    cell::RefCell,
+    fs::OpenOptions,
+    future::Future,
+    io::{BufRead, Write},
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::channel::mpsc::UnboundedSender;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "http")]
+pub mod langfuse;
+#[cfg(feature = "http")]
+pub mod langsmith;
+pub mod replay;
+
+use crate::{
+    anonymization::{json_strings, message_texts},
+    completion::{CompletionRequest, Message, Usage},
+    message::AssistantContent,
+    runtime::{SystemTime, UNIX_EPOCH},
+    telemetry::RunContext,
+    OneOrMany,
+};
+
+/// Version of the schema of the [RunTrace]s
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TraceError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[cfg(feature = "http")]
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Error writing the traces (e.g.: to a file)
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error exporting the trace (e.g.: closed channel, rejected request)
+    #[error("ExportError: {0}")]
+    ExportError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+/// Trace of a run of an agent (see the [module](self) documentation for its schema).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunTrace {
+    pub schema_version: u32,
+    /// Ids of the session and of the run (see [telemetry](crate::telemetry#session-and-run-ids))
+    pub session_id: String,
+    pub run_id: String,
+    /// Model of the agent, as reported by its provider integration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Start of the run, in milliseconds since the Unix epoch
+    pub started_at: u64,
+    pub duration_ms: u64,
+    /// Prompt of the run, as checked by the input guards of the agent
+    pub prompt: Message,
+    /// Messages preceding the prompt (including the ones of the memory of the agent)
+    #[serde(default)]
+    pub chat_history: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Total token usage of the completions of the run
+    pub usage: Usage,
+    /// Steps of the run, in the order they ended
+    #[serde(default)]
+    pub steps: Vec<TraceStep>,
+}
+
+impl RunTrace {
+    pub(crate) fn new(run: &RunContext, model: Option<&str>, prompt: Message) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            session_id: run.session_id().to_string(),
+            run_id: run.run_id().to_string(),
+            model: model.map(str::to_string),
+            started_at: now_ms(),
+            duration_ms: 0,
+            prompt,
+            chat_history: vec![],
+            response: None,
+            error: None,
+            usage: Usage::default(),
+            steps: vec![],
+        }
+    }
+
+    /// Record the outcome of the run
+    pub(crate) fn finish<E: std::fmt::Display>(&mut self, result: &Result<String, E>) {
+        self.duration_ms = now_ms().saturating_sub(self.started_at);
+        match result {
+            Ok(response) => self.response = Some(response.clone()),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        self.usage = self
+            .steps
+            .iter()
+            .filter_map(|step| match &step.kind {
+                TraceStepKind::Completion { usage, .. } => *usage,
+                _ => None,
+            })
+            .fold(Usage::default(), |total, usage| total + usage);
+    }
+
+    /// Redact the texts of the trace with `redactor`: the texts of its messages (including the
+    /// string values of the arguments of tool calls), response and error, its retrieval queries
+    /// and documents, and the preambles, messages and documents of its completion requests.
+    pub fn redact(&mut self, redactor: &dyn Redactor) {
+        let mut texts = vec![];
+        message_texts(&mut self.prompt, &mut texts);
+        for message in &mut self.chat_history {
+            message_texts(message, &mut texts);
+        }
+        texts.extend(self.response.as_mut());
+        texts.extend(self.error.as_mut());
+        for step in &mut self.steps {
+            match &mut step.kind {
+                TraceStepKind::Retrieval { query, documents } => {
+                    texts.push(query);
+                    texts.extend(documents.iter_mut().filter_map(|doc| doc.text.as_mut()));
+                }
+                TraceStepKind::Completion {
+                    choice, request, ..
+                } => {
+                    for content in choice.iter_mut() {
+                        match content {
+                            AssistantContent::Text(text) => texts.push(&mut text.text),
+                            AssistantContent::ToolCall(call) => {
+                                json_strings(&mut call.function.arguments, &mut texts)
+                            }
+                        }
+                    }
+                    if let Some(request) = request {
+                        let request = request.as_mut();
+                        texts.extend(request.preamble.as_mut());
+                        for message in request
+                            .chat_history
+                            .iter_mut()
+                            .chain(std::iter::once(&mut request.prompt))
+                        {
+                            message_texts(message, &mut texts);
+                        }
+                        texts.extend(request.documents.iter_mut().map(|doc| &mut doc.text));
+                    }
+                }
+                TraceStepKind::ToolCall {
+                    arguments,
+                    output,
+                    error,
+                    ..
+                } => {
+                    json_strings(arguments, &mut texts);
+                    texts.extend(output.as_mut());
+                    texts.extend(error.as_mut());
+                }
+            }
+        }
+        for text in texts {
+            *text = redactor.redact(text);
+        }
+    }
+}
+
+/// Step of a run, with its timing
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    /// Start of the step, in milliseconds since the Unix epoch
+    pub started_at: u64,
+    pub duration_ms: u64,
+    #[serde(flatten)]
+    pub kind: TraceStepKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TraceStepKind {
+    /// Retrieval of the dynamic context of the agent for `query`
+    Retrieval {
+        query: String,
+        documents: Vec<RetrievedDocument>,
+    },
+    /// Completion of the model, without usage when answered from the cache of the agent, and
+    /// with the request sent to the model in transcripts
+    Completion {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request: Option<Box<CompletionRequest>>,
+        choice: OneOrMany<AssistantContent>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+    },
+    /// Call of a tool requested by the model, which returned `output` or failed with `error`
+    ToolCall {
+        call_id: String,
+        name: String,
+        arguments: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+/// Document retrieved from the dynamic context, with its similarity score (and its text, as
+/// sent to the model, in transcripts)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RetrievedDocument {
+    pub id: String,
+    pub score: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Evaluation of a run (e.g.: user feedback, or the result of an evaluator), pushed to an
+/// observability platform along with the trace of the run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    /// Name of the evaluation (e.g.: `helpfulness`)
+    pub name: String,
+    /// Value of the evaluation, e.g.: between 0 and 1
+    pub value: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+impl Score {
+    pub fn new(name: &str, value: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            value,
+            comment: None,
+        }
+    }
+
+    /// Explain the score, e.g.: with the feedback of the user.
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+}
+
+/// Redaction hook of the texts of traces, see [RedactingExporter].
+pub trait Redactor: Send + Sync {
+    fn redact(&self, text: &str) -> String;
+}
+
+impl<F: Fn(&str) -> String + Send + Sync> Redactor for F {
+    fn redact(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Redactor for crate::guardrails::Redact {
+    fn redact(&self, text: &str) -> String {
+        crate::guardrails::Redact::redact(self, text)
+    }
+}
+
+/// Destination of the traces of an agent.
+pub trait TraceExporter: Send + Sync {
+    /// Export the trace of a run, once the run is over.
+    fn export(&self, trace: RunTrace) -> impl Future<Output = Result<(), TraceError>> + Send;
+}
+
+/// Wrapper trait to allow for dynamic dispatch of trace exporters
+pub trait TraceExporterDyn: Send + Sync {
+    fn export(
+        &self,
+        trace: RunTrace,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TraceError>> + Send + '_>>;
+}
+
+impl<T: TraceExporter> TraceExporterDyn for T {
+    fn export(
+        &self,
+        trace: RunTrace,
+    ) -> Pin<Box<dyn Future<Output = Result<(), TraceError>> + Send + '_>> {
+        Box::pin(<Self as TraceExporter>::export(self, trace))
+    }
+}
+
+impl<T: TraceExporter> TraceExporter for Arc<T> {
+    async fn export(&self, trace: RunTrace) -> Result<(), TraceError> {
+        self.as_ref().export(trace).await
+    }
+}
+
+impl TraceExporter for UnboundedSender<RunTrace> {
+    async fn export(&self, trace: RunTrace) -> Result<(), TraceError> {
+        self.unbounded_send(trace)
+            .map_err(|e| TraceError::ExportError(e.into_send_error().into()))
+    }
+}
+
+/// [TraceExporter] redacting the traces (see [RunTrace::redact]) before exporting them with
+/// another exporter.
+pub struct RedactingExporter<E: TraceExporter> {
+    exporter: E,
+    redactor: Box<dyn Redactor>,
+}
+
+impl<E: TraceExporter> RedactingExporter<E> {
+    pub fn new(exporter: E, redactor: impl Redactor + 'static) -> Self {
+        Self {
+            exporter,
+            redactor: Box::new(redactor),
+        }
+    }
+}
+
+impl<E: TraceExporter> TraceExporter for RedactingExporter<E> {
+    async fn export(&self, mut trace: RunTrace) -> Result<(), TraceError> {
+        trace.redact(self.redactor.as_ref());
+        self.exporter.export(trace).await
+    }
+}
+
+/// [TraceExporter] writing the traces as JSON Lines: one JSON object per line and per run.
+pub struct JsonlExporter {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonlExporter {
+    /// Exporter writing the traces to `writer`, flushing it after each trace.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    /// Exporter appending the traces to the file at `path`, creating it if needed.
+    pub fn file(path: impl AsRef<Path>) -> Result<Self, TraceError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(file))
+    }
+}
+
+impl TraceExporter for JsonlExporter {
+    async fn export(&self, trace: RunTrace) -> Result<(), TraceError> {
+        let mut line = serde_json::to_vec(&trace)?;
+        line.push(b'\n');
+        let mut writer = self.writer.lock().expect("trace writer lock poisoned");
+        writer.write_all(&line)?;
+        Ok(writer.flush()?)
+    }
+}
+
+/// Read the traces of a JSON Lines export (see [JsonlExporter]), skipping its empty lines.
+pub fn read_jsonl(reader: impl BufRead) -> Result<Vec<RunTrace>, TraceError> {
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// Recorder of the trace of a run
+pub(crate) struct Recorder {
+    trace: Mutex<RunTrace>,
+    /// Whether the full transcript of the run is recorded
+    transcript: bool,
+}
+
+impl Recorder {
+    pub(crate) fn new(trace: RunTrace, transcript: bool) -> Self {
+        Self {
+            trace: Mutex::new(trace),
+            transcript,
+        }
+    }
+
+    /// The trace recorded so far
+    pub(crate) fn trace(&self) -> RunTrace {
+        self.trace.lock().expect("run trace lock poisoned").clone()
+    }
+}
+
+thread_local! {
+    static CURRENT_RECORDER: RefCell<Option<Arc<Recorder>>> = const { RefCell::new(None) };
+}
+
+/// Run `future` recording with `recorder` (or recording nothing if `None`, e.g.: so that the
+/// runs of an agent called by another one are not recorded into the trace of the calling run)
+pub(crate) fn scope<F: Future>(recorder: Option<Arc<Recorder>>, future: F) -> TraceScoped<F> {
+    TraceScoped {
+        recorder,
+        future: Box::pin(future),
+    }
+}
+
+/// Update the trace of the current run, if it is recorded
+pub(crate) fn record(update: impl FnOnce(&mut RunTrace)) {
+    CURRENT_RECORDER.with(|recorder| {
+        if let Some(recorder) = recorder.borrow().as_ref() {
+            update(&mut recorder.trace.lock().expect("run trace lock poisoned"));
+        }
+    })
+}
+
+/// Whether the full transcript of the current run is recorded
+pub(crate) fn is_transcript() -> bool {
+    CURRENT_RECORDER.with(|recorder| {
+        recorder
+            .borrow()
+            .as_ref()
+            .is_some_and(|recorder| recorder.transcript)
+    })
+}
+
+/// Record a step of the current run which started at `started_at` and just ended. The step is
+/// only built if the run is recorded.
+pub(crate) fn record_step(started_at: u64, kind: impl FnOnce() -> TraceStepKind) {
+    record(|trace| {
+        trace.steps.push(TraceStep {
+            started_at,
+            duration_ms: now_ms().saturating_sub(started_at),
+            kind: kind(),
+        })
+    })
+}
+
+/// Current time, in milliseconds since the Unix epoch
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// UTC date and time of a timestamp in milliseconds since the Unix epoch, as
+/// `[year, month, day, hour, minute, second, millisecond]`
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+pub(crate) fn utc_datetime(timestamp_ms: u64) -> [u64; 7] {
+    let (days, ms) = (timestamp_ms / 86_400_000, timestamp_ms % 86_400_000);
+    // Civil date of a number of days since the epoch (see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    [
+        year,
+        month,
+        day,
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000,
+    ]
+}
+
+/// RFC 3339 representation of a timestamp in milliseconds since the Unix epoch, e.g.:
+/// `2025-01-15T10:00:00.123Z`
+#[cfg_attr(not(feature = "http"), allow(dead_code))]
+pub(crate) fn rfc3339(timestamp_ms: u64) -> String {
+    let [year, month, day, hour, minute, second, ms] = utc_datetime(timestamp_ms);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{ms:03}Z")
+}
+
+/// Future recording a trace, returned by [scope]
+pub(crate) struct TraceScoped<F> {
+    recorder: Option<Arc<Recorder>>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for TraceScoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        /// Restores the enclosing recorder once the future is polled, even if it panics
+        struct Restore(Option<Arc<Recorder>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_RECORDER.with(|recorder| *recorder.borrow_mut() = self.0.take());
+            }
+        }
+
+        let recorder = self.recorder.clone();
+        let _restore = Restore(CURRENT_RECORDER.with(|current| current.replace(recorder)));
+        self.future.as_mut().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_exporter() {
+        let mut trace = RunTrace::new(
+            &RunContext::with_run_id("session", "run"),
+            Some("gpt-4o"),
+            Message::user("Add 1 and 2"),
+        );
+        trace.started_at = 1000;
+        trace.steps.push(TraceStep {
+            started_at: 1001,
+            duration_ms: 5,
+            kind: TraceStepKind::ToolCall {
+                call_id: "call_1".into(),
+                name: "add".into(),
+                arguments: json!({"x": 1, "y": 2}),
+                output: Some("3".into()),
+                error: None,
+            },
+        });
+        trace.response = Some("3".into());
+
+        let buffer = Buffer::default();
+        let exporter = JsonlExporter::new(buffer.clone());
+        TraceExporter::export(&exporter, trace.clone())
+            .await
+            .unwrap();
+        TraceExporter::export(&exporter, trace.clone())
+            .await
+            .unwrap();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let value = serde_json::from_str::<Value>(lines[0]).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "schema_version": 1,
+                "session_id": "session",
+                "run_id": "run",
+                "model": "gpt-4o",
+                "started_at": 1000,
+                "duration_ms": 0,
+                "prompt": {"role": "user", "content": [{"type": "text", "text": "Add 1 and 2"}]},
+                "chat_history": [],
+                "response": "3",
+                "usage": {"input_tokens": 0, "output_tokens": 0, "total_tokens": 0},
+                "steps": [{
+                    "type": "tool_call",
+                    "started_at": 1001,
+                    "duration_ms": 5,
+                    "call_id": "call_1",
+                    "name": "add",
+                    "arguments": {"x": 1, "y": 2},
+                    "output": "3"
+                }]
+            })
+        );
+        assert_eq!(serde_json::from_value::<RunTrace>(value).unwrap(), trace);
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(rfc3339(951_782_400_000), "2000-02-29T00:00:00.000Z");
+        assert_eq!(rfc3339(1_736_935_200_123), "2025-01-15T10:00:00.123Z");
+    }
+}
@@ -0,0 +1,685 @@
+//! Offline replay of recorded agent runs, to debug production incidents.
+//!
+//! A [Replay] re-executes a run recorded as a [RunTrace] (ideally a
+//! [transcript](super#transcripts)) step by step, against mock providers answering with the
+//! recorded data: the [ReplayModel] answers the completions with the recorded choices, the
+//! [tools](Replay::tools) answer the tool calls with the recorded outputs (or errors), and the
+//! [ReplayIndex] answers the retrievals of the dynamic context with the recorded documents. The
+//! agent under investigation is built with these mocks (along with e.g. its preamble, or a fixed
+//! one) and prompted with the recorded prompt and chat history by [Replay::run]. No provider is
+//! called.
+//!
+//! Each step of the replayed run is a [ReplayStep], pairing what the agent sent (the assembled
+//! completion request, the arguments of a tool call or the query of a retrieval) with what was
+//! recorded. The steps are given to the [Replay::on_step] callback as the run progresses (e.g.:
+//! to print them, or to pause until the developer continues), and are listed in the
+//! [ReplayReport] of the run. A step which differs from the recording is
+//! [divergent](ReplayStep::is_divergent), e.g.: the requests of an agent whose preamble changed.
+//! Calls that the recording doesn't hold (e.g.: of a tool the recorded run never called) fail.
+//!
+//! # Example
+//! ```rust
+//! use rig::{agent::AgentBuilder, trace::{self, replay::Replay}};
+//!
+//! let traces = trace::read_jsonl(BufReader::new(File::open("transcripts.jsonl")?))?;
+//! let trace = traces.into_iter().find(|trace| trace.run_id == run_id).unwrap();
+//!
+//! let replay = Replay::new(trace).on_step(|step| println!("{step:#?}"));
+//! let agent = AgentBuilder::new(replay.model())
+//!     .preamble(FIXED_PREAMBLE)
+//!     .dynamic_context(3, replay.index())
+//!     .tool_group(replay.tools())?
+//!     .max_turns(5)
+//!     .build();
+//!
+//! let report = replay.run(&agent).await;
+//! if let Some(step) = report.divergence() {
+//!     println!("First divergent step: {step:#?}");
+//! }
+//! ```
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use futures::Future;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{RetrievedDocument, RunTrace, TraceStepKind};
+use crate::{
+    agent::Agent,
+    completion::{
+        self, Chat, CompletionError, CompletionModel, CompletionRequest, PromptError,
+        ToolDefinition,
+    },
+    message::AssistantContent,
+    tool::{group::ToolGroup, ToolDyn, ToolError},
+    vector_store::{VectorStoreError, VectorStoreIndex},
+    OneOrMany,
+};
+
+/// Step of a replayed run, with the recorded data it was replayed against. `step` is the index
+/// of the recorded step in [RunTrace::steps].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplayStep {
+    Retrieval {
+        step: usize,
+        recorded_query: String,
+        query: String,
+        /// Recorded documents, answered to the retrieval
+        documents: Vec<RetrievedDocument>,
+    },
+    Completion {
+        step: usize,
+        /// Recorded request, in transcripts
+        recorded_request: Option<Box<CompletionRequest>>,
+        /// Request assembled by the replayed agent
+        request: Box<CompletionRequest>,
+        /// Recorded choice, answered to the request
+        choice: OneOrMany<AssistantContent>,
+    },
+    ToolCall {
+        step: usize,
+        name: String,
+        recorded_arguments: Value,
+        arguments: Value,
+        /// Recorded output (or error) of the call, answered to the agent
+        output: Result<String, String>,
+    },
+    /// Call of the replayed run which is not part of the recording: the run diverged
+    Unrecorded { description: String },
+}
+
+impl ReplayStep {
+    /// Whether the step differs from the recording. Completion requests can only be compared
+    /// with the requests recorded in transcripts.
+    pub fn is_divergent(&self) -> bool {
+        match self {
+            ReplayStep::Retrieval {
+                recorded_query,
+                query,
+                ..
+            } => recorded_query != query,
+            ReplayStep::Completion {
+                recorded_request,
+                request,
+                ..
+            } => recorded_request
+                .as_ref()
+                .is_some_and(|recorded| recorded != request),
+            ReplayStep::ToolCall {
+                recorded_arguments,
+                arguments,
+                ..
+            } => recorded_arguments != arguments,
+            ReplayStep::Unrecorded { .. } => true,
+        }
+    }
+}
+
+/// Outcome of a replayed run
+#[derive(Debug)]
+pub struct ReplayReport {
+    /// Response of the replayed run
+    pub response: Result<String, PromptError>,
+    /// Response of the recorded run (`None` if it failed)
+    pub recorded_response: Option<String>,
+    pub steps: Vec<ReplayStep>,
+}
+
+impl ReplayReport {
+    /// First step of the replayed run which differs from the recording, if any.
+    pub fn divergence(&self) -> Option<&ReplayStep> {
+        self.steps.iter().find(|step| step.is_divergent())
+    }
+
+    /// Whether the replayed run answered the recorded response.
+    pub fn same_response(&self) -> bool {
+        matches!((&self.response, &self.recorded_response), (Ok(response), Some(recorded)) if response == recorded)
+    }
+}
+
+type StepCallback = Arc<dyn Fn(&ReplayStep) + Send + Sync>;
+
+/// Recorded steps not replayed yet, by kind (and by tool), as indexes of [RunTrace::steps]
+#[derive(Default)]
+struct ReplayState {
+    retrievals: VecDeque<usize>,
+    completions: VecDeque<usize>,
+    tool_calls: HashMap<String, VecDeque<usize>>,
+    steps: Vec<ReplayStep>,
+}
+
+impl ReplayState {
+    fn new(trace: &RunTrace) -> Self {
+        let mut state = Self::default();
+        for (i, step) in trace.steps.iter().enumerate() {
+            match &step.kind {
+                TraceStepKind::Retrieval { .. } => state.retrievals.push_back(i),
+                TraceStepKind::Completion { .. } => state.completions.push_back(i),
+                TraceStepKind::ToolCall { name, .. } => state
+                    .tool_calls
+                    .entry(name.clone())
+                    .or_default()
+                    .push_back(i),
+            }
+        }
+        state
+    }
+}
+
+/// State shared by a replay and its mocks
+#[derive(Clone)]
+struct Shared {
+    trace: Arc<RunTrace>,
+    state: Arc<Mutex<ReplayState>>,
+    on_step: Option<StepCallback>,
+}
+
+impl Shared {
+    fn state(&self) -> std::sync::MutexGuard<'_, ReplayState> {
+        self.state.lock().expect("replay lock poisoned")
+    }
+
+    /// Next recorded step of the queue selected by `queue`, with its index
+    fn next(
+        &self,
+        queue: impl FnOnce(&mut ReplayState) -> Option<&mut VecDeque<usize>>,
+    ) -> Option<(usize, &TraceStepKind)> {
+        let i = queue(&mut self.state())?.pop_front()?;
+        Some((i, &self.trace.steps[i].kind))
+    }
+
+    fn record(&self, step: ReplayStep) {
+        self.state().steps.push(step.clone());
+        if let Some(on_step) = &self.on_step {
+            on_step(&step);
+        }
+    }
+}
+
+/// Replay of a recorded run (see the [module](self) documentation).
+pub struct Replay {
+    shared: Shared,
+}
+
+impl Replay {
+    pub fn new(trace: RunTrace) -> Self {
+        Self {
+            shared: Shared {
+                state: Arc::new(Mutex::new(ReplayState::new(&trace))),
+                trace: Arc::new(trace),
+                on_step: None,
+            },
+        }
+    }
+
+    /// Call `on_step` with each step of the replayed run, as it is replayed. Must be set before
+    /// creating the mocks of the replay.
+    pub fn on_step(mut self, on_step: impl Fn(&ReplayStep) + Send + Sync + 'static) -> Self {
+        self.shared.on_step = Some(Arc::new(on_step));
+        self
+    }
+
+    /// Recorded run
+    pub fn trace(&self) -> &RunTrace {
+        &self.shared.trace
+    }
+
+    /// Completion model answering with the recorded choices, in order.
+    pub fn model(&self) -> ReplayModel {
+        ReplayModel {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Index answering the retrievals with the recorded documents, in order.
+    pub fn index(&self) -> ReplayIndex {
+        ReplayIndex {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Group (named `replay`) of the tools called by the recorded run, answering each call of a
+    /// tool with the recorded output of its next call. The definitions of the tools are taken
+    /// from the recorded requests of transcripts.
+    pub fn tools(&self) -> ToolGroup {
+        let trace = &self.shared.trace;
+        let definitions = trace
+            .steps
+            .iter()
+            .filter_map(|step| match &step.kind {
+                TraceStepKind::Completion {
+                    request: Some(request),
+                    ..
+                } => Some(&request.tools),
+                _ => None,
+            })
+            .flatten()
+            .map(|definition| (definition.name.as_str(), definition))
+            .collect::<HashMap<_, _>>();
+
+        let mut names = vec![];
+        for step in &trace.steps {
+            if let TraceStepKind::ToolCall { name, .. } = &step.kind {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        names
+            .into_iter()
+            .fold(ToolGroup::new("replay"), |group, name| {
+                let definition = definitions
+                    .get(name.as_str())
+                    .map(|definition| (*definition).clone())
+                    .unwrap_or_else(|| ToolDefinition {
+                        name: name.clone(),
+                        description: format!("Replayed tool {name}"),
+                        parameters: json!({"type": "object"}),
+                    });
+                group.tool(ReplayTool {
+                    definition,
+                    shared: self.shared.clone(),
+                })
+            })
+    }
+
+    /// Replay the run: prompt `agent` (built with the mocks of the replay) with the recorded
+    /// prompt and chat history. The replay can be run again, e.g.: with a fixed agent.
+    pub async fn run<M: CompletionModel>(&self, agent: &Agent<M>) -> ReplayReport {
+        let trace = &self.shared.trace;
+        *self.shared.state() = ReplayState::new(trace);
+        let response = agent
+            .chat(trace.prompt.clone(), trace.chat_history.clone())
+            .await;
+        ReplayReport {
+            response,
+            recorded_response: trace.response.clone(),
+            steps: std::mem::take(&mut self.shared.state().steps),
+        }
+    }
+}
+
+/// Completion model of a [Replay], answering with the recorded choices.
+#[derive(Clone)]
+pub struct ReplayModel {
+    shared: Shared,
+}
+
+impl CompletionModel for ReplayModel {
+    type Response = ();
+
+    fn model_name(&self) -> Option<&str> {
+        self.shared.trace.model.as_deref()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<()>, CompletionError> {
+        let Some((
+            step,
+            TraceStepKind::Completion {
+                request: recorded_request,
+                choice,
+                usage,
+                ..
+            },
+        )) = self.shared.next(|state| Some(&mut state.completions))
+        else {
+            self.shared.record(ReplayStep::Unrecorded {
+                description: format!(
+                    "Completion request with {} message(s) after the recorded completions",
+                    request.chat_history.len() + 1
+                ),
+            });
+            return Err(CompletionError::ProviderError(
+                "No recorded completion left".into(),
+            ));
+        };
+
+        self.shared.record(ReplayStep::Completion {
+            step,
+            recorded_request: recorded_request.clone(),
+            request: Box::new(request),
+            choice: choice.clone(),
+        });
+        Ok(completion::CompletionResponse {
+            choice: choice.clone(),
+            usage: *usage,
+            raw_response: (),
+        })
+    }
+}
+
+/// Tool of a [Replay], answering with the recorded outputs of its calls.
+struct ReplayTool {
+    definition: ToolDefinition,
+    shared: Shared,
+}
+
+impl ToolDyn for ReplayTool {
+    fn name(&self) -> String {
+        self.definition.name.clone()
+    }
+
+    fn definition(
+        &self,
+        _prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        Box::pin(async move { self.definition.clone() })
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        Box::pin(async move {
+            let name = &self.definition.name;
+            let arguments = serde_json::from_str(&args).unwrap_or(Value::String(args));
+            let Some((
+                step,
+                TraceStepKind::ToolCall {
+                    arguments: recorded_arguments,
+                    output,
+                    error,
+                    ..
+                },
+            )) = self.shared.next(|state| state.tool_calls.get_mut(name))
+            else {
+                self.shared.record(ReplayStep::Unrecorded {
+                    description: format!("Call of the tool {name} with {arguments}"),
+                });
+                return Err(ToolError::ToolCallError(
+                    format!("No recorded call of the tool {name} left").into(),
+                ));
+            };
+
+            let output = match error {
+                Some(error) => Err(error.clone()),
+                None => Ok(output.clone().unwrap_or_default()),
+            };
+            self.shared.record(ReplayStep::ToolCall {
+                step,
+                name: name.clone(),
+                recorded_arguments: recorded_arguments.clone(),
+                arguments,
+                output: output.clone(),
+            });
+            output.map_err(|error| ToolError::ToolCallError(error.into()))
+        })
+    }
+}
+
+/// Index of a [Replay], answering each retrieval with the recorded documents of the next
+/// recorded retrieval. Documents recorded without their text (i.e.: outside of transcripts) are
+/// answered as `null`.
+#[derive(Clone)]
+pub struct ReplayIndex {
+    shared: Shared,
+}
+
+impl ReplayIndex {
+    fn retrieve(&self, query: &str, n: usize) -> Vec<RetrievedDocument> {
+        let Some((
+            step,
+            TraceStepKind::Retrieval {
+                query: recorded_query,
+                documents,
+            },
+        )) = self.shared.next(|state| Some(&mut state.retrievals))
+        else {
+            self.shared.record(ReplayStep::Unrecorded {
+                description: format!("Retrieval of {n} document(s) for {query:?}"),
+            });
+            return vec![];
+        };
+
+        let documents = documents.iter().take(n).cloned().collect::<Vec<_>>();
+        self.shared.record(ReplayStep::Retrieval {
+            step,
+            recorded_query: recorded_query.clone(),
+            query: query.to_string(),
+            documents: documents.clone(),
+        });
+        documents
+    }
+}
+
+impl VectorStoreIndex for ReplayIndex {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.retrieve(query, n)
+            .into_iter()
+            .map(|doc| {
+                // The texts of the documents are rendered as JSON by the agents
+                let value = doc.text.as_deref().map_or(Value::Null, |text| {
+                    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.into()))
+                });
+                Ok((doc.score, doc.id, serde_json::from_value(value)?))
+            })
+            .collect()
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        Ok(self
+            .retrieve(query, n)
+            .into_iter()
+            .map(|doc| (doc.score, doc.id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::Message,
+        telemetry::RunContext,
+        trace::{read_jsonl, JsonlExporter, TraceExporter, TraceStep},
+    };
+
+    fn step(kind: TraceStepKind) -> TraceStep {
+        TraceStep {
+            started_at: 0,
+            duration_ms: 0,
+            kind,
+        }
+    }
+
+    fn retrieval() -> TraceStep {
+        step(TraceStepKind::Retrieval {
+            query: "Add 1 and 2".into(),
+            documents: vec![RetrievedDocument {
+                id: "doc0".into(),
+                score: 0.9,
+                text: Some("\"Use the add tool\"".into()),
+            }],
+        })
+    }
+
+    fn completion(content: AssistantContent) -> TraceStep {
+        step(TraceStepKind::Completion {
+            model: Some("gpt-4o".into()),
+            request: None,
+            choice: OneOrMany::one(content),
+            usage: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let mut trace = RunTrace::new(
+            &RunContext::new("session"),
+            Some("gpt-4o"),
+            Message::user("Add 1 and 2"),
+        );
+        trace.response = Some("3".into());
+        trace.steps = vec![
+            retrieval(),
+            completion(AssistantContent::tool_call(
+                "call_1",
+                "add",
+                json!({"x": 1, "y": 2}),
+            )),
+            step(TraceStepKind::ToolCall {
+                call_id: "call_1".into(),
+                name: "add".into(),
+                arguments: json!({"x": 1, "y": 2}),
+                output: Some("3".into()),
+                error: None,
+            }),
+            retrieval(),
+            completion(AssistantContent::text("3")),
+        ];
+
+        // Round trip through a JSONL export
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("traces.jsonl");
+        TraceExporter::export(&JsonlExporter::file(&path).unwrap(), trace)
+            .await
+            .unwrap();
+        let trace = read_jsonl(std::io::BufReader::new(std::fs::File::open(&path).unwrap()))
+            .unwrap()
+            .remove(0);
+
+        // Replay the trace, recording the transcript of the replayed run
+        let steps = Arc::new(Mutex::new(vec![]));
+        let replay = Replay::new(trace).on_step({
+            let steps = steps.clone();
+            move |step| steps.lock().unwrap().push(step.clone())
+        });
+        let (sender, mut transcripts) = futures::channel::mpsc::unbounded();
+        let agent = AgentBuilder::new(replay.model())
+            .preamble("You add numbers")
+            .dynamic_context(1, replay.index())
+            .tool_group(replay.tools())
+            .unwrap()
+            .max_turns(1)
+            .transcript(sender)
+            .build();
+
+        let report = replay.run(&agent).await;
+        assert!(report.same_response());
+        assert_eq!(report.steps, *steps.lock().unwrap());
+        assert!(matches!(
+            &report.steps[..],
+            [
+                ReplayStep::Retrieval { step: 0, .. },
+                ReplayStep::Completion { step: 1, .. },
+                ReplayStep::ToolCall { step: 2, output: Ok(output), .. },
+                ReplayStep::Retrieval { step: 3, .. },
+                ReplayStep::Completion { step: 4, request, .. },
+            ] if output == "3" && request.documents[0].text == "\"Use the add tool\""
+        ));
+        assert!(report.steps[..3].iter().all(|step| !step.is_divergent()));
+
+        // Replaying a transcript compares the requests: a new preamble diverges
+        let transcript = transcripts.try_next().unwrap().unwrap();
+        let replay = Replay::new(transcript);
+        let agent = |replay: &Replay, preamble| {
+            AgentBuilder::new(replay.model())
+                .preamble(preamble)
+                .dynamic_context(1, replay.index())
+                .tool_group(replay.tools())
+                .unwrap()
+                .max_turns(1)
+                .build()
+        };
+        let report = replay.run(&agent(&replay, "You add numbers")).await;
+        assert!(report.same_response());
+        assert_eq!(report.divergence(), None);
+
+        let report = replay.run(&agent(&replay, "You multiply numbers")).await;
+        assert!(matches!(
+            report.divergence(),
+            Some(ReplayStep::Completion { step: 1, .. })
+        ));
+
+        // Calls missing from the recording diverge, e.g.: the retrievals of a new dynamic context
+        let mut trace = replay.trace().clone();
+        trace
+            .steps
+            .retain(|step| !matches!(step.kind, TraceStepKind::Retrieval { .. }));
+        let replay = Replay::new(trace);
+        let report = replay.run(&agent(&replay, "You add numbers")).await;
+        assert!(report.same_response());
+        assert!(matches!(
+            report.divergence(),
+            Some(ReplayStep::Unrecorded { description }) if description.contains("Add 1 and 2")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replay_divergences() {
+        let mut trace = RunTrace::new(
+            &RunContext::new("session"),
+            Some("gpt-4o"),
+            Message::user("Add 1 and 2"),
+        );
+        trace.steps = vec![
+            completion(AssistantContent::tool_call(
+                "call_1",
+                "add",
+                json!({"x": 2}),
+            )),
+            step(TraceStepKind::ToolCall {
+                call_id: "call_1".into(),
+                name: "add".into(),
+                arguments: json!({"x": 1, "y": 2}),
+                output: None,
+                error: Some("Overflow".into()),
+            }),
+            completion(AssistantContent::tool_call(
+                "call_2",
+                "add",
+                json!({"x": 1}),
+            )),
+        ];
+
+        let replay = Replay::new(trace);
+        let agent = AgentBuilder::new(replay.model())
+            .tool_group(replay.tools())
+            .unwrap()
+            .max_turns(3)
+            .build();
+        let report = replay.run(&agent).await;
+
+        // The recorded run failed, and the replayed one runs out of recorded calls
+        assert!(!report.same_response());
+        assert!(matches!(
+            &report.response,
+            Err(PromptError::CompletionError(CompletionError::ProviderError(e)))
+                if e == "No recorded completion left"
+        ));
+        assert!(matches!(
+            &report.steps[..],
+            [
+                ReplayStep::Completion { step: 0, .. },
+                ReplayStep::ToolCall { step: 1, output: Err(error), .. },
+                ReplayStep::Completion { step: 2, .. },
+                ReplayStep::Unrecorded { description: tool },
+                ReplayStep::Unrecorded { description: completion },
+            ] if error == "Overflow"
+                && tool.contains("add")
+                && completion.contains("after the recorded completions")
+        ));
+        // The recorded arguments of the tool call differ from the replayed ones
+        assert!(matches!(
+            report.divergence(),
+            Some(ReplayStep::ToolCall { step: 1, arguments, .. }) if *arguments == json!({"x": 2})
+        ));
+    }
+}
@@ -0,0 +1,263 @@
+//! This module provides functionality for transcribing audio to text.
+//!
+//! A [TranscriptionModel] (e.g.: OpenAI Whisper) transcribes an audio file, given as a
+//! [TranscriptionRequest]. Requests are usually created with the [TranscriptionRequestBuilder]
+//! returned by [TranscriptionModel::transcription_request]. The transcribed text can then be
+//! used as the prompt or a context document of an agent, or be embedded in a vector store.
+//!
+//! # Example
+//! ```rust
+//! use rig::{providers::openai, transcription::TranscriptionModel};
+//!
+//! let openai = openai::Client::from_env();
+//! let whisper = openai.transcription_model(openai::WHISPER_1);
+//!
+//! let transcription = whisper
+//!     .transcription_request()
+//!     .load_file("voice_note.mp3")?
+//!     .language("en")
+//!     .send()
+//!     .await?;
+//!
+//! let agent = openai.agent("gpt-4o").context(&transcription.text).build();
+//! ```
+use std::{future::Future, path::Path};
+
+use serde_json::Value;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TranscriptionError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[cfg(feature = "http")]
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error building the transcription request
+    #[error("RequestError: {0}")]
+    RequestError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Error parsing the transcription response
+    #[error("ResponseError: {0}")]
+    ResponseError(String),
+
+    /// Error returned by the transcription model provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+/// Request to transcribe an audio file.
+#[derive(Clone, Debug)]
+pub struct TranscriptionRequest {
+    /// Content of the audio file
+    pub data: Vec<u8>,
+    /// Name of the audio file, whose extension gives its format (e.g.: `voice_note.mp3`)
+    pub filename: String,
+    /// Language of the audio (e.g.: `en`), which improves the accuracy and latency of the
+    /// transcription
+    pub language: Option<String>,
+    /// Text guiding the style of the transcription, or continuing a previous audio segment
+    pub prompt: Option<String>,
+    /// Sampling temperature of the transcription
+    pub temperature: Option<f64>,
+    /// Additional provider-specific parameters
+    pub additional_params: Option<Value>,
+}
+
+/// Transcription of an audio file, along with the raw response of the provider.
+#[derive(Debug)]
+pub struct TranscriptionResponse<T> {
+    pub text: String,
+    pub response: T,
+}
+
+/// Trait for models transcribing audio to text.
+pub trait TranscriptionModel: Clone + Send + Sync {
+    /// Raw response type returned by the provider
+    type Response: Send + Sync;
+
+    /// Transcribe the audio file of `request`.
+    fn transcription(
+        &self,
+        request: TranscriptionRequest,
+    ) -> impl Future<Output = Result<TranscriptionResponse<Self::Response>, TranscriptionError>> + Send;
+
+    /// Create a transcription request builder for this model.
+    fn transcription_request(&self) -> TranscriptionRequestBuilder<Self> {
+        TranscriptionRequestBuilder::new(self.clone())
+    }
+}
+
+/// Builder for transcription requests.
+///
+/// # Example
+/// ```rust
+/// use rig::transcription::TranscriptionModel;
+///
+/// let request = model
+///     .transcription_request()
+///     .data(audio, "interview.wav")
+///     .prompt("Interview of the Rig maintainers.")
+///     .temperature(0.0)
+///     .build();
+///
+/// let transcription = model.transcription(request).await?;
+/// ```
+pub struct TranscriptionRequestBuilder<M: TranscriptionModel> {
+    model: M,
+    data: Vec<u8>,
+    filename: String,
+    language: Option<String>,
+    prompt: Option<String>,
+    temperature: Option<f64>,
+    additional_params: Option<Value>,
+}
+
+impl<M: TranscriptionModel> TranscriptionRequestBuilder<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            data: vec![],
+            filename: String::new(),
+            language: None,
+            prompt: None,
+            temperature: None,
+            additional_params: None,
+        }
+    }
+
+    /// Set the content of the audio file, and its name (whose extension gives its format).
+    pub fn data(mut self, data: Vec<u8>, filename: impl Into<String>) -> Self {
+        self.data = data;
+        self.filename = filename.into();
+        self
+    }
+
+    /// Read the audio file at `path`.
+    pub fn load_file(self, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .map(|filename| filename.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(self.data(std::fs::read(path)?, filename))
+    }
+
+    /// Set the language of the audio (e.g.: `en`).
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Set the text guiding the style of the transcription.
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    /// Set the sampling temperature of the transcription.
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Add additional provider-specific parameters to the request.
+    pub fn additional_params(mut self, additional_params: Value) -> Self {
+        self.additional_params = match self.additional_params {
+            Some(params) => Some(crate::json_utils::merge(params, additional_params)),
+            None => Some(additional_params),
+        };
+        self
+    }
+
+    pub fn build(self) -> TranscriptionRequest {
+        TranscriptionRequest {
+            data: self.data,
+            filename: self.filename,
+            language: self.language,
+            prompt: self.prompt,
+            temperature: self.temperature,
+            additional_params: self.additional_params,
+        }
+    }
+
+    /// Build the request and send it to the model.
+    pub async fn send(self) -> Result<TranscriptionResponse<M::Response>, TranscriptionError> {
+        let model = self.model.clone();
+        model.transcription(self.build()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// Model "transcribing" audio files containing text
+    #[derive(Clone)]
+    struct TextModel;
+
+    impl TranscriptionModel for TextModel {
+        type Response = TranscriptionRequest;
+
+        async fn transcription(
+            &self,
+            request: TranscriptionRequest,
+        ) -> Result<TranscriptionResponse<TranscriptionRequest>, TranscriptionError> {
+            let text = String::from_utf8(request.data.clone())
+                .map_err(|e| TranscriptionError::RequestError(e.into()))?;
+            Ok(TranscriptionResponse {
+                text,
+                response: request,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transcription_request() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("note.wav");
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(b"Buy milk")
+            .unwrap();
+
+        let transcription = TextModel
+            .transcription_request()
+            .load_file(&path)
+            .unwrap()
+            .language("en")
+            .additional_params(json!({"a": 1}))
+            .additional_params(json!({"b": 2}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(transcription.text, "Buy milk");
+        assert_eq!(transcription.response.filename, "note.wav");
+        assert_eq!(transcription.response.language.as_deref(), Some("en"));
+        assert_eq!(
+            transcription.response.additional_params,
+            Some(json!({"a": 1, "b": 2}))
+        );
+    }
+
+    #[test]
+    fn test_load_file_errors() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        assert!(TextModel
+            .transcription_request()
+            .load_file(dir.path().join("missing.wav"))
+            .is_err());
+        // A directory is not an audio file
+        assert!(TextModel
+            .transcription_request()
+            .load_file(dir.path())
+            .is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! This module provides functionality for converting text to speech.
+//!
+//! A [SpeechModel] (e.g.: OpenAI TTS) speaks a text, given as a [SpeechRequest]. Requests are
+//! usually created with the [SpeechRequestBuilder] returned by [SpeechModel::speech_request].
+//! The speech is returned as the bytes of an audio file ([SpeechResponse]), or streamed as
+//! chunks of audio ([SpeechStream]) to start playing it before it is fully generated.
+//!
+//! Along with the [transcription](crate::transcription) of the prompts, this completes the loop
+//! of voice assistants: voice note, transcription, agent response, speech.
+//!
+//! # Example
+//! ```rust
+//! use rig::{completion::Prompt, providers::openai, transcription::TranscriptionModel, tts::SpeechModel};
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent("gpt-4o").preamble("You are a voice assistant.").build();
+//!
+//! let question = openai
+//!     .transcription_model(openai::WHISPER_1)
+//!     .transcription_request()
+//!     .load_file("question.mp3")?
+//!     .send()
+//!     .await?;
+//! let answer = agent.prompt(question.text.as_str()).await?;
+//!
+//! let speech = openai
+//!     .speech_model(openai::GPT_4O_MINI_TTS)
+//!     .speech_request()
+//!     .text(answer)
+//!     .voice("nova")
+//!     .send()
+//!     .await?;
+//! std::fs::write("answer.mp3", speech.audio)?;
+//! ```
+use std::{future::Future, pin::Pin};
+
+use bytes::Bytes;
+use futures::Stream;
+use serde_json::Value;
+
+use crate::message::AudioMediaType;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpeechError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[cfg(feature = "http")]
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error building the speech request (e.g.: unsupported audio format)
+    #[error("RequestError: {0}")]
+    RequestError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Error returned by the speech model provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+/// Request to speak a text.
+#[derive(Clone, Debug)]
+pub struct SpeechRequest {
+    /// Text to speak
+    pub text: String,
+    /// Voice of the speech (e.g.: `alloy`), the provider's default if not set
+    pub voice: Option<String>,
+    /// Format of the audio, the provider's default (usually MP3) if not set
+    pub format: Option<AudioMediaType>,
+    /// Speed of the speech, 1.0 being the normal speed
+    pub speed: Option<f64>,
+    /// Instructions on the way to speak (e.g.: tone, accent), for the models supporting them
+    pub instructions: Option<String>,
+    /// Additional provider-specific parameters
+    pub additional_params: Option<Value>,
+}
+
+/// Speech of a text, as the bytes of an audio file.
+#[derive(Debug, Clone)]
+pub struct SpeechResponse {
+    pub audio: Vec<u8>,
+    pub media_type: AudioMediaType,
+}
+
+/// Stream of the chunks of audio of a speech
+#[cfg(not(target_arch = "wasm32"))]
+pub type SpeechStream = Pin<Box<dyn Stream<Item = Result<Bytes, SpeechError>> + Send>>;
+
+#[cfg(target_arch = "wasm32")]
+pub type SpeechStream = Pin<Box<dyn Stream<Item = Result<Bytes, SpeechError>>>>;
+
+/// Trait for models converting text to speech.
+pub trait SpeechModel: Clone + Send + Sync {
+    /// Speak the text of `request`.
+    fn speech(
+        &self,
+        request: SpeechRequest,
+    ) -> impl Future<Output = Result<SpeechResponse, SpeechError>> + Send;
+
+    /// Stream the speech of the text of `request`. Defaults to a single chunk of the whole
+    /// speech, for the models which don't stream their audio.
+    fn speech_stream(
+        &self,
+        request: SpeechRequest,
+    ) -> impl Future<Output = Result<SpeechStream, SpeechError>> + Send {
+        async move {
+            let speech = self.speech(request).await?;
+            let stream: SpeechStream = Box::pin(futures::stream::once(async move {
+                Ok(Bytes::from(speech.audio))
+            }));
+            Ok(stream)
+        }
+    }
+
+    /// Create a speech request builder for this model.
+    fn speech_request(&self) -> SpeechRequestBuilder<Self> {
+        SpeechRequestBuilder::new(self.clone())
+    }
+}
+
+/// Builder for speech requests.
+///
+/// # Example
+/// ```rust
+/// use futures::StreamExt;
+/// use rig::tts::SpeechModel;
+///
+/// let mut stream = model
+///     .speech_request()
+///     .text("Your order has shipped.")
+///     .instructions("Speak in a cheerful tone.")
+///     .speed(1.2)
+///     .stream()
+///     .await?;
+///
+/// while let Some(chunk) = stream.next().await {
+///     player.play(&chunk?);
+/// }
+/// ```
+pub struct SpeechRequestBuilder<M: SpeechModel> {
+    model: M,
+    text: String,
+    voice: Option<String>,
+    format: Option<AudioMediaType>,
+    speed: Option<f64>,
+    instructions: Option<String>,
+    additional_params: Option<Value>,
+}
+
+impl<M: SpeechModel> SpeechRequestBuilder<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            text: String::new(),
+            voice: None,
+            format: None,
+            speed: None,
+            instructions: None,
+            additional_params: None,
+        }
+    }
+
+    /// Set the text to speak.
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = text.into();
+        self
+    }
+
+    /// Set the voice of the speech (e.g.: `alloy`).
+    pub fn voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+
+    /// Set the format of the audio.
+    pub fn format(mut self, format: AudioMediaType) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Set the speed of the speech, 1.0 being the normal speed.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// Set the instructions on the way to speak (e.g.: tone, accent).
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Add additional provider-specific parameters to the request.
+    pub fn additional_params(mut self, additional_params: Value) -> Self {
+        self.additional_params = match self.additional_params {
+            Some(params) => Some(crate::json_utils::merge(params, additional_params)),
+            None => Some(additional_params),
+        };
+        self
+    }
+
+    pub fn build(self) -> SpeechRequest {
+        SpeechRequest {
+            text: self.text,
+            voice: self.voice,
+            format: self.format,
+            speed: self.speed,
+            instructions: self.instructions,
+            additional_params: self.additional_params,
+        }
+    }
+
+    /// Build the request and send it to the model.
+    pub async fn send(self) -> Result<SpeechResponse, SpeechError> {
+        let model = self.model.clone();
+        model.speech(self.build()).await
+    }
+
+    /// Build the request and stream the speech from the model.
+    pub async fn stream(self) -> Result<SpeechStream, SpeechError> {
+        let model = self.model.clone();
+        model.speech_stream(self.build()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use serde_json::json;
+
+    use super::*;
+
+    /// Model "speaking" texts as their bytes
+    #[derive(Clone)]
+    struct TextModel;
+
+    impl SpeechModel for TextModel {
+        async fn speech(&self, request: SpeechRequest) -> Result<SpeechResponse, SpeechError> {
+            assert_eq!(request.additional_params, Some(json!({"a": 1, "b": 2})));
+            Ok(SpeechResponse {
+                audio: request.text.into_bytes(),
+                media_type: request.format.unwrap_or(AudioMediaType::MP3),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_speech_request() {
+        let request = || {
+            TextModel
+                .speech_request()
+                .text("Hello")
+                .additional_params(json!({"a": 1}))
+                .additional_params(json!({"b": 2}))
+        };
+
+        let speech = request().format(AudioMediaType::WAV).send().await.unwrap();
+        assert_eq!(speech.audio, b"Hello");
+        assert_eq!(speech.media_type, AudioMediaType::WAV);
+
+        // Models without streaming stream their speech as a single chunk
+        let chunks = request().stream().await.unwrap().collect::<Vec<_>>().await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].as_ref().unwrap().as_ref(), b"Hello");
+    }
+
+    /// Model failing to speak any text
+    #[derive(Clone)]
+    struct MuteModel;
+
+    impl SpeechModel for MuteModel {
+        async fn speech(&self, _request: SpeechRequest) -> Result<SpeechResponse, SpeechError> {
+            Err(SpeechError::ProviderError("Voice unavailable".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_speech_errors() {
+        assert!(matches!(
+            MuteModel.speech_request().text("Hello").send().await,
+            Err(SpeechError::ProviderError(message)) if message == "Voice unavailable"
+        ));
+
+        // The default stream fails before its first chunk
+        assert!(matches!(
+            MuteModel.speech_request().text("Hello").stream().await,
+            Err(SpeechError::ProviderError(_))
+        ));
+    }
+}
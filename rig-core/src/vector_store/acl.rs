@@ -0,0 +1,403 @@
+//! Access control of the documents of vector stores.
+//!
+//! Documents carry an [Acl] in their metadata (under the `acl` field): the users and groups
+//! allowed to read them, or whether they are public. Searches of an [AclIndex] are made on
+//! behalf of a [Principal] (a user and their groups), and only return the documents readable
+//! by the principal: its permissions are enforced as a mandatory [Filter], combined with the
+//! filter of each search, so any store supporting filters (e.g.: the in-memory store, MongoDB
+//! or LanceDB) can serve a permissioned corpus. Stores not supporting filters return
+//! [VectorStoreError::FilterNotSupported] instead of unfiltered results.
+//!
+//! The principal is either fixed (e.g.: an index per user) or the principal of the current
+//! request, set with [Principal::scope] (e.g.: by the handler serving the request of a user).
+//! Without principal, only the public documents are returned.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     completion::Prompt,
+//!     vector_store::acl::{Acl, AclIndex, Principal},
+//! };
+//!
+//! vector_store.set_metadata("handbook", Acl::public().metadata());
+//! vector_store.set_metadata("salaries", Acl::new().group("hr").metadata());
+//!
+//! let agent = openai.agent("gpt-4o")
+//!     .dynamic_context(3, AclIndex::new(vector_store.index(embedding_model)))
+//!     .build();
+//!
+//! // Only the documents readable by John are retrieved
+//! let principal = Principal::new("john").group("engineering");
+//! let response = principal.scope(agent.prompt("What is my salary?")).await?;
+//! ```
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{Filter, SearchOptions, VectorStoreError, VectorStoreIndex};
+
+/// Field of the metadata of the documents holding their [Acl]
+pub const ACL_FIELD: &str = "acl";
+
+/// Users and groups allowed to read a document.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Acl {
+    /// Whether everyone can read the document
+    #[serde(default)]
+    pub public: bool,
+    #[serde(default)]
+    pub users: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl Acl {
+    /// ACL of a document readable by nobody, until users or groups are allowed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ACL of a document readable by everyone.
+    pub fn public() -> Self {
+        Self {
+            public: true,
+            ..Self::default()
+        }
+    }
+
+    /// Allow `user` to read the document.
+    pub fn user(mut self, user: &str) -> Self {
+        self.users.push(user.to_string());
+        self
+    }
+
+    /// Allow the members of `group` to read the document.
+    pub fn group(mut self, group: &str) -> Self {
+        self.groups.push(group.to_string());
+        self
+    }
+
+    /// Metadata of a document with this ACL, e.g.: to merge with its other metadata.
+    pub fn metadata(&self) -> serde_json::Value {
+        json!({ ACL_FIELD: self })
+    }
+}
+
+thread_local! {
+    static CURRENT_PRINCIPAL: RefCell<Option<Principal>> = const { RefCell::new(None) };
+}
+
+/// User on behalf of whom searches are made, with the groups they belong to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Principal {
+    pub user: String,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl Principal {
+    pub fn new(user: &str) -> Self {
+        Self {
+            user: user.to_string(),
+            groups: vec![],
+        }
+    }
+
+    /// Add `group` to the groups of the principal.
+    pub fn group(mut self, group: &str) -> Self {
+        self.groups.push(group.to_string());
+        self
+    }
+
+    /// Filter matching the documents readable by the principal: the public ones, and the ones
+    /// allowing its user or one of its groups.
+    pub fn filter(&self) -> Filter {
+        let users = Filter::contains(format!("{ACL_FIELD}.users"), self.user.as_str());
+        let groups = self
+            .groups
+            .iter()
+            .map(|group| Filter::contains(format!("{ACL_FIELD}.groups"), group.as_str()));
+        Filter::Or([public_filter(), users].into_iter().chain(groups).collect())
+    }
+
+    /// Principal of the current request, if any (see [Principal::scope]).
+    pub fn current() -> Option<Principal> {
+        CURRENT_PRINCIPAL.with(|principal| principal.borrow().clone())
+    }
+
+    /// Run `future` on behalf of this principal: [Principal::current] returns this principal
+    /// while the future (and the futures it awaits) is polled. Tasks spawned by the future are
+    /// not run on behalf of the principal, unless they are scoped themselves.
+    pub fn scope<F: Future>(self, future: F) -> PrincipalScoped<F> {
+        PrincipalScoped {
+            principal: self,
+            future: Box::pin(future),
+        }
+    }
+}
+
+/// Filter matching the public documents only
+fn public_filter() -> Filter {
+    Filter::eq(format!("{ACL_FIELD}.public"), true)
+}
+
+/// Future running on behalf of a principal, returned by [Principal::scope]
+pub struct PrincipalScoped<F> {
+    principal: Principal,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for PrincipalScoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        /// Restores the enclosing principal once the future is polled, even if it panics
+        struct Restore(Option<Principal>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_PRINCIPAL.with(|principal| *principal.borrow_mut() = self.0.take());
+            }
+        }
+
+        let principal = self.principal.clone();
+        let _restore = Restore(CURRENT_PRINCIPAL.with(|current| current.replace(Some(principal))));
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Index only returning the documents readable by a principal (see the [module](self)
+/// documentation).
+pub struct AclIndex<I: VectorStoreIndex> {
+    index: I,
+    principal: Option<Principal>,
+}
+
+impl<I: VectorStoreIndex> AclIndex<I> {
+    /// Index searching `index` on behalf of the principal of the current request.
+    pub fn new(index: I) -> Self {
+        Self {
+            index,
+            principal: None,
+        }
+    }
+
+    /// Search on behalf of `principal`, whatever the principal of the current request.
+    pub fn principal(mut self, principal: Principal) -> Self {
+        self.principal = Some(principal);
+        self
+    }
+
+    /// Filter of the documents readable on behalf of the principal, combined with `filter`
+    fn acl_filter(&self, filter: Option<&Filter>) -> Filter {
+        let acl = match self.principal.clone().or_else(Principal::current) {
+            Some(principal) => principal.filter(),
+            None => public_filter(),
+        };
+        match filter {
+            Some(filter) => acl.and(filter.clone()),
+            None => acl,
+        }
+    }
+}
+
+impl<I: VectorStoreIndex> VectorStoreIndex for AclIndex<I> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let filter = self.acl_filter(None);
+        self.index.top_n_with_filter(query, n, &filter).await
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let filter = self.acl_filter(None);
+        self.index.top_n_ids_with_filter(query, n, &filter).await
+    }
+
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let filter = self.acl_filter(Some(filter));
+        self.index.top_n_with_filter(query, n, &filter).await
+    }
+
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let filter = self.acl_filter(Some(filter));
+        self.index.top_n_ids_with_filter(query, n, &filter).await
+    }
+
+    async fn top_n_with_options<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let options = options
+            .clone()
+            .filter(self.acl_filter(options.filter.as_ref()));
+        self.index.top_n_with_options(query, n, &options).await
+    }
+
+    async fn top_n_ids_with_options(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let options = options
+            .clone()
+            .filter(self.acl_filter(options.filter.as_ref()));
+        self.index.top_n_ids_with_options(query, n, &options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        embeddings::{Embedding, EmbeddingError, EmbeddingModel},
+        providers::mock::MockEmbeddingModel,
+        vector_store::in_memory_store::InMemoryVectorStore,
+        OneOrMany,
+    };
+
+    #[tokio::test]
+    async fn test_acl_index() {
+        let mut store = InMemoryVectorStore::default();
+        store.add_documents_with_metadata(
+            [
+                ("handbook", Acl::public()),
+                ("salaries", Acl::new().group("hr").user("ceo")),
+                ("roadmap", Acl::new().group("engineering")),
+            ]
+            .map(|(id, acl)| {
+                let embedding = Embedding {
+                    document: id.to_string(),
+                    vec: vec![1.0, 0.0],
+                };
+                (id, id, acl.metadata(), OneOrMany::one(embedding))
+            }),
+        );
+        let index = AclIndex::new(store.index(MockEmbeddingModel::new(2)));
+
+        let ids = |results: Vec<(f64, String)>| {
+            let mut ids = results.into_iter().map(|(_, id)| id).collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+
+        // Without principal, only the public documents are returned
+        assert_eq!(
+            ids(index.top_n_ids("plans", 3).await.unwrap()),
+            vec!["handbook"]
+        );
+
+        let engineer = Principal::new("john").group("engineering");
+        assert_eq!(
+            ids(engineer
+                .clone()
+                .scope(index.top_n_ids("plans", 3))
+                .await
+                .unwrap()),
+            vec!["handbook", "roadmap"]
+        );
+        let ceo = Principal::new("ceo");
+        assert_eq!(
+            ids(ceo.scope(index.top_n_ids("plans", 3)).await.unwrap()),
+            vec!["handbook", "salaries"]
+        );
+
+        // The ACL is combined with the filter of the search
+        let options = SearchOptions::new().filter(Filter::eq("missing", true));
+        assert!(engineer
+            .scope(index.top_n_ids_with_options("plans", 3, &options))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // A fixed principal takes precedence over the current one
+        let index = index.principal(Principal::new("jane").group("hr"));
+        assert_eq!(
+            ids(Principal::new("john")
+                .scope(index.top_n_ids("plans", 3))
+                .await
+                .unwrap()),
+            vec!["handbook", "salaries"]
+        );
+    }
+
+    /// Embedding model failing every request
+    #[derive(Clone)]
+    struct FailingModel;
+
+    impl EmbeddingModel for FailingModel {
+        const MAX_DOCUMENTS: usize = 1;
+
+        fn ndims(&self) -> usize {
+            2
+        }
+
+        async fn embed_texts(
+            &self,
+            _texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Err(EmbeddingError::ProviderError("Overloaded".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_acl_index_errors() {
+        // Documents without ACL are never returned
+        let mut store = InMemoryVectorStore::default();
+        store.add_documents_with_metadata([(
+            "draft",
+            "draft",
+            serde_json::json!({}),
+            OneOrMany::one(Embedding {
+                document: "draft".to_string(),
+                vec: vec![1.0, 0.0],
+            }),
+        )]);
+        let index = AclIndex::new(store.clone().index(MockEmbeddingModel::new(2)))
+            .principal(Principal::new("john"));
+        assert!(index.top_n_ids("plans", 1).await.unwrap().is_empty());
+
+        // Errors of the index are returned as is
+        let index = AclIndex::new(store.index(FailingModel)).principal(Principal::new("john"));
+        assert!(matches!(
+            index.top_n_ids("plans", 1).await,
+            Err(VectorStoreError::EmbeddingError(_))
+        ));
+
+        // Nested scopes restore the enclosing principal
+        let user = || Principal::current().map(|principal| principal.user);
+        let users = Principal::new("john")
+            .scope(async {
+                let inner = Principal::new("jane").scope(async { user() }).await;
+                (inner, user())
+            })
+            .await;
+        assert_eq!(users, (Some("jane".to_string()), Some("john".to_string())));
+        assert!(Principal::current().is_none());
+    }
+}
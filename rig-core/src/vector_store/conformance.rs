@@ -0,0 +1,362 @@
+//! Conformance tests of vector store integrations.
+//!
+//! The behavior documented by [VectorStoreIndex] and [DocumentSink] is the contract of the
+//! vector store integrations, whether they are maintained in this repository or out-of-tree.
+//! The generic checks of this module verify that contract against a store, and are meant to be
+//! run by the tests of the integrations:
+//! 1. the documents of [embeddings] (embedded by the [ConformanceModel]) are inserted into an
+//!    empty store, along with their metadata if the store supports it,
+//! 2. the checks are run against the index of the store, which embeds the queries with the
+//!    [ConformanceModel].
+//!
+//! The checks panic with a description of the broken contract, like the assertions of a test.
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::conformance::{self, ConformanceModel};
+//!
+//! #[tokio::test]
+//! async fn conformance() {
+//!     let store = MyStore::connect("my-test-collection").await.unwrap();
+//!     store.insert(conformance::embeddings()).await.unwrap();
+//!
+//!     let index = store.index(ConformanceModel);
+//!     conformance::check_index(&index).await;
+//!     // For stores supporting metadata filters
+//!     conformance::check_filters(&index).await;
+//! }
+//! ```
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{events::DocumentSink, Filter, VectorStoreIndex, VectorStoreIndexDyn};
+use crate::{
+    embeddings::{Embed, EmbedError, Embedding, EmbeddingError, EmbeddingModel, TextEmbedder},
+    OneOrMany,
+};
+
+/// Keywords of the embeddings of the [ConformanceModel], one per dimension
+const KEYWORDS: [&str; 6] = ["cat", "dog", "rust", "python", "paris", "tokyo"];
+
+/// Document of the conformance tests. Its fields are also its metadata (see [embeddings]).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConformanceDocument {
+    pub id: String,
+    pub text: String,
+    pub topic: String,
+    pub year: u32,
+}
+
+impl Embed for ConformanceDocument {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        embedder.embed(self.text.clone());
+        Ok(())
+    }
+}
+
+/// Documents of the conformance tests, with distinct embeddings
+pub fn documents() -> Vec<ConformanceDocument> {
+    [
+        ("cat", "The cat sleeps on the sofa", "animals", 2019),
+        (
+            "dog",
+            "The dog and the cat play in the garden",
+            "animals",
+            2021,
+        ),
+        (
+            "rust",
+            "Rust is a systems programming language",
+            "programming",
+            2015,
+        ),
+        (
+            "python",
+            "Python and Rust are programming languages",
+            "programming",
+            2022,
+        ),
+        ("paris", "Paris is the capital of France", "travel", 2018),
+        ("tokyo", "Tokyo and Paris are large cities", "travel", 2023),
+    ]
+    .into_iter()
+    .map(|(id, text, topic, year)| ConformanceDocument {
+        id: id.to_string(),
+        text: text.to_string(),
+        topic: topic.to_string(),
+        year,
+    })
+    .collect()
+}
+
+/// Ids, documents, metadata (the fields of the documents) and embeddings of the [documents], to
+/// insert into the store under test.
+pub fn embeddings() -> Vec<(String, ConformanceDocument, Value, OneOrMany<Embedding>)> {
+    documents()
+        .into_iter()
+        .map(|document| {
+            let metadata = json!({"topic": document.topic, "year": document.year});
+            let embedding = ConformanceModel.embed(&document.text);
+            (
+                document.id.clone(),
+                document,
+                metadata,
+                OneOrMany::one(embedding),
+            )
+        })
+        .collect()
+}
+
+/// Embedding model of the conformance tests, embedding texts by the keywords they contain.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConformanceModel;
+
+impl ConformanceModel {
+    fn embed(&self, text: &str) -> Embedding {
+        let text = text.to_lowercase();
+        Embedding {
+            vec: KEYWORDS
+                .iter()
+                .map(|keyword| text.matches(keyword).count() as f64 + 0.01)
+                .collect(),
+            document: text,
+        }
+    }
+}
+
+impl EmbeddingModel for ConformanceModel {
+    const MAX_DOCUMENTS: usize = 16;
+
+    fn ndims(&self) -> usize {
+        KEYWORDS.len()
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        Ok(texts.into_iter().map(|text| self.embed(&text)).collect())
+    }
+}
+
+fn ids<T>(results: &[(f64, String, T)]) -> Vec<&str> {
+    results.iter().map(|(_, id, _)| id.as_str()).collect()
+}
+
+/// Check the searches of an index of a store containing the documents of [embeddings]:
+/// - results are ordered by decreasing score (the best match first) and limited to `n`,
+/// - queries return all the documents when `n` exceeds their number,
+/// - documents are returned as inserted, with their ids,
+/// - `top_n_ids` returns the same results as `top_n`, and so does the dynamic
+///   ([VectorStoreIndexDyn]) interface.
+pub async fn check_index<I: VectorStoreIndex>(index: &I) {
+    let results = index
+        .top_n::<ConformanceDocument>("cat", 2)
+        .await
+        .expect("top_n should succeed");
+    assert_eq!(
+        ids(&results),
+        vec!["cat", "dog"],
+        "top_n should return the n best matches, best first"
+    );
+    assert!(
+        results[0].0 > results[1].0,
+        "Scores should be higher for better matches: {results:?}"
+    );
+    let expected = documents();
+    for (_, id, document) in &results {
+        assert_eq!(
+            Some(document),
+            expected.iter().find(|expected| &expected.id == id),
+            "top_n should return the documents as inserted"
+        );
+    }
+
+    let results = index
+        .top_n::<ConformanceDocument>("Tokyo", 100)
+        .await
+        .expect("top_n should succeed");
+    assert_eq!(
+        results.len(),
+        expected.len(),
+        "top_n should return all the documents when n exceeds their number"
+    );
+    assert_eq!(ids(&results)[0], "tokyo");
+    assert!(
+        results.windows(2).all(|pair| pair[0].0 >= pair[1].0),
+        "top_n should return the results by decreasing score: {results:?}"
+    );
+
+    let results = index
+        .top_n::<ConformanceDocument>("rust", 2)
+        .await
+        .expect("top_n should succeed");
+    let id_results = index
+        .top_n_ids("rust", 2)
+        .await
+        .expect("top_n_ids should succeed");
+    assert_eq!(
+        id_results
+            .iter()
+            .map(|(_, id)| id.as_str())
+            .collect::<Vec<_>>(),
+        ids(&results),
+        "top_n_ids should return the ids of the results of top_n"
+    );
+    for ((score, _, _), (id_score, _)) in results.iter().zip(&id_results) {
+        assert!(
+            (score - id_score).abs() < 1e-6,
+            "top_n_ids should return the scores of top_n"
+        );
+    }
+
+    let dyn_index: &dyn VectorStoreIndexDyn = index;
+    let dyn_results = dyn_index
+        .top_n("rust", 2)
+        .await
+        .expect("top_n should succeed through VectorStoreIndexDyn");
+    assert_eq!(ids(&results), vec!["rust", "python"]);
+    assert_eq!(
+        ids(&dyn_results),
+        ids(&results),
+        "VectorStoreIndexDyn should return the results of top_n"
+    );
+}
+
+/// Check the metadata filters of an index of a store containing the documents of [embeddings]:
+/// only the documents whose metadata matches the filter are returned, with `top_n_with_filter`
+/// as with `top_n_ids_with_filter`.
+pub async fn check_filters<I: VectorStoreIndex>(index: &I) {
+    let check = |filter: Filter, query: &'static str, expected: Vec<&'static str>| async move {
+        let results = index
+            .top_n_with_filter::<ConformanceDocument>(query, 10, &filter)
+            .await
+            .expect("top_n_with_filter should succeed");
+        let mut actual = ids(&results);
+        actual.sort();
+        assert_eq!(actual, expected, "Results of {filter:?}");
+
+        let results = index
+            .top_n_ids_with_filter(query, 10, &filter)
+            .await
+            .expect("top_n_ids_with_filter should succeed");
+        let mut actual = results
+            .iter()
+            .map(|(_, id)| id.as_str())
+            .collect::<Vec<_>>();
+        actual.sort();
+        assert_eq!(actual, expected, "Ids of the results of {filter:?}");
+    };
+
+    check(Filter::eq("topic", "animals"), "Paris", vec!["cat", "dog"]).await;
+    check(
+        Filter::is_in("topic", vec![json!("travel"), json!("programming")]),
+        "cat",
+        vec!["paris", "python", "rust", "tokyo"],
+    )
+    .await;
+    check(
+        Filter::gte("year", 2021),
+        "rust",
+        vec!["dog", "python", "tokyo"],
+    )
+    .await;
+    check(
+        Filter::eq("topic", "travel").and(Filter::lt("year", 2020)),
+        "Tokyo",
+        vec!["paris"],
+    )
+    .await;
+    check(
+        Filter::eq("topic", "animals").or(Filter::eq("year", 2015)),
+        "Tokyo",
+        vec!["cat", "dog", "rust"],
+    )
+    .await;
+    check(Filter::eq("topic", "cooking"), "cat", vec![]).await;
+}
+
+/// Check the writes of a [DocumentSink], starting from an empty store, using `index` (an index
+/// of the same store) to check their results:
+/// - upserted documents are searchable (once the sink is flushed),
+/// - upserting a document with the id of an existing one replaces it,
+/// - deleted documents are no longer returned, and deleting unknown ids succeeds.
+pub async fn check_sink<S, I>(sink: &S, index: &I)
+where
+    S: DocumentSink<ConformanceDocument>,
+    I: VectorStoreIndex,
+{
+    sink.upsert_documents(embeddings())
+        .await
+        .expect("upsert_documents should succeed");
+    sink.flush().await.expect("flush should succeed");
+    let results = index
+        .top_n_ids("Paris", 100)
+        .await
+        .expect("top_n_ids should succeed");
+    assert_eq!(
+        results.len(),
+        documents().len(),
+        "Upserted documents should be searchable"
+    );
+
+    // Replace the document about Paris
+    let mut document = documents().remove(4);
+    document.text = "Paris has a dog park".to_string();
+    let embedding = ConformanceModel.embed(&document.text);
+    sink.upsert_documents(vec![(
+        document.id.clone(),
+        document.clone(),
+        json!({"topic": document.topic, "year": document.year}),
+        OneOrMany::one(embedding),
+    )])
+    .await
+    .expect("upsert_documents should succeed");
+    sink.flush().await.expect("flush should succeed");
+    let results = index
+        .top_n::<ConformanceDocument>("dog", 100)
+        .await
+        .expect("top_n should succeed");
+    assert_eq!(
+        results.len(),
+        documents().len(),
+        "Upserting an existing id should replace the document"
+    );
+    let (_, _, replaced) = results
+        .iter()
+        .find(|(_, id, _)| id == "paris")
+        .expect("The replaced document should be searchable");
+    assert_eq!(replaced, &document);
+
+    sink.delete_documents(vec!["cat".to_string(), "unknown".to_string()])
+        .await
+        .expect("delete_documents should succeed, ignoring unknown ids");
+    sink.flush().await.expect("flush should succeed");
+    let results = index
+        .top_n_ids("cat", 100)
+        .await
+        .expect("top_n_ids should succeed");
+    assert!(
+        results.iter().all(|(_, id)| id != "cat"),
+        "Deleted documents should not be returned"
+    );
+    assert_eq!(results.len(), documents().len() - 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::in_memory_store::InMemoryVectorStore;
+
+    #[tokio::test]
+    async fn test_in_memory_conformance() {
+        let mut store = InMemoryVectorStore::default();
+        store.add_documents_with_metadata(embeddings());
+        let index = store.index(ConformanceModel);
+        check_index(&index).await;
+        check_filters(&index).await;
+
+        let index = InMemoryVectorStore::default().shared_index(ConformanceModel);
+        check_sink(&index, &index).await;
+    }
+}
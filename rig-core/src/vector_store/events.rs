@@ -0,0 +1,453 @@
+//! Continuous ingestion of documents into vector stores.
+//!
+//! Sources of documents (e.g.: topics of message brokers such as Kafka or NATS, database change
+//! streams, webhooks, ...) describe the creation, update and deletion of documents with
+//! [DocumentEvent]s. An [EventIngestor] consumes a stream of such events, embeds the created and
+//! updated documents and applies the changes to a [DocumentSink] (e.g.: a
+//! [SharedInMemoryIndex]), keeping the vector store in sync with the source while it is used.
+//!
+//! Writes to stores for which each request is costly can be batched with a [BufferedSink]. Its
+//! buffered writes are only written by [DocumentSink::flush], which [EventIngestor::run] calls
+//! when the stream of events ends: writes still buffered when a [BufferedSink] is dropped are
+//! lost.
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::{events::EventIngestor, in_memory_store::InMemoryVectorStore};
+//!
+//! let index = InMemoryVectorStore::<String>::default().shared_index(model.clone());
+//! let agent = openai.agent("gpt-4o").dynamic_context(3, index.clone()).build();
+//!
+//! // Keep the index in sync with the events of the source, e.g. in a background task
+//! tokio::spawn(async move { EventIngestor::new(model, index).run(events).await });
+//! ```
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::pin,
+    sync::{Mutex, PoisonError},
+};
+
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{in_memory_store::SharedInMemoryIndex, VectorStoreError};
+use crate::{
+    embeddings::{
+        Embed, EmbedError, Embedding, EmbeddingError, EmbeddingModel, EmbeddingsBuilder,
+        TextEmbedder,
+    },
+    OneOrMany,
+};
+
+/// Change of a document of a source, identified by its id.
+///
+/// Serialized with a `type` tag, e.g.:
+/// `{"type": "created", "id": "doc1", "document": "...", "metadata": {"lang": "en"}}` or
+/// `{"type": "deleted", "id": "doc1"}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DocumentEvent<D> {
+    Created {
+        id: String,
+        document: D,
+        #[serde(default)]
+        metadata: Value,
+    },
+    Updated {
+        id: String,
+        document: D,
+        #[serde(default)]
+        metadata: Value,
+    },
+    Deleted {
+        id: String,
+    },
+}
+
+impl<D> DocumentEvent<D> {
+    /// Id of the document of the event
+    pub fn id(&self) -> &str {
+        match self {
+            DocumentEvent::Created { id, .. }
+            | DocumentEvent::Updated { id, .. }
+            | DocumentEvent::Deleted { id } => id,
+        }
+    }
+}
+
+/// Trait for vector stores to which documents can be added and from which they can be deleted
+/// while they are used. Besides the [EventIngestor], the [IndexSync](super::sync::IndexSync)
+/// writes to such stores to re-index a corpus incrementally.
+pub trait DocumentSink<D>: Send + Sync {
+    /// Add documents, along with their JSON metadata and embeddings, to the store, replacing
+    /// the documents with the same ids.
+    fn upsert_documents(
+        &self,
+        documents: Vec<(String, D, Value, OneOrMany<Embedding>)>,
+    ) -> impl Future<Output = Result<(), VectorStoreError>> + Send;
+
+    /// Delete the documents with the given ids from the store, ignoring unknown ids.
+    fn delete_documents(
+        &self,
+        ids: Vec<String>,
+    ) -> impl Future<Output = Result<(), VectorStoreError>> + Send;
+
+    /// Write the changes buffered by the store, for stores that buffer their writes (does
+    /// nothing by default).
+    fn flush(&self) -> impl Future<Output = Result<(), VectorStoreError>> + Send {
+        async { Ok(()) }
+    }
+}
+
+/// Pending write of a document: its new version, or `None` if it is deleted
+type PendingWrite<D> = Option<(D, Value, OneOrMany<Embedding>)>;
+
+/// [DocumentSink] buffering the changes of documents, and writing them to its inner sink in
+/// batches of up to `capacity` documents (only the latest change of each document is written).
+///
+/// The buffered changes are written when the buffer is full or when [DocumentSink::flush] is
+/// called: since the flush is asynchronous, it cannot happen when the sink is dropped, and the
+/// changes still buffered at that point are discarded (with a warning).
+pub struct BufferedSink<S, D> {
+    sink: S,
+    capacity: usize,
+    pending: Mutex<HashMap<String, PendingWrite<D>>>,
+}
+
+impl<S, D> BufferedSink<S, D> {
+    pub fn new(sink: S, capacity: usize) -> Self {
+        Self {
+            sink,
+            capacity: capacity.max(1),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of documents whose changes are buffered
+    pub fn pending(&self) -> usize {
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Buffer the changes, returning whether the buffer is full
+    fn buffer(&self, writes: impl IntoIterator<Item = (String, PendingWrite<D>)>) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        pending.extend(writes);
+        pending.len() >= self.capacity
+    }
+}
+
+impl<S: DocumentSink<D>, D: Send> DocumentSink<D> for BufferedSink<S, D> {
+    async fn upsert_documents(
+        &self,
+        documents: Vec<(String, D, Value, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        let writes = documents
+            .into_iter()
+            .map(|(id, document, metadata, embeddings)| {
+                (id, Some((document, metadata, embeddings)))
+            });
+        if self.buffer(writes) {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_documents(&self, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        if self.buffer(ids.into_iter().map(|id| (id, None))) {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered changes to the inner sink, then flush it. The changes of a failed
+    /// flush are discarded.
+    async fn flush(&self) -> Result<(), VectorStoreError> {
+        let pending =
+            std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+
+        let (mut upserts, mut deletes) = (vec![], vec![]);
+        for (id, write) in pending {
+            match write {
+                Some((document, metadata, embeddings)) => {
+                    upserts.push((id, document, metadata, embeddings))
+                }
+                None => deletes.push(id),
+            }
+        }
+        if !upserts.is_empty() {
+            self.sink.upsert_documents(upserts).await?;
+        }
+        if !deletes.is_empty() {
+            self.sink.delete_documents(deletes).await?;
+        }
+        self.sink.flush().await
+    }
+}
+
+impl<S, D> Drop for BufferedSink<S, D> {
+    fn drop(&mut self) {
+        let pending = self.pending();
+        if pending > 0 {
+            tracing::warn!(target: "rig",
+                "BufferedSink dropped with the changes of {pending} documents not flushed, \
+                which are discarded"
+            );
+        }
+    }
+}
+
+impl<M: EmbeddingModel, D: Serialize + Eq + Send + Sync> DocumentSink<D>
+    for SharedInMemoryIndex<M, D>
+{
+    async fn upsert_documents(
+        &self,
+        documents: Vec<(String, D, Value, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.write().add_documents_with_metadata(documents);
+        Ok(())
+    }
+
+    async fn delete_documents(&self, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        let mut store = self.write();
+        for id in ids {
+            store.delete(&id);
+        }
+        Ok(())
+    }
+}
+
+/// Document to embed, along with its id and metadata
+pub(super) struct PendingDocument<D> {
+    pub(super) id: String,
+    pub(super) document: D,
+    pub(super) metadata: Value,
+}
+
+impl<D: Embed> Embed for PendingDocument<D> {
+    fn embed(&self, embedder: &mut TextEmbedder) -> Result<(), EmbedError> {
+        self.document.embed(embedder)
+    }
+}
+
+/// [EventIngestor] applies [DocumentEvent]s to a [DocumentSink], embedding the created and
+/// updated documents with an embedding model.
+pub struct EventIngestor<M: EmbeddingModel, S> {
+    model: M,
+    sink: S,
+    batch_size: usize,
+}
+
+impl<M: EmbeddingModel, S> EventIngestor<M, S> {
+    pub fn new(model: M, sink: S) -> Self {
+        Self {
+            model,
+            sink,
+            batch_size: M::MAX_DOCUMENTS,
+        }
+    }
+
+    /// Set the maximum number of events applied together by [EventIngestor::run] (defaults to
+    /// the maximum number of documents embedded in a single request by the model). The events
+    /// available at once are applied together, without waiting for more events.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Apply a batch of events to the sink. When several events of the batch concern the same
+    /// document, only the last one is applied.
+    pub async fn apply<D: Embed + Send>(
+        &self,
+        events: Vec<DocumentEvent<D>>,
+    ) -> Result<(), VectorStoreError>
+    where
+        S: DocumentSink<D>,
+    {
+        let mut latest = HashMap::new();
+        for event in events {
+            latest.insert(event.id().to_string(), event);
+        }
+
+        let (mut upserts, mut deletes) = (vec![], vec![]);
+        for event in latest.into_values() {
+            match event {
+                DocumentEvent::Created {
+                    id,
+                    document,
+                    metadata,
+                }
+                | DocumentEvent::Updated {
+                    id,
+                    document,
+                    metadata,
+                } => upserts.push(PendingDocument {
+                    id,
+                    document,
+                    metadata,
+                }),
+                DocumentEvent::Deleted { id } => deletes.push(id),
+            }
+        }
+
+        if !upserts.is_empty() {
+            let embeddings = EmbeddingsBuilder::new(self.model.clone())
+                .documents(upserts)
+                .map_err(|e| EmbeddingError::DocumentError(Box::new(e)))?
+                .build()
+                .await?;
+            self.sink
+                .upsert_documents(
+                    embeddings
+                        .into_iter()
+                        .map(|(pending, embeddings)| {
+                            (pending.id, pending.document, pending.metadata, embeddings)
+                        })
+                        .collect(),
+                )
+                .await?;
+        }
+        if !deletes.is_empty() {
+            self.sink.delete_documents(deletes).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply the events of `events` to the sink as they arrive, in batches (see
+    /// [EventIngestor::batch_size]), until the stream ends or fails. Returns the number of
+    /// events received.
+    ///
+    /// The sink is [flushed](DocumentSink::flush) once the stream ends, or after applying the
+    /// events received before an error of the stream.
+    pub async fn run<D, E>(
+        &self,
+        events: impl Stream<Item = Result<DocumentEvent<D>, E>>,
+    ) -> Result<usize, VectorStoreError>
+    where
+        D: Embed + Send,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+        S: DocumentSink<D>,
+    {
+        let mut batches = pin!(events.ready_chunks(self.batch_size));
+        let mut count = 0;
+        while let Some(batch) = batches.next().await {
+            let mut events = Vec::with_capacity(batch.len());
+            let mut error = None;
+            for event in batch {
+                match event {
+                    Ok(event) => events.push(event),
+                    Err(e) => {
+                        error = Some(e);
+                        break;
+                    }
+                }
+            }
+
+            count += events.len();
+            self.apply(events).await?;
+            if let Some(e) = error {
+                self.sink.flush().await?;
+                return Err(VectorStoreError::DatastoreError(e.into()));
+            }
+        }
+        self.sink.flush().await?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        providers::mock::MockEmbeddingModel,
+        vector_store::{in_memory_store::InMemoryVectorStore, VectorStoreIndex},
+    };
+
+    fn created(id: &str, document: &str) -> Result<DocumentEvent<String>, std::io::Error> {
+        Ok(DocumentEvent::Created {
+            id: id.into(),
+            document: document.into(),
+            metadata: Value::Null,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_event_ingestor() {
+        let model = MockEmbeddingModel::keywords(&["cat", "dog", "fish"]);
+        let index = InMemoryVectorStore::<String>::default().shared_index(model.clone());
+        let ingestor = EventIngestor::new(model, index.clone()).batch_size(2);
+
+        let events: Vec<DocumentEvent<String>> = serde_json::from_value(json!([
+            {"type": "created", "id": "a", "document": "About cats", "metadata": {"lang": "en"}},
+            {"type": "updated", "id": "a", "document": "About dogs"},
+            {"type": "deleted", "id": "b"},
+        ]))
+        .unwrap();
+        let events = [created("b", "About fish"), created("c", "About fish")]
+            .into_iter()
+            .chain(events.into_iter().map(Ok));
+        assert_eq!(
+            ingestor.run(futures::stream::iter(events)).await.unwrap(),
+            5
+        );
+
+        let mut ids = index
+            .read()
+            .iter()
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert_eq!(index.read().get_metadata("a"), Some(&Value::Null));
+
+        let results = index.top_n::<String>("my dog", 1).await.unwrap();
+        assert_eq!(results[0].1, "a");
+        assert_eq!(results[0].2, "About dogs");
+
+        // Events received before an error of the stream are applied
+        let events = vec![
+            created("d", "About cats"),
+            Err(std::io::Error::other("Connection lost")),
+            created("e", "About cats"),
+        ];
+        assert!(matches!(
+            ingestor.run(futures::stream::iter(events)).await,
+            Err(VectorStoreError::DatastoreError(_))
+        ));
+        assert_eq!(index.read().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_buffered_sink() {
+        let model = MockEmbeddingModel::keywords(&["cat", "dog", "fish"]);
+        let index = InMemoryVectorStore::<String>::default().shared_index(model.clone());
+        let sink = BufferedSink::new(index.clone(), 3);
+        let embedding = || OneOrMany::one(Embedding::default());
+        let upsert = |id: &str| (id.to_string(), id.to_string(), Value::Null, embedding());
+
+        // The changes are written once the buffer is full
+        sink.upsert_documents(vec![upsert("a"), upsert("b")])
+            .await
+            .unwrap();
+        sink.delete_documents(vec!["b".to_string()]).await.unwrap();
+        assert_eq!((sink.pending(), index.read().len()), (2, 0));
+        sink.upsert_documents(vec![upsert("c")]).await.unwrap();
+        assert_eq!((sink.pending(), index.read().len()), (0, 2));
+        assert!(index.read().get_document::<String>("b").unwrap().is_none());
+
+        // The ingestor flushes the tail of the events
+        let ingestor = EventIngestor::new(model, sink).batch_size(2);
+        let events = vec![created("d", "About cats"), created("e", "About dogs")];
+        assert_eq!(
+            ingestor.run(futures::stream::iter(events)).await.unwrap(),
+            2
+        );
+        assert_eq!(index.read().len(), 4);
+    }
+}
@@ -0,0 +1,179 @@
+//! Fallback between indexes, e.g. to keyword search when the embedding provider is unavailable.
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::{fallback::FallbackIndex, in_memory_store::InMemoryVectorStore, keyword::Bm25Index};
+//!
+//! let store = InMemoryVectorStore::from_documents(embeddings);
+//! let keyword_index = Bm25Index::from_store(&store);
+//!
+//! // Answer from the keyword index if the query cannot be embedded
+//! let index = FallbackIndex::new(store.index(embedding_model), keyword_index);
+//! let agent = openai.agent("gpt-4o").dynamic_context(5, index).build();
+//! ```
+use serde::Deserialize;
+
+use super::{Filter, VectorStoreError, VectorStoreIndex};
+
+/// [FallbackIndex] searches its primary index, and its fallback index (e.g.: a
+/// [Bm25Index](super::keyword::Bm25Index) of the same documents) if the primary search fails,
+/// e.g.: because the query could not be embedded. The error of the primary search is logged,
+/// and the error of the fallback search is returned if both fail.
+pub struct FallbackIndex<P: VectorStoreIndex, F: VectorStoreIndex> {
+    primary: P,
+    fallback: F,
+    on_empty: bool,
+}
+
+impl<P: VectorStoreIndex, F: VectorStoreIndex> FallbackIndex<P, F> {
+    pub fn new(primary: P, fallback: F) -> Self {
+        Self {
+            primary,
+            fallback,
+            on_empty: false,
+        }
+    }
+
+    /// Also search the fallback index when the primary search returns no results (e.g.: if all
+    /// the results of the primary index are below a minimum score).
+    pub fn on_empty(mut self, on_empty: bool) -> Self {
+        self.on_empty = on_empty;
+        self
+    }
+
+    /// Whether the primary `results` call for the fallback search
+    fn should_fall_back<R>(&self, results: &Result<Vec<R>, VectorStoreError>) -> bool {
+        match results {
+            Ok(results) => self.on_empty && results.is_empty(),
+            Err(e) => {
+                tracing::warn!(target: "rig", "Primary search failed, searching the fallback index: {e}");
+                true
+            }
+        }
+    }
+}
+
+impl<P: VectorStoreIndex, F: VectorStoreIndex> VectorStoreIndex for FallbackIndex<P, F> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let results = self.primary.top_n(query, n).await;
+        match self.should_fall_back(&results) {
+            true => self.fallback.top_n(query, n).await,
+            false => results,
+        }
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let results = self.primary.top_n_ids(query, n).await;
+        match self.should_fall_back(&results) {
+            true => self.fallback.top_n_ids(query, n).await,
+            false => results,
+        }
+    }
+
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let results = self.primary.top_n_with_filter(query, n, filter).await;
+        match self.should_fall_back(&results) {
+            true => self.fallback.top_n_with_filter(query, n, filter).await,
+            false => results,
+        }
+    }
+
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let results = self.primary.top_n_ids_with_filter(query, n, filter).await;
+        match self.should_fall_back(&results) {
+            true => self.fallback.top_n_ids_with_filter(query, n, filter).await,
+            false => results,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FallbackIndex;
+    use crate::{
+        embeddings::EmbeddingError,
+        vector_store::{keyword::Bm25Index, Filter, VectorStoreError, VectorStoreIndex},
+    };
+
+    /// Index whose searches fail, like an index whose embedding provider is unavailable, or
+    /// return nothing
+    struct DownIndex(bool);
+
+    impl VectorStoreIndex for DownIndex {
+        async fn top_n<T: for<'a> serde::Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            match self.0 {
+                true => Err(EmbeddingError::ProviderError("unavailable".into()).into()),
+                false => Ok(vec![]),
+            }
+        }
+
+        async fn top_n_ids(
+            &self,
+            _query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            match self.0 {
+                true => Err(EmbeddingError::ProviderError("unavailable".into()).into()),
+                false => Ok(vec![]),
+            }
+        }
+    }
+
+    fn keyword_index() -> Bm25Index<String> {
+        Bm25Index::from_documents(vec![
+            (
+                "doc1",
+                "Reset your password".to_string(),
+                "doc1".to_string(),
+            ),
+            ("doc2", "Change your email".to_string(), "doc2".to_string()),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_fallback_index() {
+        let index = FallbackIndex::new(DownIndex(true), keyword_index());
+        let results = index.top_n::<String>("password", 2).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].2, "doc1");
+
+        // The filters are applied by the fallback index
+        let filter = Filter::eq("lang", "en");
+        assert!(index
+            .top_n_ids_with_filter("password", 2, &filter)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Empty results only fall back when enabled
+        let index = FallbackIndex::new(DownIndex(false), keyword_index());
+        assert!(index.top_n_ids("email", 2).await.unwrap().is_empty());
+        let index = index.on_empty(true);
+        assert_eq!(
+            index.top_n_ids("email", 2).await.unwrap()[0].1,
+            "doc2".to_string()
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Search across several vector store indexes in one call.
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::federated::FederatedIndex;
+//!
+//! // Indexes of different backends, embedded with different models
+//! let index = FederatedIndex::new()
+//!     .index("docs", docs_store.index(openai_embedding_model))
+//!     .weighted_index("tickets", qdrant_index, 0.5);
+//!
+//! // Returns ids such as "docs:getting-started" and "tickets:1234"
+//! let results = index.top_n_ids("How do I reset my password?", 5).await?;
+//! ```
+use futures::future::try_join_all;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{Filter, VectorStoreError, VectorStoreIndex, VectorStoreIndexDyn};
+
+/// Normalization of the scores of each index of a [FederatedIndex], making the scores of
+/// different backends and embedding models comparable before merging them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// Scale the scores of each index to `[0, 1]`, from its worst to its best result (results
+    /// with the same score as the best are scored 1)
+    #[default]
+    MinMax,
+    /// Standard score of the results of each index, i.e.: the number of standard deviations
+    /// from the mean score of its results
+    ZScore,
+    /// Keep the raw scores, e.g. for indexes using the same embedding model and distance
+    None,
+}
+
+impl Normalization {
+    fn normalize(&self, scores: &mut [f64]) {
+        let len = scores.len() as f64;
+        match self {
+            Self::MinMax => {
+                let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                scores.iter_mut().for_each(|score| {
+                    *score = if max > min {
+                        (*score - min) / (max - min)
+                    } else {
+                        1.0
+                    }
+                });
+            }
+            Self::ZScore => {
+                let mean = scores.iter().sum::<f64>() / len;
+                let deviation = (scores
+                    .iter()
+                    .map(|score| (score - mean).powi(2))
+                    .sum::<f64>()
+                    / len)
+                    .sqrt();
+                scores.iter_mut().for_each(|score| {
+                    *score = if deviation > 0.0 {
+                        (*score - mean) / deviation
+                    } else {
+                        0.0
+                    }
+                });
+            }
+            Self::None => {}
+        }
+    }
+}
+
+/// Named index of a [FederatedIndex]
+struct Member {
+    name: String,
+    weight: f64,
+    index: Box<dyn VectorStoreIndexDyn>,
+}
+
+/// [FederatedIndex] queries several indexes (possibly of different vector stores, and
+/// embedded with different embedding models) concurrently, and merges their results.
+///
+/// The scores of each index are [normalized](Normalization) and multiplied by the weight of the
+/// index. The ids of the results are prefixed with the name of their index (e.g.: `docs:doc0`),
+/// since the ids of different indexes may collide.
+#[derive(Default)]
+pub struct FederatedIndex {
+    members: Vec<Member>,
+    normalization: Normalization,
+    skip_failures: bool,
+}
+
+impl FederatedIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an index, which prefixes the ids of its results with `name:`.
+    pub fn index(self, name: impl Into<String>, index: impl VectorStoreIndex + 'static) -> Self {
+        self.weighted_index(name, index, 1.0)
+    }
+
+    /// Add an index whose (normalized) scores are multiplied by `weight`.
+    pub fn weighted_index(
+        mut self,
+        name: impl Into<String>,
+        index: impl VectorStoreIndex + 'static,
+        weight: f64,
+    ) -> Self {
+        self.members.push(Member {
+            name: name.into(),
+            weight,
+            index: Box::new(index),
+        });
+        self
+    }
+
+    /// Set the normalization of the scores of each index (defaults to [Normalization::MinMax]).
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Skip the indexes whose search fails (with a warning), instead of failing the search.
+    pub fn skip_failures(mut self, skip: bool) -> Self {
+        self.skip_failures = skip;
+        self
+    }
+
+    /// Search each index with `search`, and merge the `n` best results.
+    async fn search<'a, R, F>(
+        &'a self,
+        n: usize,
+        search: impl Fn(&'a dyn VectorStoreIndexDyn) -> F,
+    ) -> Result<Vec<(f64, String, R)>, VectorStoreError>
+    where
+        F: std::future::Future<Output = Result<Vec<(f64, String, R)>, VectorStoreError>>,
+    {
+        let searches = self.members.iter().map(|member| {
+            let search = search(member.index.as_ref());
+            async move {
+                match search.await {
+                    Ok(results) => Ok(results),
+                    Err(e) if self.skip_failures => {
+                        tracing::warn!(target: "rig",
+                            "Skipping the results of index {}: {e}",
+                            member.name
+                        );
+                        Ok(vec![])
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        });
+
+        let mut merged = vec![];
+        for (member, results) in self.members.iter().zip(try_join_all(searches).await?) {
+            let mut scores = results
+                .iter()
+                .map(|(score, _, _)| *score)
+                .collect::<Vec<_>>();
+            self.normalization.normalize(&mut scores);
+            merged.extend(
+                results
+                    .into_iter()
+                    .zip(scores)
+                    .map(|((_, id, result), score)| {
+                        (
+                            member.weight * score,
+                            format!("{}:{id}", member.name),
+                            result,
+                        )
+                    }),
+            );
+        }
+
+        merged.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        merged.truncate(n);
+        Ok(merged)
+    }
+}
+
+/// Deserialize the documents of merged results
+fn deserialize<T: for<'a> Deserialize<'a>>(
+    results: Vec<(f64, String, Value)>,
+) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+    results
+        .into_iter()
+        .map(|(score, id, doc)| Ok((score, id, serde_json::from_value(doc)?)))
+        .collect()
+}
+
+/// Add an empty result to document ids, to merge them like documents
+fn with_unit(results: Vec<(f64, String)>) -> Vec<(f64, String, ())> {
+    results
+        .into_iter()
+        .map(|(score, id)| (score, id, ()))
+        .collect()
+}
+
+fn without_unit(results: Vec<(f64, String, ())>) -> Vec<(f64, String)> {
+    results
+        .into_iter()
+        .map(|(score, id, _)| (score, id))
+        .collect()
+}
+
+impl VectorStoreIndex for FederatedIndex {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        deserialize(self.search(n, |index| index.top_n(query, n)).await?)
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let results = self
+            .search(n, |index| async move {
+                Ok(with_unit(index.top_n_ids(query, n).await?))
+            })
+            .await?;
+        Ok(without_unit(results))
+    }
+
+    /// The filter is applied by each index: the search fails with
+    /// [VectorStoreError::FilterNotSupported] if some indexes do not support filters, unless
+    /// [FederatedIndex::skip_failures] is set.
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        deserialize(
+            self.search(n, |index| index.top_n_with_filter(query, n, filter))
+                .await?,
+        )
+    }
+
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let results = self
+            .search(n, |index| async move {
+                Ok(with_unit(
+                    index.top_n_ids_with_filter(query, n, filter).await?,
+                ))
+            })
+            .await?;
+        Ok(without_unit(results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::{FederatedIndex, Normalization};
+    use crate::vector_store::{Filter, VectorStoreError, VectorStoreIndex};
+
+    /// Index returning the given documents with their scores, whatever the query
+    struct ScoredIndex(Vec<(f64, &'static str)>);
+
+    impl VectorStoreIndex for ScoredIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            self.0
+                .iter()
+                .take(n)
+                .map(|(score, id)| {
+                    Ok((
+                        *score,
+                        id.to_string(),
+                        serde_json::from_value((*id).into())?,
+                    ))
+                })
+                .collect()
+        }
+
+        async fn top_n_ids(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Ok(self
+                .0
+                .iter()
+                .take(n)
+                .map(|(score, id)| (*score, id.to_string()))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_federated_index() {
+        // Cosine similarities vs. unbounded relevance scores
+        let index = FederatedIndex::new()
+            .index(
+                "docs",
+                ScoredIndex(vec![(1.0, "a"), (0.75, "b"), (0.5, "c")]),
+            )
+            .weighted_index(
+                "tickets",
+                ScoredIndex(vec![(12.0, "a"), (10.0, "x"), (2.0, "y")]),
+                0.5,
+            );
+
+        let results = index.top_n::<String>("query", 4).await.unwrap();
+        let ids = results
+            .iter()
+            .map(|(score, id, doc)| (*score, id.as_str(), doc.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                (1.0, "docs:a", "a"),
+                (0.5, "docs:b", "b"),
+                (0.5, "tickets:a", "a"),
+                (0.4, "tickets:x", "x")
+            ]
+        );
+
+        // Indexes without filter support fail the search, unless skipped
+        let filter = Filter::eq("lang", "en");
+        assert!(matches!(
+            index.top_n_ids_with_filter("query", 2, &filter).await,
+            Err(VectorStoreError::FilterNotSupported)
+        ));
+        let index = index.skip_failures(true);
+        assert!(index
+            .top_n_ids_with_filter("query", 2, &filter)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            index.top_n_ids("query", 1).await.unwrap(),
+            vec![(1.0, "docs:a".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_normalization() {
+        let mut scores = [3.0, 1.0, 2.0];
+        Normalization::ZScore.normalize(&mut scores);
+        let deviation = (2.0f64 / 3.0).sqrt();
+        assert_eq!(scores, [1.0 / deviation, -1.0 / deviation, 0.0]);
+
+        let mut scores = [0.5, 0.5];
+        Normalization::MinMax.normalize(&mut scores);
+        assert_eq!(scores, [1.0, 1.0]);
+    }
+}
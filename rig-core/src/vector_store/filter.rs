@@ -0,0 +1,192 @@
+//! Metadata filters for vector store searches.
+//!
+//! A [Filter] is an expression over the JSON metadata of the documents of a vector store,
+//! used to scope a search (e.g.: to a tenant, a source or a date range) with
+//! [VectorStoreIndex::top_n_with_filter](super::VectorStoreIndex::top_n_with_filter).
+//!
+//! Fields are referred to by their dot-separated path in the metadata (e.g.: `"source.name"`).
+//! Range comparisons apply to numbers and strings (e.g.: RFC 3339 dates), values of other
+//! types or of different types never match.
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::Filter;
+//! use serde_json::json;
+//!
+//! let filter = Filter::eq("tenant", "acme")
+//!     .and(Filter::is_in("source", vec![json!("wiki"), json!("faq")]))
+//!     .and(Filter::gte("date", "2024-01-01"));
+//!
+//! assert!(filter.matches(&json!({"tenant": "acme", "source": "faq", "date": "2024-03-12"})));
+//! ```
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Filter expression over the metadata of documents.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    /// The field is equal to the value
+    Eq(String, Value),
+    /// The field is equal to one of the values
+    In(String, Vec<Value>),
+    /// The field is an array containing the value
+    Contains(String, Value),
+    /// The field is greater than the value
+    Gt(String, Value),
+    /// The field is greater than or equal to the value
+    Gte(String, Value),
+    /// The field is less than the value
+    Lt(String, Value),
+    /// The field is less than or equal to the value
+    Lte(String, Value),
+    /// All the filters match (matches everything if empty)
+    And(Vec<Filter>),
+    /// Any of the filters matches (matches nothing if empty)
+    Or(Vec<Filter>),
+}
+
+impl Filter {
+    pub fn eq(field: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Eq(field.into(), value.into())
+    }
+
+    pub fn is_in(field: impl Into<String>, values: impl IntoIterator<Item = Value>) -> Self {
+        Self::In(field.into(), values.into_iter().collect())
+    }
+
+    pub fn contains(field: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Contains(field.into(), value.into())
+    }
+
+    pub fn gt(field: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Gt(field.into(), value.into())
+    }
+
+    pub fn gte(field: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Gte(field.into(), value.into())
+    }
+
+    pub fn lt(field: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Lt(field.into(), value.into())
+    }
+
+    pub fn lte(field: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Lte(field.into(), value.into())
+    }
+
+    /// Filter matching documents in the range `[min, max)` of the field.
+    pub fn range(field: impl Into<String>, min: impl Into<Value>, max: impl Into<Value>) -> Self {
+        let field = field.into();
+        Self::And(vec![Self::gte(field.clone(), min), Self::lt(field, max)])
+    }
+
+    /// Combine the filter with `other`, both having to match.
+    pub fn and(self, other: Filter) -> Self {
+        match self {
+            Self::And(mut filters) => {
+                filters.push(other);
+                Self::And(filters)
+            }
+            filter => Self::And(vec![filter, other]),
+        }
+    }
+
+    /// Combine the filter with `other`, either having to match.
+    pub fn or(self, other: Filter) -> Self {
+        match self {
+            Self::Or(mut filters) => {
+                filters.push(other);
+                Self::Or(filters)
+            }
+            filter => Self::Or(vec![filter, other]),
+        }
+    }
+
+    /// Whether the filter matches the given document metadata.
+    pub fn matches(&self, metadata: &Value) -> bool {
+        match self {
+            Self::Eq(field, value) => lookup(metadata, field) == Some(value),
+            Self::In(field, values) => {
+                lookup(metadata, field).is_some_and(|field| values.contains(field))
+            }
+            Self::Contains(field, value) => lookup(metadata, field)
+                .and_then(Value::as_array)
+                .is_some_and(|values| values.contains(value)),
+            Self::Gt(field, value) => compare(metadata, field, value, Ordering::is_gt),
+            Self::Gte(field, value) => compare(metadata, field, value, Ordering::is_ge),
+            Self::Lt(field, value) => compare(metadata, field, value, Ordering::is_lt),
+            Self::Lte(field, value) => compare(metadata, field, value, Ordering::is_le),
+            Self::And(filters) => filters.iter().all(|filter| filter.matches(metadata)),
+            Self::Or(filters) => filters.iter().any(|filter| filter.matches(metadata)),
+        }
+    }
+}
+
+/// Value of the field at the dot-separated `path` of `metadata`
+fn lookup<'a>(metadata: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(metadata, |value, key| value.as_object()?.get(key))
+}
+
+fn compare(metadata: &Value, field: &str, value: &Value, predicate: fn(Ordering) -> bool) -> bool {
+    let ordering = match (lookup(metadata, field), value) {
+        (Some(Value::Number(a)), Value::Number(b)) => a
+            .as_f64()
+            .zip(b.as_f64())
+            .and_then(|(a, b)| a.partial_cmp(&b)),
+        (Some(Value::String(a)), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    ordering.is_some_and(predicate)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Filter;
+
+    #[test]
+    fn test_filter_matches() {
+        let metadata = json!({
+            "tenant": "acme",
+            "source": {"name": "wiki", "pages": 12},
+            "date": "2024-03-12",
+            "tags": ["rust", "async"],
+        });
+
+        assert!(Filter::eq("tenant", "acme").matches(&metadata));
+        assert!(!Filter::eq("tenant", "globex").matches(&metadata));
+        assert!(Filter::is_in("source.name", vec![json!("faq"), json!("wiki")]).matches(&metadata));
+        assert!(Filter::gt("source.pages", 10).matches(&metadata));
+        assert!(!Filter::lte("source.pages", 11.5).matches(&metadata));
+        assert!(Filter::range("date", "2024-01-01", "2025-01-01").matches(&metadata));
+        assert!(!Filter::gt("date", 10).matches(&metadata));
+        assert!(!Filter::eq("missing.field", "x").matches(&metadata));
+        assert!(Filter::contains("tags", "rust").matches(&metadata));
+        assert!(!Filter::contains("tags", "python").matches(&metadata));
+        assert!(!Filter::contains("tenant", "acme").matches(&metadata));
+
+        assert!(Filter::eq("tenant", "globex")
+            .or(Filter::eq("source.name", "wiki"))
+            .matches(&metadata));
+        assert!(!Filter::eq("tenant", "acme")
+            .and(Filter::lt("date", "2024-01-01"))
+            .matches(&metadata));
+        assert!(Filter::And(vec![]).matches(&metadata));
+    }
+
+    #[test]
+    fn test_filter_serde() {
+        let filter = Filter::eq("tenant", "acme").and(Filter::gte("year", 2024));
+        let json = serde_json::to_value(&filter).unwrap();
+        assert_eq!(
+            json,
+            json!({"and": [{"eq": ["tenant", "acme"]}, {"gte": ["year", 2024]}]})
+        );
+        assert_eq!(serde_json::from_value::<Filter>(json).unwrap(), filter);
+    }
+}
@@ -0,0 +1,343 @@
+//! Hierarchical navigable small world (HNSW) graph, an approximate nearest neighbor index of the
+//! embeddings of an [InMemoryVectorStore].
+//!
+//! Searching the graph visits a few hundred embeddings instead of all of them, which makes the
+//! searches of large stores (e.g.: 100k+ documents) orders of magnitude faster, at the cost of
+//! sometimes missing some of the nearest documents. The trade-off between speed and recall is
+//! tuned with [HnswConfig].
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::{hnsw::HnswConfig, in_memory_store::InMemoryVectorStore};
+//!
+//! let store = InMemoryVectorStore::from_documents(embeddings)
+//!     .with_hnsw(HnswConfig::new().ef_search(100));
+//! let index = store.index(model);
+//! ```
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use ordered_float::OrderedFloat;
+
+use super::in_memory_store::DistanceMetric;
+#[cfg(doc)]
+use super::in_memory_store::InMemoryVectorStore;
+
+/// Parameters of an HNSW index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HnswConfig {
+    m: usize,
+    ef_construction: usize,
+    pub(crate) ef_search: usize,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 64,
+        }
+    }
+}
+
+impl HnswConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of neighbors of each embedding in the graph (twice as many in the bottom
+    /// layer). Larger values improve the recall at the cost of memory and of slower insertions.
+    /// Defaults to 16.
+    pub fn m(mut self, m: usize) -> Self {
+        self.m = m.max(2);
+        self
+    }
+
+    /// Set the number of candidate neighbors considered when inserting an embedding. Larger
+    /// values build a better graph, but slow down the insertions. Defaults to 100.
+    pub fn ef_construction(mut self, ef: usize) -> Self {
+        self.ef_construction = ef.max(1);
+        self
+    }
+
+    /// Set the number of candidates considered by the searches (at least the number of returned
+    /// documents). Larger values improve the recall of the searches, but slow them down.
+    /// Defaults to 64.
+    pub fn ef_search(mut self, ef: usize) -> Self {
+        self.ef_search = ef.max(1);
+        self
+    }
+}
+
+/// Embedding in the graph, i.e.: the embedding at `index` of the document `id`
+#[derive(Clone)]
+pub(crate) struct Node {
+    pub(crate) id: String,
+    pub(crate) index: usize,
+    vector: Vec<f64>,
+    /// Neighbors of the node in each of its layers
+    neighbors: Vec<Vec<usize>>,
+    /// Whether the document was removed from the store. Removed nodes are kept to navigate the
+    /// graph, but are not returned by the searches.
+    deleted: bool,
+}
+
+#[derive(Clone)]
+pub(crate) struct Hnsw {
+    config: HnswConfig,
+    metric: DistanceMetric,
+    nodes: Vec<Node>,
+    /// Nodes of each document
+    documents: HashMap<String, Vec<usize>>,
+    entry_point: Option<usize>,
+    deleted: usize,
+    rng: u64,
+}
+
+impl Hnsw {
+    pub(crate) fn new(config: HnswConfig, metric: DistanceMetric) -> Self {
+        Self {
+            config,
+            metric,
+            nodes: vec![],
+            documents: HashMap::new(),
+            entry_point: None,
+            deleted: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub(crate) fn config(&self) -> HnswConfig {
+        self.config
+    }
+
+    /// Number of nodes in the graph, including the removed ones
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn node(&self, node: usize) -> &Node {
+        &self.nodes[node]
+    }
+
+    /// Insert the embedding vectors of the document `id`, replacing its previous ones if any.
+    pub(crate) fn insert<'a>(&mut self, id: &str, vectors: impl IntoIterator<Item = &'a [f64]>) {
+        self.remove(id);
+        let nodes = vectors
+            .into_iter()
+            .enumerate()
+            .map(|(index, vector)| self.insert_node(id, index, vector.to_vec()))
+            .collect();
+        self.documents.insert(id.to_string(), nodes);
+    }
+
+    /// Remove the embeddings of the document `id` from the results of the searches. The graph is
+    /// rebuilt once most of its nodes are removed.
+    pub(crate) fn remove(&mut self, id: &str) {
+        let Some(nodes) = self.documents.remove(id) else {
+            return;
+        };
+        for node in nodes {
+            self.nodes[node].deleted = true;
+            self.deleted += 1;
+        }
+
+        if self.deleted > self.nodes.len() / 2 {
+            let nodes = std::mem::take(&mut self.nodes);
+            *self = Self {
+                rng: self.rng,
+                ..Self::new(self.config, self.metric)
+            };
+            let mut documents = HashMap::<_, Vec<_>>::new();
+            for node in nodes.into_iter().filter(|node| !node.deleted) {
+                let index = self.insert_node(&node.id, node.index, node.vector);
+                documents.entry(node.id).or_default().push(index);
+            }
+            self.documents = documents;
+        }
+    }
+
+    /// Nodes closest to `query` (from the closest), with their similarity scores. `ef` is the
+    /// number of candidates of the search, and of returned nodes (removed nodes included).
+    pub(crate) fn search(&self, query: &[f64], ef: usize) -> Vec<(f64, usize)> {
+        let Some(entry_point) = self.entry_point else {
+            return vec![];
+        };
+
+        let mut closest = entry_point;
+        for layer in (1..self.nodes[entry_point].neighbors.len()).rev() {
+            closest = self.search_layer(query, closest, 1, layer)[0].1;
+        }
+        self.search_layer(query, closest, ef.max(1), 0)
+            .into_iter()
+            .map(|(score, node)| (score.0, node))
+            .collect()
+    }
+
+    fn score(&self, query: &[f64], node: usize) -> OrderedFloat<f64> {
+        OrderedFloat(self.metric.score(query, &self.nodes[node].vector))
+    }
+
+    /// Random layer of a new node, with exponentially decreasing probabilities
+    fn random_level(&mut self) -> usize {
+        // xorshift64*
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let random = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        let uniform = (random as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() / (self.config.m as f64).ln()) as usize
+    }
+
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 {
+            2 * self.config.m
+        } else {
+            self.config.m
+        }
+    }
+
+    fn insert_node(&mut self, id: &str, index: usize, vector: Vec<f64>) -> usize {
+        let level = self.random_level();
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            id: id.to_string(),
+            index,
+            vector,
+            neighbors: vec![vec![]; level + 1],
+            deleted: false,
+        });
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(node);
+            return node;
+        };
+
+        let query = self.nodes[node].vector.clone();
+        let max_level = self.nodes[entry_point].neighbors.len() - 1;
+        let mut closest = entry_point;
+        for layer in (level + 1..=max_level).rev() {
+            closest = self.search_layer(&query, closest, 1, layer)[0].1;
+        }
+
+        for layer in (0..=level.min(max_level)).rev() {
+            let candidates = self.search_layer(&query, closest, self.config.ef_construction, layer);
+            closest = candidates[0].1;
+
+            let neighbors = candidates
+                .into_iter()
+                .take(self.max_neighbors(layer))
+                .map(|(_, neighbor)| neighbor)
+                .collect::<Vec<_>>();
+            for &neighbor in &neighbors {
+                self.connect(neighbor, node, layer);
+            }
+            self.nodes[node].neighbors[layer] = neighbors;
+        }
+
+        if level > max_level {
+            self.entry_point = Some(node);
+        }
+        node
+    }
+
+    /// Add `node` to the neighbors of `neighbor`, keeping its closest neighbors if it has too many
+    fn connect(&mut self, neighbor: usize, node: usize, layer: usize) {
+        let max_neighbors = self.max_neighbors(layer);
+        let mut neighbors = std::mem::take(&mut self.nodes[neighbor].neighbors[layer]);
+        neighbors.push(node);
+        if neighbors.len() > max_neighbors {
+            let vector = &self.nodes[neighbor].vector;
+            neighbors.sort_by_cached_key(|&other| Reverse(self.score(vector, other)));
+            neighbors.truncate(max_neighbors);
+        }
+        self.nodes[neighbor].neighbors[layer] = neighbors;
+    }
+
+    /// Greedy search of the `ef` nodes of `layer` closest to `query`, from the closest
+    fn search_layer(
+        &self,
+        query: &[f64],
+        entry_point: usize,
+        ef: usize,
+        layer: usize,
+    ) -> Vec<(OrderedFloat<f64>, usize)> {
+        let score = self.score(query, entry_point);
+        let mut visited = HashSet::from([entry_point]);
+        let mut candidates = BinaryHeap::from([(score, entry_point)]);
+        let mut results = BinaryHeap::from([Reverse((score, entry_point))]);
+
+        while let Some((score, node)) = candidates.pop() {
+            let Reverse((worst, _)) = results.peek().expect("results are not empty");
+            if score < *worst && results.len() >= ef {
+                break;
+            }
+
+            for &neighbor in &self.nodes[node].neighbors[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let score = self.score(query, neighbor);
+                let Reverse((worst, _)) = results.peek().expect("results are not empty");
+                if results.len() < ef || score > *worst {
+                    candidates.push((score, neighbor));
+                    results.push(Reverse((score, neighbor)));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        results
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(result)| result)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points of a 2D grid, with the coordinates as ids
+    fn grid(hnsw: &mut Hnsw, size: usize) {
+        for x in 0..size {
+            for y in 0..size {
+                hnsw.insert(&format!("{x},{y}"), [[x as f64, y as f64].as_slice()]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let mut hnsw = Hnsw::new(HnswConfig::new().m(4), DistanceMetric::Euclidean);
+        grid(&mut hnsw, 20);
+
+        let results = hnsw.search(&[3.2, 7.9], 10);
+        assert_eq!(results.len(), 10);
+        assert_eq!(hnsw.node(results[0].1).id, "3,8");
+        assert!(results.windows(2).all(|pair| pair[0].0 >= pair[1].0));
+
+        // Removed documents are skipped by the callers, and the graph is rebuilt when most of
+        // them are removed
+        hnsw.remove("3,8");
+        assert!(hnsw.node(hnsw.search(&[3.2, 7.9], 1)[0].1).deleted);
+        for x in 0..20 {
+            for y in 0..11 {
+                hnsw.remove(&format!("{x},{y}"));
+            }
+        }
+        assert!(hnsw.len() < 400);
+        let closest = hnsw
+            .search(&[3.2, 7.9], 40)
+            .into_iter()
+            .map(|(_, node)| hnsw.node(node))
+            .find(|node| !node.deleted)
+            .unwrap();
+        assert_eq!(closest.id, "3,11");
+    }
+}
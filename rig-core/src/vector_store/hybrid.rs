@@ -0,0 +1,423 @@
+//! Hybrid search combining a keyword index and a vector index.
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::{hybrid::HybridIndex, in_memory_store::InMemoryVectorStore, keyword::Bm25Index};
+//!
+//! let store = InMemoryVectorStore::from_documents(embeddings);
+//! let keyword_index = Bm25Index::from_store(&store);
+//! let index = HybridIndex::new(keyword_index, store.index(embedding_model));
+//!
+//! let agent = openai.agent("gpt-4o").dynamic_context(5, index).build();
+//! ```
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{Filter, SearchOptions, VectorStoreError, VectorStoreIndex};
+
+/// [HybridIndex] searches both a keyword index (e.g.: a [Bm25Index](super::keyword::Bm25Index))
+/// and a vector index and fuses their rankings with reciprocal rank fusion (RRF): the score of a
+/// document is the sum over both rankings of `weight / (k + rank)`, divided by the score of a
+/// document ranked first by both indexes, so that scores range from 0 to 1 (e.g.: for
+/// [SearchOptions::min_score]).
+///
+/// Both indexes must use the same document ids. Filters and search options are forwarded to
+/// both indexes, except the minimum score which applies to the fused scores.
+pub struct HybridIndex<K: VectorStoreIndex, V: VectorStoreIndex> {
+    keyword: K,
+    vector: V,
+    keyword_weight: f64,
+    vector_weight: f64,
+    rrf_k: f64,
+    candidates: Option<usize>,
+}
+
+impl<K: VectorStoreIndex, V: VectorStoreIndex> HybridIndex<K, V> {
+    pub fn new(keyword: K, vector: V) -> Self {
+        Self {
+            keyword,
+            vector,
+            keyword_weight: 1.0,
+            vector_weight: 1.0,
+            rrf_k: 60.0,
+            candidates: None,
+        }
+    }
+
+    /// Set the weights of the keyword and vector rankings (both default to 1).
+    pub fn weights(mut self, keyword: f64, vector: f64) -> Self {
+        self.keyword_weight = keyword;
+        self.vector_weight = vector;
+        self
+    }
+
+    /// Set the `k` constant of reciprocal rank fusion, dampening the impact of the top ranks
+    /// (defaults to 60).
+    pub fn rrf_k(mut self, k: f64) -> Self {
+        self.rrf_k = k;
+        self
+    }
+
+    /// Set the number of documents retrieved from each index before fusion (defaults to
+    /// 4 times the number of returned documents).
+    pub fn candidates(mut self, candidates: usize) -> Self {
+        self.candidates = Some(candidates);
+        self
+    }
+
+    fn candidates_for(&self, n: usize) -> usize {
+        self.candidates.unwrap_or(4 * n).max(n)
+    }
+
+    fn fuse<'a>(
+        &self,
+        keyword: impl IntoIterator<Item = &'a String>,
+        vector: impl IntoIterator<Item = &'a String>,
+        n: usize,
+    ) -> Vec<(f64, String)> {
+        let mut fused = reciprocal_rank_fusion(
+            [
+                (self.keyword_weight, keyword.into_iter().collect::<Vec<_>>()),
+                (self.vector_weight, vector.into_iter().collect::<Vec<_>>()),
+            ],
+            self.rrf_k,
+        );
+        fused.truncate(n);
+
+        let max_score = (self.keyword_weight + self.vector_weight) / (self.rrf_k + 1.0);
+        if max_score > 0.0 {
+            fused.iter_mut().for_each(|(score, _)| *score /= max_score);
+        }
+        fused
+    }
+
+    fn fuse_documents<T: for<'a> Deserialize<'a>>(
+        &self,
+        keyword: Vec<(f64, String, Value)>,
+        vector: Vec<(f64, String, Value)>,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let fused = self.fuse(
+            keyword.iter().map(|(_, id, _)| id),
+            vector.iter().map(|(_, id, _)| id),
+            n,
+        );
+        let mut documents = keyword
+            .into_iter()
+            .chain(vector)
+            .map(|(_, id, doc)| (id, doc))
+            .collect::<HashMap<_, _>>();
+
+        fused
+            .into_iter()
+            .map(|(score, id)| {
+                let doc = documents
+                    .remove(&id)
+                    .ok_or_else(|| VectorStoreError::MissingIdError(id.clone()))?;
+                Ok((score, id, serde_json::from_value(doc)?))
+            })
+            .collect()
+    }
+
+    fn fuse_ids(
+        &self,
+        keyword: Vec<(f64, String)>,
+        vector: Vec<(f64, String)>,
+        n: usize,
+    ) -> Vec<(f64, String)> {
+        self.fuse(
+            keyword.iter().map(|(_, id)| id),
+            vector.iter().map(|(_, id)| id),
+            n,
+        )
+    }
+}
+
+/// Options forwarded to both indexes: the minimum score only applies to the fused scores
+fn forwarded(options: &SearchOptions) -> SearchOptions {
+    SearchOptions {
+        min_score: None,
+        ..options.clone()
+    }
+}
+
+/// Fuse weighted rankings of document ids (best first) with reciprocal rank fusion.
+/// Returns the fused scores and document ids, best first.
+pub fn reciprocal_rank_fusion<'a>(
+    rankings: impl IntoIterator<Item = (f64, Vec<&'a String>)>,
+    k: f64,
+) -> Vec<(f64, String)> {
+    let mut scores = HashMap::<&String, f64>::new();
+    for (weight, ranking) in rankings {
+        for (rank, id) in ranking.into_iter().enumerate() {
+            *scores.entry(id).or_insert(0.0) += weight / (k + rank as f64 + 1.0);
+        }
+    }
+
+    let mut fused = scores
+        .into_iter()
+        .map(|(id, score)| (score, id.clone()))
+        .collect::<Vec<_>>();
+    fused.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    fused
+}
+
+impl<K: VectorStoreIndex, V: VectorStoreIndex> VectorStoreIndex for HybridIndex<K, V> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let candidates = self.candidates_for(n);
+        let (keyword, vector) = futures::try_join!(
+            self.keyword.top_n::<Value>(query, candidates),
+            self.vector.top_n::<Value>(query, candidates)
+        )?;
+
+        self.fuse_documents(keyword, vector, n)
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let candidates = self.candidates_for(n);
+        let (keyword, vector) = futures::try_join!(
+            self.keyword.top_n_ids(query, candidates),
+            self.vector.top_n_ids(query, candidates)
+        )?;
+
+        Ok(self.fuse_ids(keyword, vector, n))
+    }
+
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let candidates = self.candidates_for(n);
+        let (keyword, vector) = futures::try_join!(
+            self.keyword
+                .top_n_with_filter::<Value>(query, candidates, filter),
+            self.vector
+                .top_n_with_filter::<Value>(query, candidates, filter)
+        )?;
+
+        self.fuse_documents(keyword, vector, n)
+    }
+
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let candidates = self.candidates_for(n);
+        let (keyword, vector) = futures::try_join!(
+            self.keyword
+                .top_n_ids_with_filter(query, candidates, filter),
+            self.vector.top_n_ids_with_filter(query, candidates, filter)
+        )?;
+
+        Ok(self.fuse_ids(keyword, vector, n))
+    }
+
+    async fn top_n_with_options<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let candidates = self.candidates_for(n);
+        let forwarded = forwarded(options);
+        let (keyword, vector) = futures::try_join!(
+            self.keyword
+                .top_n_with_options::<Value>(query, candidates, &forwarded),
+            self.vector
+                .top_n_with_options::<Value>(query, candidates, &forwarded)
+        )?;
+
+        let mut results = self.fuse_documents(keyword, vector, n)?;
+        if let Some(min_score) = options.min_score {
+            results.retain(|(score, _, _)| *score >= min_score);
+        }
+        Ok(results)
+    }
+
+    async fn top_n_ids_with_options(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let candidates = self.candidates_for(n);
+        let forwarded = forwarded(options);
+        let (keyword, vector) = futures::try_join!(
+            self.keyword
+                .top_n_ids_with_options(query, candidates, &forwarded),
+            self.vector
+                .top_n_ids_with_options(query, candidates, &forwarded)
+        )?;
+
+        let mut results = self.fuse_ids(keyword, vector, n);
+        if let Some(min_score) = options.min_score {
+            results.retain(|(score, _)| *score >= min_score);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::HybridIndex;
+    use crate::vector_store::{
+        keyword::Bm25Index, Filter, SearchOptions, VectorStoreError, VectorStoreIndex,
+    };
+
+    /// Index returning the given documents in order, whatever the query. Filters apply to
+    /// the ids of the documents, as the `id` field of their metadata.
+    struct RankedIndex(Vec<(&'static str, &'static str)>);
+
+    impl VectorStoreIndex for RankedIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            self.0
+                .iter()
+                .take(n)
+                .map(|(id, doc)| Ok((0.5, id.to_string(), serde_json::from_value((*doc).into())?)))
+                .collect()
+        }
+
+        async fn top_n_ids(
+            &self,
+            _query: &str,
+            n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Ok(self
+                .0
+                .iter()
+                .take(n)
+                .map(|(id, _)| (0.5, id.to_string()))
+                .collect())
+        }
+
+        async fn top_n_ids_with_filter(
+            &self,
+            _query: &str,
+            n: usize,
+            filter: &Filter,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|(id, _)| filter.matches(&json!({ "id": id })))
+                .take(n)
+                .map(|(id, _)| (0.5, id.to_string()))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search() {
+        let keyword = Bm25Index::from_documents(vec![
+            ("doc1", "Error E_1042 when building".to_string(), "one"),
+            ("doc2", "Building the project".to_string(), "two"),
+            ("doc3", "Release process".to_string(), "three"),
+        ]);
+        // The vector index misses the exact error code
+        let vector = RankedIndex(vec![("doc2", "two"), ("doc3", "three"), ("doc1", "one")]);
+        let index = HybridIndex::new(keyword, vector);
+
+        let results = index
+            .top_n::<String>("error E_1042 building", 2)
+            .await
+            .unwrap();
+        // doc2 (keyword rank 2, vector rank 1) slightly outranks doc1 (ranks 1 and 3)
+        assert_eq!(
+            results
+                .iter()
+                .map(|(_, id, doc)| (id.as_str(), doc.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("doc2", "two"), ("doc1", "one")]
+        );
+
+        // Weighting the keyword ranking favors exact matches
+        let index = index.weights(3.0, 1.0);
+        let ids = index.top_n_ids("error E_1042 building", 3).await.unwrap();
+        assert_eq!(
+            ids.iter().map(|(_, id)| id.as_str()).collect::<Vec<_>>(),
+            vec!["doc1", "doc2", "doc3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_scores_and_filters() {
+        let mut keyword = Bm25Index::new();
+        keyword.add_documents_with_metadata(
+            [
+                ("doc1", "Error E_1042 when building", "one"),
+                ("doc2", "Building the project", "two"),
+                ("doc3", "Release process", "three"),
+            ]
+            .map(|(id, text, doc)| (id, text.to_string(), doc, json!({ "id": id }))),
+        );
+        let vector = RankedIndex(vec![("doc2", "two"), ("doc3", "three"), ("doc1", "one")]);
+        let index = HybridIndex::new(keyword, vector);
+
+        // A document ranked first by both indexes scores 1
+        let ids = index.top_n_ids("building project", 3).await.unwrap();
+        assert_eq!(
+            ids.iter().map(|(_, id)| id.as_str()).collect::<Vec<_>>(),
+            vec!["doc2", "doc1", "doc3"]
+        );
+        assert_eq!(ids[0].0, 1.0);
+        assert!(ids.iter().all(|(score, _)| *score > 0.0 && *score <= 1.0));
+
+        // The minimum score applies to the fused scores
+        let ids = index
+            .top_n_ids_with_options("building project", 3, &SearchOptions::new().min_score(0.9))
+            .await
+            .unwrap();
+        assert_eq!(
+            ids.iter().map(|(_, id)| id.as_str()).collect::<Vec<_>>(),
+            vec!["doc2", "doc1"]
+        );
+
+        // Filters are applied by both indexes
+        let filter = Filter::is_in("id", vec![json!("doc1"), json!("doc3")]);
+        let ids = index
+            .top_n_ids_with_filter("building project", 3, &filter)
+            .await
+            .unwrap();
+        assert_eq!(
+            ids.iter().map(|(_, id)| id.as_str()).collect::<Vec<_>>(),
+            vec!["doc1", "doc3"]
+        );
+        // Searching documents fails, since the vector index of the test only filters ids
+        assert!(matches!(
+            index
+                .top_n_with_filter::<String>("building project", 3, &filter)
+                .await,
+            Err(VectorStoreError::FilterNotSupported)
+        ));
+        let ids = index
+            .top_n_ids_with_options(
+                "building project",
+                3,
+                &SearchOptions::new().filter(filter).min_score(0.9),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+    }
+}
@@ -2,25 +2,186 @@
 use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap},
+    path::Path,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 use ordered_float::OrderedFloat;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
 
-use super::{VectorStoreError, VectorStoreIndex};
+#[cfg(feature = "hnsw")]
+use super::hnsw::{Hnsw, HnswConfig};
+use super::{
+    Filter, InsertDocuments, ScoreAggregation, SearchOptions, VectorStoreError, VectorStoreIndex,
+};
+#[cfg(feature = "encryption")]
+use crate::storage::encrypted::{self, KeyProvider};
 use crate::{
-    embeddings::{distance::VectorDistance, Embedding, EmbeddingModel},
+    embeddings::{Embedding, EmbeddingModel},
     OneOrMany,
 };
 
+/// Similarity measure ranking the documents of an [InMemoryVectorStore]. The scores of the
+/// searches are the similarities of the documents to the query (higher is closer).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DistanceMetric {
+    /// Cosine similarity, between -1 and 1
+    #[default]
+    Cosine,
+    /// Dot product of the vectors, equivalent to the cosine similarity (and cheaper to compute)
+    /// for normalized embeddings
+    DotProduct,
+    /// Euclidean distance `d`, scored as `1 / (1 + d)` (between 0 and 1)
+    Euclidean,
+}
+
+impl DistanceMetric {
+    /// Similarity score of two vectors
+    pub fn score(&self, a: &[f64], b: &[f64]) -> f64 {
+        let dot_product = || a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+        match self {
+            Self::Cosine => {
+                let magnitude = |v: &[f64]| v.iter().map(|x| x.powi(2)).sum::<f64>().sqrt();
+                dot_product() / (magnitude(a) * magnitude(b))
+            }
+            Self::DotProduct => dot_product(),
+            Self::Euclidean => {
+                let distance = a
+                    .iter()
+                    .zip(b)
+                    .map(|(x, y)| (x - y).powi(2))
+                    .sum::<f64>()
+                    .sqrt();
+                1.0 / (1.0 + distance)
+            }
+        }
+    }
+}
+
 /// [InMemoryVectorStore] is a simple in-memory vector store that stores embeddings
 /// in-memory using a HashMap.
-#[derive(Clone, Default)]
+///
+/// By default, searches compare the query to every embedding of the store. With the `hnsw`
+/// feature, [with_hnsw](Self::with_hnsw) indexes the embeddings in an approximate nearest
+/// neighbor graph instead, for large stores.
+///
+/// The documents and embeddings are shared by the [snapshot](Self::snapshot)s of the store, so
+/// that tests and experiments can branch the state of an index, mutate it and discard it
+/// without re-embedding or copying the vectors.
+#[derive(Default)]
 pub struct InMemoryVectorStore<D: Serialize> {
     /// The embeddings are stored in a HashMap.
     /// Hashmap key is the document id.
-    /// Hashmap value is a tuple of the serializable document and its corresponding embeddings.
-    embeddings: HashMap<String, (D, OneOrMany<Embedding>)>,
+    /// Hashmap value is a tuple of the serializable document and its corresponding embeddings,
+    /// shared with the snapshots of the store.
+    embeddings: HashMap<String, Arc<(D, OneOrMany<Embedding>)>>,
+    /// JSON metadata of the documents (by document id), matched against search filters.
+    metadata: HashMap<String, Value>,
+    distance: DistanceMetric,
+    /// HNSW graph of the embeddings, copied by the first insertion or removal following a
+    /// snapshot
+    #[cfg(feature = "hnsw")]
+    hnsw: Option<Arc<Hnsw>>,
+}
+
+/// Clones are [snapshot](Self::snapshot)s: they share the documents and embeddings of the store.
+impl<D: Serialize> Clone for InMemoryVectorStore<D> {
+    fn clone(&self) -> Self {
+        Self {
+            embeddings: self.embeddings.clone(),
+            metadata: self.metadata.clone(),
+            distance: self.distance,
+            #[cfg(feature = "hnsw")]
+            hnsw: self.hnsw.clone(),
+        }
+    }
+}
+
+impl<D: Serialize> InMemoryVectorStore<D> {
+    /// Store of the given documents, without metadata.
+    fn from_map(embeddings: HashMap<String, (D, OneOrMany<Embedding>)>) -> Self {
+        Self {
+            embeddings: embeddings
+                .into_iter()
+                .map(|(id, entry)| (id, Arc::new(entry)))
+                .collect(),
+            metadata: HashMap::new(),
+            distance: DistanceMetric::default(),
+            #[cfg(feature = "hnsw")]
+            hnsw: None,
+        }
+    }
+
+    /// Set the similarity measure of the searches (cosine similarity by default).
+    pub fn with_distance(mut self, distance: DistanceMetric) -> Self {
+        self.distance = distance;
+        #[cfg(feature = "hnsw")]
+        if let Some(hnsw) = &self.hnsw {
+            let config = hnsw.config();
+            return self.with_hnsw(config);
+        }
+        self
+    }
+
+    /// Index the embeddings of the store (and of the documents added later) in an HNSW graph,
+    /// so that searches visit a small share of the embeddings instead of all of them. The
+    /// searches become approximate: see [hnsw](crate::vector_store::hnsw).
+    ///
+    /// The graph is not saved by [save](Self::save): it has to be rebuilt after loading a store.
+    #[cfg(feature = "hnsw")]
+    pub fn with_hnsw(mut self, config: HnswConfig) -> Self {
+        let mut hnsw = Hnsw::new(config, self.distance);
+        let mut ids = self.embeddings.keys().collect::<Vec<_>>();
+        ids.sort();
+        for id in ids {
+            let embeddings = &self.embeddings[id].1;
+            hnsw.insert(
+                id,
+                embeddings.iter().map(|embedding| embedding.vec.as_slice()),
+            );
+        }
+        self.hnsw = Some(Arc::new(hnsw));
+        self
+    }
+
+    /// Insert a document in the store (and its index), replacing the document with the same id
+    fn insert(&mut self, id: String, document: D, embeddings: OneOrMany<Embedding>) {
+        #[cfg(feature = "hnsw")]
+        if let Some(hnsw) = &mut self.hnsw {
+            Arc::make_mut(hnsw).insert(
+                &id,
+                embeddings.iter().map(|embedding| embedding.vec.as_slice()),
+            );
+        }
+        self.embeddings.insert(id, Arc::new((document, embeddings)));
+    }
+
+    /// Snapshot of the store: a copy sharing its documents and embeddings, which can be
+    /// mutated (or discarded) without affecting the store, and conversely. Taking a snapshot
+    /// only copies the ids and metadata of the documents.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rig::vector_store::in_memory_store::InMemoryVectorStore;
+    ///
+    /// let mut store = InMemoryVectorStore::from_documents(embeddings);
+    /// let baseline = store.snapshot();
+    ///
+    /// store.add_documents(more_embeddings);
+    /// // ... search the extended index
+    ///
+    /// store.restore(&baseline);
+    /// ```
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// Reset the store to the state of `snapshot`, discarding the changes made since it was
+    /// taken (the similarity measure and HNSW graph included).
+    pub fn restore(&mut self, snapshot: &Self) {
+        *self = snapshot.snapshot();
+    }
 }
 
 impl<D: Serialize + Eq> InMemoryVectorStore<D> {
@@ -36,7 +197,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
                 store.insert(format!("doc{i}"), (doc, embeddings));
             });
 
-        Self { embeddings: store }
+        Self::from_map(store)
     }
 
     /// Create a new [InMemoryVectorStore] from documents and and their corresponding embeddings with ids.
@@ -48,7 +209,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
             store.insert(i.to_string(), (doc, embeddings));
         });
 
-        Self { embeddings: store }
+        Self::from_map(store)
     }
 
     /// Create a new [InMemoryVectorStore] from documents and their corresponding embeddings.
@@ -62,28 +223,55 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
             store.insert(f(&doc), (doc, embeddings));
         });
 
-        Self { embeddings: store }
+        Self::from_map(store)
     }
 
-    /// Implement vector search on [InMemoryVectorStore].
+    /// Implement vector search on [InMemoryVectorStore], only considering the documents whose
+    /// metadata matches `filter` if any (documents without metadata are matched as `null`), and
+    /// scoring the documents with `aggregation` of the scores of their embeddings.
     /// To be used by implementations of [VectorStoreIndex::top_n] and [VectorStoreIndex::top_n_ids] methods.
-    fn vector_search(&self, prompt_embedding: &Embedding, n: usize) -> EmbeddingRanking<D> {
+    fn vector_search(
+        &self,
+        prompt_embedding: &Embedding,
+        n: usize,
+        filter: Option<&Filter>,
+        aggregation: &ScoreAggregation,
+    ) -> EmbeddingRanking<'_, D> {
+        // The HNSW graph indexes the embeddings individually: it only finds the documents by
+        // their best-matching embedding, other aggregations scan all the documents.
+        #[cfg(feature = "hnsw")]
+        if let (Some(hnsw), ScoreAggregation::Max) = (&self.hnsw, aggregation) {
+            return self.hnsw_search(hnsw, prompt_embedding, n, filter);
+        }
+
         // Sort documents by best embedding distance
         let mut docs = BinaryHeap::new();
 
-        for (id, (doc, embeddings)) in self.embeddings.iter() {
-            // Get the best context for the document given the prompt
-            if let Some((distance, embed_doc)) = embeddings
-                .iter()
-                .map(|embedding| {
-                    (
-                        OrderedFloat(embedding.cosine_similarity(prompt_embedding, false)),
-                        &embedding.document,
-                    )
-                })
-                .max_by(|a, b| a.0.cmp(&b.0))
-            {
-                docs.push(Reverse(RankingItem(distance, id, doc, embed_doc)));
+        for (id, (doc, embeddings)) in self.iter() {
+            if let Some(filter) = filter {
+                if !filter.matches(self.metadata.get(id).unwrap_or(&Value::Null)) {
+                    continue;
+                }
+            }
+
+            // Score the document given the prompt, with its best context
+            if let Some((distance, position)) = aggregation.aggregate(
+                embeddings
+                    .iter()
+                    .map(|embedding| self.distance.score(&embedding.vec, &prompt_embedding.vec)),
+            ) {
+                let embed_doc = &embeddings
+                    .iter()
+                    .nth(position)
+                    .expect("valid position")
+                    .document;
+                docs.push(Reverse(RankingItem(
+                    OrderedFloat(distance),
+                    id,
+                    doc,
+                    embed_doc,
+                    position,
+                )));
             };
 
             // If the heap size exceeds n, pop the least old element.
@@ -92,33 +280,164 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
             }
         }
 
-        // Log selected tools with their distances
-        tracing::info!(target: "rig",
-            "Selected documents: {}",
-            docs.iter()
-                .map(|Reverse(RankingItem(distance, id, _, _))| format!("{} ({})", id, distance))
-                .collect::<Vec<String>>()
-                .join(", ")
-        );
-
+        log_ranking(&docs);
         docs
     }
 
+    /// Vector search of the HNSW graph of the store, widening the search until `n` documents
+    /// match the filter (or the whole graph was searched).
+    #[cfg(feature = "hnsw")]
+    fn hnsw_search<'a>(
+        &'a self,
+        hnsw: &Hnsw,
+        prompt_embedding: &Embedding,
+        n: usize,
+        filter: Option<&Filter>,
+    ) -> EmbeddingRanking<'a, D> {
+        let mut ef = hnsw.config().ef_search.max(n);
+        loop {
+            // The nodes are sorted by score, so the first node of each document is its best
+            // embedding
+            let mut seen = std::collections::HashSet::new();
+            let docs = hnsw
+                .search(&prompt_embedding.vec, ef)
+                .into_iter()
+                .filter_map(|(score, node)| {
+                    let node = hnsw.node(node);
+                    let (id, entry) = self.embeddings.get_key_value(&node.id)?;
+                    let (doc, embeddings) = &**entry;
+                    let embedding = embeddings.iter().nth(node.index)?;
+                    Some(RankingItem(
+                        OrderedFloat(score),
+                        id,
+                        doc,
+                        &embedding.document,
+                        node.index,
+                    ))
+                })
+                .filter(|RankingItem(_, id, _, _, _)| {
+                    filter.is_none_or(|filter| {
+                        filter.matches(self.metadata.get(*id).unwrap_or(&Value::Null))
+                    })
+                })
+                .filter(|RankingItem(_, id, _, _, _)| seen.insert(*id))
+                .take(n)
+                .map(Reverse)
+                .collect::<BinaryHeap<_>>();
+
+            if docs.len() >= n || ef >= hnsw.len() {
+                log_ranking(&docs);
+                return docs;
+            }
+            ef *= 2;
+        }
+    }
+
+    /// Vector search with the given options: the documents below the minimum score are discarded
+    /// and, if enabled, the candidates are re-ranked with maximal marginal relevance.
+    /// Returns the documents from the most to the least relevant.
+    fn vector_search_with_options(
+        &self,
+        prompt_embedding: &Embedding,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Vec<RankingItem<'_, D>> {
+        let candidates = match options.mmr_lambda {
+            Some(_) => options.fetch_k.unwrap_or(4 * n).max(n),
+            None => n,
+        };
+
+        let ranking = self
+            .vector_search(
+                prompt_embedding,
+                candidates,
+                options.filter.as_ref(),
+                &options.aggregation,
+            )
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(item)| item)
+            .filter(|RankingItem(distance, _, _, _, _)| {
+                options
+                    .min_score
+                    .is_none_or(|min_score| distance.0 >= min_score)
+            })
+            .collect::<Vec<_>>();
+
+        match options.mmr_lambda {
+            Some(lambda) => self.mmr(ranking, n, lambda),
+            None => ranking,
+        }
+    }
+
+    /// Greedily select `n` of the candidates maximizing `lambda * relevance - (1 - lambda) * redundancy`,
+    /// where the redundancy of a candidate is its highest similarity to the selected documents.
+    fn mmr<'a>(
+        &'a self,
+        candidates: Vec<RankingItem<'a, D>>,
+        n: usize,
+        lambda: f64,
+    ) -> Vec<RankingItem<'a, D>> {
+        // Best-matching embedding of each candidate
+        let embeddings = candidates
+            .iter()
+            .map(|RankingItem(_, id, _, _, position)| {
+                self.embeddings[*id]
+                    .1
+                    .iter()
+                    .nth(*position)
+                    .expect("valid position")
+            })
+            .collect::<Vec<_>>();
+
+        let mut remaining = (0..candidates.len()).collect::<Vec<_>>();
+        let mut selected = Vec::<usize>::new();
+        while selected.len() < n && !remaining.is_empty() {
+            let (position, _) = remaining
+                .iter()
+                .enumerate()
+                .map(|(position, &candidate)| {
+                    let redundancy = selected
+                        .iter()
+                        .map(|&other| {
+                            self.distance
+                                .score(&embeddings[candidate].vec, &embeddings[other].vec)
+                        })
+                        .reduce(f64::max)
+                        .unwrap_or(0.0);
+                    let relevance = candidates[candidate].0 .0;
+                    (position, lambda * relevance - (1.0 - lambda) * redundancy)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .expect("remaining candidates are not empty");
+            selected.push(remaining.remove(position));
+        }
+
+        let mut candidates = candidates.into_iter().map(Some).collect::<Vec<_>>();
+        selected
+            .into_iter()
+            .filter_map(|candidate| candidates[candidate].take())
+            .collect()
+    }
+
     /// Add documents and their corresponding embeddings to the store.
     /// Ids are automatically generated have will have the form `"doc{n}"` where `n`
-    /// is the index of the document.
+    /// is the index of the document. Ids already in use (e.g.: after a removal) are skipped.
     pub fn add_documents(
         &mut self,
         documents: impl IntoIterator<Item = (D, OneOrMany<Embedding>)>,
     ) {
-        let current_index = self.embeddings.len();
-        documents
-            .into_iter()
-            .enumerate()
-            .for_each(|(index, (doc, embeddings))| {
-                self.embeddings
-                    .insert(format!("doc{}", index + current_index), (doc, embeddings));
-            });
+        let mut index = self.embeddings.len();
+        for (doc, embeddings) in documents {
+            let id = loop {
+                let id = format!("doc{index}");
+                index += 1;
+                if !self.embeddings.contains_key(&id) {
+                    break id;
+                }
+            };
+            self.insert(id, doc, embeddings);
+        }
     }
 
     /// Add documents and their corresponding embeddings to the store with ids.
@@ -127,7 +446,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
         documents: impl IntoIterator<Item = (impl ToString, D, OneOrMany<Embedding>)>,
     ) {
         documents.into_iter().for_each(|(id, doc, embeddings)| {
-            self.embeddings.insert(id.to_string(), (doc, embeddings));
+            self.insert(id.to_string(), doc, embeddings);
         });
     }
 
@@ -140,10 +459,29 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
     ) {
         for (doc, embeddings) in documents {
             let id = f(&doc);
-            self.embeddings.insert(id, (doc, embeddings));
+            self.insert(id, doc, embeddings);
         }
     }
 
+    /// Add documents with their JSON metadata and corresponding embeddings to the store with ids.
+    pub fn add_documents_with_metadata(
+        &mut self,
+        documents: impl IntoIterator<Item = (impl ToString, D, Value, OneOrMany<Embedding>)>,
+    ) {
+        documents
+            .into_iter()
+            .for_each(|(id, doc, metadata, embeddings)| {
+                let id = id.to_string();
+                self.metadata.insert(id.clone(), metadata);
+                self.insert(id, doc, embeddings);
+            });
+    }
+
+    /// Set the JSON metadata of the document with the given id.
+    pub fn set_metadata(&mut self, id: impl ToString, metadata: Value) {
+        self.metadata.insert(id.to_string(), metadata);
+    }
+
     /// Get the document by its id and deserialize it into the given type.
     pub fn get_document<T: for<'a> Deserialize<'a>>(
         &self,
@@ -152,14 +490,14 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
         Ok(self
             .embeddings
             .get(id)
-            .map(|(doc, _)| serde_json::from_str(&serde_json::to_string(doc)?))
+            .map(|entry| serde_json::from_str(&serde_json::to_string(&entry.0)?))
             .transpose()?)
     }
 }
 
-/// RankingItem(distance, document_id, serializable document, embeddings document)
+/// RankingItem(distance, document_id, serializable document, embeddings document, embedding position)
 #[derive(Eq, PartialEq)]
-struct RankingItem<'a, D: Serialize>(OrderedFloat<f64>, &'a String, &'a D, &'a String);
+struct RankingItem<'a, D: Serialize>(OrderedFloat<f64>, &'a String, &'a D, &'a String, usize);
 
 impl<D: Serialize + Eq> Ord for RankingItem<'_, D> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
@@ -175,13 +513,128 @@ impl<D: Serialize + Eq> PartialOrd for RankingItem<'_, D> {
 
 type EmbeddingRanking<'a, D> = BinaryHeap<Reverse<RankingItem<'a, D>>>;
 
+/// Log the selected documents with their distances
+fn log_ranking<D: Serialize>(docs: &EmbeddingRanking<'_, D>) {
+    tracing::info!(target: "rig",
+        "Selected documents: {}",
+        docs.iter()
+            .map(|Reverse(RankingItem(distance, id, _, _, _))| format!("{} ({})", id, distance))
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+}
+
+impl<D: Serialize + Eq> InMemoryVectorStore<D> {
+    fn search<T: for<'a> Deserialize<'a>>(
+        &self,
+        prompt_embedding: &Embedding,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let docs = self.vector_search_with_options(prompt_embedding, n, options);
+
+        // Return n best
+        docs.into_iter()
+            .map(|RankingItem(distance, id, doc, _, _)| {
+                Ok((
+                    distance.0,
+                    id.clone(),
+                    serde_json::from_str(
+                        &serde_json::to_string(doc).map_err(VectorStoreError::JsonError)?,
+                    )
+                    .map_err(VectorStoreError::JsonError)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    fn search_ids(
+        &self,
+        prompt_embedding: &Embedding,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Vec<(f64, String)> {
+        let docs = self.vector_search_with_options(prompt_embedding, n, options);
+
+        // Return n best
+        docs.into_iter()
+            .map(|RankingItem(distance, id, _, _, _)| (distance.0, id.clone()))
+            .collect()
+    }
+
+    fn search_matches<T: for<'a> Deserialize<'a>>(
+        &self,
+        prompt_embedding: &Embedding,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchMatch<T>>, VectorStoreError> {
+        let docs = self.vector_search_with_options(prompt_embedding, n, options);
+
+        docs.into_iter()
+            .map(|RankingItem(distance, id, doc, embed_doc, position)| {
+                Ok(SearchMatch {
+                    score: distance.0,
+                    id: id.clone(),
+                    document: serde_json::from_value(serde_json::to_value(doc)?)?,
+                    embedding_index: position,
+                    matched_text: embed_doc.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Document returned by a search, with the embedding of the document which matched the query
+/// best (e.g.: to tell which of its `#[embed]` fields matched).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch<T> {
+    /// Score of the document, aggregating the scores of its embeddings
+    pub score: f64,
+    pub id: String,
+    pub document: T,
+    /// Position of the best-matching embedding among the embeddings of the document (i.e.: the
+    /// order of the `#[embed]` fields and of their texts)
+    pub embedding_index: usize,
+    /// Text of the best-matching embedding
+    pub matched_text: String,
+}
+
 impl<D: Serialize> InMemoryVectorStore<D> {
     pub fn index<M: EmbeddingModel>(self, model: M) -> InMemoryVectorIndex<M, D> {
         InMemoryVectorIndex::new(model, self)
     }
 
+    /// Create a [SharedInMemoryIndex] of the store, to which documents can be added (or from
+    /// which they can be removed) while it is used.
+    pub fn shared_index<M: EmbeddingModel>(self, model: M) -> SharedInMemoryIndex<M, D> {
+        SharedInMemoryIndex::new(model, self)
+    }
+
+    /// Get the JSON metadata of the document with the given id, if any.
+    pub fn get_metadata(&self, id: &str) -> Option<&Value> {
+        self.metadata.get(id)
+    }
+
+    /// Remove the document with the given id, and its metadata, from the store, returning
+    /// whether it was in the store.
+    pub fn delete(&mut self, id: &str) -> bool {
+        self.take(id).is_some()
+    }
+
+    /// Remove the document with the given id, and its metadata, from the store, returning the
+    /// shared document and embeddings.
+    fn take(&mut self, id: &str) -> Option<Arc<(D, OneOrMany<Embedding>)>> {
+        self.metadata.remove(id);
+        let entry = self.embeddings.remove(id)?;
+        #[cfg(feature = "hnsw")]
+        if let Some(hnsw) = &mut self.hnsw {
+            Arc::make_mut(hnsw).remove(id);
+        }
+        Some(entry)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&String, &(D, OneOrMany<Embedding>))> {
-        self.embeddings.iter()
+        self.embeddings.iter().map(|(id, entry)| (id, &**entry))
     }
 
     pub fn len(&self) -> usize {
@@ -193,6 +646,272 @@ impl<D: Serialize> InMemoryVectorStore<D> {
     }
 }
 
+impl<D: Serialize + Clone> InMemoryVectorStore<D> {
+    /// Remove the document with the given id, and its metadata, from the store. The document
+    /// and embeddings are copied if they are shared with a [snapshot](Self::snapshot).
+    pub fn remove(&mut self, id: &str) -> Option<(D, OneOrMany<Embedding>)> {
+        self.take(id).map(Arc::unwrap_or_clone)
+    }
+}
+
+/// Magic bytes starting the files written by [InMemoryVectorStore::save]
+const MAGIC: &[u8] = b"RIGVS";
+const FORMAT_VERSION: u8 = 1;
+/// Version of the files whose JSON header is compressed with zstd
+const COMPRESSED_FORMAT_VERSION: u8 = 2;
+
+/// Document of a saved [InMemoryVectorStore], with embeddings of type `E`
+#[derive(Serialize, Deserialize)]
+struct StoredDocument<D, E> {
+    id: String,
+    document: D,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Value>,
+    embeddings: Vec<E>,
+}
+
+impl<D: Serialize> InMemoryVectorStore<D> {
+    /// Save the documents, embeddings and metadata of the store to the file at `path`, so that
+    /// the store can be [loaded](Self::load) without embedding the documents again.
+    ///
+    /// The embedding vectors are written as raw little-endian floats, after a JSON header with
+    /// the documents and metadata. See [save_json](Self::save_json) for a human-readable format.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), VectorStoreError> {
+        self.save_binary(path, FORMAT_VERSION, Ok)
+    }
+
+    /// Same as [save](Self::save), but compresses the documents and metadata with zstd at the
+    /// given `level` (1 to 22, 3 being the default of zstd). The text of the documents usually
+    /// takes far more space than their vectors, which are left uncompressed.
+    ///
+    /// The compression is transparent to [load](Self::load).
+    #[cfg(feature = "zstd")]
+    pub fn save_compressed(
+        &self,
+        path: impl AsRef<Path>,
+        level: i32,
+    ) -> Result<(), VectorStoreError> {
+        self.save_binary(path, COMPRESSED_FORMAT_VERSION, |header| {
+            Ok(zstd::bulk::compress(&header, level)?)
+        })
+    }
+
+    /// Same as [save](Self::save), but encrypts the file with AES-GCM, with the current key of
+    /// `keys` (see [crate::storage::encrypted]), so that the text of the documents is
+    /// not stored in plaintext. Encrypted files are loaded with [load_encrypted](Self::load_encrypted).
+    #[cfg(feature = "encryption")]
+    pub fn save_encrypted(
+        &self,
+        path: impl AsRef<Path>,
+        keys: &impl KeyProvider,
+    ) -> Result<(), VectorStoreError> {
+        let bytes = self.binary(FORMAT_VERSION, Ok)?;
+        let bytes = encrypted::encrypt(keys, MAGIC, &bytes)
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Write the binary format of [save](Self::save), with the JSON header encoded by `encode`
+    fn save_binary(
+        &self,
+        path: impl AsRef<Path>,
+        version: u8,
+        encode: impl FnOnce(Vec<u8>) -> Result<Vec<u8>, VectorStoreError>,
+    ) -> Result<(), VectorStoreError> {
+        std::fs::write(path, self.binary(version, encode)?)?;
+        Ok(())
+    }
+
+    /// Binary format of [save](Self::save), with the JSON header encoded by `encode`
+    fn binary(
+        &self,
+        version: u8,
+        encode: impl FnOnce(Vec<u8>) -> Result<Vec<u8>, VectorStoreError>,
+    ) -> Result<Vec<u8>, VectorStoreError> {
+        let mut vectors = vec![];
+        let documents = self.stored_documents(|embedding| {
+            vectors.push(&embedding.vec);
+            (&embedding.document, embedding.vec.len())
+        });
+        let header = encode(serde_json::to_vec(&documents)?)?;
+
+        let dimensions = vectors.iter().map(|vec| vec.len()).sum::<usize>();
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 9 + header.len() + 8 * dimensions);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(version);
+        bytes.extend_from_slice(&(header.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&header);
+        vectors
+            .into_iter()
+            .flatten()
+            .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+        Ok(bytes)
+    }
+
+    /// Save the documents, embeddings and metadata of the store to the file at `path` as JSON.
+    /// The file is larger and slower to load than with [save](Self::save).
+    pub fn save_json(&self, path: impl AsRef<Path>) -> Result<(), VectorStoreError> {
+        let documents = self.stored_documents(|embedding| embedding);
+        std::fs::write(path, serde_json::to_vec(&documents)?)?;
+        Ok(())
+    }
+
+    /// Documents of the store sorted by id, with their embeddings mapped by `f`
+    fn stored_documents<'a, E>(
+        &'a self,
+        mut f: impl FnMut(&'a Embedding) -> E,
+    ) -> Vec<StoredDocument<&'a D, E>> {
+        let mut ids = self.embeddings.keys().collect::<Vec<_>>();
+        ids.sort();
+        ids.into_iter()
+            .map(|id| {
+                let (document, embeddings) = &*self.embeddings[id];
+                StoredDocument {
+                    id: id.clone(),
+                    document,
+                    metadata: self.metadata.get(id).cloned(),
+                    embeddings: embeddings.iter().map(&mut f).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl<D: Serialize + DeserializeOwned> InMemoryVectorStore<D> {
+    /// Load a store from a file written by [save](Self::save) or [save_json](Self::save_json).
+    ///
+    /// Files written by `save_compressed` can only be loaded with the `zstd` feature.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, VectorStoreError> {
+        let bytes = std::fs::read(path)?;
+        #[cfg(feature = "encryption")]
+        if encrypted::is_encrypted(&bytes) {
+            return Err(invalid_data(
+                "The file is encrypted, it must be loaded with `load_encrypted`".to_string(),
+            ));
+        }
+        Self::from_bytes(&bytes)
+    }
+
+    /// Load a store from a file written by [save_encrypted](Self::save_encrypted), decrypting
+    /// it with the key of `keys` that encrypted it.
+    #[cfg(feature = "encryption")]
+    pub fn load_encrypted(
+        path: impl AsRef<Path>,
+        keys: &impl KeyProvider,
+    ) -> Result<Self, VectorStoreError> {
+        let bytes = std::fs::read(path)?;
+        let bytes = encrypted::decrypt(keys, MAGIC, &bytes)
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Store of the content of a file written by [save](Self::save) or [save_json](Self::save_json)
+    fn from_bytes(bytes: &[u8]) -> Result<Self, VectorStoreError> {
+        let documents = match bytes.strip_prefix(MAGIC) {
+            Some(bytes) => decode_binary(bytes)?,
+            None => serde_json::from_slice(bytes)?,
+        };
+
+        let mut store = Self::from_map(HashMap::new());
+        for StoredDocument {
+            id,
+            document,
+            metadata,
+            embeddings,
+        } in documents
+        {
+            let embeddings = OneOrMany::many(embeddings)
+                .map_err(|_| invalid_data(format!("Document {id} has no embeddings")))?;
+            if let Some(metadata) = metadata {
+                store.metadata.insert(id.clone(), metadata);
+            }
+            store
+                .embeddings
+                .insert(id, Arc::new((document, embeddings)));
+        }
+        Ok(store)
+    }
+}
+
+/// Decode the documents of a file written by [InMemoryVectorStore::save] (without the magic bytes)
+fn decode_binary<D: DeserializeOwned>(
+    bytes: &[u8],
+) -> Result<Vec<StoredDocument<D, Embedding>>, VectorStoreError> {
+    let (&version, bytes) = bytes
+        .split_first()
+        .ok_or_else(|| invalid_data("Truncated file".to_string()))?;
+    if version != FORMAT_VERSION && version != COMPRESSED_FORMAT_VERSION {
+        return Err(invalid_data(format!(
+            "Unsupported format version {version}"
+        )));
+    }
+
+    let (length, bytes) = bytes
+        .split_at_checked(8)
+        .ok_or_else(|| invalid_data("Truncated file".to_string()))?;
+    let length = u64::from_le_bytes(length.try_into().expect("length is 8 bytes")) as usize;
+    let (header, vectors) = bytes
+        .split_at_checked(length)
+        .ok_or_else(|| invalid_data("Truncated file".to_string()))?;
+
+    let documents: Vec<StoredDocument<D, (String, usize)>> = if version == COMPRESSED_FORMAT_VERSION
+    {
+        serde_json::from_slice(&decompress(header)?)?
+    } else {
+        serde_json::from_slice(header)?
+    };
+    let dimensions = documents
+        .iter()
+        .flat_map(|document| document.embeddings.iter().map(|(_, dimensions)| dimensions))
+        .sum::<usize>();
+    if vectors.len() != 8 * dimensions {
+        return Err(invalid_data(format!(
+            "Expected {dimensions} embedding values, found {} bytes",
+            vectors.len()
+        )));
+    }
+
+    let mut values = vectors
+        .chunks_exact(8)
+        .map(|value| f64::from_le_bytes(value.try_into().expect("chunks are 8 bytes")));
+    Ok(documents
+        .into_iter()
+        .map(|document| StoredDocument {
+            id: document.id,
+            document: document.document,
+            metadata: document.metadata,
+            embeddings: document
+                .embeddings
+                .into_iter()
+                .map(|(text, dimensions)| Embedding {
+                    document: text,
+                    vec: values.by_ref().take(dimensions).collect(),
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>, VectorStoreError> {
+    Ok(zstd::stream::decode_all(bytes)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress(_bytes: &[u8]) -> Result<Vec<u8>, VectorStoreError> {
+    Err(invalid_data(
+        "The file is compressed, loading it requires the `zstd` feature of rig-core".to_string(),
+    ))
+}
+
+fn invalid_data(message: String) -> VectorStoreError {
+    VectorStoreError::IoError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message,
+    ))
+}
+
 pub struct InMemoryVectorIndex<M: EmbeddingModel, D: Serialize> {
     model: M,
     pub store: InMemoryVectorStore<D>,
@@ -216,6 +935,40 @@ impl<M: EmbeddingModel, D: Serialize> InMemoryVectorIndex<M, D> {
     }
 }
 
+impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> InMemoryVectorIndex<M, D> {
+    async fn search<T: for<'a> Deserialize<'a>>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let prompt_embedding = &self.model.embed_text(query).await?;
+        self.store.search(prompt_embedding, n, options)
+    }
+
+    async fn search_ids(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let prompt_embedding = &self.model.embed_text(query).await?;
+        Ok(self.store.search_ids(prompt_embedding, n, options))
+    }
+
+    /// Same as [top_n_with_options](VectorStoreIndex::top_n_with_options), but also returns
+    /// which embedding of each document matched the query best.
+    pub async fn top_n_matches<T: for<'a> Deserialize<'a>>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchMatch<T>>, VectorStoreError> {
+        let prompt_embedding = &self.model.embed_text(query).await?;
+        self.store.search_matches(prompt_embedding, n, options)
+    }
+}
+
 impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> VectorStoreIndex
     for InMemoryVectorIndex<M, D>
 {
@@ -224,23 +977,113 @@ impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> VectorStoreIndex
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let prompt_embedding = &self.model.embed_text(query).await?;
+        self.search(query, n, &SearchOptions::default()).await
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.search_ids(query, n, &SearchOptions::default()).await
+    }
 
-        let docs = self.store.vector_search(prompt_embedding, n);
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a>>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let options = SearchOptions::new().filter(filter.clone());
+        self.search(query, n, &options).await
+    }
 
-        // Return n best
-        docs.into_iter()
-            .map(|Reverse(RankingItem(distance, id, doc, _))| {
-                Ok((
-                    distance.0,
-                    id.clone(),
-                    serde_json::from_str(
-                        &serde_json::to_string(doc).map_err(VectorStoreError::JsonError)?,
-                    )
-                    .map_err(VectorStoreError::JsonError)?,
-                ))
-            })
-            .collect::<Result<Vec<_>, _>>()
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let options = SearchOptions::new().filter(filter.clone());
+        self.search_ids(query, n, &options).await
+    }
+
+    async fn top_n_with_options<T: for<'a> Deserialize<'a>>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search(query, n, options).await
+    }
+
+    async fn top_n_ids_with_options(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.search_ids(query, n, options).await
+    }
+}
+
+/// Index of an [InMemoryVectorStore] shared by all its clones, so that documents can be added
+/// to (or removed from) the store while the index is used, e.g. by agents while an
+/// [EventIngestor](crate::vector_store::events::EventIngestor) keeps the store in sync with a
+/// source of documents.
+pub struct SharedInMemoryIndex<M: EmbeddingModel, D: Serialize> {
+    model: M,
+    store: Arc<RwLock<InMemoryVectorStore<D>>>,
+}
+
+impl<M: EmbeddingModel, D: Serialize> Clone for SharedInMemoryIndex<M, D> {
+    fn clone(&self) -> Self {
+        Self {
+            model: self.model.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<M: EmbeddingModel, D: Serialize> SharedInMemoryIndex<M, D> {
+    pub fn new(model: M, store: InMemoryVectorStore<D>) -> Self {
+        Self {
+            model,
+            store: Arc::new(RwLock::new(store)),
+        }
+    }
+
+    /// Lock the store for reading.
+    pub fn read(&self) -> RwLockReadGuard<'_, InMemoryVectorStore<D>> {
+        self.store.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock the store for writing.
+    pub fn write(&self) -> RwLockWriteGuard<'_, InMemoryVectorStore<D>> {
+        self.store.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// [Snapshot](InMemoryVectorStore::snapshot) of the current state of the store.
+    pub fn snapshot(&self) -> InMemoryVectorStore<D> {
+        self.read().snapshot()
+    }
+
+    /// Reset the store to the state of `snapshot`, for all the clones of the index.
+    pub fn restore(&self, snapshot: &InMemoryVectorStore<D>) {
+        self.write().restore(snapshot);
+    }
+}
+
+impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> VectorStoreIndex
+    for SharedInMemoryIndex<M, D>
+{
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.top_n_with_options(query, n, &SearchOptions::default())
+            .await
     }
 
     async fn top_n_ids(
@@ -248,24 +1091,100 @@ impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> VectorStoreIndex
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String)>, VectorStoreError> {
-        let prompt_embedding = &self.model.embed_text(query).await?;
+        self.top_n_ids_with_options(query, n, &SearchOptions::default())
+            .await
+    }
 
-        let docs = self.store.vector_search(prompt_embedding, n);
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let options = SearchOptions::new().filter(filter.clone());
+        self.top_n_with_options(query, n, &options).await
+    }
 
-        // Return n best
-        docs.into_iter()
-            .map(|Reverse(RankingItem(distance, id, _, _))| Ok((distance.0, id.clone())))
-            .collect::<Result<Vec<_>, _>>()
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let options = SearchOptions::new().filter(filter.clone());
+        self.top_n_ids_with_options(query, n, &options).await
+    }
+
+    async fn top_n_with_options<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+        self.read().search(&prompt_embedding, n, options)
+    }
+
+    async fn top_n_ids_with_options(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+        Ok(self.read().search_ids(&prompt_embedding, n, options))
+    }
+}
+
+impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> SharedInMemoryIndex<M, D> {
+    /// Same as [top_n_with_options](VectorStoreIndex::top_n_with_options), but also returns
+    /// which embedding of each document matched the query best.
+    pub async fn top_n_matches<T: for<'a> Deserialize<'a>>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchMatch<T>>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+        self.read().search_matches(&prompt_embedding, n, options)
+    }
+}
+
+/// Inserts the documents with generated ids (see [InMemoryVectorStore::add_documents]).
+impl<D: Serialize + Eq + Send + Sync> InsertDocuments<D> for InMemoryVectorStore<D> {
+    async fn insert_documents(
+        &mut self,
+        documents: Vec<(D, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.add_documents(documents);
+        Ok(())
+    }
+
+    async fn upsert_documents(
+        &mut self,
+        documents: Vec<(String, D, Value, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.add_documents_with_metadata(documents);
+        Ok(())
+    }
+
+    async fn delete_documents(&mut self, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        for id in ids {
+            self.delete(&id);
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::cmp::Reverse;
+    use std::{cmp::Reverse, sync::Arc};
 
     use crate::{embeddings::embedding::Embedding, OneOrMany};
 
-    use super::{InMemoryVectorStore, RankingItem};
+    use super::{
+        DistanceMetric, Filter, InMemoryVectorStore, RankingItem, ScoreAggregation, SearchOptions,
+    };
 
     #[test]
     fn test_auto_ids() {
@@ -310,7 +1229,10 @@ mod tests {
             ),
         ]);
 
-        let mut store = vector_store.embeddings.into_iter().collect::<Vec<_>>();
+        let mut store = vector_store
+            .iter()
+            .map(|(id, entry)| (id.clone(), entry.clone()))
+            .collect::<Vec<_>>();
         store.sort_by_key(|(id, _)| id.clone());
 
         assert_eq!(
@@ -370,6 +1292,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_auto_ids_after_removal() {
+        let embedding = |document: &str| {
+            OneOrMany::one(Embedding {
+                document: document.to_string(),
+                vec: vec![0.1, 0.1, 0.5],
+            })
+        };
+        let mut vector_store = InMemoryVectorStore::from_documents(vec![
+            ("glarb-garb", embedding("glarb-garb")),
+            ("marble-marble", embedding("marble-marble")),
+        ]);
+
+        assert!(vector_store.remove("doc0").is_some());
+        vector_store.add_documents(vec![("brotato", embedding("brotato"))]);
+
+        let mut ids = vector_store
+            .iter()
+            .map(|(id, (doc, _))| (id.clone(), *doc))
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                ("doc1".to_string(), "marble-marble"),
+                ("doc2".to_string(), "brotato"),
+            ]
+        );
+    }
+
     #[test]
     fn test_single_embedding() {
         let vector_store = InMemoryVectorStore::from_documents_with_ids(vec![
@@ -405,12 +1357,14 @@ mod tests {
                 vec: vec![0.0, 0.1, 0.6],
             },
             1,
+            None,
+            &ScoreAggregation::Max,
         );
 
         assert_eq!(
             ranking
                 .into_iter()
-                .map(|Reverse(RankingItem(distance, id, doc, _))| {
+                .map(|Reverse(RankingItem(distance, id, doc, _, _))| {
                     (
                         distance.0,
                         id.clone(),
@@ -482,12 +1436,14 @@ mod tests {
                 vec: vec![0.0, 0.1, 0.6],
             },
             1,
+            None,
+            &ScoreAggregation::Max,
         );
 
         assert_eq!(
             ranking
                 .into_iter()
-                .map(|Reverse(RankingItem(distance, id, doc, _))| {
+                .map(|Reverse(RankingItem(distance, id, doc, _, _))| {
                     (
                         distance.0,
                         id.clone(),
@@ -502,4 +1458,509 @@ mod tests {
             )]
         )
     }
+
+    #[test]
+    fn test_filtered_search() {
+        let mut vector_store = InMemoryVectorStore::default();
+        vector_store.add_documents_with_metadata(vec![
+            (
+                "doc1",
+                "glarb-garb",
+                serde_json::json!({"tenant": "acme", "year": 2023}),
+                OneOrMany::one(Embedding {
+                    document: "glarb-garb".to_string(),
+                    vec: vec![0.1, 0.1, 0.5],
+                }),
+            ),
+            (
+                "doc2",
+                "marble-marble",
+                serde_json::json!({"tenant": "acme", "year": 2024}),
+                OneOrMany::one(Embedding {
+                    document: "marble-marble".to_string(),
+                    vec: vec![0.7, -0.3, 0.0],
+                }),
+            ),
+        ]);
+        vector_store.add_documents_with_ids(vec![(
+            "doc3",
+            "flumb-flumb",
+            OneOrMany::one(Embedding {
+                document: "flumb-flumb".to_string(),
+                vec: vec![0.3, 0.7, 0.1],
+            }),
+        )]);
+
+        let search = |filter: &Filter| {
+            let mut ids = vector_store
+                .vector_search(
+                    &Embedding {
+                        document: "glarby-glarble".to_string(),
+                        vec: vec![0.0, 0.1, 0.6],
+                    },
+                    3,
+                    Some(filter),
+                    &ScoreAggregation::Max,
+                )
+                .into_iter()
+                .map(|Reverse(RankingItem(_, id, _, _, _))| id.clone())
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(search(&Filter::eq("tenant", "acme")), vec!["doc1", "doc2"]);
+        assert_eq!(
+            search(&Filter::eq("tenant", "acme").and(Filter::gte("year", 2024))),
+            vec!["doc2"]
+        );
+        assert!(search(&Filter::eq("tenant", "globex")).is_empty());
+        assert_eq!(
+            vector_store.get_metadata("doc1"),
+            Some(&serde_json::json!({"tenant": "acme", "year": 2023}))
+        );
+    }
+
+    #[test]
+    fn test_search_options() {
+        let embedding = |document: &str, vec: Vec<f64>| {
+            OneOrMany::one(Embedding {
+                document: document.to_string(),
+                vec,
+            })
+        };
+        let vector_store = InMemoryVectorStore::from_documents_with_ids(vec![
+            (
+                "doc1",
+                "glarb-garb",
+                embedding("glarb-garb", vec![1.0, 0.0, 0.0]),
+            ),
+            (
+                "doc2",
+                "glarb-garb!",
+                embedding("glarb-garb!", vec![0.99, 0.1, 0.0]),
+            ),
+            (
+                "doc3",
+                "marble-marble",
+                embedding("marble-marble", vec![0.7, 0.7, 0.0]),
+            ),
+            (
+                "doc4",
+                "flumb-flumb",
+                embedding("flumb-flumb", vec![0.0, 0.0, 1.0]),
+            ),
+        ]);
+        let query = Embedding {
+            document: "glarby-glarble".to_string(),
+            vec: vec![1.0, 0.05, 0.0],
+        };
+        let search = |n: usize, options: SearchOptions| {
+            vector_store
+                .vector_search_with_options(&query, n, &options)
+                .into_iter()
+                .map(|RankingItem(_, id, _, _, _)| id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(search(2, SearchOptions::new()), vec!["doc1", "doc2"]);
+        // The near-duplicate of the most relevant document is skipped
+        assert_eq!(
+            search(2, SearchOptions::new().mmr(0.5)),
+            vec!["doc1", "doc3"]
+        );
+        assert_eq!(
+            search(4, SearchOptions::new().min_score(0.8)),
+            vec!["doc1", "doc2"]
+        );
+        assert_eq!(
+            search(3, SearchOptions::new().min_score(0.5).mmr(0.5)),
+            vec!["doc1", "doc3", "doc2"]
+        );
+    }
+
+    #[test]
+    fn test_score_aggregation() {
+        let embeddings = |title: Vec<f64>, body: Vec<f64>| {
+            OneOrMany::many(vec![
+                Embedding {
+                    document: "title".to_string(),
+                    vec: title,
+                },
+                Embedding {
+                    document: "body".to_string(),
+                    vec: body,
+                },
+            ])
+            .unwrap()
+        };
+        let vector_store = InMemoryVectorStore::from_documents_with_ids(vec![
+            (
+                "doc1",
+                "glarb-garb",
+                embeddings(vec![1.0, 0.0], vec![0.0, 0.0]),
+            ),
+            (
+                "doc2",
+                "marble-marble",
+                embeddings(vec![0.6, 0.0], vec![0.6, 0.0]),
+            ),
+        ])
+        .with_distance(DistanceMetric::DotProduct);
+        let query = Embedding {
+            document: "glarby-glarble".to_string(),
+            vec: vec![1.0, 0.0],
+        };
+        let search = |aggregation: ScoreAggregation| {
+            vector_store
+                .search_matches::<String>(&query, 2, &SearchOptions::new().aggregation(aggregation))
+                .unwrap()
+                .into_iter()
+                .map(|result| (result.id, result.score, result.matched_text))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            search(ScoreAggregation::Max),
+            vec![
+                ("doc1".to_string(), 1.0, "title".to_string()),
+                ("doc2".to_string(), 0.6, "title".to_string())
+            ]
+        );
+        assert_eq!(
+            search(ScoreAggregation::Mean),
+            vec![
+                ("doc2".to_string(), 0.6, "title".to_string()),
+                ("doc1".to_string(), 0.5, "title".to_string())
+            ]
+        );
+        assert_eq!(
+            search(ScoreAggregation::Weighted(vec![3.0, 1.0])),
+            vec![
+                ("doc1".to_string(), 0.75, "title".to_string()),
+                ("doc2".to_string(), 0.6, "title".to_string())
+            ]
+        );
+        // The titles are ignored
+        assert_eq!(
+            search(ScoreAggregation::Weighted(vec![0.0, 1.0])),
+            vec![
+                ("doc2".to_string(), 0.6, "body".to_string()),
+                ("doc1".to_string(), 0.0, "body".to_string())
+            ]
+        );
+        assert_eq!(
+            ScoreAggregation::Weighted(vec![0.0]).aggregate([1.0, 0.5]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_distance_metrics() {
+        let embedding = |vec: Vec<f64>| {
+            OneOrMany::one(Embedding {
+                document: String::new(),
+                vec,
+            })
+        };
+        let vector_store = InMemoryVectorStore::from_documents_with_ids(vec![
+            ("small", "small", embedding(vec![1.0, 1.0])),
+            ("large", "large", embedding(vec![10.0, 9.0])),
+        ]);
+        let query = Embedding {
+            document: String::new(),
+            vec: vec![1.0, 0.9],
+        };
+        let best = |store: &InMemoryVectorStore<&str>| {
+            store.search_ids(&query, 1, &SearchOptions::new())[0].clone()
+        };
+
+        // The large vector points in the direction of the query, but is far from it
+        assert_eq!(best(&vector_store).1, "large");
+        let (score, id) = best(
+            &vector_store
+                .clone()
+                .with_distance(DistanceMetric::DotProduct),
+        );
+        assert_eq!(id, "large");
+        assert!((score - 18.1).abs() < 1e-9);
+        let (score, id) = best(&vector_store.with_distance(DistanceMetric::Euclidean));
+        assert_eq!(id, "small");
+        assert!((score - 1.0 / 1.1).abs() < 1e-9);
+    }
+
+    #[cfg(feature = "hnsw")]
+    #[test]
+    fn test_hnsw_search() {
+        use crate::vector_store::hnsw::HnswConfig;
+
+        // Points spread over the unit sphere
+        let embedding = |i: usize| {
+            let (a, b) = (i as f64 * 2.399_963, i as f64 * 0.618_034);
+            OneOrMany::one(Embedding {
+                document: format!("point {i}"),
+                vec: vec![a.cos() * b.sin(), a.sin() * b.sin(), b.cos()],
+            })
+        };
+        let mut vector_store = InMemoryVectorStore::from_documents_with_ids(
+            (0..1000).map(|i| (format!("doc{i}"), i, embedding(i))),
+        );
+        let query = Embedding {
+            document: "query".to_string(),
+            vec: vec![0.3, -0.5, 0.8],
+        };
+        let search = |store: &InMemoryVectorStore<usize>, options: &SearchOptions| {
+            store
+                .search_ids(&query, 5, options)
+                .into_iter()
+                .map(|(_, id)| id)
+                .collect::<Vec<_>>()
+        };
+        let exact = search(&vector_store, &SearchOptions::new());
+
+        let mut indexed = vector_store.clone().with_hnsw(HnswConfig::new());
+        assert_eq!(search(&indexed, &SearchOptions::new()), exact);
+
+        // Documents added to or removed from the store are indexed
+        let removed = exact[0].clone();
+        vector_store.remove(&removed);
+        indexed.remove(&removed);
+        vector_store.add_documents_with_metadata(vec![(
+            "even",
+            0,
+            serde_json::json!({"even": true}),
+            embedding(2),
+        )]);
+        indexed.add_documents_with_metadata(vec![(
+            "even",
+            0,
+            serde_json::json!({"even": true}),
+            embedding(2),
+        )]);
+        assert_eq!(
+            search(&indexed, &SearchOptions::new()),
+            search(&vector_store, &SearchOptions::new())
+        );
+
+        // Searches are widened until enough documents match the filter
+        let filtered = SearchOptions::new().filter(Filter::eq("even", true));
+        assert_eq!(search(&indexed, &filtered), vec!["even"]);
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let embedding = |document: &str, vec: Vec<f64>| {
+            OneOrMany::one(Embedding {
+                document: document.to_string(),
+                vec,
+            })
+        };
+        let mut vector_store = InMemoryVectorStore::from_documents_with_ids(vec![
+            (
+                "doc1",
+                "glarb-garb",
+                embedding("glarb-garb", vec![0.1, 0.1, 0.5]),
+            ),
+            (
+                "doc2",
+                "marble-marble",
+                embedding("marble-marble", vec![0.7, -0.3, 0.0]),
+            ),
+        ]);
+        let snapshot = vector_store.snapshot();
+
+        // The snapshot shares the documents and embeddings of the store
+        assert!(Arc::ptr_eq(
+            &vector_store.embeddings["doc1"],
+            &snapshot.embeddings["doc1"]
+        ));
+
+        // Changes of the store don't affect the snapshot, and conversely
+        vector_store.add_documents_with_ids(vec![(
+            "doc3",
+            "flumb-flumb",
+            embedding("flumb-flumb", vec![0.3, 0.7, 0.1]),
+        )]);
+        vector_store.set_metadata("doc1", serde_json::json!({"tenant": "acme"}));
+        assert_eq!(
+            vector_store.remove("doc2").map(|(document, _)| document),
+            Some("marble-marble")
+        );
+        let mut branch = snapshot.snapshot();
+        assert!(branch.delete("doc1"));
+        assert_eq!(vector_store.len(), 2);
+        assert_eq!(snapshot.len(), 2);
+        assert!(snapshot.get_metadata("doc1").is_none());
+        assert_eq!(
+            snapshot.get_document::<String>("doc2").unwrap().as_deref(),
+            Some("marble-marble")
+        );
+
+        vector_store.restore(&snapshot);
+        let mut ids = vector_store
+            .iter()
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec!["doc1", "doc2"]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut vector_store = InMemoryVectorStore::default();
+        vector_store.add_documents_with_metadata(vec![(
+            "doc1",
+            "glarb-garb".to_string(),
+            serde_json::json!({"lang": "en"}),
+            OneOrMany::many(vec![
+                Embedding {
+                    document: "glarb".to_string(),
+                    vec: vec![0.1, -0.2],
+                },
+                Embedding {
+                    document: "garb".to_string(),
+                    vec: vec![f64::MAX, 1e-300],
+                },
+            ])
+            .unwrap(),
+        )]);
+        vector_store.add_documents_with_ids(vec![(
+            "doc2",
+            "marble-marble".to_string(),
+            OneOrMany::one(Embedding {
+                document: "marble".to_string(),
+                vec: vec![0.3, 0.4],
+            }),
+        )]);
+
+        let dir = assert_fs::TempDir::new().unwrap();
+        let json = dir.path().join("store.json");
+        let binary = dir.path().join("store.bin");
+        vector_store.save_json(&json).unwrap();
+        vector_store.save(&binary).unwrap();
+        assert!(
+            std::fs::metadata(&binary).unwrap().len() < std::fs::metadata(&json).unwrap().len()
+        );
+
+        let documents = |store: &InMemoryVectorStore<String>| {
+            let mut documents = store
+                .iter()
+                .map(|(id, (doc, embeddings))| {
+                    (
+                        id.clone(),
+                        doc.clone(),
+                        store.get_metadata(id).cloned(),
+                        embeddings
+                            .iter()
+                            .map(|embedding| (embedding.document.clone(), embedding.vec.clone()))
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            documents.sort_by(|a, b| a.0.cmp(&b.0));
+            documents
+        };
+        for path in [&json, &binary] {
+            let loaded = InMemoryVectorStore::<String>::load(path).unwrap();
+            assert_eq!(documents(&loaded), documents(&vector_store));
+        }
+
+        // Compressed files are transparently decompressed
+        #[cfg(feature = "zstd")]
+        {
+            let compressed = dir.path().join("store.zst");
+            vector_store.save_compressed(&compressed, 3).unwrap();
+            let loaded = InMemoryVectorStore::<String>::load(&compressed).unwrap();
+            assert_eq!(documents(&loaded), documents(&vector_store));
+        }
+
+        // Encrypted files do not contain the documents in plaintext
+        #[cfg(feature = "encryption")]
+        {
+            use crate::storage::encrypted::{EncryptionKey, StaticKeys};
+
+            let keys = StaticKeys::new("v1", EncryptionKey::new([7; 32]));
+            let encrypted = dir.path().join("store.enc");
+            vector_store.save_encrypted(&encrypted, &keys).unwrap();
+            let bytes = std::fs::read(&encrypted).unwrap();
+            assert!(!String::from_utf8_lossy(&bytes).contains("marble"));
+
+            let loaded = InMemoryVectorStore::<String>::load_encrypted(&encrypted, &keys).unwrap();
+            assert_eq!(documents(&loaded), documents(&vector_store));
+            assert!(InMemoryVectorStore::<String>::load(&encrypted).is_err());
+            let keys = StaticKeys::new("v1", EncryptionKey::new([8; 32]));
+            assert!(InMemoryVectorStore::<String>::load_encrypted(&encrypted, &keys).is_err());
+        }
+
+        // Truncated files are rejected
+        let bytes = std::fs::read(&binary).unwrap();
+        std::fs::write(&binary, &bytes[..bytes.len() - 4]).unwrap();
+        assert!(InMemoryVectorStore::<String>::load(&binary).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upsert_and_delete_documents() {
+        use serde_json::json;
+
+        use crate::vector_store::{InsertDocuments, VectorStoreError};
+
+        let embedding = |text: &str| {
+            OneOrMany::one(Embedding {
+                document: text.to_string(),
+                vec: vec![0.1, 0.2],
+            })
+        };
+        let mut vector_store = InMemoryVectorStore::<String>::default();
+        vector_store
+            .upsert_documents(vec![
+                (
+                    "a".into(),
+                    "first".into(),
+                    json!({"v": 1}),
+                    embedding("first"),
+                ),
+                ("b".into(), "second".into(), json!({}), embedding("second")),
+            ])
+            .await
+            .unwrap();
+        vector_store
+            .upsert_documents(vec![(
+                "a".into(),
+                "first, again".into(),
+                json!({"v": 2}),
+                embedding("first, again"),
+            )])
+            .await
+            .unwrap();
+        assert_eq!(vector_store.len(), 2);
+        assert_eq!(
+            vector_store.get_document::<String>("a").unwrap(),
+            Some("first, again".to_string())
+        );
+        assert_eq!(vector_store.get_metadata("a"), Some(&json!({"v": 2})));
+
+        vector_store
+            .delete_documents(vec!["b".into(), "unknown".into()])
+            .await
+            .unwrap();
+        assert_eq!(vector_store.len(), 1);
+
+        // Stores without ids do not support them
+        let mut documents: Vec<(String, OneOrMany<Embedding>)> = vec![];
+        assert!(matches!(
+            documents
+                .upsert_documents(vec![(
+                    "a".into(),
+                    "first".to_string(),
+                    json!({}),
+                    embedding("first")
+                )])
+                .await,
+            Err(VectorStoreError::UpsertNotSupported)
+        ));
+        assert!(matches!(
+            documents.delete_documents(vec!["a".into()]).await,
+            Err(VectorStoreError::DeleteNotSupported)
+        ));
+    }
 }
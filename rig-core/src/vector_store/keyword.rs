@@ -0,0 +1,321 @@
+//! In-memory keyword index ranking documents with BM25.
+//!
+//! Unlike embeddings, keyword search matches exact terms (e.g.: identifiers, error codes or rare
+//! words), which is why it is often combined with vector search in a
+//! [HybridIndex](super::hybrid::HybridIndex).
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{in_memory_store::InMemoryVectorStore, Filter, VectorStoreError, VectorStoreIndex};
+
+/// [Bm25Index] is a keyword index scoring documents with the Okapi BM25 ranking function.
+/// Text is split into lowercase terms made of alphanumeric characters and underscores.
+#[derive(Clone)]
+pub struct Bm25Index<D: Serialize> {
+    /// Documents by id, with the frequency of their terms and their length (in terms)
+    documents: HashMap<String, (D, HashMap<String, usize>, usize)>,
+    /// Number of documents containing each term
+    document_frequencies: HashMap<String, usize>,
+    /// JSON metadata of the documents (by document id), matched against search filters
+    metadata: HashMap<String, Value>,
+    total_length: usize,
+    k1: f64,
+    b: f64,
+}
+
+impl<D: Serialize> Default for Bm25Index<D> {
+    fn default() -> Self {
+        Self {
+            documents: HashMap::new(),
+            document_frequencies: HashMap::new(),
+            metadata: HashMap::new(),
+            total_length: 0,
+            k1: 1.2,
+            b: 0.75,
+        }
+    }
+}
+
+impl<D: Serialize> Bm25Index<D> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new [Bm25Index] from documents with ids and their text.
+    pub fn from_documents(documents: impl IntoIterator<Item = (impl ToString, String, D)>) -> Self {
+        let mut index = Self::new();
+        index.add_documents(documents);
+        index
+    }
+
+    /// Create a new [Bm25Index] of the documents of a vector store, indexing the texts of their
+    /// embeddings (so that both indexes can be combined in a [HybridIndex](super::hybrid::HybridIndex)).
+    /// The metadata of the documents is copied, so that the searches can be filtered the same way.
+    pub fn from_store(store: &InMemoryVectorStore<D>) -> Self
+    where
+        D: Clone,
+    {
+        let mut index = Self::from_documents(store.iter().map(|(id, (doc, embeddings))| {
+            let text = embeddings
+                .iter()
+                .map(|embedding| embedding.document.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            (id, text, doc.clone())
+        }));
+        index.metadata = store
+            .iter()
+            .filter_map(|(id, _)| Some((id.clone(), store.get_metadata(id)?.clone())))
+            .collect();
+        index
+    }
+
+    /// Set the term frequency saturation parameter (defaults to 1.2).
+    pub fn k1(mut self, k1: f64) -> Self {
+        self.k1 = k1;
+        self
+    }
+
+    /// Set the document length normalization parameter, from 0 to 1 (defaults to 0.75).
+    pub fn b(mut self, b: f64) -> Self {
+        self.b = b;
+        self
+    }
+
+    /// Add documents with ids and their text to the index, replacing the documents with the same ids.
+    pub fn add_documents(
+        &mut self,
+        documents: impl IntoIterator<Item = (impl ToString, String, D)>,
+    ) {
+        documents.into_iter().for_each(|(id, text, doc)| {
+            let id = id.to_string();
+            self.remove_terms(&id);
+
+            let terms = tokenize(&text);
+            let length = terms.len();
+            let mut frequencies = HashMap::new();
+            for term in terms {
+                *frequencies.entry(term).or_insert(0) += 1;
+            }
+            for term in frequencies.keys() {
+                *self.document_frequencies.entry(term.clone()).or_insert(0) += 1;
+            }
+            self.total_length += length;
+            self.documents.insert(id, (doc, frequencies, length));
+        });
+    }
+
+    /// Add documents with ids, their text and their JSON metadata to the index, replacing the
+    /// documents with the same ids.
+    pub fn add_documents_with_metadata(
+        &mut self,
+        documents: impl IntoIterator<Item = (impl ToString, String, D, Value)>,
+    ) {
+        documents.into_iter().for_each(|(id, text, doc, metadata)| {
+            let id = id.to_string();
+            self.metadata.insert(id.clone(), metadata);
+            self.add_documents([(id, text, doc)]);
+        });
+    }
+
+    /// Set the JSON metadata of the document with the given id.
+    pub fn set_metadata(&mut self, id: impl ToString, metadata: Value) {
+        self.metadata.insert(id.to_string(), metadata);
+    }
+
+    /// Remove the document with the given id, and its metadata, from the index.
+    pub fn remove(&mut self, id: &str) -> Option<D> {
+        self.metadata.remove(id);
+        self.remove_terms(id)
+    }
+
+    /// Remove the document with the given id and its terms from the statistics of the index
+    fn remove_terms(&mut self, id: &str) -> Option<D> {
+        let (doc, frequencies, length) = self.documents.remove(id)?;
+        for term in frequencies.keys() {
+            if let Some(frequency) = self.document_frequencies.get_mut(term) {
+                *frequency -= 1;
+                if *frequency == 0 {
+                    self.document_frequencies.remove(term);
+                }
+            }
+        }
+        self.total_length -= length;
+        Some(doc)
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Get the `n` documents with the best (positive) BM25 score for `query`, best first, whose
+    /// metadata matches `filter` if any (documents without metadata are matched as `null`).
+    fn search(&self, query: &str, n: usize, filter: Option<&Filter>) -> Vec<(f64, &String, &D)> {
+        let count = self.documents.len() as f64;
+        let average_length = self.total_length as f64 / count.max(1.0);
+        let terms = tokenize(query)
+            .into_iter()
+            .filter_map(|term| {
+                let frequency = *self.document_frequencies.get(&term)? as f64;
+                let idf = (1.0 + (count - frequency + 0.5) / (frequency + 0.5)).ln();
+                Some((term, idf))
+            })
+            .collect::<Vec<_>>();
+
+        let mut scores = self
+            .documents
+            .iter()
+            .filter(|(id, _)| {
+                filter.is_none_or(|filter| {
+                    filter.matches(self.metadata.get(*id).unwrap_or(&Value::Null))
+                })
+            })
+            .map(|(id, (doc, frequencies, length))| {
+                let normalization =
+                    self.k1 * (1.0 - self.b + self.b * *length as f64 / average_length.max(1.0));
+                let score = terms
+                    .iter()
+                    .filter_map(|(term, idf)| {
+                        let frequency = *frequencies.get(term)? as f64;
+                        Some(idf * frequency * (self.k1 + 1.0) / (frequency + normalization))
+                    })
+                    .sum::<f64>();
+                (score, id, doc)
+            })
+            .filter(|(score, _, _)| *score > 0.0)
+            .collect::<Vec<_>>();
+
+        scores.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        scores.truncate(n);
+        scores
+    }
+}
+
+/// Split text into lowercase terms
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Deserialize the documents of search results
+fn deserialize<D: Serialize, T: for<'a> Deserialize<'a>>(
+    results: Vec<(f64, &String, &D)>,
+) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+    results
+        .into_iter()
+        .map(|(score, id, doc)| {
+            Ok((
+                score,
+                id.clone(),
+                serde_json::from_value(serde_json::to_value(doc)?)?,
+            ))
+        })
+        .collect()
+}
+
+fn ids<D>(results: Vec<(f64, &String, &D)>) -> Vec<(f64, String)> {
+    results
+        .into_iter()
+        .map(|(score, id, _)| (score, id.clone()))
+        .collect()
+}
+
+impl<D: Serialize + Send + Sync> VectorStoreIndex for Bm25Index<D> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        deserialize(self.search(query, n, None))
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        Ok(ids(self.search(query, n, None)))
+    }
+
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        deserialize(self.search(query, n, Some(filter)))
+    }
+
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        Ok(ids(self.search(query, n, Some(filter))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{tokenize, Bm25Index};
+    use crate::vector_store::Filter;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Error E_1042: can't open config.toml"),
+            vec!["error", "e_1042", "can", "t", "open", "config", "toml"]
+        );
+    }
+
+    #[test]
+    fn test_bm25_search() {
+        let mut index = Bm25Index::from_documents(vec![
+            ("doc1", "The build fails with error E_1042".to_string(), 1),
+            ("doc2", "How to fix a failing build".to_string(), 2),
+            ("doc3", "Release notes of the build system".to_string(), 3),
+        ]);
+
+        // Rare terms outweigh common ones
+        let results = index.search("build error E_1042", 3, None);
+        assert_eq!(results[0].1, "doc1");
+        assert_eq!(results.len(), 3);
+        assert!(index.search("nothing matches", 3, None).is_empty());
+
+        // Replacing a document updates the statistics
+        index.add_documents(vec![("doc1", "Unrelated text".to_string(), 1)]);
+        assert_eq!(index.len(), 3);
+        assert!(index.search("E_1042", 3, None).is_empty());
+
+        // Filtered searches only match the documents with matching metadata
+        index.set_metadata("doc2", json!({"kind": "guide"}));
+        index.add_documents_with_metadata(vec![(
+            "doc4",
+            "Build guide".to_string(),
+            4,
+            json!({"kind": "guide"}),
+        )]);
+        let filter = Filter::eq("kind", "guide");
+        let results = index.search("build", 3, Some(&filter));
+        let mut ids = results
+            .iter()
+            .map(|(_, id, _)| id.as_str())
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec!["doc2", "doc4"]);
+
+        assert_eq!(index.remove("doc4"), Some(4));
+        assert_eq!(index.search("build", 3, Some(&filter)).len(), 1);
+    }
+}
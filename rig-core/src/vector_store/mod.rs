@@ -1,12 +1,82 @@
+//! Vector store traits, implemented by the vector store integrations, and the indexes built on
+//! top of them (in-memory, keyword, hybrid, federated, ...).
+//!
+//! # Interface of the vector store integrations
+//! Vector store integrations (e.g.: `rig-qdrant`) can be maintained out-of-tree, as they only
+//! depend on the following items, which only change with a new minor version of `rig-core`
+//! (along with the companion crates of this repository):
+//! - [VectorStoreIndex]: searches of a store. Only [VectorStoreIndex::top_n] and
+//!   [VectorStoreIndex::top_n_ids] are required: the other methods, including the ones added
+//!   later, have default implementations.
+//! - [VectorStoreIndexDyn]: object-safe version of [VectorStoreIndex] (used by agents),
+//!   implemented for all the indexes, which should not implement it themselves.
+//! - [InsertDocuments]: batch inserts of embedded documents (e.g.: the initial ingestion), and
+//!   upserts and deletes by document id for the stores supporting them.
+//! - [DocumentSink](events::DocumentSink): writes to stores updated while they are used.
+//! - [VectorStoreError]: errors of the searches and writes. It is non-exhaustive, and the errors
+//!   specific to a store are reported as [VectorStoreError::DatastoreError].
+//! - [Filter] and [SearchOptions]: options of the searches. [Filter] is exhaustive, so that
+//!   integrations translating filters into queries handle all of them.
+//!
+//! The [conformance] module checks the behavior of integrations against this contract.
+//!
+//! # Typed results
+//! The documents are stored with their serde serialization (including their fields which are
+//! not embedded), and the searches deserialize them into the type of the caller's choice: the
+//! document type itself, a type with a subset of its fields, or [serde_json::Value]:
+//! ```rust
+//! #[derive(Embed, Serialize, Deserialize)]
+//! struct Book {
+//!     id: String,
+//!     #[embed]
+//!     summary: String,
+//!     year: u32,
+//! }
+//!
+//! let results: Vec<(f64, String, Book)> = index.top_n::<Book>("desert planet", 5).await?;
+//! for (score, id, book) in results {
+//!     println!("{score:.2} {id} ({})", book.year);
+//! }
+//! ```
+use std::future::Future;
+
 use futures::future::BoxFuture;
 use serde::Deserialize;
 use serde_json::Value;
 
-use crate::embeddings::EmbeddingError;
+use crate::{
+    embeddings::{Embedding, EmbeddingError},
+    rerank::{RerankError, RerankedIndex, Reranker},
+    OneOrMany,
+};
 
+pub mod acl;
+pub mod conformance;
+pub mod events;
+pub mod fallback;
+pub mod federated;
+pub mod filter;
+#[cfg(feature = "hnsw")]
+pub mod hnsw;
+pub mod hybrid;
 pub mod in_memory_store;
+pub mod keyword;
+pub mod query_transform;
+pub mod routed;
+pub mod self_query;
+pub mod sync;
+
+pub use acl::AclIndex;
+pub use fallback::FallbackIndex;
+pub use federated::FederatedIndex;
+pub use filter::Filter;
+pub use hybrid::HybridIndex;
+pub use keyword::Bm25Index;
+pub use query_transform::TransformedIndex;
+pub use routed::RoutedIndex;
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum VectorStoreError {
     #[error("Embedding error: {0}")]
     EmbeddingError(#[from] EmbeddingError),
@@ -15,17 +85,172 @@ pub enum VectorStoreError {
     #[error("Json error: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[error("Rerank error: {0}")]
+    RerankError(#[from] RerankError),
+
+    /// Io error (e.g.: saving or loading a vector store file)
+    #[error("Io error: {0}")]
+    IoError(#[from] std::io::Error),
+
     #[error("Datastore error: {0}")]
     DatastoreError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 
     #[error("Missing Id: {0}")]
     MissingIdError(String),
+
+    /// The vector store does not support metadata filters
+    #[error("Metadata filters are not supported by this vector store")]
+    FilterNotSupported,
+
+    /// The vector store does not support maximal marginal relevance re-ranking
+    #[error("MMR re-ranking is not supported by this vector store")]
+    MmrNotSupported,
+
+    /// The vector store does not support other score aggregations than [ScoreAggregation::Max]
+    #[error("Score aggregation {0:?} is not supported by this vector store")]
+    AggregationNotSupported(ScoreAggregation),
+
+    /// The vector store does not support upserting documents by id
+    #[error("Upserts are not supported by this vector store")]
+    UpsertNotSupported,
+
+    /// The vector store does not support deleting documents by id
+    #[error("Deletes are not supported by this vector store")]
+    DeleteNotSupported,
+}
+
+/// Aggregation of the scores of the embeddings of a document (e.g.: one embedding per `#[embed]`
+/// field, in the order of the fields) into the score of the document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ScoreAggregation {
+    /// Score of the best-matching embedding
+    #[default]
+    Max,
+    /// Mean of the scores of the embeddings
+    Mean,
+    /// Weighted mean of the scores of the embeddings, by position: the `i`-th embedding is
+    /// weighted by the `i`-th weight, or the last one if there are fewer weights than embeddings
+    /// (e.g.: `Weighted(vec![2.0, 1.0])` counts the first embedding twice as much as each of the
+    /// others). Embeddings with a weight of 0 are ignored.
+    Weighted(Vec<f64>),
+}
+
+impl ScoreAggregation {
+    /// Weight of the embedding at `position`.
+    pub fn weight(&self, position: usize) -> f64 {
+        match self {
+            Self::Max | Self::Mean => 1.0,
+            Self::Weighted(weights) => weights
+                .get(position)
+                .or(weights.last())
+                .copied()
+                .unwrap_or(1.0),
+        }
+    }
+
+    /// Aggregate the scores of the embeddings of a document, in the order of the embeddings.
+    /// Returns the score of the document and the position of its best-matching embedding (the
+    /// highest score with a non-zero weight), or `None` if no embedding has a non-zero weight.
+    pub fn aggregate(&self, scores: impl IntoIterator<Item = f64>) -> Option<(f64, usize)> {
+        let mut best: Option<(f64, usize)> = None;
+        let (mut total, mut total_weight) = (0.0, 0.0);
+        for (position, score) in scores.into_iter().enumerate() {
+            let weight = self.weight(position);
+            if weight == 0.0 {
+                continue;
+            }
+            if best.is_none_or(|(best, _)| score > best) {
+                best = Some((score, position));
+            }
+            total += weight * score;
+            total_weight += weight;
+        }
+
+        let (best, position) = best?;
+        match self {
+            Self::Max => Some((best, position)),
+            Self::Mean | Self::Weighted(_) => Some((total / total_weight, position)),
+        }
+    }
+}
+
+/// Options of a vector store search, see [VectorStoreIndex::top_n_with_options].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchOptions {
+    /// Minimum similarity score of the returned documents (see [VectorStoreIndex#scores])
+    pub min_score: Option<f64>,
+    /// Trade-off between relevance (1) and diversity (0) of maximal marginal relevance (MMR)
+    /// re-ranking, if enabled
+    pub mmr_lambda: Option<f64>,
+    /// Number of candidate documents re-ranked with MMR (defaults to 4 times the number of
+    /// returned documents)
+    pub fetch_k: Option<usize>,
+    /// Filter on the metadata of the documents
+    pub filter: Option<Filter>,
+    /// Aggregation of the scores of the embeddings of each document
+    pub aggregation: ScoreAggregation,
+}
+
+impl SearchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only return documents whose similarity score is at least `min_score`.
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    /// Re-rank the results with maximal marginal relevance, selecting documents that are
+    /// relevant to the query (weighted by `lambda`) but dissimilar to the documents already
+    /// selected (weighted by `1 - lambda`), to avoid returning near-duplicates.
+    pub fn mmr(mut self, lambda: f64) -> Self {
+        self.mmr_lambda = Some(lambda.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Set the number of candidate documents re-ranked with MMR.
+    pub fn fetch_k(mut self, fetch_k: usize) -> Self {
+        self.fetch_k = Some(fetch_k);
+        self
+    }
+
+    /// Only return documents whose metadata matches `filter`.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Score the documents with multiple embeddings with `aggregation` (the score of their
+    /// best-matching embedding by default).
+    pub fn aggregation(mut self, aggregation: ScoreAggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+}
+
+/// Similarity score of a `distance` (e.g.: the euclidean distance of two embeddings), from 1 for
+/// identical embeddings down to 0, for the vector stores searching by distance (see
+/// [VectorStoreIndex#scores]).
+pub fn distance_score(distance: f64) -> f64 {
+    1.0 / (1.0 + distance.max(0.0))
 }
 
 /// Trait for vector store indexes
+///
+/// # Scores
+/// The scores returned by the indexes are similarities: the higher the score, the more relevant
+/// the document, and the results are ordered by decreasing score. The minimum scores (e.g.:
+/// [SearchOptions::min_score] or the retrieval gate of the
+/// [agents](crate::agent::RagConfig::min_score)) rely on it. Vector stores searching by
+/// distance convert their distances to similarities, e.g.: `1 - distance` for the cosine
+/// distance, or [distance_score] for the distances without an upper bound.
 pub trait VectorStoreIndex: Send + Sync {
     /// Get the top n documents based on the distance to the given query.
-    /// The result is a list of tuples of the form (score, id, document)
+    /// The result is a list of tuples of the form (score, id, document), the documents being
+    /// deserialized into `T`. Documents which cannot be deserialized into `T` are errors
+    /// ([VectorStoreError::JsonError]), not missing results.
     fn top_n<T: for<'a> Deserialize<'a> + Send>(
         &self,
         query: &str,
@@ -38,6 +263,96 @@ pub trait VectorStoreIndex: Send + Sync {
         query: &str,
         n: usize,
     ) -> impl std::future::Future<Output = Result<Vec<(f64, String)>, VectorStoreError>> + Send;
+
+    /// Same as `top_n` but only considers the documents whose metadata matches `filter`.
+    /// Returns [VectorStoreError::FilterNotSupported] unless implemented by the vector store.
+    fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        _query: &str,
+        _n: usize,
+        _filter: &Filter,
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String, T)>, VectorStoreError>> + Send
+    {
+        async { Err(VectorStoreError::FilterNotSupported) }
+    }
+
+    /// Same as `top_n_with_filter` but returns the document ids only.
+    fn top_n_ids_with_filter(
+        &self,
+        _query: &str,
+        _n: usize,
+        _filter: &Filter,
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String)>, VectorStoreError>> + Send
+    {
+        async { Err(VectorStoreError::FilterNotSupported) }
+    }
+
+    /// Same as `top_n` but with the given search options. By default, the minimum score is
+    /// applied to the results of `top_n` (or `top_n_with_filter`), MMR re-ranking returns
+    /// [VectorStoreError::MmrNotSupported] and score aggregations other than
+    /// [ScoreAggregation::Max] return [VectorStoreError::AggregationNotSupported].
+    fn top_n_with_options<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String, T)>, VectorStoreError>> + Send
+    {
+        async move {
+            if options.mmr_lambda.is_some() {
+                return Err(VectorStoreError::MmrNotSupported);
+            }
+            if options.aggregation != ScoreAggregation::Max {
+                return Err(VectorStoreError::AggregationNotSupported(
+                    options.aggregation.clone(),
+                ));
+            }
+            let mut results = match &options.filter {
+                Some(filter) => self.top_n_with_filter(query, n, filter).await?,
+                None => self.top_n(query, n).await?,
+            };
+            if let Some(min_score) = options.min_score {
+                results.retain(|(score, _, _)| *score >= min_score);
+            }
+            Ok(results)
+        }
+    }
+
+    /// Same as `top_n_with_options` but returns the document ids only.
+    fn top_n_ids_with_options(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String)>, VectorStoreError>> + Send
+    {
+        async move {
+            if options.mmr_lambda.is_some() {
+                return Err(VectorStoreError::MmrNotSupported);
+            }
+            if options.aggregation != ScoreAggregation::Max {
+                return Err(VectorStoreError::AggregationNotSupported(
+                    options.aggregation.clone(),
+                ));
+            }
+            let mut results = match &options.filter {
+                Some(filter) => self.top_n_ids_with_filter(query, n, filter).await?,
+                None => self.top_n_ids(query, n).await?,
+            };
+            if let Some(min_score) = options.min_score {
+                results.retain(|(score, _)| *score >= min_score);
+            }
+            Ok(results)
+        }
+    }
+
+    /// Re-rank the `top_k` best documents of the index with `reranker`, see [RerankedIndex].
+    fn with_reranker<R: Reranker>(self, reranker: R, top_k: usize) -> RerankedIndex<Self, R>
+    where
+        Self: Sized,
+    {
+        RerankedIndex::new(self, reranker, top_k)
+    }
 }
 
 pub type TopNResults = Result<Vec<(f64, String, Value)>, VectorStoreError>;
@@ -50,6 +365,34 @@ pub trait VectorStoreIndexDyn: Send + Sync {
         query: &'a str,
         n: usize,
     ) -> BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>>;
+
+    fn top_n_with_filter<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        filter: &'a Filter,
+    ) -> BoxFuture<'a, TopNResults>;
+
+    fn top_n_ids_with_filter<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        filter: &'a Filter,
+    ) -> BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>>;
+
+    fn top_n_with_options<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        options: &'a SearchOptions,
+    ) -> BoxFuture<'a, TopNResults>;
+
+    fn top_n_ids_with_options<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        options: &'a SearchOptions,
+    ) -> BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>>;
 }
 
 impl<I: VectorStoreIndex> VectorStoreIndexDyn for I {
@@ -75,6 +418,100 @@ impl<I: VectorStoreIndex> VectorStoreIndexDyn for I {
     ) -> BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>> {
         Box::pin(self.top_n_ids(query, n))
     }
+
+    fn top_n_with_filter<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        filter: &'a Filter,
+    ) -> BoxFuture<'a, TopNResults> {
+        Box::pin(async move {
+            Ok(self
+                .top_n_with_filter::<serde_json::Value>(query, n, filter)
+                .await?
+                .into_iter()
+                .map(|(score, id, doc)| (score, id, prune_document(doc).unwrap_or_default()))
+                .collect::<Vec<_>>())
+        })
+    }
+
+    fn top_n_ids_with_filter<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        filter: &'a Filter,
+    ) -> BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>> {
+        Box::pin(self.top_n_ids_with_filter(query, n, filter))
+    }
+
+    fn top_n_with_options<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        options: &'a SearchOptions,
+    ) -> BoxFuture<'a, TopNResults> {
+        Box::pin(async move {
+            Ok(self
+                .top_n_with_options::<serde_json::Value>(query, n, options)
+                .await?
+                .into_iter()
+                .map(|(score, id, doc)| (score, id, prune_document(doc).unwrap_or_default()))
+                .collect::<Vec<_>>())
+        })
+    }
+
+    fn top_n_ids_with_options<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+        options: &'a SearchOptions,
+    ) -> BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>> {
+        Box::pin(self.top_n_ids_with_options(query, n, options))
+    }
+}
+
+/// Trait for vector stores to which embedded documents can be inserted in batches, e.g.: by
+/// [EmbeddingsBuilder::build_into](crate::embeddings::EmbeddingsBuilder::build_into), which
+/// inserts the documents as they are embedded.
+///
+/// Stores keeping documents under stable ids can also implement upserts and deletes (e.g.: to
+/// re-index a corpus incrementally, see [sync]).
+pub trait InsertDocuments<D>: Send {
+    /// Insert documents and their embeddings into the store.
+    fn insert_documents(
+        &mut self,
+        documents: Vec<(D, OneOrMany<Embedding>)>,
+    ) -> impl Future<Output = Result<(), VectorStoreError>> + Send;
+
+    /// Insert documents with their ids, JSON metadata and embeddings into the store, replacing
+    /// the documents with the same ids. Returns [VectorStoreError::UpsertNotSupported] unless
+    /// implemented by the vector store.
+    fn upsert_documents(
+        &mut self,
+        _documents: Vec<(String, D, Value, OneOrMany<Embedding>)>,
+    ) -> impl Future<Output = Result<(), VectorStoreError>> + Send {
+        async { Err(VectorStoreError::UpsertNotSupported) }
+    }
+
+    /// Delete the documents with the given ids from the store, ignoring unknown ids. Returns
+    /// [VectorStoreError::DeleteNotSupported] unless implemented by the vector store.
+    fn delete_documents(
+        &mut self,
+        _ids: Vec<String>,
+    ) -> impl Future<Output = Result<(), VectorStoreError>> + Send {
+        async { Err(VectorStoreError::DeleteNotSupported) }
+    }
+}
+
+/// Collects the inserted documents, in order.
+impl<D: Send> InsertDocuments<D> for Vec<(D, OneOrMany<Embedding>)> {
+    async fn insert_documents(
+        &mut self,
+        documents: Vec<(D, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.extend(documents);
+        Ok(())
+    }
 }
 
 fn prune_document(document: serde_json::Value) -> Option<serde_json::Value> {
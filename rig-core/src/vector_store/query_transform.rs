@@ -0,0 +1,331 @@
+//! Query transformations improving the recall of terse queries, such as the prompts of users.
+//!
+//! A [TransformedIndex] asks a completion model to transform each query before searching its
+//! index, with one of the [QueryTransform]s:
+//! - [QueryTransform::Hyde] (Hypothetical Document Embeddings): the model writes a passage
+//!   answering the query, which is searched instead of the query, since it is closer to the
+//!   documents answering it than the question itself,
+//! - [QueryTransform::MultiQuery]: the model paraphrases the query into several queries, which
+//!   are searched concurrently. Their results are merged, keeping the best score of the
+//!   documents found by several queries.
+//!
+//! # Example
+//! ```rust
+//! use rig::{providers::openai, vector_store::query_transform::{QueryTransform, TransformedIndex}};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! // Search 3 paraphrases of the prompts, along with the prompts themselves
+//! let index = TransformedIndex::new(
+//!     openai.completion_model(openai::GPT_4O_MINI),
+//!     docs_index,
+//!     QueryTransform::MultiQuery { queries: 3 },
+//! )
+//! .include_original(true);
+//!
+//! let agent = openai.agent(openai::GPT_4O).dynamic_context(5, index).build();
+//! ```
+use std::collections::HashSet;
+
+use futures::future::try_join_all;
+use serde::Deserialize;
+
+use super::{Filter, VectorStoreError, VectorStoreIndex};
+use crate::completion::{AssistantContent, CompletionError, CompletionModel, Message};
+
+const HYDE_PREAMBLE: &str = "\
+You write a short passage answering the question, as it would appear in a document of the \
+knowledge base (e.g.: a documentation page or an article). Don't mention the question, and \
+respond with the passage only.";
+
+const MULTI_QUERY_PREAMBLE: &str = "\
+You rewrite the search query into different queries, used to retrieve the documents needed to \
+answer it from a knowledge base. Use different terms (e.g.: synonyms, more specific or more \
+general ones) and cover the different aspects of the query. Respond with one query per line, \
+without numbering.";
+
+/// Transformation of the queries of a [TransformedIndex]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryTransform {
+    /// Search a hypothetical passage answering the query
+    Hyde,
+    /// Search several paraphrases of the query, and merge their results
+    MultiQuery {
+        /// Number of paraphrases of the query
+        queries: usize,
+    },
+}
+
+/// [TransformedIndex] transforms the queries with a completion model before searching its
+/// index (see the [module](self) documentation).
+///
+/// If the query cannot be transformed (e.g.: the request to the model failed, or the model
+/// answered with no query), the index is searched with the original query.
+pub struct TransformedIndex<M: CompletionModel, I: VectorStoreIndex> {
+    model: M,
+    index: I,
+    transform: QueryTransform,
+    preamble: String,
+    include_original: bool,
+}
+
+impl<M: CompletionModel, I: VectorStoreIndex> TransformedIndex<M, I> {
+    pub fn new(model: M, index: I, transform: QueryTransform) -> Self {
+        let preamble = match transform {
+            QueryTransform::Hyde => HYDE_PREAMBLE,
+            QueryTransform::MultiQuery { .. } => MULTI_QUERY_PREAMBLE,
+        };
+        Self {
+            model,
+            index,
+            transform,
+            preamble: preamble.to_string(),
+            include_original: false,
+        }
+    }
+
+    /// Set the instructions of the model transforming the queries, e.g.: to describe the
+    /// documents of the index, or to write in the language of the documents.
+    pub fn preamble(mut self, preamble: &str) -> Self {
+        self.preamble = preamble.to_string();
+        self
+    }
+
+    /// Also search the original query, merging its results with those of the transformed
+    /// queries (defaults to false).
+    pub fn include_original(mut self, include_original: bool) -> Self {
+        self.include_original = include_original;
+        self
+    }
+
+    /// Queries searched for `query`: its transformations, and the query itself if
+    /// [included](Self::include_original) or if it could not be transformed.
+    pub async fn queries(&self, query: &str) -> Vec<String> {
+        let mut queries = match self.transform(query).await {
+            Ok(queries) => queries,
+            Err(e) => {
+                tracing::warn!(target: "rig", "Searching the original query, failed to transform it: {e}");
+                vec![]
+            }
+        };
+        if queries.is_empty() || self.include_original {
+            queries.insert(0, query.to_string());
+        }
+        let mut seen = HashSet::new();
+        queries.retain(|query| seen.insert(query.clone()));
+        tracing::debug!(target: "rig", "Searching {query:?} with the queries {queries:?}");
+        queries
+    }
+
+    async fn transform(&self, query: &str) -> Result<Vec<String>, CompletionError> {
+        let prompt = match self.transform {
+            QueryTransform::Hyde => format!("Question: {query}"),
+            QueryTransform::MultiQuery { queries: 0 } => return Ok(vec![]),
+            QueryTransform::MultiQuery { queries } => {
+                format!("Write {queries} different queries for:\n{query}")
+            }
+        };
+
+        let response = self
+            .model
+            .completion_request(Message::user(prompt))
+            .preamble(self.preamble.clone())
+            .send()
+            .await?;
+        let text = response
+            .choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                AssistantContent::ToolCall(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(match self.transform {
+            QueryTransform::Hyde => Some(text.trim().to_string())
+                .filter(|passage| !passage.is_empty())
+                .into_iter()
+                .collect(),
+            QueryTransform::MultiQuery { queries } => text
+                .lines()
+                .map(|line| {
+                    line.trim()
+                        .trim_start_matches(|c: char| c.is_ascii_digit())
+                        .trim_start_matches(['.', ')', '-', '*'])
+                        .trim()
+                        .trim_matches('"')
+                        .to_string()
+                })
+                .filter(|query| !query.is_empty())
+                .take(queries)
+                .collect(),
+        })
+    }
+
+    /// Search the queries of `query` with `search`, and merge the `n` best results.
+    async fn search<R, F>(
+        &self,
+        query: &str,
+        n: usize,
+        search: impl Fn(String) -> F,
+    ) -> Result<Vec<(f64, String, R)>, VectorStoreError>
+    where
+        F: std::future::Future<Output = Result<Vec<(f64, String, R)>, VectorStoreError>>,
+    {
+        let searches = self.queries(query).await.into_iter().map(search);
+        let mut merged = try_join_all(searches)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        // The best result of the documents found by several queries is kept
+        merged.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        let mut ids = HashSet::new();
+        merged.retain(|(_, id, _)| ids.insert(id.clone()));
+        merged.truncate(n);
+        Ok(merged)
+    }
+}
+
+/// Add an empty result to document ids, to merge them like documents
+fn with_unit(results: Vec<(f64, String)>) -> Vec<(f64, String, ())> {
+    results
+        .into_iter()
+        .map(|(score, id)| (score, id, ()))
+        .collect()
+}
+
+fn without_unit(results: Vec<(f64, String, ())>) -> Vec<(f64, String)> {
+    results
+        .into_iter()
+        .map(|(score, id, ())| (score, id))
+        .collect()
+}
+
+impl<M: CompletionModel, I: VectorStoreIndex> VectorStoreIndex for TransformedIndex<M, I> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search(query, n, |query| async move {
+            self.index.top_n(&query, n).await
+        })
+        .await
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let results = self
+            .search(query, n, |query| async move {
+                Ok(with_unit(self.index.top_n_ids(&query, n).await?))
+            })
+            .await?;
+        Ok(without_unit(results))
+    }
+
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search(query, n, |query| async move {
+            self.index.top_n_with_filter(&query, n, filter).await
+        })
+        .await
+    }
+
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let results = self
+            .search(query, n, |query| async move {
+                Ok(with_unit(
+                    self.index.top_n_ids_with_filter(&query, n, filter).await?,
+                ))
+            })
+            .await?;
+        Ok(without_unit(results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{providers::mock::MockCompletionModel, vector_store::keyword::Bm25Index};
+
+    fn index() -> Bm25Index<String> {
+        Bm25Index::from_documents(vec![
+            (
+                "doc1",
+                "Reset your password from the login page".to_string(),
+                "doc1".to_string(),
+            ),
+            (
+                "doc2",
+                "Change the email of your account".to_string(),
+                "doc2".to_string(),
+            ),
+            (
+                "doc3",
+                "Delete your account".to_string(),
+                "doc3".to_string(),
+            ),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_hyde() {
+        let model = MockCompletionModel::new()
+            .text("Passwords can be reset from the login page.")
+            .text("");
+        let index = TransformedIndex::new(model.clone(), index(), QueryTransform::Hyde);
+
+        // The terse query matches nothing, the hypothetical answer does
+        let results = index.top_n_ids("forgot it", 1).await.unwrap();
+        assert_eq!(results[0].1, "doc1");
+        let request = model.last_request().unwrap();
+        assert_eq!(request.preamble.as_deref(), Some(HYDE_PREAMBLE));
+
+        // Empty passages fall back to the original query
+        assert_eq!(index.queries("forgot it").await, vec!["forgot it"]);
+    }
+
+    #[tokio::test]
+    async fn test_multi_query() {
+        let model = MockCompletionModel::new().repeat(
+            "1. reset password\n2. \"change account email\"\n\n3. delete account\n4. extra",
+        );
+        let index = TransformedIndex::new(
+            model.clone(),
+            index(),
+            QueryTransform::MultiQuery { queries: 3 },
+        );
+        assert_eq!(
+            index.queries("account?").await,
+            vec!["reset password", "change account email", "delete account"]
+        );
+
+        // The results of the queries are merged and deduplicated
+        let results = index.top_n::<String>("account?", 5).await.unwrap();
+        let mut ids = results
+            .iter()
+            .map(|(_, id, _)| id.as_str())
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec!["doc1", "doc2", "doc3"]);
+        assert_eq!(index.top_n_ids("account?", 2).await.unwrap().len(), 2);
+
+        let index = index.include_original(true);
+        assert_eq!(index.queries("account?").await[0], "account?");
+    }
+}
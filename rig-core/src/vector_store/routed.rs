@@ -0,0 +1,316 @@
+//! Retrieval routed by query classification.
+//!
+//! # Example
+//! ```rust
+//! use rig::{orchestrator::EmbeddingRouter, vector_store::routed::RoutedIndex};
+//!
+//! // Only search the collection matching the topic of the query
+//! let index = RoutedIndex::new(EmbeddingRouter::new(embedding_model.clone()))
+//!     .index("code", "Source code of the project: functions, types and modules", code_index)
+//!     .index("docs", "User guides and API documentation", docs_index)
+//!     .index("tickets", "Bug reports and support tickets", tickets_index)
+//!     .fallback("docs")
+//!     .threshold(0.3);
+//!
+//! let agent = openai.agent("gpt-4o").dynamic_context(5, index).build();
+//! ```
+use serde::Deserialize;
+
+use super::{Filter, SearchOptions, VectorStoreError, VectorStoreIndex, VectorStoreIndexDyn};
+use crate::orchestrator::{Route, Router};
+
+/// [RoutedIndex] classifies each query with a [Router] (e.g.: a
+/// [ModelRouter](crate::orchestrator::ModelRouter) asking a completion model, or an
+/// [EmbeddingRouter](crate::orchestrator::EmbeddingRouter) comparing the query to the
+/// descriptions of the indexes), and only searches the index the query was routed to.
+///
+/// Searching only the relevant collection of a multi-corpus deployment (e.g.: "code" vs "docs"
+/// vs "tickets") avoids retrieving similar but off-topic documents of the other collections.
+/// Queries routed with a confidence below the threshold are searched in the fallback index if
+/// any, and return no documents otherwise.
+pub struct RoutedIndex<R: Router> {
+    router: R,
+    routes: Vec<Route>,
+    indexes: Vec<Box<dyn VectorStoreIndexDyn>>,
+    fallback: Option<String>,
+    threshold: f64,
+}
+
+impl<R: Router> RoutedIndex<R> {
+    pub fn new(router: R) -> Self {
+        Self {
+            router,
+            routes: vec![],
+            indexes: vec![],
+            fallback: None,
+            threshold: 0.0,
+        }
+    }
+
+    /// Add an index, described to the router by `description`. Adding an index with the name
+    /// of a previous one replaces it.
+    pub fn index(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        index: impl VectorStoreIndex + 'static,
+    ) -> Self {
+        let route = Route {
+            name: name.into(),
+            description: description.into(),
+        };
+        match self
+            .routes
+            .iter()
+            .position(|other| other.name == route.name)
+        {
+            Some(position) => {
+                self.routes[position] = route;
+                self.indexes[position] = Box::new(index);
+            }
+            None => {
+                self.routes.push(route);
+                self.indexes.push(Box::new(index));
+            }
+        }
+        self
+    }
+
+    /// Set the index searched when the router picks no index with enough confidence.
+    pub fn fallback(mut self, name: impl Into<String>) -> Self {
+        self.fallback = Some(name.into());
+        self
+    }
+
+    /// Set the minimum confidence of the router (from 0 to 1) for a query to be routed to the
+    /// index it picked. Defaults to 0.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Indexes of the router, in the order they were added.
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Name of the index `query` is routed to, if any.
+    pub async fn route(&self, query: &str) -> Result<Option<String>, VectorStoreError> {
+        Ok(self.routed_index(query).await?.map(|(name, _)| name))
+    }
+
+    async fn routed_index(
+        &self,
+        query: &str,
+    ) -> Result<Option<(String, &dyn VectorStoreIndexDyn)>, VectorStoreError> {
+        let choice = self
+            .router
+            .route(query, &self.routes)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?
+            .filter(|choice| choice.confidence >= self.threshold);
+
+        let name = match (choice, &self.fallback) {
+            (Some(choice), _) if self.position(&choice.agent).is_some() => choice.agent,
+            (_, Some(fallback)) => {
+                tracing::debug!(target: "rig", "Searching fallback index {fallback}");
+                fallback.clone()
+            }
+            (_, None) => {
+                tracing::debug!(target: "rig", "No index found for the query");
+                return Ok(None);
+            }
+        };
+
+        match self.position(&name) {
+            Some(position) => Ok(Some((name, self.indexes[position].as_ref()))),
+            None => Err(VectorStoreError::DatastoreError(
+                format!("Unknown fallback index: {name}").into(),
+            )),
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.routes.iter().position(|route| route.name == name)
+    }
+}
+
+/// Deserialize the documents of the routed index
+fn deserialize<T: for<'a> Deserialize<'a>>(
+    results: Vec<(f64, String, serde_json::Value)>,
+) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+    results
+        .into_iter()
+        .map(|(score, id, doc)| Ok((score, id, serde_json::from_value(doc)?)))
+        .collect()
+}
+
+impl<R: Router> VectorStoreIndex for RoutedIndex<R> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        match self.routed_index(query).await? {
+            Some((_, index)) => deserialize(index.top_n(query, n).await?),
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        match self.routed_index(query).await? {
+            Some((_, index)) => index.top_n_ids(query, n).await,
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        match self.routed_index(query).await? {
+            Some((_, index)) => deserialize(index.top_n_with_filter(query, n, filter).await?),
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        match self.routed_index(query).await? {
+            Some((_, index)) => index.top_n_ids_with_filter(query, n, filter).await,
+            None => Ok(vec![]),
+        }
+    }
+
+    /// The options (e.g.: MMR re-ranking) are applied by the routed index.
+    async fn top_n_with_options<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        match self.routed_index(query).await? {
+            Some((_, index)) => deserialize(index.top_n_with_options(query, n, options).await?),
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn top_n_ids_with_options(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        match self.routed_index(query).await? {
+            Some((_, index)) => index.top_n_ids_with_options(query, n, options).await,
+            None => Ok(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::RoutedIndex;
+    use crate::{
+        orchestrator::{OrchestratorError, Route, RouteChoice, Router},
+        vector_store::{VectorStoreError, VectorStoreIndex},
+    };
+
+    /// Routes queries to the first index whose name they contain, with full confidence, or to
+    /// "tickets" with low confidence
+    struct NameRouter;
+
+    impl Router for NameRouter {
+        async fn route(
+            &self,
+            prompt: &str,
+            routes: &[Route],
+        ) -> Result<Option<RouteChoice>, OrchestratorError> {
+            Ok(Some(
+                routes
+                    .iter()
+                    .find(|route| prompt.contains(&route.name))
+                    .map(|route| RouteChoice {
+                        agent: route.name.clone(),
+                        confidence: 1.0,
+                    })
+                    .unwrap_or(RouteChoice {
+                        agent: "tickets".to_string(),
+                        confidence: 0.2,
+                    }),
+            ))
+        }
+    }
+
+    /// Index returning a single document, whatever the query
+    struct OneDocIndex(&'static str);
+
+    impl VectorStoreIndex for OneDocIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            Ok(vec![(
+                1.0,
+                self.0.to_string(),
+                serde_json::from_value(self.0.into())?,
+            )])
+        }
+
+        async fn top_n_ids(
+            &self,
+            _query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Ok(vec![(1.0, self.0.to_string())])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routed_index() {
+        let index = RoutedIndex::new(NameRouter)
+            .index("code", "Source code", OneDocIndex("main.rs"))
+            .index("docs", "Documentation", OneDocIndex("guide.md"))
+            .index("tickets", "Support tickets", OneDocIndex("ticket-1"))
+            .threshold(0.5);
+
+        let results = index.top_n::<String>("Where is the code of main?", 3).await;
+        assert_eq!(
+            results.unwrap(),
+            vec![(1.0, "main.rs".to_string(), "main.rs".to_string())]
+        );
+        assert_eq!(
+            index
+                .top_n_ids("Which docs explain this?", 3)
+                .await
+                .unwrap(),
+            vec![(1.0, "guide.md".to_string())]
+        );
+
+        // Queries routed below the threshold go to the fallback index, if any
+        assert!(index.top_n_ids("Hello", 3).await.unwrap().is_empty());
+        let index = index.fallback("docs");
+        assert_eq!(index.route("Hello").await.unwrap(), Some("docs".into()));
+        let index = index.threshold(0.1);
+        assert_eq!(index.route("Hello").await.unwrap(), Some("tickets".into()));
+
+        let index = index.threshold(0.5).fallback("faq");
+        assert!(matches!(
+            index.top_n_ids("Hello", 3).await,
+            Err(VectorStoreError::DatastoreError(_))
+        ));
+    }
+}
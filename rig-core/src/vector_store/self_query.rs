@@ -0,0 +1,388 @@
+//! Self-querying retrieval: natural language constraints of the queries turned into metadata
+//! filters.
+//!
+//! # Example
+//! ```rust
+//! use rig::{providers::openai, vector_store::self_query::SelfQueryIndex};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let index = SelfQueryIndex::builder(openai.completion_model(openai::GPT_4O_MINI), articles_index)
+//!     .field("author", "Name of the author of the article")
+//!     .field("date", "Publication date of the article, as YYYY-MM-DD")
+//!     .build();
+//!
+//! // Searches "rust" in the articles with `author = "Jane"` and `"2023-01-01" <= date < "2024-01-01"`
+//! let results = index.top_n_ids("articles from 2023 about rust by Jane", 5).await?;
+//! ```
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{Filter, VectorStoreError, VectorStoreIndex};
+use crate::{
+    completion::CompletionModel,
+    extractor::{Extractor, ExtractorBuilder},
+};
+
+/// Comparison of a [Condition]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Operator {
+    /// The field is equal to the value
+    Eq,
+    /// The field is equal to one of the values (an array)
+    In,
+    /// The field is greater than the value
+    Gt,
+    /// The field is greater than or equal to the value
+    Gte,
+    /// The field is less than the value
+    Lt,
+    /// The field is less than or equal to the value
+    Lte,
+}
+
+/// Constraint of a query on a metadata field
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct Condition {
+    /// Name of the metadata field
+    pub field: String,
+    pub operator: Operator,
+    /// Value compared to the field (an array for the `in` operator)
+    pub value: Value,
+}
+
+impl Condition {
+    fn filter(&self) -> Filter {
+        let field = self.field.clone();
+        let value = self.value.clone();
+        match self.operator {
+            Operator::Eq => Filter::eq(field, value),
+            Operator::In => match value {
+                Value::Array(values) => Filter::is_in(field, values),
+                value => Filter::is_in(field, [value]),
+            },
+            Operator::Gt => Filter::gt(field, value),
+            Operator::Gte => Filter::gte(field, value),
+            Operator::Lt => Filter::lt(field, value),
+            Operator::Lte => Filter::lte(field, value),
+        }
+    }
+}
+
+/// Query split into a semantic query and constraints on the metadata of the documents
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct StructuredQuery {
+    /// Query searched semantically, without the constraints expressed as conditions
+    pub query: String,
+    /// Constraints on the metadata fields, all of which must hold
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+}
+
+impl StructuredQuery {
+    /// Filter of the conditions of the query, if any.
+    pub fn filter(&self) -> Option<Filter> {
+        match self.conditions.as_slice() {
+            [] => None,
+            [condition] => Some(condition.filter()),
+            conditions => Some(Filter::And(
+                conditions.iter().map(Condition::filter).collect(),
+            )),
+        }
+    }
+}
+
+/// [SelfQueryIndex] uses an [Extractor] to split each query into a semantic query and metadata
+/// filters (e.g.: "articles from 2023 about rust by Jane" into "rust", with filters on the date
+/// and author of the articles), and searches its index (which must support filters) with them.
+///
+/// Conditions on fields that were not declared with [SelfQueryIndexBuilder::field] are ignored.
+/// If the query cannot be extracted, the index is searched with the raw query, without filters.
+pub struct SelfQueryIndex<M: CompletionModel, I: VectorStoreIndex> {
+    extractor: Extractor<M, StructuredQuery>,
+    fields: Vec<String>,
+    index: I,
+}
+
+impl<M: CompletionModel, I: VectorStoreIndex> SelfQueryIndex<M, I> {
+    pub fn builder(model: M, index: I) -> SelfQueryIndexBuilder<M, I> {
+        SelfQueryIndexBuilder::new(model, index)
+    }
+
+    /// Split `query` into a semantic query and conditions on the declared metadata fields.
+    pub async fn structured_query(&self, query: &str) -> StructuredQuery {
+        match self.extractor.extract(query).await {
+            Ok(mut structured) => {
+                structured.conditions.retain(|condition| {
+                    let known = self.fields.contains(&condition.field);
+                    if !known {
+                        tracing::warn!(target: "rig",
+                            "Ignoring condition on unknown metadata field {}",
+                            condition.field
+                        );
+                    }
+                    known
+                });
+                if structured.query.trim().is_empty() {
+                    structured.query = query.to_string();
+                }
+                structured
+            }
+            Err(e) => {
+                tracing::warn!(target: "rig", "Searching the raw query, failed to structure it: {e}");
+                StructuredQuery {
+                    query: query.to_string(),
+                    conditions: vec![],
+                }
+            }
+        }
+    }
+
+    /// Semantic query and filter of `query`
+    async fn filtered_query(&self, query: &str) -> (String, Option<Filter>) {
+        let structured = self.structured_query(query).await;
+        let filter = structured.filter();
+        tracing::debug!(target: "rig",
+            "Searching {:?} with filter {:?}",
+            structured.query,
+            filter
+        );
+        (structured.query, filter)
+    }
+}
+
+/// Combine the filter of a search with the filter extracted from its query
+fn combine(filter: &Filter, extracted: Option<Filter>) -> Filter {
+    match extracted {
+        Some(extracted) => filter.clone().and(extracted),
+        None => filter.clone(),
+    }
+}
+
+impl<M: CompletionModel, I: VectorStoreIndex> VectorStoreIndex for SelfQueryIndex<M, I> {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        match self.filtered_query(query).await {
+            (query, Some(filter)) => self.index.top_n_with_filter(&query, n, &filter).await,
+            (query, None) => self.index.top_n(&query, n).await,
+        }
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        match self.filtered_query(query).await {
+            (query, Some(filter)) => self.index.top_n_ids_with_filter(&query, n, &filter).await,
+            (query, None) => self.index.top_n_ids(&query, n).await,
+        }
+    }
+
+    /// The extracted filters are combined with `filter`.
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let (query, extracted) = self.filtered_query(query).await;
+        let filter = combine(filter, extracted);
+        self.index.top_n_with_filter(&query, n, &filter).await
+    }
+
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let (query, extracted) = self.filtered_query(query).await;
+        let filter = combine(filter, extracted);
+        self.index.top_n_ids_with_filter(&query, n, &filter).await
+    }
+}
+
+/// Builder for the [SelfQueryIndex]
+pub struct SelfQueryIndexBuilder<M: CompletionModel, I: VectorStoreIndex> {
+    model: M,
+    index: I,
+    fields: Vec<(String, String)>,
+    preamble: Option<String>,
+}
+
+impl<M: CompletionModel, I: VectorStoreIndex> SelfQueryIndexBuilder<M, I> {
+    pub fn new(model: M, index: I) -> Self {
+        Self {
+            model,
+            index,
+            fields: vec![],
+            preamble: None,
+        }
+    }
+
+    /// Declare a metadata field that queries can be filtered on. The description (e.g.: its
+    /// meaning, type, format or possible values) tells the model how to build the conditions.
+    pub fn field(mut self, name: &str, description: &str) -> Self {
+        self.fields
+            .push((name.to_string(), description.to_string()));
+        self
+    }
+
+    /// Add instructions to the extractor, e.g.: the current date for relative dates.
+    pub fn preamble(mut self, preamble: &str) -> Self {
+        self.preamble = Some(preamble.to_string());
+        self
+    }
+
+    pub fn build(self) -> SelfQueryIndex<M, I> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, description)| format!("- {name}: {description}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut preamble = format!(
+            "Split the search query into a semantic query and conditions on the metadata of the \
+            documents. Only add conditions for the constraints stated by the query, on the \
+            following metadata fields:\n{fields}\n\n\
+            The semantic query is the query without the constraints expressed as conditions, \
+            e.g.: \"articles from 2023 about rust by Jane\" becomes \"rust\", with conditions \
+            on the date and the author."
+        );
+        if let Some(instructions) = self.preamble {
+            preamble = format!("{preamble}\n\n{instructions}");
+        }
+
+        SelfQueryIndex {
+            extractor: ExtractorBuilder::new(self.model)
+                .preamble(&preamble)
+                .build(),
+            fields: self.fields.into_iter().map(|(name, _)| name).collect(),
+            index: self.index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::{CompletionError, CompletionRequest, CompletionResponse},
+        message::AssistantContent,
+        OneOrMany,
+    };
+
+    /// Model submitting the given structured query
+    #[derive(Clone)]
+    struct QueryModel(Value);
+
+    impl CompletionModel for QueryModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::tool_call(
+                    "call",
+                    "submit",
+                    self.0.clone(),
+                )),
+                usage: None,
+                raw_response: (),
+            })
+        }
+    }
+
+    /// Index of documents with metadata, recording the queries it is searched with
+    #[derive(Default)]
+    struct MetadataIndex {
+        queries: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MetadataIndex {
+        fn search(&self, query: &str, filter: Option<&Filter>) -> Vec<(f64, String)> {
+            self.queries.lock().unwrap().push(query.to_string());
+            [
+                ("a", json!({"author": "Jane", "date": "2023-04-02"})),
+                ("b", json!({"author": "John", "date": "2023-06-20"})),
+                ("c", json!({"author": "Jane", "date": "2024-01-08"})),
+            ]
+            .into_iter()
+            .filter(|(_, metadata)| filter.is_none_or(|filter| filter.matches(metadata)))
+            .map(|(id, _)| (1.0, id.to_string()))
+            .collect()
+        }
+    }
+
+    impl VectorStoreIndex for MetadataIndex {
+        async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+            &self,
+            _query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+            Ok(vec![])
+        }
+
+        async fn top_n_ids(
+            &self,
+            query: &str,
+            _n: usize,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Ok(self.search(query, None))
+        }
+
+        async fn top_n_ids_with_filter(
+            &self,
+            query: &str,
+            _n: usize,
+            filter: &Filter,
+        ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+            Ok(self.search(query, Some(filter)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_self_query_index() {
+        let model = QueryModel(json!({
+            "query": "rust",
+            "conditions": [
+                {"field": "author", "operator": "eq", "value": "Jane"},
+                {"field": "date", "operator": "gte", "value": "2023-01-01"},
+                {"field": "date", "operator": "lt", "value": "2024-01-01"},
+                {"field": "language", "operator": "in", "value": ["en"]}
+            ]
+        }));
+        let index = MetadataIndex::default();
+        let queries = index.queries.clone();
+        let index = SelfQueryIndex::builder(model, index)
+            .field("author", "Name of the author")
+            .field("date", "Publication date, as YYYY-MM-DD")
+            .build();
+
+        // The condition on the undeclared `language` field is ignored
+        let results = index
+            .top_n_ids("articles from 2023 about rust by Jane", 5)
+            .await
+            .unwrap();
+        assert_eq!(results, vec![(1.0, "a".to_string())]);
+        assert_eq!(*queries.lock().unwrap(), vec!["rust"]);
+
+        let results = index
+            .top_n_ids_with_filter("rust", 5, &Filter::eq("author", "John"))
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+}
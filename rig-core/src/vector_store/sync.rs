@@ -0,0 +1,370 @@
+//! Incremental re-indexing of a corpus of documents into a vector store.
+//!
+//! An [IndexSync] keeps a [SyncManifest] of the content hashes of the documents it indexed.
+//! When the corpus is synced again (e.g.: by a nightly job reading the documents with a
+//! [loader](crate::loaders)), only the new and changed documents are embedded and upserted to
+//! the [DocumentSink], and the documents which are no longer part of the corpus are deleted.
+//! The manifest can be saved between the runs of the job.
+//!
+//! # Example
+//! ```rust
+//! use rig::{
+//!     loaders::FileLoader,
+//!     vector_store::sync::{IndexSync, SyncManifest},
+//! };
+//!
+//! let documents = FileLoader::with_glob("docs/**/*.md")?
+//!     .read_with_path()
+//!     .ignore_errors()
+//!     .into_iter()
+//!     .map(|(path, text)| (path.display().to_string(), text));
+//!
+//! let mut sync = IndexSync::new(model, index).manifest(SyncManifest::load("docs.manifest.json")?);
+//! let report = sync.sync_from_loader(documents).await?;
+//! println!("{} documents re-embedded", report.added + report.updated);
+//!
+//! sync.current_manifest().save("docs.manifest.json")?;
+//! ```
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{
+    events::{DocumentSink, PendingDocument},
+    VectorStoreError,
+};
+use crate::embeddings::{
+    Embed, EmbedError, EmbeddingError, EmbeddingModel, EmbeddingsBuilder, TextEmbedder,
+};
+
+/// Content hashes of the documents indexed by an [IndexSync], by document id.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncManifest {
+    hashes: BTreeMap<String, String>,
+}
+
+impl SyncManifest {
+    /// Load a manifest saved with [SyncManifest::save]. A missing file is loaded as an empty
+    /// manifest, so that the first run of a job indexes the whole corpus.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, VectorStoreError> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save the manifest as JSON
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), VectorStoreError> {
+        fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Content hash of the indexed document `id`
+    pub fn hash(&self, id: &str) -> Option<&str> {
+        self.hashes.get(id).map(String::as_str)
+    }
+
+    /// Ids of the indexed documents, in order
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.hashes.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+}
+
+/// Number of documents added, updated, unchanged and deleted by a sync.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+}
+
+/// [IndexSync] syncs a [DocumentSink] with a corpus of documents, embedding only the documents
+/// whose content changed since the previous sync.
+pub struct IndexSync<M: EmbeddingModel, S> {
+    model: M,
+    sink: S,
+    manifest: SyncManifest,
+    delete_missing: bool,
+}
+
+impl<M: EmbeddingModel, S> IndexSync<M, S> {
+    pub fn new(model: M, sink: S) -> Self {
+        Self {
+            model,
+            sink,
+            manifest: SyncManifest::default(),
+            delete_missing: true,
+        }
+    }
+
+    /// Set the manifest of the previous sync (defaults to an empty manifest, with which all the
+    /// documents are indexed).
+    pub fn manifest(mut self, manifest: SyncManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Set whether the documents of the manifest missing from the synced documents are deleted
+    /// from the sink (defaults to `true`). Disable it to sync a part of the corpus only.
+    pub fn delete_missing(mut self, delete_missing: bool) -> Self {
+        self.delete_missing = delete_missing;
+        self
+    }
+
+    /// Manifest of the documents indexed so far, to save for the next sync
+    pub fn current_manifest(&self) -> &SyncManifest {
+        &self.manifest
+    }
+
+    pub fn into_manifest(self) -> SyncManifest {
+        self.manifest
+    }
+
+    /// Sync the sink with `documents`, given as `(id, document)` pairs (e.g.: the paths and
+    /// contents read by a loader), without metadata. See [IndexSync::sync].
+    pub async fn sync_from_loader<D: Embed + Send>(
+        &mut self,
+        documents: impl IntoIterator<Item = (String, D)>,
+    ) -> Result<SyncReport, VectorStoreError>
+    where
+        S: DocumentSink<D>,
+    {
+        self.sync(
+            documents
+                .into_iter()
+                .map(|(id, document)| (id, document, Value::Null)),
+        )
+        .await
+    }
+
+    /// Sync the sink with `documents`, given as `(id, document, metadata)`: the new documents
+    /// and the documents whose text (as embedded) or metadata changed are embedded and upserted,
+    /// and the documents of the manifest missing from `documents` are deleted (see
+    /// [IndexSync::delete_missing]). The sink is flushed afterwards.
+    ///
+    /// The manifest is only updated once the changes are written to the sink, so that a failed
+    /// sync is retried entirely by the next one.
+    pub async fn sync<D: Embed + Send>(
+        &mut self,
+        documents: impl IntoIterator<Item = (String, D, Value)>,
+    ) -> Result<SyncReport, VectorStoreError>
+    where
+        S: DocumentSink<D>,
+    {
+        let mut report = SyncReport::default();
+        let mut seen = HashSet::new();
+        let mut upserts = vec![];
+        let mut hashes = vec![];
+        for (id, document, metadata) in documents {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let hash = content_hash(&document, &metadata)
+                .map_err(|e| EmbeddingError::DocumentError(Box::new(e)))?;
+            match self.manifest.hash(&id) {
+                Some(previous) if previous == hash => {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(_) => report.updated += 1,
+                None => report.added += 1,
+            }
+            hashes.push((id.clone(), hash));
+            upserts.push(PendingDocument {
+                id,
+                document,
+                metadata,
+            });
+        }
+        let deletes = match self.delete_missing {
+            true => self
+                .manifest
+                .ids()
+                .filter(|id| !seen.contains(*id))
+                .map(str::to_string)
+                .collect::<Vec<_>>(),
+            false => vec![],
+        };
+        report.deleted = deletes.len();
+
+        if !upserts.is_empty() {
+            let embeddings = EmbeddingsBuilder::new(self.model.clone())
+                .documents(upserts)
+                .map_err(|e| EmbeddingError::DocumentError(Box::new(e)))?
+                .build()
+                .await?;
+            self.sink
+                .upsert_documents(
+                    embeddings
+                        .into_iter()
+                        .map(|(pending, embeddings)| {
+                            (pending.id, pending.document, pending.metadata, embeddings)
+                        })
+                        .collect(),
+                )
+                .await?;
+        }
+        if !deletes.is_empty() {
+            self.sink.delete_documents(deletes.clone()).await?;
+        }
+        self.sink.flush().await?;
+
+        self.manifest.hashes.extend(hashes);
+        for id in deletes {
+            self.manifest.hashes.remove(&id);
+        }
+        Ok(report)
+    }
+}
+
+/// SHA-256 hash of the texts of `document`, as embedded, and of its metadata
+fn content_hash(document: &impl Embed, metadata: &Value) -> Result<String, EmbedError> {
+    let mut embedder = TextEmbedder::default();
+    document.embed(&mut embedder)?;
+
+    let mut hasher = Sha256::new();
+    let metadata = metadata.to_string();
+    for text in embedder.into_texts().iter().chain([&metadata]) {
+        // Length prefixes, so that moving text between two texts changes the hash
+        hasher.update((text.len() as u64).to_le_bytes());
+        hasher.update(text.as_bytes());
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{embeddings::Embedding, vector_store::in_memory_store::InMemoryVectorStore};
+
+    /// Embedding model counting the texts it embeds
+    #[derive(Clone, Default)]
+    struct CountingModel(Arc<Mutex<usize>>);
+
+    impl EmbeddingModel for CountingModel {
+        const MAX_DOCUMENTS: usize = 10;
+
+        fn ndims(&self) -> usize {
+            2
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            let embeddings = texts
+                .into_iter()
+                .map(|text| Embedding {
+                    vec: vec![text.contains("cat") as u8 as f64 + 0.01, 0.5],
+                    document: text,
+                })
+                .collect::<Vec<_>>();
+            *self.0.lock().unwrap() += embeddings.len();
+            Ok(embeddings)
+        }
+    }
+
+    fn documents(docs: &[(&str, &str)]) -> Vec<(String, String)> {
+        docs.iter()
+            .map(|(id, text)| (id.to_string(), text.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_index_sync() {
+        let model = CountingModel::default();
+        let index = InMemoryVectorStore::<String>::default().shared_index(model.clone());
+        let mut sync = IndexSync::new(model.clone(), index.clone());
+
+        let report = sync
+            .sync_from_loader(documents(&[("a", "About cats"), ("b", "About dogs")]))
+            .await
+            .unwrap();
+        assert_eq!((report.added, *model.0.lock().unwrap()), (2, 2));
+        assert_eq!(index.read().len(), 2);
+        assert_eq!(sync.current_manifest().hash("a").unwrap().len(), 64);
+
+        // Only the changed and new documents are embedded, the missing ones are deleted
+        let report = sync
+            .sync_from_loader(documents(&[
+                ("a", "About cats"),
+                ("b", "About dogs and cats"),
+                ("c", "About fish"),
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            SyncReport {
+                added: 1,
+                updated: 1,
+                unchanged: 1,
+                deleted: 0,
+            }
+        );
+        assert_eq!(*model.0.lock().unwrap(), 4);
+        assert_eq!(
+            index.read().get_document::<String>("b").unwrap(),
+            Some("About dogs and cats".to_string())
+        );
+
+        let report = sync
+            .sync_from_loader(documents(&[("b", "About dogs and cats")]))
+            .await
+            .unwrap();
+        assert_eq!((report.unchanged, report.deleted), (1, 2));
+        assert_eq!(*model.0.lock().unwrap(), 4);
+        assert_eq!(index.read().len(), 1);
+        assert_eq!(sync.current_manifest().ids().collect::<Vec<_>>(), vec!["b"]);
+
+        // The manifest of a previous run
+        let path = std::env::temp_dir().join("rig-test-sync-manifest.json");
+        sync.current_manifest().save(&path).unwrap();
+        let manifest = SyncManifest::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&manifest, sync.current_manifest());
+        assert!(SyncManifest::load(&path).unwrap().is_empty());
+
+        let mut sync = IndexSync::new(model.clone(), index.clone())
+            .manifest(manifest)
+            .delete_missing(false);
+        let report = sync
+            .sync(vec![
+                // Changed metadata
+                (
+                    "b".to_string(),
+                    "About dogs and cats".to_string(),
+                    serde_json::json!({"v": 2}),
+                ),
+                ("d".to_string(), "About birds".to_string(), Value::Null),
+            ])
+            .await
+            .unwrap();
+        assert_eq!((report.added, report.updated), (1, 1));
+        assert_eq!(*model.0.lock().unwrap(), 6);
+        assert_eq!(index.read().len(), 2);
+    }
+}
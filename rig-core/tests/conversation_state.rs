@@ -0,0 +1,93 @@
+use rig::{
+    prompt::PromptTemplate,
+    session::Session,
+    state::{ConversationState, StateError, StateVariable},
+};
+
+#[derive(rig::ConversationState, Debug, Default, PartialEq)]
+struct Booking {
+    /// Name of the customer
+    #[state(readonly)]
+    customer: String,
+    /// City of the trip,
+    /// once known
+    city: Option<String>,
+    #[state(rename = "nights")]
+    night_count: Option<u32>,
+    #[state(skip)]
+    #[allow(dead_code)]
+    attempts: usize,
+}
+
+#[test]
+fn test_conversation_state() {
+    assert_eq!(
+        Booking::variables(),
+        vec![
+            StateVariable {
+                name: "customer",
+                description: "Name of the customer",
+                extract: false,
+            },
+            StateVariable {
+                name: "city",
+                description: "City of the trip, once known",
+                extract: true,
+            },
+            StateVariable {
+                name: "nights",
+                description: "",
+                extract: true,
+            },
+        ]
+    );
+    let instructions = Booking::instructions();
+    assert!(instructions.ends_with("Variables:\n- city: City of the trip, once known\n- nights"));
+
+    let mut booking = Booking {
+        customer: "John".to_string(),
+        ..Default::default()
+    };
+    let template =
+        PromptTemplate::new("You book trips for {customer}.{#if city} They go to {city}.{/if}")
+            .unwrap();
+    assert_eq!(
+        template.render(booking.to_variables().unwrap()).unwrap(),
+        "You book trips for John."
+    );
+
+    // The model updates the state, but not its readonly variables
+    let answer = booking
+        .extract(
+            "How many nights?\n<state>{\"city\": \"Lisbon\", \"customer\": \"Jane\", \"other\": 1}</state>",
+        )
+        .unwrap();
+    assert_eq!(answer, "How many nights?");
+    assert_eq!(booking.city.as_deref(), Some("Lisbon"));
+    assert_eq!(booking.customer, "John");
+    assert_eq!(booking.extract("No update").unwrap(), "No update");
+    assert!(matches!(
+        booking.extract("<state>{\"nights\": \"two\"}</state>"),
+        Err(StateError::InvalidVariable(name, _)) if name == "nights"
+    ));
+    assert!(matches!(
+        booking.extract("<state>nights: 2</state>"),
+        Err(StateError::InvalidUpdate(_))
+    ));
+    booking
+        .extract("<state>```json\n{\"nights\": 2}\n```</state>")
+        .unwrap();
+    assert_eq!(booking.night_count, Some(2));
+
+    // The state is saved in the session, and loaded back in the next turns
+    let mut session = Session::new("42");
+    assert_eq!(Booking::load(&session).unwrap(), Booking::default());
+    booking.save(&mut session).unwrap();
+    assert_eq!(
+        Booking::load(&session).unwrap(),
+        Booking {
+            attempts: 0,
+            ..booking
+        }
+    );
+}
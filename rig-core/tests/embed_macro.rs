@@ -215,3 +215,472 @@ fn test_multiple_embed_tags() {
         ]
     );
 }
+
+#[test]
+fn test_enum_embed() {
+    #[derive(Embed, Serialize)]
+    enum Document {
+        Pdf {
+            #[allow(dead_code)]
+            id: String,
+            #[embed]
+            text: String,
+        },
+        Web {
+            #[embed]
+            body: String,
+            #[embed(embed_with = "embed_links")]
+            links: Vec<String>,
+        },
+        Note(#[embed] String, i32),
+        Image {
+            url: String,
+        },
+    }
+
+    fn embed_links(embedder: &mut TextEmbedder, links: Vec<String>) -> Result<(), EmbedError> {
+        embedder.embed(links.join(", "));
+        Ok(())
+    }
+
+    let documents = vec![
+        Document::Pdf {
+            id: "doc1".to_string(),
+            text: "Pdf text".to_string(),
+        },
+        Document::Web {
+            body: "Web body".to_string(),
+            links: vec!["a.com".to_string(), "b.com".to_string()],
+        },
+        Document::Note("Note".to_string(), 1),
+        Document::Image {
+            url: "c.com/cat.png".to_string(),
+        },
+    ];
+
+    assert_eq!(
+        documents
+            .into_iter()
+            .map(|document| embeddings::to_texts(document).unwrap())
+            .collect::<Vec<_>>(),
+        vec![
+            vec!["Pdf text".to_string()],
+            vec!["Web body".to_string(), "a.com, b.com".to_string()],
+            vec!["Note".to_string()],
+            vec![r#"{"Image":{"url":"c.com/cat.png"}}"#.to_string()],
+        ]
+    );
+}
+
+#[test]
+fn test_generic_embed() {
+    #[derive(Embed)]
+    struct Doc<T: Embed> {
+        #[embed]
+        inner: T,
+    }
+
+    let doc = Doc {
+        inner: generic::Labeled {
+            id: 1,
+            label: "label",
+            values: vec![1.5, 2.5],
+        },
+    };
+
+    assert_eq!(
+        embeddings::to_texts(doc).unwrap(),
+        vec!["label".to_string(), "1.5".to_string(), "2.5".to_string()]
+    );
+}
+
+#[test]
+fn test_container_embed() {
+    #[derive(Embed)]
+    struct Article {
+        #[embed]
+        title: std::borrow::Cow<'static, str>,
+        #[embed]
+        subtitle: Option<String>,
+        #[embed]
+        summary: Option<String>,
+        #[embed]
+        sections: std::collections::BTreeMap<u32, String>,
+        #[embed]
+        author: std::sync::Arc<(String, Box<f64>)>,
+    }
+
+    let article = Article {
+        title: "Title".into(),
+        subtitle: None,
+        summary: Some("Summary".to_string()),
+        sections: [(2, "Second".to_string()), (1, "First".to_string())].into(),
+        author: std::sync::Arc::new(("Jane".to_string(), Box::new(1.5))),
+    };
+
+    assert_eq!(
+        embeddings::to_texts(article).unwrap(),
+        vec![
+            "Title".to_string(),
+            "Summary".to_string(),
+            "First".to_string(),
+            "Second".to_string(),
+            "Jane".to_string(),
+            "1.5".to_string()
+        ]
+    );
+}
+
+/// Non-embedded fields are stored along with the embedded ones, so that searches return the
+/// documents as they were embedded
+#[test]
+fn test_embed_options() {
+    #[derive(Embed)]
+    struct WordDefinition {
+        #[allow(dead_code)]
+        id: String,
+        #[embed(prefix = "Word: ")]
+        word: String,
+        #[embed(format = "{word}: {definition}")]
+        definition: String,
+        #[embed(prefix = "Synonym: ", skip_if_empty)]
+        synonyms: Vec<String>,
+        #[embed(embed_with = "embed_notes", skip_if_empty)]
+        notes: String,
+    }
+
+    fn embed_notes(embedder: &mut TextEmbedder, notes: String) -> Result<(), EmbedError> {
+        notes
+            .split(';')
+            .for_each(|note| embedder.embed(note.to_string()));
+        Ok(())
+    }
+
+    let definition = WordDefinition {
+        id: "doc1".to_string(),
+        word: "house".to_string(),
+        definition: "a building in which people live".to_string(),
+        synonyms: vec!["home".to_string(), " ".to_string(), "".to_string()],
+        notes: "noun;;".to_string(),
+    };
+
+    assert_eq!(
+        embeddings::to_texts(definition).unwrap(),
+        vec![
+            "Word: house".to_string(),
+            "house: a building in which people live".to_string(),
+            "Synonym: home".to_string(),
+            "noun".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_enum_embed_options() {
+    #[derive(Embed, Serialize)]
+    enum Document {
+        Pdf {
+            #[embed(prefix = "Title: ")]
+            title: String,
+            #[embed(format = "{title} ({pages} pages): {text}")]
+            text: String,
+            pages: u32,
+        },
+        Note(#[embed(skip_if_empty)] String),
+    }
+
+    let documents = vec![
+        Document::Pdf {
+            title: "Rig".to_string(),
+            text: "Pdf text".to_string(),
+            pages: 3,
+        },
+        Document::Note("".to_string()),
+    ];
+
+    assert_eq!(
+        documents
+            .into_iter()
+            .map(|document| embeddings::to_texts(document).unwrap())
+            .collect::<Vec<_>>(),
+        vec![
+            vec![
+                "Title: Rig".to_string(),
+                "Rig (3 pages): Pdf text".to_string()
+            ],
+            vec![],
+        ]
+    );
+}
+
+#[test]
+fn test_concat_embed() {
+    #[derive(Embed)]
+    #[embed(mode = "concat")]
+    struct Article {
+        #[allow(dead_code)]
+        id: String,
+        #[embed(prefix = "Title: ")]
+        title: String,
+        #[embed(prefix = "Tag: ")]
+        tags: Vec<String>,
+        #[embed(prefix = "Body: ")]
+        body: String,
+    }
+
+    let article = Article {
+        id: "doc1".to_string(),
+        title: "Rig".to_string(),
+        tags: vec!["rust".to_string(), "llm".to_string()],
+        body: "Rig is a Rust library.".to_string(),
+    };
+
+    assert_eq!(
+        embeddings::to_texts(article).unwrap(),
+        vec!["Title: Rig\nTag: rust\nTag: llm\nBody: Rig is a Rust library.".to_string()]
+    );
+
+    #[derive(Embed, Serialize)]
+    #[embed(mode = "concat", separator = " | ")]
+    enum Document {
+        Web {
+            #[embed]
+            url: String,
+            #[embed(skip_if_empty)]
+            links: Vec<String>,
+        },
+        Empty {
+            #[embed(skip_if_empty)]
+            text: String,
+        },
+    }
+
+    let documents = vec![
+        Document::Web {
+            url: "a.com".to_string(),
+            links: vec!["b.com".to_string(), "c.com".to_string()],
+        },
+        Document::Empty {
+            text: "".to_string(),
+        },
+    ];
+
+    assert_eq!(
+        documents
+            .into_iter()
+            .map(|document| embeddings::to_texts(document).unwrap())
+            .collect::<Vec<_>>(),
+        vec![vec!["a.com | b.com | c.com".to_string()], vec![]]
+    );
+}
+
+#[cfg(feature = "providers")]
+#[tokio::test]
+async fn test_typed_retrieval() {
+    use rig::{
+        embeddings::EmbeddingsBuilder,
+        providers::mock::MockEmbeddingModel,
+        vector_store::{in_memory_store::InMemoryVectorStore, VectorStoreIndex},
+    };
+
+    #[derive(Embed, Clone, Debug, Default, PartialEq, Eq, Serialize, serde::Deserialize)]
+    struct Book {
+        id: String,
+        #[embed]
+        summary: String,
+        year: u32,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct BookYear {
+        year: u32,
+    }
+
+    let books = vec![
+        Book {
+            id: "dune".to_string(),
+            summary: "A desert planet and its spice".to_string(),
+            year: 1965,
+            tags: vec!["classic".to_string()],
+        },
+        Book {
+            id: "foundation".to_string(),
+            summary: "The fall of a galactic empire".to_string(),
+            year: 1951,
+            tags: vec![],
+        },
+    ];
+    let model = MockEmbeddingModel::new(16);
+    let embeddings = EmbeddingsBuilder::new(model.clone())
+        .documents(books.clone())
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let index = InMemoryVectorStore::from_documents(embeddings).index(model);
+
+    let results = index
+        .top_n::<Book>("A desert planet and its spice", 1)
+        .await
+        .unwrap();
+    assert_eq!(results[0].2, books[0]);
+
+    // A subset of the fields
+    let results = index
+        .top_n::<BookYear>("The fall of a galactic empire", 1)
+        .await
+        .unwrap();
+    assert_eq!(results[0].2, BookYear { year: 1951 });
+
+    let results =
+        rig::vector_store::VectorStoreIndexDyn::top_n(&index, "A desert planet and its spice", 1)
+            .await
+            .unwrap();
+    assert_eq!(results[0].2["tags"], serde_json::json!(["classic"]));
+
+    // Documents which cannot be deserialized are errors
+    assert!(index.top_n::<String>("spice", 1).await.is_err());
+}
+
+/// The `Embed` trait is not in scope here
+mod generic {
+    use rig::embeddings::{EmbedError, TextEmbedder};
+
+    #[derive(rig::Embed)]
+    pub struct Labeled<'a, T, U = i32>
+    where
+        T: rig::Embed,
+    {
+        #[allow(dead_code)]
+        pub id: U,
+        #[embed]
+        pub label: &'a str,
+        #[embed(embed_with = "embed_all")]
+        pub values: Vec<T>,
+    }
+
+    fn embed_all<T: rig::Embed>(
+        embedder: &mut TextEmbedder,
+        values: Vec<T>,
+    ) -> Result<(), EmbedError> {
+        values
+            .into_iter()
+            .try_for_each(|value| value.embed(embedder))
+    }
+}
+
+#[test]
+fn test_check_serialize() {
+    struct Cache;
+
+    #[derive(Embed, Serialize)]
+    #[embed(check_serialize)]
+    struct Document<T> {
+        #[embed]
+        text: String,
+        metadata: T,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        cache: Cache,
+    }
+
+    #[derive(Embed, Serialize)]
+    #[embed(check_serialize, mode = "concat", separator = " ")]
+    enum Record {
+        Note {
+            #[embed]
+            title: String,
+            #[embed]
+            body: String,
+        },
+        #[allow(dead_code)]
+        Link { url: String },
+    }
+
+    let document = Document {
+        text: "Hello".to_string(),
+        metadata: 42,
+        cache: Cache,
+    };
+    assert_eq!(embeddings::to_texts(document).unwrap(), vec!["Hello"]);
+
+    let record = Record::Note {
+        title: "Title".to_string(),
+        body: "Body".to_string(),
+    };
+    assert_eq!(embeddings::to_texts(record).unwrap(), vec!["Title Body"]);
+}
+
+#[test]
+fn test_nested_embed() {
+    #[derive(Embed)]
+    #[embed(mode = "concat", separator = ", ")]
+    struct Address {
+        #[embed]
+        street: String,
+        #[embed]
+        city: String,
+    }
+
+    #[derive(Embed)]
+    struct Tag {
+        #[embed(prefix = "#")]
+        name: String,
+    }
+
+    #[derive(Embed)]
+    struct Category {
+        #[embed]
+        name: String,
+        #[embed]
+        subcategories: Vec<Category>,
+    }
+
+    #[derive(Embed)]
+    struct Company {
+        #[embed]
+        name: String,
+        #[embed(prefix = "Address: ")]
+        address: Address,
+        #[embed]
+        tags: Vec<Tag>,
+        #[embed]
+        category: Category,
+    }
+
+    let company = Company {
+        name: "Rig".to_string(),
+        address: Address {
+            street: "1 Main St".to_string(),
+            city: "Montreal".to_string(),
+        },
+        tags: vec![
+            Tag {
+                name: "ai".to_string(),
+            },
+            Tag {
+                name: "rust".to_string(),
+            },
+        ],
+        category: Category {
+            name: "Software".to_string(),
+            subcategories: vec![Category {
+                name: "Libraries".to_string(),
+                subcategories: vec![],
+            }],
+        },
+    };
+
+    assert_eq!(
+        embeddings::to_texts(company).unwrap(),
+        vec![
+            "Rig".to_string(),
+            "#ai".to_string(),
+            "#rust".to_string(),
+            "Software".to_string(),
+            "Libraries".to_string(),
+            "Address: 1 Main St, Montreal".to_string(),
+        ]
+    );
+}
@@ -0,0 +1,22 @@
+use rig::prompt;
+
+#[test]
+fn test_prompt_macro() {
+    let article = String::from("Rust 2024 is out");
+
+    // The variables can be set in any order, and are rendered wherever they are used
+    let prompt = prompt!("Summarize {doc} for {audience}. {audience} are busy.")
+        .audience("engineers")
+        .doc(&article)
+        .build();
+    assert_eq!(
+        prompt,
+        "Summarize Rust 2024 is out for engineers. engineers are busy."
+    );
+
+    // Values are formatted with `Display`, and literal braces escaped
+    let builder = prompt!("Return {{\"count\": {count}}}");
+    assert_eq!(builder.count(3).build(), "Return {\"count\": 3}");
+
+    assert_eq!(prompt!("Hello!").build(), "Hello!");
+}
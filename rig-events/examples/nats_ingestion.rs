@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use rig::{
+    completion::Prompt,
+    providers::openai,
+    vector_store::{events::EventIngestor, in_memory_store::InMemoryVectorStore},
+};
+use rig_events::nats::NatsSource;
+use serde_json::json;
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    // Requires a NATS server, e.g.: `docker run -p 4222:4222 nats`
+    let nats = async_nats::connect("localhost:4222").await?;
+
+    let openai = openai::Client::from_env();
+    let model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+
+    let index = InMemoryVectorStore::<String>::default().shared_index(model.clone());
+    let agent = openai
+        .agent(openai::GPT_4O)
+        .preamble("You are a dictionary assistant here to assist the user in understanding the meaning of words.")
+        .dynamic_context(1, index.clone())
+        .build();
+
+    // Keep the index in sync with the events published on `documents.*`
+    let source = NatsSource::subscribe(&nats, "documents.*").await?;
+    let ingestion = tokio::spawn(async move {
+        EventIngestor::new(model, index)
+            .run(source.events::<String>())
+            .await
+    });
+
+    for event in [
+        json!({"type": "created", "id": "flurbo", "document": "Definition of a *flurbo*: A flurbo is a green alien that lives on cold planets"}),
+        json!({"type": "updated", "id": "flurbo", "document": "Definition of a *flurbo*: A flurbo is a green alien that lives on warm planets"}),
+        json!({"type": "created", "id": "glarb", "document": "Definition of a *glarb-glarb*: A glarb-glarb is an ancient tool used by the ancestors of the inhabitants of planet Jiro to farm the land."}),
+    ] {
+        nats.publish("documents.dictionary", event.to_string().into())
+            .await?;
+    }
+    nats.flush().await?;
+
+    // Give the ingestor some time to embed the documents
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let response = agent.prompt("What does \"flurbo\" mean?").await?;
+    println!("{}", response);
+
+    ingestion.abort();
+    Ok(())
+}
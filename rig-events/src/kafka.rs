@@ -0,0 +1,69 @@
+//! Kafka source of document events.
+//!
+//! # Example
+//! ```rust
+//! use rig_events::kafka::KafkaSource;
+//!
+//! let source = KafkaSource::new("localhost:9092", "rig", &["documents"])?;
+//! let count = ingestor.run(source.events::<String>()).await?;
+//! ```
+use futures::{Stream, StreamExt};
+use rdkafka::{
+    config::ClientConfig,
+    consumer::{Consumer, StreamConsumer},
+    error::KafkaError,
+    Message,
+};
+use rig::vector_store::events::DocumentEvent;
+use serde::de::DeserializeOwned;
+
+use crate::{decode, SourceError};
+
+/// [KafkaSource] reads [DocumentEvent]s from the messages of Kafka topics.
+pub struct KafkaSource {
+    consumer: StreamConsumer,
+}
+
+impl KafkaSource {
+    /// Subscribe to `topics` of the `brokers` (comma-separated `host:port` list) as a member of
+    /// the consumer group `group_id`. Topics are consumed from their start when the group has no
+    /// committed offsets, and offsets are committed automatically.
+    pub fn new(brokers: &str, group_id: &str, topics: &[&str]) -> Result<Self, KafkaError> {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("group.id", group_id)
+            .set("auto.offset.reset", "earliest")
+            .create()?;
+        consumer.subscribe(topics)?;
+        Ok(Self::from_consumer(consumer))
+    }
+
+    /// Create a source from a consumer which is already configured and subscribed to its topics
+    /// (e.g.: to authenticate to the brokers, or to commit offsets manually).
+    pub fn from_consumer(consumer: StreamConsumer) -> Self {
+        Self { consumer }
+    }
+
+    /// Stream of the events of the subscribed topics. Messages without a payload, or whose
+    /// payload is not a valid event, are skipped. Errors of the consumer are
+    /// items of the stream, which continues after them.
+    pub fn events<D: DeserializeOwned>(
+        &self,
+    ) -> impl Stream<Item = Result<DocumentEvent<D>, SourceError>> + '_ {
+        self.consumer.stream().filter_map(|message| async move {
+            match message {
+                Ok(message) => match message.payload() {
+                    Some(payload) => decode(payload, message.topic()).map(Ok),
+                    None => {
+                        tracing::warn!(target: "rig",
+                            "Skipping message without payload from {}",
+                            message.topic()
+                        );
+                        None
+                    }
+                },
+                Err(e) => Some(Err(e.into())),
+            }
+        })
+    }
+}
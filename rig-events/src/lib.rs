@@ -0,0 +1,80 @@
+//! Sources of [DocumentEvent]s read from message brokers, to keep Rig vector stores in sync with
+//! the documents of other systems using an [EventIngestor](rig::vector_store::events::EventIngestor).
+//!
+//! Events are JSON messages, e.g.:
+//! `{"type": "created", "id": "doc1", "document": "...", "metadata": {"lang": "en"}}` or
+//! `{"type": "deleted", "id": "doc1"}`. Messages which are not valid events are logged and
+//! skipped, so that a single malformed message does not stop the ingestion.
+//!
+//! - `kafka` feature: [KafkaSource](kafka::KafkaSource), consuming Kafka topics.
+//! - `nats` feature: [NatsSource](nats::NatsSource), subscribing to NATS subjects.
+//!
+//! Conversely, the `nats` feature provides [NatsEventBus](nats::NatsEventBus), which publishes
+//! the [activity of agents](rig::outbox) on a NATS subject, so that other services can react to it.
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::{events::EventIngestor, in_memory_store::InMemoryVectorStore};
+//! use rig_events::kafka::KafkaSource;
+//!
+//! let index = InMemoryVectorStore::<String>::default().shared_index(model.clone());
+//! let source = KafkaSource::new("localhost:9092", "rig", &["documents"])?;
+//!
+//! EventIngestor::new(model, index).run(source.events::<String>()).await?;
+//! ```
+use std::fmt::Display;
+
+use rig::vector_store::events::DocumentEvent;
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "nats")]
+pub mod nats;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SourceError {
+    /// Kafka error (e.g.: broker not available, offset commit failure, etc.)
+    #[cfg(feature = "kafka")]
+    #[error("KafkaError: {0}")]
+    KafkaError(#[from] rdkafka::error::KafkaError),
+}
+
+/// Decode the JSON `payload` of a message received from `origin` (e.g.: a topic) as an event,
+/// returning `None` (and logging a warning) if it is not a valid event.
+#[cfg_attr(not(any(feature = "kafka", feature = "nats")), allow(dead_code))]
+fn decode<D: DeserializeOwned>(payload: &[u8], origin: impl Display) -> Option<DocumentEvent<D>> {
+    match serde_json::from_slice(payload) {
+        Ok(event) => Some(event),
+        Err(e) => {
+            tracing::warn!(target: "rig", "Skipping invalid document event from {origin}: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let payload = json!({"type": "updated", "id": "doc1", "document": "About cats"});
+        assert_eq!(
+            decode::<String>(payload.to_string().as_bytes(), "documents"),
+            Some(DocumentEvent::Updated {
+                id: "doc1".into(),
+                document: "About cats".into(),
+                metadata: serde_json::Value::Null,
+            })
+        );
+
+        assert_eq!(decode::<String>(b"not json", "documents"), None);
+        assert_eq!(
+            decode::<String>(br#"{"type": "renamed", "id": "doc1"}"#, "documents"),
+            None
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! NATS source of document events, and NATS bus of agent events.
+//!
+//! # Example
+//! ```rust
+//! use rig_events::nats::{NatsEventBus, NatsSource};
+//!
+//! let client = async_nats::connect("localhost:4222").await?;
+//! let source = NatsSource::subscribe(&client, "documents.>").await?;
+//! let count = ingestor.run(source.events::<String>()).await?;
+//!
+//! // Publish the activity of an agent on `agents.support`
+//! let agent = openai.agent("gpt-4o").outbox(NatsEventBus::new(client, "agents.support")).build();
+//! ```
+use async_nats::{subject::ToSubject, Client, Subject, SubscribeError, Subscriber};
+use futures::{Stream, StreamExt};
+use rig::{
+    outbox::{AgentEvent, EventBus, OutboxError},
+    vector_store::events::DocumentEvent,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{decode, SourceError};
+
+/// [NatsSource] reads [DocumentEvent]s from the messages published on NATS subjects.
+///
+/// Core NATS delivers messages at most once: events published while the source is not
+/// subscribed are not received.
+pub struct NatsSource {
+    subscriber: Subscriber,
+}
+
+impl NatsSource {
+    /// Subscribe to `subject` (which may contain wildcards, e.g.: `documents.>`).
+    pub async fn subscribe(
+        client: &Client,
+        subject: impl ToSubject,
+    ) -> Result<Self, SubscribeError> {
+        Ok(Self::from_subscriber(client.subscribe(subject).await?))
+    }
+
+    /// Create a source from an existing subscription (e.g.: of a queue group, to share the
+    /// events between several ingestors).
+    pub fn from_subscriber(subscriber: Subscriber) -> Self {
+        Self { subscriber }
+    }
+
+    /// Stream of the events of the subscription, which ends when the subscription is closed.
+    /// Messages whose payload is not a valid event are skipped.
+    pub fn events<D: DeserializeOwned>(
+        self,
+    ) -> impl Stream<Item = Result<DocumentEvent<D>, SourceError>> {
+        self.subscriber
+            .filter_map(|message| async move { decode(&message.payload, &message.subject).map(Ok) })
+    }
+}
+
+/// [EventBus] publishing the events of an agent as JSON messages on a NATS subject.
+#[derive(Clone)]
+pub struct NatsEventBus {
+    client: Client,
+    subject: Subject,
+}
+
+impl NatsEventBus {
+    pub fn new(client: Client, subject: impl ToSubject) -> Self {
+        Self {
+            client,
+            subject: subject.to_subject(),
+        }
+    }
+}
+
+impl EventBus for NatsEventBus {
+    async fn publish(&self, event: AgentEvent) -> Result<(), OutboxError> {
+        self.client
+            .publish(self.subject.clone(), serde_json::to_vec(&event)?.into())
+            .await
+            .map_err(|e| OutboxError::PublishError(e.into()))
+    }
+}
@@ -1,12 +1,20 @@
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-pub use fastembed::EmbeddingModel as FastembedModel;
-use fastembed::{InitOptions, TextEmbedding};
+pub use fastembed::{EmbeddingModel as FastembedModel, Pooling};
+use fastembed::{
+    InitOptions, InitOptionsUserDefined, TextEmbedding, TokenizerFiles, UserDefinedEmbeddingModel,
+};
 use rig::{
     embeddings::{self, EmbeddingError, EmbeddingsBuilder},
     Embed,
 };
 
+#[cfg(feature = "rerank")]
+pub mod rerank;
+
 #[derive(Clone)]
 pub struct Client;
 
@@ -63,6 +71,65 @@ impl Client {
     ) -> EmbeddingsBuilder<EmbeddingModel, D> {
         EmbeddingsBuilder::new(self.embedding_model(model))
     }
+
+    /// Load a sentence-transformer model exported to ONNX from the local directory `dir`
+    /// (see [LocalEmbeddingModel::from_dir]), to embed documents offline.
+    ///
+    /// # Example
+    /// ```
+    /// use rig_fastembed::Client;
+    ///
+    /// let fastembed_client = Client::new();
+    ///
+    /// let embedding_model = fastembed_client
+    ///     .local_embedding_model("models/all-MiniLM-L6-v2")
+    ///     .expect("Failed to load the model");
+    /// ```
+    pub fn local_embedding_model(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> Result<LocalEmbeddingModel, EmbeddingError> {
+        LocalEmbeddingModel::from_dir(dir)
+    }
+
+    /// Create an embedding builder with the local model of the directory `dir`.
+    pub fn local_embeddings<D: Embed>(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> Result<EmbeddingsBuilder<LocalEmbeddingModel, D>, EmbeddingError> {
+        Ok(EmbeddingsBuilder::new(self.local_embedding_model(dir)?))
+    }
+
+    /// Create a cross-encoder rerank model with the given name, downloaded on first use.
+    ///
+    /// # Example
+    /// ```
+    /// use rig_fastembed::{rerank::RerankerModel, Client};
+    ///
+    /// let fastembed_client = Client::new();
+    ///
+    /// let reranker = fastembed_client
+    ///     .rerank_model(&RerankerModel::BGERerankerBase)
+    ///     .expect("Failed to load the model");
+    /// let index = vector_store.index(embedding_model).with_reranker(reranker, 20);
+    /// ```
+    #[cfg(feature = "rerank")]
+    pub fn rerank_model(
+        &self,
+        model: &rerank::RerankerModel,
+    ) -> Result<rerank::RerankModel, rig::rerank::RerankError> {
+        rerank::RerankModel::new(model)
+    }
+
+    /// Load a cross-encoder exported to ONNX from the local directory `dir` (see
+    /// [rerank::RerankModel::from_dir]), to re-rank documents offline.
+    #[cfg(feature = "rerank")]
+    pub fn local_rerank_model(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> Result<rerank::RerankModel, rig::rerank::RerankError> {
+        rerank::RerankModel::from_dir(dir)
+    }
 }
 
 #[derive(Clone)]
@@ -100,26 +167,125 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         &self,
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
-        let documents_as_strings: Vec<String> = documents.into_iter().collect();
+        embed(&self.embedder, documents)
+    }
+}
+
+/// Sentence-transformer model exported to ONNX, loaded from the files of a local directory
+/// (e.g.: the `onnx` export of `sentence-transformers/all-MiniLM-L6-v2` downloaded from the
+/// Hugging Face hub), so that documents are embedded offline and without API costs.
+#[derive(Clone)]
+pub struct LocalEmbeddingModel {
+    embedder: Arc<TextEmbedding>,
+    /// Directory the model was loaded from
+    pub dir: PathBuf,
+    ndims: usize,
+}
+
+impl LocalEmbeddingModel {
+    /// Load the model of `dir`, which contains the `model.onnx` file (or `onnx/model.onnx`) and
+    /// the `tokenizer.json`, `config.json`, `special_tokens_map.json` and
+    /// `tokenizer_config.json` files of the model.
+    /// The embeddings are the mean of the token embeddings, as for most sentence-transformers,
+    /// and their dimension is the `hidden_size` of `config.json`.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, EmbeddingError> {
+        Self::from_dir_with_pooling(dir, Pooling::Mean)
+    }
+
+    /// Same as `from_dir`, pooling the token embeddings with `pooling` (e.g.: [Pooling::Cls]
+    /// for BGE models).
+    pub fn from_dir_with_pooling(
+        dir: impl AsRef<Path>,
+        pooling: Pooling,
+    ) -> Result<Self, EmbeddingError> {
+        let dir = dir.as_ref();
+        let onnx_file = read_onnx_file(dir).map_err(EmbeddingError::ProviderError)?;
+        let tokenizer_files = read_tokenizer_files(dir).map_err(EmbeddingError::ProviderError)?;
+        let config: serde_json::Value = serde_json::from_slice(&tokenizer_files.config_file)?;
+        let ndims = config["hidden_size"]
+            .as_u64()
+            .or(config["dim"].as_u64())
+            .ok_or_else(|| {
+                EmbeddingError::ProviderError(format!(
+                    "No hidden_size in {}",
+                    dir.join("config.json").display()
+                ))
+            })? as usize;
+
+        let model =
+            UserDefinedEmbeddingModel::new(onnx_file, tokenizer_files).with_pooling(pooling);
+        let embedder =
+            TextEmbedding::try_new_from_user_defined(model, InitOptionsUserDefined::new())
+                .map_err(|err| EmbeddingError::ProviderError(err.to_string()))?;
 
-        let documents_as_vec = self
-            .embedder
-            .embed(documents_as_strings.clone(), None)
-            .map_err(|err| EmbeddingError::ProviderError(err.to_string()))?;
+        Ok(Self {
+            embedder: Arc::new(embedder),
+            dir: dir.to_path_buf(),
+            ndims,
+        })
+    }
+}
+
+impl embeddings::EmbeddingModel for LocalEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
 
-        let docs = documents_as_strings
-            .into_iter()
-            .zip(documents_as_vec)
-            .map(|(document, embedding)| embeddings::Embedding {
-                document,
-                vec: embedding.into_iter().map(|f| f as f64).collect(),
-            })
-            .collect::<Vec<embeddings::Embedding>>();
+    async fn embed_texts(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        embed(&self.embedder, documents)
+    }
+}
 
-        Ok(docs)
+/// Read the `model.onnx` file (or `onnx/model.onnx`) of the model directory `dir`.
+fn read_onnx_file(dir: &Path) -> Result<Vec<u8>, String> {
+    match dir.join("model.onnx").exists() {
+        true => read_model_file(dir, "model.onnx"),
+        false => read_model_file(dir, "onnx/model.onnx"),
     }
 }
 
+/// Read the tokenizer and configuration files of the model directory `dir`.
+fn read_tokenizer_files(dir: &Path) -> Result<TokenizerFiles, String> {
+    Ok(TokenizerFiles {
+        tokenizer_file: read_model_file(dir, "tokenizer.json")?,
+        config_file: read_model_file(dir, "config.json")?,
+        special_tokens_map_file: read_model_file(dir, "special_tokens_map.json")?,
+        tokenizer_config_file: read_model_file(dir, "tokenizer_config.json")?,
+    })
+}
+
+fn read_model_file(dir: &Path, file: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(dir.join(file))
+        .map_err(|e| format!("Failed to read {}: {e}", dir.join(file).display()))
+}
+
+fn embed(
+    embedder: &TextEmbedding,
+    documents: impl IntoIterator<Item = String>,
+) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+    let documents_as_strings: Vec<String> = documents.into_iter().collect();
+
+    let documents_as_vec = embedder
+        .embed(documents_as_strings.clone(), None)
+        .map_err(|err| EmbeddingError::ProviderError(err.to_string()))?;
+
+    let docs = documents_as_strings
+        .into_iter()
+        .zip(documents_as_vec)
+        .map(|(document, embedding)| embeddings::Embedding {
+            document,
+            vec: embedding.into_iter().map(|f| f as f64).collect(),
+        })
+        .collect::<Vec<embeddings::Embedding>>();
+
+    Ok(docs)
+}
+
 /// As seen on the text embedding model cards file: <https://github.com/Anush008/fastembed-rs/blob/main/src/models/text_embedding.rs>
 pub fn fetch_model_ndims(model: &FastembedModel) -> usize {
     match model {
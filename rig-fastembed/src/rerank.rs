@@ -0,0 +1,102 @@
+//! Local cross-encoder rerank models, scoring the relevance of documents to a query on the
+//! CPU with ONNX runtime, so that re-ranking does not cost an API call per query.
+//!
+//! Besides the rerank models supported by `fastembed` ([RerankerModel], downloaded on first use),
+//! cross-encoders exported to ONNX (e.g.: the `onnx` export of `cross-encoder/ms-marco-MiniLM-L-6-v2`
+//! downloaded from the Hugging Face hub) are loaded from a local directory with
+//! [RerankModel::from_dir].
+//!
+//! # Example
+//! ```rust
+//! use rig::vector_store::VectorStoreIndex;
+//! use rig_fastembed::rerank::RerankModel;
+//!
+//! let reranker = RerankModel::from_dir("models/ms-marco-MiniLM-L-6-v2")?;
+//!
+//! // Re-rank the 20 best matches of the vector store and keep the 3 most relevant
+//! let index = vector_store.index(embedding_model).with_reranker(reranker, 20);
+//! let agent = openai.agent("gpt-4o").dynamic_context(3, index).build();
+//! ```
+use std::{path::Path, sync::Arc};
+
+pub use fastembed::RerankerModel;
+use fastembed::{
+    RerankInitOptions, RerankInitOptionsUserDefined, TextRerank, UserDefinedRerankingModel,
+};
+use rig::rerank::{RerankError, Reranker};
+
+use crate::{read_onnx_file, read_tokenizer_files};
+
+/// Cross-encoder rerank model, scoring each document with the logit of the model for the pair
+/// (query, document). The scores are not normalized: they are only comparable to the scores of
+/// the same model.
+#[derive(Clone)]
+pub struct RerankModel {
+    reranker: Arc<TextRerank>,
+    batch_size: Option<usize>,
+}
+
+impl RerankModel {
+    /// Load the rerank model `model`, downloaded on first use.
+    pub fn new(model: &RerankerModel) -> Result<Self, RerankError> {
+        let reranker = TextRerank::try_new(
+            RerankInitOptions::new(model.to_owned()).with_show_download_progress(true),
+        )
+        .map_err(|err| RerankError::ProviderError(err.to_string()))?;
+
+        Ok(Self::from_reranker(reranker))
+    }
+
+    /// Load the cross-encoder of `dir`, which contains the `model.onnx` file (or
+    /// `onnx/model.onnx`) and the `tokenizer.json`, `config.json`, `special_tokens_map.json` and
+    /// `tokenizer_config.json` files of the model.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, RerankError> {
+        let dir = dir.as_ref();
+        let model = UserDefinedRerankingModel::new(
+            read_onnx_file(dir).map_err(RerankError::ProviderError)?,
+            read_tokenizer_files(dir).map_err(RerankError::ProviderError)?,
+        );
+        let reranker =
+            TextRerank::try_new_from_user_defined(model, RerankInitOptionsUserDefined::default())
+                .map_err(|err| RerankError::ProviderError(err.to_string()))?;
+
+        Ok(Self::from_reranker(reranker))
+    }
+
+    /// Wrap a reranker configured with `fastembed` (e.g.: with a custom cache directory or
+    /// execution providers).
+    pub fn from_reranker(reranker: TextRerank) -> Self {
+        Self {
+            reranker: Arc::new(reranker),
+            batch_size: None,
+        }
+    }
+
+    /// Set the number of documents scored per inference batch (256 by default).
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+}
+
+impl Reranker for RerankModel {
+    async fn rerank(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+        top_n: usize,
+    ) -> Result<Vec<(f64, usize)>, RerankError> {
+        let query = query.to_string();
+        let results = self
+            .reranker
+            .rerank(query, documents, false, self.batch_size)
+            .map_err(|err| RerankError::ProviderError(err.to_string()))?;
+
+        // The results are sorted by decreasing score
+        Ok(results
+            .into_iter()
+            .take(top_n)
+            .map(|result| (result.score as f64, result.index))
+            .collect())
+    }
+}
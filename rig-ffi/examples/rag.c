@@ -0,0 +1,46 @@
+/*
+ * Answer a question about a few definitions added to a vector store.
+ *
+ * Build the library with `cargo build --release -p rig-ffi`, then from the root of the
+ * repository:
+ *   cc rig-ffi/examples/rag.c -Irig-ffi/include -Ltarget/release -lrig_ffi -o rag
+ *   LD_LIBRARY_PATH=target/release OPENAI_API_KEY=... ./rag
+ */
+#include <stdio.h>
+
+#include "rig.h"
+
+static int fail(void) {
+    fprintf(stderr, "error: %s\n", rig_last_error());
+    return 1;
+}
+
+int main(void) {
+    RigClient *client = rig_openai_client_new(NULL, NULL);
+    if (client == NULL) {
+        return fail();
+    }
+    RigEmbeddingModel *model = rig_embedding_model_new(client, "text-embedding-3-small", 0);
+    RigVectorStore *store = rig_vector_store_new(model);
+
+    if (rig_vector_store_add(store, "flurbo", "A flurbo is a green alien that lives on cold planets.",
+                             NULL) != 0 ||
+        rig_vector_store_add(store, "glarb-glarb",
+                             "A glarb-glarb is an ancient tool used to farm the land.", NULL) != 0) {
+        return fail();
+    }
+
+    RigAgent *agent = rig_agent_new(client, "gpt-4o", "You are a dictionary assistant.", store, 1);
+    char *answer = rig_agent_prompt(agent, "What does \"glarb-glarb\" mean?");
+    if (answer == NULL) {
+        return fail();
+    }
+    printf("%s\n", answer);
+    rig_string_free(answer);
+
+    rig_agent_free(agent);
+    rig_vector_store_free(store);
+    rig_embedding_model_free(model);
+    rig_client_free(client);
+    return 0;
+}
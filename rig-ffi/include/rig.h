@@ -0,0 +1,105 @@
+/*
+ * C API of Rig: OpenAI (and OpenAI-compatible) agents, embedding models and in-memory vector
+ * stores.
+ *
+ * Objects are opaque, thread-safe handles created by the `rig_*_new` (or `rig_*_load`)
+ * functions and destroyed by the corresponding `rig_*_free` functions. Strings are
+ * null-terminated UTF-8, and strings returned by the API must be freed with `rig_string_free`.
+ *
+ * Functions report failures by returning NULL (or -1), and the message of the error can then be
+ * retrieved with `rig_last_error`. Calls to the model providers block the calling thread.
+ */
+#ifndef RIG_H
+#define RIG_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RigClient RigClient;
+typedef struct RigAgent RigAgent;
+typedef struct RigEmbeddingModel RigEmbeddingModel;
+typedef struct RigVectorStore RigVectorStore;
+
+/* Errors and strings */
+
+/* Message of the error of the last call made on the calling thread, or NULL if it succeeded.
+ * Owned by the library and valid until the next call on the thread. */
+const char *rig_last_error(void);
+
+/* Free a string returned by the API. Does nothing if `string` is NULL. */
+void rig_string_free(char *string);
+
+/* Clients */
+
+/* Create a client of the OpenAI API, or of an OpenAI-compatible API if `base_url` is not NULL.
+ * If `api_key` is NULL, the OPENAI_API_KEY environment variable is used. */
+RigClient *rig_openai_client_new(const char *api_key, const char *base_url);
+
+void rig_client_free(RigClient *client);
+
+/* Agents */
+
+/* Create an agent using the completion model `model`, with an optional `preamble`. If `store` is
+ * not NULL, the `sample` documents of the store most relevant to each prompt are added to the
+ * context of the agent. */
+RigAgent *rig_agent_new(const RigClient *client,
+                        const char *model,
+                        const char *preamble,
+                        const RigVectorStore *store,
+                        size_t sample);
+
+/* Send a prompt to an agent and return its answer. */
+char *rig_agent_prompt(const RigAgent *agent, const char *prompt);
+
+void rig_agent_free(RigAgent *agent);
+
+/* Embeddings */
+
+/* Create an embedding model. `ndims` is only required for models unknown to Rig, and is
+ * otherwise 0. */
+RigEmbeddingModel *rig_embedding_model_new(const RigClient *client,
+                                           const char *model,
+                                           size_t ndims);
+
+void rig_embedding_model_free(RigEmbeddingModel *model);
+
+/* Embed `text`, returning its embedding and writing its number of dimensions to `ndims`. */
+double *rig_embed(const RigEmbeddingModel *model, const char *text, size_t *ndims);
+
+/* Free an embedding returned by `rig_embed`, given its number of dimensions. */
+void rig_embedding_free(double *embedding, size_t ndims);
+
+/* Vector stores */
+
+/* Create an empty vector store whose documents are embedded with `model`. */
+RigVectorStore *rig_vector_store_new(const RigEmbeddingModel *model);
+
+/* Load a vector store saved with `rig_vector_store_save` (or `InMemoryVectorStore::save`). */
+RigVectorStore *rig_vector_store_load(const RigEmbeddingModel *model, const char *path);
+
+/* Save a vector store to the file at `path`. Returns 0 on success and -1 on error. */
+int32_t rig_vector_store_save(const RigVectorStore *store, const char *path);
+
+/* Embed `document` and add it to a vector store with the id `id` and optional JSON `metadata`,
+ * replacing any document with the same id. Returns 0 on success and -1 on error. */
+int32_t rig_vector_store_add(const RigVectorStore *store,
+                             const char *id,
+                             const char *document,
+                             const char *metadata);
+
+/* Search the `n` documents most similar to `query`, returned as a JSON array of
+ * {"score": ..., "id": ..., "document": ...} objects. */
+char *rig_vector_store_search(const RigVectorStore *store, const char *query, size_t n);
+
+/* Free a vector store. Agents using the store keep their own reference to its documents. */
+void rig_vector_store_free(RigVectorStore *store);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RIG_H */
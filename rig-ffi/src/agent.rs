@@ -0,0 +1,66 @@
+use std::ffi::c_char;
+
+use rig::{agent::Agent, completion::Prompt, providers::openai};
+
+use crate::{
+    block_on, ffi_call, free_handle, handle, into_handle, to_c_string, to_opt_str, to_str,
+    RigClient, RigVectorStore,
+};
+
+/// Agent backed by an OpenAI completion model
+pub struct RigAgent(Agent<openai::CompletionModel>);
+
+/// Create an agent using the completion model `model` of `client`, with an optional `preamble`.
+/// If `store` is not `NULL`, the `sample` documents of the store most relevant to each prompt
+/// are added to the context of the agent. Returns `NULL` on error.
+///
+/// # Safety
+/// `client` must be a live client, `model` a null-terminated string, `preamble` `NULL` or a
+/// null-terminated string and `store` `NULL` or a live vector store.
+#[no_mangle]
+pub unsafe extern "C" fn rig_agent_new(
+    client: *const RigClient,
+    model: *const c_char,
+    preamble: *const c_char,
+    store: *const RigVectorStore,
+    sample: usize,
+) -> *mut RigAgent {
+    ffi_call(std::ptr::null_mut(), || {
+        let client = handle(client, "client")?;
+        let mut agent = client.0.agent(to_str(model, "model")?);
+        if let Some(preamble) = to_opt_str(preamble, "preamble")? {
+            agent = agent.preamble(preamble);
+        }
+        if let Some(store) = store.as_ref() {
+            agent = agent.dynamic_context(sample, store.shared_index());
+        }
+        Ok(into_handle(RigAgent(agent.build())))
+    })
+}
+
+/// Send a prompt to an agent and return its answer, to be freed with `rig_string_free`.
+/// Returns `NULL` on error.
+///
+/// # Safety
+/// `agent` must be a live agent and `prompt` a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rig_agent_prompt(
+    agent: *const RigAgent,
+    prompt: *const c_char,
+) -> *mut c_char {
+    ffi_call(std::ptr::null_mut(), || {
+        let agent = handle(agent, "agent")?;
+        let prompt = to_str(prompt, "prompt")?;
+        let answer = block_on(agent.0.prompt(prompt)).map_err(|e| e.to_string())?;
+        to_c_string(answer)
+    })
+}
+
+/// Free an agent. Does nothing if `agent` is `NULL`.
+///
+/// # Safety
+/// `agent` must be `NULL` or a live agent, which must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rig_agent_free(agent: *mut RigAgent) {
+    free_handle(agent)
+}
@@ -0,0 +1,41 @@
+use std::ffi::c_char;
+
+use rig::providers::openai;
+
+use crate::{ffi_call, free_handle, into_handle, to_opt_str};
+
+/// Client of the OpenAI API, or of an OpenAI-compatible API
+pub struct RigClient(pub(crate) openai::Client);
+
+/// Create a client of the OpenAI API, or of an OpenAI-compatible API (e.g.: Ollama) if
+/// `base_url` is not `NULL`. If `api_key` is `NULL`, the `OPENAI_API_KEY` environment variable
+/// is used. Returns `NULL` on error.
+///
+/// # Safety
+/// `api_key` and `base_url` must be `NULL` or null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn rig_openai_client_new(
+    api_key: *const c_char,
+    base_url: *const c_char,
+) -> *mut RigClient {
+    ffi_call(std::ptr::null_mut(), || {
+        let api_key = match to_opt_str(api_key, "api_key")? {
+            Some(api_key) => api_key.to_string(),
+            None => std::env::var("OPENAI_API_KEY").map_err(|_| "OPENAI_API_KEY not set")?,
+        };
+        let client = match to_opt_str(base_url, "base_url")? {
+            Some(base_url) => openai::Client::from_url(&api_key, base_url),
+            None => openai::Client::new(&api_key),
+        };
+        Ok(into_handle(RigClient(client)))
+    })
+}
+
+/// Free a client. Does nothing if `client` is `NULL`.
+///
+/// # Safety
+/// `client` must be `NULL` or a live client, which must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rig_client_free(client: *mut RigClient) {
+    free_handle(client)
+}
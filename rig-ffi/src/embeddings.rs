@@ -0,0 +1,79 @@
+use std::ffi::c_char;
+
+use rig::{embeddings::EmbeddingModel, providers::openai};
+
+use crate::{block_on, ffi_call, free_handle, handle, into_handle, to_str, RigClient};
+
+/// OpenAI embedding model
+pub struct RigEmbeddingModel(pub(crate) openai::EmbeddingModel);
+
+/// Create the embedding model `model` of `client`. `ndims` is only required for models unknown
+/// to Rig, and is otherwise `0`. Returns `NULL` on error.
+///
+/// # Safety
+/// `client` must be a live client and `model` a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rig_embedding_model_new(
+    client: *const RigClient,
+    model: *const c_char,
+    ndims: usize,
+) -> *mut RigEmbeddingModel {
+    ffi_call(std::ptr::null_mut(), || {
+        let client = handle(client, "client")?;
+        let model = to_str(model, "model")?;
+        Ok(into_handle(RigEmbeddingModel(match ndims {
+            0 => client.0.embedding_model(model),
+            ndims => client.0.embedding_model_with_ndims(model, ndims),
+        })))
+    })
+}
+
+/// Free an embedding model. Does nothing if `model` is `NULL`.
+///
+/// # Safety
+/// `model` must be `NULL` or a live embedding model, which must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rig_embedding_model_free(model: *mut RigEmbeddingModel) {
+    free_handle(model)
+}
+
+/// Embed `text`, returning its embedding (to be freed with `rig_embedding_free`) and writing its
+/// number of dimensions to `ndims`. Returns `NULL` on error.
+///
+/// # Safety
+/// `model` must be a live embedding model, `text` a null-terminated string and `ndims` a valid
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn rig_embed(
+    model: *const RigEmbeddingModel,
+    text: *const c_char,
+    ndims: *mut usize,
+) -> *mut f64 {
+    ffi_call(std::ptr::null_mut(), || {
+        let model = handle(model, "model")?;
+        let text = to_str(text, "text")?;
+        if ndims.is_null() {
+            return Err("ndims is NULL".to_string());
+        }
+        let embedding = block_on(model.0.embed_text(text)).map_err(|e| e.to_string())?;
+
+        let embedding = embedding.vec.into_boxed_slice();
+        *ndims = embedding.len();
+        Ok(Box::into_raw(embedding).cast())
+    })
+}
+
+/// Free an embedding returned by `rig_embed`, given its number of dimensions. Does nothing if
+/// `embedding` is `NULL`.
+///
+/// # Safety
+/// `embedding` must be `NULL` or an embedding returned by `rig_embed` with `ndims` dimensions,
+/// not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn rig_embedding_free(embedding: *mut f64, ndims: usize) {
+    if !embedding.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            embedding, ndims,
+        )));
+    }
+}
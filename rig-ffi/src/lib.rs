@@ -0,0 +1,313 @@
+//! C API of Rig, to embed OpenAI (and OpenAI-compatible) agents, embedding models and in-memory
+//! vector stores in applications written in other languages. The API is declared in
+//! `include/rig.h`.
+//!
+//! # Conventions
+//! - Objects are opaque handles created by `rig_*_new` (or `rig_*_load`) functions and destroyed
+//!   by the corresponding `rig_*_free` function. Handles are thread-safe.
+//! - Strings are null-terminated UTF-8. Strings returned by the API are owned by the caller and
+//!   must be freed with [rig_string_free].
+//! - Functions report failures by returning `NULL` (or `-1`), and the message of the error can
+//!   then be retrieved with [rig_last_error].
+//! - Calls to the model providers block the calling thread until they complete.
+//!
+//! # Example
+//! ```c
+//! RigClient *client = rig_openai_client_new(NULL, NULL);
+//! RigAgent *agent = rig_agent_new(client, "gpt-4o", "You are a helpful assistant.", NULL, 0);
+//!
+//! char *answer = rig_agent_prompt(agent, "Who are you?");
+//! if (answer == NULL) {
+//!     fprintf(stderr, "error: %s\n", rig_last_error());
+//! } else {
+//!     printf("%s\n", answer);
+//!     rig_string_free(answer);
+//! }
+//!
+//! rig_agent_free(agent);
+//! rig_client_free(client);
+//! ```
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    future::Future,
+    panic::AssertUnwindSafe,
+    ptr,
+    sync::OnceLock,
+};
+
+use tokio::runtime::Runtime;
+
+mod agent;
+mod client;
+mod embeddings;
+mod vector_store;
+
+pub use agent::*;
+pub use client::*;
+pub use embeddings::*;
+pub use vector_store::*;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Message of the error of the last call made to the API on the calling thread, or `NULL` if it
+/// succeeded. The message is owned by the library and valid until the next call on the thread.
+#[no_mangle]
+pub extern "C" fn rig_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |error| error.as_ptr())
+    })
+}
+
+/// Free a string returned by the API. Does nothing if `string` is `NULL`.
+///
+/// # Safety
+/// `string` must be `NULL` or a string returned by the API, not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn rig_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Run `f`, recording its error (or panic) as the last error of the thread and returning
+/// `default` if it fails.
+fn ffi_call<T>(default: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    let result = std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        Err(panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Rig panicked".to_string()))
+    });
+
+    let (value, error) = match result {
+        Ok(value) => (value, None),
+        Err(error) => (
+            default,
+            // Interior null bytes cannot be represented in a C string
+            Some(CString::new(error.replace('\0', " ")).expect("null bytes are replaced")),
+        ),
+    };
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = error);
+    value
+}
+
+/// Runtime on which the futures of Rig are executed
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Tokio runtime should build")
+    })
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
+
+/// Borrow the object behind a handle.
+///
+/// # Safety
+/// `handle` must be `NULL` or a live handle of type `T`.
+unsafe fn handle<'a, T>(handle: *const T, name: &str) -> Result<&'a T, String> {
+    handle.as_ref().ok_or_else(|| format!("{name} is NULL"))
+}
+
+/// Borrow a C string argument.
+///
+/// # Safety
+/// `string` must be `NULL` or a null-terminated string.
+unsafe fn to_str<'a>(string: *const c_char, name: &str) -> Result<&'a str, String> {
+    to_opt_str(string, name)?.ok_or_else(|| format!("{name} is NULL"))
+}
+
+/// Borrow an optional C string argument (`NULL` meaning `None`).
+///
+/// # Safety
+/// `string` must be `NULL` or a null-terminated string.
+unsafe fn to_opt_str<'a>(string: *const c_char, name: &str) -> Result<Option<&'a str>, String> {
+    if string.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map(Some)
+        .map_err(|e| format!("{name} is not valid UTF-8: {e}"))
+}
+
+/// Convert a string to a C string owned by the caller.
+fn to_c_string(string: String) -> Result<*mut c_char, String> {
+    CString::new(string)
+        .map(CString::into_raw)
+        .map_err(|e| e.to_string())
+}
+
+/// Move an object to the heap and return its handle.
+fn into_handle<T>(object: T) -> *mut T {
+    Box::into_raw(Box::new(object))
+}
+
+/// Destroy the object behind a handle. Does nothing if `handle` is `NULL`.
+///
+/// # Safety
+/// `handle` must be `NULL` or a live handle of type `T`, which must not be used afterwards.
+unsafe fn free_handle<T>(handle: *mut T) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn last_error() -> Option<String> {
+        let error = rig_last_error();
+        (!error.is_null()).then(|| {
+            unsafe { CStr::from_ptr(error) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    #[test]
+    fn test_last_error() {
+        assert_eq!(ffi_call(0, || Err("failed\0".to_string())), 0);
+        assert_eq!(last_error().as_deref(), Some("failed "));
+
+        assert_eq!(ffi_call(0, || panic!("boom")), 0);
+        assert_eq!(last_error().as_deref(), Some("boom"));
+
+        assert_eq!(ffi_call(0, || Ok(1)), 1);
+        assert_eq!(last_error(), None);
+    }
+
+    #[test]
+    fn test_null_arguments() {
+        let model = CString::new("gpt-4o").unwrap();
+        let agent =
+            unsafe { rig_agent_new(ptr::null(), model.as_ptr(), ptr::null(), ptr::null(), 0) };
+        assert!(agent.is_null());
+        assert_eq!(last_error().as_deref(), Some("client is NULL"));
+
+        unsafe {
+            rig_agent_free(ptr::null_mut());
+            rig_string_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_embed_search_and_prompt() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/embeddings")
+                .body_contains("flurbo");
+            then.status(200)
+                .json_body(embeddings_response(vec![1.0, 0.0]));
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/embeddings").body_contains("glarb");
+            then.status(200)
+                .json_body(embeddings_response(vec![0.0, 1.0]));
+        });
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/chat/completions")
+                .body_contains("A flurbo is a green alien");
+            then.status(200).json_body(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "A green alien."},
+                    "logprobs": null,
+                    "finish_reason": "stop"
+                }]
+            }));
+        });
+
+        let api_key = CString::new("TEST").unwrap();
+        let base_url = CString::new(server.base_url()).unwrap();
+        let embedding_model = CString::new("text-embedding-3-small").unwrap();
+        let completion_model = CString::new("gpt-4o").unwrap();
+        let id = CString::new("flurbo").unwrap();
+        let document = CString::new("A flurbo is a green alien").unwrap();
+        let metadata = CString::new(r#"{"lang": "en"}"#).unwrap();
+        let query = CString::new("What is a flurbo?").unwrap();
+
+        unsafe {
+            let client = rig_openai_client_new(api_key.as_ptr(), base_url.as_ptr());
+            let model = rig_embedding_model_new(client, embedding_model.as_ptr(), 2);
+            assert!(!model.is_null());
+
+            let mut ndims = 0;
+            let embedding = rig_embed(model, query.as_ptr(), &mut ndims);
+            assert_eq!(
+                std::slice::from_raw_parts(embedding, ndims),
+                &[1.0, 0.0][..]
+            );
+            rig_embedding_free(embedding, ndims);
+
+            let store = rig_vector_store_new(model);
+            assert_eq!(
+                rig_vector_store_add(store, id.as_ptr(), document.as_ptr(), metadata.as_ptr()),
+                0
+            );
+            let results = rig_vector_store_search(store, query.as_ptr(), 1);
+            let parsed: Value =
+                serde_json::from_str(CStr::from_ptr(results).to_str().unwrap()).unwrap();
+            assert_eq!(
+                parsed,
+                json!([{"score": 1.0, "id": "flurbo", "document": "A flurbo is a green alien"}])
+            );
+            rig_string_free(results);
+
+            let agent = rig_agent_new(client, completion_model.as_ptr(), ptr::null(), store, 1);
+            rig_vector_store_free(store);
+            let answer = rig_agent_prompt(agent, query.as_ptr());
+            assert!(!answer.is_null(), "{:?}", last_error());
+            assert_eq!(CStr::from_ptr(answer).to_str(), Ok("A green alien."));
+            rig_string_free(answer);
+
+            let bad_metadata = CString::new("{").unwrap();
+            let store = rig_vector_store_new(model);
+            assert_eq!(
+                rig_vector_store_add(store, id.as_ptr(), document.as_ptr(), bad_metadata.as_ptr()),
+                -1
+            );
+            assert!(last_error()
+                .unwrap()
+                .starts_with("metadata is not valid JSON"));
+
+            rig_vector_store_free(store);
+            rig_agent_free(agent);
+            rig_embedding_model_free(model);
+            rig_client_free(client);
+        }
+    }
+
+    fn embeddings_response(embedding: Vec<f64>) -> Value {
+        json!({
+            "object": "list",
+            "data": [{"object": "embedding", "embedding": embedding, "index": 0}],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 8, "total_tokens": 8}
+        })
+    }
+}
@@ -0,0 +1,226 @@
+use std::{ffi::c_char, sync::Arc};
+
+use rig::{
+    embeddings::EmbeddingModel,
+    providers::openai,
+    vector_store::{
+        in_memory_store::{InMemoryVectorIndex, InMemoryVectorStore},
+        SearchOptions, VectorStoreError, VectorStoreIndex,
+    },
+    OneOrMany,
+};
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+
+use crate::{
+    block_on, ffi_call, free_handle, handle, into_handle, to_c_string, to_opt_str, to_str,
+    RigEmbeddingModel,
+};
+
+type Index = InMemoryVectorIndex<openai::EmbeddingModel, Value>;
+
+/// Index shared by a [RigVectorStore] and the agents using it, so that the agents see the
+/// documents added to the store after their creation.
+#[derive(Clone)]
+pub(crate) struct SharedIndex(Arc<RwLock<Index>>);
+
+impl VectorStoreIndex for SharedIndex {
+    async fn top_n<T: for<'a> serde::Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.0.read().await.top_n(query, n).await
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.0.read().await.top_n_ids(query, n).await
+    }
+
+    async fn top_n_with_options<T: for<'a> serde::Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.0
+            .read()
+            .await
+            .top_n_with_options(query, n, options)
+            .await
+    }
+
+    async fn top_n_ids_with_options(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.0
+            .read()
+            .await
+            .top_n_ids_with_options(query, n, options)
+            .await
+    }
+}
+
+/// In-memory vector store of text documents, embedded with an embedding model
+pub struct RigVectorStore {
+    model: openai::EmbeddingModel,
+    index: SharedIndex,
+}
+
+impl RigVectorStore {
+    fn from_store(model: &RigEmbeddingModel, store: InMemoryVectorStore<Value>) -> Self {
+        Self {
+            model: model.0.clone(),
+            index: SharedIndex(Arc::new(RwLock::new(store.index(model.0.clone())))),
+        }
+    }
+
+    pub(crate) fn shared_index(&self) -> SharedIndex {
+        self.index.clone()
+    }
+}
+
+/// Create an empty vector store whose documents are embedded with `model`. Returns `NULL` on
+/// error.
+///
+/// # Safety
+/// `model` must be a live embedding model.
+#[no_mangle]
+pub unsafe extern "C" fn rig_vector_store_new(
+    model: *const RigEmbeddingModel,
+) -> *mut RigVectorStore {
+    ffi_call(std::ptr::null_mut(), || {
+        let model = handle(model, "model")?;
+        Ok(into_handle(RigVectorStore::from_store(
+            model,
+            InMemoryVectorStore::default(),
+        )))
+    })
+}
+
+/// Load a vector store saved with `rig_vector_store_save` (or with `InMemoryVectorStore::save`
+/// in Rust) from the file at `path`. Returns `NULL` on error.
+///
+/// # Safety
+/// `model` must be a live embedding model and `path` a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rig_vector_store_load(
+    model: *const RigEmbeddingModel,
+    path: *const c_char,
+) -> *mut RigVectorStore {
+    ffi_call(std::ptr::null_mut(), || {
+        let model = handle(model, "model")?;
+        let store = InMemoryVectorStore::load(to_str(path, "path")?).map_err(|e| e.to_string())?;
+        Ok(into_handle(RigVectorStore::from_store(model, store)))
+    })
+}
+
+/// Save the documents, embeddings and metadata of a vector store to the file at `path`.
+/// Returns `0` on success and `-1` on error.
+///
+/// # Safety
+/// `store` must be a live vector store and `path` a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rig_vector_store_save(
+    store: *const RigVectorStore,
+    path: *const c_char,
+) -> i32 {
+    ffi_call(-1, || {
+        let store = handle(store, "store")?;
+        let path = to_str(path, "path")?;
+        store
+            .index
+            .0
+            .blocking_read()
+            .store
+            .save(path)
+            .map_err(|e| e.to_string())?;
+        Ok(0)
+    })
+}
+
+/// Embed `document` and add it to a vector store with the id `id` and optional JSON `metadata`.
+/// A document with the same id is replaced. Returns `0` on success and `-1` on error.
+///
+/// # Safety
+/// `store` must be a live vector store, `id` and `document` null-terminated strings and
+/// `metadata` `NULL` or a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rig_vector_store_add(
+    store: *const RigVectorStore,
+    id: *const c_char,
+    document: *const c_char,
+    metadata: *const c_char,
+) -> i32 {
+    ffi_call(-1, || {
+        let store = handle(store, "store")?;
+        let id = to_str(id, "id")?;
+        let document = to_str(document, "document")?;
+        let metadata = match to_opt_str(metadata, "metadata")? {
+            Some(metadata) => serde_json::from_str(metadata)
+                .map_err(|e| format!("metadata is not valid JSON: {e}"))?,
+            None => Value::Null,
+        };
+
+        block_on(async {
+            let embedding = store.model.embed_text(document).await?;
+            store
+                .index
+                .0
+                .write()
+                .await
+                .store
+                .add_documents_with_metadata([(
+                    id,
+                    Value::String(document.to_string()),
+                    metadata,
+                    OneOrMany::one(embedding),
+                )]);
+            Ok::<_, VectorStoreError>(())
+        })
+        .map_err(|e| e.to_string())?;
+        Ok(0)
+    })
+}
+
+/// Search the `n` documents of a vector store most similar to `query`, returned as a JSON array
+/// of `{"score": ..., "id": ..., "document": ...}` objects to be freed with `rig_string_free`.
+/// Returns `NULL` on error.
+///
+/// # Safety
+/// `store` must be a live vector store and `query` a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rig_vector_store_search(
+    store: *const RigVectorStore,
+    query: *const c_char,
+    n: usize,
+) -> *mut c_char {
+    ffi_call(std::ptr::null_mut(), || {
+        let store = handle(store, "store")?;
+        let query = to_str(query, "query")?;
+        let results = block_on(store.index.top_n::<Value>(query, n)).map_err(|e| e.to_string())?;
+
+        let results = results
+            .into_iter()
+            .map(|(score, id, document)| json!({"score": score, "id": id, "document": document}))
+            .collect::<Vec<_>>();
+        to_c_string(Value::Array(results).to_string())
+    })
+}
+
+/// Free a vector store. Agents using the store keep their own reference to its documents. Does
+/// nothing if `store` is `NULL`.
+///
+/// # Safety
+/// `store` must be `NULL` or a live vector store, which must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rig_vector_store_free(store: *mut RigVectorStore) {
+    free_handle(store)
+}
@@ -0,0 +1,48 @@
+use rig::{
+    embeddings::EmbeddingsBuilder,
+    providers::openai::{self, GPT_4O, TEXT_EMBEDDING_3_SMALL},
+    vector_store::in_memory_store::InMemoryVectorStore,
+};
+use rig_grpc::RigService;
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    // Create OpenAI client and embedding model
+    let client = openai::Client::from_env();
+    let model = client.embedding_model(TEXT_EMBEDDING_3_SMALL);
+
+    // Embed a few documents in an in-memory vector store
+    let embeddings = EmbeddingsBuilder::new(model.clone())
+        .documents([
+            "Definition of a *flurbo*: A flurbo is a green alien that lives on cold planets",
+            "Definition of a *glarb-glarb*: A glarb-glarb is an ancient tool used to farm the land",
+        ])?
+        .build()
+        .await?;
+    let store = InMemoryVectorStore::from_documents(
+        embeddings
+            .into_iter()
+            .map(|(doc, embedding)| (doc.to_string(), embedding)),
+    );
+
+    // Create a RAG agent using a second index of the store
+    let agent = client
+        .agent(GPT_4O)
+        .preamble("You are a dictionary assistant.")
+        .dynamic_context(1, store.clone().index(model.clone()))
+        .build();
+
+    // Serve the model, the index and the agent, e.g.:
+    // grpcurl -plaintext -import-path rig-grpc/proto -proto rig.proto \
+    //   -d '{"agent": "dictionary", "prompt": "What is a flurbo?"}' localhost:50051 rig.v1.Rig/Prompt
+    println!("Serving on localhost:50051");
+    RigService::builder()
+        .embedding_model("small", model.clone())
+        .index("definitions", store.index(model))
+        .agent("dictionary", agent)
+        .build()
+        .serve("0.0.0.0:50051".parse()?)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! gRPC service serving the embedding models, vector store indexes and agents of a Rig
+//! application, so that it can be deployed as a microservice used from any language with
+//! clients generated from `proto/rig.proto`.
+//!
+//! Models, indexes and agents are registered under a name, which requests use to select them.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rig::providers::openai;
+//! use rig_grpc::RigService;
+//!
+//! let client = openai::Client::from_env();
+//! let model = client.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//! let index = vector_store.index(model.clone());
+//!
+//! RigService::builder()
+//!     .embedding_model("small", model)
+//!     .index("docs", index)
+//!     .agent("assistant", client.agent(openai::GPT_4O).build())
+//!     .build()
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! ```
+//!
+//! The generated client ([proto::rig_client::RigClient]) can be used to call the service from
+//! Rust.
+mod service;
+
+pub use service::{RigService, RigServiceBuilder};
+
+/// Messages, server and client generated from `proto/rig.proto`
+pub mod proto {
+    tonic::include_proto!("rig.v1");
+}
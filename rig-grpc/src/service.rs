@@ -0,0 +1,378 @@
+// `tonic::Status` is large, but it is the error type of the generated service trait
+#![allow(clippy::result_large_err)]
+
+use std::{collections::HashMap, net::SocketAddr};
+
+use futures::future::BoxFuture;
+use rig::{
+    completion::{Chat, Message, PromptError},
+    embeddings::{Embedding, EmbeddingError, EmbeddingModel},
+    vector_store::{Filter, SearchOptions, VectorStoreIndexDyn},
+};
+use tonic::{Request, Response, Status};
+
+use crate::proto::{
+    rig_server::{Rig, RigServer},
+    ChatMessage, EmbedRequest, EmbedResponse, PromptRequest, PromptResponse, Role, SearchRequest,
+    SearchResponse, SearchResult,
+};
+
+/// Wrapper trait to allow for dynamic dispatch of embedding models
+trait EmbeddingModelDyn: Send + Sync {
+    fn embed_texts(
+        &self,
+        texts: Vec<String>,
+    ) -> BoxFuture<'_, Result<Vec<Embedding>, EmbeddingError>>;
+}
+
+impl<M: EmbeddingModel> EmbeddingModelDyn for M {
+    fn embed_texts(
+        &self,
+        texts: Vec<String>,
+    ) -> BoxFuture<'_, Result<Vec<Embedding>, EmbeddingError>> {
+        Box::pin(async move {
+            let mut embeddings = Vec::with_capacity(texts.len());
+            for batch in texts.chunks(M::MAX_DOCUMENTS.max(1)) {
+                embeddings.extend(EmbeddingModel::embed_texts(self, batch.to_vec()).await?);
+            }
+            Ok(embeddings)
+        })
+    }
+}
+
+/// Wrapper trait to allow for dynamic dispatch of agents
+trait ChatDyn: Send + Sync {
+    fn chat(
+        &self,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> BoxFuture<'_, Result<String, PromptError>>;
+}
+
+impl<A: Chat> ChatDyn for A {
+    fn chat(
+        &self,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> BoxFuture<'_, Result<String, PromptError>> {
+        Box::pin(Chat::chat(self, prompt, history))
+    }
+}
+
+/// gRPC service (see `proto/rig.proto`) backed by named embedding models, vector store indexes
+/// and agents. Created with [RigService::builder].
+pub struct RigService {
+    embedding_models: HashMap<String, Box<dyn EmbeddingModelDyn>>,
+    indexes: HashMap<String, Box<dyn VectorStoreIndexDyn>>,
+    agents: HashMap<String, Box<dyn ChatDyn>>,
+}
+
+impl RigService {
+    pub fn builder() -> RigServiceBuilder {
+        RigServiceBuilder::default()
+    }
+
+    /// Wrap the service in a tonic server, to add it to a [tonic::transport::Server] (e.g.: next
+    /// to other services).
+    pub fn into_server(self) -> RigServer<Self> {
+        RigServer::new(self)
+    }
+
+    /// Serve the service at `addr` until the server fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(self.into_server())
+            .serve(addr)
+            .await
+    }
+}
+
+fn lookup<'a, T: ?Sized>(
+    items: &'a HashMap<String, Box<T>>,
+    kind: &str,
+    name: &str,
+) -> Result<&'a T, Status> {
+    items
+        .get(name)
+        .map(|item| &**item)
+        .ok_or_else(|| Status::not_found(format!("No {kind} named {name:?}")))
+}
+
+#[tonic::async_trait]
+impl Rig for RigService {
+    async fn embed(
+        &self,
+        request: Request<EmbedRequest>,
+    ) -> Result<Response<EmbedResponse>, Status> {
+        let EmbedRequest { model, texts } = request.into_inner();
+        let model = lookup(&self.embedding_models, "embedding model", &model)?;
+
+        let embeddings = model
+            .embed_texts(texts)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(EmbedResponse {
+            embeddings: embeddings
+                .into_iter()
+                .map(|embedding| crate::proto::Embedding { vec: embedding.vec })
+                .collect(),
+        }))
+    }
+
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<SearchResponse>, Status> {
+        let SearchRequest {
+            index,
+            query,
+            n,
+            filter,
+            min_score,
+        } = request.into_inner();
+        let index = lookup(&self.indexes, "index", &index)?;
+
+        let mut options = SearchOptions::new();
+        if let Some(filter) = filter {
+            let filter = serde_json::from_str::<Filter>(&filter)
+                .map_err(|e| Status::invalid_argument(format!("Invalid filter: {e}")))?;
+            options = options.filter(filter);
+        }
+        if let Some(min_score) = min_score {
+            options = options.min_score(min_score);
+        }
+
+        let results = index
+            .top_n_with_options(&query, n as usize, &options)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(SearchResponse {
+            results: results
+                .into_iter()
+                .map(|(score, id, document)| SearchResult {
+                    score,
+                    id,
+                    document: document.to_string(),
+                })
+                .collect(),
+        }))
+    }
+
+    async fn prompt(
+        &self,
+        request: Request<PromptRequest>,
+    ) -> Result<Response<PromptResponse>, Status> {
+        let PromptRequest {
+            agent,
+            prompt,
+            history,
+        } = request.into_inner();
+        let agent = lookup(&self.agents, "agent", &agent)?;
+
+        let history = history
+            .into_iter()
+            .map(|ChatMessage { role, content }| match Role::try_from(role) {
+                Ok(Role::User) => Ok(Message::user(content)),
+                Ok(Role::Assistant) => Ok(Message::assistant(content)),
+                _ => Err(Status::invalid_argument(format!("Invalid role: {role}"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let response = agent
+            .chat(prompt, history)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(PromptResponse { response }))
+    }
+}
+
+/// Builder for [RigService]
+#[derive(Default)]
+pub struct RigServiceBuilder {
+    embedding_models: HashMap<String, Box<dyn EmbeddingModelDyn>>,
+    indexes: HashMap<String, Box<dyn VectorStoreIndexDyn>>,
+    agents: HashMap<String, Box<dyn ChatDyn>>,
+}
+
+impl RigServiceBuilder {
+    /// Serve an embedding model under `name`.
+    pub fn embedding_model(
+        mut self,
+        name: impl Into<String>,
+        model: impl EmbeddingModel + 'static,
+    ) -> Self {
+        self.embedding_models.insert(name.into(), Box::new(model));
+        self
+    }
+
+    /// Serve a vector store index under `name`.
+    pub fn index(
+        mut self,
+        name: impl Into<String>,
+        index: impl VectorStoreIndexDyn + 'static,
+    ) -> Self {
+        self.indexes.insert(name.into(), Box::new(index));
+        self
+    }
+
+    /// Serve an agent (or any other [Chat] implementation) under `name`.
+    pub fn agent(mut self, name: impl Into<String>, agent: impl Chat + 'static) -> Self {
+        self.agents.insert(name.into(), Box::new(agent));
+        self
+    }
+
+    pub fn build(self) -> RigService {
+        RigService {
+            embedding_models: self.embedding_models,
+            indexes: self.indexes,
+            agents: self.agents,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rig::{
+        agent::AgentBuilder,
+        completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse},
+        message::{AssistantContent, UserContent},
+        providers::mock::MockEmbeddingModel,
+        vector_store::in_memory_store::InMemoryVectorStore,
+        OneOrMany,
+    };
+    use serde_json::json;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{transport::Server, Code};
+
+    use super::*;
+    use crate::proto::rig_client::RigClient;
+
+    /// Model answering with the number of messages of the chat history and the prompt
+    #[derive(Clone)]
+    struct EchoModel;
+
+    impl CompletionModel for EchoModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            let prompt = match request.prompt {
+                Message::User { content } => match content.first() {
+                    UserContent::Text(text) => text.text,
+                    _ => unreachable!("Prompts are texts"),
+                },
+                _ => unreachable!("Prompts are user messages"),
+            };
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::text(format!(
+                    "{} messages, last: {prompt:?}",
+                    request.chat_history.len(),
+                ))),
+                usage: None,
+                raw_response: (),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_service() -> anyhow::Result<()> {
+        let store = InMemoryVectorStore::from_documents_with_ids(
+            [("cats", "All about cats"), ("dogs", "All about dogs")]
+                .into_iter()
+                .map(|(id, doc)| {
+                    let embedding = Embedding {
+                        document: doc.to_string(),
+                        vec: if id == "cats" {
+                            vec![1.0, 0.0]
+                        } else {
+                            vec![0.0, 1.0]
+                        },
+                    };
+                    (id, doc.to_string(), OneOrMany::one(embedding))
+                }),
+        );
+        let model = MockEmbeddingModel::keywords(&["cat", "dog"]);
+        let service = RigService::builder()
+            .embedding_model("keywords", model.clone())
+            .index("docs", store.index(model))
+            .agent("echo", AgentBuilder::new(EchoModel).build())
+            .build();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(
+            Server::builder()
+                .add_service(service.into_server())
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let mut client = RigClient::connect(format!("http://{addr}")).await?;
+
+        let embeddings = client
+            .embed(EmbedRequest {
+                model: "keywords".into(),
+                texts: vec!["a cat".into(), "a dog".into()],
+            })
+            .await?
+            .into_inner()
+            .embeddings;
+        assert_eq!(
+            embeddings
+                .into_iter()
+                .map(|embedding| embedding.vec)
+                .collect::<Vec<_>>(),
+            vec![vec![1.0, 0.01], vec![0.01, 1.0]]
+        );
+
+        let results = client
+            .search(SearchRequest {
+                index: "docs".into(),
+                query: "my dog".into(),
+                n: 1,
+                filter: None,
+                min_score: None,
+            })
+            .await?
+            .into_inner()
+            .results;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "dogs");
+        assert_eq!(results[0].document, json!("All about dogs").to_string());
+
+        let response = client
+            .prompt(PromptRequest {
+                agent: "echo".into(),
+                prompt: "How are you?".into(),
+                history: vec![ChatMessage {
+                    role: Role::User.into(),
+                    content: "Hi".into(),
+                }],
+            })
+            .await?
+            .into_inner()
+            .response;
+        assert_eq!(response, r#"1 messages, last: "How are you?""#);
+
+        let error = client
+            .prompt(PromptRequest {
+                agent: "unknown".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Code::NotFound);
+
+        let error = client
+            .search(SearchRequest {
+                index: "docs".into(),
+                filter: Some("{".into()),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+
+        Ok(())
+    }
+}
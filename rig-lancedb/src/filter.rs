@@ -0,0 +1,106 @@
+//! Translation of the metadata filters of rig to the SQL filters of LanceDB.
+use rig::vector_store::{Filter, VectorStoreError};
+use serde_json::Value;
+
+/// Translate a metadata filter to a SQL `WHERE` clause on the columns of a table. Nested fields
+/// (e.g.: `source.name`) refer to the fields of struct columns.
+pub(crate) fn to_sql(filter: &Filter) -> Result<String, VectorStoreError> {
+    let comparison = |field: &str, operator: &str, value: &Value| {
+        Ok(format!("{} {operator} {}", column(field), literal(value)?))
+    };
+
+    match filter {
+        Filter::Eq(field, Value::Null) => Ok(format!("{} IS NULL", column(field))),
+        Filter::Eq(field, value) => comparison(field, "=", value),
+        Filter::Gt(field, value) => comparison(field, ">", value),
+        Filter::Gte(field, value) => comparison(field, ">=", value),
+        Filter::Lt(field, value) => comparison(field, "<", value),
+        Filter::Lte(field, value) => comparison(field, "<=", value),
+        Filter::In(_, values) if values.is_empty() => Ok("FALSE".to_string()),
+        Filter::In(field, values) => Ok(format!(
+            "{} IN ({})",
+            column(field),
+            values
+                .iter()
+                .map(literal)
+                .collect::<Result<Vec<_>, _>>()?
+                .join(", ")
+        )),
+        Filter::Contains(field, value) => {
+            Ok(format!("array_has({}, {})", column(field), literal(value)?))
+        }
+        Filter::And(filters) => combine(filters, "AND", "TRUE"),
+        Filter::Or(filters) => combine(filters, "OR", "FALSE"),
+    }
+}
+
+fn combine(filters: &[Filter], operator: &str, empty: &str) -> Result<String, VectorStoreError> {
+    if filters.is_empty() {
+        return Ok(empty.to_string());
+    }
+    Ok(filters
+        .iter()
+        .map(|filter| Ok(format!("({})", to_sql(filter)?)))
+        .collect::<Result<Vec<_>, VectorStoreError>>()?
+        .join(&format!(" {operator} ")))
+}
+
+/// Column name, with each segment quoted with backticks unless it is a plain identifier
+fn column(field: &str) -> String {
+    field
+        .split('.')
+        .map(|segment| {
+            let plain = segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !segment.starts_with(|c: char| c.is_ascii_digit());
+            if plain {
+                segment.to_string()
+            } else {
+                format!("`{}`", segment.replace('`', "``"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn literal(value: &Value) -> Result<String, VectorStoreError> {
+    match value {
+        Value::String(value) => Ok(format!("'{}'", value.replace('\'', "''"))),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(value.to_string().to_uppercase()),
+        _ => Err(VectorStoreError::DatastoreError(
+            format!("Unsupported filter value: {value}").into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_to_sql() {
+        let filter = Filter::eq("tenant", "o'brien")
+            .and(Filter::is_in("source", vec![json!("wiki"), json!(3)]))
+            .and(Filter::gte("meta.date", "2024-01-01").or(Filter::eq("draft", true)));
+        assert_eq!(
+            to_sql(&filter).unwrap(),
+            "(tenant = 'o''brien') AND (source IN ('wiki', 3)) AND \
+            ((meta.date >= '2024-01-01') OR (draft = TRUE))"
+        );
+
+        assert_eq!(
+            to_sql(&Filter::eq("my field", json!(null))).unwrap(),
+            "`my field` IS NULL"
+        );
+        assert_eq!(to_sql(&Filter::Or(vec![])).unwrap(), "FALSE");
+        assert!(to_sql(&Filter::eq("tags", json!(["a"]))).is_err());
+        assert_eq!(
+            to_sql(&Filter::contains("acl.users", "alice")).unwrap(),
+            "array_has(acl.users, 'alice')"
+        );
+    }
+}
@@ -4,12 +4,13 @@ use lancedb::{
 };
 use rig::{
     embeddings::embedding::EmbeddingModel,
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    vector_store::{distance_score, Filter, VectorStoreError, VectorStoreIndex},
 };
 use serde::Deserialize;
 use serde_json::Value;
 use utils::{FilterTableColumns, QueryToJson};
 
+mod filter;
 mod utils;
 
 fn lancedb_to_rig_error(e: lancedb::Error) -> VectorStoreError {
@@ -61,6 +62,25 @@ impl<M: EmbeddingModel> LanceDbVectorIndex<M> {
         })
     }
 
+    /// Open the table `table_name` of the LanceDB database at `uri`, e.g.: a local directory
+    /// (`data/lancedb-store`) or an object store (`s3://bucket/path`). The table is
+    /// stored in the columnar Lance format, so no database server is needed.
+    pub async fn open(
+        uri: &str,
+        table_name: &str,
+        model: M,
+        id_field: &str,
+        search_params: SearchParams,
+    ) -> Result<Self, lancedb::Error> {
+        let table = lancedb::connect(uri)
+            .execute()
+            .await?
+            .open_table(table_name)
+            .execute()
+            .await?;
+        Self::new(table, model, id_field, search_params).await
+    }
+
     /// Apply the search_params to the vector query.
     /// This is a helper function used by the methods `top_n` and `top_n_ids` of the `VectorStoreIndex` trait.
     fn build_query(&self, mut query: VectorQuery) -> VectorQuery {
@@ -127,6 +147,16 @@ pub struct SearchParams {
 }
 
 impl SearchParams {
+    /// Similarity score of a `distance` returned by LanceDB, the higher the better (see
+    /// [VectorStoreIndex](rig::vector_store::VectorStoreIndex#scores)): `1 - distance` for the
+    /// cosine and dot distances, and [distance_score] of the L2 and hamming distances.
+    fn similarity(&self, distance: f64) -> f64 {
+        match self.distance_type {
+            Some(DistanceType::Cosine | DistanceType::Dot) => 1.0 - distance,
+            _ => distance_score(distance),
+        }
+    }
+
     /// Sets the distance type of the search params.
     /// Always set the distance_type to match the value used to train the index.
     /// The default is DistanceType::L2.
@@ -198,6 +228,66 @@ impl<M: EmbeddingModel + Sync + Send> VectorStoreIndex for LanceDbVectorIndex<M>
         &self,
         query: &str,
         n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search(query, n, None).await
+    }
+
+    /// Implement the `top_n_ids` method of the `VectorStoreIndex` trait for `LanceDbVectorIndex`.
+    /// # Example
+    /// ```
+    /// use rig_lancedb::{LanceDbVectorIndex, SearchParams};
+    /// use rig::providers::openai::{Client, TEXT_EMBEDDING_ADA_002, EmbeddingModel};
+    ///
+    /// let openai_client = Client::from_env();
+    ///
+    /// let table: lancedb::Table = db.create_table(""); // <-- Replace with your lancedb table here.
+    /// let model: EmbeddingModel = openai_client.embedding_model(TEXT_EMBEDDING_ADA_002); // <-- Replace with your embedding model here.
+    /// let vector_store_index = LanceDbVectorIndex::new(table, model, "id", SearchParams::default()).await?;
+    ///
+    /// // Query the index
+    /// let result = vector_store_index
+    ///     .top_n_ids("My boss says I zindle too much, what does that mean?", 1)
+    ///     .await?;
+    /// ```
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.search_ids(query, n, None).await
+    }
+
+    /// The filter is translated to a SQL `WHERE` clause on the columns of the table (e.g.:
+    /// `Filter::eq("category", "noun")` to `category = 'noun'`), applied before the vector
+    /// search unless [SearchParams::post_filter] is set.
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search(query, n, Some(filter::to_sql(filter)?)).await
+    }
+
+    /// Same as `top_n_with_filter` but returns the document ids only.
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.search_ids(query, n, Some(filter::to_sql(filter)?))
+            .await
+    }
+}
+
+impl<M: EmbeddingModel + Sync + Send> LanceDbVectorIndex<M> {
+    /// Vector search of `query`, returning the documents matching the SQL `filter`.
+    async fn search<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: Option<String>,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
         let prompt_embedding = self.model.embed_text(query).await?;
 
@@ -213,6 +303,10 @@ impl<M: EmbeddingModel + Sync + Send> VectorStoreIndex for LanceDbVectorIndex<M>
                     .map_err(lancedb_to_rig_error)?
                     .filter_embeddings(),
             ));
+        let query = match filter {
+            Some(filter) => query.only_if(filter),
+            None => query,
+        };
 
         self.build_query(query)
             .execute_query()
@@ -222,7 +316,9 @@ impl<M: EmbeddingModel + Sync + Send> VectorStoreIndex for LanceDbVectorIndex<M>
             .map(|(i, value)| {
                 Ok((
                     match value.get("_distance") {
-                        Some(Value::Number(distance)) => distance.as_f64().unwrap_or_default(),
+                        Some(Value::Number(distance)) => self
+                            .search_params
+                            .similarity(distance.as_f64().unwrap_or_default()),
                         _ => 0.0,
                     },
                     match value.get(self.id_field.clone()) {
@@ -235,27 +331,12 @@ impl<M: EmbeddingModel + Sync + Send> VectorStoreIndex for LanceDbVectorIndex<M>
             .collect()
     }
 
-    /// Implement the `top_n_ids` method of the `VectorStoreIndex` trait for `LanceDbVectorIndex`.
-    /// # Example
-    /// ```
-    /// use rig_lancedb::{LanceDbVectorIndex, SearchParams};
-    /// use rig::providers::openai::{Client, TEXT_EMBEDDING_ADA_002, EmbeddingModel};
-    ///
-    /// let openai_client = Client::from_env();
-    ///
-    /// let table: lancedb::Table = db.create_table(""); // <-- Replace with your lancedb table here.
-    /// let model: EmbeddingModel = openai_client.embedding_model(TEXT_EMBEDDING_ADA_002); // <-- Replace with your embedding model here.
-    /// let vector_store_index = LanceDbVectorIndex::new(table, model, "id", SearchParams::default()).await?;
-    ///
-    /// // Query the index
-    /// let result = vector_store_index
-    ///     .top_n_ids("My boss says I zindle too much, what does that mean?", 1)
-    ///     .await?;
-    /// ```
-    async fn top_n_ids(
+    /// Vector search of `query`, returning the ids of the documents matching the SQL `filter`.
+    async fn search_ids(
         &self,
         query: &str,
         n: usize,
+        filter: Option<String>,
     ) -> Result<Vec<(f64, String)>, VectorStoreError> {
         let prompt_embedding = self.model.embed_text(query).await?;
 
@@ -266,6 +347,10 @@ impl<M: EmbeddingModel + Sync + Send> VectorStoreIndex for LanceDbVectorIndex<M>
             .nearest_to(prompt_embedding.vec.clone())
             .map_err(lancedb_to_rig_error)?
             .limit(n);
+        let query = match filter {
+            Some(filter) => query.only_if(filter),
+            None => query,
+        };
 
         self.build_query(query)
             .execute_query()
@@ -274,7 +359,9 @@ impl<M: EmbeddingModel + Sync + Send> VectorStoreIndex for LanceDbVectorIndex<M>
             .map(|value| {
                 Ok((
                     match value.get("distance") {
-                        Some(Value::Number(distance)) => distance.as_f64().unwrap_or_default(),
+                        Some(Value::Number(distance)) => self
+                            .search_params
+                            .similarity(distance.as_f64().unwrap_or_default()),
                         _ => 0.0,
                     },
                     match value.get(self.id_field.clone()) {
@@ -6,7 +6,7 @@ use lancedb::index::vector::IvfPqIndexBuilder;
 use rig::{
     embeddings::{EmbeddingModel, EmbeddingsBuilder},
     providers::openai,
-    vector_store::VectorStoreIndex,
+    vector_store::{distance_score, VectorStoreIndex},
 };
 use rig_lancedb::{LanceDbVectorIndex, SearchParams};
 use std::sync::Arc;
@@ -161,7 +161,9 @@ async fn vector_search_test() {
         .await
         .unwrap();
 
-    let (distance, _, value) = &results.first().unwrap();
+    let (score, _, value) = &results.first().unwrap();
+    let distance = value["_distance"].as_f64().unwrap();
+    assert_eq!(*score, distance_score(distance));
 
     assert_eq!(
         *value,
@@ -1,9 +1,16 @@
+use std::time::{Duration, Instant};
+
 use futures::StreamExt;
-use mongodb::bson::{self, doc};
+use mongodb::{
+    bson::{self, doc},
+    SearchIndexModel, SearchIndexType,
+};
 
 use rig::{
     embeddings::embedding::{Embedding, EmbeddingModel},
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    runtime,
+    vector_store::{Filter, InsertDocuments, VectorStoreError, VectorStoreIndex},
+    Embed, OneOrMany,
 };
 use serde::{Deserialize, Serialize};
 
@@ -20,10 +27,10 @@ struct SearchIndex {
 }
 
 impl SearchIndex {
-    async fn get_search_index<C: Send + Sync>(
+    async fn find_search_index<C: Send + Sync>(
         collection: mongodb::Collection<C>,
         index_name: &str,
-    ) -> Result<SearchIndex, VectorStoreError> {
+    ) -> Result<Option<SearchIndex>, VectorStoreError> {
         collection
             .list_search_indexes()
             .name(index_name)
@@ -33,7 +40,15 @@ impl SearchIndex {
             .next()
             .await
             .transpose()
-            .map_err(mongodb_to_rig_error)?
+            .map_err(mongodb_to_rig_error)
+    }
+
+    async fn get_search_index<C: Send + Sync>(
+        collection: mongodb::Collection<C>,
+        index_name: &str,
+    ) -> Result<SearchIndex, VectorStoreError> {
+        Self::find_search_index(collection, index_name)
+            .await?
             .ok_or(VectorStoreError::DatastoreError("Index not found".into()))
     }
 }
@@ -49,14 +64,182 @@ struct Field {
     #[serde(rename = "type")]
     field_type: String,
     path: String,
-    num_dimensions: i32,
-    similarity: String,
+    // Only set for `vector` fields (not for `filter` fields)
+    num_dimensions: Option<i32>,
+    similarity: Option<String>,
 }
 
 fn mongodb_to_rig_error(e: mongodb::error::Error) -> VectorStoreError {
     VectorStoreError::DatastoreError(Box::new(e))
 }
 
+/// Similarity function of a vector search index.
+/// See [MongoDB Vector Search](https://www.mongodb.com/docs/atlas/atlas-vector-search/vector-search-type/#about-the-similarity-functions)
+/// for more information.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Similarity {
+    #[default]
+    Cosine,
+    Euclidean,
+    DotProduct,
+}
+
+impl Similarity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Similarity::Cosine => "cosine",
+            Similarity::Euclidean => "euclidean",
+            Similarity::DotProduct => "dotProduct",
+        }
+    }
+}
+
+/// Definition of an Atlas Vector Search index, to create with [create_vector_index].
+///
+/// The fields used in the metadata filters of the searches (see
+/// [VectorStoreIndex::top_n_with_filter]) must be indexed as filter fields.
+#[derive(Debug, Clone)]
+pub struct VectorIndexDefinition {
+    path: String,
+    num_dimensions: usize,
+    similarity: Similarity,
+    filter_fields: Vec<String>,
+}
+
+impl VectorIndexDefinition {
+    /// Index the embeddings of `num_dimensions` dimensions (i.e.: the `ndims` of the embedding
+    /// model) stored in the field `path` of the documents, compared with the cosine similarity.
+    pub fn new(path: &str, num_dimensions: usize) -> Self {
+        Self {
+            path: path.to_string(),
+            num_dimensions,
+            similarity: Similarity::default(),
+            filter_fields: vec![],
+        }
+    }
+
+    /// Sets the similarity function of the index.
+    pub fn similarity(mut self, similarity: Similarity) -> Self {
+        self.similarity = similarity;
+        self
+    }
+
+    /// Indexes the field `path` of the documents for metadata filtering.
+    pub fn filter_field(mut self, path: &str) -> Self {
+        self.filter_fields.push(path.to_string());
+        self
+    }
+
+    fn to_document(&self) -> bson::Document {
+        let mut fields = vec![doc! {
+            "type": "vector",
+            "path": &self.path,
+            "numDimensions": self.num_dimensions as i32,
+            "similarity": self.similarity.as_str(),
+        }];
+        fields.extend(
+            self.filter_fields
+                .iter()
+                .map(|path| doc! { "type": "filter", "path": path }),
+        );
+        doc! { "fields": fields }
+    }
+}
+
+/// Create the vector search index called `name` on `collection`. The index is built
+/// asynchronously by MongoDB: see [wait_for_vector_index] to wait until it can be queried.
+pub async fn create_vector_index<C: Send + Sync>(
+    collection: &mongodb::Collection<C>,
+    name: &str,
+    definition: &VectorIndexDefinition,
+) -> Result<(), VectorStoreError> {
+    collection
+        .create_search_index(
+            SearchIndexModel::builder()
+                .name(Some(name.to_string()))
+                .index_type(Some(SearchIndexType::VectorSearch))
+                .definition(definition.to_document())
+                .build(),
+        )
+        .await
+        .map_err(mongodb_to_rig_error)?;
+    Ok(())
+}
+
+/// Wait until the search index called `name` of `collection` is queryable, polling its status
+/// every second, for at most `timeout`.
+pub async fn wait_for_vector_index<C: Send + Sync>(
+    collection: &mongodb::Collection<C>,
+    name: &str,
+    timeout: Duration,
+) -> Result<(), VectorStoreError> {
+    let start = Instant::now();
+    loop {
+        match SearchIndex::find_search_index(collection.clone(), name).await? {
+            Some(index) if index.queryable => return Ok(()),
+            Some(index) => tracing::debug!(target: "rig",
+                "Waiting for index {} (status: {})", name, index.status
+            ),
+            // The index is not listed until its creation was processed
+            None => {}
+        }
+
+        if start.elapsed() >= timeout {
+            return Err(VectorStoreError::DatastoreError(
+                format!("Index {name} is not queryable after {timeout:?}").into(),
+            ));
+        }
+        runtime::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Translate a metadata filter to a MongoDB query filter, or `None` if it matches nothing.
+fn filter_to_document(filter: &Filter) -> Result<Option<bson::Document>, VectorStoreError> {
+    let to_bson = |value: &serde_json::Value| {
+        bson::to_bson(value).map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))
+    };
+    let comparison = |field: &str, operator: &str, value: &serde_json::Value| {
+        Ok::<_, VectorStoreError>(Some(doc! { field: { operator: to_bson(value)? } }))
+    };
+
+    match filter {
+        Filter::Eq(field, value) => comparison(field, "$eq", value),
+        Filter::Gt(field, value) => comparison(field, "$gt", value),
+        Filter::Gte(field, value) => comparison(field, "$gte", value),
+        Filter::Lt(field, value) => comparison(field, "$lt", value),
+        Filter::Lte(field, value) => comparison(field, "$lte", value),
+        Filter::In(_, values) if values.is_empty() => Ok(None),
+        Filter::In(field, values) => comparison(field, "$in", &values.clone().into()),
+        // Equality with an array field matches the arrays containing the value
+        Filter::Contains(field, value) => comparison(field, "$eq", value),
+        Filter::And(filters) => {
+            let mut documents = vec![];
+            for filter in filters {
+                match filter_to_document(filter)? {
+                    Some(document) => documents.push(document),
+                    None => return Ok(None),
+                }
+            }
+            Ok(Some(match documents.len() {
+                0 => doc! {},
+                1 => documents.remove(0),
+                _ => doc! { "$and": documents },
+            }))
+        }
+        Filter::Or(filters) => {
+            let mut documents = vec![];
+            for filter in filters {
+                documents.extend(filter_to_document(filter)?);
+            }
+            Ok(match documents.len() {
+                0 => None,
+                1 => Some(documents.remove(0)),
+                _ => Some(doc! { "$or": documents }),
+            })
+        }
+    }
+}
+
 /// A vector index for a MongoDB collection.
 /// # Example
 /// ```rust
@@ -104,12 +287,25 @@ pub struct MongoDbVectorIndex<M: EmbeddingModel, C: Send + Sync> {
 impl<M: EmbeddingModel, C: Send + Sync> MongoDbVectorIndex<M, C> {
     /// Vector search stage of aggregation pipeline of mongoDB collection.
     /// To be used by implementations of top_n and top_n_ids methods on VectorStoreIndex trait for MongoDbVectorIndex.
-    fn pipeline_search_stage(&self, prompt_embedding: &Embedding, n: usize) -> bson::Document {
+    /// `filter` is combined with the filter of the search params.
+    fn pipeline_search_stage(
+        &self,
+        prompt_embedding: &Embedding,
+        n: usize,
+        filter: Option<bson::Document>,
+    ) -> bson::Document {
         let SearchParams {
-            filter,
+            filter: params_filter,
             exact,
             num_candidates,
         } = &self.search_params;
+        let filter = match filter {
+            Some(filter) if !params_filter.is_empty() => {
+                doc! { "$and": [params_filter.clone(), filter] }
+            }
+            Some(filter) => filter,
+            None => params_filter.clone(),
+        };
 
         doc! {
           "$vectorSearch": {
@@ -158,8 +354,8 @@ impl<M: EmbeddingModel, C: Send + Sync> MongoDbVectorIndex<M, C> {
             .latest_definition
             .fields
             .into_iter()
+            .find(|field| field.field_type == "vector")
             .map(|field| field.path)
-            .next()
             // This error shouldn't occur if the index is queryable
             .ok_or(VectorStoreError::DatastoreError(
                 "No embedded fields found".into(),
@@ -173,6 +369,139 @@ impl<M: EmbeddingModel, C: Send + Sync> MongoDbVectorIndex<M, C> {
             search_params,
         })
     }
+
+    /// Insert documents and their embedding (stored in the embedded field of the index) in the
+    /// collection, e.g.: the output of an
+    /// [EmbeddingsBuilder](rig::embeddings::EmbeddingsBuilder).
+    ///
+    /// MongoDB indexes a single vector per document: documents with several embeddings are
+    /// rejected, and should be split into one document per embedded chunk instead.
+    pub async fn insert_documents<Doc: Serialize + Embed + Send>(
+        &self,
+        documents: Vec<(Doc, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        let documents = documents
+            .into_iter()
+            .map(|(document, embeddings)| {
+                if embeddings.len() > 1 {
+                    return Err(VectorStoreError::DatastoreError(
+                        "Documents with several embeddings are not supported".into(),
+                    ));
+                }
+                let mut document = bson::to_document(&document)
+                    .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+                document.insert(self.embedded_field.clone(), embeddings.first().vec);
+                Ok(document)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.collection
+            .clone_with_type::<bson::Document>()
+            .insert_many(documents)
+            .await
+            .map_err(mongodb_to_rig_error)?;
+        Ok(())
+    }
+
+    /// Run the vector search of `query`, returning the matching documents without their
+    /// embeddings.
+    async fn search(
+        &self,
+        query: &str,
+        n: usize,
+        filter: Option<bson::Document>,
+    ) -> Result<Vec<serde_json::Value>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+
+        let mut cursor = self
+            .collection
+            .aggregate([
+                self.pipeline_search_stage(&prompt_embedding, n, filter),
+                self.pipeline_score_stage(),
+                doc! {
+                    "$project": {
+                        self.embedded_field.clone(): 0,
+                    },
+                },
+            ])
+            .await
+            .map_err(mongodb_to_rig_error)?
+            .with_type::<serde_json::Value>();
+
+        let mut results = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            results.push(doc.map_err(mongodb_to_rig_error)?);
+        }
+        Ok(results)
+    }
+
+    /// Run the vector search of `query`, returning the scores and ids of the matching documents.
+    async fn search_ids(
+        &self,
+        query: &str,
+        n: usize,
+        filter: Option<bson::Document>,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let prompt_embedding = self.model.embed_text(query).await?;
+
+        let mut cursor = self
+            .collection
+            .aggregate([
+                self.pipeline_search_stage(&prompt_embedding, n, filter),
+                self.pipeline_score_stage(),
+                doc! {
+                    "$project": {
+                        "_id": 1,
+                        "score": 1
+                    },
+                },
+            ])
+            .await
+            .map_err(mongodb_to_rig_error)?
+            .with_type::<serde_json::Value>();
+
+        let mut results = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(mongodb_to_rig_error)?;
+            let score = doc.get("score").expect("score").as_f64().expect("f64");
+            let id = doc.get("_id").expect("_id").to_string();
+            results.push((score, id));
+        }
+
+        tracing::info!(target: "rig",
+            "Selected documents: {}",
+            results.iter()
+                .map(|(distance, id)| format!("{} ({})", id, distance))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+
+        Ok(results)
+    }
+}
+
+fn to_results<T: for<'a> Deserialize<'a>>(
+    documents: Vec<serde_json::Value>,
+) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+    let results = documents
+        .into_iter()
+        .map(|doc| {
+            let score = doc.get("score").expect("score").as_f64().expect("f64");
+            let id = doc.get("_id").expect("_id").to_string();
+            let doc_t: T = serde_json::from_value(doc).map_err(VectorStoreError::JsonError)?;
+            Ok((score, id, doc_t))
+        })
+        .collect::<Result<Vec<_>, VectorStoreError>>()?;
+
+    tracing::info!(target: "rig",
+        "Selected documents: {}",
+        results.iter()
+            .map(|(distance, id, _)| format!("{} ({})", id, distance))
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+
+    Ok(results)
 }
 
 /// See [MongoDB Vector Search](`https://www.mongodb.com/docs/atlas/atlas-vector-search/vector-search-stage/`) for more information
@@ -220,6 +549,17 @@ impl SearchParams {
     }
 }
 
+impl<M: EmbeddingModel, C: Send + Sync, Doc: Serialize + Embed + Send> InsertDocuments<Doc>
+    for MongoDbVectorIndex<M, C>
+{
+    async fn insert_documents(
+        &mut self,
+        documents: Vec<(Doc, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        MongoDbVectorIndex::insert_documents(self, documents).await
+    }
+}
+
 impl<M: EmbeddingModel + Sync + Send, C: Sync + Send> VectorStoreIndex
     for MongoDbVectorIndex<M, C>
 {
@@ -229,43 +569,7 @@ impl<M: EmbeddingModel + Sync + Send, C: Sync + Send> VectorStoreIndex
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let prompt_embedding = self.model.embed_text(query).await?;
-
-        let mut cursor = self
-            .collection
-            .aggregate([
-                self.pipeline_search_stage(&prompt_embedding, n),
-                self.pipeline_score_stage(),
-                {
-                    doc! {
-                        "$project": {
-                            self.embedded_field.clone(): 0,
-                        },
-                    }
-                },
-            ])
-            .await
-            .map_err(mongodb_to_rig_error)?
-            .with_type::<serde_json::Value>();
-
-        let mut results = Vec::new();
-        while let Some(doc) = cursor.next().await {
-            let doc = doc.map_err(mongodb_to_rig_error)?;
-            let score = doc.get("score").expect("score").as_f64().expect("f64");
-            let id = doc.get("_id").expect("_id").to_string();
-            let doc_t: T = serde_json::from_value(doc).map_err(VectorStoreError::JsonError)?;
-            results.push((score, id, doc_t));
-        }
-
-        tracing::info!(target: "rig",
-            "Selected documents: {}",
-            results.iter()
-                .map(|(distance, id, _)| format!("{} ({})", id, distance))
-                .collect::<Vec<String>>()
-                .join(", ")
-        );
-
-        Ok(results)
+        to_results(self.search(query, n, None).await?)
     }
 
     /// Implement the `top_n_ids` method of the `VectorStoreIndex` trait for `MongoDbVectorIndex`.
@@ -274,40 +578,34 @@ impl<M: EmbeddingModel + Sync + Send, C: Sync + Send> VectorStoreIndex
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String)>, VectorStoreError> {
-        let prompt_embedding = self.model.embed_text(query).await?;
-
-        let mut cursor = self
-            .collection
-            .aggregate([
-                self.pipeline_search_stage(&prompt_embedding, n),
-                self.pipeline_score_stage(),
-                doc! {
-                    "$project": {
-                        "_id": 1,
-                        "score": 1
-                    },
-                },
-            ])
-            .await
-            .map_err(mongodb_to_rig_error)?
-            .with_type::<serde_json::Value>();
+        self.search_ids(query, n, None).await
+    }
 
-        let mut results = Vec::new();
-        while let Some(doc) = cursor.next().await {
-            let doc = doc.map_err(mongodb_to_rig_error)?;
-            let score = doc.get("score").expect("score").as_f64().expect("f64");
-            let id = doc.get("_id").expect("_id").to_string();
-            results.push((score, id));
+    /// The fields of the filter must be indexed as filter fields of the vector index (see
+    /// [VectorIndexDefinition::filter_field]). The filter is combined with the one of the
+    /// search params.
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        match filter_to_document(filter)? {
+            Some(filter) => to_results(self.search(query, n, Some(filter)).await?),
+            None => Ok(vec![]),
         }
+    }
 
-        tracing::info!(target: "rig",
-            "Selected documents: {}",
-            results.iter()
-                .map(|(distance, id)| format!("{} ({})", id, distance))
-                .collect::<Vec<String>>()
-                .join(", ")
-        );
-
-        Ok(results)
+    /// Same as `top_n_with_filter` but returns the document ids only.
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        match filter_to_document(filter)? {
+            Some(filter) => self.search_ids(query, n, Some(filter)).await,
+            None => Ok(vec![]),
+        }
     }
 }
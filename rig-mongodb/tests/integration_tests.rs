@@ -1,13 +1,14 @@
-use futures::StreamExt;
-use mongodb::{
-    bson::{self, doc},
-    options::ClientOptions,
-    Collection, SearchIndexModel,
-};
+use mongodb::{bson, options::ClientOptions, Collection};
 use rig::{
-    embeddings::EmbeddingsBuilder, providers::openai, vector_store::VectorStoreIndex, Embed,
+    embeddings::EmbeddingsBuilder,
+    providers::openai,
+    vector_store::{Filter, VectorStoreIndex},
+    Embed,
+};
+use rig_mongodb::{
+    create_vector_index, wait_for_vector_index, MongoDbVectorIndex, SearchParams, Similarity,
+    VectorIndexDefinition,
 };
-use rig_mongodb::{MongoDbVectorIndex, SearchParams};
 use serde_json::json;
 use testcontainers::{
     core::{IntoContainerPort, WaitFor},
@@ -130,25 +131,24 @@ async fn vector_search_test() {
 
     let collection = bootstrap_collection(host, port).await;
 
-    let embeddings = create_embeddings(model.clone()).await;
-
-    collection.insert_many(embeddings).await.unwrap();
-
-    // Wait for the new documents to be indexed
-    sleep(Duration::from_secs(5)).await;
-
     // Create a vector index on our vector store.
     // Note: a vector index called "vector_index" must exist on the MongoDB collection you are querying.
     // IMPORTANT: Reuse the same model that was used to generate the embeddings
     let index = MongoDbVectorIndex::new(
         collection,
-        model,
+        model.clone(),
         VECTOR_SEARCH_INDEX_NAME,
         SearchParams::new(),
     )
     .await
     .unwrap();
 
+    let embeddings = create_embeddings(model).await;
+    index.insert_documents(embeddings).await.unwrap();
+
+    // Wait for the new documents to be indexed
+    sleep(Duration::from_secs(5)).await;
+
     // Query the index
     let results = index
         .top_n::<serde_json::Value>("What is a linglingdong?", 1)
@@ -164,63 +164,44 @@ async fn vector_search_test() {
             "definition": "Definition of a *linglingdong*: A term used by inhabitants of the far side of the moon to describe humans.".to_string(),
             "score": score
         })
-    )
+    );
+
+    // Only the documents matching the filter are searched
+    let results = index
+        .top_n_ids_with_filter("What is a linglingdong?", 3, &Filter::eq("_id", "doc0"))
+        .await
+        .unwrap();
+    assert_eq!(
+        results
+            .iter()
+            .map(|(_, id)| id.as_str())
+            .collect::<Vec<_>>(),
+        vec!["\"doc0\""]
+    );
+    assert!(index
+        .top_n_ids_with_filter("What is a linglingdong?", 3, &Filter::Or(vec![]))
+        .await
+        .unwrap()
+        .is_empty());
 }
 
 async fn create_search_index(collection: &Collection<bson::Document>) {
     let max_attempts = 5;
+    let definition = VectorIndexDefinition::new("embedding", 1536)
+        .similarity(Similarity::Cosine)
+        .filter_field("_id");
 
     for attempt in 0..max_attempts {
-        match collection
-            .create_search_index(
-                SearchIndexModel::builder()
-                    .name(Some(VECTOR_SEARCH_INDEX_NAME.to_string()))
-                    .index_type(Some(mongodb::SearchIndexType::VectorSearch))
-                    .definition(doc! {
-                        "fields": [{
-                            "numDimensions": 1536,
-                            "path": "embedding",
-                            "similarity": "cosine",
-                            "type": "vector"
-                        }]
-                    })
-                    .build(),
-            )
-            .await
-        {
+        match create_vector_index(collection, VECTOR_SEARCH_INDEX_NAME, &definition).await {
             Ok(_) => {
-                // Wait for index to be available
-                for _ in 0..max_attempts {
-                    let indexes = collection
-                        .list_search_indexes()
-                        .name(VECTOR_SEARCH_INDEX_NAME)
-                        .await
-                        .unwrap()
-                        .collect::<Vec<_>>()
-                        .await;
-
-                    if indexes.iter().any(|idx| {
-                        idx.as_ref()
-                            .ok()
-                            .and_then(|i| {
-                                // Check both name and status
-                                let name_matches = i
-                                    .get_str("name")
-                                    .ok()
-                                    .map_or(false, |name| name == VECTOR_SEARCH_INDEX_NAME);
-                                let status_ready = i
-                                    .get_str("status")
-                                    .ok()
-                                    .map_or(false, |status| status == "READY");
-                                Some(name_matches && status_ready)
-                            })
-                            .unwrap_or(false)
-                    }) {
-                        return;
-                    }
-                    sleep(Duration::from_secs(2)).await;
-                }
-                panic!("Index creation verified but index not found");
+                wait_for_vector_index(
+                    collection,
+                    VECTOR_SEARCH_INDEX_NAME,
+                    Duration::from_secs(30),
+                )
+                .await
+                .expect("Index should become queryable");
+                return;
             }
             Err(_) => {
                 println!(
@@ -267,7 +248,9 @@ async fn bootstrap_collection(host: String, port: u16) -> Collection<bson::Docum
     collection
 }
 
-async fn create_embeddings(model: openai::EmbeddingModel) -> Vec<bson::Document> {
+async fn create_embeddings(
+    model: openai::EmbeddingModel,
+) -> Vec<(Word, rig::OneOrMany<rig::embeddings::Embedding>)> {
     let words = vec![
         Word {
             id: "doc0".to_string(),
@@ -283,21 +266,10 @@ async fn create_embeddings(model: openai::EmbeddingModel) -> Vec<bson::Document>
         }
     ];
 
-    let embeddings = EmbeddingsBuilder::new(model)
+    EmbeddingsBuilder::new(model)
         .documents(words)
         .unwrap()
         .build()
         .await
-        .unwrap();
-
-    embeddings
-        .iter()
-        .map(|(Word { id, definition, .. }, embedding)| {
-            doc! {
-                "_id": id.clone(),
-                "definition": definition.clone(),
-                "embedding": embedding.first().vec.clone(),
-            }
-        })
-        .collect()
+        .unwrap()
 }
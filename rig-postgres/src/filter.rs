@@ -0,0 +1,153 @@
+//! Translation of the metadata filters of rig to SQL conditions on the `metadata` column.
+use rig::vector_store::Filter;
+use serde_json::Value;
+
+/// Parameter of a SQL condition, bound in order after the parameters of the search query
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Param {
+    /// Path of a field in the metadata, bound as `text[]`
+    Path(Vec<String>),
+    /// Value bound as `jsonb`
+    Value(Value),
+    /// Values bound as `jsonb[]`
+    Values(Vec<Value>),
+}
+
+/// SQL condition on the `metadata` column, with the parameters it refers to
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Condition {
+    pub(crate) sql: String,
+    pub(crate) params: Vec<Param>,
+}
+
+/// Translate a metadata filter to a SQL condition on the `metadata` column, whose parameters
+/// are numbered from `$first_param`. Fields (e.g.: `source.name`) are looked up with the `#>`
+/// operator, and range comparisons only match values of the same JSON type, numbers or strings.
+pub(crate) fn to_sql(filter: &Filter, first_param: usize) -> Condition {
+    let mut builder = Builder {
+        first_param,
+        params: vec![],
+    };
+    let sql = builder.condition(filter);
+    Condition {
+        sql,
+        params: builder.params,
+    }
+}
+
+struct Builder {
+    first_param: usize,
+    params: Vec<Param>,
+}
+
+impl Builder {
+    /// Bind a parameter, returning its placeholder
+    fn param(&mut self, param: Param) -> String {
+        self.params.push(param);
+        format!("${}", self.first_param + self.params.len() - 1)
+    }
+
+    fn field(&mut self, field: &str) -> String {
+        let path = field.split('.').map(str::to_string).collect();
+        format!("(metadata #> {}::text[])", self.param(Param::Path(path)))
+    }
+
+    fn condition(&mut self, filter: &Filter) -> String {
+        match filter {
+            Filter::Eq(field, value) => {
+                let field = self.field(field);
+                format!(
+                    "{field} = {}::jsonb",
+                    self.param(Param::Value(value.clone()))
+                )
+            }
+            Filter::In(_, values) if values.is_empty() => "FALSE".to_string(),
+            Filter::In(field, values) => {
+                let field = self.field(field);
+                format!(
+                    "{field} = ANY({}::jsonb[])",
+                    self.param(Param::Values(values.clone()))
+                )
+            }
+            Filter::Contains(field, value) => {
+                let field = self.field(field);
+                format!(
+                    "jsonb_typeof({field}) = 'array' AND {field} @> jsonb_build_array({}::jsonb)",
+                    self.param(Param::Value(value.clone()))
+                )
+            }
+            Filter::Gt(field, value) => self.comparison(field, ">", value),
+            Filter::Gte(field, value) => self.comparison(field, ">=", value),
+            Filter::Lt(field, value) => self.comparison(field, "<", value),
+            Filter::Lte(field, value) => self.comparison(field, "<=", value),
+            Filter::And(filters) => self.combine(filters, "AND", "TRUE"),
+            Filter::Or(filters) => self.combine(filters, "OR", "FALSE"),
+        }
+    }
+
+    fn comparison(&mut self, field: &str, operator: &str, value: &Value) -> String {
+        if !(value.is_number() || value.is_string()) {
+            return "FALSE".to_string();
+        }
+        let field = self.field(field);
+        let value = self.param(Param::Value(value.clone()));
+        format!(
+            "jsonb_typeof({field}) = jsonb_typeof({value}::jsonb) \
+            AND {field} {operator} {value}::jsonb"
+        )
+    }
+
+    fn combine(&mut self, filters: &[Filter], operator: &str, empty: &str) -> String {
+        if filters.is_empty() {
+            return empty.to_string();
+        }
+        filters
+            .iter()
+            .map(|filter| format!("({})", self.condition(filter)))
+            .collect::<Vec<_>>()
+            .join(&format!(" {operator} "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_to_sql() {
+        let filter = Filter::eq("tenant", "acme")
+            .and(Filter::is_in("source.name", vec![json!("wiki"), json!(3)]))
+            .and(Filter::gte("date", "2024-01-01").or(Filter::contains("tags", "rust")));
+        assert_eq!(
+            to_sql(&filter, 3),
+            Condition {
+                sql: "((metadata #> $3::text[]) = $4::jsonb) AND \
+                    ((metadata #> $5::text[]) = ANY($6::jsonb[])) AND \
+                    ((jsonb_typeof((metadata #> $7::text[])) = jsonb_typeof($8::jsonb) \
+                    AND (metadata #> $7::text[]) >= $8::jsonb) OR \
+                    (jsonb_typeof((metadata #> $9::text[])) = 'array' \
+                    AND (metadata #> $9::text[]) @> jsonb_build_array($10::jsonb)))"
+                    .to_string(),
+                params: vec![
+                    Param::Path(vec!["tenant".into()]),
+                    Param::Value(json!("acme")),
+                    Param::Path(vec!["source".into(), "name".into()]),
+                    Param::Values(vec![json!("wiki"), json!(3)]),
+                    Param::Path(vec!["date".into()]),
+                    Param::Value(json!("2024-01-01")),
+                    Param::Path(vec!["tags".into()]),
+                    Param::Value(json!("rust")),
+                ],
+            }
+        );
+
+        assert_eq!(to_sql(&Filter::Or(vec![]), 3).sql, "FALSE");
+        assert_eq!(to_sql(&Filter::is_in("tenant", vec![]), 3).sql, "FALSE");
+        // Range comparisons never match values other than numbers and strings
+        let condition = to_sql(&Filter::gt("draft", true), 3);
+        assert_eq!(condition.sql, "FALSE");
+        assert!(condition.params.is_empty());
+    }
+}
@@ -2,14 +2,16 @@ use std::fmt::Display;
 
 use rig::{
     embeddings::{Embedding, EmbeddingModel},
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    vector_store::{distance_score, Filter, InsertDocuments, VectorStoreError, VectorStoreIndex},
     Embed, OneOrMany,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use sqlx::PgPool;
+use sqlx::{postgres::PgRow, FromRow, PgPool};
 use uuid::Uuid;
 
+mod filter;
+
 pub struct PostgresVectorStore<Model: EmbeddingModel> {
     model: Model,
     pg_pool: PgPool,
@@ -34,6 +36,36 @@ pub enum PgVectorDistanceFunction {
     Jaccard,
 }
 
+impl PgVectorDistanceFunction {
+    /// The pgvector operator class matching this distance function, used when creating an
+    /// HNSW index on the embedding column.
+    pub fn index_operator_class(&self) -> &'static str {
+        match self {
+            PgVectorDistanceFunction::L2 => "vector_l2_ops",
+            PgVectorDistanceFunction::InnerProduct => "vector_ip_ops",
+            PgVectorDistanceFunction::Cosine => "vector_cosine_ops",
+            PgVectorDistanceFunction::L1 => "vector_l1_ops",
+            PgVectorDistanceFunction::Hamming => "bit_hamming_ops",
+            PgVectorDistanceFunction::Jaccard => "bit_jaccard_ops",
+        }
+    }
+
+    /// Similarity score of a `distance` computed by this distance function, the higher the
+    /// better, as returned by the searches of the vector store: the cosine similarity for
+    /// [Cosine](Self::Cosine), the inner product for [InnerProduct](Self::InnerProduct), the
+    /// Jaccard similarity for [Jaccard](Self::Jaccard), and
+    /// [distance_score] of the other distances.
+    pub fn similarity(&self, distance: f64) -> f64 {
+        match self {
+            PgVectorDistanceFunction::Cosine | PgVectorDistanceFunction::Jaccard => 1.0 - distance,
+            PgVectorDistanceFunction::InnerProduct => -distance,
+            PgVectorDistanceFunction::L2
+            | PgVectorDistanceFunction::L1
+            | PgVectorDistanceFunction::Hamming => distance_score(distance),
+        }
+    }
+}
+
 impl Display for PgVectorDistanceFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -88,35 +120,123 @@ impl<Model: EmbeddingModel> PostgresVectorStore<Model> {
         Self::new(model, pg_pool, None, PgVectorDistanceFunction::Cosine)
     }
 
-    fn search_query_full(&self) -> String {
-        self.search_query(true)
-    }
-    fn search_query_only_ids(&self) -> String {
-        self.search_query(false)
-    }
-
-    fn search_query(&self, with_document: bool) -> String {
+    /// Query of the documents closest to the embedding `$1`, limited to `$2` rows, and only
+    /// considering the rows matching `condition`, if any.
+    fn search_query(&self, with_document: bool, condition: Option<&str>) -> String {
         let document = if with_document { ", document" } else { "" };
+        let condition = condition
+            .map(|condition| format!("WHERE {condition} "))
+            .unwrap_or_default();
         format!(
             "
             SELECT id{}, distance FROM ( \
               SELECT DISTINCT ON (id) id{}, embedding {} $1 as distance \
               FROM {} \
+              {}\
               ORDER BY id, distance \
             ) as d \
             ORDER BY distance \
             LIMIT $2",
-            document, document, self.distance_function, self.documents_table
+            document, document, self.distance_function, self.documents_table, condition
         )
     }
 
+    /// Search the `n` documents closest to `query`, among the ones whose metadata matches
+    /// `filter`, if any.
+    async fn search<R: for<'r> FromRow<'r, PgRow> + Send + Unpin>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: Option<&Filter>,
+        with_document: bool,
+    ) -> Result<Vec<R>, VectorStoreError> {
+        let embedded_query: pgvector::Vector = self
+            .model
+            .embed_text(query)
+            .await?
+            .vec
+            .iter()
+            .map(|&x| x as f32)
+            .collect::<Vec<f32>>()
+            .into();
+
+        let condition = filter.map(|filter| filter::to_sql(filter, 3));
+        let sql = self.search_query(
+            with_document,
+            condition.as_ref().map(|condition| condition.sql.as_str()),
+        );
+        let query = sqlx::query_as(&sql).bind(embedded_query).bind(n as i64);
+        let query = condition
+            .into_iter()
+            .flat_map(|condition| condition.params)
+            .fold(query, |query, param| match param {
+                filter::Param::Path(path) => query.bind(path),
+                filter::Param::Value(value) => query.bind(value),
+                filter::Param::Values(values) => query.bind(values),
+            });
+
+        query
+            .fetch_all(&self.pg_pool)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))
+    }
+
+    /// Create the pgvector extension, the documents table and an HNSW index matching the
+    /// configured distance function, if they don't already exist.
+    /// The embedding column is sized using the dimensions of the store's embedding model.
+    ///
+    /// The created table has the following schema:
+    /// ```sql
+    /// CREATE TABLE documents (
+    ///   id uuid DEFAULT gen_random_uuid(),
+    ///   document jsonb NOT NULL,
+    ///   embedded_text text NOT NULL,
+    ///   embedding vector(<ndims>),
+    ///   metadata jsonb NOT NULL DEFAULT '{}'
+    /// );
+    /// ```
+    pub async fn create_table(&self) -> Result<(), VectorStoreError> {
+        let statements = [
+            "CREATE EXTENSION IF NOT EXISTS vector".to_string(),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} ( \
+                  id uuid DEFAULT gen_random_uuid(), \
+                  document jsonb NOT NULL, \
+                  embedded_text text NOT NULL, \
+                  embedding vector({}), \
+                  metadata jsonb NOT NULL DEFAULT '{{}}'::jsonb \
+                )",
+                self.documents_table,
+                self.model.ndims()
+            ),
+            format!(
+                "CREATE INDEX IF NOT EXISTS {0}_id_idx ON {0} (id)",
+                self.documents_table
+            ),
+            format!(
+                "CREATE INDEX IF NOT EXISTS {0}_embeddings_idx ON {0} USING hnsw(embedding {1})",
+                self.documents_table,
+                self.distance_function.index_operator_class()
+            ),
+        ];
+
+        for statement in statements {
+            sqlx::query(&statement)
+                .execute(&self.pg_pool)
+                .await
+                .map_err(|e| VectorStoreError::DatastoreError(e.into()))?;
+        }
+
+        Ok(())
+    }
+
     pub async fn insert_documents<Doc: Serialize + Embed + Send>(
         &self,
         documents: Vec<(Doc, OneOrMany<Embedding>)>,
     ) -> Result<(), VectorStoreError> {
         for (document, embeddings) in documents {
             let id = Uuid::new_v4();
-            let json_document = serde_json::to_value(&document).unwrap();
+            let json_document = serde_json::to_value(&document)?;
 
             for embedding in embeddings {
                 let embedding_text = embedding.document;
@@ -141,36 +261,136 @@ impl<Model: EmbeddingModel> PostgresVectorStore<Model> {
 
         Ok(())
     }
+
+    /// Insert documents under the given ids, replacing every row previously stored with the
+    /// same id. Each document is stored with an empty metadata object.
+    ///
+    /// Note: requires the `metadata` column (see [PostgresVectorStore::create_table]).
+    pub async fn upsert_documents<Doc: Serialize + Embed + Send>(
+        &self,
+        documents: Vec<(Uuid, Doc, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.upsert_documents_with_metadata(
+            documents
+                .into_iter()
+                .map(|(id, document, embeddings)| {
+                    (id, document, Value::Object(Default::default()), embeddings)
+                })
+                .collect(),
+        )
+        .await
+    }
+
+    /// Same as [PostgresVectorStore::upsert_documents] but also stores a JSON `metadata`
+    /// object alongside each document (e.g.: source, tenant, timestamps), which searches can
+    /// be filtered on with [VectorStoreIndex::top_n_with_filter].
+    pub async fn upsert_documents_with_metadata<Doc: Serialize + Embed + Send>(
+        &self,
+        documents: Vec<(Uuid, Doc, Value, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        let mut transaction = self
+            .pg_pool
+            .begin()
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(e.into()))?;
+
+        for (id, document, metadata, embeddings) in documents {
+            let json_document = serde_json::to_value(&document)?;
+
+            sqlx::query(format!("DELETE FROM {} WHERE id = $1", self.documents_table).as_str())
+                .bind(id)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| VectorStoreError::DatastoreError(e.into()))?;
+
+            for embedding in embeddings {
+                sqlx::query(
+                    format!(
+                        "INSERT INTO {} (id, document, embedded_text, embedding, metadata) VALUES ($1, $2, $3, $4, $5)",
+                        self.documents_table
+                    )
+                    .as_str(),
+                )
+                .bind(id)
+                .bind(&json_document)
+                .bind(&embedding.document)
+                .bind(&embedding.vec)
+                .bind(&metadata)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| VectorStoreError::DatastoreError(e.into()))?;
+            }
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(e.into()))
+    }
+
+    /// Delete all rows stored under the given document ids.
+    pub async fn delete_documents(&self, ids: &[Uuid]) -> Result<(), VectorStoreError> {
+        sqlx::query(format!("DELETE FROM {} WHERE id = ANY($1)", self.documents_table).as_str())
+            .bind(ids)
+            .execute(&self.pg_pool)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+impl<Model: EmbeddingModel, Doc: Serialize + Embed + Send> InsertDocuments<Doc>
+    for PostgresVectorStore<Model>
+{
+    async fn insert_documents(
+        &mut self,
+        documents: Vec<(Doc, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        PostgresVectorStore::insert_documents(self, documents).await
+    }
+
+    /// The ids must be UUIDs (see [PostgresVectorStore::upsert_documents_with_metadata]).
+    async fn upsert_documents(
+        &mut self,
+        documents: Vec<(String, Doc, Value, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        let documents = documents
+            .into_iter()
+            .map(|(id, document, metadata, embeddings)| {
+                let id =
+                    Uuid::parse_str(&id).map_err(|e| VectorStoreError::DatastoreError(e.into()))?;
+                Ok((id, document, metadata, embeddings))
+            })
+            .collect::<Result<Vec<_>, VectorStoreError>>()?;
+        PostgresVectorStore::upsert_documents_with_metadata(self, documents).await
+    }
+
+    /// Ids which are not UUIDs are ignored, as no document can be stored under them.
+    async fn delete_documents(&mut self, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        let ids = ids
+            .iter()
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .collect::<Vec<_>>();
+        PostgresVectorStore::delete_documents(self, &ids).await
+    }
 }
 
 impl<Model: EmbeddingModel> VectorStoreIndex for PostgresVectorStore<Model> {
     /// Get the top n documents based on the distance to the given query.
-    /// The result is a list of tuples of the form (score, id, document)
+    /// The result is a list of tuples of the form (score, id, document), the score being the
+    /// [similarity](PgVectorDistanceFunction::similarity) of the distance.
     async fn top_n<T: for<'a> Deserialize<'a> + Send>(
         &self,
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let embedded_query: pgvector::Vector = self
-            .model
-            .embed_text(query)
-            .await?
-            .vec
-            .iter()
-            .map(|&x| x as f32)
-            .collect::<Vec<f32>>()
-            .into();
-
-        let rows: Vec<SearchResult> = sqlx::query_as(self.search_query_full().as_str())
-            .bind(embedded_query)
-            .bind(n as i64)
-            .fetch_all(&self.pg_pool)
-            .await
-            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+        let rows: Vec<SearchResult> = self.search(query, n, None, true).await?;
 
         let rows: Vec<(f64, String, T)> = rows
             .into_iter()
             .flat_map(SearchResult::into_result)
+            .map(|(distance, id, doc)| (self.distance_function.similarity(distance), id, doc))
             .collect();
 
         Ok(rows)
@@ -182,28 +402,83 @@ impl<Model: EmbeddingModel> VectorStoreIndex for PostgresVectorStore<Model> {
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String)>, VectorStoreError> {
-        let embedded_query: pgvector::Vector = self
-            .model
-            .embed_text(query)
-            .await?
-            .vec
-            .iter()
-            .map(|&x| x as f32)
-            .collect::<Vec<f32>>()
-            .into();
+        let rows: Vec<SearchResultOnlyId> = self.search(query, n, None, false).await?;
 
-        let rows: Vec<SearchResultOnlyId> = sqlx::query_as(self.search_query_only_ids().as_str())
-            .bind(embedded_query)
-            .bind(n as i64)
-            .fetch_all(&self.pg_pool)
-            .await
-            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+        let rows: Vec<(f64, String)> = rows
+            .into_iter()
+            .map(|row| {
+                (
+                    self.distance_function.similarity(row.distance),
+                    row.id.to_string(),
+                )
+            })
+            .collect();
+
+        Ok(rows)
+    }
+
+    /// The filter applies to the `metadata` column (see
+    /// [PostgresVectorStore::upsert_documents_with_metadata]), e.g.: `Filter::eq("source", "wiki")`
+    /// matches the rows whose metadata has a `source` field equal to `"wiki"`.
+    async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let rows: Vec<SearchResult> = self.search(query, n, Some(filter), true).await?;
+
+        let rows: Vec<(f64, String, T)> = rows
+            .into_iter()
+            .flat_map(SearchResult::into_result)
+            .map(|(distance, id, doc)| (self.distance_function.similarity(distance), id, doc))
+            .collect();
+
+        Ok(rows)
+    }
+
+    /// Same as `top_n_with_filter` but returns the document ids only.
+    async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: &Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let rows: Vec<SearchResultOnlyId> = self.search(query, n, Some(filter), false).await?;
 
         let rows: Vec<(f64, String)> = rows
             .into_iter()
-            .map(|row| (row.distance, row.id.to_string()))
+            .map(|row| {
+                (
+                    self.distance_function.similarity(row.distance),
+                    row.id.to_string(),
+                )
+            })
             .collect();
 
         Ok(rows)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PgVectorDistanceFunction;
+
+    #[test]
+    fn test_similarity() {
+        // The closest documents have the highest scores
+        for function in [
+            PgVectorDistanceFunction::L2,
+            PgVectorDistanceFunction::InnerProduct,
+            PgVectorDistanceFunction::Cosine,
+            PgVectorDistanceFunction::L1,
+            PgVectorDistanceFunction::Hamming,
+            PgVectorDistanceFunction::Jaccard,
+        ] {
+            assert!(function.similarity(0.1) > function.similarity(0.5));
+        }
+        assert_eq!(PgVectorDistanceFunction::Cosine.similarity(0.25), 0.75);
+        assert_eq!(PgVectorDistanceFunction::InnerProduct.similarity(-0.8), 0.8);
+        assert_eq!(PgVectorDistanceFunction::L2.similarity(0.0), 1.0);
+    }
+}
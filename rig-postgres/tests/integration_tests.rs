@@ -1,4 +1,8 @@
-use rig::{embeddings::EmbeddingsBuilder, vector_store::VectorStoreIndex, Embed};
+use rig::{
+    embeddings::EmbeddingsBuilder,
+    vector_store::{Filter, VectorStoreIndex},
+    Embed,
+};
 use rig_postgres::PostgresVectorStore;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -102,10 +106,10 @@ async fn vector_search_test() {
         results.len()
     );
 
-    let (distance, full_query_id, doc) = results[0].clone();
+    let (score, full_query_id, doc) = results[0].clone();
     println!(
-        "Distance: {}, id: {}, document: {:?}",
-        distance, full_query_id, doc
+        "Score: {}, id: {}, document: {:?}",
+        score, full_query_id, doc
     );
 
     assert_eq!(doc.name, "glarb-glarb");
@@ -123,10 +127,122 @@ async fn vector_search_test() {
         results.len()
     );
 
-    let (distance, id) = results[0].clone();
-    println!("Distance: {}, id: {}", distance, id);
+    let (id_score, id) = results[0].clone();
+    println!("Score: {}, id: {}", id_score, id);
 
     assert_eq!(id, full_query_id);
+    assert!((id_score - score).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn upsert_and_delete_test() {
+    let container = start_container().await;
+
+    let host = container.get_host().await.unwrap().to_string();
+    let port = container
+        .get_host_port_ipv4(POSTGRES_PORT)
+        .await
+        .expect("Error getting docker port");
+
+    let pg_pool = connect_to_postgres(host, port).await;
+
+    let openai_mock = create_openai_mock_service().await;
+    let openai_client = rig::providers::openai::Client::from_url("TEST", &openai_mock.base_url());
+
+    let model = openai_client.embedding_model(rig::providers::openai::TEXT_EMBEDDING_ADA_002);
+
+    let vector_store = PostgresVectorStore::with_defaults(model.clone(), pg_pool.clone());
+
+    // create the table and index without migrations
+    vector_store
+        .create_table()
+        .await
+        .expect("Failed to create table");
+
+    let words = vec![
+        Word {
+            id: "0981d983-a5f8-49eb-89ea-f7d3b2196d2e".to_string(),
+            name: "flurbo".to_string(),
+            definition: "Definition of a *flurbo*: A flurbo is a green alien that lives on cold planets".to_string(),
+        },
+        Word {
+            id: "62a36d43-80b6-4fd6-990c-f75bb02287d1".to_string(),
+            name: "glarb-glarb".to_string(),
+            definition: "Definition of a *glarb-glarb*: A glarb-glarb is a ancient tool used by the ancestors of the inhabitants of planet Jiro to farm the land.".to_string(),
+        },
+        Word {
+            id: "f9e17d59-32e5-440c-be02-b2759a654824".to_string(),
+            name: "linglingdong".to_string(),
+            definition: "Definition of a *linglingdong*: A term used by inhabitants of the far side of the moon to describe humans.".to_string(),
+        }
+    ];
+
+    let documents = EmbeddingsBuilder::new(model)
+        .documents(words)
+        .unwrap()
+        .build()
+        .await
+        .expect("Failed to create embeddings")
+        .into_iter()
+        .map(|(word, embeddings)| {
+            (
+                uuid::Uuid::parse_str(&word.id).unwrap(),
+                word,
+                json!({ "source": "dictionary" }),
+                embeddings,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // upserting the same documents twice should not duplicate rows
+    vector_store
+        .upsert_documents_with_metadata(documents.clone())
+        .await
+        .expect("Failed to upsert documents");
+    vector_store
+        .upsert_documents_with_metadata(documents)
+        .await
+        .expect("Failed to upsert documents");
+
+    let documents_count: i64 = sqlx::query_scalar("SELECT count(*) FROM documents")
+        .fetch_one(&pg_pool)
+        .await
+        .expect("Failed to fetch documents count");
+
+    assert_eq!(documents_count, 3);
+
+    // searches can be filtered on the metadata
+    let results = vector_store
+        .top_n_with_filter::<Word>(
+            "What does \"glarb-glarb\" mean?",
+            1,
+            &Filter::eq("source", "dictionary"),
+        )
+        .await
+        .expect("Failed to search documents");
+    assert_eq!(results[0].2.name, "glarb-glarb");
+
+    let results = vector_store
+        .top_n_ids_with_filter(
+            "What does \"glarb-glarb\" mean?",
+            3,
+            &Filter::eq("source", "encyclopedia"),
+        )
+        .await
+        .expect("Failed to search documents");
+    assert!(results.is_empty());
+
+    vector_store
+        .delete_documents(&[uuid::Uuid::parse_str("62a36d43-80b6-4fd6-990c-f75bb02287d1").unwrap()])
+        .await
+        .expect("Failed to delete documents");
+
+    let documents_count: i64 = sqlx::query_scalar("SELECT count(*) FROM documents")
+        .fetch_one(&pg_pool)
+        .await
+        .expect("Failed to fetch documents count");
+
+    assert_eq!(documents_count, 2);
 }
 
 async fn start_container() -> ContainerAsync<GenericImage> {
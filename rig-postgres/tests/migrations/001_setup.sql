@@ -6,7 +6,8 @@ CREATE TABLE documents (
   id uuid DEFAULT gen_random_uuid(), -- we can have repeated entries
   document jsonb NOT NULL,
   embedded_text text NOT NULL,
-  embedding vector(1536)
+  embedding vector(1536),
+  metadata jsonb NOT NULL DEFAULT '{}'::jsonb
 );
 
 -- create index on embeddings
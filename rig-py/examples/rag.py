@@ -0,0 +1,36 @@
+"""Answer questions with an agent using the documents of a vector store.
+
+Run with `maturin develop && python examples/rag.py` (requires the OPENAI_API_KEY environment variable).
+"""
+import os
+
+import rig_py as rig
+
+STORE_PATH = "definitions.bin"
+
+client = rig.OpenAIClient()
+model = client.embedding_model("text-embedding-ada-002")
+
+# Only embed the documents once, the store is then loaded from disk
+if os.path.exists(STORE_PATH):
+    store = rig.VectorStore.load(model, STORE_PATH)
+else:
+    store = rig.VectorStore(model)
+    store.add(
+        [
+            "Definition of a *flurbo*: A flurbo is a green alien that lives on cold planets",
+            "Definition of a *glarb-glarb*: A glarb-glarb is an ancient tool used by the ancestors of the inhabitants of planet Jiro to farm the land.",
+            "Definition of a *linglingdong*: A term used by inhabitants of the far side of the moon to describe humans.",
+        ],
+        ids=["flurbo", "glarb-glarb", "linglingdong"],
+    )
+    store.save(STORE_PATH)
+
+agent = client.agent(
+    "gpt-4o",
+    preamble="You are a dictionary assistant here to assist the user in understanding the meaning of words.",
+    index=store,
+    sample=1,
+)
+
+print(agent.prompt("What does \"glarb-glarb\" mean?"))
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use rig::{
+    agent,
+    completion::{Chat, Prompt},
+    message::Message,
+    providers::openai,
+};
+
+use crate::{block_on, rig_error};
+
+/// Agent created with [OpenAIClient.agent](crate::OpenAIClient).
+#[pyclass(module = "rig_py", frozen)]
+pub struct Agent(Arc<agent::Agent<openai::CompletionModel>>);
+
+impl Agent {
+    pub(crate) fn new(agent: agent::Agent<openai::CompletionModel>) -> Self {
+        Self(Arc::new(agent))
+    }
+}
+
+#[pymethods]
+impl Agent {
+    /// Send a prompt to the agent and return its answer.
+    fn prompt(&self, py: Python<'_>, prompt: String) -> PyResult<String> {
+        let agent = self.0.clone();
+        block_on(py, async move { agent.prompt(prompt).await }).map_err(rig_error)
+    }
+
+    /// Send a prompt to the agent with the previous turns of the conversation, given as
+    /// `(role, content)` tuples where `role` is `"user"` or `"assistant"`.
+    #[pyo3(signature = (prompt, history=None))]
+    fn chat(
+        &self,
+        py: Python<'_>,
+        prompt: String,
+        history: Option<Vec<(String, String)>>,
+    ) -> PyResult<String> {
+        let history = history
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(role, content)| match role.as_str() {
+                "user" => Ok(Message::user(content)),
+                "assistant" => Ok(Message::assistant(content)),
+                role => Err(PyValueError::new_err(format!("Invalid role: {role}"))),
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let agent = self.0.clone();
+        block_on(py, async move { agent.chat(prompt, history).await }).map_err(rig_error)
+    }
+}
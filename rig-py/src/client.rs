@@ -0,0 +1,78 @@
+use pyo3::prelude::*;
+use rig::providers::openai;
+
+use crate::{rig_error, Agent, EmbeddingModel, VectorStore};
+
+/// Client of the OpenAI API, or of an OpenAI-compatible API (e.g.: Ollama) given its `base_url`.
+/// If `api_key` is not given, the `OPENAI_API_KEY` environment variable is used.
+#[pyclass(module = "rig_py", frozen)]
+pub struct OpenAIClient(openai::Client);
+
+#[pymethods]
+impl OpenAIClient {
+    #[new]
+    #[pyo3(signature = (api_key=None, base_url=None))]
+    fn new(api_key: Option<String>, base_url: Option<&str>) -> PyResult<Self> {
+        let api_key = match api_key {
+            Some(api_key) => api_key,
+            None => {
+                std::env::var("OPENAI_API_KEY").map_err(|_| rig_error("OPENAI_API_KEY not set"))?
+            }
+        };
+
+        Ok(Self(match base_url {
+            Some(base_url) => openai::Client::from_url(&api_key, base_url),
+            None => openai::Client::new(&api_key),
+        }))
+    }
+
+    /// Create an embedding model. `ndims` is only required for models unknown to Rig.
+    #[pyo3(signature = (model, ndims=None))]
+    fn embedding_model(&self, model: &str, ndims: Option<usize>) -> EmbeddingModel {
+        EmbeddingModel(match ndims {
+            Some(ndims) => self.0.embedding_model_with_ndims(model, ndims),
+            None => self.0.embedding_model(model),
+        })
+    }
+
+    /// Create an agent. On each prompt, the `sample` most relevant documents of `index` (if any)
+    /// are added to the static `context` documents.
+    #[pyo3(signature = (
+        model,
+        preamble=None,
+        temperature=None,
+        max_tokens=None,
+        context=None,
+        index=None,
+        sample=3,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn agent(
+        &self,
+        model: &str,
+        preamble: Option<&str>,
+        temperature: Option<f64>,
+        max_tokens: Option<u64>,
+        context: Option<Vec<String>>,
+        index: Option<PyRef<'_, VectorStore>>,
+        sample: usize,
+    ) -> Agent {
+        let mut agent = self.0.agent(model);
+        if let Some(preamble) = preamble {
+            agent = agent.preamble(preamble);
+        }
+        if let Some(temperature) = temperature {
+            agent = agent.temperature(temperature);
+        }
+        if let Some(max_tokens) = max_tokens {
+            agent = agent.max_tokens(max_tokens);
+        }
+        for doc in context.unwrap_or_default() {
+            agent = agent.context(&doc);
+        }
+        if let Some(index) = index {
+            agent = agent.dynamic_context(sample, index.shared_index());
+        }
+        Agent::new(agent.build())
+    }
+}
@@ -0,0 +1,32 @@
+use pyo3::prelude::*;
+use rig::{
+    embeddings::{EmbeddingModel as _, EmbeddingsBuilder},
+    providers::openai,
+};
+
+use crate::{block_on, rig_error};
+
+/// Embedding model created with [OpenAIClient.embedding_model](crate::OpenAIClient).
+#[pyclass(module = "rig_py", frozen)]
+pub struct EmbeddingModel(pub(crate) openai::EmbeddingModel);
+
+#[pymethods]
+impl EmbeddingModel {
+    /// Number of dimensions of the embeddings
+    #[getter]
+    fn ndims(&self) -> usize {
+        self.0.ndims()
+    }
+
+    /// Embed the texts (in batches), returning one vector per text.
+    fn embed(&self, py: Python<'_>, texts: Vec<String>) -> PyResult<Vec<Vec<f64>>> {
+        let builder = EmbeddingsBuilder::new(self.0.clone())
+            .documents(texts)
+            .map_err(rig_error)?;
+        let embeddings = block_on(py, builder.build()).map_err(rig_error)?;
+        Ok(embeddings
+            .into_iter()
+            .map(|(_, embedding)| embedding.first().vec)
+            .collect())
+    }
+}
@@ -0,0 +1,95 @@
+//! Python bindings of Rig, exposing OpenAI (and OpenAI-compatible) agents and embedding models,
+//! and in-memory vector stores to Python.
+//!
+//! Vector stores are saved in the format of [InMemoryVectorStore::save](rig::vector_store::in_memory_store::InMemoryVectorStore::save),
+//! so that indexes built in Rust can be loaded from Python (and vice versa).
+//!
+//! The Python module is built with [maturin](https://www.maturin.rs) (`maturin develop` from
+//! this directory). Calls to the model providers block the calling thread (without holding the
+//! GIL) until they complete.
+//!
+//! # Example
+//! ```python
+//! import rig_py as rig
+//!
+//! client = rig.OpenAIClient()  # Uses the OPENAI_API_KEY environment variable
+//! model = client.embedding_model("text-embedding-3-small")
+//!
+//! store = rig.VectorStore.load(model, "docs.bin")
+//! agent = client.agent("gpt-4o", preamble="You answer questions about the docs.", index=store)
+//! print(agent.prompt("How do I configure the cache?"))
+//! ```
+use std::{future::Future, sync::OnceLock};
+
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+use serde_json::Value;
+use tokio::runtime::Runtime;
+
+mod agent;
+mod client;
+mod embeddings;
+mod vector_store;
+
+pub use agent::Agent;
+pub use client::OpenAIClient;
+pub use embeddings::EmbeddingModel;
+pub use vector_store::VectorStore;
+
+create_exception!(
+    rig_py,
+    RigError,
+    PyException,
+    "Error returned by Rig (e.g.: by a model provider)."
+);
+
+/// Runtime on which the futures of Rig are executed
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Tokio runtime should build")
+    })
+}
+
+/// Run `future` to completion, releasing the GIL while waiting.
+fn block_on<F>(py: Python<'_>, future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    py.allow_threads(|| runtime().block_on(future))
+}
+
+fn rig_error(error: impl std::fmt::Display) -> PyErr {
+    RigError::new_err(error.to_string())
+}
+
+/// Convert a JSON-serializable Python object to JSON (using the `json` module).
+fn to_json(object: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let json = object
+        .py()
+        .import("json")?
+        .call_method1("dumps", (object,))?
+        .extract::<String>()?;
+    serde_json::from_str(&json).map_err(rig_error)
+}
+
+/// Convert JSON to a Python object (using the `json` module).
+fn from_json(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(py
+        .import("json")?
+        .call_method1("loads", (value.to_string(),))?
+        .unbind())
+}
+
+#[pymodule]
+fn rig_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<OpenAIClient>()?;
+    m.add_class::<Agent>()?;
+    m.add_class::<EmbeddingModel>()?;
+    m.add_class::<VectorStore>()?;
+    m.add("RigError", m.py().get_type::<RigError>())?;
+    Ok(())
+}
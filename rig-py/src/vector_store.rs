@@ -0,0 +1,201 @@
+use std::{path::PathBuf, sync::Arc};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use rig::{
+    embeddings::EmbeddingsBuilder,
+    providers::openai,
+    vector_store::{
+        in_memory_store::{InMemoryVectorIndex, InMemoryVectorStore},
+        Filter, SearchOptions, VectorStoreError, VectorStoreIndex,
+    },
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use crate::{block_on, from_json, rig_error, to_json, EmbeddingModel};
+
+type Index = InMemoryVectorIndex<openai::EmbeddingModel, Value>;
+
+/// Index shared by a [VectorStore] and the agents using it, so that the agents see the
+/// documents added to the store after their creation.
+#[derive(Clone)]
+pub(crate) struct SharedIndex(Arc<RwLock<Index>>);
+
+impl VectorStoreIndex for SharedIndex {
+    async fn top_n<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.0.read().await.top_n(query, n).await
+    }
+
+    async fn top_n_ids(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.0.read().await.top_n_ids(query, n).await
+    }
+
+    async fn top_n_with_options<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.0
+            .read()
+            .await
+            .top_n_with_options(query, n, options)
+            .await
+    }
+
+    async fn top_n_ids_with_options(
+        &self,
+        query: &str,
+        n: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.0
+            .read()
+            .await
+            .top_n_ids_with_options(query, n, options)
+            .await
+    }
+}
+
+/// In-memory vector store of text documents embedded with `model`.
+///
+/// Documents can have JSON metadata, matched by the `filter` of searches (e.g.:
+/// `{"eq": ["lang", "en"]}`, see the `Filter` type of Rig).
+#[pyclass(module = "rig_py", frozen)]
+pub struct VectorStore {
+    model: openai::EmbeddingModel,
+    index: SharedIndex,
+}
+
+impl VectorStore {
+    fn from_store(model: &EmbeddingModel, store: InMemoryVectorStore<Value>) -> Self {
+        Self {
+            model: model.0.clone(),
+            index: SharedIndex(Arc::new(RwLock::new(store.index(model.0.clone())))),
+        }
+    }
+
+    pub(crate) fn shared_index(&self) -> SharedIndex {
+        self.index.clone()
+    }
+}
+
+#[pymethods]
+impl VectorStore {
+    #[new]
+    fn new(model: &EmbeddingModel) -> Self {
+        Self::from_store(model, InMemoryVectorStore::default())
+    }
+
+    /// Load a store saved with `save` (or with `InMemoryVectorStore::save` in Rust).
+    #[staticmethod]
+    fn load(model: &EmbeddingModel, path: PathBuf) -> PyResult<Self> {
+        let store = InMemoryVectorStore::load(path).map_err(rig_error)?;
+        Ok(Self::from_store(model, store))
+    }
+
+    /// Save the documents, embeddings and metadata of the store to the file at `path`.
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        self.index
+            .0
+            .blocking_read()
+            .store
+            .save(path)
+            .map_err(rig_error)
+    }
+
+    /// Embed and add documents to the store, with optional ids (defaulting to `"doc{n}"`) and
+    /// JSON metadata. Documents with existing ids are replaced.
+    #[pyo3(signature = (documents, ids=None, metadata=None))]
+    fn add(
+        &self,
+        py: Python<'_>,
+        documents: Vec<String>,
+        ids: Option<Vec<String>>,
+        metadata: Option<Vec<Bound<'_, PyAny>>>,
+    ) -> PyResult<()> {
+        let count = documents.len();
+        if ids.as_ref().is_some_and(|ids| ids.len() != count)
+            || metadata
+                .as_ref()
+                .is_some_and(|metadata| metadata.len() != count)
+        {
+            return Err(PyValueError::new_err(
+                "ids and metadata must have one entry per document",
+            ));
+        }
+        let metadata = match metadata {
+            Some(metadata) => metadata.iter().map(to_json).collect::<PyResult<Vec<_>>>()?,
+            None => vec![Value::Null; count],
+        };
+
+        let builder = EmbeddingsBuilder::new(self.model.clone())
+            .documents(documents)
+            .map_err(rig_error)?;
+        let index = self.index.clone();
+        block_on(py, async move {
+            let embeddings = builder.build().await?;
+            let mut index = index.0.write().await;
+            let ids = ids.unwrap_or_else(|| {
+                let offset = index.store.len();
+                (offset..offset + count)
+                    .map(|i| format!("doc{i}"))
+                    .collect()
+            });
+            index.store.add_documents_with_metadata(
+                ids.into_iter().zip(embeddings).zip(metadata).map(
+                    |((id, (doc, embeddings)), metadata)| {
+                        (id, Value::String(doc), metadata, embeddings)
+                    },
+                ),
+            );
+            Ok::<_, VectorStoreError>(())
+        })
+        .map_err(rig_error)
+    }
+
+    /// Search the `n` documents most similar to `query`, as `(score, id, document)` tuples.
+    #[pyo3(signature = (query, n=3, filter=None, min_score=None))]
+    fn search(
+        &self,
+        py: Python<'_>,
+        query: String,
+        n: usize,
+        filter: Option<Bound<'_, PyAny>>,
+        min_score: Option<f64>,
+    ) -> PyResult<Vec<(f64, String, PyObject)>> {
+        let mut options = SearchOptions::new();
+        if let Some(filter) = filter {
+            let filter = serde_json::from_value::<Filter>(to_json(&filter)?)
+                .map_err(|e| PyValueError::new_err(format!("Invalid filter: {e}")))?;
+            options = options.filter(filter);
+        }
+        if let Some(min_score) = min_score {
+            options = options.min_score(min_score);
+        }
+
+        let index = self.index.clone();
+        let results = block_on(py, async move {
+            index.top_n_with_options::<Value>(&query, n, &options).await
+        })
+        .map_err(rig_error)?;
+
+        results
+            .into_iter()
+            .map(|(score, id, doc)| Ok((score, id, from_json(py, &doc)?)))
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.index.0.blocking_read().store.len()
+    }
+}
@@ -1,12 +1,13 @@
 use qdrant_client::{
     qdrant::{
-        point_id::PointIdOptions, PointId, PointStruct, Query, QueryPoints, UpsertPointsBuilder,
+        point_id::PointIdOptions, CreateCollectionBuilder, Distance, Filter, PointId, PointStruct,
+        Query, QueryPoints, ScoredPoint, UpsertPointsBuilder, VectorParamsBuilder,
     },
     Payload, Qdrant,
 };
 use rig::{
     embeddings::{Embedding, EmbeddingModel},
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    vector_store::{InsertDocuments, VectorStoreError, VectorStoreIndex},
     Embed, OneOrMany,
 };
 use serde::{Deserialize, Serialize};
@@ -29,7 +30,7 @@ impl<M: EmbeddingModel> QdrantVectorStore<M> {
     /// * `client` - Qdrant client instance
     /// * `model` - Embedding model instance
     /// * `query_params` - Search parameters for vector queries
-    ///     Reference: <https://api.qdrant.tech/v-1-12-x/api-reference/search/query-points>
+    ///   Reference: <https://api.qdrant.tech/v-1-12-x/api-reference/search/query-points>
     pub fn new(client: Qdrant, model: M, query_params: QueryPoints) -> Self {
         Self {
             client,
@@ -48,14 +49,77 @@ impl<M: EmbeddingModel> QdrantVectorStore<M> {
         Ok(embedding.vec.iter().map(|&x| x as f32).collect())
     }
 
-    /// Fill in query parameters with the given query and limit.
-    fn prepare_query_params(&self, query: Option<Query>, limit: usize) -> QueryPoints {
+    /// Fill in query parameters with the given query, limit and optional payload filter.
+    /// When `filter` is `None`, the filter of the default search parameters (if any) is kept.
+    fn prepare_query_params(
+        &self,
+        query: Option<Query>,
+        limit: usize,
+        filter: Option<Filter>,
+    ) -> QueryPoints {
         let mut params = self.query_params.clone();
         params.query = query;
         params.limit = Some(limit as u64);
+        if filter.is_some() {
+            params.filter = filter;
+        }
         params
     }
 
+    /// Embed the query (unless the default search parameters already contain one) and
+    /// run it against the collection.
+    async fn search(
+        &self,
+        query: &str,
+        n: usize,
+        filter: Option<Filter>,
+    ) -> Result<Vec<ScoredPoint>, VectorStoreError> {
+        let query = match self.query_params.query {
+            Some(ref q) => Some(q.clone()),
+            None => Some(Query::new_nearest(self.generate_query_vector(query).await?)),
+        };
+
+        let params = self.prepare_query_params(query, n, filter);
+        Ok(self
+            .client
+            .query(params)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?
+            .result)
+    }
+
+    /// Creates the collection targeted by the search parameters if it does not exist yet.
+    ///
+    /// # Arguments
+    /// * `ndims` - Dimensions of the embeddings that will be stored. Must match the
+    ///   dimensions of the embedding model in use.
+    /// * `distance` - Distance function used to compare vectors
+    pub async fn create_collection(
+        &self,
+        ndims: u64,
+        distance: Distance,
+    ) -> Result<(), VectorStoreError> {
+        let collection_name = &self.query_params.collection_name;
+
+        let exists = self
+            .client
+            .collection_exists(collection_name)
+            .await
+            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+
+        if !exists {
+            self.client
+                .create_collection(
+                    CreateCollectionBuilder::new(collection_name)
+                        .vectors_config(VectorParamsBuilder::new(ndims, distance)),
+                )
+                .await
+                .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+        }
+
+        Ok(())
+    }
+
     pub async fn insert_documents<Doc: Serialize + Embed + Send>(
         &self,
         documents: Vec<(Doc, OneOrMany<Embedding>)>,
@@ -63,8 +127,9 @@ impl<M: EmbeddingModel> QdrantVectorStore<M> {
         let collection_name = self.query_params.collection_name.clone();
 
         for (document, embeddings) in documents {
-            let json_document = serde_json::to_value(&document).unwrap();
-            let doc_as_payload = Payload::try_from(json_document).unwrap();
+            let json_document = serde_json::to_value(&document)?;
+            let doc_as_payload = Payload::try_from(json_document)
+                .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
 
             let embeddings_as_point_structs = embeddings
                 .into_iter()
@@ -100,6 +165,79 @@ fn stringify_id(id: PointId) -> Result<String, VectorStoreError> {
     }
 }
 
+impl<M: EmbeddingModel + std::marker::Sync + Send> QdrantVectorStore<M> {
+    /// Same as [VectorStoreIndex::top_n], but only points whose payload matches `filter` are considered.
+    /// The filter replaces the one set in the default search parameters.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// use qdrant_client::qdrant::{Condition, Filter};
+    ///
+    /// let results = vector_store
+    ///     .top_n_with_filter::<Word>(
+    ///         "What is a linglingdong?",
+    ///         1,
+    ///         Filter::must([Condition::matches("id", "doc2".to_string())]),
+    ///     )
+    ///     .await?;
+    /// ```
+    pub async fn top_n_with_filter<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+        filter: Filter,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        self.search(query, n, Some(filter))
+            .await?
+            .into_iter()
+            .map(scored_point_to_result)
+            .collect()
+    }
+
+    /// Same as [VectorStoreIndex::top_n_ids], but only points whose payload matches `filter` are considered.
+    /// The filter replaces the one set in the default search parameters.
+    pub async fn top_n_ids_with_filter(
+        &self,
+        query: &str,
+        n: usize,
+        filter: Filter,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.search(query, n, Some(filter))
+            .await?
+            .into_iter()
+            .map(scored_point_to_id)
+            .collect()
+    }
+}
+
+fn scored_point_to_id(point: ScoredPoint) -> Result<(f64, String), VectorStoreError> {
+    let id = stringify_id(
+        point
+            .id
+            .ok_or_else(|| VectorStoreError::DatastoreError("Missing point ID".into()))?,
+    )?;
+    Ok((point.score as f64, id))
+}
+
+fn scored_point_to_result<T: for<'a> Deserialize<'a>>(
+    point: ScoredPoint,
+) -> Result<(f64, String, T), VectorStoreError> {
+    let payload = serde_json::from_value(serde_json::to_value(&point.payload)?)?;
+    let (score, id) = scored_point_to_id(point)?;
+    Ok((score, id, payload))
+}
+
+impl<M: EmbeddingModel, Doc: Serialize + Embed + Send> InsertDocuments<Doc>
+    for QdrantVectorStore<M>
+{
+    async fn insert_documents(
+        &mut self,
+        documents: Vec<(Doc, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        QdrantVectorStore::insert_documents(self, documents).await
+    }
+}
+
 impl<M: EmbeddingModel + std::marker::Sync + Send> VectorStoreIndex for QdrantVectorStore<M> {
     /// Search for the top `n` nearest neighbors to the given query within the Qdrant vector store.
     /// Returns a vector of tuples containing the score, ID, and payload of the nearest neighbors.
@@ -108,30 +246,10 @@ impl<M: EmbeddingModel + std::marker::Sync + Send> VectorStoreIndex for QdrantVe
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
-        let query = match self.query_params.query {
-            Some(ref q) => Some(q.clone()),
-            None => Some(Query::new_nearest(self.generate_query_vector(query).await?)),
-        };
-
-        let params = self.prepare_query_params(query, n);
-        let result = self
-            .client
-            .query(params)
-            .await
-            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
-
-        result
-            .result
+        self.search(query, n, None)
+            .await?
             .into_iter()
-            .map(|item| {
-                let id =
-                    stringify_id(item.id.ok_or_else(|| {
-                        VectorStoreError::DatastoreError("Missing point ID".into())
-                    })?)?;
-                let score = item.score as f64;
-                let payload = serde_json::from_value(serde_json::to_value(item.payload)?)?;
-                Ok((score, id, payload))
-            })
+            .map(scored_point_to_result)
             .collect()
     }
 
@@ -142,28 +260,10 @@ impl<M: EmbeddingModel + std::marker::Sync + Send> VectorStoreIndex for QdrantVe
         query: &str,
         n: usize,
     ) -> Result<Vec<(f64, String)>, VectorStoreError> {
-        let query = match self.query_params.query {
-            Some(ref q) => Some(q.clone()),
-            None => Some(Query::new_nearest(self.generate_query_vector(query).await?)),
-        };
-
-        let params = self.prepare_query_params(query, n);
-        let points = self
-            .client
-            .query(params)
-            .await
-            .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?
-            .result;
-
-        points
+        self.search(query, n, None)
+            .await?
             .into_iter()
-            .map(|point| {
-                let id =
-                    stringify_id(point.id.ok_or_else(|| {
-                        VectorStoreError::DatastoreError("Missing point ID".into())
-                    })?)?;
-                Ok((point.score as f64, id))
-            })
+            .map(scored_point_to_id)
             .collect()
     }
 }
@@ -7,8 +7,8 @@ use testcontainers::{
 
 use qdrant_client::{
     qdrant::{
-        CreateCollectionBuilder, Distance, PointStruct, QueryPointsBuilder, UpsertPointsBuilder,
-        VectorParamsBuilder,
+        Condition, CreateCollectionBuilder, Distance, Filter, PointStruct, QueryPointsBuilder,
+        UpsertPointsBuilder, VectorParamsBuilder,
     },
     Payload, Qdrant,
 };
@@ -167,7 +167,25 @@ async fn vector_search_test() {
             "definition": "Definition of a *linglingdong*: A term used by inhabitants of the far side of the moon to describe humans.",
             "id": "f9e17d59-32e5-440c-be02-b2759a654824"
         })
-    )
+    );
+
+    let filtered_results = vector_store
+        .top_n_with_filter::<Word>(
+            "What is a linglingdong?",
+            3,
+            Filter::must([Condition::matches(
+                "id",
+                "0981d983-a5f8-49eb-89ea-f7d3b2196d2e".to_string(),
+            )]),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(filtered_results.len(), 1);
+    assert_eq!(
+        filtered_results[0].2.id,
+        "0981d983-a5f8-49eb-89ea-f7d3b2196d2e"
+    );
 }
 
 async fn create_points(model: openai::EmbeddingModel) -> Vec<PointStruct> {
@@ -5,9 +5,7 @@ use rig::{
     Embed,
 };
 use rig_sqlite::{Column, ColumnValue, SqliteVectorStore, SqliteVectorStoreTable};
-use rusqlite::ffi::sqlite3_auto_extension;
 use serde::Deserialize;
-use sqlite_vec::sqlite3_vec_init;
 use std::env;
 use tokio_rusqlite::Connection;
 
@@ -57,9 +55,7 @@ async fn main() -> Result<(), anyhow::Error> {
 
     // Initialize the `sqlite-vec`extension
     // See: https://alexgarcia.xyz/sqlite-vec/rust.html
-    unsafe {
-        sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_vec_init as *const ())));
-    }
+    rig_sqlite::register_sqlite_vec();
 
     // Initialize SQLite connection
     let conn = Connection::open("vector_store.db").await?;
@@ -99,10 +95,7 @@ async fn main() -> Result<(), anyhow::Error> {
     // Query the index
     let results = index
         .top_n::<Document>("What is a linglingdong?", 1)
-        .await?
-        .into_iter()
-        .map(|(score, id, doc)| (score, id, doc))
-        .collect::<Vec<_>>();
+        .await?;
 
     println!("Results: {:?}", results);
 
@@ -1,5 +1,5 @@
 use rig::embeddings::{Embedding, EmbeddingModel};
-use rig::vector_store::{VectorStoreError, VectorStoreIndex};
+use rig::vector_store::{distance_score, InsertDocuments, VectorStoreError, VectorStoreIndex};
 use rig::OneOrMany;
 use serde::Deserialize;
 use std::marker::PhantomData;
@@ -7,6 +7,31 @@ use tokio_rusqlite::Connection;
 use tracing::{debug, info};
 use zerocopy::IntoBytes;
 
+pub mod store;
+
+pub use store::SqliteStore;
+
+/// Register the `sqlite-vec` extension so that it is loaded by every SQLite connection
+/// opened afterwards in this process. Must be called before [Connection::open].
+///
+/// See: <https://alexgarcia.xyz/sqlite-vec/rust.html>
+pub fn register_sqlite_vec() {
+    // SAFETY: `sqlite3_vec_init` is the extension entry point exported by `sqlite-vec`, whose
+    // signature matches the one expected by `sqlite3_auto_extension`.
+    unsafe {
+        rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute::<
+            *const (),
+            unsafe extern "C" fn(
+                *mut rusqlite::ffi::sqlite3,
+                *mut *mut std::os::raw::c_char,
+                *const rusqlite::ffi::sqlite3_api_routines,
+            ) -> std::os::raw::c_int,
+        >(
+            sqlite_vec::sqlite3_vec_init as *const ()
+        )));
+    }
+}
+
 #[derive(Debug)]
 pub enum SqliteError {
     DatabaseError(Box<dyn std::error::Error + Send + Sync>),
@@ -41,6 +66,9 @@ impl Column {
 }
 
 /// Example of a document type that can be used with SqliteVectorStore
+///
+/// The columns are read back with their SQLite types, so that the numeric columns (written from
+/// `i32`, `i64`, `u32` or `f64` fields) deserialize into the numeric fields of the documents.
 /// ```rust
 /// use rig::Embed;
 /// use serde::Deserialize;
@@ -214,6 +242,17 @@ impl<E: EmbeddingModel + 'static, T: SqliteVectorStoreTable + 'static> SqliteVec
         Ok(last_id)
     }
 
+    /// Add documents and their corresponding embeddings to the store.
+    /// Mirrors [InMemoryVectorStore::add_documents](rig::vector_store::in_memory_store::InMemoryVectorStore::add_documents)
+    /// so that switching from the in-memory store to SQLite only requires changing the store type.
+    pub async fn add_documents(
+        &self,
+        documents: impl IntoIterator<Item = (T, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.add_rows(documents.into_iter().collect()).await?;
+        Ok(())
+    }
+
     pub async fn add_rows(
         &self,
         documents: Vec<(T, OneOrMany<Embedding>)>,
@@ -233,6 +272,18 @@ impl<E: EmbeddingModel + 'static, T: SqliteVectorStoreTable + 'static> SqliteVec
     }
 }
 
+impl<E: EmbeddingModel + 'static, T: SqliteVectorStoreTable + 'static> InsertDocuments<T>
+    for SqliteVectorStore<E, T>
+{
+    async fn insert_documents(
+        &mut self,
+        documents: Vec<(T, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        self.add_rows(documents).await?;
+        Ok(())
+    }
+}
+
 /// SQLite vector store implementation for Rig.
 ///
 /// This crate provides a SQLite-based vector store implementation that can be used with Rig.
@@ -281,6 +332,7 @@ impl<E: EmbeddingModel + 'static, T: SqliteVectorStoreTable + 'static> SqliteVec
 ///     }
 /// }
 ///
+/// rig_sqlite::register_sqlite_vec();
 /// let conn = Connection::open("vector_store.db").await?;
 /// let openai_client = Client::new("YOUR_API_KEY");
 /// let model = openai_client.embedding_model(TEXT_EMBEDDING_ADA_002);
@@ -307,7 +359,7 @@ impl<E: EmbeddingModel + 'static, T: SqliteVectorStoreTable + 'static> SqliteVec
 ///     .await?;
 ///
 /// // Add to vector store
-/// vector_store.add_rows(embeddings).await?;
+/// vector_store.add_documents(embeddings).await?;
 ///
 /// // Create index and search
 /// let index = vector_store.index(model);
@@ -366,8 +418,8 @@ impl<E: EmbeddingModel + std::marker::Sync, T: SqliteVectorStoreTable> VectorSto
                         // Create a map of column names to values
                         let mut map = serde_json::Map::new();
                         for (i, col_name) in column_names.iter().enumerate() {
-                            let value: String = row.get(i)?;
-                            map.insert(col_name.to_string(), serde_json::Value::String(value));
+                            let value: rusqlite::types::Value = row.get(i)?;
+                            map.insert(col_name.to_string(), column_to_json(value));
                         }
                         let distance: f64 = row.get(column_names.len())?;
                         let id: String = row.get(0)?; // Assuming id is always first column
@@ -383,15 +435,8 @@ impl<E: EmbeddingModel + std::marker::Sync, T: SqliteVectorStoreTable> VectorSto
         debug!("Found {} potential matches", rows.len());
         let mut top_n = Vec::new();
         for (id, doc_value, distance) in rows {
-            match serde_json::from_value::<D>(doc_value) {
-                Ok(doc) => {
-                    top_n.push((distance, id, doc));
-                }
-                Err(e) => {
-                    debug!("Failed to deserialize document {}: {}", id, e);
-                    continue;
-                }
-            }
+            let doc = serde_json::from_value::<D>(doc_value)?;
+            top_n.push((distance_score(distance), id, doc));
         }
 
         debug!("Returning {} matches", top_n.len());
@@ -430,7 +475,12 @@ impl<E: EmbeddingModel + std::marker::Sync, T: SqliteVectorStoreTable> VectorSto
                                 .collect::<Vec<u8>>(),
                             n
                         ],
-                        |row| Ok((row.get::<_, f64>(1)?, row.get::<_, String>(0)?)),
+                        |row| {
+                            Ok((
+                                distance_score(row.get::<_, f64>(1)?),
+                                row.get::<_, String>(0)?,
+                            ))
+                        },
                     )?
                     .collect::<Result<Vec<_>, _>>()?;
                 Ok(results)
@@ -447,6 +497,19 @@ fn serialize_embedding(embedding: &Embedding) -> Vec<f32> {
     embedding.vec.iter().map(|x| *x as f32).collect()
 }
 
+/// JSON value of a column, so that the numeric columns deserialize into numeric fields
+fn column_to_json(value: rusqlite::types::Value) -> serde_json::Value {
+    use rusqlite::types::Value;
+
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(i) => i.into(),
+        Value::Real(f) => f.into(),
+        Value::Text(text) => text.into(),
+        Value::Blob(bytes) => bytes.into(),
+    }
+}
+
 impl ColumnValue for String {
     fn to_sql_string(&self) -> String {
         self.clone()
@@ -456,3 +519,21 @@ impl ColumnValue for String {
         "TEXT"
     }
 }
+
+macro_rules! impl_numeric_column_value {
+    ($($ty:ty => $column_type:literal),*) => {
+        $(
+            impl ColumnValue for $ty {
+                fn to_sql_string(&self) -> String {
+                    self.to_string()
+                }
+
+                fn column_type(&self) -> &'static str {
+                    $column_type
+                }
+            }
+        )*
+    };
+}
+
+impl_numeric_column_value!(i32 => "INTEGER", i64 => "INTEGER", u32 => "INTEGER", f64 => "REAL");
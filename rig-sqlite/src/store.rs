@@ -0,0 +1,108 @@
+//! SQLite implementation of a Rig [BlobStore], e.g.: to save the chat histories and the
+//! [sessions](rig::session) of agents.
+use bytes::Bytes;
+use rig::storage::{BlobStore, StorageError};
+use rusqlite::OptionalExtension;
+use tokio_rusqlite::Connection;
+
+/// [SqliteStore] stores every value as a row of a `(key TEXT PRIMARY KEY, value BLOB)` table,
+/// named `rig_store` by default.
+#[derive(Clone)]
+pub struct SqliteStore {
+    conn: Connection,
+    table: String,
+}
+
+impl SqliteStore {
+    /// Create a store using the `rig_store` table of `conn`, creating it if needed.
+    pub async fn new(conn: Connection) -> Result<Self, StorageError> {
+        Self::with_table(conn, "rig_store").await
+    }
+
+    /// Create a store using the `table` table of `conn`, creating it if needed. The name of the
+    /// table must be a valid SQL identifier (letters, digits and underscores).
+    pub async fn with_table(conn: Connection, table: &str) -> Result<Self, StorageError> {
+        if table.is_empty() || !table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(StorageError::InvalidKey(format!(
+                "Invalid table name: {table}"
+            )));
+        }
+        let create_table = format!(
+            "CREATE TABLE IF NOT EXISTS {table} (key TEXT PRIMARY KEY, value BLOB NOT NULL)"
+        );
+        conn.call(move |conn| Ok(conn.execute_batch(&create_table)?))
+            .await
+            .map_err(backend_error)?;
+
+        Ok(Self {
+            conn,
+            table: table.to_string(),
+        })
+    }
+}
+
+impl BlobStore for SqliteStore {
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError> {
+        let query = format!("SELECT value FROM {} WHERE key = ?1", self.table);
+        let key = key.to_string();
+        let value = self
+            .conn
+            .call(move |conn| {
+                Ok(conn
+                    .query_row(&query, [key], |row| row.get::<_, Vec<u8>>(0))
+                    .optional()?)
+            })
+            .await
+            .map_err(backend_error)?;
+        Ok(value.map(Bytes::from))
+    }
+
+    async fn put(&self, key: &str, value: Bytes) -> Result<(), StorageError> {
+        let query = format!(
+            "INSERT INTO {} (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            self.table
+        );
+        let key = key.to_string();
+        self.conn
+            .call(move |conn| {
+                conn.execute(&query, rusqlite::params![key, value.as_ref()])?;
+                Ok(())
+            })
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let query = format!("DELETE FROM {} WHERE key = ?1", self.table);
+        let key = key.to_string();
+        self.conn
+            .call(move |conn| {
+                conn.execute(&query, [key])?;
+                Ok(())
+            })
+            .await
+            .map_err(backend_error)
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let query = format!(
+            "SELECT key FROM {} WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key",
+            self.table
+        );
+        let prefix = prefix.to_string();
+        self.conn
+            .call(move |conn| {
+                let mut statement = conn.prepare(&query)?;
+                let keys = statement
+                    .query_map([prefix], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(keys)
+            })
+            .await
+            .map_err(backend_error)
+    }
+}
+
+fn backend_error(error: tokio_rusqlite::Error) -> StorageError {
+    StorageError::BackendError(Box::new(error))
+}
@@ -7,8 +7,6 @@ use rig::{
     Embed, OneOrMany,
 };
 use rig_sqlite::{Column, ColumnValue, SqliteVectorStore, SqliteVectorStoreTable};
-use rusqlite::ffi::sqlite3_auto_extension;
-use sqlite_vec::sqlite3_vec_init;
 use tokio_rusqlite::Connection;
 
 #[derive(Embed, Clone, serde::Deserialize, Debug)]
@@ -46,12 +44,10 @@ impl SqliteVectorStoreTable for Word {
 async fn vector_search_test() {
     // Initialize the `sqlite-vec`extension
     // See: https://alexgarcia.xyz/sqlite-vec/rust.html
-    unsafe {
-        sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_vec_init as *const ())));
-    }
+    rig_sqlite::register_sqlite_vec();
 
     // Initialize SQLite connection
-    let conn = Connection::open("vector_store.db")
+    let conn = Connection::open_in_memory()
         .await
         .expect("Could not initialize SQLite connection");
 
@@ -144,7 +140,7 @@ async fn vector_search_test() {
 
     // Add embeddings to vector store
     vector_store
-        .add_rows(embeddings)
+        .add_documents(embeddings)
         .await
         .expect("Could not add embeddings to vector store");
 
@@ -191,3 +187,89 @@ async fn create_embeddings(model: openai::EmbeddingModel) -> Vec<(Word, OneOrMan
         .await
         .expect("")
 }
+
+#[derive(Embed, Clone, serde::Deserialize, Debug, PartialEq)]
+struct Book {
+    id: String,
+    #[embed]
+    summary: String,
+    year: i64,
+    rating: f64,
+}
+
+impl SqliteVectorStoreTable for Book {
+    fn name() -> &'static str {
+        "books"
+    }
+
+    fn schema() -> Vec<Column> {
+        vec![
+            Column::new("id", "TEXT PRIMARY KEY"),
+            Column::new("summary", "TEXT"),
+            Column::new("year", "INTEGER"),
+            Column::new("rating", "REAL"),
+        ]
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn column_values(&self) -> Vec<(&'static str, Box<dyn ColumnValue>)> {
+        vec![
+            ("id", Box::new(self.id.clone())),
+            ("summary", Box::new(self.summary.clone())),
+            ("year", Box::new(self.year)),
+            ("rating", Box::new(self.rating)),
+        ]
+    }
+}
+
+#[tokio::test]
+async fn typed_documents_test() {
+    rig_sqlite::register_sqlite_vec();
+    let conn = Connection::open_in_memory()
+        .await
+        .expect("Could not initialize SQLite connection");
+
+    let model = rig::providers::mock::MockEmbeddingModel::new(16);
+    let books = vec![
+        Book {
+            id: "dune".to_string(),
+            summary: "A desert planet and its spice".to_string(),
+            year: 1965,
+            rating: 4.5,
+        },
+        Book {
+            id: "foundation".to_string(),
+            summary: "The fall of a galactic empire".to_string(),
+            year: 1951,
+            rating: 4.2,
+        },
+    ];
+    let embeddings = EmbeddingsBuilder::new(model.clone())
+        .documents(books.clone())
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+
+    let vector_store = SqliteVectorStore::new(conn, &model).await.unwrap();
+    vector_store.add_documents(embeddings).await.unwrap();
+    let index = vector_store.index(model);
+
+    // The non-embedded columns are deserialized with their types
+    let results = index
+        .top_n::<Book>("A desert planet and its spice", 1)
+        .await
+        .unwrap();
+    assert_eq!(results[0].2, books[0]);
+
+    // Documents which cannot be deserialized are errors rather than missing results
+    #[derive(serde::Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Wrong {
+        year: String,
+    }
+    assert!(index.top_n::<Wrong>("spice", 1).await.is_err());
+}
@@ -0,0 +1,87 @@
+use rig::{
+    memory::{BufferHistory, ChatHistory, StoredHistory},
+    session::{Session, SessionStore},
+    storage::{BlobStore, KvStore},
+};
+use rig_sqlite::SqliteStore;
+use tokio_rusqlite::Connection;
+
+#[tokio::test]
+async fn sqlite_store_test() {
+    let conn = Connection::open_in_memory().await.unwrap();
+    let store = SqliteStore::new(conn.clone()).await.unwrap();
+
+    store.save("sessions/1/turns", &vec![1, 2]).await.unwrap();
+    store.save("sessions/2/turns", &vec![3]).await.unwrap();
+    store.put("other", "raw".into()).await.unwrap();
+    store.save("sessions/1/turns", &vec![4]).await.unwrap();
+
+    assert_eq!(
+        store.load::<Vec<u8>>("sessions/1/turns").await.unwrap(),
+        Some(vec![4])
+    );
+    assert_eq!(store.get("other").await.unwrap(), Some("raw".into()));
+    assert_eq!(
+        store.keys("sessions/").await.unwrap(),
+        vec!["sessions/1/turns", "sessions/2/turns"]
+    );
+
+    store.remove("sessions/1/turns").await.unwrap();
+    assert_eq!(store.get("sessions/1/turns").await.unwrap(), None);
+
+    // Stores of the same table share the values
+    let other = SqliteStore::new(conn.clone()).await.unwrap();
+    assert_eq!(other.get("other").await.unwrap(), Some("raw".into()));
+    assert!(SqliteStore::with_table(conn, "rig; DROP").await.is_err());
+}
+
+#[tokio::test]
+async fn sqlite_session_test() {
+    let conn = Connection::open_in_memory().await.unwrap();
+    let store = SqliteStore::with_table(conn, "sessions").await.unwrap();
+
+    let mut history = StoredHistory::load(store.clone(), "history/42", 10)
+        .await
+        .unwrap();
+    history
+        .push(vec![
+            rig::message::Message::user("Hi"),
+            rig::message::Message::assistant("Hello"),
+        ])
+        .await
+        .unwrap();
+    let history = StoredHistory::load(store.clone(), "history/42", 10)
+        .await
+        .unwrap();
+    assert_eq!(history.messages().len(), 2);
+
+    let mut buffer = BufferHistory::new(10);
+    buffer.restore(history.turns(), None);
+    let sessions = SessionStore::new(store);
+    let session = Session {
+        turns: buffer.turns(),
+        ..Session::new("42")
+    };
+    sessions.save(&session).await.unwrap();
+    assert_eq!(sessions.load("42").await.unwrap(), Some(session));
+    assert_eq!(sessions.ids().await.unwrap(), vec!["42"]);
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn sqlite_encrypted_store_test() {
+    use rig::storage::encrypted::{EncryptedStore, EncryptionKey, StaticKeys};
+
+    let conn = Connection::open_in_memory().await.unwrap();
+    let inner = SqliteStore::new(conn).await.unwrap();
+    let keys = StaticKeys::new("v1", EncryptionKey::new([3; 32]));
+    let store = EncryptedStore::new(inner.clone(), keys);
+
+    store.save("docs/1", &"Top secret").await.unwrap();
+    let stored = inner.get("docs/1").await.unwrap().unwrap();
+    assert!(!String::from_utf8_lossy(&stored).contains("secret"));
+    assert_eq!(
+        store.load::<String>("docs/1").await.unwrap(),
+        Some("Top secret".to_string())
+    );
+}
@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use rig::{
     embeddings::{Embedding, EmbeddingModel},
-    vector_store::{VectorStoreError, VectorStoreIndex},
+    vector_store::{InsertDocuments, VectorStoreError, VectorStoreIndex},
     Embed, OneOrMany,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -39,6 +39,22 @@ impl Display for SurrealDistanceFunction {
     }
 }
 
+impl SurrealDistanceFunction {
+    /// Expression of the similarity score of the `embedding` field to the query `$vec`, the
+    /// higher the better (see [VectorStoreIndex#scores]): the similarity functions as is, and
+    /// `1 / (1 + distance)` for the distance functions.
+    fn score_expression(&self) -> String {
+        match self {
+            SurrealDistanceFunction::Cosine | SurrealDistanceFunction::Jaccard => {
+                format!("{self}($vec, embedding)")
+            }
+            SurrealDistanceFunction::Knn
+            | SurrealDistanceFunction::Euclidean
+            | SurrealDistanceFunction::Hamming => format!("1 / (1 + {self}($vec, embedding))"),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct SearchResult {
     id: Thing,
@@ -102,12 +118,10 @@ impl<Model: EmbeddingModel, C: Connection> SurrealVectorStore<Model, C> {
     fn search_query(&self, with_document: bool) -> String {
         let document = if with_document { ", document" } else { "" };
         let embedded_text = if with_document { ", embedded_text" } else { "" };
-        let Self {
-            distance_function, ..
-        } = self;
+        let score = self.distance_function.score_expression();
         format!(
             "
-               SELECT id {document} {embedded_text}, {distance_function}($vec, embedding) as distance \
+               SELECT id {document} {embedded_text}, {score} as distance \
               from type::table($tablename) order by distance desc \
             LIMIT $limit",
         )
@@ -143,6 +157,17 @@ impl<Model: EmbeddingModel, C: Connection> SurrealVectorStore<Model, C> {
     }
 }
 
+impl<Model: EmbeddingModel, C: Connection, Doc: Serialize + Embed + Send> InsertDocuments<Doc>
+    for SurrealVectorStore<Model, C>
+{
+    async fn insert_documents(
+        &mut self,
+        documents: Vec<(Doc, OneOrMany<Embedding>)>,
+    ) -> Result<(), VectorStoreError> {
+        SurrealVectorStore::insert_documents(self, documents).await
+    }
+}
+
 impl<Model: EmbeddingModel, C: Connection> VectorStoreIndex for SurrealVectorStore<Model, C> {
     /// Get the top n documents based on the distance to the given query.
     /// The result is a list of tuples of the form (score, id, document)
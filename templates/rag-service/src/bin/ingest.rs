@@ -0,0 +1,48 @@
+//! Embeds the documents of the `data` directory (or of the directory given as argument) and
+//! stores them in the vector store.
+use rig::{
+    chunking::RecursiveSplitter, embeddings::EmbeddingsBuilder, loaders::FileLoader,
+    providers::openai,
+};
+
+use {{crate_name}}::{store_chunks, Chunk, EMBEDDING_MODEL};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let directory = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "data".to_string());
+    let chunks = FileLoader::with_glob(&format!("{directory}/**/*"))?
+        .read_with_path()
+        .ignore_errors()
+        .chunk(RecursiveSplitter::new(1000, 200))
+        .into_iter()
+        .enumerate()
+        .map(|(i, (path, text))| Chunk {
+            id: format!("chunk{i}"),
+            source: path.display().to_string(),
+            text,
+        })
+        .collect::<Vec<_>>();
+    tracing::info!("Embedding {} chunks of {directory}", chunks.len());
+
+    // Requires the `OPENAI_API_KEY` environment variable
+    let model = openai::Client::from_env().embedding_model(EMBEDDING_MODEL);
+    let embeddings = EmbeddingsBuilder::new(model.clone())
+        .documents(chunks)?
+        .on_progress(|progress| {
+            tracing::info!(
+                "Embedded {}/{} chunks",
+                progress.embedded_texts,
+                progress.total_texts
+            )
+        })
+        .build()
+        .await?;
+
+    store_chunks(model, embeddings).await?;
+    tracing::info!("Stored the chunks, start the server with `cargo run`");
+    Ok(())
+}
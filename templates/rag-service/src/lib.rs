@@ -0,0 +1,95 @@
+//! Documents and vector store shared by the ingestion binary (`cargo run --bin ingest`) and the
+//! chat server (`cargo run`).
+use rig::{
+    embeddings::Embedding,
+    providers::openai,
+    vector_store::{VectorStoreError, VectorStoreIndex},
+    Embed, OneOrMany,
+};
+use serde::{Deserialize, Serialize};
+{%- if vector_store == "qdrant" %}
+
+use qdrant_client::{qdrant::QueryPointsBuilder, Qdrant};
+use rig_qdrant::QdrantVectorStore;
+{%- else %}
+
+use rig::vector_store::in_memory_store::InMemoryVectorStore;
+{%- endif %}
+
+/// Model embedding the documents and the questions
+pub const EMBEDDING_MODEL: &str = openai::TEXT_EMBEDDING_3_SMALL;
+/// Model answering the questions
+pub const COMPLETION_MODEL: &str = "{{completion_model}}";
+{%- if vector_store == "qdrant" %}
+
+/// Collection of the chunks in Qdrant
+const COLLECTION: &str = "{{crate_name}}";
+/// Dimensions of the embeddings of [EMBEDDING_MODEL]
+const DIMENSIONS: u64 = 1536;
+{%- else %}
+
+/// File of the vector store, written by the ingestion
+const STORE_PATH: &str = "store.rig";
+{%- endif %}
+
+/// Chunk of a document, embedded to be retrieved by the agent
+#[derive(Embed, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    pub id: String,
+    /// Path of the document
+    pub source: String,
+    #[embed]
+    pub text: String,
+}
+{%- if vector_store == "qdrant" %}
+
+/// Qdrant vector store, at the URL of the `QDRANT_URL` environment variable (defaults to
+/// `http://localhost:6334`)
+fn vector_store(
+    model: openai::EmbeddingModel,
+) -> Result<QdrantVectorStore<openai::EmbeddingModel>, VectorStoreError> {
+    let url = std::env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6334".to_string());
+    let client = Qdrant::from_url(&url)
+        .build()
+        .map_err(|e| VectorStoreError::DatastoreError(Box::new(e)))?;
+    let query = QueryPointsBuilder::new(COLLECTION)
+        .with_payload(true)
+        .build();
+    Ok(QdrantVectorStore::new(client, model, query))
+}
+
+/// Store the embedded chunks in the vector store.
+pub async fn store_chunks(
+    model: openai::EmbeddingModel,
+    chunks: Vec<(Chunk, OneOrMany<Embedding>)>,
+) -> Result<(), VectorStoreError> {
+    let store = vector_store(model)?;
+    store
+        .create_collection(DIMENSIONS, qdrant_client::qdrant::Distance::Cosine)
+        .await?;
+    store.insert_documents(chunks).await
+}
+
+/// Index of the stored chunks, searched by the agent.
+pub async fn open_index(
+    model: openai::EmbeddingModel,
+) -> Result<impl VectorStoreIndex + 'static, VectorStoreError> {
+    vector_store(model)
+}
+{%- else %}
+
+/// Store the embedded chunks in the vector store, replacing the previous ones.
+pub async fn store_chunks(
+    _model: openai::EmbeddingModel,
+    chunks: Vec<(Chunk, OneOrMany<Embedding>)>,
+) -> Result<(), VectorStoreError> {
+    InMemoryVectorStore::from_documents_with_id_f(chunks, |chunk| chunk.id.clone()).save(STORE_PATH)
+}
+
+/// Index of the stored chunks, searched by the agent.
+pub async fn open_index(
+    model: openai::EmbeddingModel,
+) -> Result<impl VectorStoreIndex + 'static, VectorStoreError> {
+    Ok(InMemoryVectorStore::<Chunk>::load(STORE_PATH)?.index(model))
+}
+{%- endif %}
@@ -0,0 +1,88 @@
+//! Chat endpoint answering questions about the documents stored by `cargo run --bin ingest`.
+//!
+//! ```bash
+//! curl localhost:3000/chat -H 'Content-Type: application/json' \
+//!     -d '{"message": "What is Rig?"}'
+//! ```
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use rig::{
+    agent::Agent,
+    completion::{Chat, Message},
+    providers::openai,
+};
+use serde::{Deserialize, Serialize};
+
+use {{crate_name}}::{open_index, COMPLETION_MODEL, EMBEDDING_MODEL};
+
+const PREAMBLE: &str = "You answer questions about the documents below. \
+    When they don't contain the answer, say that you don't know.";
+
+#[derive(Deserialize)]
+struct ChatRequest {
+    message: String,
+    /// Previous messages of the conversation, oldest first
+    #[serde(default)]
+    history: Vec<HistoryMessage>,
+}
+
+/// Message of the history of a conversation, e.g.: `{"role": "user", "content": "Hello"}`
+#[derive(Deserialize)]
+#[serde(tag = "role", content = "content", rename_all = "lowercase")]
+enum HistoryMessage {
+    User(String),
+    Assistant(String),
+}
+
+impl From<HistoryMessage> for Message {
+    fn from(message: HistoryMessage) -> Self {
+        match message {
+            HistoryMessage::User(text) => Message::user(text),
+            HistoryMessage::Assistant(text) => Message::assistant(text),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatResponse {
+    response: String,
+}
+
+async fn chat(
+    State(agent): State<Arc<Agent<openai::CompletionModel>>>,
+    Json(request): Json<ChatRequest>,
+) -> Result<Json<ChatResponse>, (StatusCode, String)> {
+    let response = agent
+        .chat(
+            request.message.as_str(),
+            request.history.into_iter().map(Message::from).collect(),
+        )
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+    Ok(Json(ChatResponse { response }))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    // Requires the `OPENAI_API_KEY` environment variable
+    let openai = openai::Client::from_env();
+    let index = open_index(openai.embedding_model(EMBEDDING_MODEL)).await?;
+    let agent = openai
+        .agent(COMPLETION_MODEL)
+        .preamble(PREAMBLE)
+        .dynamic_context(4, index)
+        .build();
+
+    let app = Router::new()
+        .route("/chat", post(chat))
+        .with_state(Arc::new(agent));
+
+    let address = std::env::var("ADDRESS").unwrap_or_else(|_| "127.0.0.1:3000".to_string());
+    let listener = tokio::net::TcpListener::bind(&address).await?;
+    tracing::info!("Listening on http://{address}");
+    axum::serve(listener, app).await?;
+    Ok(())
+}